@@ -0,0 +1,175 @@
+// Keys the user can bind to calculator commands, beside the fixed keypad keys
+use serde::{Deserialize, Serialize};
+
+// A calculator command that can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyAction {
+    Sqrt,
+    Square,
+    Reciprocal,
+    Sin,
+    Cos,
+    Tan,
+    Ln,
+    Log,
+    Negate,
+    Multiply,
+    Divide,
+    Power,
+    Modulo,
+    OpenParen,
+    CloseParen,
+    Calculate,
+    Clear,
+    ClearEntry,
+    MemoryClear,
+    MemoryRecall,
+    MemoryAdd,
+    MemorySubtract,
+    ToggleAngleMode,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 23] = [
+        KeyAction::Sqrt,
+        KeyAction::Square,
+        KeyAction::Reciprocal,
+        KeyAction::Sin,
+        KeyAction::Cos,
+        KeyAction::Tan,
+        KeyAction::Ln,
+        KeyAction::Log,
+        KeyAction::Negate,
+        KeyAction::Multiply,
+        KeyAction::Divide,
+        KeyAction::Power,
+        KeyAction::Modulo,
+        KeyAction::OpenParen,
+        KeyAction::CloseParen,
+        KeyAction::Calculate,
+        KeyAction::Clear,
+        KeyAction::ClearEntry,
+        KeyAction::MemoryClear,
+        KeyAction::MemoryRecall,
+        KeyAction::MemoryAdd,
+        KeyAction::MemorySubtract,
+        KeyAction::ToggleAngleMode,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyAction::Sqrt => "√",
+            KeyAction::Square => "x²",
+            KeyAction::Reciprocal => "1/x",
+            KeyAction::Sin => "sin",
+            KeyAction::Cos => "cos",
+            KeyAction::Tan => "tan",
+            KeyAction::Ln => "ln",
+            KeyAction::Log => "log",
+            KeyAction::Negate => "±",
+            KeyAction::Multiply => "×",
+            KeyAction::Divide => "÷",
+            KeyAction::Power => "x^y",
+            KeyAction::Modulo => "mod",
+            KeyAction::OpenParen => "(",
+            KeyAction::CloseParen => ")",
+            KeyAction::Calculate => "=",
+            KeyAction::Clear => "C",
+            KeyAction::ClearEntry => "CE",
+            KeyAction::MemoryClear => "MC",
+            KeyAction::MemoryRecall => "MR",
+            KeyAction::MemoryAdd => "M+",
+            KeyAction::MemorySubtract => "M-",
+            KeyAction::ToggleAngleMode => "DEG/RAD",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: egui::Key,
+    pub action: KeyAction,
+}
+
+// Keys handled by the built-in keypad shortcuts, which cannot be rebound:
+// the digits and every key whose typed character is an operator or the point
+pub const RESERVED_KEYS: [egui::Key; 17] = [
+    egui::Key::Num0,
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+    egui::Key::Plus,
+    egui::Key::Minus,
+    egui::Key::Slash,
+    egui::Key::Period,
+    egui::Key::Enter,
+    egui::Key::Escape,
+    egui::Key::Backspace,
+];
+
+pub fn default_key_bindings() -> Vec<KeyBinding> {
+    [
+        (egui::Key::Q, KeyAction::Sqrt),
+        (egui::Key::F1, KeyAction::MemoryRecall),
+        (egui::Key::F2, KeyAction::MemoryAdd),
+        (egui::Key::F3, KeyAction::MemorySubtract),
+        (egui::Key::F4, KeyAction::MemoryClear),
+    ]
+    .into_iter()
+    .map(|(key, action)| KeyBinding { key, action })
+    .collect()
+}
+
+pub fn bound_action(bindings: &[KeyBinding], key: egui::Key) -> Option<KeyAction> {
+    bindings
+        .iter()
+        .find(|binding| binding.key == key)
+        .map(|binding| binding.action)
+}
+
+// Why `key` cannot be bound, if it conflicts with the keypad or another binding
+pub fn conflict(bindings: &[KeyBinding], key: egui::Key) -> Option<String> {
+    if RESERVED_KEYS.contains(&key) {
+        Some(format!("{} is used by the keypad", key.name()))
+    } else {
+        bound_action(bindings, key)
+            .map(|action| format!("{} is already bound to {}", key.name(), action.label()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad_keys_and_bound_keys_conflict() {
+        let bindings = default_key_bindings();
+        // Every key the keypad types with, operators and the point included
+        for key in [
+            egui::Key::Num5,
+            egui::Key::Slash,
+            egui::Key::Period,
+            egui::Key::Plus,
+        ] {
+            assert_eq!(
+                conflict(&bindings, key),
+                Some(format!("{} is used by the keypad", key.name()))
+            );
+        }
+        assert_eq!(
+            conflict(&bindings, egui::Key::Q),
+            Some("Q is already bound to √".to_string())
+        );
+        assert_eq!(conflict(&bindings, egui::Key::S), None);
+        assert_eq!(
+            bound_action(&bindings, egui::Key::F2),
+            Some(KeyAction::MemoryAdd)
+        );
+    }
+}
@@ -1,45 +1,115 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod keys;
+
+use calc_core::color::{format_hex_color, hsl_to_rgb, parse_hex_color, rgb_to_hsl};
+use calc_core::format::{
+    add_thousands_separators, format_big_integer, format_hex_float, format_with_style,
+    number_to_words, parse_hex_float,
+};
+use calc_core::math::{self, big_factorial, big_to_f64, exact_f64, to_fraction, BigUint};
+use calc_core::programmer::{
+    self, char_name, checksums, lookup_char, parse_hex_bytes, printable_char, to_hex, value_bytes,
+    WordSize,
+};
+use calc_core::script::{Script, DEFAULT_SCRIPT};
+use calc_core::subnet::{parse_prefix, SubnetInfo};
+use calc_core::uncertain::{format_uncertain, Uncertain};
+use calc_core::{
+    format_number, stats, DisplayFormat, Evaluator, ModuloMode, NumberBase, Operation, RoundingMode,
+};
 use eframe::egui;
 use egui::{Color32, RichText, Vec2};
-use num_bigint::BigUint;
-use num_traits::One;
+use gui_common::dialog::FileType;
+use gui_common::ipc::{self, Message};
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{filled_button, palette, Labeled};
+use keypad::{Display, Keypad, Operator, PadKey};
+use keys::{default_key_bindings, KeyAction, KeyBinding};
+use serde::{Deserialize, Serialize};
 use std::f64::consts::{E, PI};
+use std::net::Ipv4Addr;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+const APP_ID: &str = "Scientific Calculator";
+// Where settings were kept before they moved to their own file
+const SETTINGS_KEY: &str = "calc_settings";
 
 fn main() -> eframe::Result<()> {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1024.0, 1024.0])
-            .with_title("Scientific Calculator"),
-        ..Default::default()
-    };
-    eframe::run_native(
+    // Without a log the calculator still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<CalcSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
         "Scientific Calculator",
+        [1024.0, 1024.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
+    eframe::run_native(
+        APP_ID,
         options,
-        Box::new(|_cc| Ok(Box::<Calculator>::default())),
+        Box::new(|cc| Ok(Box::new(Calculator::new(cc, saved)))),
     )
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Operation {
-    Add,
-    Subtract,
-    Multiply,
-    Divide,
-    Power,
-    Root,
-    Modulo,
-    Permutation, // nPr
-    Combination, // nCr
+// A completed calculation, kept for the history list and export
+#[derive(Clone, Serialize)]
+struct HistoryEntry {
+    expression: String,
+    result: f64,
+}
+
+// A memory store or recall, kept for the memory history panel
+struct MemoryEvent {
+    time: String,         // Local time of the action, HH:MM:SS
+    action: &'static str, // MC, MR, M+ or M-
+    value: f64,           // Memory contents after the action
 }
 
 #[derive(Clone, Copy, PartialEq)]
-enum DisplayFormat {
-    Regular,     // Standard format
-    Fixed,       // Fixed decimal places
-    Scientific,  // Scientific notation
-    Engineering, // Engineering notation (exponent is multiple of 3)
-    Triads,      // Thousands separators (commas)
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// User preferences persisted between sessions
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct CalcSettings {
+    tax_rate: f64,  // Percent used by +TAX / -TAX
+    script: String, // Rhai source for user-defined functions
+    modulo_mode: ModuloMode,
+    rounding_mode: RoundingMode, // Fixed format display and round()
+    fraction_digits: usize,      // Digits after the point when converting bases
+    word_size: WordSize,         // Width for NOT and 2's complement patterns
+    fraction_tolerance: f64,     // Maximum error accepted by → a/b
+    key_bindings: Vec<KeyBinding>,
+    // The modes the calculator was left in
+    degree_mode: bool,
+    base_mode: NumberBase,
+    display_format: DisplayFormat,
+    common: CommonSettings,
+}
+
+impl Default for CalcSettings {
+    fn default() -> Self {
+        Self {
+            tax_rate: 10.0,
+            script: DEFAULT_SCRIPT.to_string(),
+            modulo_mode: ModuloMode::Truncated,
+            rounding_mode: RoundingMode::HalfUp,
+            fraction_digits: 8,
+            word_size: WordSize::QWord,
+            fraction_tolerance: 1e-6,
+            key_bindings: default_key_bindings(),
+            degree_mode: true,
+            base_mode: NumberBase::Dec,
+            display_format: DisplayFormat::Regular,
+            common: CommonSettings::default(),
+        }
+    }
 }
 
 struct Calculator {
@@ -48,13 +118,45 @@ struct Calculator {
     operation: Option<Operation>,
     new_number: bool,
     memory: f64,
-    degree_mode: bool, // true = degrees, false = radians
+    memory_history: Vec<MemoryEvent>, // Memory actions, oldest first
+    degree_mode: bool,                // true = degrees, false = radians
     expression_input: String,
-    base_mode: String, // "DEC", "BIN", "OCT", "HEX"
+    base_mode: NumberBase,
+    hex_float_input: String,
+    show_char_table: bool,
+    char_query: String,
+    hash_input: String,
+    color_rgb: [u8; 3],
+    color_hsl: [f64; 3], // Hue in degrees, saturation and lightness in percent
+    color_hex_input: String,
+    subnet_address: String,
+    subnet_mask: String, // Prefix length ("24", "/24") or dotted netmask
     bitwise_operand: Option<i64>,
-    stat_data: Vec<f64>,           // Data for statistics calculations
-    previous_display: String,      // Store previous value before overflow
+    stat_data: Vec<f64>,        // Data for statistics calculations
+    history: Vec<HistoryEntry>, // Completed calculations, oldest first
+    previous_display: String,   // Store previous value before overflow
+    last_calculation: Option<(String, String)>, // Completed "a op b =" and the display it produced
+    keypad_expression: String,  // Built with the ( and ) keys, evaluated on =
+    uncertainty_mode: bool,     // Values carry "± sigma" through + − × ÷ and x^y
+    current_uncertainty: f64,   // Sigma of current_value in uncertainty mode
     display_format: DisplayFormat, // Number display format
+    show_words: bool,           // Spell out the current value below the display
+    settings: CalcSettings,
+    // Scripting
+    script: Script,
+    script_status: String,
+    show_script_editor: bool,
+    // Key binding editor
+    show_key_bindings: bool,
+    show_settings: bool,
+    show_log: bool,      // Recent log lines, for problem reports
+    recording_key: bool, // The next key press becomes new_binding_key
+    new_binding_key: Option<egui::Key>,
+    new_binding_action: KeyAction,
+    // Expressions sent from the note app, and results sent back to it
+    ipc: Option<ipc::Listener>,
+    note_status: Option<String>, // How the last Insert into Note went
+    note_job: Option<(String, Receiver<Result<(), ipc::SendError>>)>, // Value being sent
 }
 
 impl Default for Calculator {
@@ -65,18 +167,129 @@ impl Default for Calculator {
             operation: None,
             new_number: true,
             memory: 0.0,
+            memory_history: Vec::new(),
             degree_mode: true,
             expression_input: String::new(),
-            base_mode: "DEC".to_string(),
+            base_mode: NumberBase::Dec,
+            hex_float_input: String::new(),
+            show_char_table: false,
+            char_query: String::new(),
+            hash_input: String::new(),
+            color_rgb: [255, 128, 0],
+            color_hsl: rgb_to_hsl([255, 128, 0]),
+            color_hex_input: "#FF8000".to_string(),
+            subnet_address: "192.168.1.10".to_string(),
+            subnet_mask: "24".to_string(),
             bitwise_operand: None,
             stat_data: Vec::new(),
+            history: Vec::new(),
             previous_display: String::new(),
+            last_calculation: None,
+            keypad_expression: String::new(),
+            uncertainty_mode: false,
+            current_uncertainty: 0.0,
             display_format: DisplayFormat::Regular,
+            show_words: false,
+            settings: CalcSettings::default(),
+            script: Script::default(),
+            script_status: String::new(),
+            show_script_editor: false,
+            show_key_bindings: false,
+            show_settings: false,
+            show_log: false,
+            recording_key: false,
+            new_binding_key: None,
+            new_binding_action: KeyAction::Sqrt,
+            ipc: None,
+            note_status: None,
+            note_job: None,
         }
     }
 }
 
 impl Calculator {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<CalcSettings>) -> Self {
+        let settings: CalcSettings = saved
+            .or_else(|| {
+                cc.storage
+                    .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            })
+            .unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let mut calculator = Self {
+            degree_mode: settings.degree_mode,
+            base_mode: settings.base_mode,
+            display_format: settings.display_format,
+            settings,
+            ..Default::default()
+        };
+        calculator.compile_script();
+        calculator.ipc = ipc::listen(APP_ID, &cc.egui_ctx)
+            .inspect_err(|e| tracing::warn!(error = %e, "Not taking expressions from notes"))
+            .ok();
+        // Started by the note app's Calculate Selection
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if let [flag, expression] = args.as_slice() {
+            if flag == "--calculate" {
+                calculator.calculate_sent(expression);
+            }
+        }
+        calculator
+    }
+
+    // Work out an expression sent from a note, as if typed in the expression field
+    fn calculate_sent(&mut self, expression: &str) {
+        self.expression_input = expression.to_string();
+        self.evaluate_expression();
+    }
+
+    fn receive_messages(&mut self, ctx: &egui::Context) {
+        let messages = self
+            .ipc
+            .as_ref()
+            .map(ipc::Listener::messages)
+            .unwrap_or_default();
+        for message in messages {
+            if let Message::Calculate(expression) = message {
+                self.calculate_sent(&expression);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+        }
+    }
+
+    // Send the displayed value to the caret of the open note
+    fn insert_into_note(&mut self) {
+        if self.display.starts_with("Error:") {
+            self.note_status = Some("Nothing to insert".to_string());
+            return;
+        }
+        let job = ipc::send_in_background(ipc::NOTE_APP, Message::Insert(self.display.clone()));
+        self.note_job = Some((self.display.clone(), job));
+        self.note_status = Some("Sending...".to_string());
+    }
+
+    fn poll_insert(&mut self, ctx: &egui::Context) {
+        let Some((value, job)) = &self.note_job else {
+            return;
+        };
+        let status = match job.try_recv() {
+            Ok(Ok(())) => format!("Inserted {}", value),
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Insert into Note failed");
+                format!("{}; open a note first", e)
+            }
+            Err(TryRecvError::Empty) => {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+                return;
+            }
+            Err(TryRecvError::Disconnected) => "Sending stopped unexpectedly".to_string(),
+        };
+        self.note_status = Some(status);
+        self.note_job = None;
+    }
+
     fn set_display_result(&mut self, num: f64) {
         if num.is_infinite() || num.is_nan() {
             // Store the previous display value before showing error
@@ -90,107 +303,37 @@ impl Calculator {
             };
         } else {
             self.previous_display.clear();
-            self.display = self.format_number_with_style(num);
-        }
-    }
-
-    fn format_number_with_style(&self, num: f64) -> String {
-        if num.is_infinite() {
-            return "Error: Overflow".to_string();
-        }
-        if num.is_nan() {
-            return "Error: Invalid".to_string();
-        }
-
-        match self.display_format {
-            DisplayFormat::Regular => {
-                // Standard format with up to 18 significant digits
-                let formatted = format!("{:.18}", num);
-                let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
-                if num.abs() >= 1e15 || (num.abs() < 1e-15 && num != 0.0) {
-                    format!("{:.12e}", num)
-                } else {
-                    trimmed.to_string()
-                }
-            }
-            DisplayFormat::Fixed => {
-                // Fixed 6 decimal places
-                format!("{:.6}", num)
-            }
-            DisplayFormat::Scientific => {
-                // Always scientific notation
-                format!("{:.12e}", num)
-            }
-            DisplayFormat::Engineering => {
-                // Engineering notation (exponent is multiple of 3)
-                if num == 0.0 {
-                    return "0.000000000000e0".to_string();
-                }
-
-                let abs_num = num.abs();
-                let sign = if num < 0.0 { "-" } else { "" };
-
-                // Calculate the base-10 exponent
-                let exponent = abs_num.log10().floor() as i32;
-
-                // Round down to nearest multiple of 3
-                let eng_exponent = (exponent / 3) * 3;
-
-                // Calculate mantissa (should be between 1 and 999.999...)
-                let mantissa = abs_num / 10_f64.powi(eng_exponent);
-
-                format!(
-                    "{}{}e{}",
-                    sign,
-                    format!("{:.9}", mantissa)
-                        .trim_end_matches('0')
-                        .trim_end_matches('.'),
-                    eng_exponent
-                )
-            }
-            DisplayFormat::Triads => {
-                // Format with thousands separators
-                let formatted = format!("{:.18}", num);
-                let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
-
-                if let Some(dot_pos) = trimmed.find('.') {
-                    let integer_part = &trimmed[..dot_pos];
-                    let decimal_part = &trimmed[dot_pos..];
-                    let formatted_int = self.add_thousands_separators(integer_part);
-                    format!("{}{}", formatted_int, decimal_part)
-                } else {
-                    self.add_thousands_separators(trimmed)
-                }
-            }
-        }
-    }
-
-    fn add_thousands_separators(&self, num_str: &str) -> String {
-        let is_negative = num_str.starts_with('-');
-        let num_str = if is_negative { &num_str[1..] } else { num_str };
-
-        let len = num_str.len();
-        if len <= 3 {
-            return if is_negative {
-                format!("-{}", num_str)
+            self.display = if self.base_mode == NumberBase::HexFloat {
+                format_hex_float(num)
             } else {
-                num_str.to_string()
+                self.format_display(num)
             };
         }
+    }
 
-        let mut formatted = String::new();
-        for (i, ch) in num_str.chars().enumerate() {
-            if i > 0 && (len - i) % 3 == 0 {
-                formatted.push(',');
-            }
-            formatted.push(ch);
-        }
+    fn format_display(&self, num: f64) -> String {
+        format_with_style(num, self.display_format, self.settings.rounding_mode)
+    }
 
-        if is_negative {
-            format!("-{}", formatted)
-        } else {
-            formatted
-        }
+    // The current value rendered in each "Copy as" format, independent of the
+    // display format and base
+    fn copy_formats(&self) -> Vec<(&'static str, String)> {
+        let value = self.get_display_value();
+        let rounding = self.settings.rounding_mode;
+        let digits = self.settings.fraction_digits;
+        vec![
+            ("decimal", format_number(value)),
+            ("hex", NumberBase::Hex.format(value, digits)),
+            ("binary", NumberBase::Bin.format(value, digits)),
+            (
+                "scientific",
+                format_with_style(value, DisplayFormat::Scientific, rounding),
+            ),
+            (
+                "with separators",
+                format_with_style(value, DisplayFormat::Triads, rounding),
+            ),
+        ]
     }
 
     fn append_digit(&mut self, digit: &str) {
@@ -198,11 +341,13 @@ impl Calculator {
             self.display = digit.to_string();
             self.new_number = false;
         } else {
+            // In "12.3 ± 0.2" digits go to the uncertainty once ± has been entered
+            let entry = self.display.rsplit('±').next().unwrap_or_default();
             if self.display == "0" && digit != "." {
                 self.display = digit.to_string();
-            } else if !(digit == "." && self.display.contains('.')) {
+            } else if !(digit == "." && entry.contains('.')) {
                 // Limit to 18 digits precision (not counting decimal point)
-                let digit_count = self.display.chars().filter(|c| c.is_numeric()).count();
+                let digit_count = entry.chars().filter(|c| c.is_numeric()).count();
                 if digit_count < 18 {
                     self.display.push_str(digit);
                 }
@@ -210,11 +355,76 @@ impl Calculator {
         }
     }
 
+    // Backspace: drop the last digit typed, or clear a result
+    fn delete_digit(&mut self) {
+        if !self.new_number && self.display.len() > 1 {
+            self.display.pop();
+        } else {
+            self.display = "0".to_string();
+            self.new_number = true;
+        }
+    }
+
+    // A key clicked on the number pad
+    fn press(&mut self, key: PadKey) {
+        match key {
+            PadKey::Digit(d) => self.append_digit(&d.to_string()),
+            PadKey::Point => self.append_digit("."),
+            PadKey::Operator(operator) => self.set_operation(match operator {
+                Operator::Add => Operation::Add,
+                Operator::Subtract => Operation::Subtract,
+                Operator::Multiply => Operation::Multiply,
+                Operator::Divide => Operation::Divide,
+            }),
+            PadKey::Equals => self.calculate(),
+            PadKey::Clear => self.clear(),
+            PadKey::ClearEntry => self.clear_entry(),
+            PadKey::Delete => self.delete_digit(),
+            PadKey::Negate => {
+                let val = self.get_display_value();
+                self.display = format_number(-val);
+            }
+        }
+    }
+
+    // Start entering the uncertainty of the value on the display
+    fn start_uncertainty(&mut self) {
+        if self.display.contains('±') || self.display.starts_with("Error:") {
+            return;
+        }
+        if self.new_number {
+            self.display = format_number(self.get_display_value());
+        }
+        self.display.push_str(" ± ");
+        self.new_number = false;
+    }
+
     fn clear(&mut self) {
         self.display = "0".to_string();
         self.current_value = 0.0;
+        self.current_uncertainty = 0.0;
         self.operation = None;
         self.new_number = true;
+        self.last_calculation = None;
+        self.keypad_expression.clear();
+    }
+
+    fn memory_action(&mut self, action: &'static str) {
+        match action {
+            "MC" => self.memory = 0.0,
+            "MR" => {
+                self.display = format_number(self.memory);
+                self.new_number = true;
+            }
+            "M+" => self.memory += self.get_display_value(),
+            "M-" => self.memory -= self.get_display_value(),
+            _ => return,
+        }
+        self.memory_history.push(MemoryEvent {
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+            action,
+            value: self.memory,
+        });
     }
 
     fn clear_entry(&mut self) {
@@ -223,390 +433,562 @@ impl Calculator {
     }
 
     fn set_operation(&mut self, op: Operation) {
+        // Inside parentheses operators extend the keypad expression instead
+        if !self.keypad_expression.is_empty() {
+            match op.infix() {
+                Some(infix) => {
+                    self.push_keypad_operand();
+                    self.keypad_expression.push(infix);
+                }
+                None => self.display = format!("Error: {} inside parentheses", op.symbol()),
+            }
+            self.new_number = true;
+            return;
+        }
         if !self.new_number {
             self.calculate();
         }
         self.current_value = self.get_display_value();
+        self.current_uncertainty = if self.uncertainty_mode {
+            self.display_uncertain().sigma
+        } else {
+            0.0
+        };
         self.operation = Some(op);
         self.new_number = true;
     }
 
     fn calculate(&mut self) {
+        if !self.keypad_expression.is_empty() {
+            self.evaluate_keypad_expression();
+            return;
+        }
         if let Some(op) = self.operation {
+            if self.uncertainty_mode && self.calculate_uncertain(op) {
+                return;
+            }
             let second = self.get_display_value();
-            let result = match op {
-                Operation::Add => self.current_value + second,
-                Operation::Subtract => self.current_value - second,
-                Operation::Multiply => self.current_value * second,
-                Operation::Divide => {
-                    if second != 0.0 {
-                        self.current_value / second
-                    } else {
-                        self.display = "Error: Div by 0".to_string();
-                        self.new_number = true;
-                        return;
-                    }
-                }
-                Operation::Power => self.current_value.powf(second),
-                Operation::Root => {
-                    if second != 0.0 {
-                        self.current_value.powf(1.0 / second)
-                    } else {
-                        self.display = "Error: Root 0".to_string();
-                        self.new_number = true;
-                        return;
-                    }
-                }
-                Operation::Modulo => self.current_value % second,
-                Operation::Permutation => {
-                    self.permutation(self.current_value, second);
-                    return;
-                }
-                Operation::Combination => {
-                    self.combination(self.current_value, second);
+            let expression = format!(
+                "{} {} {} =",
+                format_number(self.current_value),
+                op.symbol(),
+                format_number(second)
+            );
+            // nPr and nCr are computed exactly and shown with every digit
+            let result = match op.apply_exact(self.current_value, second) {
+                Some(exact) => exact.map(|value| (big_to_f64(&value), Some(value))),
+                None => op
+                    .apply(self.current_value, second)
+                    .map(|result| (result, None)),
+            };
+            let (result, exact) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    self.display = format!("Error: {}", e);
+                    self.last_calculation = Some((expression, self.display.clone()));
+                    self.new_number = true;
                     return;
                 }
             };
-            self.set_display_result(result);
+            if result.is_finite() {
+                self.push_history(op, second, result);
+            }
+            match exact {
+                Some(value) => self.set_display_exact(&value),
+                None => self.set_display_result(result),
+            }
+            self.last_calculation = Some((expression, self.display.clone()));
             self.current_value = result;
+            self.current_uncertainty = 0.0;
             self.operation = None;
             self.new_number = true;
         }
     }
 
-    fn get_display_value(&self) -> f64 {
-        // Parse display value according to current base mode
-        match self.base_mode.as_str() {
-            "BIN" => i64::from_str_radix(&self.display, 2).unwrap_or(0) as f64,
-            "OCT" => i64::from_str_radix(&self.display, 8).unwrap_or(0) as f64,
-            "HEX" => i64::from_str_radix(&self.display, 16).unwrap_or(0) as f64,
-            _ => self.display.parse().unwrap_or(0.0), // DEC
+    // Propagate uncertainties through the pending operation. Returns false for
+    // operations without propagation rules, which then run on the plain values.
+    fn calculate_uncertain(&mut self, op: Operation) -> bool {
+        let first = self.current_uncertain();
+        let second = self.display_uncertain();
+        let Some(result) = first.apply(op, second) else {
+            return false;
+        };
+        let expression = format!(
+            "{} {} {}",
+            format_uncertain(first),
+            op.symbol(),
+            format_uncertain(second)
+        );
+
+        match result {
+            Ok(result) if result.value.is_finite() => {
+                self.history.push(HistoryEntry {
+                    expression: expression.clone(),
+                    result: result.value,
+                });
+                self.previous_display.clear();
+                self.display = format_uncertain(result);
+                self.current_value = result.value;
+                self.current_uncertainty = result.sigma;
+            }
+            Ok(result) => self.set_display_result(result.value),
+            Err(e) => self.display = format!("Error: {}", e),
         }
+        self.last_calculation = Some((format!("{} =", expression), self.display.clone()));
+        self.operation = None;
+        self.new_number = true;
+        true
     }
 
-    fn apply_function<F>(&mut self, f: F)
-    where
-        F: Fn(f64) -> f64,
-    {
-        let value = self.get_display_value();
-        let result = f(value);
-        self.set_display_result(result);
-        self.new_number = true;
+    fn current_uncertain(&self) -> Uncertain {
+        Uncertain::new(self.current_value, self.current_uncertainty)
     }
 
-    fn evaluate_expression(&mut self) {
-        let expr = self.expression_input.trim();
-        if expr.is_empty() {
-            return;
-        }
+    // The display as a value with uncertainty; plain numbers are exact
+    fn display_uncertain(&self) -> Uncertain {
+        let sigma = self
+            .display
+            .split_once('±')
+            .and_then(|(_, sigma)| sigma.trim().replace(',', "").parse().ok())
+            .unwrap_or(0.0);
+        Uncertain::new(self.get_display_value(), sigma)
+    }
 
-        // Simple expression evaluator
-        match self.parse_and_evaluate(expr) {
-            Ok(result) => {
-                self.set_display_result(result);
-                self.new_number = true;
-                self.expression_input.clear();
-            }
-            Err(e) => {
-                self.display = format!("Error: {}", e);
-                self.new_number = true;
+    fn open_paren(&mut self) {
+        if self.keypad_expression.is_empty() {
+            // Carry a pending "a op" over into the expression
+            if let Some(op) = self.operation {
+                let Some(infix) = op.infix() else {
+                    self.display = format!("Error: {} before parentheses", op.symbol());
+                    self.new_number = true;
+                    return;
+                };
+                self.keypad_expression = expression_operand(self.current_value);
+                self.keypad_expression.push(infix);
+                self.operation = None;
             }
         }
+        // A number typed right before "(" multiplies the group
+        if !self.new_number {
+            self.push_keypad_operand();
+        }
+        self.keypad_expression.push('(');
+        self.new_number = true;
     }
 
-    fn parse_and_evaluate(&self, expr: &str) -> Result<f64, String> {
-        // Remove spaces
-        let mut expr = expr.replace(" ", "");
-
-        // Handle implicit multiplication: )( -> )*(
-        expr = expr.replace(")(", ")*(");
-        // Handle implicit multiplication: number( -> number*(
-        expr = self.add_implicit_multiplication(&expr);
-
-        // Try to evaluate as a simple arithmetic expression
-        self.evaluate_with_precedence(&expr)
+    fn close_paren(&mut self) {
+        if self.open_paren_count() == 0 {
+            return;
+        }
+        self.push_keypad_operand();
+        self.keypad_expression.push(')');
+        self.new_number = true;
     }
 
-    fn add_implicit_multiplication(&self, expr: &str) -> String {
-        let mut result = String::new();
-        let chars: Vec<char> = expr.chars().collect();
+    fn open_paren_count(&self) -> usize {
+        let opened = self.keypad_expression.matches('(').count();
+        opened.saturating_sub(self.keypad_expression.matches(')').count())
+    }
 
-        for i in 0..chars.len() {
-            result.push(chars[i]);
+    // Append the number being entered to the keypad expression. Right after ")"
+    // it is only added if the user typed one (implicit multiplication).
+    fn push_keypad_operand(&mut self) {
+        if !self.new_number || !self.keypad_expression.ends_with(')') {
+            let value = self.get_display_value();
+            self.keypad_expression.push_str(&expression_operand(value));
+        }
+    }
 
-            if i + 1 < chars.len() {
-                let current = chars[i];
-                let next = chars[i + 1];
+    fn evaluate_keypad_expression(&mut self) {
+        self.push_keypad_operand();
+        // Close any groups left open
+        let unclosed = self.open_paren_count();
+        let mut expression = std::mem::take(&mut self.keypad_expression);
+        expression.push_str(&")".repeat(unclosed));
 
-                // Add * between: digit and (, ) and digit, ) and (
-                if (current.is_numeric() && next == '(')
-                    || (current == ')' && next.is_numeric())
-                    || (current == ')' && next == '(')
-                {
-                    result.push('*');
+        match self.evaluator().evaluate(&expression) {
+            Ok(result) => {
+                if result.is_finite() {
+                    self.history.push(HistoryEntry {
+                        expression: expression.clone(),
+                        result,
+                    });
                 }
+                self.set_display_result(result);
+                self.current_value = result;
             }
+            Err(e) => self.display = format!("Error: {}", e),
         }
-        result
+        self.last_calculation = Some((format!("{} =", expression), self.display.clone()));
+        self.new_number = true;
     }
 
-    fn evaluate_with_precedence(&self, expr: &str) -> Result<f64, String> {
-        // Handle parentheses first
-        if let Some(result) = self.handle_parentheses(expr)? {
-            return Ok(result);
+    // Secondary display line: the operation in progress (e.g. "128 ×" or
+    // "128 × 5"), or the last completed one while its result is still shown
+    fn pending_line(&self) -> String {
+        if !self.keypad_expression.is_empty() {
+            return if self.new_number {
+                self.keypad_expression.clone()
+            } else {
+                format!("{}{}", self.keypad_expression, self.display)
+            };
         }
-
-        // Check for addition/subtraction (lowest precedence)
-        // Need to skip operators inside parentheses
-        if let Some(pos) = self.find_operator_outside_parens(expr, '+') {
-            let left = self.evaluate_with_precedence(&expr[..pos])?;
-            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-            return Ok(left + right);
+        let current = format_uncertain(self.current_uncertain());
+        match self.operation {
+            Some(op) if self.new_number => format!("{} {}", current, op.symbol()),
+            Some(op) => format!("{} {} {}", current, op.symbol(), self.display),
+            None => match &self.last_calculation {
+                Some((expression, display)) if *display == self.display => expression.clone(),
+                _ => String::new(),
+            },
         }
+    }
 
-        if let Some(pos) = self.find_operator_outside_parens(expr, '-') {
-            if pos > 0 {
-                // Check if it's a negative sign or subtraction
-                let prev_char = expr.chars().nth(pos - 1);
-                if let Some(ch) = prev_char {
-                    if ch != '('
-                        && ch != '*'
-                        && ch != '/'
-                        && ch != '^'
-                        && ch != '+'
-                        && ch != '-'
-                        && ch != '%'
-                    {
-                        let left = self.evaluate_with_precedence(&expr[..pos])?;
-                        let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-                        return Ok(left - right);
-                    }
-                }
+    // Show an exact integer result. Values that fit an f64 exactly use the normal
+    // display path; larger ones show all digits unless scientific or engineering
+    // format is selected.
+    fn set_display_exact(&mut self, value: &BigUint) {
+        match exact_f64(value) {
+            Some(value) => self.set_display_result(value),
+            None => {
+                self.previous_display.clear();
+                self.display = format_big_integer(&value.to_string(), self.display_format);
             }
         }
+    }
 
-        // Check for multiplication/division
-        if let Some(pos) = self.find_operator_outside_parens(expr, '*') {
-            let left = self.evaluate_with_precedence(&expr[..pos])?;
-            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-            return Ok(left * right);
-        }
+    // A factorial too long to multiply out, by its leading digits
+    fn set_display_factorial_scientific(&mut self, n: f64) {
+        self.previous_display.clear();
+        self.display = match math::factorial_scientific(n) {
+            Ok(value) => value,
+            Err(e) => format!("Error: {}", e),
+        };
+    }
 
-        if let Some(pos) = self.find_operator_outside_parens(expr, '/') {
-            let left = self.evaluate_with_precedence(&expr[..pos])?;
-            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-            if right == 0.0 {
-                return Err("Division by zero".to_string());
-            }
-            return Ok(left / right);
-        }
+    fn push_history(&mut self, op: Operation, second: f64, result: f64) {
+        self.history.push(HistoryEntry {
+            expression: format!(
+                "{} {} {}",
+                format_number(self.current_value),
+                op.symbol(),
+                format_number(second)
+            ),
+            result,
+        });
+    }
 
-        // Check for modulo
-        if let Some(pos) = self.find_operator_outside_parens(expr, '%') {
-            let left = self.evaluate_with_precedence(&expr[..pos])?;
-            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-            return Ok(left % right);
+    fn get_display_value(&self) -> f64 {
+        // The uncertainty part of "12.3 ± 0.2" is read by display_uncertain
+        let value = self.display.split('±').next().unwrap_or_default().trim();
+        if let Some((numerator, denominator)) = value.split_once('/') {
+            // A fraction shown by → a/b
+            return match (
+                self.base_mode.parse(numerator),
+                self.base_mode.parse(denominator),
+            ) {
+                (Some(numerator), Some(denominator)) => numerator / denominator,
+                _ => 0.0,
+            };
         }
+        self.base_mode.parse(value).unwrap_or(0.0)
+    }
 
-        // Check for power
-        if let Some(pos) = self.find_operator_outside_parens(expr, '^') {
-            let left = self.evaluate_with_precedence(&expr[..pos])?;
-            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
-            return Ok(left.powf(right));
+    fn show_as_fraction(&mut self) {
+        let value = self.get_display_value();
+        match to_fraction(value, self.settings.fraction_tolerance) {
+            Some((numerator, 1)) => self.display = self.base_mode.format(numerator as f64, 0),
+            Some((numerator, denominator)) => {
+                self.display = format!(
+                    "{}/{}",
+                    self.base_mode.format(numerator as f64, 0),
+                    self.base_mode.format(denominator as f64, 0)
+                );
+            }
+            None => {
+                self.previous_display = self.display.clone();
+                self.display = "Error: No fraction within tolerance".to_string();
+            }
         }
+        self.new_number = true;
+    }
 
-        // Handle functions
-        if expr.starts_with("sqrt(") && expr.ends_with(")") {
-            let inner = &expr[5..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            return Ok(val.sqrt());
-        }
+    fn apply_function<F>(&mut self, f: F)
+    where
+        F: Fn(f64) -> f64,
+    {
+        let value = self.get_display_value();
+        let result = f(value);
+        self.set_display_result(result);
+        self.new_number = true;
+    }
 
-        if expr.starts_with("sin(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            let angle = if self.degree_mode {
-                val * PI / 180.0
-            } else {
-                val
-            };
-            return Ok(angle.sin());
+    fn evaluator(&self) -> Evaluator<'_> {
+        Evaluator {
+            degree_mode: self.degree_mode,
+            modulo_mode: self.settings.modulo_mode,
+            rounding_mode: self.settings.rounding_mode,
+            functions: Some(&self.script),
         }
+    }
 
-        if expr.starts_with("cos(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            let angle = if self.degree_mode {
-                val * PI / 180.0
-            } else {
-                val
-            };
-            return Ok(angle.cos());
+    fn evaluate_expression(&mut self) {
+        let expr = self.expression_input.trim();
+        if expr.is_empty() {
+            return;
         }
 
-        if expr.starts_with("tan(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            let angle = if self.degree_mode {
-                val * PI / 180.0
-            } else {
-                val
-            };
-            return Ok(angle.tan());
+        match self.evaluator().evaluate(expr) {
+            Ok(result) => {
+                if result.is_finite() {
+                    self.history.push(HistoryEntry {
+                        expression: expr.to_string(),
+                        result,
+                    });
+                }
+                self.set_display_result(result);
+                self.new_number = true;
+                self.expression_input.clear();
+            }
+            Err(e) => {
+                self.display = format!("Error: {}", e);
+                self.new_number = true;
+            }
         }
+    }
 
-        if expr.starts_with("log(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            return Ok(val.log10());
-        }
+    fn compile_script(&mut self) {
+        self.script_status = match self.script.compile(&self.settings.script) {
+            Ok(names) if names.is_empty() => "Compiled (no functions defined)".to_string(),
+            Ok(names) => format!("Functions: {}", names.join(", ")),
+            Err(e) => {
+                tracing::warn!(error = %e, "Script did not compile");
+                format!("Error: {}", e)
+            }
+        };
+    }
 
-        if expr.starts_with("ln(") && expr.ends_with(")") {
-            let inner = &expr[3..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            return Ok(val.ln());
+    // Run the script's top-level statements and show the final value
+    fn run_script(&mut self) {
+        self.compile_script();
+        if self.script_status.starts_with("Error:") {
+            return;
         }
-
-        // Handle factorial function
-        if expr.starts_with("factorial(") && expr.ends_with(")") {
-            let inner = &expr[10..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            return Ok(self.factorial(val));
+        match self.script.run() {
+            Ok(value) => self.set_display_result(value),
+            Err(e) => {
+                tracing::warn!(error = %e, "Script failed");
+                self.script_status = format!("Error: {}", e);
+            }
         }
+        self.new_number = true;
+    }
 
-        if expr.starts_with("fact(") && expr.ends_with(")") {
-            let inner = &expr[5..expr.len() - 1];
-            let val = self.evaluate_with_precedence(inner)?;
-            return Ok(self.factorial(val));
-        }
+    fn show_script_editor_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_script_editor;
+        egui::Window::new("Script Editor")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.label("Define functions in Rhai; call them from the expression field.");
+                egui::ScrollArea::vertical()
+                    .max_height(350.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.settings.script)
+                                .code_editor()
+                                .desired_rows(16)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button("Compile").clicked() {
+                        self.compile_script();
+                    }
+                    if ui
+                        .button("Run")
+                        .on_hover_text("Run top-level statements and show the result")
+                        .clicked()
+                    {
+                        self.run_script();
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.settings.script = DEFAULT_SCRIPT.to_string();
+                        self.compile_script();
+                    }
+                });
+                let color = if self.script_status.starts_with("Error:") {
+                    palette::ERROR
+                } else {
+                    Color32::from_gray(100)
+                };
+                ui.add(egui::Label::new(RichText::new(&self.script_status).color(color)).wrap());
+            });
+        self.show_script_editor = open;
+    }
 
-        // Handle nPr and nCr functions
-        if expr.starts_with("nPr(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            if let Some(comma_pos) = inner.find(',') {
-                let n = self.evaluate_with_precedence(&inner[..comma_pos])?;
-                let r = self.evaluate_with_precedence(&inner[comma_pos + 1..])?;
-                if n < 0.0 || r < 0.0 || r > n || n.fract() != 0.0 || r.fract() != 0.0 {
-                    return Err("Invalid nPr arguments".to_string());
-                }
-                if n > 170.0 {
-                    return Err("n too large (max 170)".to_string());
-                }
-                // Calculate nPr efficiently without overflow
-                let mut result = 1.0_f64;
-                for i in 0..(r as i32) {
-                    result *= n - i as f64;
-                }
-                return Ok(result);
+    fn perform(&mut self, action: KeyAction) {
+        let deg_mode = self.degree_mode;
+        let to_radians = move |x: f64| if deg_mode { x * PI / 180.0 } else { x };
+        match action {
+            KeyAction::Sqrt => self.apply_function(|x| x.sqrt()),
+            KeyAction::Square => self.apply_function(|x| x * x),
+            KeyAction::Reciprocal => {
+                self.apply_function(|x| if x != 0.0 { 1.0 / x } else { f64::INFINITY })
             }
-            return Err("nPr requires two arguments: nPr(n,r)".to_string());
-        }
-
-        if expr.starts_with("nCr(") && expr.ends_with(")") {
-            let inner = &expr[4..expr.len() - 1];
-            if let Some(comma_pos) = inner.find(',') {
-                let n = self.evaluate_with_precedence(&inner[..comma_pos])?;
-                let r = self.evaluate_with_precedence(&inner[comma_pos + 1..])?;
-                if n < 0.0 || r < 0.0 || r > n || n.fract() != 0.0 || r.fract() != 0.0 {
-                    return Err("Invalid nCr arguments".to_string());
-                }
-                if n > 170.0 {
-                    return Err("n too large (max 170)".to_string());
-                }
-                // Calculate nCr efficiently without overflow
-                let mut result = 1.0_f64;
-                let r_use = if r > n - r { n - r } else { r };
-                for i in 0..(r_use as i32) {
-                    result *= (n - i as f64) / (i as f64 + 1.0);
-                }
-                return Ok(result);
+            KeyAction::Sin => self.apply_function(|x| to_radians(x).sin()),
+            KeyAction::Cos => self.apply_function(|x| to_radians(x).cos()),
+            KeyAction::Tan => self.apply_function(|x| to_radians(x).tan()),
+            KeyAction::Ln => self.apply_function(|x| x.ln()),
+            KeyAction::Log => self.apply_function(|x| x.log10()),
+            KeyAction::Negate => {
+                let val = self.get_display_value();
+                self.display = format_number(-val);
             }
-            return Err("nCr requires two arguments: nCr(n,r)".to_string());
+            KeyAction::Multiply => self.set_operation(Operation::Multiply),
+            KeyAction::Divide => self.set_operation(Operation::Divide),
+            KeyAction::Power => self.set_operation(Operation::Power),
+            KeyAction::Modulo => self.set_operation(Operation::Modulo(self.settings.modulo_mode)),
+            KeyAction::OpenParen => self.open_paren(),
+            KeyAction::CloseParen => self.close_paren(),
+            KeyAction::Calculate => self.calculate(),
+            KeyAction::Clear => self.clear(),
+            KeyAction::ClearEntry => self.clear_entry(),
+            KeyAction::MemoryClear => self.memory_action("MC"),
+            KeyAction::MemoryRecall => self.memory_action("MR"),
+            KeyAction::MemoryAdd => self.memory_action("M+"),
+            KeyAction::MemorySubtract => self.memory_action("M-"),
+            KeyAction::ToggleAngleMode => self.degree_mode = !self.degree_mode,
         }
+    }
 
-        // Handle parentheses
-        if expr.starts_with("(") && expr.ends_with(")") {
-            return self.evaluate_with_precedence(&expr[1..expr.len() - 1]);
-        }
+    fn bound_action(&self, key: egui::Key) -> Option<KeyAction> {
+        keys::bound_action(&self.settings.key_bindings, key)
+    }
 
-        // Handle constants
-        if expr == "pi" {
-            return Ok(PI);
-        }
-        if expr == "e" {
-            return Ok(E);
-        }
+    fn binding_conflict(&self, key: egui::Key) -> Option<String> {
+        keys::conflict(&self.settings.key_bindings, key)
+    }
 
-        // Try to parse as a number
-        expr.parse::<f64>()
-            .map_err(|_| format!("Invalid expression: {}", expr))
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            if ui.button("⌨ Key Bindings...").clicked() {
+                self.show_key_bindings = true;
+            }
+        });
+        self.settings.common = common;
+        self.show_settings = open;
     }
 
-    // Find the rightmost occurrence of an operator outside of parentheses
-    fn find_operator_outside_parens(&self, expr: &str, op: char) -> Option<usize> {
-        let mut depth = 0;
-        let mut last_pos = None;
+    fn show_key_bindings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_key_bindings;
+        egui::Window::new("Key Bindings")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.label("Keys that trigger calculator commands when no text field has focus.");
+                let mut remove = None;
+                egui::Grid::new("key_bindings_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (index, binding) in self.settings.key_bindings.iter_mut().enumerate() {
+                            ui.monospace(binding.key.name());
+                            egui::ComboBox::from_id_salt(("key_action", index))
+                                .selected_text(binding.action.label())
+                                .show_ui(ui, |ui| {
+                                    for action in KeyAction::ALL {
+                                        ui.selectable_value(
+                                            &mut binding.action,
+                                            action,
+                                            action.label(),
+                                        );
+                                    }
+                                });
+                            if ui.button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = remove {
+                    self.settings.key_bindings.remove(index);
+                }
 
-        for (i, c) in expr.chars().enumerate() {
-            match c {
-                '(' => depth += 1,
-                ')' => depth -= 1,
-                _ => {
-                    if c == op && depth == 0 {
-                        last_pos = Some(i);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let key_text = if self.recording_key {
+                        "Press a key...".to_string()
+                    } else {
+                        self.new_binding_key
+                            .map_or("Record key".to_string(), |key| key.name().to_string())
+                    };
+                    if ui.button(key_text).clicked() {
+                        self.recording_key = !self.recording_key;
                     }
+                    egui::ComboBox::from_id_salt("new_key_action")
+                        .selected_text(self.new_binding_action.label())
+                        .show_ui(ui, |ui| {
+                            for action in KeyAction::ALL {
+                                ui.selectable_value(
+                                    &mut self.new_binding_action,
+                                    action,
+                                    action.label(),
+                                );
+                            }
+                        });
+                    let conflict = self
+                        .new_binding_key
+                        .and_then(|key| self.binding_conflict(key));
+                    let can_add = self.new_binding_key.is_some() && conflict.is_none();
+                    if ui.add_enabled(can_add, egui::Button::new("Add")).clicked() {
+                        if let Some(key) = self.new_binding_key.take() {
+                            self.settings.key_bindings.push(KeyBinding {
+                                key,
+                                action: self.new_binding_action,
+                            });
+                        }
+                    }
+                    if let Some(conflict) = conflict {
+                        ui.colored_label(palette::ERROR, conflict);
+                    }
+                });
+                if ui.button("Reset to defaults").clicked() {
+                    self.settings.key_bindings = default_key_bindings();
                 }
-            }
-        }
+            });
+        self.show_key_bindings = open;
+    }
 
-        last_pos
+    fn convert_base(&mut self, new_base: NumberBase) {
+        let current_val = self.get_display_value();
+        self.base_mode = new_base;
+        self.display = new_base.format(current_val, self.settings.fraction_digits);
+        self.new_number = true;
     }
 
-    // Handle parentheses - check if entire expression is wrapped
-    fn handle_parentheses(&self, expr: &str) -> Result<Option<f64>, String> {
-        if expr.starts_with("(") && expr.ends_with(")") {
-            // Verify matching parentheses
-            let mut depth = 0;
-            for (i, c) in expr.chars().enumerate() {
-                match c {
-                    '(' => depth += 1,
-                    ')' => depth -= 1,
-                    _ => {}
-                }
-                // If depth reaches 0 before the end, outer parens don't wrap everything
-                if depth == 0 && i < expr.len() - 1 {
-                    return Ok(None);
-                }
+    fn enter_hex_float(&mut self) {
+        match parse_hex_float(&self.hex_float_input) {
+            Some(value) => {
+                self.set_display_result(value);
+                self.hex_float_input.clear();
             }
-            // The entire expression is wrapped in parentheses
-            return Ok(Some(
-                self.evaluate_with_precedence(&expr[1..expr.len() - 1])?,
-            ));
+            None => self.display = "Error: Invalid hex float".to_string(),
         }
-        Ok(None)
-    }
-
-    fn convert_base(&mut self, new_base: &str) {
-        // Get the numeric value from current base
-        let current_val = self.get_display_value() as i64;
-
-        // Update base mode
-        self.base_mode = new_base.to_string();
-
-        // Format display in new base
-        self.display = match new_base {
-            "BIN" => format!("{:b}", current_val),
-            "OCT" => format!("{:o}", current_val),
-            "HEX" => format!("{:X}", current_val),
-            _ => current_val.to_string(), // DEC
-        };
         self.new_number = true;
     }
 
     fn apply_bitwise_not(&mut self) {
         let val = self.get_display_value() as i64;
-        let result = !val;
-        self.display = format_number(result as f64);
+        let result = programmer::bitwise_not(val, self.settings.word_size);
+        self.set_display_bits(result);
+    }
+
+    // Show an unsigned bit pattern in the current base
+    fn set_display_bits(&mut self, value: u64) {
+        self.previous_display.clear();
+        self.display = self.base_mode.format_bits(value);
         self.new_number = true;
     }
 
@@ -619,54 +1001,311 @@ impl Calculator {
 
     fn apply_shift_left(&mut self) {
         let val = self.get_display_value() as i64;
-        let result = val << 1; // Shift left by 1 bit
-        self.display = format_number(result as f64);
+        self.display = format_number(programmer::shift_left(val) as f64);
         self.new_number = true;
     }
 
     fn apply_shift_right(&mut self) {
         let val = self.get_display_value() as i64;
-        let result = val >> 1; // Shift right by 1 bit
-        self.display = format_number(result as f64);
+        self.display = format_number(programmer::shift_right(val) as f64);
         self.new_number = true;
     }
 
-    fn show_ascii_value(&mut self) {
-        let val = self.get_display_value() as u8;
-        if val < 128 {
-            let ch = val as char;
-            self.display = format!("{} = '{}'", val, ch);
-        } else {
-            self.display = format!("{} (non-ASCII)", val);
+    fn set_color_rgb(&mut self, rgb: [u8; 3]) {
+        self.color_rgb = rgb;
+        self.color_hsl = rgb_to_hsl(rgb);
+        self.color_hex_input = format_hex_color(rgb);
+    }
+
+    fn show_color_tools(&mut self, ui: &mut egui::Ui) {
+        let [r, g, b] = self.color_rgb;
+
+        ui.horizontal(|ui| {
+            // Live swatch preview
+            let (rect, _) = ui.allocate_exact_size(Vec2::new(60.0, 60.0), egui::Sense::hover());
+            ui.painter()
+                .rect_filled(rect, 4.0, Color32::from_rgb(r, g, b));
+            ui.painter()
+                .rect_stroke(rect, 4.0, egui::Stroke::new(1.0, Color32::from_gray(100)));
+
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Hex:");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.color_hex_input).desired_width(80.0),
+                    );
+                    if response.changed() {
+                        if let Some(rgb) = parse_hex_color(&self.color_hex_input) {
+                            self.color_rgb = rgb;
+                            self.color_hsl = rgb_to_hsl(rgb);
+                        }
+                    }
+                });
+
+                let mut rgb = self.color_rgb;
+                ui.horizontal(|ui| {
+                    ui.label("RGB:");
+                    let mut changed = false;
+                    for channel in rgb.iter_mut() {
+                        changed |= ui.add(egui::DragValue::new(channel)).changed();
+                    }
+                    if changed {
+                        self.set_color_rgb(rgb);
+                    }
+                });
+
+                let mut hsl = self.color_hsl;
+                ui.horizontal(|ui| {
+                    ui.label("HSL:");
+                    let mut changed = ui
+                        .add(
+                            egui::DragValue::new(&mut hsl[0])
+                                .range(0.0..=360.0)
+                                .suffix("°"),
+                        )
+                        .changed();
+                    for value in hsl[1..].iter_mut() {
+                        changed |= ui
+                            .add(egui::DragValue::new(value).range(0.0..=100.0).suffix("%"))
+                            .changed();
+                    }
+                    if changed {
+                        self.color_hsl = hsl;
+                        self.color_rgb = hsl_to_rgb(hsl);
+                        self.color_hex_input = format_hex_color(self.color_rgb);
+                    }
+                });
+            });
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("From display")
+                .on_hover_text("Use the current value as 0xRRGGBB")
+                .clicked()
+            {
+                let value = (self.get_display_value() as i64 & 0xFF_FFFF) as u32;
+                self.set_color_rgb([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+            }
+            if ui
+                .button("To display")
+                .on_hover_text("Show the color as a 24-bit value")
+                .clicked()
+            {
+                let value = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+                self.set_display_result(value as f64);
+                self.new_number = true;
+            }
+        });
+    }
+
+    fn show_subnet_calculator(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Address:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.subnet_address)
+                    .desired_width(130.0)
+                    .hint_text("10.0.0.1/8"),
+            );
+            ui.label("Mask:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.subnet_mask)
+                    .desired_width(110.0)
+                    .hint_text("24 or 255.255.255.0"),
+            );
+        });
+
+        // An address in CIDR form overrides the mask field
+        let (address, prefix) = match self.subnet_address.split_once('/') {
+            Some((address, prefix)) => (address.trim(), parse_prefix(prefix)),
+            None => (self.subnet_address.trim(), parse_prefix(&self.subnet_mask)),
+        };
+        let (Ok(address), Some(prefix)) = (address.parse::<Ipv4Addr>(), prefix) else {
+            ui.colored_label(
+                palette::ERROR,
+                "Enter an IPv4 address and a prefix (0-32) or netmask",
+            );
+            return;
+        };
+
+        let info = SubnetInfo::new(address, prefix);
+        egui::Grid::new("subnet_info")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let rows = [
+                    ("Network:", format!("{}/{}", info.network, prefix)),
+                    ("Broadcast:", info.broadcast.to_string()),
+                    ("Netmask:", info.netmask.to_string()),
+                    ("Wildcard:", info.wildcard.to_string()),
+                    (
+                        "Host range:",
+                        format!("{} – {}", info.first_host, info.last_host),
+                    ),
+                    (
+                        "Hosts:",
+                        add_thousands_separators(&info.host_count.to_string()),
+                    ),
+                ];
+                for (label, value) in rows {
+                    ui.label(label);
+                    ui.label(RichText::new(value).monospace());
+                    ui.end_row();
+                }
+            });
+
+        // Binary view of the mask: network bits highlighted, host bits dimmed
+        let mut job = egui::text::LayoutJob::default();
+        let mask_bits = u32::from(info.netmask);
+        for bit in 0..32 {
+            if bit > 0 && bit % 8 == 0 {
+                job.append(
+                    ".",
+                    0.0,
+                    egui::TextFormat::simple(egui::FontId::monospace(14.0), Color32::GRAY),
+                );
+            }
+            let is_network = mask_bits & (1 << (31 - bit)) != 0;
+            let (text, color) = if is_network {
+                ("1", palette::ACCENT)
+            } else {
+                ("0", Color32::from_gray(150))
+            };
+            job.append(
+                text,
+                0.0,
+                egui::TextFormat::simple(egui::FontId::monospace(14.0), color),
+            );
         }
-        self.new_number = true;
+        ui.label(job);
+    }
+
+    fn show_char_table_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_char_table;
+        egui::Window::new("Character Table")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Code point or character:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.char_query)
+                            .desired_width(140.0)
+                            .hint_text("U+263A, 0x41, A"),
+                    );
+                    if ui.button("From display").clicked() {
+                        // As U+ hex, so a single digit is not taken for the character
+                        self.char_query = format!("U+{:04X}", self.get_display_value() as i64);
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                // Details for the looked-up character
+                let query = self.char_query.trim().to_string();
+                let looked_up = lookup_char(&query);
+                if let Some(ch) = looked_up {
+                    let code = ch as u32;
+                    let mut utf8 = [0u8; 4];
+                    let bytes: Vec<String> = ch
+                        .encode_utf8(&mut utf8)
+                        .bytes()
+                        .map(|b| format!("{:02X}", b))
+                        .collect();
+
+                    egui::Grid::new("char_details")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Character:");
+                            ui.label(RichText::new(printable_char(ch)).size(32.0));
+                            ui.end_row();
+                            ui.label("Name:");
+                            ui.label(char_name(ch));
+                            ui.end_row();
+                            ui.label("Code point:");
+                            ui.label(format!("U+{:04X}", code));
+                            ui.end_row();
+                            ui.label("DEC / HEX:");
+                            ui.label(format!("{} / {:X}", code, code));
+                            ui.end_row();
+                            ui.label("OCT / BIN:");
+                            ui.label(format!("{:o} / {:b}", code, code));
+                            ui.end_row();
+                            ui.label("UTF-8 bytes:");
+                            ui.label(bytes.join(" "));
+                            ui.end_row();
+                        });
+
+                    if ui.button("Use code point").clicked() {
+                        self.set_display_result(code as f64);
+                        self.new_number = true;
+                    }
+                } else if !query.is_empty() {
+                    ui.label("No matching character. Filtering the table by name.");
+                }
+
+                ui.separator();
+
+                // ASCII/Latin-1 table, filtered by name when the query is not a code point
+                let filter = if looked_up.is_none() {
+                    query.to_uppercase()
+                } else {
+                    String::new()
+                };
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("char_table")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("DEC");
+                                ui.strong("HEX");
+                                ui.strong("Char");
+                                ui.strong("Name");
+                                ui.end_row();
+                                for code in 0u32..=0xFF {
+                                    let Some(ch) = char::from_u32(code) else {
+                                        continue;
+                                    };
+                                    let name = char_name(ch);
+                                    if !filter.is_empty() && !name.contains(&filter) {
+                                        continue;
+                                    }
+                                    ui.label(code.to_string());
+                                    ui.label(format!("{:02X}", code));
+                                    if ui.button(printable_char(ch)).clicked() {
+                                        self.char_query = format!("U+{:04X}", code);
+                                    }
+                                    ui.label(name);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        self.show_char_table = open;
     }
 
     fn apply_twos_complement(&mut self) {
         let val = self.get_display_value() as i64;
-        let result = -val; // Two's complement is simply negation in Rust
-        self.display = format_number(result as f64);
-        self.new_number = true;
+        let result = programmer::twos_complement(val, self.settings.word_size);
+        self.set_display_bits(result);
     }
 
     fn count_bits(&mut self) {
         let val = self.get_display_value() as u64;
-        let count = val.count_ones(); // Count set bits (1s)
-        self.display = format!("{} bits set", count);
+        self.display = format!("{} bits set", programmer::count_bits(val));
         self.new_number = true;
     }
 
     fn apply_rotate_left(&mut self) {
         let val = self.get_display_value() as u32;
-        let result = val.rotate_left(1); // Rotate left by 1 bit
-        self.display = format_number(result as f64);
+        self.display = format_number(programmer::rotate_left(val) as f64);
         self.new_number = true;
     }
 
     fn apply_rotate_right(&mut self) {
         let val = self.get_display_value() as u32;
-        let result = val.rotate_right(1); // Rotate right by 1 bit
-        self.display = format_number(result as f64);
+        self.display = format_number(programmer::rotate_right(val) as f64);
         self.new_number = true;
     }
 
@@ -685,23 +1324,18 @@ impl Calculator {
     }
 
     fn stat_mean(&mut self) {
-        if self.stat_data.is_empty() {
-            self.display = "Error: No data".to_string();
-        } else {
-            let sum: f64 = self.stat_data.iter().sum();
-            let mean = sum / self.stat_data.len() as f64;
-            self.display = format_number(mean);
-        }
+        self.display = match stats::mean(&self.stat_data) {
+            Some(mean) => format_number(mean),
+            None => "Error: No data".to_string(),
+        };
         self.new_number = true;
     }
 
     fn stat_sum(&mut self) {
-        if self.stat_data.is_empty() {
-            self.display = "Error: No data".to_string();
-        } else {
-            let sum: f64 = self.stat_data.iter().sum();
-            self.display = format_number(sum);
-        }
+        self.display = match stats::sum(&self.stat_data) {
+            Some(sum) => format_number(sum),
+            None => "Error: No data".to_string(),
+        };
         self.new_number = true;
     }
 
@@ -711,151 +1345,174 @@ impl Calculator {
     }
 
     fn stat_std_dev(&mut self) {
-        if self.stat_data.len() < 2 {
-            self.display = "Error: Need 2+ values".to_string();
-        } else {
-            let mean = self.stat_data.iter().sum::<f64>() / self.stat_data.len() as f64;
-            let variance = self
-                .stat_data
-                .iter()
-                .map(|x| (x - mean).powi(2))
-                .sum::<f64>()
-                / self.stat_data.len() as f64;
-            let std_dev = variance.sqrt();
-            self.display = format_number(std_dev);
-        }
+        self.display = match stats::std_dev(&self.stat_data) {
+            Some(std_dev) => format_number(std_dev),
+            None => "Error: Need 2+ values".to_string(),
+        };
         self.new_number = true;
     }
 
     fn stat_variance(&mut self) {
-        if self.stat_data.len() < 2 {
-            self.display = "Error: Need 2+ values".to_string();
-        } else {
-            let mean = self.stat_data.iter().sum::<f64>() / self.stat_data.len() as f64;
-            let variance = self
-                .stat_data
-                .iter()
-                .map(|x| (x - mean).powi(2))
-                .sum::<f64>()
-                / self.stat_data.len() as f64;
-            self.display = format_number(variance);
-        }
+        self.display = match stats::variance(&self.stat_data) {
+            Some(variance) => format_number(variance),
+            None => "Error: Need 2+ values".to_string(),
+        };
         self.new_number = true;
     }
 
-    // Probability Functions
-    fn permutation(&mut self, n: f64, r: f64) {
-        if n < 0.0 || r < 0.0 || r > n || n.fract() != 0.0 || r.fract() != 0.0 {
-            self.display = "Error: Invalid nPr".to_string();
-        } else if n > 170.0 {
-            self.display = "Error: n too large".to_string();
-        } else {
-            // Calculate nPr = n! / (n-r)! more efficiently
-            let mut result = 1.0_f64;
-            for i in 0..(r as i32) {
-                result *= n - i as f64;
-            }
-            self.display = format_number(result);
-        }
-        self.new_number = true;
+    // Business Functions
+    fn add_tax(&mut self) {
+        let rate = self.settings.tax_rate;
+        self.apply_function(|x| math::add_tax(x, rate));
     }
 
-    fn combination(&mut self, n: f64, r: f64) {
-        if n < 0.0 || r < 0.0 || r > n || n.fract() != 0.0 || r.fract() != 0.0 {
-            self.display = "Error: Invalid nCr".to_string();
-        } else if n > 170.0 {
-            self.display = "Error: n too large".to_string();
-        } else {
-            // Calculate nCr = n! / (r! * (n-r)!) more efficiently
-            // nCr = (n * (n-1) * ... * (n-r+1)) / (r * (r-1) * ... * 1)
-            let mut result = 1.0_f64;
-            let r_use = if r > n - r { n - r } else { r }; // Use smaller of r and n-r
-            for i in 0..(r_use as i32) {
-                result *= (n - i as f64) / (i as f64 + 1.0);
-            }
-            self.display = format_number(result);
-        }
-        self.new_number = true;
+    fn remove_tax(&mut self) {
+        let rate = self.settings.tax_rate;
+        self.apply_function(|x| math::remove_tax(x, rate));
     }
 
-    // Calculate factorial using f64 to handle large values (up to ~170)
-    fn factorial(&self, n: f64) -> f64 {
-        if n < 0.0 || n.fract() != 0.0 {
-            return f64::NAN; // Factorial only defined for non-negative integers
-        }
-        if n > 170.0 {
-            return f64::INFINITY; // Overflow protection
-        }
-        let mut result = 1.0;
-        for i in 2..=(n as i64) {
-            result *= i as f64;
-        }
-        result
+    fn markup_to_margin(&mut self) {
+        self.apply_function(math::markup_to_margin);
     }
 
-    // Calculate large factorials using BigUint (for values > 170)
-    fn big_factorial(&self, n: f64) -> String {
-        if n < 0.0 || n.fract() != 0.0 {
-            return "Error: Invalid (not a non-negative integer)".to_string();
-        }
-        if n > 100000.0 {
-            return "Error: Too large (max 100000)".to_string();
-        }
-
-        let n_int = n as u64;
-        let mut result: BigUint = One::one();
+    fn margin_to_markup(&mut self) {
+        self.apply_function(math::margin_to_markup);
+    }
 
-        for i in 2..=n_int {
-            result *= i;
-        }
+    // Export
+    fn export_history(&mut self, format: ExportFormat) {
+        let content = match format {
+            ExportFormat::Csv => {
+                let mut csv = String::from("expression,result\n");
+                for entry in &self.history {
+                    csv.push_str(&format!(
+                        "{},{}\n",
+                        csv_field(&entry.expression),
+                        entry.result
+                    ));
+                }
+                Ok(csv)
+            }
+            ExportFormat::Json => serde_json::to_string_pretty(&self.history),
+        };
+        self.write_export("history", format, content, self.history.len());
+    }
 
-        // Format with thousands separators for readability
-        let result_str = result.to_string();
-        self.format_with_separators(&result_str)
+    fn export_stat_data(&mut self, format: ExportFormat) {
+        let content = match format {
+            ExportFormat::Csv => {
+                let mut csv = String::from("index,value\n");
+                for (i, value) in self.stat_data.iter().enumerate() {
+                    csv.push_str(&format!("{},{}\n", i + 1, value));
+                }
+                Ok(csv)
+            }
+            ExportFormat::Json => serde_json::to_string_pretty(&self.stat_data),
+        };
+        self.write_export("statistics", format, content, self.stat_data.len());
     }
 
-    fn format_with_separators(&self, num_str: &str) -> String {
-        let len = num_str.len();
-        if len <= 3 {
-            return num_str.to_string();
-        }
+    fn write_export(
+        &mut self,
+        name: &str,
+        format: ExportFormat,
+        content: serde_json::Result<String>,
+        count: usize,
+    ) {
+        let (file_type, extension) = match format {
+            ExportFormat::Csv => (FileType::Csv, "csv"),
+            ExportFormat::Json => (FileType::Json, "json"),
+        };
+        let file_name = format!("{}.{}", name, extension);
+        let Some(path) = self.settings.common.dialogs.save(&[file_type], &file_name) else {
+            return;
+        };
 
-        let mut formatted = String::new();
-        for (i, ch) in num_str.chars().enumerate() {
-            if i > 0 && (len - i) % 3 == 0 {
-                formatted.push(',');
+        let result = content
+            .map_err(|e| e.to_string())
+            .and_then(|content| std::fs::write(&path, content).map_err(|e| e.to_string()));
+        self.display = match result {
+            Ok(()) => {
+                tracing::info!(path = %path.display(), count, "Exported {}", name);
+                format!("Exported {} items", count)
             }
-            formatted.push(ch);
-        }
-        formatted
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Export failed");
+                format!("Error: Export failed ({})", e)
+            }
+        };
+        self.new_number = true;
     }
 }
 
-fn format_number(num: f64) -> String {
-    if num.is_infinite() {
-        return "Error: Overflow".to_string();
-    }
-    if num.is_nan() {
-        return "Error: Invalid".to_string();
+// Number text for an expression; negatives are wrapped so "2*-3" stays parseable
+fn expression_operand(value: f64) -> String {
+    if value < 0.0 {
+        format!("({})", value)
+    } else {
+        value.to_string()
     }
+}
 
-    // Format with up to 18 significant digits
-    let formatted = format!("{:.18}", num);
-    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+// Show hash rows; clicking a hash copies it to the clipboard
+fn show_checksums(ui: &mut egui::Ui, id: &str, bytes: &[u8]) {
+    egui::Grid::new(id).num_columns(2).show(ui, |ui| {
+        for (name, hash) in checksums(bytes) {
+            ui.label(format!("{}:", name));
+            if ui
+                .add(egui::Label::new(RichText::new(&hash).monospace()).sense(egui::Sense::click()))
+                .on_hover_text("Click to copy")
+                .clicked()
+            {
+                ui.ctx().copy_text(hash);
+            }
+            ui.end_row();
+        }
+    });
+}
 
-    // Handle very large or very small numbers with scientific notation
-    if num.abs() >= 1e15 || (num.abs() < 1e-15 && num != 0.0) {
-        format!("{:.12e}", num)
+// Quote a CSV field if it contains separators, quotes or line breaks
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
     } else {
-        trimmed.to_string()
+        field.to_string()
     }
 }
 
 impl eframe::App for Calculator {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.settings.degree_mode = self.degree_mode;
+        self.settings.base_mode = self.base_mode;
+        self.settings.display_format = self.display_format;
+        // Saved again on the next save or at exit
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // egui's Ctrl+Plus and Ctrl+Minus zoom changes the scale too; keep it
+        self.settings.common.ui_scale = ctx.zoom_factor();
+        self.settings.common.remember_window(ctx);
+        if ctx.input_mut(|i| {
+            i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::L)
+        }) {
+            self.show_log = !self.show_log;
+        }
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        self.receive_messages(ctx);
+        self.poll_insert(ctx);
         egui::CentralPanel::default().show(ctx, |ui| {
             // Handle keyboard input
+            let text_focused = ctx.wants_keyboard_input();
+            // Enter presses a button that has keyboard focus instead of calculating
+            let button_focused = !text_focused && ctx.memory(|m| m.focused().is_some());
             ctx.input(|i| {
+                // A bound key's own character is not typed as well
+                let mut bound_key_pressed = false;
                 for event in &i.events {
                     if let egui::Event::Key {
                         key,
@@ -865,6 +1522,19 @@ impl eframe::App for Calculator {
                         ..
                     } = event
                     {
+                        if self.recording_key {
+                            self.new_binding_key = Some(*key);
+                            self.recording_key = false;
+                            continue;
+                        }
+                        bound_key_pressed = false;
+                        if !text_focused && !modifiers.ctrl && !modifiers.alt {
+                            if let Some(action) = self.bound_action(*key) {
+                                self.perform(action);
+                                bound_key_pressed = true;
+                                continue;
+                            }
+                        }
                         // Ignore number keys when Shift is pressed (for parentheses and other symbols)
                         match key {
                             egui::Key::Num0 if !modifiers.shift => self.append_digit("0"),
@@ -879,19 +1549,15 @@ impl eframe::App for Calculator {
                             egui::Key::Num9 if !modifiers.shift => self.append_digit("9"),
                             egui::Key::Plus => self.set_operation(Operation::Add),
                             egui::Key::Minus => self.set_operation(Operation::Subtract),
-                            egui::Key::Enter => self.calculate(),
+                            egui::Key::Enter if !button_focused => self.calculate(),
                             egui::Key::Escape => self.clear(),
-                            egui::Key::Backspace => {
-                                if !self.new_number && self.display.len() > 1 {
-                                    self.display.pop();
-                                } else {
-                                    self.display = "0".to_string();
-                                    self.new_number = true;
-                                }
-                            }
+                            egui::Key::Backspace => self.delete_digit(),
                             _ => {}
                         }
                     } else if let egui::Event::Text(text) = event {
+                        if std::mem::take(&mut bound_key_pressed) {
+                            continue;
+                        }
                         // Handle text input for operators and decimal
                         match text.as_str() {
                             "+" => self.set_operation(Operation::Add),
@@ -905,6 +1571,19 @@ impl eframe::App for Calculator {
                 }
             });
 
+            if self.show_char_table {
+                self.show_char_table_window(ctx);
+            }
+            if self.show_script_editor {
+                self.show_script_editor_window(ctx);
+            }
+            if self.show_key_bindings {
+                self.show_key_bindings_window(ctx);
+            }
+            if self.show_settings {
+                self.show_settings_window(ctx);
+            }
+
             egui::ScrollArea::vertical().show(ui, |ui| {
                 // Top margin
                 ui.add_space(10.0);
@@ -915,63 +1594,31 @@ impl eframe::App for Calculator {
 
                     ui.vertical(|ui| {
                         // Display at the top
-                        egui::Frame::none()
-                            .fill(Color32::from_gray(240))
-                            .stroke(egui::Stroke::new(2.0, Color32::from_gray(100)))
-                            .inner_margin(10.0)
-                            .show(ui, |ui| {
-                                ui.set_min_width(900.0);
-                                ui.set_max_width(900.0);
-                                ui.set_min_height(150.0);
-
-                                // Check if we have an error with a previous value
-                                if self.display.starts_with("Error:")
-                                    && !self.previous_display.is_empty()
-                                {
-                                    ui.vertical(|ui| {
-                                        ui.with_layout(
-                                            egui::Layout::right_to_left(egui::Align::Center),
-                                            |ui| {
-                                                let error_text = RichText::new(&self.display)
-                                                    .size(32.0)
-                                                    .monospace();
-                                                ui.label(error_text);
-                                            },
-                                        );
-                                        ui.with_layout(
-                                            egui::Layout::right_to_left(egui::Align::Center),
-                                            |ui| {
-                                                let prev_text =
-                                                    RichText::new(&self.previous_display)
-                                                        .size(16.0)
-                                                        .monospace()
-                                                        .color(Color32::from_gray(120));
-                                                ui.label(prev_text);
-                                            },
-                                        );
-                                    });
-                                } else {
-                                    // Use ScrollArea for long numbers with text wrapping
-                                    egui::ScrollArea::vertical()
-                                        .max_height(130.0)
-                                        .show(ui, |ui| {
-                                            ui.with_layout(
-                                                egui::Layout::top_down(egui::Align::Max),
-                                                |ui| {
-                                                    ui.set_max_width(880.0);
-                                                    ui.add(
-                                                        egui::Label::new(
-                                                            RichText::new(&self.display)
-                                                                .size(18.0)
-                                                                .monospace(),
-                                                        )
-                                                        .wrap(),
-                                                    );
-                                                },
-                                            );
-                                        });
-                                }
-                            });
+                        let pending = self.pending_line();
+                        if self.display.starts_with("Error:") && !self.previous_display.is_empty() {
+                            Display::new(&self.display)
+                                .pending(&pending)
+                                .note(&self.previous_display)
+                                .value_size(32.0)
+                                .show(ui);
+                        } else {
+                            Display::new(&self.display)
+                                .pending(&pending)
+                                .show(ui)
+                                .on_hover_text("Right-click to copy")
+                                .context_menu(|ui| {
+                                    for (name, text) in self.copy_formats() {
+                                        if ui
+                                            .button(format!("Copy as {}", name))
+                                            .on_hover_text(&text)
+                                            .clicked()
+                                        {
+                                            ui.ctx().copy_text(text);
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                        }
 
                         ui.add_space(10.0);
 
@@ -983,6 +1630,59 @@ impl eframe::App for Calculator {
                             ));
                             ui.separator();
                             ui.label(format!("Memory: {:.2}", self.memory));
+                            ui.separator();
+                            egui::ComboBox::from_label("mod")
+                                .selected_text(self.settings.modulo_mode.label())
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.settings.modulo_mode,
+                                        ModuloMode::Truncated,
+                                        "Truncated (-7 mod 3 = -1)",
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.settings.modulo_mode,
+                                        ModuloMode::Floored,
+                                        "Floored (-7 mod 3 = 2)",
+                                    );
+                                });
+                            ui.separator();
+                            egui::ComboBox::from_label("round")
+                                .selected_text(self.settings.rounding_mode.label())
+                                .show_ui(ui, |ui| {
+                                    for (mode, example) in [
+                                        (RoundingMode::HalfUp, "2.5 → 3, -2.5 → -3"),
+                                        (RoundingMode::HalfEven, "2.5 → 2, 3.5 → 4"),
+                                        (RoundingMode::TowardZero, "2.9 → 2, -2.9 → -2"),
+                                    ] {
+                                        ui.selectable_value(
+                                            &mut self.settings.rounding_mode,
+                                            mode,
+                                            format!("{} ({})", mode.label(), example),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Rounding for the Fixed format and round()");
+                            ui.separator();
+                            ui.toggle_value(&mut self.uncertainty_mode, "± Uncertainty")
+                                .on_hover_text(
+                                    "Enter values as 12.3 ± 0.2 with the ±σ key; \
+                                     + − × ÷ and x^y propagate the uncertainty",
+                                );
+                            ui.separator();
+                            if ui
+                                .add_enabled(
+                                    self.note_job.is_none(),
+                                    egui::Button::new("📝 Insert into Note"),
+                                )
+                                .on_hover_text("Put the result at the caret of the open note")
+                                .clicked()
+                            {
+                                self.insert_into_note();
+                            }
+                            if let Some(status) = &self.note_status {
+                                ui.weak(status);
+                            }
                         });
 
                         ui.add_space(5.0);
@@ -993,35 +1693,82 @@ impl eframe::App for Calculator {
                             if ui.button("Regular").clicked() {
                                 self.display_format = DisplayFormat::Regular;
                                 if let Ok(val) = self.display.replace(",", "").parse::<f64>() {
-                                    self.display = self.format_number_with_style(val);
+                                    self.display = self.format_display(val);
                                 }
                             }
                             if ui.button("Fixed").clicked() {
                                 self.display_format = DisplayFormat::Fixed;
                                 if let Ok(val) = self.display.replace(",", "").parse::<f64>() {
-                                    self.display = self.format_number_with_style(val);
+                                    self.display = self.format_display(val);
                                 }
                             }
                             if ui.button("Scientific").clicked() {
                                 self.display_format = DisplayFormat::Scientific;
                                 if let Ok(val) = self.display.replace(",", "").parse::<f64>() {
-                                    self.display = self.format_number_with_style(val);
+                                    self.display = self.format_display(val);
                                 }
                             }
                             if ui.button("Engineer").clicked() {
                                 self.display_format = DisplayFormat::Engineering;
                                 if let Ok(val) = self.display.replace(",", "").parse::<f64>() {
-                                    self.display = self.format_number_with_style(val);
+                                    self.display = self.format_display(val);
                                 }
                             }
                             if ui.button("Triads").clicked() {
                                 self.display_format = DisplayFormat::Triads;
                                 if let Ok(val) = self.display.replace(",", "").parse::<f64>() {
-                                    self.display = self.format_number_with_style(val);
+                                    self.display = self.format_display(val);
                                 }
                             }
+                            ui.separator();
+                            ui.toggle_value(&mut self.show_words, "Words")
+                                .on_hover_text("Spell out the current value in English");
+                            ui.separator();
+                            if ui
+                                .button("→ a/b")
+                                .on_hover_text("Show the nearest simple fraction")
+                                .clicked()
+                            {
+                                self.show_as_fraction();
+                            }
+                            egui::ComboBox::from_id_salt("fraction_tolerance")
+                                .selected_text(format!(
+                                    "tol {:e}",
+                                    self.settings.fraction_tolerance
+                                ))
+                                .show_ui(ui, |ui| {
+                                    for tolerance in
+                                        [1e-2, 1e-3, 1e-4, 1e-5, 1e-6, 1e-8, 1e-10, 1e-12]
+                                    {
+                                        ui.selectable_value(
+                                            &mut self.settings.fraction_tolerance,
+                                            tolerance,
+                                            format!("{:e}", tolerance),
+                                        );
+                                    }
+                                })
+                                .response
+                                .on_hover_text(
+                                    "Maximum difference between the value and the fraction",
+                                );
                         });
 
+                        if self.show_words && !self.display.starts_with("Error:") {
+                            ui.add_space(5.0);
+                            ui.horizontal(|ui| {
+                                ui.set_max_width(900.0);
+                                let words = number_to_words(self.get_display_value());
+                                ui.add(egui::Label::new(RichText::new(&words).italics()).wrap())
+                                    .on_hover_text("Right-click to copy")
+                                    .context_menu(|ui| {
+                                        if ui.button("Copy").clicked() {
+                                            ui.ctx().copy_text(words.clone());
+                                            ui.close_menu();
+                                        }
+                                    });
+                            });
+                        }
+
                         ui.add_space(10.0);
 
                         // Main content area with buttons side by side
@@ -1034,36 +1781,33 @@ impl eframe::App for Calculator {
 
                                 // Memory and Mode buttons
                                 ui.horizontal(|ui| {
-                                    if ui
-                                        .add_sized(small_button_size, egui::Button::new("MC"))
-                                        .clicked()
-                                    {
-                                        self.memory = 0.0;
-                                    }
-                                    if ui
-                                        .add_sized(small_button_size, egui::Button::new("MR"))
-                                        .clicked()
-                                    {
-                                        self.display = format_number(self.memory);
-                                        self.new_number = true;
+                                    for action in ["MC", "MR", "M+", "M-"] {
+                                        if ui
+                                            .add_sized(small_button_size, egui::Button::new(action))
+                                            .clicked()
+                                        {
+                                            self.memory_action(action);
+                                        }
                                     }
                                     if ui
-                                        .add_sized(small_button_size, egui::Button::new("M+"))
+                                        .add_sized(small_button_size, egui::Button::new("DEG/RAD"))
                                         .clicked()
                                     {
-                                        self.memory += self.get_display_value();
+                                        self.degree_mode = !self.degree_mode;
                                     }
                                     if ui
-                                        .add_sized(small_button_size, egui::Button::new("M-"))
+                                        .add_sized(small_button_size, egui::Button::new("Keys..."))
+                                        .on_hover_text("Edit key bindings")
                                         .clicked()
                                     {
-                                        self.memory -= self.get_display_value();
+                                        self.show_key_bindings = !self.show_key_bindings;
                                     }
                                     if ui
-                                        .add_sized(small_button_size, egui::Button::new("DEG/RAD"))
+                                        .add_sized(small_button_size, egui::Button::new("⚙"))
+                                        .labeled("Settings")
                                         .clicked()
                                     {
-                                        self.degree_mode = !self.degree_mode;
+                                        self.show_settings = !self.show_settings;
                                     }
                                 });
 
@@ -1128,24 +1872,33 @@ impl eframe::App for Calculator {
                                 ui.horizontal(|ui| {
                                     if ui
                                         .add_sized(small_button_size, egui::Button::new("n!"))
+                                        .labeled("Factorial")
                                         .clicked()
                                     {
                                         let value = self.get_display_value();
-                                        let result = self.factorial(value);
-                                        self.set_display_result(result);
+                                        match big_factorial(value) {
+                                            Ok(result) => self.set_display_exact(&result),
+                                            Err(_) => self.set_display_factorial_scientific(value),
+                                        }
                                         self.new_number = true;
                                     }
                                     if ui
                                         .add_sized(
                                             small_button_size,
-                                            egui::Button::new("n!!")
-                                                .fill(Color32::from_rgb(255, 215, 0)),
+                                            filled_button("n!!", palette::SPECIAL),
                                         )
+                                        .labeled("Exact factorial with thousands separators")
                                         .clicked()
                                     {
                                         let value = self.get_display_value();
-                                        self.display = self.big_factorial(value);
-                                        self.previous_display.clear();
+                                        match big_factorial(value) {
+                                            Ok(result) => {
+                                                self.display =
+                                                    add_thousands_separators(&result.to_string());
+                                                self.previous_display.clear();
+                                            }
+                                            Err(_) => self.set_display_factorial_scientific(value),
+                                        }
                                         self.new_number = true;
                                     }
                                 });
@@ -1196,12 +1949,14 @@ impl eframe::App for Calculator {
                                     }
                                     if ui
                                         .add_sized(small_button_size, egui::Button::new("√"))
+                                        .labeled("Square root")
                                         .clicked()
                                     {
                                         self.apply_function(|x| x.sqrt());
                                     }
                                     if ui
                                         .add_sized(small_button_size, egui::Button::new("x²"))
+                                        .labeled("Square")
                                         .clicked()
                                     {
                                         self.apply_function(|x| x * x);
@@ -1230,6 +1985,7 @@ impl eframe::App for Calculator {
                                     }
                                     if ui
                                         .add_sized(small_button_size, egui::Button::new("π"))
+                                        .labeled("Pi")
                                         .clicked()
                                     {
                                         self.display = format_number(PI);
@@ -1237,6 +1993,7 @@ impl eframe::App for Calculator {
                                     }
                                     if ui
                                         .add_sized(small_button_size, egui::Button::new("e"))
+                                        .labeled("Euler's number e")
                                         .clicked()
                                     {
                                         self.display = format_number(E);
@@ -1246,125 +2003,51 @@ impl eframe::App for Calculator {
 
                                 ui.add_space(5.0);
 
-                                // Clear buttons
+                                // Parentheses build an expression shown above the display
                                 ui.horizontal(|ui| {
                                     if ui
-                                        .add_sized(
-                                            button_size,
-                                            egui::Button::new("C")
-                                                .fill(Color32::from_rgb(255, 165, 0)),
-                                        )
-                                        .clicked()
-                                    {
-                                        self.clear();
-                                    }
-                                    if ui
-                                        .add_sized(
-                                            button_size,
-                                            egui::Button::new("CE")
-                                                .fill(Color32::from_rgb(255, 0, 0)),
-                                        )
+                                        .add_sized(button_size, egui::Button::new("("))
+                                        .labeled("Open parenthesis")
                                         .clicked()
                                     {
-                                        self.clear_entry();
-                                    }
+                                        self.open_paren();
+                                    }
+                                    let open = self.open_paren_count();
+                                    let close_label = if open > 0 {
+                                        format!(") [{}]", open)
+                                    } else {
+                                        ")".to_string()
+                                    };
                                     if ui
-                                        .add_sized(
-                                            button_size,
-                                            egui::Button::new("DEL")
-                                                .fill(Color32::from_rgb(173, 216, 230)),
+                                        .add_enabled(
+                                            open > 0,
+                                            egui::Button::new(close_label).min_size(button_size),
                                         )
+                                        .labeled("Close parenthesis")
                                         .clicked()
                                     {
-                                        if !self.new_number && self.display.len() > 1 {
-                                            self.display.pop();
-                                        } else {
-                                            self.display = "0".to_string();
-                                            self.new_number = true;
-                                        }
+                                        self.close_paren();
                                     }
-                                    if ui.add_sized(button_size, egui::Button::new("±")).clicked()
+                                    if self.uncertainty_mode
+                                        && ui
+                                            .add_sized(button_size, egui::Button::new("±σ"))
+                                            .on_hover_text("Enter the uncertainty of this value")
+                                            .clicked()
                                     {
-                                        let val = self.get_display_value();
-                                        self.display = format_number(-val);
+                                        self.start_uncertainty();
                                     }
                                 });
 
                                 // Number pad and operations
-                                ui.horizontal(|ui| {
-                                    if ui.add_sized(button_size, egui::Button::new("7")).clicked() {
-                                        self.append_digit("7");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("8")).clicked() {
-                                        self.append_digit("8");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("9")).clicked() {
-                                        self.append_digit("9");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("÷")).clicked()
-                                    {
-                                        self.set_operation(Operation::Divide);
-                                    }
-                                });
-
-                                ui.horizontal(|ui| {
-                                    if ui.add_sized(button_size, egui::Button::new("4")).clicked() {
-                                        self.append_digit("4");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("5")).clicked() {
-                                        self.append_digit("5");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("6")).clicked() {
-                                        self.append_digit("6");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("×")).clicked()
-                                    {
-                                        self.set_operation(Operation::Multiply);
-                                    }
-                                });
-
-                                ui.horizontal(|ui| {
-                                    if ui.add_sized(button_size, egui::Button::new("1")).clicked() {
-                                        self.append_digit("1");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("2")).clicked() {
-                                        self.append_digit("2");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("3")).clicked() {
-                                        self.append_digit("3");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("−")).clicked()
-                                    {
-                                        self.set_operation(Operation::Subtract);
-                                    }
-                                });
-
-                                ui.horizontal(|ui| {
-                                    if ui.add_sized(button_size, egui::Button::new("0")).clicked() {
-                                        self.append_digit("0");
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new(".")).clicked() {
-                                        self.append_digit(".");
-                                    }
-                                    if ui
-                                        .add_sized(
-                                            button_size,
-                                            egui::Button::new("=")
-                                                .fill(Color32::from_rgb(0, 200, 0)),
-                                        )
-                                        .clicked()
-                                    {
-                                        self.calculate();
-                                    }
-                                    if ui.add_sized(button_size, egui::Button::new("+")).clicked() {
-                                        self.set_operation(Operation::Add);
-                                    }
-                                });
+                                if let Some(key) = Keypad::new().button_size(button_size).show(ui) {
+                                    self.press(key);
+                                }
 
                                 // Advanced operations
                                 ui.horizontal(|ui| {
                                     if ui
                                         .add_sized(button_size, egui::Button::new("x^y"))
+                                        .labeled("Power")
                                         .clicked()
                                     {
                                         self.set_operation(Operation::Power);
@@ -1379,10 +2062,13 @@ impl eframe::App for Calculator {
                                         .add_sized(button_size, egui::Button::new("mod"))
                                         .clicked()
                                     {
-                                        self.set_operation(Operation::Modulo);
+                                        self.set_operation(Operation::Modulo(
+                                            self.settings.modulo_mode,
+                                        ));
                                     }
                                     if ui
                                         .add_sized(button_size, egui::Button::new("1/x"))
+                                        .labeled("Reciprocal")
                                         .clicked()
                                     {
                                         self.apply_function(|x| {
@@ -1412,33 +2098,161 @@ impl eframe::App for Calculator {
                                     if ui.button("Evaluate").clicked() {
                                         self.evaluate_expression();
                                     }
+                                    if ui
+                                        .button("Script...")
+                                        .on_hover_text("Define custom functions")
+                                        .clicked()
+                                    {
+                                        self.show_script_editor = !self.show_script_editor;
+                                    }
+                                });
+
+                                ui.add_space(5.0);
+                                ui.collapsing(format!("History ({})", self.history.len()), |ui| {
+                                    ui.horizontal(|ui| {
+                                        if ui.button("Export CSV").clicked() {
+                                            self.export_history(ExportFormat::Csv);
+                                        }
+                                        if ui.button("Export JSON").clicked() {
+                                            self.export_history(ExportFormat::Json);
+                                        }
+                                        if ui.button("Clear").clicked() {
+                                            self.history.clear();
+                                        }
+                                    });
+                                    egui::ScrollArea::vertical()
+                                        .id_salt("history_list")
+                                        .max_height(200.0)
+                                        .show(ui, |ui| {
+                                            let mut recall = None;
+                                            for entry in self.history.iter().rev() {
+                                                let text = format!(
+                                                    "{} = {}",
+                                                    entry.expression,
+                                                    format_number(entry.result)
+                                                );
+                                                if ui
+                                                    .selectable_label(false, text)
+                                                    .on_hover_text("Click to recall result")
+                                                    .clicked()
+                                                {
+                                                    recall = Some(entry.result);
+                                                }
+                                            }
+                                            if let Some(value) = recall {
+                                                self.set_display_result(value);
+                                                self.new_number = true;
+                                            }
+                                        });
                                 });
+                                ui.collapsing(
+                                    format!("Memory History ({})", self.memory_history.len()),
+                                    |ui| {
+                                        if ui.button("Clear").clicked() {
+                                            self.memory_history.clear();
+                                        }
+                                        egui::ScrollArea::vertical()
+                                            .id_salt("memory_history_list")
+                                            .max_height(150.0)
+                                            .show(ui, |ui| {
+                                                let mut restore = None;
+                                                for event in self.memory_history.iter().rev() {
+                                                    let text = format!(
+                                                        "{}  {}  M = {}",
+                                                        event.time,
+                                                        event.action,
+                                                        format_number(event.value)
+                                                    );
+                                                    if ui
+                                                        .selectable_label(false, text)
+                                                        .on_hover_text(
+                                                            "Click to restore this memory value",
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        restore = Some(event.value);
+                                                    }
+                                                }
+                                                if let Some(value) = restore {
+                                                    self.memory = value;
+                                                }
+                                            });
+                                    },
+                                );
                             }); // Close left column vertical
 
                             ui.add_space(15.0);
 
                             // Right column: Base conversion and bitwise operations
                             ui.vertical(|ui| {
-                                ui.label(format!("Mode: {}", self.base_mode));
+                                ui.label(format!("Mode: {}", self.base_mode.label()));
                                 ui.add_space(5.0);
                                 ui.label("Base Conversion:");
                                 ui.horizontal(|ui| {
                                     if ui.button("DEC").clicked() {
-                                        self.convert_base("DEC");
+                                        self.convert_base(NumberBase::Dec);
                                     }
                                     if ui.button("BIN").clicked() {
-                                        self.convert_base("BIN");
+                                        self.convert_base(NumberBase::Bin);
                                     }
                                     if ui.button("OCT").clicked() {
-                                        self.convert_base("OCT");
+                                        self.convert_base(NumberBase::Oct);
                                     }
                                     if ui.button("HEX").clicked() {
-                                        self.convert_base("HEX");
+                                        self.convert_base(NumberBase::Hex);
+                                    }
+                                    if ui
+                                        .button("HEXF")
+                                        .on_hover_text("Hex float, e.g. 0x1.8p3 = 12")
+                                        .clicked()
+                                    {
+                                        self.convert_base(NumberBase::HexFloat);
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Fraction digits:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut self.settings.fraction_digits)
+                                            .range(0..=32),
+                                    )
+                                    .on_hover_text("0 drops the fraction when converting");
+                                });
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Hex float:");
+                                    let response = ui.add(
+                                        egui::TextEdit::singleline(&mut self.hex_float_input)
+                                            .desired_width(120.0)
+                                            .hint_text("0x1.8p3"),
+                                    );
+                                    if response.lost_focus()
+                                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                                    {
+                                        self.enter_hex_float();
+                                    }
+                                    if ui.button("Enter").clicked() {
+                                        self.enter_hex_float();
                                     }
                                 });
 
                                 ui.add_space(10.0);
-                                ui.label("Bitwise Operations:");
+                                ui.horizontal(|ui| {
+                                    ui.label("Bitwise Operations:");
+                                    egui::ComboBox::from_id_salt("word_size")
+                                        .selected_text(self.settings.word_size.label())
+                                        .show_ui(ui, |ui| {
+                                            for size in WordSize::ALL {
+                                                ui.selectable_value(
+                                                    &mut self.settings.word_size,
+                                                    size,
+                                                    size.label(),
+                                                );
+                                            }
+                                        })
+                                        .response
+                                        .on_hover_text("Word size for NOT and 2's Comp");
+                                });
 
                                 // NOT operation (unary)
                                 ui.horizontal(|ui| {
@@ -1480,10 +2294,10 @@ impl eframe::App for Calculator {
                                 ui.add_space(5.0);
                                 ui.label("Bit Shifts:");
                                 ui.horizontal(|ui| {
-                                    if ui.button("<<").clicked() {
+                                    if ui.button("<<").labeled("Shift left").clicked() {
                                         self.apply_shift_left();
                                     }
-                                    if ui.button(">>").clicked() {
+                                    if ui.button(">>").labeled("Shift right").clicked() {
                                         self.apply_shift_right();
                                     }
                                 });
@@ -1492,8 +2306,8 @@ impl eframe::App for Calculator {
                                 ui.label("Programmer Tools:");
 
                                 ui.horizontal(|ui| {
-                                    if ui.button("ASCII").clicked() {
-                                        self.show_ascii_value();
+                                    if ui.button("Char Table").clicked() {
+                                        self.show_char_table = !self.show_char_table;
                                     }
                                     if ui.button("2's Comp").clicked() {
                                         self.apply_twos_complement();
@@ -1518,6 +2332,43 @@ impl eframe::App for Calculator {
                                     }
                                 });
 
+                                ui.add_space(5.0);
+                                ui.collapsing("Color Tools", |ui| {
+                                    self.show_color_tools(ui);
+                                });
+
+                                ui.collapsing("Subnet Calculator", |ui| {
+                                    self.show_subnet_calculator(ui);
+                                });
+
+                                ui.collapsing("Checksums & Hashes", |ui| {
+                                    let (bytes, repr) = value_bytes(self.get_display_value());
+                                    ui.label(format!("Current value ({}):", repr));
+                                    ui.label(RichText::new(to_hex(&bytes)).monospace().weak());
+                                    show_checksums(ui, "value_hashes", &bytes);
+
+                                    ui.add_space(5.0);
+                                    ui.label("Hex bytes:");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.hash_input)
+                                            .desired_width(250.0)
+                                            .hint_text("DE AD BE EF"),
+                                    );
+                                    if !self.hash_input.trim().is_empty() {
+                                        match parse_hex_bytes(&self.hash_input) {
+                                            Some(bytes) => {
+                                                show_checksums(ui, "input_hashes", &bytes)
+                                            }
+                                            None => {
+                                                ui.colored_label(
+                                                    palette::ERROR,
+                                                    "Invalid hex string",
+                                                );
+                                            }
+                                        }
+                                    }
+                                });
+
                                 ui.add_space(15.0);
                                 ui.separator();
                                 ui.add_space(5.0);
@@ -1532,6 +2383,15 @@ impl eframe::App for Calculator {
                                     }
                                 });
 
+                                ui.horizontal(|ui| {
+                                    if ui.button("Export CSV").clicked() {
+                                        self.export_stat_data(ExportFormat::Csv);
+                                    }
+                                    if ui.button("Export JSON").clicked() {
+                                        self.export_stat_data(ExportFormat::Json);
+                                    }
+                                });
+
                                 // Data display window - Resizable
                                 egui::Frame::group(ui.style()).show(ui, |ui| {
                                     egui::ScrollArea::vertical()
@@ -1600,6 +2460,70 @@ impl eframe::App for Calculator {
                                         self.set_operation(Operation::Combination);
                                     }
                                 });
+
+                                ui.add_space(10.0);
+                                ui.label("Business:");
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Tax %:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut self.settings.tax_rate)
+                                            .speed(0.1)
+                                            .range(0.0..=100.0),
+                                    );
+                                });
+
+                                ui.horizontal(|ui| {
+                                    if ui.button("+TAX").clicked() {
+                                        self.add_tax();
+                                    }
+                                    if ui.button("−TAX").clicked() {
+                                        self.remove_tax();
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .button("MU→Sell")
+                                        .on_hover_text("cost MU→Sell markup% = sell price")
+                                        .clicked()
+                                    {
+                                        self.set_operation(Operation::SellFromMarkup);
+                                    }
+                                    if ui
+                                        .button("MG→Sell")
+                                        .on_hover_text("cost MG→Sell margin% = sell price")
+                                        .clicked()
+                                    {
+                                        self.set_operation(Operation::SellFromMargin);
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .button("MU→Cost")
+                                        .on_hover_text("sell MU→Cost markup% = cost")
+                                        .clicked()
+                                    {
+                                        self.set_operation(Operation::CostFromMarkup);
+                                    }
+                                    if ui
+                                        .button("MG→Cost")
+                                        .on_hover_text("sell MG→Cost margin% = cost")
+                                        .clicked()
+                                    {
+                                        self.set_operation(Operation::CostFromMargin);
+                                    }
+                                });
+
+                                ui.horizontal(|ui| {
+                                    if ui.button("MU%→MG%").clicked() {
+                                        self.markup_to_margin();
+                                    }
+                                    if ui.button("MG%→MU%").clicked() {
+                                        self.margin_to_markup();
+                                    }
+                                });
                             });
                         }); // Close horizontal for main content
                     });
@@ -0,0 +1,298 @@
+// Picking a color anywhere on the screen. While picking, the color under the
+// pointer is previewed as it moves; a left click takes it and a right click
+// cancels. Reading the screen is up to each platform: X11 on Linux and the
+// BSDs, and GDI on Windows
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often the pointer is looked at while picking
+const POLL_INTERVAL: Duration = Duration::from_millis(15);
+
+pub enum PickEvent {
+    Hover([u8; 3]), // The color under the pointer, while it moves
+    Picked([u8; 3]),
+    Cancelled,
+    Failed(String),
+}
+
+// A pick in progress on its own thread; dropping it cancels the pick
+pub struct Eyedropper {
+    events: Receiver<PickEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Eyedropper {
+    pub fn start(ctx: &egui::Context) -> Self {
+        let (sender, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&cancel);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let send = |event| {
+                let _ = sender.send(event);
+                ctx.request_repaint();
+            };
+            if let Err(e) = platform::pick(&stop, &send) {
+                send(PickEvent::Failed(e));
+            }
+        });
+        Self { events, cancel }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    // Events since the last call
+    pub fn events(&self) -> Vec<PickEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for Eyedropper {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::{PickEvent, POLL_INTERVAL};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{
+        ConnectionExt, EventMask, GrabMode, GrabStatus, ImageFormat, ImageOrder, Screen, Setup,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::{CURRENT_TIME, NONE};
+
+    // The crosshair in X's standard cursor font
+    const XC_CROSSHAIR: u16 = 34;
+
+    fn failed(e: impl std::fmt::Display) -> String {
+        format!("Screen not readable: {}", e)
+    }
+
+    // How the root window's pixels are laid out
+    struct PixelFormat {
+        bytes: usize,
+        msb_first: bool,
+        masks: [u32; 3],
+    }
+
+    impl PixelFormat {
+        fn of(setup: &Setup, screen: &Screen) -> Option<Self> {
+            let visual = screen
+                .allowed_depths
+                .iter()
+                .flat_map(|depth| &depth.visuals)
+                .find(|visual| visual.visual_id == screen.root_visual)?;
+            let bits = setup
+                .pixmap_formats
+                .iter()
+                .find(|format| format.depth == screen.root_depth)?
+                .bits_per_pixel;
+            // Palette-based screens of 8 bits or fewer are long gone
+            (bits == 16 || bits == 24 || bits == 32).then_some(Self {
+                bytes: bits as usize / 8,
+                msb_first: setup.image_byte_order == ImageOrder::MSB_FIRST,
+                masks: [visual.red_mask, visual.green_mask, visual.blue_mask],
+            })
+        }
+
+        fn rgb(&self, data: &[u8]) -> Option<[u8; 3]> {
+            let bytes = data.get(..self.bytes)?;
+            let fold = |pixel: u32, byte: &u8| pixel << 8 | *byte as u32;
+            let pixel = if self.msb_first {
+                bytes.iter().fold(0, fold)
+            } else {
+                bytes.iter().rev().fold(0, fold)
+            };
+            Some(
+                self.masks
+                    .map(|mask| {
+                        let max = mask >> mask.trailing_zeros();
+                        ((pixel & mask) >> mask.trailing_zeros()) * 255 / max.max(1)
+                    })
+                    .map(|channel| channel as u8),
+            )
+        }
+    }
+
+    pub fn pick(stop: &AtomicBool, send: &dyn Fn(PickEvent)) -> Result<(), String> {
+        let (conn, screen_number) = x11rb::connect(None).map_err(failed)?;
+        let screen = &conn.setup().roots[screen_number];
+        let root = screen.root;
+        let format = PixelFormat::of(conn.setup(), screen)
+            .ok_or("Screen not readable: unsupported pixel format")?;
+        let pixel = |x: i16, y: i16| -> Result<[u8; 3], String> {
+            let image = conn
+                .get_image(ImageFormat::Z_PIXMAP, root, x, y, 1, 1, !0)
+                .map_err(failed)?
+                .reply()
+                .map_err(failed)?;
+            format
+                .rgb(&image.data)
+                .ok_or_else(|| failed("no pixel data"))
+        };
+
+        // A crosshair over every window, and every click comes here
+        let font = conn.generate_id().map_err(failed)?;
+        conn.open_font(font, b"cursor").map_err(failed)?;
+        let cursor = conn.generate_id().map_err(failed)?;
+        conn.create_glyph_cursor(
+            cursor,
+            font,
+            font,
+            XC_CROSSHAIR,
+            XC_CROSSHAIR + 1,
+            0,
+            0,
+            0,
+            0xffff,
+            0xffff,
+            0xffff,
+        )
+        .map_err(failed)?;
+        let grab = conn
+            .grab_pointer(
+                false,
+                root,
+                EventMask::BUTTON_PRESS | EventMask::POINTER_MOTION,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                NONE,
+                cursor,
+                CURRENT_TIME,
+            )
+            .map_err(failed)?
+            .reply()
+            .map_err(failed)?;
+        let result = if grab.status == GrabStatus::SUCCESS {
+            follow_pointer(&conn, root, stop, send, &pixel)
+        } else {
+            Err("Another program is holding the pointer".to_string())
+        };
+        let _ = conn.ungrab_pointer(CURRENT_TIME);
+        let _ = conn.free_cursor(cursor);
+        let _ = conn.close_font(font);
+        let _ = conn.flush();
+        result
+    }
+
+    fn follow_pointer(
+        conn: &RustConnection,
+        root: u32,
+        stop: &AtomicBool,
+        send: &dyn Fn(PickEvent),
+        pixel: &dyn Fn(i16, i16) -> Result<[u8; 3], String>,
+    ) -> Result<(), String> {
+        let start = conn
+            .query_pointer(root)
+            .map_err(failed)?
+            .reply()
+            .map_err(failed)?;
+        send(PickEvent::Hover(pixel(start.root_x, start.root_y)?));
+        loop {
+            if stop.load(Ordering::Relaxed) {
+                send(PickEvent::Cancelled);
+                return Ok(());
+            }
+            let mut moved_to = None;
+            while let Some(event) = conn.poll_for_event().map_err(failed)? {
+                match event {
+                    Event::ButtonPress(press) if press.detail == 1 => {
+                        send(PickEvent::Picked(pixel(press.root_x, press.root_y)?));
+                        return Ok(());
+                    }
+                    Event::ButtonPress(_) => {
+                        send(PickEvent::Cancelled);
+                        return Ok(());
+                    }
+                    Event::MotionNotify(motion) => moved_to = Some((motion.root_x, motion.root_y)),
+                    _ => {}
+                }
+            }
+            if let Some((x, y)) = moved_to {
+                send(PickEvent::Hover(pixel(x, y)?));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{PickEvent, POLL_INTERVAL};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::Graphics::Gdi::{GetDC, GetPixel, ReleaseDC, CLR_INVALID};
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, VK_ESCAPE, VK_LBUTTON, VK_RBUTTON,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+    fn pressed(key: u16) -> bool {
+        // The high bit is set while the key is down
+        unsafe { GetAsyncKeyState(key as i32) < 0 }
+    }
+
+    fn pixel_under_pointer() -> Result<[u8; 3], String> {
+        let mut point = POINT { x: 0, y: 0 };
+        // SAFETY: the screen's device context is released before returning
+        let color = unsafe {
+            if GetCursorPos(&mut point) == 0 {
+                return Err("Pointer position not available".to_string());
+            }
+            let screen = GetDC(std::ptr::null_mut());
+            let color = GetPixel(screen, point.x, point.y);
+            ReleaseDC(std::ptr::null_mut(), screen);
+            color
+        };
+        if color == CLR_INVALID {
+            return Err("Screen not readable".to_string());
+        }
+        // COLORREF is 0x00BBGGRR
+        Ok([color as u8, (color >> 8) as u8, (color >> 16) as u8])
+    }
+
+    pub fn pick(stop: &AtomicBool, send: &dyn Fn(PickEvent)) -> Result<(), String> {
+        // The click that started picking may still be held
+        while pressed(VK_LBUTTON) {
+            thread::sleep(POLL_INTERVAL);
+        }
+        let mut last = None;
+        loop {
+            if stop.load(Ordering::Relaxed) || pressed(VK_RBUTTON) || pressed(VK_ESCAPE) {
+                send(PickEvent::Cancelled);
+                return Ok(());
+            }
+            let rgb = pixel_under_pointer()?;
+            if pressed(VK_LBUTTON) {
+                send(PickEvent::Picked(rgb));
+                return Ok(());
+            }
+            if last != Some(rgb) {
+                send(PickEvent::Hover(rgb));
+                last = Some(rgb);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+mod platform {
+    use super::PickEvent;
+    use std::sync::atomic::AtomicBool;
+
+    pub fn pick(_stop: &AtomicBool, _send: &dyn Fn(PickEvent)) -> Result<(), String> {
+        Err("Picking from the screen is not supported on this system".to_string())
+    }
+}
@@ -0,0 +1,594 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod eyedropper;
+mod notation;
+mod palettes;
+
+use calc_core::color::{
+    format_hex_color, hsl_to_rgb, hsv_to_rgb, parse_hex_color, rgb_to_hsl, rgb_to_hsv,
+};
+use eframe::egui;
+use egui::{Color32, Sense, Vec2};
+use eyedropper::{Eyedropper, PickEvent};
+use gui_common::dialog::FileType;
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{palette, style, Labeled};
+use notation::{parse_color, Notation};
+use palettes::Palette;
+use serde::{Deserialize, Serialize};
+
+const APP_ID: &str = "Color Picker";
+// Recently picked or pasted colors kept
+const MAX_RECENT: usize = 16;
+
+fn main() -> eframe::Result<()> {
+    // Without a log the app still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<ColorSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
+        "Color Picker",
+        [820.0, 620.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
+    eframe::run_native(
+        APP_ID,
+        options,
+        Box::new(|cc| Ok(Box::new(ColorApp::new(cc, saved)))),
+    )
+}
+
+// User preferences persisted between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ColorSettings {
+    color: [u8; 3],         // The color last shown
+    recent: Vec<[u8; 3]>,   // Picked from the screen or pasted, newest first
+    palette: usize,         // Palette shown in the side panel
+    common: CommonSettings, // Theme and window, as in the other apps
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        let [r, g, b, _] = palette::ACCENT.to_array();
+        Self {
+            color: [r, g, b],
+            recent: Vec::new(),
+            palette: 0,
+            common: CommonSettings::default(),
+        }
+    }
+}
+
+struct ColorApp {
+    settings: ColorSettings,
+    rgb: [u8; 3],
+    // Kept apart from `rgb` so dragging through gray or black does not lose
+    // the hue and saturation
+    hsl: [f64; 3],
+    hsv: [f64; 3],
+    hex_text: String,   // As typed; applied once it parses
+    paste_text: String, // A color in any notation
+    palettes: Vec<Palette>,
+    palettes_changed: bool, // Not yet written to palettes.json
+    eyedropper: Option<Eyedropper>,
+    hover: Option<[u8; 3]>, // Under the pointer while picking
+    status: String,
+    error_message: Option<String>,
+    show_settings: bool,
+    show_log: bool, // Recent log lines, for problem reports
+}
+
+impl ColorApp {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<ColorSettings>) -> Self {
+        let mut settings = saved.unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let (palettes, error_message) = match palettes::load() {
+            Ok(palettes) => (palettes, None),
+            Err(e) => {
+                tracing::error!(error = %e, "Palettes not loaded");
+                (vec![Palette::new("My Colors")], Some(e))
+            }
+        };
+        settings.palette = settings.palette.min(palettes.len().saturating_sub(1));
+        let rgb = settings.color;
+        Self {
+            settings,
+            rgb,
+            hsl: rgb_to_hsl(rgb),
+            hsv: rgb_to_hsv(rgb),
+            hex_text: format_hex_color(rgb),
+            paste_text: String::new(),
+            palettes,
+            palettes_changed: false,
+            eyedropper: None,
+            hover: None,
+            status: String::new(),
+            error_message,
+            show_settings: false,
+            show_log: false,
+        }
+    }
+
+    // Show `rgb`, updating every notation but the one it was typed in
+    fn set_color(&mut self, rgb: [u8; 3], from: Option<Notation>) {
+        self.rgb = rgb;
+        if from != Some(Notation::Hex) {
+            self.hex_text = format_hex_color(rgb);
+        }
+        if from != Some(Notation::Hsl) {
+            self.hsl = rgb_to_hsl(rgb);
+        }
+        if from != Some(Notation::Hsv) {
+            self.hsv = rgb_to_hsv(rgb);
+        }
+    }
+
+    fn remember(&mut self, rgb: [u8; 3]) {
+        let recent = &mut self.settings.recent;
+        recent.retain(|&other| other != rgb);
+        recent.insert(0, rgb);
+        recent.truncate(MAX_RECENT);
+    }
+
+    fn save_palettes(&mut self) {
+        self.palettes_changed = false;
+        match palettes::save(&self.palettes) {
+            Ok(()) => self.error_message = None,
+            Err(e) => {
+                tracing::error!(error = %e, "Palettes not saved");
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    fn copy(&mut self, ctx: &egui::Context, text: String) {
+        self.status = format!("Copied {}", text.lines().next().unwrap_or_default());
+        ctx.copy_text(text);
+    }
+
+    fn handle_pick_events(&mut self) {
+        let events = self
+            .eyedropper
+            .as_ref()
+            .map(Eyedropper::events)
+            .unwrap_or_default();
+        for event in events {
+            match event {
+                PickEvent::Hover(rgb) => self.hover = Some(rgb),
+                PickEvent::Picked(rgb) => {
+                    self.set_color(rgb, None);
+                    self.remember(rgb);
+                    self.status = format!("Picked {}", format_hex_color(rgb));
+                    self.eyedropper = None;
+                }
+                PickEvent::Cancelled => self.eyedropper = None,
+                PickEvent::Failed(e) => {
+                    tracing::error!(error = %e, "Eyedropper failed");
+                    self.error_message = Some(e);
+                    self.eyedropper = None;
+                }
+            }
+        }
+        if self.eyedropper.is_none() {
+            self.hover = None;
+        }
+    }
+
+    fn show_color(&mut self, ui: &mut egui::Ui) {
+        // While picking, the color under the pointer
+        let shown = self.hover.unwrap_or(self.rgb);
+        let fill = Color32::from_rgb(shown[0], shown[1], shown[2]);
+        let (rect, _) =
+            ui.allocate_exact_size(Vec2::new(ui.available_width(), 110.0), Sense::hover());
+        ui.painter().rect_filled(rect, 8.0, fill);
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            format_hex_color(shown),
+            egui::FontId::proportional(28.0),
+            style::text_on(fill),
+        );
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            if self.eyedropper.is_some() {
+                ui.label("Click anywhere on the screen to pick; right-click or Esc cancels");
+                if ui.button("Cancel").clicked() {
+                    self.eyedropper = None;
+                    self.hover = None;
+                }
+            } else if ui
+                .button("💧 Pick from Screen")
+                .on_hover_text("Ctrl+P")
+                .clicked()
+            {
+                self.eyedropper = Some(Eyedropper::start(ui.ctx()));
+            }
+        });
+    }
+
+    fn show_conversions(&mut self, ui: &mut egui::Ui) {
+        let mut changed = None;
+        let mut copy = None;
+        egui::Grid::new("conversions")
+            .num_columns(3)
+            .spacing([12.0, 8.0])
+            .show(ui, |ui| {
+                for notation in Notation::ALL {
+                    ui.strong(notation.label());
+                    ui.horizontal(|ui| match notation {
+                        Notation::Hex => {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.hex_text)
+                                    .desired_width(100.0)
+                                    .font(egui::TextStyle::Monospace),
+                            );
+                            let parsed = parse_hex_color(&self.hex_text);
+                            if response.changed() {
+                                if let Some(rgb) = parsed {
+                                    changed = Some((rgb, notation));
+                                }
+                            }
+                            if parsed.is_none() {
+                                ui.colored_label(palette::ERROR, "Not a hex color");
+                            }
+                        }
+                        Notation::Rgb => {
+                            let mut rgb = self.rgb;
+                            let mut edited = false;
+                            for (channel, name) in rgb.iter_mut().zip(["R ", "G ", "B "]) {
+                                edited |= ui
+                                    .add(egui::DragValue::new(channel).range(0..=255).prefix(name))
+                                    .changed();
+                            }
+                            if edited {
+                                changed = Some((rgb, notation));
+                            }
+                        }
+                        Notation::Hsl | Notation::Hsv => {
+                            let hsl = notation == Notation::Hsl;
+                            let values = if hsl { &mut self.hsl } else { &mut self.hsv };
+                            let convert: fn([f64; 3]) -> [u8; 3] =
+                                if hsl { hsl_to_rgb } else { hsv_to_rgb };
+                            let third = if notation == Notation::Hsl {
+                                "L "
+                            } else {
+                                "V "
+                            };
+                            let mut edited = ui
+                                .add(
+                                    egui::DragValue::new(&mut values[0])
+                                        .range(0.0..=360.0)
+                                        .max_decimals(0)
+                                        .prefix("H ")
+                                        .suffix("°"),
+                                )
+                                .changed();
+                            for (value, name) in values[1..].iter_mut().zip(["S ", third]) {
+                                edited |= ui
+                                    .add(
+                                        egui::DragValue::new(value)
+                                            .range(0.0..=100.0)
+                                            .max_decimals(0)
+                                            .prefix(name)
+                                            .suffix("%"),
+                                    )
+                                    .changed();
+                            }
+                            if edited {
+                                changed = Some((convert(*values), notation));
+                            }
+                        }
+                    });
+                    if ui
+                        .small_button("📋")
+                        .labeled(&format!("Copy as {}", notation.label()))
+                        .clicked()
+                    {
+                        copy = Some(notation.format(self.rgb));
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some((rgb, notation)) = changed {
+            self.set_color(rgb, Some(notation));
+        }
+        if let Some(text) = copy {
+            self.copy(ui.ctx(), text);
+        }
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.paste_text)
+                    .hint_text("Paste a color: #1E6FD9, rgb(30, 111, 217), hsl(214, 76%, 48%)")
+                    .desired_width(ui.available_width() - 60.0),
+            );
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Use").clicked() || entered) && !self.paste_text.trim().is_empty() {
+                match parse_color(&self.paste_text) {
+                    Some(rgb) => {
+                        self.set_color(rgb, None);
+                        self.remember(rgb);
+                        self.paste_text.clear();
+                    }
+                    None => self.status = format!("Not a color: {}", self.paste_text.trim()),
+                }
+            }
+        });
+    }
+
+    // The picker, shades of the color and the recent colors
+    fn show_variations(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        let mut color = Color32::from_rgb(self.rgb[0], self.rgb[1], self.rgb[2]);
+        if egui::color_picker::color_picker_color32(
+            ui,
+            &mut color,
+            egui::color_picker::Alpha::Opaque,
+        ) {
+            chosen = Some([color.r(), color.g(), color.b()]);
+        }
+        ui.add_space(6.0);
+        ui.label("Shades:");
+        ui.horizontal(|ui| {
+            let [hue, saturation, _] = self.hsl;
+            for lightness in (1..10).map(|step| step as f64 * 10.0) {
+                let rgb = hsl_to_rgb([hue, saturation, lightness]);
+                if swatch(ui, rgb, 28.0, rgb == self.rgb).clicked() {
+                    chosen = Some(rgb);
+                }
+            }
+        });
+        if !self.settings.recent.is_empty() {
+            ui.label("Recent:");
+            ui.horizontal_wrapped(|ui| {
+                for &rgb in &self.settings.recent {
+                    if swatch(ui, rgb, 28.0, rgb == self.rgb).clicked() {
+                        chosen = Some(rgb);
+                    }
+                }
+            });
+        }
+        if let Some(rgb) = chosen {
+            self.set_color(rgb, None);
+        }
+    }
+
+    fn show_palettes(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Palettes");
+        let index = self.settings.palette;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("palette")
+                .selected_text(&self.palettes[index].name)
+                .width(140.0)
+                .show_ui(ui, |ui| {
+                    for (i, palette) in self.palettes.iter().enumerate() {
+                        ui.selectable_value(&mut self.settings.palette, i, &palette.name);
+                    }
+                });
+            if ui.button("➕").labeled("New Palette").clicked() {
+                let name = format!("Palette {}", self.palettes.len() + 1);
+                self.palettes.push(Palette::new(&name));
+                self.settings.palette = self.palettes.len() - 1;
+                self.palettes_changed = true;
+            }
+            if ui
+                .add_enabled(self.palettes.len() > 1, egui::Button::new("🗑"))
+                .labeled("Delete Palette")
+                .clicked()
+            {
+                self.palettes.remove(index);
+                self.settings.palette = index.min(self.palettes.len() - 1);
+                self.palettes_changed = true;
+            }
+        });
+        let index = self.settings.palette;
+        let palette = &mut self.palettes[index];
+        let name = ui.add(egui::TextEdit::singleline(&mut palette.name).hint_text("Name"));
+        // Saved once the name is finished rather than at every letter
+        if name.lost_focus() {
+            self.palettes_changed = true;
+        }
+        ui.add_space(4.0);
+        if ui.button("➕ Add Current Color").clicked() {
+            palette.add(self.rgb);
+            self.palettes_changed = true;
+        }
+        let mut chosen = None;
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, &rgb) in palette.colors.iter().enumerate() {
+                let response = swatch(ui, rgb, 32.0, rgb == self.rgb);
+                if response.clicked() {
+                    chosen = Some(rgb);
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+        if palette.colors.is_empty() {
+            ui.weak("No colors yet");
+        } else {
+            ui.weak("Click a color to show it, right-click to remove it");
+        }
+        if let Some(i) = remove {
+            palette.colors.remove(i);
+            self.palettes_changed = true;
+        }
+        if let Some(rgb) = chosen {
+            self.set_color(rgb, None);
+        }
+
+        ui.separator();
+        if ui.button("📋 Copy as CSS").clicked() {
+            let css = self.palettes[index].to_css();
+            self.copy(ui.ctx(), css);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Export .gpl...").clicked() {
+                self.export_palette(index);
+            }
+            if ui.button("Import .gpl...").clicked() {
+                self.import_palette();
+            }
+        });
+    }
+
+    fn export_palette(&mut self, index: usize) {
+        let palette = &self.palettes[index];
+        let file_name = format!("{}.gpl", palette.name);
+        let dialogs = &mut self.settings.common.dialogs;
+        let Some(path) = dialogs.save(&[FileType::GimpPalette], &file_name) else {
+            return;
+        };
+        match std::fs::write(&path, palette.to_gpl()) {
+            Ok(()) => {
+                tracing::info!(path = %path.display(), "Exported palette");
+                self.status = format!("Exported {}", path.display());
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Export failed");
+                self.error_message = Some(format!("Export failed: {}", e));
+            }
+        }
+    }
+
+    fn import_palette(&mut self) {
+        let Some(path) = self.settings.common.dialogs.open(&[FileType::GimpPalette]) else {
+            return;
+        };
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let result = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| palettes::parse_gpl(&text, &name));
+        match result {
+            Ok(palette) => {
+                self.status = format!("Imported {} colors", palette.colors.len());
+                self.palettes.push(palette);
+                self.settings.palette = self.palettes.len() - 1;
+                self.palettes_changed = true;
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Import failed");
+                self.error_message = Some(format!("Import failed: {}", e));
+            }
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            if let Some(path) = palettes::path() {
+                ui.label(format!("Palettes are kept in {}", path.display()));
+            }
+            if !self.settings.recent.is_empty() && ui.button("Clear Recent Colors").clicked() {
+                self.settings.recent.clear();
+            }
+        });
+        self.settings.common = common;
+        self.show_settings = open;
+    }
+}
+
+// A clickable square of color, outlined when `selected`
+fn swatch(ui: &mut egui::Ui, rgb: [u8; 3], size: f32, selected: bool) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(Vec2::splat(size), Sense::click());
+    ui.painter()
+        .rect_filled(rect, 3.0, Color32::from_rgb(rgb[0], rgb[1], rgb[2]));
+    let stroke = if selected {
+        ui.visuals().selection.stroke
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    };
+    ui.painter().rect_stroke(rect, 3.0, stroke);
+    response.labeled(&format_hex_color(rgb))
+}
+
+impl eframe::App for ColorApp {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.settings.color = self.rgb;
+        // Saved again on the next save or at exit
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+        }
+        if self.palettes_changed {
+            self.save_palettes();
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.settings.common.ui_scale = ctx.zoom_factor();
+        self.settings.common.remember_window(ctx);
+        self.handle_pick_events();
+        let (log, pick, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::L),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::P),
+                self.eyedropper.is_some()
+                    && i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if log {
+            self.show_log = !self.show_log;
+        }
+        if pick && self.eyedropper.is_none() {
+            self.eyedropper = Some(Eyedropper::start(ctx));
+        }
+        if escape {
+            self.eyedropper = None;
+            self.hover = None;
+        }
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(error) = &self.error_message {
+                    ui.colored_label(palette::ERROR, error);
+                } else {
+                    ui.label(&self.status);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("⚙").labeled("Settings").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                });
+            });
+        });
+        egui::SidePanel::right("palettes")
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.show_palettes(ui));
+            });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.show_color(ui);
+                ui.add_space(8.0);
+                ui.columns(2, |columns| {
+                    self.show_conversions(&mut columns[0]);
+                    self.show_variations(&mut columns[1]);
+                });
+            });
+        });
+
+        if self.palettes_changed {
+            self.save_palettes();
+        }
+    }
+}
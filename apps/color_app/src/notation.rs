@@ -0,0 +1,114 @@
+// The ways a color is written, for the conversion rows and for reading a
+// color pasted in any of them
+use calc_core::color::{
+    format_hex_color, hsl_to_rgb, hsv_to_rgb, parse_hex_color, rgb_to_hsl, rgb_to_hsv,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Notation {
+    Hex,
+    Rgb,
+    Hsl,
+    Hsv,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 4] = [Notation::Hex, Notation::Rgb, Notation::Hsl, Notation::Hsv];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Notation::Hex => "Hex",
+            Notation::Rgb => "RGB",
+            Notation::Hsl => "HSL",
+            Notation::Hsv => "HSV",
+        }
+    }
+
+    // As CSS writes it; HSV has no CSS form, so it borrows HSL's
+    pub fn format(self, rgb: [u8; 3]) -> String {
+        match self {
+            Notation::Hex => format_hex_color(rgb),
+            Notation::Rgb => format!("rgb({}, {}, {})", rgb[0], rgb[1], rgb[2]),
+            Notation::Hsl => {
+                let [h, s, l] = rgb_to_hsl(rgb);
+                format!("hsl({:.0}, {:.0}%, {:.0}%)", h, s, l)
+            }
+            Notation::Hsv => {
+                let [h, s, v] = rgb_to_hsv(rgb);
+                format!("hsv({:.0}, {:.0}%, {:.0}%)", h, s, v)
+            }
+        }
+    }
+}
+
+// "#1E6FD9", "1e6fd9", "rgb(30, 111, 217)", "30 111 217", "hsl(214, 76%, 48%)"
+// or "hsv(214 86 85)"
+pub fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let text = text.trim().to_lowercase();
+    if let Some(rgb) = parse_hex_color(&text) {
+        return Some(rgb);
+    }
+    let (notation, args) = if let Some(args) = function_args(&text, "hsl") {
+        (Notation::Hsl, args)
+    } else if let Some(args) = function_args(&text, "hsv") {
+        (Notation::Hsv, args)
+    } else {
+        (Notation::Rgb, function_args(&text, "rgb").unwrap_or(&text))
+    };
+    let values: Vec<f64> = args
+        .split([',', ' ', '/'])
+        .filter(|part| !part.is_empty())
+        .map(|part| part.trim_end_matches(['%', '°']).parse().ok())
+        .collect::<Option<_>>()?;
+    let values: [f64; 3] = values.try_into().ok()?;
+    match notation {
+        Notation::Hsl => Some(hsl_to_rgb(values)),
+        Notation::Hsv => Some(hsv_to_rgb(values)),
+        _ => values
+            .iter()
+            .all(|c| (0.0..=255.0).contains(c))
+            .then(|| values.map(|c| c.round() as u8)),
+    }
+}
+
+// "10, 20, 30" from "rgb(10, 20, 30)"; also takes "rgba(" and "hsla(" forms
+// without their alpha
+fn function_args<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(name)?;
+    let rest = rest.strip_prefix('a').unwrap_or(rest);
+    let args = rest.strip_prefix('(')?.strip_suffix(')')?;
+    // Drop an alpha value
+    Some(match args.matches(',').count() {
+        3 => &args[..args.rfind(',')?],
+        _ => args.split('/').next()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_notation_reads_back() {
+        let rgb = [30, 111, 217];
+        assert_eq!(Notation::Hex.format(rgb), "#1E6FD9");
+        assert_eq!(Notation::Rgb.format(rgb), "rgb(30, 111, 217)");
+        assert_eq!(Notation::Hsl.format(rgb), "hsl(214, 76%, 48%)");
+        for notation in [Notation::Hex, Notation::Rgb] {
+            assert_eq!(parse_color(&notation.format(rgb)), Some(rgb));
+        }
+        // HSL and HSV are rounded to whole numbers when written
+        for notation in [Notation::Hsl, Notation::Hsv] {
+            let back = parse_color(&notation.format(rgb)).unwrap();
+            assert!(back.iter().zip(rgb).all(|(a, b)| a.abs_diff(b) <= 2));
+        }
+
+        assert_eq!(parse_color(" 30 111 217 "), Some(rgb));
+        assert_eq!(parse_color("RGBA(30, 111, 217, 0.5)"), Some(rgb));
+        assert_eq!(parse_color("rgb(30 111 217 / 50%)"), Some(rgb));
+        assert_eq!(parse_color("hsv(120, 100%, 50%)"), Some([0, 128, 0]));
+        assert_eq!(parse_color("rgb(300, 0, 0)"), None);
+        assert_eq!(parse_color("10, 20"), None);
+        assert_eq!(parse_color("blue"), None);
+    }
+}
@@ -0,0 +1,154 @@
+// Named lists of colors, kept in palettes.json beside the settings, and the
+// GIMP palette (.gpl) and CSS forms they are exported as
+use calc_core::color::format_hex_color;
+use gui_common::settings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = "palettes.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            colors: Vec::new(),
+        }
+    }
+
+    // A color already in the palette is not added twice
+    pub fn add(&mut self, rgb: [u8; 3]) {
+        if !self.colors.contains(&rgb) {
+            self.colors.push(rgb);
+        }
+    }
+
+    // The palette file GIMP, Inkscape and Krita read
+    pub fn to_gpl(&self) -> String {
+        let mut text = format!("GIMP Palette\nName: {}\nColumns: 8\n#\n", self.name);
+        for rgb in &self.colors {
+            text.push_str(&format!(
+                "{:3} {:3} {:3}\t{}\n",
+                rgb[0],
+                rgb[1],
+                rgb[2],
+                format_hex_color(*rgb)
+            ));
+        }
+        text
+    }
+
+    // Custom properties named after the palette, "--my-colors-1: #1E6FD9;"
+    pub fn to_css(&self) -> String {
+        let slug: String = self
+            .name
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let slug = if slug.is_empty() { "color" } else { &slug };
+        let mut css = String::from(":root {\n");
+        for (n, rgb) in self.colors.iter().enumerate() {
+            css.push_str(&format!(
+                "  --{}-{}: {};\n",
+                slug,
+                n + 1,
+                format_hex_color(*rgb)
+            ));
+        }
+        css.push_str("}\n");
+        css
+    }
+}
+
+// A GIMP palette file; `fallback_name` names a palette without a "Name:" line
+pub fn parse_gpl(text: &str, fallback_name: &str) -> Result<Palette, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err("Not a GIMP palette".to_string());
+    }
+    let mut palette = Palette::new(fallback_name);
+    for (number, line) in lines.enumerate() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name:") {
+            palette.name = name.trim().to_string();
+        } else if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+            continue;
+        } else {
+            // "R G B" and an optional color name
+            let rgb: Option<Vec<u8>> = line
+                .split_whitespace()
+                .take(3)
+                .map(|value| value.parse().ok())
+                .collect();
+            let rgb: [u8; 3] = rgb
+                .and_then(|rgb| rgb.try_into().ok())
+                .ok_or_else(|| format!("Line {} is not a color: {}", number + 2, line))?;
+            palette.add(rgb);
+        }
+    }
+    Ok(palette)
+}
+
+pub fn path() -> Option<PathBuf> {
+    settings::dir(crate::APP_ID).map(|dir| dir.join(FILE_NAME))
+}
+
+// The saved palettes, or a single empty one before the first save. A file
+// that cannot be read is kept beside as "palettes.json.bad"
+pub fn load() -> Result<Vec<Palette>, String> {
+    let path = path().ok_or("No data folder for palettes")?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(vec![Palette::new("My Colors")])
+        }
+        Err(e) => return Err(format!("Error reading palettes: {}", e)),
+    };
+    serde_json::from_str(&text).map_err(|e| {
+        let _ = fs::copy(&path, path.with_extension("json.bad"));
+        format!("Palettes unreadable, kept as palettes.json.bad: {}", e)
+    })
+}
+
+pub fn save(palettes: &[Palette]) -> Result<(), String> {
+    let path = path().ok_or("No data folder for palettes")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating data folder: {}", e))?;
+    }
+    settings::write_json(&path, &palettes).map_err(|e| format!("Error saving palettes: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpl_round_trip() {
+        let mut palette = Palette::new("Brand Blues");
+        palette.add([30, 111, 217]);
+        palette.add([0, 0, 0]);
+        palette.add([30, 111, 217]);
+        assert_eq!(palette.colors.len(), 2);
+        assert_eq!(parse_gpl(&palette.to_gpl(), "x"), Ok(palette.clone()));
+        assert_eq!(
+            palette.to_css(),
+            ":root {\n  --brand-blues-1: #1E6FD9;\n  --brand-blues-2: #000000;\n}\n"
+        );
+
+        let gimp = "GIMP Palette\nColumns: 4\n# A comment\n255   0   0\tRed\n 0 255 0\n";
+        let parsed = parse_gpl(gimp, "Imported").unwrap();
+        assert_eq!(parsed.name, "Imported");
+        assert_eq!(parsed.colors, [[255, 0, 0], [0, 255, 0]]);
+        assert!(parse_gpl("Name: x\n1 2 3", "x").is_err());
+        assert!(parse_gpl("GIMP Palette\n1 2 300", "x").is_err());
+    }
+}
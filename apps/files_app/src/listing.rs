@@ -0,0 +1,226 @@
+// A folder's entries as a pane lists them: sorted with folders first, and
+// narrowed by the pane's filter
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64, // Zero for folders
+    pub modified: Option<SystemTime>,
+}
+
+impl Entry {
+    // Dot files on Unix; on Windows too, where they are rarer
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Extension,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 4] = [
+        SortKey::Name,
+        SortKey::Extension,
+        SortKey::Size,
+        SortKey::Modified,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Extension => "Type",
+            SortKey::Size => "Size",
+            SortKey::Modified => "Modified",
+        }
+    }
+}
+
+// The folder's entries, unsorted; ones that vanish while being read are left out
+pub fn read_dir(dir: &Path) -> Result<Vec<Entry>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Cannot open {}: {}", dir.display(), e))?;
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| {
+            // Follows links, so a link to a folder opens like one
+            let metadata = fs::metadata(entry.path())
+                .or_else(|_| entry.metadata())
+                .ok()?;
+            Some(Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: entry.path(),
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                modified: metadata.modified().ok(),
+            })
+        })
+        .collect())
+}
+
+fn extension(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// Folders before files whichever way; ties go by name, ignoring case
+pub fn sort(entries: &mut [Entry], key: SortKey, descending: bool) {
+    entries.sort_by(|a, b| {
+        let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        let order = match key {
+            SortKey::Name => by_name(),
+            SortKey::Extension => extension(&a.name)
+                .cmp(&extension(&b.name))
+                .then_with(by_name),
+            SortKey::Size => a.size.cmp(&b.size).then_with(by_name),
+            SortKey::Modified => a.modified.cmp(&b.modified).then_with(by_name),
+        };
+        let order = if descending { order.reverse() } else { order };
+        b.is_dir.cmp(&a.is_dir).then(order)
+    });
+}
+
+// Does the name match the filter: a part of the name ignoring case, or a
+// pattern with * and ? such as "*.txt"
+pub fn matches(name: &str, filter: &str) -> bool {
+    let filter = filter.trim().to_lowercase();
+    let name = name.to_lowercase();
+    if filter.contains(['*', '?']) {
+        let name: Vec<char> = name.chars().collect();
+        let pattern: Vec<char> = filter.chars().collect();
+        wildcard(&name, &pattern)
+    } else {
+        name.contains(&filter)
+    }
+}
+
+// Matched in one pass: on a mismatch only the last * seen takes one more
+// character, as earlier ones could not do better
+fn wildcard(name: &[char], pattern: &[char]) -> bool {
+    let (mut n, mut p) = (0, 0);
+    let mut star = None; // Pattern position after the last *, and where it matched up to
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                star = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                n += 1;
+                p += 1;
+            }
+            _ => match star {
+                Some((after_star, matched)) => {
+                    p = after_star;
+                    n = matched + 1;
+                    star = Some((after_star, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// "512 B", "1.5 KB", "20.0 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(name: &str, is_dir: bool, size: u64, age: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            is_dir,
+            size,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1000 - age)),
+        }
+    }
+
+    fn names(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn folders_come_first() {
+        let mut entries = vec![
+            entry("b.txt", false, 10, 1),
+            entry("Docs", true, 0, 5),
+            entry("a.rtxt", false, 300, 3),
+            entry("C.txt", false, 20, 2),
+            entry("apps", true, 0, 4),
+        ];
+        sort(&mut entries, SortKey::Name, false);
+        assert_eq!(
+            names(&entries),
+            ["apps", "Docs", "a.rtxt", "b.txt", "C.txt"]
+        );
+        sort(&mut entries, SortKey::Name, true);
+        assert_eq!(
+            names(&entries),
+            ["Docs", "apps", "C.txt", "b.txt", "a.rtxt"]
+        );
+        sort(&mut entries, SortKey::Size, true);
+        assert_eq!(
+            names(&entries),
+            ["Docs", "apps", "a.rtxt", "C.txt", "b.txt"]
+        );
+        sort(&mut entries, SortKey::Modified, false);
+        assert_eq!(
+            names(&entries),
+            ["Docs", "apps", "a.rtxt", "C.txt", "b.txt"]
+        );
+        sort(&mut entries, SortKey::Extension, false);
+        assert_eq!(
+            names(&entries),
+            ["apps", "Docs", "a.rtxt", "b.txt", "C.txt"]
+        );
+    }
+
+    #[test]
+    fn filters_and_sizes() {
+        assert!(matches("Report.TXT", "report"));
+        assert!(matches("Report.TXT", "*.txt"));
+        assert!(matches("notes-2026.rtxt", "notes-????.*"));
+        assert!(!matches("notes.rtxt.bak", "*.rtxt"));
+        assert!(matches("anything", ""));
+        assert!(matches("abc", "a*b*c*"));
+        assert!(!matches("abcd", "a*c"));
+        assert!(matches("mississippi", "*sip*"));
+        // Would take exponential time trying every split between the stars
+        let long = "a".repeat(200);
+        assert!(!matches(&long, "*a*a*a*a*a*a*a*a*b"));
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(20 * 1024 * 1024), "20.0 MB");
+    }
+}
@@ -0,0 +1,1015 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod listing;
+mod ops;
+
+use chrono::{DateTime, Local};
+use eframe::egui;
+use egui::{Align2, Color32, FontId, Key, Modifiers, Sense, Vec2};
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{launch, palette, Labeled};
+use listing::{Entry, SortKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+const APP_ID: &str = "File Manager";
+// Opened in the note app by a double-click; other files go to the system's
+// default program
+const NOTE_EXTENSIONS: [&str; 3] = ["rtxt", "txt", "md"];
+const ROW_HEIGHT: f32 = 22.0;
+
+fn main() -> eframe::Result<()> {
+    // Without a log the app still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<FilesSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
+        "File Manager",
+        [1100.0, 700.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
+    eframe::run_native(
+        APP_ID,
+        options,
+        Box::new(|cc| Ok(Box::new(FilesApp::new(cc, saved)))),
+    )
+}
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map_or_else(|| PathBuf::from("/"), PathBuf::from)
+}
+
+// A pane's folder and order, reopened at the next start
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct PaneSettings {
+    dir: PathBuf,
+    sort: SortKey,
+    descending: bool,
+}
+
+impl Default for PaneSettings {
+    fn default() -> Self {
+        Self {
+            dir: home_dir(),
+            sort: SortKey::Name,
+            descending: false,
+        }
+    }
+}
+
+// User preferences persisted between sessions
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FilesSettings {
+    panes: [PaneSettings; 2], // Left and right
+    show_hidden: bool,        // List dot files
+    common: CommonSettings,   // Theme and window, as in the other apps
+}
+
+struct Pane {
+    dir: PathBuf,
+    entries: Vec<Entry>, // Sorted, before filtering
+    selected: BTreeSet<PathBuf>,
+    anchor: Option<PathBuf>, // Last clicked, where Shift+click ranges start
+    filter: String,
+    path_text: String, // The path bar, as typed
+    sort: SortKey,
+    descending: bool,
+    error: Option<String>, // The folder could not be read
+    scroll_to_anchor: bool,
+}
+
+impl Pane {
+    fn new(saved: &PaneSettings) -> Self {
+        let mut pane = Self {
+            dir: saved.dir.clone(),
+            entries: Vec::new(),
+            selected: BTreeSet::new(),
+            anchor: None,
+            filter: String::new(),
+            path_text: String::new(),
+            sort: saved.sort,
+            descending: saved.descending,
+            error: None,
+            scroll_to_anchor: false,
+        };
+        // A folder removed since falls back to home
+        if !pane.dir.is_dir() {
+            pane.dir = home_dir();
+        }
+        pane.reload();
+        pane
+    }
+
+    fn settings(&self) -> PaneSettings {
+        PaneSettings {
+            dir: self.dir.clone(),
+            sort: self.sort,
+            descending: self.descending,
+        }
+    }
+
+    // Read the folder again, keeping the selection that still exists
+    fn reload(&mut self) {
+        self.path_text = self.dir.display().to_string();
+        match listing::read_dir(&self.dir) {
+            Ok(mut entries) => {
+                listing::sort(&mut entries, self.sort, self.descending);
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Folder not read");
+                self.entries.clear();
+                self.error = Some(e);
+            }
+        }
+        let entries = &self.entries;
+        self.selected
+            .retain(|path| entries.iter().any(|entry| &entry.path == path));
+    }
+
+    fn go_to(&mut self, dir: PathBuf) {
+        self.dir = dir;
+        self.selected.clear();
+        self.anchor = None;
+        self.filter.clear();
+        self.reload();
+    }
+
+    // To the parent folder, with the folder left selected
+    fn up(&mut self) {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let left = std::mem::replace(&mut self.dir, parent.clone());
+        self.go_to(parent);
+        self.select_only(left);
+    }
+
+    fn select_only(&mut self, path: PathBuf) {
+        self.selected = BTreeSet::from([path.clone()]);
+        self.anchor = Some(path);
+        self.scroll_to_anchor = true;
+    }
+
+    fn set_sort(&mut self, key: SortKey) {
+        if self.sort == key {
+            self.descending = !self.descending;
+        } else {
+            self.sort = key;
+            self.descending = false;
+        }
+        listing::sort(&mut self.entries, self.sort, self.descending);
+    }
+
+    // The entries listed, in order
+    fn shown(&self, show_hidden: bool) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| show_hidden || !entry.is_hidden())
+            .filter(|entry| listing::matches(&entry.name, &self.filter))
+            .collect()
+    }
+
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        self.selected.iter().cloned().collect()
+    }
+
+    // Move the selection up or down a row
+    fn step(&mut self, show_hidden: bool, down: bool) {
+        let shown: Vec<PathBuf> = self
+            .shown(show_hidden)
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        let current = self
+            .anchor
+            .as_ref()
+            .and_then(|anchor| shown.iter().position(|path| path == anchor));
+        let next = match current {
+            Some(i) if down => (i + 1).min(shown.len().saturating_sub(1)),
+            Some(i) => i.saturating_sub(1),
+            None => 0,
+        };
+        if let Some(path) = shown.get(next) {
+            self.select_only(path.clone());
+        }
+    }
+}
+
+// A question asked in a small window before acting
+enum Prompt {
+    Rename { path: PathBuf, name: String },
+    NewFolder { name: String },
+    Delete { paths: Vec<PathBuf> },
+}
+
+enum RowAction {
+    Open(PathBuf),
+    OpenInNotes(PathBuf),
+    OpenWithSystem(PathBuf),
+    Rename(PathBuf),
+    Copy,
+    Move,
+    Delete,
+}
+
+// What a transfer running on its own thread reports
+enum TransferUpdate {
+    Progress(usize),                     // Items done so far
+    Finished(Vec<PathBuf>, Vec<String>), // Where they went, and the errors
+}
+
+// A copy or move in progress
+struct Transfer {
+    updates: Receiver<TransferUpdate>,
+    moving: bool,
+    target: usize, // Pane showing the destination
+    dest_dir: PathBuf,
+    total: usize,
+    done: usize,
+}
+
+struct FilesApp {
+    settings: FilesSettings,
+    panes: [Pane; 2],
+    active: usize, // Pane the keys and toolbar act on; the other is the target
+    prompt: Option<Prompt>,
+    status: String,
+    error_message: Option<String>,
+    transfer: Option<Transfer>, // Copy or move running in the background
+    show_settings: bool,
+    show_log: bool, // Recent log lines, for problem reports
+}
+
+impl FilesApp {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<FilesSettings>) -> Self {
+        let settings = saved.unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let panes = [Pane::new(&settings.panes[0]), Pane::new(&settings.panes[1])];
+        Self {
+            settings,
+            panes,
+            active: 0,
+            prompt: None,
+            status: String::new(),
+            error_message: None,
+            transfer: None,
+            show_settings: false,
+            show_log: false,
+        }
+    }
+
+    fn reload(&mut self) {
+        for pane in &mut self.panes {
+            pane.reload();
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        tracing::error!(error = %error, "File operation failed");
+        self.error_message = Some(error);
+    }
+
+    fn open(&mut self, path: PathBuf) {
+        if path.is_dir() {
+            self.panes[self.active].go_to(path);
+            return;
+        }
+        let for_notes = path.extension().is_some_and(|ext| {
+            NOTE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+        });
+        if for_notes {
+            self.open_in_notes(&[path]);
+        } else {
+            self.open_with_system(&path);
+        }
+    }
+
+    fn open_in_notes(&mut self, paths: &[PathBuf]) {
+        match launch::start_app("note_app", paths) {
+            Ok(()) => self.status = format!("Opened {} file(s) in the note app", paths.len()),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn open_with_system(&mut self, path: &Path) {
+        if let Err(e) = launch::open_with_system(path) {
+            self.fail(e);
+        }
+    }
+
+    // Copy or move the active pane's selection into the other pane's folder,
+    // on a worker thread as folders may be large
+    fn transfer(&mut self, moving: bool) {
+        let sources = self.panes[self.active].selected_paths();
+        let target = 1 - self.active;
+        let dest_dir = self.panes[target].dir.clone();
+        if sources.is_empty() || self.transfer.is_some() {
+            return;
+        }
+        if moving && self.panes[self.active].dir == dest_dir {
+            self.fail("Both panes show the same folder".to_string());
+            return;
+        }
+        let (sender, updates) = mpsc::channel();
+        let total = sources.len();
+        let dir = dest_dir.clone();
+        thread::spawn(move || {
+            let (done, errors) = ops::transfer(&sources, &dir, moving, |done| {
+                let _ = sender.send(TransferUpdate::Progress(done));
+            });
+            let _ = sender.send(TransferUpdate::Finished(done, errors));
+        });
+        self.transfer = Some(Transfer {
+            updates,
+            moving,
+            target,
+            dest_dir,
+            total,
+            done: 0,
+        });
+        self.error_message = None;
+    }
+
+    fn poll_transfer(&mut self, ctx: &egui::Context) {
+        let Some(transfer) = &mut self.transfer else {
+            return;
+        };
+        let (done, errors) = loop {
+            match transfer.updates.try_recv() {
+                Ok(TransferUpdate::Progress(done)) => transfer.done = done,
+                Ok(TransferUpdate::Finished(done, errors)) => break (done, errors),
+                Err(TryRecvError::Empty) => {
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    return;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    break (
+                        Vec::new(),
+                        vec!["The transfer stopped unexpectedly".to_string()],
+                    );
+                }
+            }
+        };
+        let Some(transfer) = self.transfer.take() else {
+            return;
+        };
+        self.reload();
+        let verb = if transfer.moving { "Moved" } else { "Copied" };
+        self.status = format!(
+            "{} {} item(s) to {}",
+            verb,
+            done.len(),
+            transfer.dest_dir.display()
+        );
+        // The results stay selected in the other pane
+        self.panes[transfer.target].selected = done.into_iter().collect();
+        if let Some(first) = errors.first() {
+            self.fail(if errors.len() > 1 {
+                format!("{} (and {} more errors)", first, errors.len() - 1)
+            } else {
+                first.clone()
+            });
+        }
+    }
+
+    fn confirm_prompt(&mut self, prompt: Prompt) {
+        let pane = &mut self.panes[self.active];
+        let result = match prompt {
+            Prompt::Rename { path, name } => ops::rename(&path, &name).map(|new_path| {
+                tracing::info!(from = %path.display(), to = %new_path.display(), "Renamed");
+                pane.reload();
+                pane.select_only(new_path);
+                format!("Renamed to {}", name.trim())
+            }),
+            Prompt::NewFolder { name } => ops::new_folder(&pane.dir, &name).map(|path| {
+                tracing::info!(path = %path.display(), "Created folder");
+                pane.reload();
+                pane.select_only(path);
+                format!("Created {}", name.trim())
+            }),
+            Prompt::Delete { paths } => {
+                let mut deleted = 0;
+                let mut error = None;
+                for path in &paths {
+                    match ops::delete(path) {
+                        Ok(()) => {
+                            tracing::info!(path = %path.display(), "Deleted");
+                            deleted += 1;
+                        }
+                        Err(e) => error = error.or(Some(e)),
+                    }
+                }
+                self.reload();
+                match error {
+                    Some(e) => Err(e),
+                    None => Ok(format!("Deleted {} item(s)", deleted)),
+                }
+            }
+        };
+        match result {
+            Ok(status) => {
+                self.status = status;
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.reload();
+                self.fail(e);
+            }
+        }
+    }
+
+    fn start_rename(&mut self) {
+        let pane = &self.panes[self.active];
+        if let [path] = pane.selected_paths().as_slice() {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            self.prompt = Some(Prompt::Rename {
+                path: path.clone(),
+                name,
+            });
+        }
+    }
+
+    fn start_delete(&mut self) {
+        let paths = self.panes[self.active].selected_paths();
+        if !paths.is_empty() {
+            self.prompt = Some(Prompt::Delete { paths });
+        }
+    }
+
+    fn show_prompt(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        let (enter, escape) =
+            ctx.input(|i| (i.key_pressed(Key::Enter), i.key_pressed(Key::Escape)));
+        let mut confirmed = enter;
+        let mut cancelled = escape;
+        let title = match prompt {
+            Prompt::Rename { .. } => "Rename",
+            Prompt::NewFolder { .. } => "New Folder",
+            Prompt::Delete { .. } => "Delete",
+        };
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                match prompt {
+                    Prompt::Rename { name, .. } | Prompt::NewFolder { name } => {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(name)
+                                .hint_text("Name")
+                                .desired_width(280.0),
+                        );
+                        response.request_focus();
+                    }
+                    Prompt::Delete { paths } => {
+                        match paths.as_slice() {
+                            [path] => ui.label(format!("Delete {} for good?", path.display())),
+                            _ => ui.label(format!("Delete {} items for good?", paths.len())),
+                        };
+                        ui.colored_label(palette::ERROR, "There is no undo or trash");
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let label = if matches!(prompt, Prompt::Delete { .. }) {
+                        "🗑 Delete"
+                    } else {
+                        "OK"
+                    };
+                    confirmed |= ui.button(label).clicked();
+                    cancelled |= ui.button("Cancel").clicked();
+                });
+            });
+        if cancelled {
+            self.prompt = None;
+        } else if confirmed {
+            if let Some(prompt) = self.prompt.take() {
+                self.confirm_prompt(prompt);
+            }
+        }
+    }
+
+    fn show_toolbar(&mut self, ui: &mut egui::Ui) {
+        let count = self.panes[self.active].selected.len();
+        let idle = self.transfer.is_none();
+        let arrow = if self.active == 0 { "→" } else { "←" };
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    count > 0 && idle,
+                    egui::Button::new(format!("📋 Copy {}", arrow)),
+                )
+                .on_hover_text("Copy to the other pane's folder (F5)")
+                .clicked()
+            {
+                self.transfer(false);
+            }
+            if ui
+                .add_enabled(
+                    count > 0 && idle,
+                    egui::Button::new(format!("✂ Move {}", arrow)),
+                )
+                .on_hover_text("Move to the other pane's folder (F6)")
+                .clicked()
+            {
+                self.transfer(true);
+            }
+            if ui
+                .add_enabled(count == 1, egui::Button::new("✏ Rename"))
+                .on_hover_text("F2")
+                .clicked()
+            {
+                self.start_rename();
+            }
+            if ui.button("📁 New Folder").on_hover_text("F7").clicked() {
+                self.prompt = Some(Prompt::NewFolder {
+                    name: String::new(),
+                });
+            }
+            if ui
+                .add_enabled(count > 0, egui::Button::new("🗑 Delete"))
+                .on_hover_text("Delete or F8")
+                .clicked()
+            {
+                self.start_delete();
+            }
+            ui.separator();
+            let files: Vec<PathBuf> = self.panes[self.active]
+                .selected_paths()
+                .into_iter()
+                .filter(|path| path.is_file())
+                .collect();
+            if ui
+                .add_enabled(!files.is_empty(), egui::Button::new("📝 Open in Note App"))
+                .clicked()
+            {
+                self.open_in_notes(&files);
+            }
+            if ui
+                .add_enabled(count == 1, egui::Button::new("↗ Open with System"))
+                .clicked()
+            {
+                let path = self.panes[self.active].selected_paths().remove(0);
+                self.open_with_system(&path);
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("⚙").labeled("Settings").clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+                ui.checkbox(&mut self.settings.show_hidden, "Hidden files")
+                    .on_hover_text("Ctrl+H");
+            });
+        });
+    }
+
+    fn show_pane(&mut self, ui: &mut egui::Ui, index: usize) {
+        let active = index == self.active;
+        let show_hidden = self.settings.show_hidden;
+        let stroke = if active {
+            ui.visuals().selection.stroke
+        } else {
+            ui.visuals().widgets.noninteractive.bg_stroke
+        };
+        let mut action = None;
+        let frame = egui::Frame::group(ui.style())
+            .stroke(stroke)
+            .show(ui, |ui| {
+                let pane = &mut self.panes[index];
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(pane.dir.parent().is_some(), egui::Button::new("⬆"))
+                        .labeled("Up (Backspace)")
+                        .clicked()
+                    {
+                        pane.up();
+                    }
+                    if ui.button("🏠").labeled("Home").clicked() {
+                        pane.go_to(home_dir());
+                    }
+                    if ui.button("🔄").labeled("Refresh (Ctrl+R)").clicked() {
+                        pane.reload();
+                    }
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut pane.path_text)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                        let dir = PathBuf::from(pane.path_text.trim());
+                        if dir.is_dir() {
+                            pane.go_to(dir);
+                        } else {
+                            pane.error = Some(format!("No folder {}", dir.display()));
+                        }
+                    }
+                });
+                ui.add(
+                    egui::TextEdit::singleline(&mut pane.filter)
+                        .hint_text("🔍 Filter, e.g. report or *.txt")
+                        .desired_width(f32::INFINITY),
+                );
+                if let Some(error) = &pane.error {
+                    ui.colored_label(palette::ERROR, error);
+                }
+                let widths = column_widths(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for (key, width) in SortKey::ALL.into_iter().zip(widths) {
+                        let arrow = match (pane.sort == key, pane.descending) {
+                            (true, false) => " ⏶",
+                            (true, true) => " ⏷",
+                            (false, _) => "",
+                        };
+                        let text = format!("{}{}", key.label(), arrow);
+                        if ui
+                            .add_sized([width, ROW_HEIGHT], egui::Button::new(text).frame(false))
+                            .on_hover_text("Sort by this column; again to reverse")
+                            .clicked()
+                        {
+                            pane.set_sort(key);
+                        }
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_salt(("pane", index))
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        let shown: Vec<Entry> =
+                            pane.shown(show_hidden).into_iter().cloned().collect();
+                        for (row, entry) in shown.iter().enumerate() {
+                            let selected = pane.selected.contains(&entry.path);
+                            let response = entry_row(ui, entry, selected, row, &widths);
+                            if pane.scroll_to_anchor && pane.anchor.as_ref() == Some(&entry.path) {
+                                response.scroll_to_me(None);
+                                pane.scroll_to_anchor = false;
+                            }
+                            if response.clicked() {
+                                let modifiers = ui.input(|i| i.modifiers);
+                                click(pane, &shown, row, modifiers);
+                            }
+                            if response.secondary_clicked() && !selected {
+                                pane.select_only(entry.path.clone());
+                            }
+                            if response.double_clicked() {
+                                action = Some(RowAction::Open(entry.path.clone()));
+                            }
+                            response.context_menu(|ui| {
+                                let mut item =
+                                    |ui: &mut egui::Ui, label: &str, chosen: RowAction| {
+                                        if ui.button(label).clicked() {
+                                            action = Some(chosen);
+                                            ui.close_menu();
+                                        }
+                                    };
+                                item(ui, "Open", RowAction::Open(entry.path.clone()));
+                                if !entry.is_dir {
+                                    item(
+                                        ui,
+                                        "📝 Open in Note App",
+                                        RowAction::OpenInNotes(entry.path.clone()),
+                                    );
+                                }
+                                item(
+                                    ui,
+                                    "↗ Open with System",
+                                    RowAction::OpenWithSystem(entry.path.clone()),
+                                );
+                                ui.separator();
+                                item(ui, "📋 Copy to Other Pane", RowAction::Copy);
+                                item(ui, "✂ Move to Other Pane", RowAction::Move);
+                                item(ui, "✏ Rename", RowAction::Rename(entry.path.clone()));
+                                item(ui, "🗑 Delete", RowAction::Delete);
+                            });
+                        }
+                        if shown.is_empty() && pane.error.is_none() {
+                            ui.weak(if pane.entries.is_empty() {
+                                "Empty folder"
+                            } else {
+                                "Nothing matches the filter"
+                            });
+                        }
+                    });
+            });
+        // Any click in a pane makes it the active one
+        let rect = frame.response.rect;
+        let pressed_here = ui.input(|i| {
+            i.pointer.any_pressed()
+                && i.pointer
+                    .interact_pos()
+                    .is_some_and(|pos| rect.contains(pos))
+        });
+        if pressed_here || action.is_some() {
+            self.active = index;
+        }
+        match action {
+            Some(RowAction::Open(path)) => self.open(path),
+            Some(RowAction::OpenInNotes(path)) => self.open_in_notes(&[path]),
+            Some(RowAction::OpenWithSystem(path)) => self.open_with_system(&path),
+            Some(RowAction::Rename(path)) => {
+                self.panes[index].select_only(path);
+                self.start_rename();
+            }
+            Some(RowAction::Copy) => self.transfer(false),
+            Some(RowAction::Move) => self.transfer(true),
+            Some(RowAction::Delete) => self.start_delete(),
+            None => {}
+        }
+    }
+
+    fn show_status_bar(&self, ui: &mut egui::Ui) {
+        let pane = &self.panes[self.active];
+        ui.horizontal(|ui| {
+            let shown = pane.shown(self.settings.show_hidden).len();
+            let bytes: u64 = pane
+                .entries
+                .iter()
+                .filter(|entry| pane.selected.contains(&entry.path))
+                .map(|entry| entry.size)
+                .sum();
+            ui.label(format!(
+                "{} items, {} selected ({})",
+                shown,
+                pane.selected.len(),
+                listing::format_size(bytes)
+            ));
+            ui.separator();
+            if let Some(transfer) = &self.transfer {
+                ui.spinner();
+                let verb = if transfer.moving { "Moving" } else { "Copying" };
+                ui.label(format!(
+                    "{} {} of {}...",
+                    verb,
+                    (transfer.done + 1).min(transfer.total),
+                    transfer.total
+                ));
+                return;
+            }
+            match &self.error_message {
+                Some(error) => ui.colored_label(palette::ERROR, error),
+                None => ui.label(&self.status),
+            };
+        });
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        // Typing in the path bar, a filter or a prompt keeps its keys
+        if ctx.wants_keyboard_input() || self.prompt.is_some() {
+            return;
+        }
+        let show_hidden = self.settings.show_hidden;
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(Modifiers::NONE, key));
+        if pressed(Key::Tab) {
+            self.active = 1 - self.active;
+        }
+        if pressed(Key::ArrowDown) {
+            self.panes[self.active].step(show_hidden, true);
+        }
+        if pressed(Key::ArrowUp) {
+            self.panes[self.active].step(show_hidden, false);
+        }
+        if pressed(Key::Backspace) {
+            self.panes[self.active].up();
+        }
+        if pressed(Key::Enter) {
+            if let [path] = self.panes[self.active].selected_paths().as_slice() {
+                self.open(path.clone());
+            }
+        }
+        if pressed(Key::F2) {
+            self.start_rename();
+        }
+        if pressed(Key::F5) {
+            self.transfer(false);
+        }
+        if pressed(Key::F6) {
+            self.transfer(true);
+        }
+        if pressed(Key::F7) {
+            self.prompt = Some(Prompt::NewFolder {
+                name: String::new(),
+            });
+        }
+        if pressed(Key::Delete) || pressed(Key::F8) {
+            self.start_delete();
+        }
+        let command = |key| ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, key));
+        if command(Key::A) {
+            let pane = &mut self.panes[self.active];
+            pane.selected = pane
+                .shown(show_hidden)
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect();
+        }
+        if command(Key::R) {
+            self.reload();
+        }
+        if command(Key::H) {
+            self.settings.show_hidden = !self.settings.show_hidden;
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            ui.checkbox(&mut self.settings.show_hidden, "Show hidden files");
+            ui.label("Files opened in the note app by a double-click:");
+            ui.weak(NOTE_EXTENSIONS.map(|ext| format!(".{}", ext)).join(", "));
+        });
+        self.settings.common = common;
+        self.show_settings = open;
+    }
+}
+
+// Name, type, size and modified
+fn column_widths(total: f32) -> [f32; 4] {
+    let (kind, size, modified) = (70.0, 80.0, 130.0);
+    [
+        (total - kind - size - modified).max(120.0),
+        kind,
+        size,
+        modified,
+    ]
+}
+
+// One entry as a row of the table, selected or not
+fn entry_row(
+    ui: &mut egui::Ui,
+    entry: &Entry,
+    selected: bool,
+    row: usize,
+    widths: &[f32; 4],
+) -> egui::Response {
+    let width = widths.iter().sum();
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(width, ROW_HEIGHT), Sense::click());
+    response.widget_info(|| {
+        egui::WidgetInfo::selected(
+            egui::WidgetType::SelectableLabel,
+            true,
+            selected,
+            &entry.name,
+        )
+    });
+    let visuals = ui.visuals();
+    let fill = if selected {
+        visuals.selection.bg_fill
+    } else if response.hovered() {
+        visuals.widgets.hovered.weak_bg_fill
+    } else if row % 2 == 1 {
+        visuals.faint_bg_color
+    } else {
+        Color32::TRANSPARENT
+    };
+    let text_color = if selected {
+        visuals.selection.stroke.color
+    } else {
+        visuals.text_color()
+    };
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, fill);
+    let font = FontId::proportional(14.0);
+    let icon = if entry.is_dir { "📁" } else { "📄" };
+    let kind = if entry.is_dir {
+        "Folder".to_string()
+    } else {
+        Path::new(&entry.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_uppercase())
+            .unwrap_or_default()
+    };
+    let size = if entry.is_dir {
+        String::new()
+    } else {
+        listing::format_size(entry.size)
+    };
+    let modified = entry
+        .modified
+        .map(|time| {
+            DateTime::<Local>::from(time)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+    let mut x = rect.left() + 4.0;
+    let y = rect.center().y;
+    painter.text(
+        egui::pos2(x, y),
+        Align2::LEFT_CENTER,
+        format!("{} {}", icon, entry.name),
+        font.clone(),
+        text_color,
+    );
+    x = rect.left() + widths[0];
+    painter.text(
+        egui::pos2(x, y),
+        Align2::LEFT_CENTER,
+        kind,
+        font.clone(),
+        text_color,
+    );
+    x += widths[1] + widths[2] - 8.0;
+    painter.text(
+        egui::pos2(x, y),
+        Align2::RIGHT_CENTER,
+        size,
+        font.clone(),
+        text_color,
+    );
+    x += 16.0;
+    painter.text(
+        egui::pos2(x, y),
+        Align2::LEFT_CENTER,
+        modified,
+        font,
+        text_color,
+    );
+    response.on_hover_text(entry.path.display().to_string())
+}
+
+// Select as a click with these modifiers does: one entry, one more with
+// Ctrl, or a range from the last clicked with Shift
+fn click(pane: &mut Pane, shown: &[Entry], row: usize, modifiers: Modifiers) {
+    let path = shown[row].path.clone();
+    if modifiers.shift {
+        let anchor = pane
+            .anchor
+            .as_ref()
+            .and_then(|anchor| shown.iter().position(|entry| &entry.path == anchor))
+            .unwrap_or(row);
+        let range = anchor.min(row)..=anchor.max(row);
+        pane.selected = shown[range]
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+    } else if modifiers.command {
+        if !pane.selected.remove(&path) {
+            pane.selected.insert(path.clone());
+        }
+        pane.anchor = Some(path);
+    } else {
+        pane.selected = BTreeSet::from([path.clone()]);
+        pane.anchor = Some(path);
+    }
+}
+
+impl eframe::App for FilesApp {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.settings.panes = [self.panes[0].settings(), self.panes[1].settings()];
+        // Saved again on the next save or at exit
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.settings.common.ui_scale = ctx.zoom_factor();
+        self.settings.common.remember_window(ctx);
+        if ctx.input_mut(|i| i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::L)) {
+            self.show_log = !self.show_log;
+        }
+        self.handle_keys(ctx);
+        self.poll_transfer(ctx);
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+        self.show_prompt(ctx);
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.add_space(4.0);
+            self.show_toolbar(ui);
+            ui.add_space(2.0);
+        });
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            self.show_status_bar(ui);
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.columns(2, |columns| {
+                for (index, ui) in columns.iter_mut().enumerate() {
+                    self.show_pane(ui, index);
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,226 @@
+// Copying, moving, renaming and deleting. Nothing is overwritten: an entry
+// copied or moved onto a name that is taken gets a free one, "name (2).txt"
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// "name.txt" in `dir`, or "name (2).txt" and so on when that is taken
+pub fn free_path(dir: &Path, name: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let path = dir.join(name);
+    if !taken(&path) {
+        return path;
+    }
+    // A folder's dots are part of its name as much as a file's extension
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    (2..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !taken(path))
+        .unwrap_or(path)
+}
+
+// Would copying or moving `source` into `dest_dir` put a folder inside itself
+pub fn into_itself(source: &Path, dest_dir: &Path) -> bool {
+    dest_dir.starts_with(source)
+}
+
+fn file_name(path: &Path) -> io::Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))
+}
+
+// Links are copied as links, never followed, so a link back up to a folder
+// being copied cannot make the copy endless
+fn copy_recursive(source: &Path, dest: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(source)?;
+    if metadata.is_symlink() {
+        copy_link(source, dest)
+    } else if metadata.is_dir() {
+        fs::create_dir(dest)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(source, dest).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_link(source: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, dest)
+}
+
+#[cfg(windows)]
+fn copy_link(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    let target = fs::read_link(source)?;
+    // Windows links say whether they point to a folder
+    if fs::metadata(source).is_ok_and(|metadata| metadata.is_dir()) {
+        symlink_dir(target, dest)
+    } else {
+        symlink_file(target, dest)
+    }
+}
+
+fn check_target(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    if into_itself(source, dest_dir) {
+        return Err(format!("{} cannot go inside itself", source.display()));
+    }
+    let name = file_name(source).map_err(|e| e.to_string())?;
+    Ok(free_path(dest_dir, &name, |path| path.exists()))
+}
+
+// Copy a file or folder into `dest_dir`, returning the copy's path
+pub fn copy(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    let dest = check_target(source, dest_dir)?;
+    copy_recursive(source, &dest)
+        .map(|()| dest)
+        .map_err(|e| format!("Error copying {}: {}", source.display(), e))
+}
+
+// Move a file or folder into `dest_dir`; across drives, where a rename
+// cannot, by copying and then deleting the original
+pub fn move_to(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    if source.parent() == Some(dest_dir) {
+        return Ok(source.to_path_buf());
+    }
+    let dest = check_target(source, dest_dir)?;
+    if fs::rename(source, &dest).is_ok() {
+        return Ok(dest);
+    }
+    copy_recursive(source, &dest)
+        .and_then(|()| remove(source))
+        .map(|()| dest)
+        .map_err(|e| format!("Error moving {}: {}", source.display(), e))
+}
+
+// Copy or move each of `sources` into `dest_dir`, calling `progress` with
+// how many are done after each. Returns where they went and the errors
+pub fn transfer(
+    sources: &[PathBuf],
+    dest_dir: &Path,
+    moving: bool,
+    mut progress: impl FnMut(usize),
+) -> (Vec<PathBuf>, Vec<String>) {
+    let mut done = Vec::new();
+    let mut errors = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        let result = if moving {
+            move_to(source, dest_dir)
+        } else {
+            copy(source, dest_dir)
+        };
+        match result {
+            Ok(dest) => {
+                tracing::info!(from = %source.display(), to = %dest.display(), moving, "Transferred");
+                done.push(dest);
+            }
+            Err(e) => errors.push(e),
+        }
+        progress(index + 1);
+    }
+    (done, errors)
+}
+
+pub fn rename(path: &Path, new_name: &str) -> Result<PathBuf, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) || new_name == "." || new_name == ".."
+    {
+        return Err(format!("Not a valid name: {}", new_name));
+    }
+    let dest = path.with_file_name(new_name);
+    if dest != path && dest.exists() {
+        return Err(format!("{} already exists", new_name));
+    }
+    fs::rename(path, &dest)
+        .map(|()| dest)
+        .map_err(|e| format!("Error renaming {}: {}", path.display(), e))
+}
+
+pub fn new_folder(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!("Not a valid name: {}", name));
+    }
+    let path = dir.join(name);
+    fs::create_dir(&path)
+        .map(|()| path)
+        .map_err(|e| format!("Error creating {}: {}", name, e))
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    // A link to a folder is removed, not the folder it points to
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+// Delete for good; there is no trash
+pub fn delete(path: &Path) -> Result<(), String> {
+    remove(path).map_err(|e| format!("Error deleting {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taken_names_get_a_number() {
+        let taken = [
+            Path::new("d/report.pdf"),
+            Path::new("d/report (2).pdf"),
+            Path::new("d/src"),
+        ];
+        let taken = |path: &Path| taken.contains(&path);
+        let dir = Path::new("d");
+        assert_eq!(free_path(dir, "photo.jpg", taken), dir.join("photo.jpg"));
+        assert_eq!(
+            free_path(dir, "report.pdf", taken),
+            dir.join("report (3).pdf")
+        );
+        assert_eq!(free_path(dir, "src", taken), dir.join("src (2)"));
+        assert_eq!(
+            free_path(Path::new("."), ".bashrc", |_| false),
+            Path::new(".").join(".bashrc")
+        );
+
+        assert!(into_itself(Path::new("/a/b"), Path::new("/a/b/c")));
+        assert!(into_itself(Path::new("/a/b"), Path::new("/a/b")));
+        assert!(!into_itself(Path::new("/a/b"), Path::new("/a/bc")));
+        assert!(rename(Path::new("x"), "a/b").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_are_copied_not_followed() {
+        let dir = std::env::temp_dir().join(format!("files_app_links_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let source = dir.join("folder");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        // A link back up to the folder holding it
+        std::os::unix::fs::symlink(&source, source.join("loop")).unwrap();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+
+        let mut steps = Vec::new();
+        let (done, errors) = transfer(std::slice::from_ref(&source), &dest_dir, false, |n| {
+            steps.push(n)
+        });
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(steps, [1]);
+        let copy = dest_dir.join("folder");
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0], copy);
+        assert_eq!(fs::read_to_string(copy.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_link(copy.join("loop")).unwrap(), source);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
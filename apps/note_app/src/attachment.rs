@@ -0,0 +1,137 @@
+// Files attached to a note: copies kept in an "attachments" folder beside the
+// note, listed by their path relative to the note's folder in its metadata
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Hidden from the notes tree
+pub const FOLDER: &str = "attachments";
+
+// Names come from the note's file, which may have been written elsewhere, so
+// only "attachments/<file name>" is accepted: nothing that could reach
+// outside the folder, such as "..", a drive letter or an absolute path
+pub fn is_valid(attachment: &str) -> bool {
+    let Some(name) = attachment
+        .strip_prefix(FOLDER)
+        .and_then(|rest| rest.strip_prefix('/'))
+    else {
+        return false;
+    };
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':'])
+        && !name.chars().any(char::is_control)
+}
+
+// The note's attachments folder
+fn folder(note: &Path) -> PathBuf {
+    note.parent().unwrap_or(Path::new("")).join(FOLDER)
+}
+
+// Where an attachment of the note is stored, unless its name is not one
+// `attach` would give
+pub fn path(note: &Path, attachment: &str) -> Option<PathBuf> {
+    let name = attachment.rsplit('/').next()?;
+    is_valid(attachment).then(|| folder(note).join(name))
+}
+
+fn invalid(attachment: &str) -> String {
+    format!("Not an attachment name: {}", attachment)
+}
+
+// "name.pdf", or "name (2).pdf" and so on when that is taken
+fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (2..)
+        .map(|n| format!("{} ({}){}", stem, n, extension))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_default()
+}
+
+// Copy a file into the note's attachments folder; returns the name to list
+// in the note's metadata
+pub fn attach(note: &Path, source: &Path) -> Result<String, String> {
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or("Not a file")?;
+    let dir = folder(note);
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating attachments folder: {}", e))?;
+    let name = unique_name(&name, |name| dir.join(name).exists());
+    fs::copy(source, dir.join(&name)).map_err(|e| format!("Error attaching {}: {}", name, e))?;
+    Ok(format!("{}/{}", FOLDER, name))
+}
+
+// Delete the stored copy; one that is already gone is fine
+pub fn remove(note: &Path, attachment: &str) -> Result<(), String> {
+    let file = path(note, attachment).ok_or_else(|| invalid(attachment))?;
+    match fs::remove_file(file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Error removing {}: {}", attachment, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Copy a note's attachments beside a copy of the note saved elsewhere
+pub fn copy_all(note: &Path, copy: &Path, attachments: &[String]) -> Result<(), String> {
+    if note.parent() == copy.parent() {
+        return Ok(());
+    }
+    for attachment in attachments {
+        let (Some(source), Some(target)) = (path(note, attachment), path(copy, attachment)) else {
+            return Err(invalid(attachment));
+        };
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Error creating attachments folder: {}", e))?;
+        }
+        fs::copy(source, &target).map_err(|e| format!("Error copying {}: {}", attachment, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attachments_get_free_names_beside_the_note() {
+        let taken = ["report.pdf", "report (2).pdf", "README"];
+        let taken = |name: &str| taken.contains(&name);
+        assert_eq!(unique_name("photo.jpg", taken), "photo.jpg");
+        assert_eq!(unique_name("report.pdf", taken), "report (3).pdf");
+        assert_eq!(unique_name("README", taken), "README (2)");
+        assert_eq!(
+            path(&Path::new("notes").join("plan.rtxt"), "attachments/a.pdf"),
+            Some(Path::new("notes").join("attachments").join("a.pdf"))
+        );
+    }
+
+    #[test]
+    fn names_cannot_leave_the_attachments_folder() {
+        assert!(is_valid("attachments/plan (2).pdf"));
+        for name in [
+            "../../.bashrc",
+            "attachments/../../.bashrc",
+            "attachments/..",
+            "attachments/",
+            "attachments/a/b.pdf",
+            "attachments/..\\evil.exe",
+            "attachments/C:evil.exe",
+            "/etc/passwd",
+            "other/a.pdf",
+            "attachments",
+        ] {
+            assert!(!is_valid(name), "{}", name);
+            assert_eq!(path(Path::new("plan.rtxt"), name), None);
+        }
+        assert!(remove(Path::new("plan.rtxt"), "attachments/../plan.rtxt").is_err());
+    }
+}
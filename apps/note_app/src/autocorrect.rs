@@ -0,0 +1,173 @@
+// As-you-type substitutions: typographic quotes, dashes and ellipses, and a
+// table of words replaced when they are finished
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Autocorrect {
+    pub smart_quotes: bool, // " and ' become “ ” and ‘ ’
+    pub dashes: bool,       // "--" between words becomes "—"
+    pub ellipsis: bool,     // "..." becomes "…"
+    pub replace_words: bool,
+    pub table: Vec<(String, String)>, // Word typed and its replacement
+}
+
+impl Default for Autocorrect {
+    fn default() -> Self {
+        let table = [
+            ("teh", "the"),
+            ("adn", "and"),
+            ("recieve", "receive"),
+            ("seperate", "separate"),
+            ("definately", "definitely"),
+            ("(c)", "©"),
+            ("(tm)", "™"),
+            ("->", "→"),
+        ];
+        Self {
+            smart_quotes: false,
+            dashes: false,
+            ellipsis: false,
+            replace_words: false,
+            table: table
+                .iter()
+                .map(|(typed, replacement)| (typed.to_string(), replacement.to_string()))
+                .collect(),
+        }
+    }
+}
+
+// Replace the last `remove` characters before the cursor with `insert`,
+// which takes the place of the typed character
+#[derive(Debug, PartialEq)]
+pub struct Correction {
+    pub remove: usize,
+    pub insert: String,
+}
+
+impl Autocorrect {
+    pub fn any_enabled(&self) -> bool {
+        self.smart_quotes || self.dashes || self.ellipsis || self.replace_words
+    }
+
+    // What typing `typed` after `before`, the line up to the cursor, turns
+    // into; None to type it as it is
+    pub fn correct(&self, before: &str, typed: char) -> Option<Correction> {
+        let previous = before.chars().next_back();
+        if self.smart_quotes && (typed == '"' || typed == '\'') {
+            let opening = previous.is_none_or(|c| c.is_whitespace() || "([{“‘—–-/".contains(c));
+            let quote = match (typed, opening) {
+                ('"', true) => '“',
+                ('"', false) => '”',
+                (_, true) => '‘',
+                (_, false) => '’',
+            };
+            return Some(Correction {
+                remove: 0,
+                insert: quote.to_string(),
+            });
+        }
+        if self.ellipsis && typed == '.' && before.ends_with("..") && !before.ends_with("...") {
+            return Some(Correction {
+                remove: 2,
+                insert: "…".to_string(),
+            });
+        }
+        if typed.is_alphanumeric() {
+            return None;
+        }
+        // The rest apply when a word is finished
+        if self.dashes && typed != '-' {
+            if let Some(start) = before.strip_suffix("--") {
+                // Not a Markdown rule or table line, nor a longer run of dashes
+                let after_text = start
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| !matches!(c, '-' | '|' | ':'));
+                if after_text && !start.trim().is_empty() {
+                    return Some(Correction {
+                        remove: 2,
+                        insert: format!("—{}", typed),
+                    });
+                }
+            }
+        }
+        if self.replace_words {
+            let word_start = before.rfind(char::is_whitespace).map_or(0, |i| {
+                i + before[i..].chars().next().map_or(1, char::len_utf8)
+            });
+            let word = &before[word_start..];
+            let replacement = self.table.iter().find_map(|(from, to)| {
+                if from.is_empty() {
+                    None
+                } else if word == from {
+                    Some(to.clone())
+                } else if word == capitalize(from) {
+                    Some(capitalize(to))
+                } else {
+                    None
+                }
+            });
+            if let Some(replacement) = replacement {
+                return Some(Correction {
+                    remove: word.chars().count(),
+                    insert: format!("{}{}", replacement, typed),
+                });
+            }
+        }
+        None
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Autocorrect {
+        Autocorrect {
+            smart_quotes: true,
+            dashes: true,
+            ellipsis: true,
+            replace_words: true,
+            ..Default::default()
+        }
+    }
+
+    fn fix(remove: usize, insert: &str) -> Option<Correction> {
+        Some(Correction {
+            remove,
+            insert: insert.to_string(),
+        })
+    }
+
+    #[test]
+    fn typography() {
+        let autocorrect = all();
+        assert_eq!(autocorrect.correct("He said ", '"'), fix(0, "“"));
+        assert_eq!(autocorrect.correct("He said “hi", '"'), fix(0, "”"));
+        assert_eq!(autocorrect.correct("it", '\''), fix(0, "’"));
+        assert_eq!(autocorrect.correct("Wait..", '.'), fix(2, "…"));
+        assert_eq!(autocorrect.correct("one--", ' '), fix(2, "— "));
+        assert_eq!(autocorrect.correct("one--", '-'), None);
+        assert_eq!(autocorrect.correct("|--", '|'), None);
+        assert_eq!(autocorrect.correct("--", ' '), None);
+        assert_eq!(Autocorrect::default().correct("x ", '"'), None);
+    }
+
+    #[test]
+    fn words_from_the_table() {
+        let autocorrect = all();
+        assert_eq!(autocorrect.correct("I saw teh", ' '), fix(3, "the "));
+        assert_eq!(autocorrect.correct("Teh", ','), fix(3, "The,"));
+        assert_eq!(autocorrect.correct("café (c)", ' '), fix(3, "© "));
+        assert_eq!(autocorrect.correct("tehx", ' '), None);
+        assert_eq!(autocorrect.correct("teh", 'x'), None);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::document::Document;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Present while the app runs; left behind if it exits uncleanly
+const SESSION_LOCK: &str = "session.lock";
+// Maps each recovery file to the path of the document it came from
+const RECOVERY_INDEX: &str = "index.txt";
+// How often unsaved buffers are copied to the recovery folder, so a crash or
+// power loss costs at most this much work
+pub const RECOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+// A buffer written to the recovery directory by a previous session
+pub struct RecoveredBuffer {
+    pub recovery_file: PathBuf,
+    pub original_path: Option<PathBuf>,
+}
+
+pub fn recovery_dir() -> Option<PathBuf> {
+    eframe::storage_dir(crate::APP_ID).map(|dir| dir.join("recovery"))
+}
+
+// Mark the session as running and return the buffers left by a previous
+// session that did not exit cleanly
+pub fn start_session() -> Vec<RecoveredBuffer> {
+    let Some(dir) = recovery_dir() else {
+        return Vec::new();
+    };
+    let lock = dir.join(SESSION_LOCK);
+    let unclean = lock.exists();
+    let recovered = if unclean {
+        read_index(&dir)
+    } else {
+        Vec::new()
+    };
+    if fs::create_dir_all(&dir).is_ok() {
+        let _ = fs::write(lock, std::process::id().to_string());
+    }
+    recovered
+}
+
+// Remove the lock and any recovery files on a normal exit
+pub fn end_session() {
+    if let Some(dir) = recovery_dir() {
+        clear_recovery_files(&dir);
+        let _ = fs::remove_file(dir.join(SESSION_LOCK));
+    }
+}
+
+// Replace the recovery files with the current unsaved buffers
+fn write_recovery_files(documents: &[Document]) -> Result<(), String> {
+    let Some(dir) = recovery_dir() else {
+        return Err("No data directory for recovery files".to_string());
+    };
+    clear_recovery_files(&dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating recovery folder: {}", e))?;
+
+    let mut index = String::new();
+    for doc in documents.iter().filter(|doc| doc.dirty) {
+        let name = format!("buffer-{}.rtxt", doc.id);
+        doc.save_with_formatting(&dir.join(&name))?;
+        let original = doc
+            .file_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        index.push_str(&format!("{}\t{}\n", name, original));
+    }
+    fs::write(dir.join(RECOVERY_INDEX), index)
+        .map_err(|e| format!("Error writing recovery index: {}", e))
+}
+
+// Writes recovery files on a background thread, so typing never waits for
+// the disk
+pub struct RecoveryWriter {
+    sender: Option<Sender<Vec<Document>>>,
+    errors: Receiver<String>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RecoveryWriter {
+    pub fn start() -> Self {
+        let (sender, buffers) = mpsc::channel::<Vec<Document>>();
+        let (error_sender, errors) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("recovery".to_string())
+            .spawn(move || {
+                while let Ok(mut documents) = buffers.recv() {
+                    // Only the newest copies matter if the disk fell behind
+                    while let Ok(newer) = buffers.try_recv() {
+                        documents = newer;
+                    }
+                    if let Err(e) = write_recovery_files(&documents) {
+                        let _ = error_sender.send(e);
+                    }
+                }
+            })
+            .ok();
+        Self {
+            sender: thread.is_some().then_some(sender),
+            errors,
+            thread,
+        }
+    }
+
+    // Queue copies of the unsaved buffers to replace the recovery files
+    pub fn write(&self, documents: &[Document]) -> Result<(), String> {
+        let copies: Vec<Document> = documents
+            .iter()
+            .filter(|doc| doc.dirty)
+            .map(Document::content_copy)
+            .collect();
+        match &self.sender {
+            Some(sender) => sender
+                .send(copies)
+                .map_err(|_| "The recovery thread stopped".to_string()),
+            // Without a thread, write them here
+            None => write_recovery_files(&copies),
+        }
+    }
+
+    // The last error from the background thread, if any
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_iter().last()
+    }
+
+    // Wait for queued writes, before the recovery files are removed on exit
+    pub fn finish(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+pub fn discard(recovered: &[RecoveredBuffer]) {
+    for buffer in recovered {
+        let _ = fs::remove_file(&buffer.recovery_file);
+    }
+    if let Some(dir) = recovery_dir() {
+        let _ = fs::remove_file(dir.join(RECOVERY_INDEX));
+    }
+}
+
+fn read_index(dir: &std::path::Path) -> Vec<RecoveredBuffer> {
+    let Ok(index) = fs::read_to_string(dir.join(RECOVERY_INDEX)) else {
+        return Vec::new();
+    };
+    index
+        .lines()
+        .filter_map(|line| {
+            let (name, original) = line.split_once('\t')?;
+            let recovery_file = dir.join(name);
+            recovery_file.exists().then(|| RecoveredBuffer {
+                recovery_file,
+                original_path: (!original.is_empty()).then(|| PathBuf::from(original)),
+            })
+        })
+        .collect()
+}
+
+fn clear_recovery_files(dir: &std::path::Path) {
+    for buffer in read_index(dir) {
+        let _ = fs::remove_file(buffer.recovery_file);
+    }
+    let _ = fs::remove_file(dir.join(RECOVERY_INDEX));
+}
@@ -0,0 +1,74 @@
+// Copies of a file's previous contents, made before a save overwrites it:
+// "name.rtxt.bak" when one is kept, or "name.rtxt.<time>.bak" copies with the
+// oldest removed past the limit
+use crate::history;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The save time in a timestamped backup name of the file, if it is one
+fn stamp(file_name: &str, candidate: &str) -> Option<u64> {
+    candidate
+        .strip_prefix(file_name)?
+        .strip_prefix('.')?
+        .strip_suffix(".bak")?
+        .parse()
+        .ok()
+}
+
+// Timestamped backups of the file, newest first
+pub fn list(path: &Path) -> Vec<(u64, PathBuf)> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let file_name = file_name.to_string_lossy();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let time = stamp(&file_name, &entry.file_name().to_string_lossy())?;
+            Some((time, entry.path()))
+        })
+        .collect();
+    backups.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    backups
+}
+
+// Copy the file as it is on disk before it is overwritten, keeping `keep`
+// backups; nothing to do for a file not yet written
+pub fn back_up(path: &Path, keep: usize) -> Result<(), String> {
+    if keep == 0 || !path.is_file() {
+        return Ok(());
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    if keep == 1 {
+        name.push(".bak");
+    } else {
+        name.push(format!(".{}.bak", history::now()));
+    }
+    fs::copy(path, path.with_file_name(name))
+        .map_err(|e| format!("Error writing backup: {}", e))?;
+    if keep > 1 {
+        for (_, old) in list(path).into_iter().skip(keep) {
+            fs::remove_file(old).map_err(|e| format!("Error removing old backup: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamped_names() {
+        assert_eq!(
+            stamp("a.rtxt", "a.rtxt.1700000000.bak"),
+            Some(1_700_000_000)
+        );
+        assert_eq!(stamp("a.rtxt", "a.rtxt.bak"), None);
+        assert_eq!(stamp("a.rtxt", "ba.rtxt.1700000000.bak"), None);
+        assert_eq!(stamp("a.rtxt", "a.rtxt.x.bak"), None);
+    }
+}
@@ -0,0 +1,191 @@
+// Word completion from the words already in the document and an optional
+// dictionary file
+use crate::document::Document;
+use crate::{text, wikilink};
+use std::collections::HashMap;
+use std::path::Path;
+
+const MAX_SUGGESTIONS: usize = 8;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Character index where the word ending at the cursor starts, and that
+// part of the word
+pub fn word_before(text: &str, cursor: usize) -> (usize, String) {
+    let before = text::char_slice(text, 0..cursor);
+    let prefix: Vec<char> = before
+        .chars()
+        .rev()
+        .take_while(|&c| is_word_char(c))
+        .collect();
+    let start = cursor.min(text::char_count(text)) - prefix.len();
+    (start, prefix.into_iter().rev().collect())
+}
+
+// Words starting with `prefix` (ignoring case), the most frequent in the
+// document first, then dictionary words
+pub fn suggestions(text: &str, prefix: &str, dictionary: &[String]) -> Vec<String> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let lower = prefix.to_lowercase();
+    let matches = |word: &str| word != prefix && word.to_lowercase().starts_with(&lower);
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for word in text.split(|c: char| !is_word_char(c)) {
+        if matches(word) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    for word in dictionary {
+        if matches(word) {
+            counts.entry(word).or_default();
+        }
+    }
+    let mut words: Vec<(&str, usize)> = counts.into_iter().collect();
+    words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    words
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(word, _)| word.to_string())
+        .collect()
+}
+
+// Words of a dictionary file, separated by whitespace
+pub fn load_dictionary(path: &Path) -> Result<Vec<String>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Error reading dictionary: {}", e))?;
+    Ok(content.split_whitespace().map(str::to_string).collect())
+}
+
+// The completion popup for the word at the cursor
+pub struct Completion {
+    pub doc_id: u64,
+    pub start: usize, // Character index of the start of the word
+    pub prefix: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+    pub hovered: bool,  // Clicking the list takes focus away from the editor
+    pub suffix: String, // Added after the chosen item unless already there
+}
+
+pub enum CompletionKey {
+    Accept,
+    Cancel,
+}
+
+impl Completion {
+    // Suggestions for the word before the cursor, if there are any
+    pub fn new(doc: &Document, dictionary: &[String], min_len: usize) -> Option<Self> {
+        let cursor = doc.cursor_range.clone().filter(|range| range.is_empty())?;
+        let (start, prefix) = word_before(&doc.text_content, cursor.start);
+        if text::char_count(&prefix) < min_len.max(1) {
+            return None;
+        }
+        let items = suggestions(&doc.text_content, &prefix, dictionary);
+        (!items.is_empty()).then_some(Self {
+            doc_id: doc.id,
+            start,
+            prefix,
+            items,
+            selected: 0,
+            hovered: false,
+            suffix: String::new(),
+        })
+    }
+
+    // Note names for the [[link]] at the cursor, those starting with the
+    // typed name first; offered from the first keystroke
+    pub fn link(doc: &Document, note_names: &[String]) -> Option<Self> {
+        let cursor = doc.cursor_range.clone().filter(|range| range.is_empty())?;
+        let (start, prefix) = wikilink::name_before(&doc.text_content, cursor.start)?;
+        let lower = prefix.to_lowercase();
+        let mut items: Vec<&String> = note_names
+            .iter()
+            .filter(|name| name.to_lowercase().contains(&lower))
+            .collect();
+        items.sort_by_key(|name| {
+            (
+                !name.to_lowercase().starts_with(&lower),
+                name.to_lowercase(),
+            )
+        });
+        (!items.is_empty()).then(|| Self {
+            doc_id: doc.id,
+            start,
+            prefix,
+            items: items.into_iter().take(MAX_SUGGESTIONS).cloned().collect(),
+            selected: 0,
+            hovered: false,
+            suffix: "]]".to_string(),
+        })
+    }
+
+    // Arrow keys move through the list, Enter or Tab insert and Esc closes;
+    // the keys are consumed before the editor sees them
+    pub fn handle_keys(&mut self, ctx: &egui::Context) -> Option<CompletionKey> {
+        ctx.input_mut(|i| {
+            let none = egui::Modifiers::NONE;
+            if i.consume_key(none, egui::Key::ArrowDown) {
+                self.selected = (self.selected + 1) % self.items.len();
+            }
+            if i.consume_key(none, egui::Key::ArrowUp) {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+            }
+            if i.consume_key(none, egui::Key::Enter) || i.consume_key(none, egui::Key::Tab) {
+                return Some(CompletionKey::Accept);
+            }
+            if i.consume_key(none, egui::Key::Escape) {
+                return Some(CompletionKey::Cancel);
+            }
+            None
+        })
+    }
+
+    // Show the list below `pos`; returns a clicked suggestion
+    pub fn show(&mut self, ctx: &egui::Context, pos: egui::Pos2) -> Option<usize> {
+        let mut clicked = None;
+        let area = egui::Area::new(egui::Id::new("completion"))
+            .fixed_pos(pos)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (index, item) in self.items.iter().enumerate() {
+                        if ui.selectable_label(index == self.selected, item).clicked() {
+                            clicked = Some(index);
+                        }
+                    }
+                });
+            });
+        self.hovered = area.response.contains_pointer();
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_before_the_cursor() {
+        let text = "naïve café_au";
+        assert_eq!(word_before(text, 4), (0, "naïv".to_string()));
+        assert_eq!(word_before(text, 13), (6, "café_au".to_string()));
+        assert_eq!(word_before(text, 6), (6, String::new()));
+    }
+
+    #[test]
+    fn suggestions_rank_document_words_by_frequency() {
+        let text = "Termin term terminal terminal Terminal te";
+        let dictionary = vec!["terminus".to_string(), "other".to_string()];
+        assert_eq!(
+            suggestions(text, "ter", &dictionary),
+            vec!["terminal", "Termin", "Terminal", "term", "terminus"]
+        );
+        assert!(suggestions(text, "", &dictionary).is_empty());
+        // The word being typed is not suggested back
+        assert_eq!(suggestions("term te", "term", &[]), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,342 @@
+// Line diff between the saved file and the buffer, and the "Compare with
+// saved" window that shows it
+use crate::document::Document;
+use egui::{Color32, RichText};
+use std::ops::Range;
+use std::path::Path;
+
+// Above this many line pairs the changed middle is reported as a single hunk
+// instead of being matched line by line
+const MAX_MATCH_CELLS: usize = 4_000_000;
+
+// A run of changed lines: the `old` lines of the saved text were replaced by
+// the `new` lines of the buffer; either range may be empty
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+pub fn lines(text: &str) -> Vec<&str> {
+    text.split('\n').collect()
+}
+
+pub fn diff(old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    // Only the middle between the common prefix and suffix needs matching
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return Vec::new();
+    }
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if n * m > MAX_MATCH_CELLS {
+        return vec![Hunk {
+            old: prefix..prefix + n,
+            new: prefix..prefix + m,
+        }];
+    }
+
+    // Longest common subsequence lengths of every pair of suffixes
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if old_mid[i] == new_mid[j] {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    // Walk the table, grouping the unmatched lines between matches into hunks
+    let mut hunks = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            if let Some((old_start, new_start)) = start.take() {
+                hunks.push(Hunk {
+                    old: prefix + old_start..prefix + i,
+                    new: prefix + new_start..prefix + j,
+                });
+            }
+            i += 1;
+            j += 1;
+        } else {
+            start.get_or_insert((i, j));
+            if j < m && (i == n || lcs[at(i, j + 1)] >= lcs[at(i + 1, j)]) {
+                j += 1;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    if let Some((old_start, new_start)) = start {
+        hunks.push(Hunk {
+            old: prefix + old_start..prefix + n,
+            new: prefix + new_start..prefix + m,
+        });
+    }
+    hunks
+}
+
+// The buffer with the hunk's lines put back to their saved version
+pub fn revert_hunk(buffer: &str, saved: &str, hunk: &Hunk) -> String {
+    let saved_lines = lines(saved);
+    let mut buffer_lines = lines(buffer);
+    buffer_lines.splice(
+        hunk.new.clone(),
+        saved_lines[hunk.old.clone()].iter().copied(),
+    );
+    buffer_lines.join("\n")
+}
+
+// Unchanged lines shown around each hunk
+const CONTEXT_LINES: usize = 3;
+
+pub struct Comparison {
+    pub doc_id: u64,
+    saved: String,
+    buffer: String, // Buffer text the hunks were computed for
+    hunks: Vec<Hunk>,
+    side_by_side: bool,
+    error: Option<String>,
+}
+
+impl Comparison {
+    pub fn open(doc: &Document) -> Result<Self, String> {
+        let path = doc
+            .file_path
+            .as_ref()
+            .ok_or_else(|| "Save the document before comparing it".to_string())?;
+        let mut comparison = Self {
+            doc_id: doc.id,
+            saved: read_saved(path)?,
+            buffer: doc.text_content.clone(),
+            hunks: Vec::new(),
+            side_by_side: false,
+            error: None,
+        };
+        comparison.compare();
+        Ok(comparison)
+    }
+
+    fn compare(&mut self) {
+        self.hunks = diff(&lines(&self.saved), &lines(&self.buffer));
+    }
+
+    // Show the window; returns the buffer text with a hunk reverted when the
+    // user asks for it, and sets `open` to false when the window is closed
+    pub fn show(&mut self, ctx: &egui::Context, doc: &Document, open: &mut bool) -> Option<String> {
+        // Follow edits made while the window is open
+        if self.buffer != doc.text_content {
+            self.buffer = doc.text_content.clone();
+            self.compare();
+        }
+        let mut revert = None;
+        let mut reload = false;
+        egui::Window::new(format!("Compare with saved: {}", doc.title()))
+            .id(egui::Id::new("compare_with_saved"))
+            .open(open)
+            .default_size([700.0, 500.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.side_by_side, false, "Inline");
+                    ui.radio_value(&mut self.side_by_side, true, "Side by side");
+                    ui.separator();
+                    reload = ui
+                        .button("⟳ Reload")
+                        .on_hover_text("Read the saved file again")
+                        .clicked();
+                    ui.label(format!("{} changed sections", self.hunks.len()));
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                ui.separator();
+                if self.hunks.is_empty() {
+                    ui.label("The text matches the saved file");
+                }
+                let saved = lines(&self.saved);
+                let buffer = lines(&self.buffer);
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (index, hunk) in self.hunks.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.strong(format!(
+                                    "@@ -{},{} +{},{} @@",
+                                    hunk.old.start + 1,
+                                    hunk.old.len(),
+                                    hunk.new.start + 1,
+                                    hunk.new.len()
+                                ));
+                                if ui
+                                    .small_button("↶ Revert")
+                                    .on_hover_text("Put these lines back to the saved version")
+                                    .clicked()
+                                {
+                                    revert = Some(index);
+                                }
+                            });
+                            if self.side_by_side {
+                                show_side_by_side(ui, hunk, &saved, &buffer);
+                            } else {
+                                show_inline(ui, hunk, &saved, &buffer);
+                            }
+                            ui.separator();
+                        }
+                    });
+            });
+
+        if reload {
+            match doc.file_path.as_deref().map(read_saved) {
+                Some(Ok(saved)) => {
+                    self.saved = saved;
+                    self.error = None;
+                    self.compare();
+                }
+                Some(Err(e)) => self.error = Some(e),
+                None => {}
+            }
+        }
+        let hunk = &self.hunks[revert?];
+        Some(revert_hunk(&self.buffer, &self.saved, hunk))
+    }
+}
+
+// The text of the file as it would be loaded, without its formatting
+fn read_saved(path: &Path) -> Result<String, String> {
+    let mut saved = Document::new(0);
+    saved.load_with_formatting(&path.to_path_buf())?;
+    Ok(saved.text_content)
+}
+
+fn show_inline(ui: &mut egui::Ui, hunk: &Hunk, saved: &[&str], buffer: &[&str]) {
+    let before = hunk.new.start.saturating_sub(CONTEXT_LINES)..hunk.new.start;
+    let after = hunk.new.end..(hunk.new.end + CONTEXT_LINES).min(buffer.len());
+    for line in &buffer[before] {
+        diff_line(ui, ' ', line, None);
+    }
+    for line in &saved[hunk.old.clone()] {
+        diff_line(ui, '-', line, Some(REMOVED));
+    }
+    for line in &buffer[hunk.new.clone()] {
+        diff_line(ui, '+', line, Some(ADDED));
+    }
+    for line in &buffer[after] {
+        diff_line(ui, ' ', line, None);
+    }
+}
+
+fn show_side_by_side(ui: &mut egui::Ui, hunk: &Hunk, saved: &[&str], buffer: &[&str]) {
+    let rows = hunk.old.len().max(hunk.new.len());
+    ui.columns(2, |columns| {
+        let sides = [(saved, &hunk.old, REMOVED), (buffer, &hunk.new, ADDED)];
+        for (ui, (lines, range, color)) in columns.iter_mut().zip(sides) {
+            ui.spacing_mut().item_spacing.y = 0.0;
+            let before = range.start.saturating_sub(CONTEXT_LINES)..range.start;
+            // Pad the context so both columns start their changes on the same row
+            for _ in before.len()..CONTEXT_LINES.min(hunk.old.start.max(hunk.new.start)) {
+                diff_line(ui, ' ', "", None);
+            }
+            for line in &lines[before] {
+                diff_line(ui, ' ', line, None);
+            }
+            for line in &lines[range.clone()] {
+                diff_line(ui, ' ', line, Some(color));
+            }
+            for _ in range.len()..rows {
+                diff_line(ui, ' ', "", None);
+            }
+            let after = range.end..(range.end + CONTEXT_LINES).min(lines.len());
+            for line in &lines[after] {
+                diff_line(ui, ' ', line, None);
+            }
+        }
+    });
+}
+
+const REMOVED: Color32 = Color32::from_rgb(255, 215, 215);
+const ADDED: Color32 = Color32::from_rgb(215, 255, 215);
+
+fn diff_line(ui: &mut egui::Ui, marker: char, line: &str, background: Option<Color32>) {
+    let mut text = RichText::new(format!("{} {}", marker, line)).monospace();
+    if let Some(background) = background {
+        text = text.color(Color32::BLACK).background_color(background);
+    }
+    ui.label(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hunks(old: &str, new: &str) -> Vec<Hunk> {
+        diff(&lines(old), &lines(new))
+    }
+
+    #[test]
+    fn finds_changed_inserted_and_removed_lines() {
+        assert!(hunks("a\nb\nc", "a\nb\nc").is_empty());
+        assert_eq!(
+            hunks("a\nb\nc", "a\nB\nc"),
+            vec![Hunk {
+                old: 1..2,
+                new: 1..2
+            }]
+        );
+        assert_eq!(
+            hunks("a\nc", "a\nb\nc\nd"),
+            vec![
+                Hunk {
+                    old: 1..1,
+                    new: 1..2
+                },
+                Hunk {
+                    old: 2..2,
+                    new: 3..4
+                }
+            ]
+        );
+        assert_eq!(
+            hunks("a\nb\nc\nd\ne", "a\nc\ne"),
+            vec![
+                Hunk {
+                    old: 1..2,
+                    new: 1..1
+                },
+                Hunk {
+                    old: 3..4,
+                    new: 2..2
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn reverting_every_hunk_restores_the_saved_text() {
+        let saved = "title\n\nfirst\nsecond\nthird\nend";
+        let buffer = "title\nnew line\n\nfirst\n2nd\nthird";
+        let mut text = buffer.to_string();
+        // From the last hunk back, so earlier line numbers stay valid
+        for hunk in hunks(saved, buffer).iter().rev() {
+            text = revert_hunk(&text, saved, hunk);
+        }
+        assert_eq!(text, saved);
+
+        let first = &hunks(saved, buffer)[0];
+        assert_eq!(
+            revert_hunk(buffer, saved, first),
+            "title\n\nfirst\n2nd\nthird"
+        );
+    }
+}
@@ -0,0 +1,1471 @@
+use crate::autocorrect::Autocorrect;
+use crate::encoding::{self, Encoding};
+use crate::footnote::{self, Renumbering};
+use crate::pipetable::{PipeTable, TableEdit};
+use crate::rtxt::{self, Metadata};
+use crate::sketch::Sketch;
+use crate::stylesheet::{self, NamedStyle};
+use crate::table::Table;
+use crate::text;
+use crate::undo::UndoHistory;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextFormatting {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl TextFormatting {
+    fn new(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => TextFormatting::Regular,
+            (true, false) => TextFormatting::Bold,
+            (false, true) => TextFormatting::Italic,
+            (true, true) => TextFormatting::BoldItalic,
+        }
+    }
+
+    pub fn is_bold(self) -> bool {
+        matches!(self, TextFormatting::Bold | TextFormatting::BoldItalic)
+    }
+
+    pub fn is_italic(self) -> bool {
+        matches!(self, TextFormatting::Italic | TextFormatting::BoldItalic)
+    }
+}
+
+// Font size of a styled range: a heading level scaled from the editor font
+// size, or an explicit size in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextSize {
+    Heading(u8),
+    Pixels(f32),
+}
+
+impl TextSize {
+    pub fn resolve(self, base_size: f32) -> f32 {
+        match self {
+            TextSize::Heading(1) => base_size * 2.0,
+            TextSize::Heading(2) => base_size * 1.6,
+            TextSize::Heading(_) => base_size * 1.3,
+            TextSize::Pixels(px) => px,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            TextSize::Heading(level) => format!("H{}", level),
+            TextSize::Pixels(px) => format!("{:.0}px", px),
+        }
+    }
+
+    // Legacy .rtxt field: "h1".."h3" or a pixel size such as "18"
+    pub fn parse_field(field: &str) -> Option<Self> {
+        if let Some(level) = field.strip_prefix('h') {
+            level
+                .parse()
+                .ok()
+                .filter(|level| (1..=3).contains(level))
+                .map(TextSize::Heading)
+        } else {
+            field
+                .parse()
+                .ok()
+                .filter(|px: &f32| px.is_finite() && *px > 0.0)
+                .map(TextSize::Pixels)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledRange {
+    pub range: Range<usize>, // Character indices, like the editor cursor
+    pub style: TextFormatting,
+    pub text_color: Option<Color32>,
+    pub bg_color: Option<Color32>,
+    pub size: Option<TextSize>, // None uses the editor font size
+    pub underline: bool,
+    pub named: Option<String>, // Style from the document's stylesheet
+}
+
+// One formatting attribute set on a selection, leaving the others as they are
+#[derive(Clone, Debug, PartialEq)]
+pub enum StyleChange {
+    Bold(bool),
+    Italic(bool),
+    Underline(bool),
+    TextColor(Option<Color32>),
+    Highlight(Option<Color32>),
+    Size(Option<TextSize>),
+    Named(Option<String>),
+    Clear, // Back to plain text
+}
+
+impl StyledRange {
+    pub fn same_format(&self, other: &StyledRange) -> bool {
+        self.style == other.style
+            && self.text_color == other.text_color
+            && self.bg_color == other.bg_color
+            && self.size == other.size
+            && self.underline == other.underline
+            && self.named == other.named
+    }
+
+    fn plain(range: Range<usize>) -> Self {
+        StyledRange {
+            range,
+            style: TextFormatting::Regular,
+            text_color: None,
+            bg_color: None,
+            size: None,
+            underline: false,
+            named: None,
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        self.same_format(&StyledRange::plain(0..0))
+    }
+
+    fn apply(&mut self, change: &StyleChange) {
+        let (bold, italic) = (self.style.is_bold(), self.style.is_italic());
+        match change {
+            StyleChange::Bold(bold) => self.style = TextFormatting::new(*bold, italic),
+            StyleChange::Italic(italic) => self.style = TextFormatting::new(bold, *italic),
+            StyleChange::Underline(underline) => self.underline = *underline,
+            StyleChange::TextColor(color) => self.text_color = *color,
+            StyleChange::Highlight(color) => self.bg_color = *color,
+            StyleChange::Size(size) => self.size = *size,
+            StyleChange::Named(name) => self.named = name.clone(),
+            StyleChange::Clear => *self = StyledRange::plain(self.range.clone()),
+        }
+    }
+}
+
+// One open note: its text, formatting, file and editing history
+pub struct Document {
+    pub id: u64, // Stable id for the editor widget state across tab switches
+    pub text_content: String,
+    pub styled_ranges: Vec<StyledRange>,
+    pub tables: Vec<Table>, // Anchored in the text by their placeholder characters
+    pub sketches: Vec<Sketch>, // Likewise
+    pub named_styles: Vec<NamedStyle>,
+    pub file_path: Option<PathBuf>,
+    pub dirty: bool, // Changed since the last save or load
+    pub metadata: Metadata,
+    pub encoding: Encoding, // Of the file, for plain text
+    pub cursor_range: Option<Range<usize>>,
+    pub history: UndoHistory,
+    pub last_find_position: usize,
+    pub find_scope: Option<Range<usize>>, // Find & Replace "In selection" range
+    // Pending cursor position after programmatic text changes
+    pub pending_cursor_pos: Option<usize>,
+    // Flag to prevent cursor capture when programmatically setting selection
+    pub skip_cursor_capture: bool,
+    pub scroll_offset: f32,   // Vertical scroll position of the editor
+    pub restore_scroll: bool, // Apply scroll_offset instead of scrolling to the cursor
+    pub pinned: bool,         // Kept at the left of the tab bar
+}
+
+impl Document {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            text_content: String::new(),
+            styled_ranges: Vec::new(),
+            tables: Vec::new(),
+            sketches: Vec::new(),
+            named_styles: stylesheet::defaults(),
+            file_path: None,
+            dirty: false,
+            metadata: Metadata::default(),
+            encoding: Encoding::default(),
+            cursor_range: None,
+            history: UndoHistory::default(),
+            last_find_position: 0,
+            find_scope: None,
+            pending_cursor_pos: None,
+            skip_cursor_capture: false,
+            scroll_offset: 0.0,
+            restore_scroll: false,
+            pinned: false,
+        }
+    }
+
+    // File name for tabs and the status bar
+    pub fn title(&self) -> String {
+        match &self.file_path {
+            Some(path) => path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("Unknown")
+                .to_string(),
+            None => "Untitled".to_string(),
+        }
+    }
+
+    // The text, formatting and file without the editing state, to be
+    // written out on another thread
+    pub fn content_copy(&self) -> Document {
+        Document {
+            text_content: self.text_content.clone(),
+            styled_ranges: self.styled_ranges.clone(),
+            tables: self.tables.clone(),
+            sketches: self.sketches.clone(),
+            named_styles: self.named_styles.clone(),
+            file_path: self.file_path.clone(),
+            dirty: self.dirty,
+            metadata: self.metadata.clone(),
+            encoding: self.encoding,
+            ..Document::new(self.id)
+        }
+    }
+
+    // An untitled, unmodified, empty document can be replaced by an opened file
+    pub fn is_blank(&self) -> bool {
+        self.file_path.is_none() && !self.dirty && self.text_content.is_empty()
+    }
+
+    pub fn save_with_formatting(&self, path: &PathBuf) -> Result<(), String> {
+        // Check file extension
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+        let content = if extension == "rtxt" {
+            // Save with formatting metadata for .rtxt files
+            rtxt::write(self)?.into_bytes()
+        } else {
+            // Save plain text for .txt and other files, with tables as pipe
+            // tables, in the file's encoding
+            self.encoding.encode(&self.plain_text())?
+        };
+        std::fs::write(path, content).map_err(|e| format!("Error saving file: {}", e))
+    }
+
+    pub fn load_with_formatting(&mut self, path: &PathBuf) -> Result<(), String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Error reading file: {}", e))?;
+        let (content, encoding) = encoding::decode(&bytes);
+        self.encoding = encoding;
+        rtxt::read(self, &content)
+    }
+
+    // Character index where the zero-based line starts, clamped to the last line
+    pub fn line_start_char(&self, line: usize) -> usize {
+        if line == 0 {
+            return 0;
+        }
+        self.text_content
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '\n')
+            .nth(line - 1)
+            .map_or_else(
+                || self.line_start_char(self.text_content.matches('\n').count()),
+                |(index, _)| index + 1,
+            )
+    }
+
+    // Characters of the zero-based lines `first..=last`, including the final
+    // line break, as selected by clicking line numbers
+    pub fn lines_char_range(&self, first: usize, last: usize) -> Range<usize> {
+        let start = self.line_start_char(first);
+        let end = if last < self.text_content.matches('\n').count() {
+            self.line_start_char(last + 1)
+        } else {
+            self.char_count()
+        };
+        start..end
+    }
+
+    // One-based line and column of the character index, as shown in the status bar
+    pub fn line_col(&self, char_index: usize) -> (usize, usize) {
+        let mut position = (1, 1);
+        for c in self.text_content.chars().take(char_index) {
+            position = if c == '\n' {
+                (position.0 + 1, 1)
+            } else {
+                (position.0, position.1 + 1)
+            };
+        }
+        position
+    }
+
+    pub fn char_to_byte(&self, char_index: usize) -> usize {
+        text::char_to_byte(&self.text_content, char_index)
+    }
+
+    pub fn byte_to_char(&self, offset: usize) -> usize {
+        text::byte_to_char(&self.text_content, offset)
+    }
+
+    pub fn char_count(&self) -> usize {
+        text::char_count(&self.text_content)
+    }
+
+    // The text with each table placeholder expanded to a pipe table; sketches
+    // have no text form and are left out
+    pub fn plain_text(&self) -> String {
+        let mut text = String::new();
+        for c in self.text_content.chars() {
+            match self.tables.iter().find(|table| table.placeholder() == c) {
+                Some(table) => text.push_str(&table.to_markdown()),
+                None if Table::is_placeholder(c) || Sketch::is_placeholder(c) => {}
+                None => text.push(c),
+            }
+        }
+        text
+    }
+
+    // Replace a character range of the text, shifting styled ranges after it
+    // and clipping ranges that overlapped the replaced text
+    pub fn replace_chars(&mut self, range: Range<usize>, replacement: &str) {
+        let bytes = text::char_range_to_bytes(&self.text_content, range.clone());
+        self.text_content.replace_range(bytes, replacement);
+        self.adjust_styles(range, text::char_count(replacement));
+    }
+
+    // Update styled ranges after the editor changed the text from `old_text`
+    pub fn sync_styles_after_edit(&mut self, old_text: &str) {
+        let (start, old_end, new_end) = text::changed_range(old_text, &self.text_content);
+        self.adjust_styles(start..old_end, new_end - start);
+        // Typing straight after styled text continues that style
+        if start == old_end {
+            for styled in &mut self.styled_ranges {
+                if styled.range.end == start && styled.range.start < start {
+                    styled.range.end = new_end;
+                }
+            }
+        }
+        self.merge_adjacent_styles();
+    }
+
+    // Account for the characters in `replaced` having been replaced by
+    // `new_len` characters
+    fn adjust_styles(&mut self, replaced: Range<usize>, new_len: usize) {
+        let (start, old_end) = (replaced.start, replaced.end);
+        let new_end = start + new_len;
+        let shift = |pos: usize| pos + new_end - old_end;
+        for styled in &mut self.styled_ranges {
+            let r = styled.range.clone();
+            styled.range.start = if r.start < start {
+                r.start
+            } else if r.start >= old_end {
+                shift(r.start)
+            } else {
+                new_end
+            };
+            styled.range.end = if r.end <= start {
+                r.end
+            } else if r.end >= old_end {
+                shift(r.end)
+            } else {
+                start
+            };
+        }
+        self.styled_ranges
+            .retain(|styled| styled.range.start < styled.range.end);
+    }
+
+    // Join touching ranges with the same formatting, such as the two halves
+    // of a range split by a deletion that was then undone by retyping
+    fn merge_adjacent_styles(&mut self) {
+        self.styled_ranges.sort_by_key(|styled| styled.range.start);
+        let mut merged: Vec<StyledRange> = Vec::with_capacity(self.styled_ranges.len());
+        for styled in self.styled_ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.range.end == styled.range.start && last.same_format(&styled) => {
+                    last.range.end = styled.range.end;
+                }
+                _ => merged.push(styled),
+            }
+        }
+        self.styled_ranges = merged;
+    }
+
+    // Character range of the whole lines touched by the selection, without
+    // the final newline
+    fn selected_lines(&self) -> Range<usize> {
+        let lines = self.selected_line_bytes();
+        self.byte_to_char(lines.start)..self.byte_to_char(lines.end)
+    }
+
+    fn selected_line_bytes(&self) -> Range<usize> {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        let start = self.char_to_byte(selection.start);
+        let mut end = self.char_to_byte(selection.end);
+        // A selection ending at the start of a line does not include that line
+        if selection.end > selection.start && self.text_content[..end].ends_with('\n') {
+            end -= 1;
+        }
+        let line_start = self.text_content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.text_content[end..]
+            .find('\n')
+            .map_or(self.text_content.len(), |i| end + i);
+        line_start..line_end.max(line_start)
+    }
+
+    // Start of each line touched by the selection, as character indices
+    fn selected_line_starts(&self) -> Vec<usize> {
+        let lines = self.selected_lines();
+        let newlines = text::char_slice(&self.text_content, lines.clone())
+            .chars()
+            .enumerate()
+            .filter(|&(_, c)| c == '\n')
+            .map(|(index, _)| lines.start + index + 1);
+        std::iter::once(lines.start).chain(newlines).collect()
+    }
+
+    // Insert or remove text at line starts, from the last line up, and move
+    // the selection with the text
+    fn edit_line_starts(&mut self, edits: &[(usize, usize, &str)]) {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        self.save_state_for_undo();
+        for &(start, removed, inserted) in edits.iter().rev() {
+            self.replace_chars(start..start + removed, inserted);
+        }
+        // Edits at the position itself move it too, except at the start of
+        // a selection so that it keeps the whole first line
+        let moved = |pos: usize, at_start: bool| {
+            edits
+                .iter()
+                .fold(pos, |moved, &(start, removed, inserted)| {
+                    if start > pos || (start == pos && at_start && !selection.is_empty()) {
+                        return moved;
+                    }
+                    moved - pos.saturating_sub(start).min(removed) + text::char_count(inserted)
+                })
+        };
+        let (start, end) = (moved(selection.start, true), moved(selection.end, false));
+        self.cursor_range = Some(start..end);
+        self.pending_cursor_pos = Some(end);
+        self.skip_cursor_capture = true;
+    }
+
+    // Indent every line touched by a selection that spans lines; otherwise
+    // insert one level of indentation at the cursor
+    pub fn indent(&mut self, unit: &str) {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        if !text::char_slice(&self.text_content, selection).contains('\n') {
+            self.insert_text(unit);
+            return;
+        }
+        let len = self.char_count();
+        let edits: Vec<(usize, usize, &str)> = self
+            .selected_line_starts()
+            .into_iter()
+            // Blank lines stay empty
+            .filter(|&start| start < len && self.char_at(start) != Some('\n'))
+            .map(|start| (start, 0, unit))
+            .collect();
+        self.edit_line_starts(&edits);
+    }
+
+    // Remove one level of indentation, a tab or up to `tab_width` spaces,
+    // from each line touched by the selection
+    pub fn outdent(&mut self, tab_width: usize) {
+        let edits: Vec<(usize, usize, &str)> = self
+            .selected_line_starts()
+            .into_iter()
+            .filter_map(|start| {
+                let line = text::char_slice(&self.text_content, start..self.char_count());
+                let removed = if line.starts_with('\t') {
+                    1
+                } else {
+                    line.chars()
+                        .take(tab_width)
+                        .take_while(|&c| c == ' ')
+                        .count()
+                };
+                (removed > 0).then_some((start, removed, ""))
+            })
+            .collect();
+        if !edits.is_empty() {
+            self.edit_line_starts(&edits);
+        }
+    }
+
+    fn char_at(&self, index: usize) -> Option<char> {
+        text::char_slice(&self.text_content, index..index + 1)
+            .chars()
+            .next()
+    }
+
+    // Move the selection by a number of characters and restore it next frame
+    fn shift_selection(&mut self, chars: isize) {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        let moved = |pos: usize| pos.saturating_add_signed(chars);
+        let (start, end) = (moved(selection.start), moved(selection.end));
+        self.cursor_range = Some(start..end);
+        self.pending_cursor_pos = Some(end);
+        self.skip_cursor_capture = true;
+    }
+
+    // Change the case of the selection, keeping its styles on the same
+    // characters
+    pub fn change_case(&mut self, case: text::Case) {
+        let Some(selection) = self.cursor_range.clone().filter(|range| !range.is_empty()) else {
+            return;
+        };
+        let pieces = text::change_case(
+            text::char_slice(&self.text_content, selection.clone()),
+            case,
+        );
+        // Where each selected character, and the end of the selection, ends up
+        let mut positions = Vec::with_capacity(pieces.len() + 1);
+        let mut pos = selection.start;
+        for piece in &pieces {
+            positions.push(pos);
+            pos += text::char_count(piece);
+        }
+        positions.push(pos);
+        let moved = |old: usize| {
+            if old < selection.start {
+                old
+            } else if old > selection.end {
+                old + pos - selection.end
+            } else {
+                positions[old - selection.start]
+            }
+        };
+
+        self.save_state_for_undo();
+        let bytes = text::char_range_to_bytes(&self.text_content, selection.clone());
+        self.text_content.replace_range(bytes, &pieces.concat());
+        for styled in &mut self.styled_ranges {
+            styled.range = moved(styled.range.start)..moved(styled.range.end);
+        }
+        self.cursor_range = Some(selection.start..pos);
+        self.pending_cursor_pos = Some(pos);
+        self.skip_cursor_capture = true;
+    }
+
+    // A character range with its formatting, tables and sketches as a
+    // document of its own, sharing this one's named styles
+    pub fn extract(&self, range: Range<usize>) -> Document {
+        let text = text::char_slice(&self.text_content, range.clone()).to_string();
+        let styled_ranges = self
+            .styled_ranges
+            .iter()
+            .filter_map(|styled| {
+                let start = styled.range.start.max(range.start);
+                let end = styled.range.end.min(range.end);
+                (start < end).then(|| StyledRange {
+                    range: start - range.start..end - range.start,
+                    ..styled.clone()
+                })
+            })
+            .collect();
+        Document {
+            tables: self
+                .tables
+                .iter()
+                .filter(|table| text.contains(table.placeholder()))
+                .cloned()
+                .collect(),
+            sketches: self
+                .sketches
+                .iter()
+                .filter(|sketch| text.contains(sketch.placeholder()))
+                .cloned()
+                .collect(),
+            text_content: text,
+            styled_ranges,
+            named_styles: self.named_styles.clone(),
+            ..Document::new(0)
+        }
+    }
+
+    // The Markdown pipe table the caret is in, if any
+    pub fn pipe_table(&self) -> Option<PipeTable> {
+        let caret = self.cursor_range.as_ref().map_or(0, |range| range.start);
+        PipeTable::at(&self.text_content, caret)
+    }
+
+    // Change the table around the caret and line its pipes up again
+    pub fn edit_pipe_table(&mut self, edit: TableEdit) -> bool {
+        let Some(mut table) = self.pipe_table() else {
+            return false;
+        };
+        table.apply(edit);
+        let (formatted, caret) = table.format();
+        self.save_state_for_undo();
+        self.replace_chars(table.range.clone(), &formatted);
+        let caret = table.range.start + caret;
+        self.cursor_range = Some(caret..caret);
+        self.pending_cursor_pos = Some(caret);
+        self.skip_cursor_capture = true;
+        true
+    }
+
+    // Add a footnote reference at the cursor, numbered in order with the
+    // others, and put the cursor in its note at the end
+    pub fn insert_footnote(&mut self) -> Result<(), String> {
+        let caret = self.cursor_range.as_ref().map_or(0, |range| range.end);
+        let renumbering = footnote::insert(&self.text_content, self.char_to_byte(caret))
+            .ok_or("Put the cursor in the text to add a footnote")?;
+        self.apply_renumbering(renumbering);
+        Ok(())
+    }
+
+    pub fn renumber_footnotes(&mut self) {
+        let renumbering = footnote::renumber(&self.text_content);
+        if !renumbering.edits.is_empty() {
+            self.apply_renumbering(renumbering);
+        }
+    }
+
+    fn apply_renumbering(&mut self, renumbering: Renumbering) {
+        let edits: Vec<(Range<usize>, String)> = renumbering
+            .edits
+            .into_iter()
+            .map(|(range, text)| {
+                (
+                    self.byte_to_char(range.start)..self.byte_to_char(range.end),
+                    text,
+                )
+            })
+            .collect();
+        self.save_state_for_undo();
+        // From the end, so the earlier ranges stay valid
+        for (range, text) in edits.into_iter().rev() {
+            self.replace_chars(range, &text);
+        }
+        if let Some(from_end) = renumbering.caret_from_end {
+            let caret = self.char_count() - from_end;
+            self.cursor_range = Some(caret..caret);
+            self.pending_cursor_pos = Some(caret);
+            self.skip_cursor_capture = true;
+        }
+    }
+
+    // Character index a click on a footnote reference or note leads to
+    pub fn footnote_target(&self, char_index: usize) -> Option<usize> {
+        footnote::jump_target(&self.text_content, self.char_to_byte(char_index))
+            .map(|offset| self.byte_to_char(offset))
+    }
+
+    pub fn duplicate_lines(&mut self) {
+        let lines = self.selected_lines();
+        let block = text::char_slice(&self.text_content, lines.clone()).to_string();
+        self.save_state_for_undo();
+
+        // Copy the styles inside the block along with the text
+        let offset = text::char_count(&block) + 1;
+        let copies: Vec<StyledRange> = self
+            .styled_ranges
+            .iter()
+            .filter_map(|styled| {
+                let start = styled.range.start.max(lines.start);
+                let end = styled.range.end.min(lines.end);
+                (start < end).then(|| StyledRange {
+                    range: start + offset..end + offset,
+                    ..styled.clone()
+                })
+            })
+            .collect();
+        self.replace_chars(lines.end..lines.end, &format!("\n{}", block));
+        self.styled_ranges.extend(copies);
+        self.styled_ranges.sort_by_key(|r| r.range.start);
+        self.shift_selection(offset as isize);
+    }
+
+    pub fn delete_lines(&mut self) {
+        let lines = self.selected_lines();
+        // Take the newline after the lines, or before them on the last line
+        let range = if lines.end < self.char_count() {
+            lines.start..lines.end + 1
+        } else {
+            lines.start.saturating_sub(1)..lines.end
+        };
+        self.save_state_for_undo();
+        self.replace_chars(range.clone(), "");
+        let cursor = if range.start < lines.start {
+            // Deleted the last line: go to the start of the new last line
+            let newlines = text::char_slice(&self.text_content, 0..range.start)
+                .matches('\n')
+                .count();
+            self.line_start_char(newlines)
+        } else {
+            range.start
+        };
+        self.cursor_range = Some(cursor..cursor);
+        self.pending_cursor_pos = Some(cursor);
+        self.skip_cursor_capture = true;
+    }
+
+    // Swap the selected lines with the line above or below
+    pub fn move_lines(&mut self, up: bool) {
+        let lines = self.selected_line_bytes();
+        let (first, second) = if up {
+            if lines.start == 0 {
+                return;
+            }
+            let above = self.text_content[..lines.start - 1]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            (above..lines.start - 1, lines.clone())
+        } else {
+            if lines.end >= self.text_content.len() {
+                return;
+            }
+            let below = self.text_content[lines.end + 1..]
+                .find('\n')
+                .map_or(self.text_content.len(), |i| lines.end + 1 + i);
+            (lines.clone(), lines.end + 1..below)
+        };
+        let to_chars =
+            |range: Range<usize>| self.byte_to_char(range.start)..self.byte_to_char(range.end);
+        let (first, second) = (to_chars(first), to_chars(second));
+        self.save_state_for_undo();
+        self.swap_adjacent(first.clone(), second.clone());
+
+        let moved_chars = if up {
+            -(first.len() as isize + 1)
+        } else {
+            second.len() as isize + 1
+        };
+        self.shift_selection(moved_chars);
+    }
+
+    // Swap two character ranges separated by a single newline, carrying the
+    // styles of each part with it
+    fn swap_adjacent(&mut self, first: Range<usize>, second: Range<usize>) {
+        let swapped = format!(
+            "{}\n{}",
+            text::char_slice(&self.text_content, second.clone()),
+            text::char_slice(&self.text_content, first.clone())
+        );
+        let first_shift = second.len() + 1; // First part moves right by this much
+        let second_shift = first.len() + 1; // Second part moves left by this much
+
+        let mut moved = Vec::new();
+        for styled in &self.styled_ranges {
+            let r = &styled.range;
+            let outside = r.end <= first.start || r.start >= second.end;
+            if outside {
+                moved.push(styled.clone());
+                continue;
+            }
+            if r.start < first.start {
+                moved.push(StyledRange {
+                    range: r.start..first.start,
+                    ..styled.clone()
+                });
+            }
+            let (start, end) = (r.start.max(first.start), r.end.min(first.end));
+            if start < end {
+                moved.push(StyledRange {
+                    range: start + first_shift..end + first_shift,
+                    ..styled.clone()
+                });
+            }
+            let (start, end) = (r.start.max(second.start), r.end.min(second.end));
+            if start < end {
+                moved.push(StyledRange {
+                    range: start - second_shift..end - second_shift,
+                    ..styled.clone()
+                });
+            }
+            if r.end > second.end {
+                moved.push(StyledRange {
+                    range: second.end..r.end,
+                    ..styled.clone()
+                });
+            }
+        }
+        let bytes = text::char_range_to_bytes(&self.text_content, first.start..second.end);
+        self.text_content.replace_range(bytes, &swapped);
+        moved.sort_by_key(|r| r.range.start);
+        self.styled_ranges = moved;
+    }
+
+    // Replace the selection with text and put the cursor the given number of
+    // characters into it
+    fn insert_at_cursor(&mut self, text: &str, cursor_offset: usize) {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        let (old_text, old_styles) = (self.text_content.clone(), self.styled_ranges.clone());
+        self.replace_chars(selection.clone(), text);
+        // Undone together with the typing around it
+        self.history.record_typing(
+            &old_text,
+            &old_styles,
+            &self.text_content,
+            &self.styled_ranges,
+        );
+        self.dirty = true;
+        let cursor = selection.start + cursor_offset;
+        self.cursor_range = Some(cursor..cursor);
+        self.pending_cursor_pos = Some(cursor);
+        self.skip_cursor_capture = true;
+    }
+
+    // Replace the selection with text, leaving the cursor after it
+    pub fn insert_text(&mut self, text: &str) {
+        self.insert_at_cursor(text, text::char_count(text));
+    }
+
+    // Start a new line with the same leading whitespace as the current one
+    pub fn newline_with_indent(&mut self) {
+        let cursor = self.cursor_range.as_ref().map_or(0, |range| range.start);
+        let byte_pos = self.char_to_byte(cursor);
+        let line_start = self.text_content[..byte_pos]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let indent: String = self.text_content[line_start..byte_pos]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let text = format!("\n{}", indent);
+        self.insert_at_cursor(&text, text.chars().count());
+    }
+
+    // Handle a typed bracket or quote when auto-closing is on; returns false
+    // if the character should be inserted normally
+    pub fn type_paired(&mut self, typed: char) -> bool {
+        let selection = self.cursor_range.clone().unwrap_or(0..0);
+        let byte_pos = self.char_to_byte(selection.start);
+        let next = self.text_content[self.char_to_byte(selection.end)..]
+            .chars()
+            .next();
+        let previous = self.text_content[..byte_pos].chars().next_back();
+
+        // Typing a closer in front of the same closer steps over it
+        let is_closer = matches!(typed, ')' | ']' | '}' | '"' | '\'');
+        if is_closer && selection.is_empty() && next == Some(typed) {
+            let cursor = selection.start + 1;
+            self.cursor_range = Some(cursor..cursor);
+            self.pending_cursor_pos = Some(cursor);
+            self.skip_cursor_capture = true;
+            return true;
+        }
+        // Leave apostrophes and quotes inside words alone
+        let in_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+        if (typed == '"' || typed == '\'')
+            && selection.is_empty()
+            && (in_word(previous) || in_word(next))
+        {
+            return false;
+        }
+        let Some(closer) = closing_bracket(typed) else {
+            return false;
+        };
+
+        // Wrap a selection, otherwise insert the pair around the cursor
+        let selected = self.text_content[byte_pos..self.char_to_byte(selection.end)].to_string();
+        let text = format!("{}{}{}", typed, selected, closer);
+        self.insert_at_cursor(&text, 1 + selected.chars().count());
+        true
+    }
+
+    // Apply an autocorrect substitution for a typed character; returns false
+    // if the character should be inserted normally
+    pub fn autocorrect(&mut self, autocorrect: &Autocorrect, typed: char) -> bool {
+        let Some(cursor) = self.cursor_range.clone().filter(|range| range.is_empty()) else {
+            return false;
+        };
+        let byte_pos = self.char_to_byte(cursor.start);
+        let line_start = self.text_content[..byte_pos]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let Some(correction) = autocorrect.correct(&self.text_content[line_start..byte_pos], typed)
+        else {
+            return false;
+        };
+        let start = cursor.start - correction.remove;
+        self.save_state_for_undo();
+        self.replace_chars(start..cursor.start, &correction.insert);
+        let after = start + text::char_count(&correction.insert);
+        self.cursor_range = Some(after..after);
+        self.pending_cursor_pos = Some(after);
+        self.skip_cursor_capture = true;
+        true
+    }
+
+    // Character positions of the bracket next to the cursor and its match
+    pub fn bracket_pair_at_cursor(&self) -> Option<(usize, usize)> {
+        let selection = self.cursor_range.clone()?;
+        if !selection.is_empty() {
+            return None;
+        }
+        let byte_pos = self.char_to_byte(selection.start);
+        let before = byte_pos.checked_sub(1).filter(|pos| {
+            self.text_content
+                .as_bytes()
+                .get(*pos)
+                .is_some_and(|b| b"()[]{}".contains(b))
+        });
+        // Brackets are single bytes, so these positions are character boundaries
+        [before, Some(byte_pos)]
+            .into_iter()
+            .flatten()
+            .find_map(|pos| matching_bracket(&self.text_content, pos).map(|other| (pos, other)))
+            .map(|(a, b)| (self.byte_to_char(a), self.byte_to_char(b)))
+    }
+
+    // Character index of each checklist marker and whether it is checked
+    pub fn checklist(&self) -> Vec<(usize, bool)> {
+        checkbox_markers(&self.text_content)
+            .into_iter()
+            .map(|(offset, checked)| (self.byte_to_char(offset), checked))
+            .collect()
+    }
+
+    // Checked and total checklist items
+    pub fn checklist_progress(&self) -> (usize, usize) {
+        let markers = checkbox_markers(&self.text_content);
+        let done = markers.iter().filter(|(_, checked)| *checked).count();
+        (done, markers.len())
+    }
+
+    // Flip the marker starting at the character index between "[ ]" and "[x]"
+    pub fn toggle_checkbox(&mut self, marker: usize) {
+        let mark = text::char_slice(&self.text_content, marker + 1..marker + 2);
+        let replacement = if mark == " " { "x" } else { " " };
+        self.save_state_for_undo();
+        self.replace_chars(marker + 1..marker + 2, replacement);
+    }
+
+    // Insert a new table on its own line at the cursor
+    pub fn insert_table(&mut self, rows: usize, columns: usize) -> Result<(), String> {
+        let id = Table::next_id(&self.tables).ok_or("Too many tables in this document")?;
+        let table = Table::new(id, rows, columns);
+        self.save_state_for_undo();
+
+        let char_pos = self.cursor_range.as_ref().map_or(0, |range| range.start);
+        let block = format!("\n{}\n", table.placeholder());
+        self.replace_chars(char_pos..char_pos, &block);
+
+        let after = char_pos + text::char_count(&block);
+        self.cursor_range = Some(after..after);
+        self.pending_cursor_pos = Some(after);
+        self.tables.push(table);
+        Ok(())
+    }
+
+    // Insert an empty sketch canvas on its own line at the cursor
+    pub fn insert_sketch(&mut self) -> Result<(), String> {
+        let id = Sketch::next_id(&self.sketches).ok_or("Too many sketches in this document")?;
+        let sketch = Sketch::new(id);
+        self.save_state_for_undo();
+
+        let char_pos = self.cursor_range.as_ref().map_or(0, |range| range.start);
+        let block = format!("\n{}\n", sketch.placeholder());
+        self.replace_chars(char_pos..char_pos, &block);
+
+        let after = char_pos + text::char_count(&block);
+        self.cursor_range = Some(after..after);
+        self.pending_cursor_pos = Some(after);
+        self.sketches.push(sketch);
+        Ok(())
+    }
+
+    // Whether every character of the selection has a formatting attribute;
+    // unstyled characters count as plain text
+    pub fn selection_has(&self, attribute: impl Fn(&StyledRange) -> bool) -> bool {
+        let Some(selection) = self.cursor_range.clone().filter(|range| !range.is_empty()) else {
+            return false;
+        };
+        let mut covered = selection.start;
+        let mut ranges: Vec<&StyledRange> = self
+            .styled_ranges
+            .iter()
+            .filter(|styled| {
+                styled.range.start < selection.end && styled.range.end > selection.start
+            })
+            .collect();
+        ranges.sort_by_key(|styled| styled.range.start);
+        for styled in ranges {
+            if styled.range.start > covered || !attribute(styled) {
+                return false;
+            }
+            covered = covered.max(styled.range.end);
+        }
+        covered >= selection.end
+    }
+
+    // Set one attribute on the selection: ranges crossing its edges are split,
+    // and the other attributes of the text inside are kept
+    pub fn apply_style_to_selection(&mut self, change: StyleChange) {
+        if let Some(selection) = self.cursor_range.clone().filter(|range| !range.is_empty()) {
+            self.save_state_for_undo();
+            self.apply_style_to_range(selection, &change);
+        }
+    }
+
+    // Give the selection a named style; paragraph styles take whole lines, so
+    // they also apply with just the caret in a line
+    pub fn apply_named_style(&mut self, name: Option<&str>) {
+        let paragraph = name.is_some_and(|name| {
+            self.named_styles
+                .iter()
+                .any(|style| style.name == name && style.paragraph)
+        });
+        let range = if paragraph {
+            self.selected_lines()
+        } else {
+            self.cursor_range.clone().unwrap_or(0..0)
+        };
+        if !range.is_empty() {
+            self.save_state_for_undo();
+            self.apply_style_to_range(range, &StyleChange::Named(name.map(str::to_string)));
+        }
+    }
+
+    // Text using a renamed style follows it; text using a deleted style loses it
+    pub fn rename_named_style(&mut self, old: &str, new: Option<&str>) {
+        for styled in &mut self.styled_ranges {
+            if styled.named.as_deref() == Some(old) {
+                styled.named = new.map(str::to_string);
+            }
+        }
+        self.styled_ranges.retain(|styled| !styled.is_plain());
+        self.merge_adjacent_styles();
+        self.dirty = true;
+    }
+
+    fn apply_style_to_range(&mut self, selection: Range<usize>, change: &StyleChange) {
+        let mut styled_ranges = Vec::with_capacity(self.styled_ranges.len() + 2);
+        for styled in self.styled_ranges.drain(..) {
+            let r = styled.range.clone();
+            for piece in [
+                r.start..r.end.min(selection.start),
+                r.start.max(selection.start)..r.end.min(selection.end),
+                r.start.max(selection.end)..r.end,
+            ] {
+                if piece.start < piece.end {
+                    styled_ranges.push(StyledRange {
+                        range: piece,
+                        ..styled.clone()
+                    });
+                }
+            }
+        }
+        // Unstyled stretches of the selection get ranges of their own
+        styled_ranges.sort_by_key(|styled| styled.range.start);
+        let mut gaps = Vec::new();
+        let mut covered = selection.start;
+        for styled in &styled_ranges {
+            let r = &styled.range;
+            if r.end <= selection.start || r.start >= selection.end {
+                continue;
+            }
+            if r.start > covered {
+                gaps.push(StyledRange::plain(covered..r.start));
+            }
+            covered = covered.max(r.end);
+        }
+        if covered < selection.end {
+            gaps.push(StyledRange::plain(covered..selection.end));
+        }
+        styled_ranges.extend(gaps);
+
+        for styled in &mut styled_ranges {
+            if styled.range.start >= selection.start && styled.range.end <= selection.end {
+                styled.apply(change);
+            }
+        }
+        styled_ranges.retain(|styled| !styled.is_plain());
+        self.styled_ranges = styled_ranges;
+        self.merge_adjacent_styles();
+    }
+
+    // Undo/Redo functionality; call before changing the text or styles
+    pub fn save_state_for_undo(&mut self) {
+        self.history
+            .checkpoint(&self.text_content, &self.styled_ranges);
+        self.dirty = true;
+    }
+
+    // Update styles after the editor changed the text from `old_text`, and
+    // record the keystroke for undo
+    pub fn apply_editor_edit(&mut self, old_text: &str) {
+        let old_styles = self.styled_ranges.clone();
+        self.sync_styles_after_edit(old_text);
+        self.history.record_typing(
+            old_text,
+            &old_styles,
+            &self.text_content,
+            &self.styled_ranges,
+        );
+        self.dirty = true;
+    }
+
+    pub fn undo(&mut self) {
+        let cursor = self
+            .history
+            .undo(&mut self.text_content, &mut self.styled_ranges);
+        self.restore_cursor(cursor);
+    }
+
+    pub fn redo(&mut self) {
+        let cursor = self
+            .history
+            .redo(&mut self.text_content, &mut self.styled_ranges);
+        self.restore_cursor(cursor);
+    }
+
+    // Put the cursor at the end of the undone or redone change
+    fn restore_cursor(&mut self, cursor: Option<usize>) {
+        if let Some(cursor) = cursor {
+            self.dirty = true;
+            self.cursor_range = Some(cursor..cursor);
+            self.pending_cursor_pos = Some(cursor);
+            self.skip_cursor_capture = true;
+        }
+    }
+}
+
+// Byte offsets of the "[ ]" / "[x]" markers that start checklist lines, with
+// whether each is checked. A marker may follow indentation and a "- " or "* "
+// bullet, and must be followed by a space or the end of the line
+pub fn checkbox_markers(text: &str) -> Vec<(usize, bool)> {
+    let mut markers = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let trimmed = line.trim_start_matches([' ', '\t']);
+        let (bullet, rest) = match trimmed.strip_prefix("- ").or(trimmed.strip_prefix("* ")) {
+            Some(rest) => (2, rest),
+            None => (0, trimmed),
+        };
+        let checked = match rest.get(..3) {
+            Some("[ ]") => Some(false),
+            Some("[x]" | "[X]") => Some(true),
+            _ => None,
+        };
+        if let Some(checked) = checked {
+            if rest[3..].is_empty() || rest[3..].starts_with(' ') {
+                markers.push((line_start + line.len() - trimmed.len() + bullet, checked));
+            }
+        }
+        line_start += line.len() + 1;
+    }
+    markers
+}
+
+fn closing_bracket(opener: char) -> Option<char> {
+    match opener {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+// Byte position of the bracket matching the one at `pos`, skipping nested pairs
+pub fn matching_bracket(text: &str, pos: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let (open, close, forward) = match bytes.get(pos)? {
+        b'(' => (b'(', b')', true),
+        b'[' => (b'[', b']', true),
+        b'{' => (b'{', b'}', true),
+        b')' => (b'(', b')', false),
+        b']' => (b'[', b']', false),
+        b'}' => (b'{', b'}', false),
+        _ => return None,
+    };
+    let mut depth = 0usize;
+    let positions: Box<dyn Iterator<Item = usize>> = if forward {
+        Box::new(pos..bytes.len())
+    } else {
+        Box::new((0..=pos).rev())
+    };
+    for index in positions {
+        let byte = bytes[index];
+        if byte == open || byte == close {
+            if (byte == open) == forward {
+                depth += 1;
+            } else {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(range: Range<usize>) -> StyledRange {
+        StyledRange {
+            range,
+            style: TextFormatting::Bold,
+            text_color: None,
+            bg_color: None,
+            size: None,
+            underline: false,
+            named: None,
+        }
+    }
+
+    // Styles given as (start, end) character indices
+    fn document(text: &str, styles: &[(usize, usize)]) -> Document {
+        let mut doc = Document::new(0);
+        doc.text_content = text.to_string();
+        doc.styled_ranges = styles
+            .iter()
+            .map(|&(start, end)| styled(start..end))
+            .collect();
+        doc
+    }
+
+    fn ranges(doc: &Document) -> Vec<Range<usize>> {
+        doc.styled_ranges.iter().map(|s| s.range.clone()).collect()
+    }
+
+    #[test]
+    fn replace_shifts_and_clips_styles() {
+        // "wörld" is styled; the emoji before it is replaced by a longer string
+        let mut doc = document("😀 wörld ✓", &[(2, 7)]);
+        doc.replace_chars(0..1, "héllo");
+        assert_eq!(doc.text_content, "héllo wörld ✓");
+        assert_eq!(ranges(&doc), vec![6..11]);
+
+        // Deleting across the start of the range clips it
+        doc.replace_chars(4..8, "");
+        assert_eq!(doc.text_content, "héllrld ✓");
+        assert_eq!(ranges(&doc), vec![4..7]);
+
+        // Deleting the whole range drops it
+        doc.replace_chars(3..8, "");
+        assert!(doc.styled_ranges.is_empty());
+    }
+
+    #[test]
+    fn extract_keeps_the_formatting_of_the_range() {
+        let doc = document("one twö three", &[(0, 5), (6, 9)]);
+        let part = doc.extract(4..10);
+        assert_eq!(part.text_content, "twö th");
+        assert_eq!(ranges(&part), vec![0..1, 2..5]);
+    }
+
+    #[test]
+    fn indent_and_outdent_selected_lines() {
+        let mut doc = document("ä\n\n  b\nc", &[(0, 1)]);
+        // From inside the first line to the start of the last one, which is
+        // not included
+        doc.cursor_range = Some(1..7);
+        doc.indent("    ");
+        assert_eq!(doc.text_content, "    ä\n\n      b\nc");
+        assert_eq!(ranges(&doc), vec![4..5]);
+        assert_eq!(doc.cursor_range, Some(5..15));
+
+        doc.outdent(4);
+        assert_eq!(doc.text_content, "ä\n\n  b\nc");
+        assert_eq!(doc.cursor_range, Some(1..7));
+        doc.outdent(4);
+        assert_eq!(doc.text_content, "ä\n\nb\nc");
+
+        // A tab is one level; a cursor outdents its own line
+        let mut doc = document("x\n\t\ty", &[]);
+        doc.cursor_range = Some(5..5);
+        doc.outdent(4);
+        assert_eq!(doc.text_content, "x\n\ty");
+        assert_eq!(doc.cursor_range, Some(4..4));
+
+        // Without a multi-line selection the selection is replaced
+        doc.cursor_range = Some(0..1);
+        doc.indent("\t");
+        assert_eq!(doc.text_content, "\t\n\ty");
+    }
+
+    #[test]
+    fn case_change_keeps_styles_on_their_characters() {
+        // "große" is styled and grows by one character in upper case
+        let mut doc = document("eine große straße", &[(5, 10), (12, 17)]);
+        doc.cursor_range = Some(0..10);
+        doc.change_case(text::Case::Upper);
+        assert_eq!(doc.text_content, "EINE GROSSE straße");
+        assert_eq!(ranges(&doc), vec![5..11, 13..18]);
+        assert_eq!(doc.cursor_range, Some(0..11));
+
+        doc.undo();
+        assert_eq!(doc.text_content, "eine große straße");
+        assert_eq!(ranges(&doc), vec![5..10, 12..17]);
+    }
+
+    #[test]
+    fn styles_follow_editor_changes() {
+        let mut doc = document("ab 😀 bold end", &[(5, 9)]);
+
+        // Typing before the range shifts it
+        let old = doc.text_content.clone();
+        doc.text_content = "ab é😀 bold end".to_string();
+        doc.sync_styles_after_edit(&old);
+        assert_eq!(ranges(&doc), vec![6..10]);
+        assert_eq!(text::char_slice(&doc.text_content, 6..10), "bold");
+
+        // Typing at its end extends it
+        let old = doc.text_content.clone();
+        doc.text_content = "ab é😀 bolder end".to_string();
+        doc.sync_styles_after_edit(&old);
+        assert_eq!(ranges(&doc), vec![6..12]);
+
+        // Deleting inside it shrinks it
+        let old = doc.text_content.clone();
+        doc.text_content = "ab é😀 boer end".to_string();
+        doc.sync_styles_after_edit(&old);
+        assert_eq!(ranges(&doc), vec![6..10]);
+
+        // Deleting text that contains it removes it
+        let old = doc.text_content.clone();
+        doc.text_content = "ab end".to_string();
+        doc.sync_styles_after_edit(&old);
+        assert!(doc.styled_ranges.is_empty());
+    }
+
+    #[test]
+    fn touching_ranges_with_the_same_format_merge() {
+        let mut doc = document("one two", &[(0, 3), (4, 7)]);
+        let old = doc.text_content.clone();
+        doc.text_content = "onetwo".to_string();
+        doc.sync_styles_after_edit(&old);
+        assert_eq!(ranges(&doc), vec![0..6]);
+    }
+
+    #[test]
+    fn checklist_items() {
+        let text = "[ ] plain\n  - [x] done ✓\n* [X]\n[ ]no space\n[y] other\nnot [ ] start";
+        assert_eq!(
+            checkbox_markers(text),
+            vec![(0, false), (14, true), (29, true)]
+        );
+
+        let mut doc = document("😀\n- [ ] café\n- [x] tea", &[]);
+        assert_eq!(doc.checklist(), vec![(4, false), (15, true)]);
+        assert_eq!(doc.checklist_progress(), (1, 2));
+        doc.toggle_checkbox(4);
+        doc.toggle_checkbox(15);
+        assert_eq!(doc.text_content, "😀\n- [x] café\n- [ ] tea");
+        doc.undo();
+        assert_eq!(doc.checklist_progress(), (2, 2));
+    }
+
+    #[test]
+    fn apply_style_uses_cursor_char_indices() {
+        let mut doc = document("ünïcode 😀 text", &[]);
+        doc.cursor_range = Some(8..9);
+        doc.apply_style_to_selection(StyleChange::Italic(true));
+        let range = doc.styled_ranges[0].range.clone();
+        assert_eq!(text::char_slice(&doc.text_content, range), "😀");
+    }
+
+    #[test]
+    fn style_attributes_combine_on_overlap() {
+        // "one two three" with "one two" bold
+        let mut doc = document("one two three", &[(0, 7)]);
+        doc.cursor_range = Some(4..13);
+        doc.apply_style_to_selection(StyleChange::TextColor(Some(Color32::RED)));
+        assert_eq!(ranges(&doc), vec![0..4, 4..7, 7..13]);
+        let middle = &doc.styled_ranges[1];
+        assert!(middle.style.is_bold() && middle.text_color == Some(Color32::RED));
+        assert!(!doc.styled_ranges[2].style.is_bold());
+
+        assert!(!doc.selection_has(|styled| styled.style.is_bold()));
+        doc.cursor_range = Some(0..7);
+        assert!(doc.selection_has(|styled| styled.style.is_bold()));
+        doc.apply_style_to_selection(StyleChange::Bold(false));
+        doc.apply_style_to_selection(StyleChange::Underline(true));
+        assert_eq!(ranges(&doc), vec![0..4, 4..7, 7..13]);
+        assert_eq!(doc.styled_ranges[0].style, TextFormatting::Regular);
+
+        // Clearing removes the ranges that end up plain and merges the rest
+        doc.cursor_range = Some(0..7);
+        doc.apply_style_to_selection(StyleChange::Clear);
+        doc.cursor_range = Some(4..7);
+        doc.apply_style_to_selection(StyleChange::TextColor(Some(Color32::RED)));
+        assert_eq!(ranges(&doc), vec![4..13]);
+    }
+
+    #[test]
+    fn named_styles_apply_by_line_and_follow_renames() {
+        let mut doc = document("Tïtle\nsome code here", &[]);
+        // A paragraph style takes the caret's whole line
+        doc.cursor_range = Some(2..2);
+        doc.apply_named_style(Some("Heading"));
+        assert_eq!(ranges(&doc), vec![0..5]);
+        doc.cursor_range = Some(11..15);
+        doc.apply_named_style(Some("Code"));
+        doc.apply_style_to_selection(StyleChange::Bold(true));
+        assert_eq!(ranges(&doc), vec![0..5, 11..15]);
+
+        let code = stylesheet::effective(Some(&doc.styled_ranges[1]), &doc.named_styles);
+        assert!(code.monospace && code.bold);
+        doc.rename_named_style("Heading", Some("Title"));
+        assert_eq!(doc.styled_ranges[0].named.as_deref(), Some("Title"));
+        // Without its style, only the range's own bold is left
+        doc.rename_named_style("Code", None);
+        assert_eq!(ranges(&doc), vec![0..5, 11..15]);
+        assert_eq!(doc.styled_ranges[1].named, None);
+    }
+
+    #[test]
+    fn line_and_column_count_characters() {
+        let doc = document("ab\nc😀d\n", &[]);
+        assert_eq!(doc.line_col(0), (1, 1));
+        assert_eq!(doc.line_col(2), (1, 3));
+        assert_eq!(doc.line_col(3), (2, 1));
+        assert_eq!(doc.line_col(5), (2, 3));
+        assert_eq!(doc.line_col(7), (3, 1));
+        assert_eq!(doc.line_col(100), (3, 1));
+    }
+
+    #[test]
+    fn line_ranges_include_the_line_break() {
+        let doc = document("ä\nbö\nc", &[]);
+        assert_eq!(doc.lines_char_range(0, 0), 0..2);
+        assert_eq!(doc.lines_char_range(1, 1), 2..5);
+        assert_eq!(doc.lines_char_range(1, 2), 2..6);
+        assert_eq!(doc.lines_char_range(2, 9), 5..6);
+    }
+
+    #[test]
+    fn line_operations_with_multibyte_text() {
+        let mut doc = document("ä1\nö2😀\nü3", &[(3, 6)]);
+        doc.cursor_range = Some(4..4);
+        doc.duplicate_lines();
+        assert_eq!(doc.text_content, "ä1\nö2😀\nö2😀\nü3");
+        assert_eq!(ranges(&doc), vec![3..6, 7..10]);
+        assert_eq!(doc.cursor_range, Some(8..8));
+
+        doc.move_lines(true);
+        assert_eq!(doc.text_content, "ä1\nö2😀\nö2😀\nü3");
+        assert_eq!(doc.cursor_range, Some(4..4));
+
+        doc.cursor_range = Some(0..0);
+        doc.move_lines(false);
+        assert_eq!(doc.text_content, "ö2😀\nä1\nö2😀\nü3");
+        assert_eq!(ranges(&doc), vec![0..3, 7..10]);
+
+        // The cursor moved down with its line
+        assert_eq!(doc.cursor_range, Some(4..4));
+        doc.delete_lines();
+        assert_eq!(doc.text_content, "ö2😀\nö2😀\nü3");
+        assert_eq!(ranges(&doc), vec![0..3, 4..7]);
+
+        // Undo restores each operation in one step
+        doc.undo();
+        assert_eq!(doc.text_content, "ö2😀\nä1\nö2😀\nü3");
+    }
+
+    #[test]
+    fn brackets_match_across_multibyte_text() {
+        let mut doc = document("f(😀, [é])", &[]);
+        doc.cursor_range = Some(2..2);
+        assert_eq!(doc.bracket_pair_at_cursor(), Some((1, 8)));
+        doc.cursor_range = Some(5..5);
+        assert_eq!(doc.bracket_pair_at_cursor(), Some((5, 7)));
+        doc.cursor_range = Some(9..9);
+        assert_eq!(doc.bracket_pair_at_cursor(), Some((8, 1)));
+    }
+}
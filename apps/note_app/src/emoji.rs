@@ -0,0 +1,110 @@
+// Emoji picker window, searchable by the Unicode name of each emoji
+use egui::FontId;
+use gui_common::Labeled;
+
+// Unicode blocks with emoji and pictographs
+const RANGES: [std::ops::RangeInclusive<u32>; 7] = [
+    0x1F600..=0x1F64F, // Emoticons
+    0x1F300..=0x1F5FF, // Miscellaneous Symbols and Pictographs
+    0x1F680..=0x1F6FF, // Transport and Map Symbols
+    0x1F900..=0x1F9FF, // Supplemental Symbols and Pictographs
+    0x1FA70..=0x1FAFF, // Symbols and Pictographs Extended-A
+    0x2600..=0x26FF,   // Miscellaneous Symbols
+    0x2700..=0x27BF,   // Dingbats
+];
+
+// Every word of the query appears in the name, ignoring case
+pub fn name_matches(name: &str, query: &str) -> bool {
+    let name = name.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| name.contains(word))
+}
+
+// Named characters of the emoji blocks that `has_glyph` can draw
+fn emoji(has_glyph: impl Fn(char) -> bool) -> Vec<(char, String)> {
+    RANGES
+        .into_iter()
+        .flatten()
+        .filter_map(char::from_u32)
+        .filter(|&c| has_glyph(c))
+        .filter_map(|c| Some((c, unicode_names2::name(c)?.to_string().to_lowercase())))
+        .collect()
+}
+
+pub struct EmojiPicker {
+    query: String,
+    emoji: Vec<(char, String)>,
+}
+
+impl EmojiPicker {
+    // Only emoji the editor font can show are offered
+    pub fn new(ctx: &egui::Context) -> Self {
+        let font_id = FontId::proportional(16.0);
+        Self {
+            query: String::new(),
+            emoji: ctx.fonts(|fonts| emoji(|c| fonts.has_glyph(&font_id, c))),
+        }
+    }
+
+    // Show the window; returns the emoji that was clicked. It stays open so
+    // several can be inserted
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<char> {
+        let mut chosen = None;
+        egui::Window::new("Emoji")
+            .open(open)
+            .default_size([360.0, 320.0])
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Search, e.g. smiling cat")
+                        .desired_width(f32::INFINITY),
+                );
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for (c, name) in &self.emoji {
+                                if !name_matches(name, &self.query) {
+                                    continue;
+                                }
+                                let label = egui::RichText::new(c.to_string()).size(22.0);
+                                if ui
+                                    .add(egui::Button::new(label).frame(false))
+                                    .labeled(name)
+                                    .clicked()
+                                {
+                                    chosen = Some(*c);
+                                }
+                            }
+                        });
+                    });
+            });
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_are_found_by_name() {
+        let all = emoji(|_| true);
+        let found: Vec<char> = all
+            .iter()
+            .filter(|(_, name)| name_matches(name, "Grinning  FACE"))
+            .map(|(c, _)| *c)
+            .collect();
+        assert!(found.contains(&'😀'));
+        assert!(found
+            .iter()
+            .all(|c| unicode_names2::name(*c)
+                .is_some_and(|name| name.to_string().contains("GRINNING"))));
+        assert!(name_matches("black heart suit", ""));
+        assert!(!name_matches("black heart suit", "white"));
+        // Characters the font cannot draw are left out
+        assert!(emoji(|c| c != '😀').iter().all(|(c, _)| *c != '😀'));
+    }
+}
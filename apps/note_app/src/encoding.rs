@@ -0,0 +1,159 @@
+// Text file encodings: detected when a file is opened and used again when it
+// is saved. .rtxt files are always UTF-8
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le, // Written with a byte order mark
+    Utf16Be,
+    Windows1252, // Latin-1 with the printable 0x80-0x9F range of Windows
+}
+
+// Characters for bytes 0x80-0x9F in Windows-1252; the five unused bytes map
+// to the C1 controls as in Latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+impl Encoding {
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Windows1252,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        let utf16 = |bom: &[u8], to_bytes: fn(u16) -> [u8; 2]| {
+            let mut bytes = bom.to_vec();
+            bytes.extend(text.encode_utf16().flat_map(to_bytes));
+            bytes
+        };
+        Ok(match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Encoding::Utf16Le => utf16(UTF16_LE_BOM, u16::to_le_bytes),
+            Encoding::Utf16Be => utf16(UTF16_BE_BOM, u16::to_be_bytes),
+            Encoding::Windows1252 => text
+                .chars()
+                .map(|c| match c as u32 {
+                    code @ (0..=0x7F | 0xA0..=0xFF) => Ok(code as u8),
+                    _ => WINDOWS_1252_HIGH
+                        .iter()
+                        .position(|&high| high == c)
+                        .map(|index| 0x80 + index as u8)
+                        .ok_or_else(|| format!("\"{}\" cannot be saved as {}", c, self.label())),
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+// Decode a file's bytes, guessing the encoding from a byte order mark, the
+// zero bytes typical of UTF-16 text, then UTF-8 validity
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (
+            String::from_utf8_lossy(rest).into_owned(),
+            Encoding::Utf8Bom,
+        );
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+    }
+    if looks_like_utf16(bytes) {
+        // ASCII text in UTF-16 LE has its zero bytes at odd positions
+        let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+        let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+        return if odd_zeros >= even_zeros {
+            (decode_utf16(bytes, u16::from_le_bytes), Encoding::Utf16Le)
+        } else {
+            (decode_utf16(bytes, u16::from_be_bytes), Encoding::Utf16Be)
+        };
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), Encoding::Utf8);
+    }
+    let text = bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+            _ => char::from(b),
+        })
+        .collect();
+    (text, Encoding::Windows1252)
+}
+
+// An even number of bytes with at least a quarter of them zero
+fn looks_like_utf16(bytes: &[u8]) -> bool {
+    !bytes.is_empty()
+        && bytes.len().is_multiple_of(2)
+        && bytes.iter().filter(|&&b| b == 0).count() * 4 >= bytes.len()
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let text = "Grüße, “quotes” € 😀\n";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(decode(&bytes), (text.to_string(), encoding));
+        }
+        let latin = "Grüße, “quotes” €\n";
+        let bytes = Encoding::Windows1252.encode(latin).unwrap();
+        assert_eq!(bytes.len(), latin.chars().count());
+        assert_eq!(decode(&bytes), (latin.to_string(), Encoding::Windows1252));
+        assert!(Encoding::Windows1252.encode("😀").is_err());
+    }
+
+    #[test]
+    fn detection_without_byte_order_mark() {
+        assert_eq!(decode(b"plain"), ("plain".to_string(), Encoding::Utf8));
+        assert_eq!(decode(b""), (String::new(), Encoding::Utf8));
+        // "hi" in UTF-16 without a BOM
+        assert_eq!(decode(b"h\0i\0"), ("hi".to_string(), Encoding::Utf16Le));
+        assert_eq!(decode(b"\0h\0i"), ("hi".to_string(), Encoding::Utf16Be));
+        // Latin-1 bytes are not valid UTF-8
+        assert_eq!(
+            decode(b"caf\xe9"),
+            ("café".to_string(), Encoding::Windows1252)
+        );
+    }
+}
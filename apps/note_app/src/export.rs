@@ -0,0 +1,142 @@
+use crate::document::{Document, StyledRange};
+use crate::page::PageSetup;
+use crate::stylesheet;
+use crate::text;
+use egui::Color32;
+
+// Standalone HTML page with the formatting as inline markup, so it survives
+// being pasted into mail clients and wikis. Printed from a browser, it uses
+// the page size and margins of the paginated preview
+pub fn to_html(doc: &Document, font_family: &str, font_size: f32, page: &PageSetup) -> String {
+    let css_family = match font_family {
+        "Monospace" => "monospace".to_string(),
+        "Proportional" | "Emoji" => "sans-serif".to_string(),
+        name => format!("'{}', sans-serif", escape(name)),
+    };
+    let mut body = String::new();
+    let char_count = doc.char_count();
+
+    let mut pos = 0;
+    while pos < char_count {
+        // Next point where the formatting changes, as in the editor layouter
+        let next_change = doc
+            .styled_ranges
+            .iter()
+            .flat_map(|styled| [styled.range.start, styled.range.end])
+            .filter(|&boundary| boundary > pos && boundary < char_count)
+            .min()
+            .unwrap_or(char_count);
+        let styled = doc
+            .styled_ranges
+            .iter()
+            .find(|styled| styled.range.contains(&pos));
+        body.push_str(&styled_segment(
+            doc,
+            text::char_slice(&doc.text_content, pos..next_change),
+            styled,
+            font_size,
+        ));
+        pos = next_change;
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n\
+         <body>\n<div style=\"font-family: {}; font-size: {}px; white-space: pre-wrap;\">{}</div>\n\
+         </body>\n</html>\n",
+        escape(&doc.title()),
+        page.css(),
+        css_family,
+        font_size,
+        body
+    )
+}
+
+fn styled_segment(
+    doc: &Document,
+    segment: &str,
+    styled: Option<&StyledRange>,
+    font_size: f32,
+) -> String {
+    let mut html = String::new();
+    for c in segment.chars() {
+        match doc.tables.iter().find(|table| table.placeholder() == c) {
+            Some(table) => html.push_str(&table_html(&table.cells)),
+            None if crate::table::Table::is_placeholder(c) => {}
+            None if crate::sketch::Sketch::is_placeholder(c) => {
+                if let Some(sketch) = doc.sketches.iter().find(|s| s.placeholder() == c) {
+                    html.push_str(&sketch.to_svg());
+                }
+            }
+            None if c == '\n' => html.push_str("<br>\n"),
+            None => html.push_str(&escape(&c.to_string())),
+        }
+    }
+    // Named styles are written out as their formatting
+    let styled = stylesheet::effective(styled, &doc.named_styles);
+    let mut css = Vec::new();
+    if styled.monospace {
+        css.push("font-family: monospace".to_string());
+    }
+    if let Some(color) = styled.text_color {
+        css.push(format!("color: {}", css_color(color)));
+    }
+    if let Some(color) = styled.bg_color {
+        css.push(format!("background-color: {}", css_color(color)));
+    }
+    if let Some(size) = styled.size {
+        css.push(format!("font-size: {}px", size.resolve(font_size)));
+    }
+    if !css.is_empty() {
+        html = format!("<span style=\"{}\">{}</span>", css.join("; "), html);
+    }
+    if styled.underline {
+        html = format!("<u>{}</u>", html);
+    }
+    if styled.italic {
+        html = format!("<i>{}</i>", html);
+    }
+    if styled.bold {
+        html = format!("<b>{}</b>", html);
+    }
+    html
+}
+
+fn table_html(cells: &[Vec<String>]) -> String {
+    let cell_style = "border: 1px solid #999; padding: 2px 6px;";
+    let mut html = String::from("<table style=\"border-collapse: collapse;\">");
+    for (index, row) in cells.iter().enumerate() {
+        // The first row is the header, as in the pipe table export
+        let tag = if index == 0 { "th" } else { "td" };
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!(
+                "<{tag} style=\"{cell_style}\">{}</{tag}>",
+                escape(cell)
+            ));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn css_color(color: Color32) -> String {
+    if color.a() == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+    } else {
+        format!(
+            "rgba({}, {}, {}, {:.2})",
+            color.r(),
+            color.g(),
+            color.b(),
+            color.a() as f32 / 255.0
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,215 @@
+// Find and replace across the notes folder: the changes to each note are
+// listed for review, and notes can be left out, before anything is written
+use crate::document::Document;
+use egui::{Color32, RichText};
+use std::path::{Path, PathBuf};
+
+const MAX_PREVIEW_LINES: usize = 20;
+
+// A line with matches, as it is and as it will be
+#[derive(Debug, PartialEq)]
+pub struct LinePreview {
+    pub line: usize, // 1-based
+    pub before: String,
+    pub after: String,
+}
+
+pub struct FileChange {
+    pub path: PathBuf,
+    pub name: String, // Relative to the notes folder
+    pub matches: usize,
+    pub lines: Vec<LinePreview>,
+    pub include: bool,
+}
+
+pub enum ReplaceAction {
+    Apply,
+    Undo,
+    Rescan,
+}
+
+pub struct ReplaceInFiles {
+    pub find: String,
+    pub replace: String,
+    pub changes: Vec<FileChange>,
+    pub written: Vec<Document>, // Closed notes as they were before the last replace
+    pub status: Option<String>,
+}
+
+// Lines of the text containing `find`, up to a limit
+pub fn preview(text: &str, find: &str, replace: &str) -> Vec<LinePreview> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(find))
+        .take(MAX_PREVIEW_LINES)
+        .map(|(index, line)| LinePreview {
+            line: index + 1,
+            before: line.trim().to_string(),
+            after: line.replace(find, replace).trim().to_string(),
+        })
+        .collect()
+}
+
+// Replace every match as one undo step; returns the number replaced
+pub fn replace_in(doc: &mut Document, find: &str, replace: &str) -> usize {
+    let find_len = find.chars().count();
+    let matches: Vec<usize> = doc
+        .text_content
+        .match_indices(find)
+        .map(|(pos, _)| doc.byte_to_char(pos))
+        .collect();
+    if matches.is_empty() {
+        return 0;
+    }
+    doc.save_state_for_undo();
+    // From the end so earlier positions stay valid
+    for pos in matches.iter().rev() {
+        doc.replace_chars(*pos..pos + find_len, replace);
+    }
+    matches.len()
+}
+
+impl ReplaceInFiles {
+    // Look for `find` in every note; `open_text` gives the text of notes open
+    // in a tab, which may differ from the file
+    pub fn scan(
+        root: &Path,
+        notes: &[PathBuf],
+        find: &str,
+        replace: &str,
+        open_text: impl Fn(&Path) -> Option<String>,
+    ) -> Self {
+        let changes = notes
+            .iter()
+            .filter_map(|path| {
+                let text = open_text(path).or_else(|| {
+                    let mut doc = Document::new(0);
+                    doc.load_with_formatting(path).ok()?;
+                    Some(doc.text_content)
+                })?;
+                let matches = text.matches(find).count();
+                (matches > 0).then(|| FileChange {
+                    path: path.clone(),
+                    name: path
+                        .strip_prefix(root)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string(),
+                    matches,
+                    lines: preview(&text, find, replace),
+                    include: true,
+                })
+            })
+            .collect();
+        Self {
+            find: find.to_string(),
+            replace: replace.to_string(),
+            changes,
+            written: Vec::new(),
+            status: None,
+        }
+    }
+
+    // Show the window; sets `open` to false when it is closed
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<ReplaceAction> {
+        let mut action = None;
+        egui::Window::new("Replace in Notes")
+            .open(open)
+            .default_size([560.0, 420.0])
+            .show(ctx, |ui| {
+                ui.label(format!("“{}” → “{}”", self.find, self.replace));
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                ui.horizontal(|ui| {
+                    let included = self.changes.iter().filter(|change| change.include);
+                    let (files, matches) = included.fold((0, 0), |(files, matches), change| {
+                        (files + 1, matches + change.matches)
+                    });
+                    if ui
+                        .add_enabled(
+                            files > 0,
+                            egui::Button::new(format!(
+                                "Replace {} match(es) in {} note(s)",
+                                matches, files
+                            )),
+                        )
+                        .clicked()
+                    {
+                        action = Some(ReplaceAction::Apply);
+                    }
+                    if ui.button("⟳ Search again").clicked() {
+                        action = Some(ReplaceAction::Rescan);
+                    }
+                    if !self.written.is_empty()
+                        && ui
+                            .button("↶ Undo")
+                            .on_hover_text(
+                                "Write back the closed notes changed by the last replace",
+                            )
+                            .clicked()
+                    {
+                        action = Some(ReplaceAction::Undo);
+                    }
+                });
+                ui.separator();
+                if self.changes.is_empty() {
+                    ui.label("No matches in the notes folder");
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for change in &mut self.changes {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut change.include, "")
+                                    .on_hover_text("Include this note");
+                                egui::CollapsingHeader::new(format!(
+                                    "{} ({})",
+                                    change.name, change.matches
+                                ))
+                                .id_salt(&change.path)
+                                .show(ui, |ui| {
+                                    for line in &change.lines {
+                                        ui.label(
+                                            RichText::new(format!(
+                                                "{}: {}",
+                                                line.line, line.before
+                                            ))
+                                            .strikethrough()
+                                            .color(Color32::from_rgb(0xb0, 0x40, 0x40)),
+                                        );
+                                        ui.label(
+                                            RichText::new(format!("{}: {}", line.line, line.after))
+                                                .color(Color32::from_rgb(0x2e, 0x7d, 0x32)),
+                                        );
+                                    }
+                                });
+                            });
+                        }
+                    });
+            });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_and_replacements() {
+        let lines = preview("one cat\nno match\n  two cats  ", "cat", "dog");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].line, 3);
+        assert_eq!(lines[1].before, "two cats");
+        assert_eq!(lines[1].after, "two dogs");
+
+        let mut doc = Document::new(0);
+        doc.text_content = "é cat, cat".to_string();
+        assert_eq!(replace_in(&mut doc, "cat", "lion"), 2);
+        assert_eq!(doc.text_content, "é lion, lion");
+        doc.undo();
+        assert_eq!(doc.text_content, "é cat, cat");
+    }
+}
@@ -0,0 +1,109 @@
+use egui::{FontData, FontDefinitions, FontFamily, FontId};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Names of the families egui ships with, as shown in the font menu
+pub const BUILT_IN: [&str; 3] = ["Monospace", "Proportional", "Emoji"];
+
+pub fn font_id(family: &str, size: f32) -> FontId {
+    match family {
+        "Monospace" => FontId::monospace(size),
+        "Proportional" | "Emoji" => FontId::proportional(size),
+        name => FontId::new(size, FontFamily::Name(name.into())),
+    }
+}
+
+// Fonts loaded from files, registered with egui on top of its defaults
+#[derive(Default)]
+pub struct CustomFonts {
+    definitions: Option<FontDefinitions>,
+    pub loaded: Vec<(String, PathBuf)>, // Family name and the file it came from
+}
+
+impl CustomFonts {
+    // Register the font file as a family named after the file, returning
+    // that name for the font menu
+    pub fn load(&mut self, ctx: &egui::Context, path: &Path) -> Result<String, String> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .ok_or_else(|| "Invalid font file name".to_string())?;
+        if self.loaded.iter().any(|(loaded, _)| *loaded == name) {
+            return Ok(name);
+        }
+        let bytes = fs::read(path).map_err(|e| format!("Error reading font: {}", e))?;
+        // Reject files egui cannot parse before they reach the font atlas
+        ab_glyph::FontRef::try_from_slice(&bytes)
+            .map_err(|_| format!("{} is not a supported font", path.display()))?;
+
+        let definitions = self
+            .definitions
+            .get_or_insert_with(FontDefinitions::default);
+        definitions
+            .font_data
+            .insert(name.clone(), FontData::from_owned(bytes));
+        // Fall back to the default fonts for glyphs the file lacks
+        let mut family = vec![name.clone()];
+        family.extend(
+            definitions
+                .families
+                .get(&FontFamily::Proportional)
+                .cloned()
+                .unwrap_or_default(),
+        );
+        definitions
+            .families
+            .insert(FontFamily::Name(name.as_str().into()), family);
+        ctx.set_fonts(definitions.clone());
+
+        self.loaded.push((name.clone(), path.to_path_buf()));
+        Ok(name)
+    }
+}
+
+// .ttf and .otf files in the usual system and user font folders
+pub fn system_fonts() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+        PathBuf::from("/System/Library/Fonts"),
+        PathBuf::from("/Library/Fonts"),
+        PathBuf::from("C:\\Windows\\Fonts"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+    }
+
+    let mut fonts = Vec::new();
+    for dir in dirs {
+        collect_fonts(&dir, &mut fonts);
+    }
+    fonts.sort_by_key(|path| path.file_stem().map(|stem| stem.to_ascii_lowercase()));
+    fonts.dedup();
+    fonts
+}
+
+fn collect_fonts(dir: &Path, fonts: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            collect_fonts(&path, fonts);
+        } else if is_font_file(&path) {
+            fonts.push(path);
+        }
+    }
+}
+
+pub fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ttf") || ext.eq_ignore_ascii_case("otf"))
+}
@@ -0,0 +1,321 @@
+// Markdown footnotes: "[^1]" references in the text and "[^1]: Note" lines
+// kept in a section at the end of the document
+use std::collections::HashMap;
+use std::ops::Range;
+
+// A "[^label]" in the text: its byte range and label
+#[derive(Debug, PartialEq)]
+pub struct Reference {
+    pub range: Range<usize>,
+    pub label: String,
+}
+
+// A "[^label]: note" line with any indented lines continuing it
+#[derive(Debug)]
+struct Definition {
+    lines: Range<usize>,  // Whole lines, with the last line break
+    marker: Range<usize>, // "[^label]:"
+    label: String,
+    body: String, // The note after the marker; continuation lines keep their indent
+}
+
+// Text changes that renumber the footnotes and rewrite the section at the end
+#[derive(Debug, Default)]
+pub struct Renumbering {
+    pub edits: Vec<(Range<usize>, String)>, // Byte ranges of the old text, in order
+    pub caret_from_end: Option<usize>,      // Characters after the new note's caret
+}
+
+// Byte length of the "[^label]" at the start of `text`, and the label
+fn marker(text: &str) -> Option<(usize, &str)> {
+    let rest = text.strip_prefix("[^")?;
+    let close = rest.find(|c: char| c == ']' || c == '[' || c.is_whitespace())?;
+    (close > 0 && rest[close..].starts_with(']')).then(|| (close + 3, &rest[..close]))
+}
+
+// Every reference, leaving out the markers that start definitions
+pub fn references(text: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find("[^").map(|i| from + i) {
+        match marker(&text[start..]) {
+            Some((len, label)) => {
+                let end = start + len;
+                let line_start = start == 0 || text[..start].ends_with('\n');
+                if !(line_start && text[end..].starts_with(':')) {
+                    references.push(Reference {
+                        range: start..end,
+                        label: label.to_string(),
+                    });
+                }
+                from = end;
+            }
+            None => from = start + 2,
+        }
+    }
+    references
+}
+
+fn definitions(text: &str) -> Vec<Definition> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+        if let Some((len, label)) =
+            marker(content).filter(|(len, _)| content[*len..].starts_with(':'))
+        {
+            definitions.push(Definition {
+                lines: start..line_start,
+                marker: start..start + len + 1,
+                label: label.to_string(),
+                body: content[len + 1..].trim_start().to_string(),
+            });
+        } else if let Some(last) = definitions.last_mut().filter(|last| {
+            last.lines.end == start
+                && (content.starts_with("    ") || content.starts_with('\t'))
+                && !content.trim().is_empty()
+        }) {
+            last.lines.end = line_start;
+            last.body.push('\n');
+            last.body.push_str(content);
+        }
+    }
+    definitions
+}
+
+// Replace the labels of the references in `text`
+fn relabel_references(text: &str, relabel: impl Fn(&str) -> String) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for reference in references(text) {
+        result.push_str(&text[last..reference.range.start]);
+        result.push_str(&format!("[^{}]", relabel(&reference.label)));
+        last = reference.range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+// Add a reference at a byte offset with the next number and an empty note;
+// None when the offset is inside the notes themselves
+pub fn insert(text: &str, at: usize) -> Option<Renumbering> {
+    rebuild(text, Some(at))
+}
+
+// Number the references in order and gather every note at the end
+pub fn renumber(text: &str) -> Renumbering {
+    rebuild(text, None).unwrap_or_default()
+}
+
+fn rebuild(text: &str, insert_at: Option<usize>) -> Option<Renumbering> {
+    let definitions = definitions(text);
+    // Notes with nothing but blank lines after them make up the section
+    let mut section_start = text.len();
+    for definition in definitions.iter().rev() {
+        if !text[definition.lines.end..section_start].trim().is_empty() {
+            break;
+        }
+        section_start = definition.lines.start;
+    }
+    let has_section = section_start < text.len() && !text[section_start..].trim().is_empty();
+    let content_end = text[..section_start].trim_end().len();
+    let in_definition = |pos: usize| {
+        definitions
+            .iter()
+            .any(|definition| definition.lines.contains(&pos))
+    };
+
+    let references: Vec<Reference> = references(text)
+        .into_iter()
+        .filter(|reference| !in_definition(reference.range.start))
+        .collect();
+    let insert_at = match insert_at {
+        Some(at) if in_definition(at) || (has_section && at >= section_start) => return None,
+        // Not inside a reference, nor in the blank lines before the notes
+        Some(at) => Some(
+            references
+                .iter()
+                .find(|reference| reference.range.start < at && at < reference.range.end)
+                .map_or(at, |reference| reference.range.end)
+                .min(content_end),
+        ),
+        None => None,
+    };
+
+    // Labels in the order they are first referenced, the new one included;
+    // numeric labels are numbered in that order and named ones kept
+    let mut order: Vec<Option<&str>> = Vec::new();
+    for reference in &references {
+        if insert_at.is_some_and(|at| at <= reference.range.start) && !order.contains(&None) {
+            order.push(None);
+        }
+        if !order.contains(&Some(reference.label.as_str())) {
+            order.push(Some(&reference.label));
+        }
+    }
+    if insert_at.is_some() && !order.contains(&None) {
+        order.push(None);
+    }
+    for definition in &definitions {
+        if !order.contains(&Some(definition.label.as_str())) {
+            order.push(Some(&definition.label));
+        }
+    }
+    let is_numbered = |label: &str| label.parse::<u32>().is_ok();
+    let mut numbers: HashMap<Option<&str>, usize> = HashMap::new();
+    for &label in &order {
+        if label.is_none_or(is_numbered) {
+            numbers.insert(label, numbers.len() + 1);
+        }
+    }
+    let relabel = |label: &str| {
+        numbers
+            .get(&Some(label))
+            .map_or(label.to_string(), usize::to_string)
+    };
+
+    let mut edits = Vec::new();
+    for reference in &references {
+        let label = relabel(&reference.label);
+        if label != reference.label {
+            edits.push((reference.range.clone(), format!("[^{}]", label)));
+        }
+    }
+    let new_label = numbers.get(&None).map(usize::to_string);
+    if let (Some(at), Some(label)) = (insert_at, &new_label) {
+        edits.push((at..at, format!("[^{}]", label)));
+    }
+    for definition in &definitions {
+        if definition.lines.start < section_start {
+            edits.push((definition.lines.clone(), String::new()));
+        }
+    }
+
+    // The section: notes in reference order, the new one empty
+    let mut notes: Vec<(usize, String)> = definitions
+        .iter()
+        .map(|definition| {
+            let position = order
+                .iter()
+                .position(|&label| label == Some(definition.label.as_str()));
+            let body = relabel_references(&definition.body, relabel);
+            let note = format!("[^{}]: {}", relabel(&definition.label), body);
+            (position.unwrap_or(order.len()), note)
+        })
+        .collect();
+    if let Some(label) = &new_label {
+        let position = order.iter().position(Option::is_none).unwrap_or(0);
+        notes.push((position, format!("[^{}]: ", label)));
+    }
+    notes.sort_by_key(|(position, _)| *position);
+    let mut caret_from_end = None;
+    if !notes.is_empty() {
+        let mut section = if content_end == 0 {
+            String::new()
+        } else {
+            "\n\n".to_string()
+        };
+        let new_note = new_label.map(|label| format!("[^{}]: ", label));
+        let mut caret = None;
+        for (i, (_, note)) in notes.iter().enumerate() {
+            if i > 0 {
+                section.push('\n');
+            }
+            section.push_str(note);
+            if new_note.as_ref() == Some(note) {
+                caret = Some(section.len());
+            }
+        }
+        if text.ends_with('\n') {
+            section.push('\n');
+        }
+        caret_from_end = caret.map(|caret| section[caret..].chars().count());
+        if section != text[content_end..] {
+            edits.push((content_end..text.len(), section));
+        }
+    }
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    Some(Renumbering {
+        edits,
+        caret_from_end,
+    })
+}
+
+// Where a click on a footnote leads: from a reference to the start of its
+// note, and from a note's marker back to the first reference to it
+pub fn jump_target(text: &str, pos: usize) -> Option<usize> {
+    let definitions = definitions(text);
+    if let Some(definition) = definitions
+        .iter()
+        .find(|definition| definition.marker.contains(&pos))
+    {
+        return references(text)
+            .into_iter()
+            .find(|reference| reference.label == definition.label)
+            .map(|reference| reference.range.start);
+    }
+    let reference = references(text)
+        .into_iter()
+        .find(|reference| reference.range.contains(&pos))?;
+    definitions
+        .iter()
+        .find(|definition| definition.label == reference.label)
+        .map(|definition| {
+            let after = &text[definition.marker.end..definition.lines.end];
+            definition.marker.end + after.len() - after.trim_start_matches([' ', '\t']).len()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, renumbering: &Renumbering) -> String {
+        let mut text = text.to_string();
+        for (range, replacement) in renumbering.edits.iter().rev() {
+            text.replace_range(range.clone(), replacement);
+        }
+        text
+    }
+
+    #[test]
+    fn insert_numbers_in_order() {
+        let text = "One[^1] two three[^2].\n\n[^1]: First.\n[^2]: Second.\n";
+        let at = text.find(" three").unwrap();
+        let renumbering = insert(text, at).unwrap();
+        let result = apply(text, &renumbering);
+        assert_eq!(
+            result,
+            "One[^1] two[^2] three[^3].\n\n[^1]: First.\n[^2]: \n[^3]: Second.\n"
+        );
+        let caret = result.len() - renumbering.caret_from_end.unwrap();
+        assert_eq!(&result[caret..], "\n[^3]: Second.\n");
+
+        let first = insert("No notes yet", 2).unwrap();
+        assert_eq!(apply("No notes yet", &first), "No[^1] notes yet\n\n[^1]: ");
+        assert!(insert(text, text.find("First").unwrap()).is_none());
+    }
+
+    #[test]
+    fn renumber_gathers_notes_at_the_end() {
+        let text = "A[^3] b[^note] c[^1].\n[^1]: One, see [^3].\n\nMore.\n\n[^3]: Three\n    continued.\n[^note]: Named.";
+        let result = apply(text, &renumber(text));
+        assert_eq!(
+            result,
+            "A[^1] b[^note] c[^2].\n\nMore.\n\n[^1]: Three\n    continued.\n[^note]: Named.\n[^2]: One, see [^1]."
+        );
+        assert!(renumber(&result).edits.is_empty());
+    }
+
+    #[test]
+    fn jump_between_reference_and_note() {
+        let text = "See[^1].\n\n[^1]:  The note.";
+        let note = jump_target(text, text.find("[^1]").unwrap() + 1).unwrap();
+        assert!(text[note..].starts_with("The note"));
+        let back = jump_target(text, text.rfind("[^1]").unwrap()).unwrap();
+        assert_eq!(back, 3);
+        assert_eq!(jump_target(text, 0), None);
+    }
+}
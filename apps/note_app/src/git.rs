@@ -0,0 +1,419 @@
+// Git support for a notes folder inside a repository: status markers for the
+// sidebar, committing the folder's changes, and browsing a note's revisions
+use crate::document::Document;
+use crate::{encoding, history, rtxt};
+use egui::Color32;
+use git2::{IndexAddOption, Oid, Repository, Signature, Sort, Status, StatusOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoteStatus {
+    New,      // Not tracked yet
+    Modified, // Changed since the last commit and not staged
+    Staged,   // Changes staged for the next commit
+    Conflicted,
+}
+
+impl NoteStatus {
+    pub fn marker(self) -> &'static str {
+        match self {
+            NoteStatus::New => "?",
+            NoteStatus::Modified => "M",
+            NoteStatus::Staged => "+",
+            NoteStatus::Conflicted => "!",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NoteStatus::New => "Not committed yet",
+            NoteStatus::Modified => "Changed since the last commit",
+            NoteStatus::Staged => "Changes staged for the next commit",
+            NoteStatus::Conflicted => "Merge conflict",
+        }
+    }
+
+    pub fn color(self) -> Color32 {
+        match self {
+            NoteStatus::New => Color32::from_rgb(0x2e, 0x8b, 0x57),
+            NoteStatus::Modified => Color32::from_rgb(0xd2, 0x8c, 0x00),
+            NoteStatus::Staged => Color32::from_rgb(0x1e, 0x6f, 0xd9),
+            NoteStatus::Conflicted => Color32::RED,
+        }
+    }
+}
+
+// Unstaged changes win over staged ones, as they are what still needs doing
+fn note_status(status: Status) -> Option<NoteStatus> {
+    if status.is_conflicted() {
+        Some(NoteStatus::Conflicted)
+    } else if status.is_wt_new() {
+        Some(NoteStatus::New)
+    } else if status.intersects(Status::WT_MODIFIED | Status::WT_RENAMED | Status::WT_TYPECHANGE) {
+        Some(NoteStatus::Modified)
+    } else if status.intersects(
+        Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE,
+    ) {
+        Some(NoteStatus::Staged)
+    } else {
+        None
+    }
+}
+
+fn git_error(e: git2::Error) -> String {
+    e.message().to_string()
+}
+
+// A folder name for a pathspec, with the wildcard characters escaped so a
+// folder called `[drafts]` matches only itself
+fn literal_pathspec(folder: &str) -> String {
+    let mut escaped = String::with_capacity(folder.len());
+    for c in folder.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// A commit that changed a note
+pub struct Revision {
+    pub id: Oid,
+    pub time: u64, // Seconds since the Unix epoch
+    pub summary: String,
+    pub author: String,
+}
+
+pub struct NotesRepo {
+    repo: Repository,
+}
+
+impl NotesRepo {
+    // The repository containing the folder, if any
+    pub fn discover(dir: &Path) -> Option<Self> {
+        let repo = Repository::discover(dir).ok()?;
+        repo.workdir()?;
+        Some(Self { repo })
+    }
+
+    // A path relative to the repository, as git names it
+    fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let workdir = self.repo.workdir()?.canonicalize().ok()?;
+        let path = path.canonicalize().ok()?;
+        path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+    }
+
+    // Status of every changed file, by absolute path
+    pub fn status(&self) -> HashMap<PathBuf, NoteStatus> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        let (Some(workdir), Ok(statuses)) =
+            (self.repo.workdir(), self.repo.statuses(Some(&mut options)))
+        else {
+            return HashMap::new();
+        };
+        statuses
+            .iter()
+            .filter_map(|entry| Some((workdir.join(entry.path()?), note_status(entry.status())?)))
+            .collect()
+    }
+
+    // Stage every change in the folder, including deletions, and commit it
+    pub fn commit_folder(&self, dir: &Path, message: &str) -> Result<(), String> {
+        let relative = self
+            .relative(dir)
+            .ok_or("The notes folder is not inside the repository")?;
+        let pathspec = match relative.to_string_lossy().replace('\\', "/") {
+            folder if folder.is_empty() => "*".to_string(),
+            folder => format!("{}/*", literal_pathspec(&folder)),
+        };
+        let mut index = self.repo.index().map_err(git_error)?;
+        index
+            .add_all([&pathspec], IndexAddOption::DEFAULT, None)
+            .map_err(git_error)?;
+        index.update_all([&pathspec], None).map_err(git_error)?;
+        index.write().map_err(git_error)?;
+        let tree = self
+            .repo
+            .find_tree(index.write_tree().map_err(git_error)?)
+            .map_err(git_error)?;
+        let parent = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        if parent
+            .as_ref()
+            .is_some_and(|parent| parent.tree_id() == tree.id())
+        {
+            return Err("There are no changes to commit".to_string());
+        }
+        // Fall back to a generic name when git has no user configured
+        let signature = self
+            .repo
+            .signature()
+            .or_else(|_| Signature::now("Notes", "notes@localhost"))
+            .map_err(git_error)?;
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .map(|_| ())
+            .map_err(git_error)
+    }
+
+    // Commits that changed the file, newest first. The walk reads the whole
+    // history, so it runs on its own thread with its own handle on the
+    // repository, and the result arrives on the receiver
+    pub fn revisions(
+        &self,
+        ctx: &egui::Context,
+        path: &Path,
+    ) -> Result<Receiver<Result<Vec<Revision>, String>>, String> {
+        let relative = self
+            .relative(path)
+            .ok_or("The note is not inside the repository")?;
+        let git_dir = self.repo.path().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let revisions = Repository::open(git_dir)
+                .map_err(git_error)
+                .and_then(|repo| file_revisions(&repo, &relative));
+            let _ = sender.send(revisions);
+            ctx.request_repaint();
+        });
+        Ok(receiver)
+    }
+
+    // The note as it was in a commit
+    pub fn load_revision(&self, id: Oid, path: &Path) -> Result<Document, String> {
+        let relative = self
+            .relative(path)
+            .ok_or("The note is not inside the repository")?;
+        let tree = self
+            .repo
+            .find_commit(id)
+            .and_then(|commit| commit.tree())
+            .map_err(git_error)?;
+        let blob = tree
+            .get_path(&relative)
+            .and_then(|entry| entry.to_object(&self.repo))
+            .and_then(|object| object.peel_to_blob())
+            .map_err(git_error)?;
+        let mut doc = Document::new(0);
+        let (content, encoding) = encoding::decode(blob.content());
+        doc.encoding = encoding;
+        rtxt::read(&mut doc, &content)?;
+        Ok(doc)
+    }
+}
+
+fn file_revisions(repo: &Repository, relative: &Path) -> Result<Vec<Revision>, String> {
+    let mut walk = repo.revwalk().map_err(git_error)?;
+    if walk.push_head().is_err() {
+        return Ok(Vec::new()); // No commits yet
+    }
+    walk.set_sorting(Sort::TIME).map_err(git_error)?;
+    let blob_id = |commit: &git2::Commit| {
+        let tree = commit.tree().ok()?;
+        let entry = tree.get_path(relative).ok()?;
+        Some(entry.id())
+    };
+    let mut revisions = Vec::new();
+    for id in walk {
+        let commit = repo
+            .find_commit(id.map_err(git_error)?)
+            .map_err(git_error)?;
+        let blob = blob_id(&commit);
+        let parent_blob = commit.parent(0).ok().and_then(|parent| blob_id(&parent));
+        if blob.is_some() && blob != parent_blob {
+            revisions.push(Revision {
+                id: commit.id(),
+                time: u64::try_from(commit.time().seconds()).unwrap_or(0),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    Ok(revisions)
+}
+
+// Window listing the commits that changed a note
+pub struct GitHistory {
+    pub doc_id: u64,
+    path: PathBuf,
+    revisions: Vec<Revision>,
+    loading: Option<Receiver<Result<Vec<Revision>, String>>>, // While the history is read
+    selected: Option<usize>,
+    preview: Option<Document>,
+    error: Option<String>,
+}
+
+impl GitHistory {
+    pub fn open(ctx: &egui::Context, repo: &NotesRepo, doc: &Document) -> Result<Self, String> {
+        let path = doc
+            .file_path
+            .clone()
+            .ok_or_else(|| "Save the document to see its git history".to_string())?;
+        Ok(Self {
+            doc_id: doc.id,
+            loading: Some(repo.revisions(ctx, &path)?),
+            revisions: Vec::new(),
+            path,
+            selected: None,
+            preview: None,
+            error: None,
+        })
+    }
+
+    // Show the window; returns the revision to restore when the user asks for
+    // it, and sets `open` to false when the window is closed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        repo: &NotesRepo,
+        doc: &Document,
+        open: &mut bool,
+    ) -> Option<Document> {
+        self.poll_revisions();
+        let mut restore = false;
+        let mut select = None;
+        let now = history::now();
+        egui::Window::new(format!("Git History: {}", doc.title()))
+            .id(egui::Id::new("git_history"))
+            .open(open)
+            .default_size([700.0, 450.0])
+            .show(ctx, |ui| {
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if self.loading.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Reading the history...");
+                    });
+                    return;
+                }
+                if self.revisions.is_empty() {
+                    ui.label("This note has not been committed yet");
+                    return;
+                }
+                ui.horizontal_top(|ui| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("revisions")
+                        .max_width(220.0)
+                        .show(ui, |ui| {
+                            for (index, revision) in self.revisions.iter().enumerate() {
+                                let label = format!(
+                                    "{}\n{}",
+                                    revision.summary,
+                                    history::age(revision.time, now)
+                                );
+                                if ui
+                                    .selectable_label(self.selected == Some(index), label)
+                                    .on_hover_text(format!(
+                                        "{} by {}",
+                                        &revision.id.to_string()[..7],
+                                        revision.author
+                                    ))
+                                    .clicked()
+                                {
+                                    select = Some(index);
+                                }
+                            }
+                        });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        let Some(preview) = &self.preview else {
+                            ui.label("Pick a commit to preview the note as it was");
+                            return;
+                        };
+                        restore = ui
+                            .button("↶ Restore this version")
+                            .on_hover_text("Replace the text with this revision (undoable)")
+                            .clicked();
+                        egui::ScrollArea::vertical()
+                            .id_salt("revision_preview")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new(&preview.text_content).monospace());
+                            });
+                    });
+                });
+            });
+
+        if let Some(index) = select {
+            match repo.load_revision(self.revisions[index].id, &self.path) {
+                Ok(preview) => {
+                    self.selected = Some(index);
+                    self.preview = Some(preview);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if restore {
+            self.selected = None;
+            return self.preview.take();
+        }
+        None
+    }
+
+    fn poll_revisions(&mut self) {
+        let Some(loading) = &self.loading else {
+            return;
+        };
+        match loading.try_recv() {
+            Ok(Ok(revisions)) => self.revisions = revisions,
+            Ok(Err(e)) => self.error = Some(e),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.error = Some("Reading the history stopped unexpectedly".to_string());
+            }
+        }
+        self.loading = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_markers() {
+        assert_eq!(note_status(Status::WT_NEW), Some(NoteStatus::New));
+        assert_eq!(note_status(Status::WT_MODIFIED), Some(NoteStatus::Modified));
+        assert_eq!(note_status(Status::INDEX_NEW), Some(NoteStatus::Staged));
+        // Staged, then edited again
+        assert_eq!(
+            note_status(Status::INDEX_MODIFIED | Status::WT_MODIFIED),
+            Some(NoteStatus::Modified)
+        );
+        assert_eq!(
+            note_status(Status::CONFLICTED),
+            Some(NoteStatus::Conflicted)
+        );
+        assert_eq!(note_status(Status::CURRENT), None);
+        assert_eq!(note_status(Status::IGNORED), None);
+    }
+
+    #[test]
+    fn folder_names_are_not_wildcards() {
+        assert_eq!(literal_pathspec("notes/work"), "notes/work");
+        assert_eq!(literal_pathspec("[drafts]"), "\\[drafts\\]");
+        assert_eq!(literal_pathspec("what?*"), "what\\?\\*");
+    }
+}
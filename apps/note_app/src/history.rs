@@ -0,0 +1,284 @@
+// Timestamped snapshots of saved documents, kept in the app data folder, and
+// the history window for browsing and restoring them
+use crate::document::Document;
+use egui::Color32;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Records which document a snapshot folder belongs to
+const SOURCE_FILE: &str = "source.txt";
+
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub time: u64, // Seconds since the Unix epoch
+    pub size: u64,
+}
+
+// How many snapshots are kept per document
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub max_count: usize,
+    pub max_bytes: u64,
+}
+
+// One folder per document file, named after a hash of its path
+pub fn history_dir(doc_path: &Path) -> Option<PathBuf> {
+    let dir = eframe::storage_dir(crate::APP_ID)?.join("history");
+    Some(dir.join(format!("{:016x}", path_hash(doc_path))))
+}
+
+fn path_hash(path: &Path) -> u64 {
+    fnv1a(path.to_string_lossy().as_bytes())
+}
+
+// FNV-1a, so hashes stay the same across builds
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Snapshots of the document file, newest first
+pub fn list(doc_path: &Path) -> Vec<Snapshot> {
+    let Some(Ok(entries)) = history_dir(doc_path).map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let time = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".rtxt")?
+                .parse()
+                .ok()?;
+            let size = entry.metadata().ok()?.len();
+            Some(Snapshot { path, time, size })
+        })
+        .collect();
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.time));
+    snapshots
+}
+
+// Write a snapshot of a document that has a file, unless it matches the
+// newest one. Returns whether a snapshot was written
+pub fn snapshot(doc: &Document, limits: Limits) -> Result<bool, String> {
+    let Some(doc_path) = &doc.file_path else {
+        return Ok(false);
+    };
+    let Some(dir) = history_dir(doc_path) else {
+        return Err("No data directory for version history".to_string());
+    };
+    let snapshots = list(doc_path);
+    if let Some(newest) = snapshots.first() {
+        let mut previous = Document::new(0);
+        if previous.load_with_formatting(&newest.path).is_ok()
+            && previous.text_content == doc.text_content
+            && previous.styled_ranges == doc.styled_ranges
+        {
+            return Ok(false);
+        }
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating history folder: {}", e))?;
+    let _ = fs::write(dir.join(SOURCE_FILE), doc_path.display().to_string());
+    doc.save_with_formatting(&dir.join(format!("{}.rtxt", now())))?;
+
+    for old in expired(&list(doc_path), limits) {
+        let _ = fs::remove_file(&old.path);
+    }
+    Ok(true)
+}
+
+// Snapshots (newest first) beyond the count or total size limit; the newest
+// one is always kept
+fn expired(snapshots: &[Snapshot], limits: Limits) -> &[Snapshot] {
+    let mut total = 0;
+    let keep = snapshots
+        .iter()
+        .take(limits.max_count.max(1))
+        .take_while(|snapshot| {
+            total += snapshot.size;
+            total <= limits.max_bytes
+        })
+        .count()
+        .max(1);
+    &snapshots[keep.min(snapshots.len())..]
+}
+
+// "5 min ago" style label for a snapshot time
+pub fn age(time: u64, now: u64) -> String {
+    let seconds = now.saturating_sub(time);
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+pub struct HistoryBrowser {
+    pub doc_id: u64,
+    snapshots: Vec<Snapshot>,
+    selected: Option<usize>,
+    preview: Option<Document>,
+    error: Option<String>,
+}
+
+impl HistoryBrowser {
+    pub fn open(doc: &Document) -> Result<Self, String> {
+        let path = doc
+            .file_path
+            .as_ref()
+            .ok_or_else(|| "Save the document to start its version history".to_string())?;
+        Ok(Self {
+            doc_id: doc.id,
+            snapshots: list(path),
+            selected: None,
+            preview: None,
+            error: None,
+        })
+    }
+
+    // Show the window; returns the snapshot to restore when the user asks for
+    // it, and sets `open` to false when the window is closed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        doc: &Document,
+        open: &mut bool,
+    ) -> Option<Document> {
+        let mut restore = false;
+        let mut select = None;
+        let now = now();
+        egui::Window::new(format!("History: {}", doc.title()))
+            .id(egui::Id::new("version_history"))
+            .open(open)
+            .default_size([650.0, 450.0])
+            .show(ctx, |ui| {
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if self.snapshots.is_empty() {
+                    ui.label("No snapshots yet; one is taken on every save");
+                    return;
+                }
+                ui.horizontal_top(|ui| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("snapshots")
+                        .max_width(160.0)
+                        .show(ui, |ui| {
+                            for (index, snapshot) in self.snapshots.iter().enumerate() {
+                                if ui
+                                    .selectable_label(
+                                        self.selected == Some(index),
+                                        age(snapshot.time, now),
+                                    )
+                                    .on_hover_text(format!("{} bytes", snapshot.size))
+                                    .clicked()
+                                {
+                                    select = Some(index);
+                                }
+                            }
+                        });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        let Some(preview) = &self.preview else {
+                            ui.label("Pick a snapshot to preview it");
+                            return;
+                        };
+                        restore = ui
+                            .button("↶ Restore this version")
+                            .on_hover_text("Replace the text with this snapshot (undoable)")
+                            .clicked();
+                        egui::ScrollArea::vertical()
+                            .id_salt("snapshot_preview")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new(&preview.text_content).monospace());
+                            });
+                    });
+                });
+            });
+
+        if let Some(index) = select {
+            let mut preview = Document::new(0);
+            match preview.load_with_formatting(&self.snapshots[index].path) {
+                Ok(()) => {
+                    self.selected = Some(index);
+                    self.preview = Some(preview);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if restore {
+            self.selected = None;
+            return self.preview.take();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshots(sizes: &[u64]) -> Vec<Snapshot> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(index, &size)| Snapshot {
+                path: PathBuf::from(format!("{}.rtxt", 100 - index)),
+                time: 100 - index as u64,
+                size,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn oldest_snapshots_expire_past_the_limits() {
+        let all = snapshots(&[10, 10, 10, 10]);
+        let by_count = Limits {
+            max_count: 3,
+            max_bytes: 1000,
+        };
+        assert_eq!(expired(&all, by_count).len(), 1);
+        assert_eq!(expired(&all, by_count)[0].time, 97);
+
+        let by_size = Limits {
+            max_count: 10,
+            max_bytes: 25,
+        };
+        assert_eq!(expired(&all, by_size).len(), 2);
+
+        // The newest snapshot survives even when it alone is too large
+        let large = snapshots(&[500, 10]);
+        assert_eq!(expired(&large, by_size).len(), 1);
+        assert_eq!(expired(&large, by_size)[0].time, 99);
+    }
+
+    #[test]
+    fn ages_and_folder_names() {
+        assert_eq!(age(1000, 1030), "just now");
+        assert_eq!(age(1000, 1000 + 150), "2 min ago");
+        assert_eq!(age(1000, 1000 + 7200), "2 h ago");
+        assert_eq!(age(1000, 1000 + 3 * 86400), "3 days ago");
+        assert_eq!(
+            path_hash(Path::new("/notes/a.rtxt")),
+            path_hash(Path::new("/notes/a.rtxt"))
+        );
+        assert_ne!(
+            path_hash(Path::new("/notes/a.rtxt")),
+            path_hash(Path::new("/notes/b.rtxt"))
+        );
+    }
+}
@@ -0,0 +1,415 @@
+// Keyboard shortcuts for editor commands, rebindable in the settings
+use egui::{Color32, Event, Key, Modifiers};
+use gui_common::Labeled;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Command {
+    Undo,
+    Redo,
+    Find,
+    GoTo,
+    Indent,
+    Outdent,
+    DuplicateLines,
+    DeleteLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    NextTab,
+    PreviousTab,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Complete,
+    FocusMode,
+    ReadAloud,
+    AlignTable,
+    Preview,
+    Footnote,
+    ExtractNote,
+    OpenLinks,
+    Calculate,
+    RecordMacro,
+    PlayMacro,
+    MenuBar,
+    ShowLog,
+}
+
+impl Command {
+    pub const ALL: [Command; 28] = [
+        Command::Undo,
+        Command::Redo,
+        Command::Find,
+        Command::GoTo,
+        Command::Indent,
+        Command::Outdent,
+        Command::DuplicateLines,
+        Command::DeleteLines,
+        Command::MoveLinesUp,
+        Command::MoveLinesDown,
+        Command::NextTab,
+        Command::PreviousTab,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ZoomReset,
+        Command::Complete,
+        Command::FocusMode,
+        Command::ReadAloud,
+        Command::AlignTable,
+        Command::Preview,
+        Command::Footnote,
+        Command::ExtractNote,
+        Command::OpenLinks,
+        Command::Calculate,
+        Command::RecordMacro,
+        Command::PlayMacro,
+        Command::MenuBar,
+        Command::ShowLog,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::Find => "Find & Replace",
+            Command::GoTo => "Go to line",
+            Command::Indent => "Indent",
+            Command::Outdent => "Outdent",
+            Command::DuplicateLines => "Duplicate line(s)",
+            Command::DeleteLines => "Delete line(s)",
+            Command::MoveLinesUp => "Move line(s) up",
+            Command::MoveLinesDown => "Move line(s) down",
+            Command::NextTab => "Next tab",
+            Command::PreviousTab => "Previous tab",
+            Command::ZoomIn => "Larger font",
+            Command::ZoomOut => "Smaller font",
+            Command::ZoomReset => "Default font size",
+            Command::Complete => "Complete word",
+            Command::FocusMode => "Focus mode",
+            Command::ReadAloud => "Read aloud",
+            Command::AlignTable => "Align Markdown table",
+            Command::Preview => "Preview",
+            Command::Footnote => "Insert footnote",
+            Command::ExtractNote => "Extract selection to note",
+            Command::OpenLinks => "Open links in the selection",
+            Command::Calculate => "Calculate the selection",
+            Command::RecordMacro => "Start or stop recording a macro",
+            Command::PlayMacro => "Play the last macro",
+            Command::MenuBar => "Move keyboard focus to the menu bar",
+            Command::ShowLog => "Show the log",
+        }
+    }
+
+    // Only run while the editor has focus, so that elsewhere the keys keep
+    // their usual meaning, like Tab moving between fields
+    pub fn needs_editor(self) -> bool {
+        matches!(self, Command::Indent | Command::Outdent | Command::Complete)
+    }
+
+    fn default_shortcut(self) -> Shortcut {
+        let ctrl = |key| Shortcut::new(true, false, false, key);
+        let ctrl_shift = |key| Shortcut::new(true, true, false, key);
+        let alt = |key| Shortcut::new(false, false, true, key);
+        match self {
+            Command::Undo => ctrl(Key::Z),
+            Command::Redo => ctrl(Key::Y),
+            Command::Find => ctrl(Key::F),
+            Command::GoTo => ctrl(Key::G),
+            Command::Indent => Shortcut::new(false, false, false, Key::Tab),
+            Command::Outdent => Shortcut::new(false, true, false, Key::Tab),
+            Command::DuplicateLines => ctrl_shift(Key::D),
+            Command::DeleteLines => ctrl_shift(Key::K),
+            Command::MoveLinesUp => alt(Key::ArrowUp),
+            Command::MoveLinesDown => alt(Key::ArrowDown),
+            Command::NextTab => ctrl(Key::Tab),
+            Command::PreviousTab => ctrl_shift(Key::Tab),
+            Command::ZoomIn => ctrl(Key::Equals),
+            Command::ZoomOut => ctrl(Key::Minus),
+            Command::ZoomReset => ctrl(Key::Num0),
+            Command::Complete => ctrl(Key::Space),
+            Command::FocusMode => Shortcut::new(false, false, false, Key::F11),
+            Command::ReadAloud => ctrl_shift(Key::R),
+            Command::AlignTable => ctrl_shift(Key::T),
+            Command::Preview => ctrl_shift(Key::P),
+            Command::Footnote => Shortcut::new(true, false, true, Key::F),
+            Command::ExtractNote => ctrl_shift(Key::E),
+            Command::OpenLinks => ctrl_shift(Key::O),
+            Command::Calculate => ctrl_shift(Key::Enter),
+            Command::RecordMacro => ctrl_shift(Key::M),
+            Command::PlayMacro => ctrl(Key::M),
+            Command::MenuBar => Shortcut::new(false, false, false, Key::F10),
+            Command::ShowLog => ctrl_shift(Key::L),
+        }
+    }
+}
+
+// A key with modifiers; Ctrl stands for Cmd on macOS. Stored as text such as
+// "Ctrl+Shift+D"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: Key,
+}
+
+impl Shortcut {
+    pub const fn new(ctrl: bool, shift: bool, alt: bool, key: Key) -> Self {
+        Self {
+            ctrl,
+            shift,
+            alt,
+            key,
+        }
+    }
+
+    fn modifiers(self) -> Modifiers {
+        let mut modifiers = Modifiers::NONE;
+        if self.ctrl {
+            modifiers = modifiers | Modifiers::COMMAND;
+        }
+        if self.shift {
+            modifiers = modifiers | Modifiers::SHIFT;
+        }
+        if self.alt {
+            modifiers = modifiers | Modifiers::ALT;
+        }
+        modifiers
+    }
+
+    fn modifier_count(self) -> usize {
+        [self.ctrl, self.shift, self.alt]
+            .into_iter()
+            .filter(|&on| on)
+            .count()
+    }
+
+    fn join(self, key: &str) -> String {
+        let mut parts = Vec::new();
+        if self.ctrl {
+            parts.push("Ctrl");
+        }
+        if self.shift {
+            parts.push("Shift");
+        }
+        if self.alt {
+            parts.push("Alt");
+        }
+        parts.push(key);
+        parts.join("+")
+    }
+
+    // For display, with symbols such as "[" instead of key names
+    pub fn label(self) -> String {
+        self.join(self.key.symbol_or_name())
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(shortcut: Shortcut) -> String {
+        shortcut.join(shortcut.key.name())
+    }
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let mut shortcut = Shortcut::new(false, false, false, Key::A);
+        let (modifiers, key) = text.rsplit_once('+').unwrap_or(("", &text));
+        for modifier in modifiers.split('+').filter(|part| !part.is_empty()) {
+            match modifier {
+                "Ctrl" => shortcut.ctrl = true,
+                "Shift" => shortcut.shift = true,
+                "Alt" => shortcut.alt = true,
+                _ => return Err(format!("Unknown modifier in shortcut \"{}\"", text)),
+            }
+        }
+        shortcut.key =
+            Key::from_name(key).ok_or_else(|| format!("Unknown key in shortcut \"{}\"", text))?;
+        Ok(shortcut)
+    }
+}
+
+// Only bindings that differ from the defaults are stored, so commands added
+// later get their default shortcut
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    changed: BTreeMap<Command, Option<Shortcut>>, // None removes the shortcut
+}
+
+impl Keymap {
+    pub fn shortcut(&self, command: Command) -> Option<Shortcut> {
+        match self.changed.get(&command) {
+            Some(shortcut) => *shortcut,
+            None => Some(command.default_shortcut()),
+        }
+    }
+
+    pub fn set(&mut self, command: Command, shortcut: Option<Shortcut>) {
+        if shortcut == Some(command.default_shortcut()) {
+            self.changed.remove(&command);
+        } else {
+            self.changed.insert(command, shortcut);
+        }
+    }
+
+    // Other commands bound to the same shortcut as `command`
+    pub fn conflicts(&self, command: Command) -> Vec<Command> {
+        let Some(shortcut) = self.shortcut(command) else {
+            return Vec::new();
+        };
+        Command::ALL
+            .into_iter()
+            .filter(|&other| other != command && self.shortcut(other) == Some(shortcut))
+            .collect()
+    }
+
+    // Enabled commands whose shortcut was pressed this frame, consuming the
+    // key presses so the editor does not see them as well
+    pub fn pressed(&self, ctx: &egui::Context, enabled: impl Fn(Command) -> bool) -> Vec<Command> {
+        let mut bindings: Vec<(Command, Shortcut)> = Command::ALL
+            .into_iter()
+            .filter(|&command| enabled(command))
+            .filter_map(|command| Some((command, self.shortcut(command)?)))
+            .collect();
+        // Extra Shift or Alt still match a shortcut, so try Shift+Tab before
+        // Tab
+        bindings.sort_by_key(|(_, shortcut)| std::cmp::Reverse(shortcut.modifier_count()));
+        ctx.input_mut(|i| {
+            bindings
+                .into_iter()
+                .filter(|(_, shortcut)| i.consume_key(shortcut.modifiers(), shortcut.key))
+                .map(|(command, _)| command)
+                .collect()
+        })
+    }
+
+    // Table of commands and their shortcuts. Clicking a shortcut waits for the
+    // next key press, remembered in `capture`, to rebind it
+    pub fn show_editor(&mut self, ui: &mut egui::Ui, capture: &mut Option<Command>) {
+        if let Some(command) = *capture {
+            let pressed = ui.input_mut(|i| {
+                let pressed = i.events.iter().find_map(|event| match event {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                });
+                // Keep the key away from the editor and other widgets
+                i.events
+                    .retain(|event| !matches!(event, Event::Key { .. } | Event::Text(_)));
+                pressed
+            });
+            match pressed {
+                Some((Key::Escape, modifiers)) if modifiers.is_none() => *capture = None,
+                Some((key, modifiers)) => {
+                    let shortcut =
+                        Shortcut::new(modifiers.command, modifiers.shift, modifiers.alt, key);
+                    self.set(command, Some(shortcut));
+                    *capture = None;
+                }
+                None => {}
+            }
+        }
+
+        egui::Grid::new("keymap")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for command in Command::ALL {
+                    ui.label(command.label());
+                    let label = if *capture == Some(command) {
+                        "Press keys…".to_string()
+                    } else {
+                        self.shortcut(command)
+                            .map_or("—".to_string(), Shortcut::label)
+                    };
+                    if ui
+                        .selectable_label(*capture == Some(command), label)
+                        .on_hover_text("Click, then press the new shortcut (Esc cancels)")
+                        .clicked()
+                    {
+                        *capture = Some(command);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button("✖")
+                            .labeled("Remove the shortcut")
+                            .clicked()
+                        {
+                            self.set(command, None);
+                        }
+                        if ui
+                            .small_button("↺")
+                            .labeled("Reset to the default")
+                            .clicked()
+                        {
+                            self.set(command, Some(command.default_shortcut()));
+                        }
+                    });
+                    let conflicts = self.conflicts(command);
+                    if conflicts.is_empty() {
+                        ui.label("");
+                    } else {
+                        let names: Vec<&str> = conflicts.into_iter().map(Command::label).collect();
+                        ui.colored_label(
+                            Color32::RED,
+                            format!("Also used by {}", names.join(", ")),
+                        );
+                    }
+                    ui.end_row();
+                }
+            });
+        if ui.button("Reset all").clicked() {
+            self.changed.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_round_trip_through_text() {
+        let shortcut = Shortcut::new(true, true, false, Key::OpenBracket);
+        let text = String::from(shortcut);
+        assert_eq!(text, "Ctrl+Shift+OpenBracket");
+        assert_eq!(Shortcut::try_from(text), Ok(shortcut));
+        assert_eq!(shortcut.label(), "Ctrl+Shift+[");
+        assert_eq!(
+            Shortcut::try_from("F5".to_string()),
+            Ok(Shortcut::new(false, false, false, Key::F5))
+        );
+        assert!(Shortcut::try_from("Hyper+A".to_string()).is_err());
+        assert!(Shortcut::try_from("Ctrl+Nope".to_string()).is_err());
+    }
+
+    #[test]
+    fn rebinding_and_conflicts() {
+        let mut keymap = Keymap::default();
+        assert!(Command::ALL
+            .into_iter()
+            .all(|command| keymap.conflicts(command).is_empty()));
+
+        let ctrl_f = Shortcut::new(true, false, false, Key::F);
+        keymap.set(Command::GoTo, Some(ctrl_f));
+        assert_eq!(keymap.conflicts(Command::Find), vec![Command::GoTo]);
+        assert_eq!(keymap.conflicts(Command::GoTo), vec![Command::Find]);
+
+        keymap.set(Command::Find, None);
+        assert_eq!(keymap.shortcut(Command::Find), None);
+        assert!(keymap.conflicts(Command::GoTo).is_empty());
+
+        // Setting the default again forgets the change
+        keymap.set(Command::GoTo, Some(Command::GoTo.default_shortcut()));
+        assert_eq!(keymap.changed.len(), 1);
+    }
+}
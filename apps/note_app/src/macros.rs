@@ -0,0 +1,240 @@
+// Keystroke macros: the keys and text typed into the editor, recorded once and
+// played back to repeat an edit
+use egui::{Event, Key};
+use gui_common::Labeled;
+use serde::{Deserialize, Serialize};
+
+// Runs "to the end of the document" stop here, should the caret never get there
+pub const MAX_RUNS: usize = 10_000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub events: Vec<Event>,
+}
+
+// The input events a macro keeps
+fn recordable(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Text(_) | Event::Paste(_) | Event::Key { pressed: true, .. }
+    )
+}
+
+fn is_text(event: &Event) -> bool {
+    matches!(event, Event::Text(_) | Event::Paste(_))
+}
+
+// End of the events handed to the editor in one frame, starting at `from`.
+// Each key gets a frame of its own, so shortcuts and auto-indent, which take
+// their keys before the editor sees the rest, act in order; text runs
+// together unless typing is corrected as it goes
+pub fn next_batch(events: &[Event], from: usize, batch_text: bool) -> usize {
+    if !batch_text || !events.get(from).is_some_and(is_text) {
+        return (from + 1).min(events.len());
+    }
+    from + events[from..]
+        .iter()
+        .take_while(|event| is_text(event))
+        .count()
+}
+
+// A macro being played back
+pub struct Playback {
+    pub events: Vec<Event>,
+    pub position: usize,          // Next event to hand over
+    pub runs_left: Option<usize>, // None to run until the end of the document
+    pub run_start: usize,         // Caret when the current run began
+}
+
+pub enum MacroAction {
+    ToggleRecording,
+    Play(Vec<Event>),
+}
+
+pub struct Macros {
+    pub recording: Option<Vec<Event>>,
+    pub last: Vec<Event>, // The most recent recording
+    pub playback: Option<Playback>,
+    runs: usize,
+    to_end: bool,
+    name: String, // For saving the last recording
+}
+
+impl Default for Macros {
+    fn default() -> Self {
+        Self {
+            recording: None,
+            last: Vec::new(),
+            playback: None,
+            runs: 1,
+            to_end: false,
+            name: String::new(),
+        }
+    }
+}
+
+impl Macros {
+    pub fn record(&mut self, events: &[Event]) {
+        if let Some(recording) = &mut self.recording {
+            recording.extend(events.iter().filter(|event| recordable(event)).cloned());
+        }
+    }
+
+    // Start recording, or stop and keep the recording; `stop_key` is the
+    // shortcut key that stopped it, which is not part of the macro
+    pub fn toggle_recording(&mut self, stop_key: Option<Key>) {
+        match self.recording.take() {
+            Some(mut events) => {
+                if let (Some(Event::Key { key, .. }), Some(stop_key)) = (events.last(), stop_key) {
+                    if *key == stop_key {
+                        events.pop();
+                    }
+                }
+                self.last = events;
+            }
+            None => self.recording = Some(Vec::new()),
+        }
+    }
+
+    pub fn play(&mut self, events: Vec<Event>, caret: usize) {
+        if events.is_empty() || self.recording.is_some() {
+            return;
+        }
+        self.playback = Some(Playback {
+            events,
+            position: 0,
+            runs_left: (!self.to_end).then_some(self.runs.max(1)),
+            run_start: caret,
+        });
+    }
+
+    // Show the window; sets `open` to false when it is closed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        saved: &mut Vec<Macro>,
+        open: &mut bool,
+    ) -> Option<MacroAction> {
+        let mut action = None;
+        egui::Window::new("Macros")
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let label = if self.recording.is_some() {
+                        "⏹ Stop Recording"
+                    } else {
+                        "⏺ Record"
+                    };
+                    if ui.button(label).clicked() {
+                        action = Some(MacroAction::ToggleRecording);
+                    }
+                    match &self.recording {
+                        Some(events) => ui.label(format!("Recording… {} key(s)", events.len())),
+                        None => ui.label(format!("Last recording: {} key(s)", self.last.len())),
+                    };
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Play");
+                    ui.add_enabled(
+                        !self.to_end,
+                        egui::DragValue::new(&mut self.runs)
+                            .range(1..=MAX_RUNS)
+                            .suffix(" time(s)"),
+                    );
+                    ui.checkbox(&mut self.to_end, "or until the end")
+                        .on_hover_text("Until the caret reaches the end or stops moving forward");
+                });
+                let idle = self.recording.is_none() && self.playback.is_none();
+                if ui
+                    .add_enabled(
+                        idle && !self.last.is_empty(),
+                        egui::Button::new("▶ Play Last Recording"),
+                    )
+                    .clicked()
+                {
+                    action = Some(MacroAction::Play(self.last.clone()));
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.name)
+                            .hint_text("Name")
+                            .desired_width(140.0),
+                    );
+                    let name = self.name.trim();
+                    if ui
+                        .add_enabled(
+                            !self.last.is_empty() && !name.is_empty(),
+                            egui::Button::new("💾 Save Last"),
+                        )
+                        .clicked()
+                    {
+                        let name = name.to_string();
+                        saved.retain(|saved| saved.name != name);
+                        saved.push(Macro {
+                            name,
+                            events: self.last.clone(),
+                        });
+                        self.name.clear();
+                    }
+                });
+                let mut remove = None;
+                for (index, saved) in saved.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(idle, egui::Button::new("▶"))
+                            .labeled("Play")
+                            .clicked()
+                        {
+                            action = Some(MacroAction::Play(saved.events.clone()));
+                        }
+                        if ui.small_button("🗑").labeled("Delete").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.label(format!("{} ({} keys)", saved.name, saved.events.len()));
+                    });
+                }
+                if let Some(index) = remove {
+                    saved.remove(index);
+                }
+            });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn keys_get_their_own_batch() {
+        let text = |s: &str| Event::Text(s.to_string());
+        let events = [text("a"), text("b"), key(Key::Enter), text("c")];
+        assert_eq!(next_batch(&events, 0, true), 2);
+        assert_eq!(next_batch(&events, 0, false), 1);
+        assert_eq!(next_batch(&events, 2, true), 3);
+        assert_eq!(next_batch(&events, 3, true), 4);
+    }
+
+    #[test]
+    fn the_stop_key_is_left_out() {
+        let mut macros = Macros::default();
+        macros.toggle_recording(None);
+        macros.record(&[key(Key::Home), Event::Text("- ".to_string()), key(Key::M)]);
+        macros.toggle_recording(Some(Key::M));
+        assert_eq!(macros.last.len(), 2);
+        assert!(macros.recording.is_none());
+    }
+}
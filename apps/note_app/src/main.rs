@@ -1,447 +1,3786 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod attachment;
+mod autocorrect;
+mod autosave;
+mod backup;
+mod complete;
+mod diff;
+mod document;
+mod emoji;
+mod encoding;
+mod export;
+mod filereplace;
+mod fonts;
+mod footnote;
+mod git;
+mod history;
+mod keymap;
+mod macros;
+mod math;
+mod minimap;
+mod notebook;
+mod page;
+mod pipetable;
+mod properties;
+mod rtxt;
+mod scratch;
+mod search;
+mod session;
+mod sketch;
+mod speech;
+mod stylesheet;
+mod symbols;
+mod sync;
+mod table;
+mod text;
+mod undo;
+mod webdav;
+mod weblink;
+mod wikilink;
+
+use autocorrect::Autocorrect;
+use autosave::{RecoveredBuffer, RecoveryWriter};
+use complete::{Completion, CompletionKey};
+use diff::Comparison;
+use document::{Document, StyleChange, StyledRange, TextSize};
 use eframe::egui;
-use egui::{Color32, FontId, TextEdit};
-use std::ops::Range;
+use egui::{Color32, TextEdit};
+use emoji::EmojiPicker;
+use filereplace::{ReplaceAction, ReplaceInFiles};
+use fonts::CustomFonts;
+use git::GitHistory;
+use gui_common::dialog::FileType;
+use gui_common::ipc::{self, Message};
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{launch, palette, Labeled};
+use history::HistoryBrowser;
+use keymap::{Command, Keymap};
+use macros::{Macro, MacroAction, Macros};
+use notebook::{Notebook, NotebookAction};
+use page::{PageSetup, PaperSize};
+use pipetable::TableEdit;
+use serde::{Deserialize, Serialize};
+use session::{Session, SESSION_KEY};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use stylesheet::NamedStyle;
+use symbols::SymbolDialog;
+
+const APP_ID: &str = "Note App";
+// Where settings were kept before they moved to their own file
+const SETTINGS_KEY: &str = "note_settings";
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+const MIN_FONT_SIZE: f32 = 8.0;
+const MAX_FONT_SIZE: f32 = 72.0;
+// Letters typed before completions are offered without asking
+const COMPLETE_AFTER: usize = 3;
+// How long a notice stays in the status bar
+const STATUS_DURATION: Duration = Duration::from_secs(4);
 
 fn main() -> eframe::Result<()> {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1200.0, 1024.0])
-            .with_title("Note App - Rich Text Editor"),
-        ..Default::default()
-    };
+    // Without a log the app still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<NoteSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
+        "Note App - Rich Text Editor",
+        [1200.0, 1024.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
     eframe::run_native(
-        "Note App",
+        APP_ID,
         options,
-        Box::new(|_cc| Ok(Box::<NoteApp>::default())),
+        Box::new(|cc| Ok(Box::new(NoteApp::new(cc, saved)))),
     )
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum TextFormatting {
-    Regular,
-    Bold,
-    Italic,
-    BoldItalic,
+// User preferences persisted between sessions
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct NoteSettings {
+    autosave: bool,               // Periodically save modified documents that have a file
+    autosave_interval_secs: u64,  // Also how often version history snapshots are taken
+    autosave_on_focus_loss: bool, // Also save when the window loses focus
+    autosave_files: HashMap<PathBuf, bool>, // Files that override `autosave`
+    backup_on_save: bool,         // Copy the previous file before overwriting it
+    backup_count: usize,          // One ".bak", or this many timestamped copies
+    auto_indent: bool,            // New lines keep the previous line's indentation
+    tab_width: usize,             // Spaces per indentation level
+    indent_with_tabs: bool,       // Tab inserts a tab character instead of spaces
+    match_brackets: bool,         // Highlight the bracket matching the one at the cursor
+    highlight_line: bool,         // Shade the line with the caret
+    find_results: bool,           // List the matches under the find bar
+    macros: Vec<Macro>,           // Saved by name in the macros window
+    auto_close_brackets: bool,    // Typing an opening bracket or quote adds the closing one
+    font_file: Option<PathBuf>,   // Custom editor font, loaded at startup
+    common: CommonSettings,       // Theme and recent files, as in the other apps
+    restore_session: bool,        // Reopen the previous session's files at startup
+    notes_dir: Option<PathBuf>,   // Folder shown in the notebook sidebar
+    show_notebook: bool,
+    keep_history: bool, // Snapshot saved documents for the history window
+    history_max_snapshots: usize, // Per document; the oldest are removed first
+    history_max_mb: u64,
+    keymap: Keymap,
+    show_minimap: bool,               // Overview strip to the right of the editor
+    complete_as_you_type: bool,       // Otherwise only on the completion shortcut
+    dictionary_file: Option<PathBuf>, // Extra words offered as completions
+    recent_symbols: Vec<char>,        // Most recent first
+    sync_account: webdav::Account,    // WebDAV folder the notes folder syncs with
+    line_spacing: f32,                // Multiple of each font's normal line height
+    paragraph_spacing: f32,           // Points of space after each line break
+    focus_width: f32,                 // Text column width in focus mode
+    page: PageSetup,                  // Paginated preview and printing
+    autocorrect: Autocorrect,
+}
+
+impl Default for NoteSettings {
+    fn default() -> Self {
+        Self {
+            autosave: false,
+            autosave_interval_secs: 60,
+            autosave_on_focus_loss: false,
+            autosave_files: HashMap::new(),
+            backup_on_save: false,
+            backup_count: 1,
+            auto_indent: true,
+            tab_width: 4,
+            indent_with_tabs: false,
+            match_brackets: true,
+            highlight_line: true,
+            find_results: true,
+            macros: Vec::new(),
+            auto_close_brackets: false,
+            font_file: None,
+            common: CommonSettings::default(),
+            restore_session: true,
+            notes_dir: None,
+            show_notebook: false,
+            keep_history: true,
+            history_max_snapshots: 50,
+            history_max_mb: 20,
+            keymap: Keymap::default(),
+            show_minimap: true,
+            complete_as_you_type: false,
+            dictionary_file: None,
+            recent_symbols: Vec::new(),
+            sync_account: webdav::Account::default(),
+            line_spacing: 1.0,
+            paragraph_spacing: 0.0,
+            focus_width: 720.0,
+            page: PageSetup::default(),
+            autocorrect: Autocorrect::default(),
+        }
+    }
+}
+
+// What is waiting on the "Save changes?" prompt
+#[derive(Clone)]
+enum PendingClose {
+    Tabs(Vec<u64>), // Document ids
+    Window,
+}
+
+// The "Extract to Note" window: the new note's name and what becomes of the
+// selection
+struct ExtractNote {
+    name: String,
+    copy: bool, // Leave the selection in place
+    link: bool, // Replace a moved selection with a [[link]] to the note
+}
+
+// Two editor panes; the focused one always shows the active tab
+struct SplitView {
+    vertical: bool,       // Side by side rather than one above the other
+    other: u64,           // Document id shown in the unfocused pane
+    second_focused: bool, // The active tab is in the right or bottom pane
+}
+
+fn editor_id(doc_id: u64, pane: usize) -> egui::Id {
+    egui::Id::new(("editor", doc_id, pane))
+}
+
+// Font, color and background for text in a styled range, or plain text
+fn text_format(
+    styled: Option<&StyledRange>,
+    sheet: &[NamedStyle],
+    font_family: &str,
+    font_size: f32,
+) -> egui::TextFormat {
+    let styled = stylesheet::effective(styled, sheet);
+    let size = styled
+        .size
+        .map_or(font_size, |size| size.resolve(font_size));
+    // Bold text is drawn larger, as the fonts have no bold face
+    let size = if styled.bold { size * 1.3 } else { size };
+    let color = styled.text_color.unwrap_or(Color32::BLACK);
+    egui::TextFormat {
+        font_id: if styled.monospace {
+            egui::FontId::monospace(size)
+        } else {
+            fonts::font_id(font_family, size)
+        },
+        color,
+        background: styled.bg_color.unwrap_or(Color32::TRANSPARENT),
+        italics: styled.italic,
+        underline: if styled.underline {
+            egui::Stroke::new(1.0, color)
+        } else {
+            egui::Stroke::NONE
+        },
+        ..Default::default()
+    }
+}
+
+// A document laid out for reading: formatting applied, $math$ rendered and
+// tables shown as pipe tables. Sketches have no text form and are left out
+fn preview_job(doc: &Document, font_family: &str, font_size: f32) -> egui::text::LayoutJob {
+    let text = &doc.text_content;
+    let boundaries = text::char_boundaries(text);
+    let to_byte = |index: usize| boundaries[index.min(boundaries.len() - 1)];
+    let styled_ranges: Vec<StyledRange> = doc
+        .styled_ranges
+        .iter()
+        .map(|styled| StyledRange {
+            range: to_byte(styled.range.start)..to_byte(styled.range.end),
+            ..styled.clone()
+        })
+        .collect();
+    let regions = math::regions(text);
+
+    let mut job = egui::text::LayoutJob::default();
+    let mut pos = 0;
+    while pos < text.len() {
+        let styled = styled_ranges
+            .iter()
+            .find(|styled| styled.range.contains(&pos));
+        let format = text_format(styled, &doc.named_styles, font_family, font_size);
+        if let Some(region) = regions.iter().find(|region| region.start == pos) {
+            math::append(&mut job, &text[region.start + 1..region.end - 1], &format);
+            pos = region.end;
+            continue;
+        }
+        let end = regions
+            .iter()
+            .map(|region| region.start)
+            .chain(
+                styled_ranges
+                    .iter()
+                    .flat_map(|styled| [styled.range.start, styled.range.end]),
+            )
+            .filter(|&boundary| boundary > pos)
+            .min()
+            .unwrap_or(text.len())
+            .min(text.len());
+        let mut plain = String::new();
+        for c in text[pos..end].chars() {
+            if let Some(table) = doc.tables.iter().find(|table| table.placeholder() == c) {
+                job.append(&std::mem::take(&mut plain), 0.0, format.clone());
+                let monospace = egui::TextFormat {
+                    font_id: egui::FontId::monospace(font_size),
+                    ..format.clone()
+                };
+                job.append(&table.to_markdown(), 0.0, monospace);
+            } else if !table::Table::is_placeholder(c) && !sketch::Sketch::is_placeholder(c) {
+                plain.push(c);
+            }
+        }
+        job.append(&plain, 0.0, format);
+        pos = end;
+    }
+    job
+}
+
+// The text laid out on sheets of the page size, broken between rows
+fn show_pages(ui: &mut egui::Ui, mut job: egui::text::LayoutJob, page: &PageSetup) {
+    let (size, margin, content) = (page.size(), page.margin(), page.content_size());
+    job.wrap.max_width = content.x;
+    let galley = ui.fonts(|f| f.layout_job(job));
+    let rows: Vec<std::ops::Range<f32>> = galley
+        .rows
+        .iter()
+        .map(|row| row.rect.top()..row.rect.bottom())
+        .collect();
+    let breaks = page::page_breaks(&rows, content.y);
+
+    for (i, &first) in breaks.iter().enumerate() {
+        let end = breaks.get(i + 1).copied().unwrap_or(rows.len());
+        let top = rows.get(first).map_or(0.0, |row| row.start);
+        let bottom = rows[..end].last().map_or(top, |row| row.end);
+        ui.vertical_centered(|ui| {
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let painter = ui.painter();
+            painter.rect_filled(
+                rect.translate(egui::vec2(3.0, 3.0)),
+                0.0,
+                Color32::from_black_alpha(40),
+            );
+            painter.rect(
+                rect,
+                0.0,
+                Color32::WHITE,
+                egui::Stroke::new(1.0, Color32::GRAY),
+            );
+            let text_top = rect.top() + margin;
+            let clip = egui::Rect::from_x_y_ranges(
+                rect.left() + margin..=rect.right() - margin,
+                text_top..=text_top + (bottom - top).min(content.y),
+            );
+            painter
+                .with_clip_rect(clip.intersect(ui.clip_rect()))
+                .galley(
+                    egui::pos2(rect.left() + margin, text_top - top),
+                    galley.clone(),
+                    Color32::BLACK,
+                );
+            painter.text(
+                rect.center_bottom() - egui::vec2(0.0, margin / 2.0),
+                egui::Align2::CENTER_CENTER,
+                format!("{} / {}", i + 1, breaks.len()),
+                egui::FontId::proportional(11.0),
+                Color32::GRAY,
+            );
+        });
+        ui.add_space(16.0);
+    }
 }
 
-#[derive(Clone, Debug)]
-struct StyledRange {
-    range: Range<usize>,
-    style: TextFormatting,
-    text_color: Option<Color32>,
-    bg_color: Option<Color32>,
+// Move every row after a line break down by `spacing`. egui has no paragraph
+// spacing, and a taller glyph would push the row's text out of line
+fn space_paragraphs(galley: &mut egui::Galley, spacing: f32) {
+    let mut offset = 0.0;
+    for row in &mut galley.rows {
+        if offset > 0.0 {
+            let shift = egui::vec2(0.0, offset);
+            row.rect = row.rect.translate(shift);
+            row.visuals.mesh.translate(shift);
+            row.visuals.mesh_bounds = row.visuals.mesh_bounds.translate(shift);
+            for glyph in &mut row.glyphs {
+                glyph.pos += shift;
+            }
+        }
+        if row.ends_with_newline {
+            offset += spacing;
+        }
+    }
+    galley.rect.max.y += offset;
+    galley.mesh_bounds.max.y += offset;
 }
 
-#[derive(Clone, Debug)]
-struct EditorState {
-    text_content: String,
-    styled_ranges: Vec<StyledRange>,
+// Shade the rows of one line, zero-based, across the wrap width. The shading
+// goes first in each row's mesh so the text is drawn over it
+fn highlight_line(galley: &mut egui::Galley, line: usize, color: Color32) {
+    let width = if galley.job.wrap.max_width.is_finite() {
+        galley.job.wrap.max_width.max(galley.rect.width())
+    } else {
+        galley.rect.width()
+    };
+    let mut current = 0;
+    for row in &mut galley.rows {
+        if current == line {
+            let rect = egui::Rect::from_x_y_ranges(0.0..=width, row.rect.y_range());
+            let mut mesh = egui::Mesh::default();
+            mesh.add_colored_rect(rect, color);
+            let (vertices, indices) = (mesh.vertices.len(), mesh.indices.len());
+            mesh.append(std::mem::take(&mut row.visuals.mesh));
+            row.visuals.mesh = mesh;
+            // egui puts the selection just before the glyphs using these
+            row.visuals.glyph_index_start += indices;
+            let glyphs = &mut row.visuals.glyph_vertex_range;
+            *glyphs = glyphs.start + vertices..glyphs.end + vertices;
+            row.visuals.mesh_bounds = row.visuals.mesh_bounds.union(rect);
+        }
+        if row.ends_with_newline {
+            current += 1;
+        }
+    }
 }
 
 struct NoteApp {
-    text_content: String,
-    styled_ranges: Vec<StyledRange>,
-    file_path: Option<PathBuf>,
+    // Open documents, one per tab
+    documents: Vec<Document>,
+    active_tab: usize,
+    next_document_id: u64,
     error_message: Option<String>,
-    current_style: TextFormatting,
-    cursor_range: Option<Range<usize>>,
+    status_message: Option<(String, Instant)>, // A notice, shown until it expires
+    current_size: Option<TextSize>,            // Heading or explicit size applied to selections
+    custom_size: f32,                          // Pixel size offered in the size menu
     font_size: f32,
-    // Undo/Redo
-    undo_stack: Vec<EditorState>,
-    redo_stack: Vec<EditorState>,
     // Find & Replace
     find_text: String,
+    replace_in_files: Option<ReplaceInFiles>, // Find & Replace across the notes folder
     replace_text: String,
     show_find_replace: bool,
-    last_find_position: usize,
     // Display options
     show_line_numbers: bool,
-    tab_width: usize,
+    focus_mode: bool,               // Full screen with only the text
+    focus_fullscreen: bool,         // Full screen state last requested for focus mode
+    preview: bool,                  // Read-only view with math rendered
+    page_view: bool,                // Preview split into pages
+    reader: Option<speech::Reader>, // Read aloud in progress
     font_family: String,
+    custom_fonts: CustomFonts,
+    show_font_picker: bool,
+    font_filter: String,
+    system_fonts: Option<Vec<PathBuf>>, // Scanned when the picker first opens
+    notebook: Notebook,
     // Color options
     current_text_color: Color32,
     current_bg_color: Option<Color32>,
-    // Pending cursor position after programmatic text changes
-    pending_cursor_pos: Option<usize>,
-    // Flag to prevent cursor capture when programmatically setting selection
-    skip_cursor_capture: bool,
+    // Go to line dialog
+    show_go_to: bool,
+    extract: Option<ExtractNote>,
+    macros: Macros,
+    show_macros: bool,
+    focus_menu_bar: bool, // Give the first menu bar button keyboard focus
+    show_log: bool,
+    logged_message: Option<String>, // The error_message last written to the log
+    go_to_input: String,
+    go_to_offset: bool, // Interpret the input as a byte offset instead of a line
+    // Insert table dialog
+    show_insert_table: bool,
+    table_rows: usize,
+    table_columns: usize,
+    // Settings and autosave
+    settings: NoteSettings,
+    show_settings: bool,
+    editing_style: Option<usize>, // Named style open in the styles window
+    show_shortcuts: bool,
+    show_word_count: bool,
+    show_properties: bool,
+    shortcut_capture: Option<Command>, // Command waiting for its new shortcut
+    last_autosave: Instant,
+    recovery: RecoveryWriter,
+    last_recovery: Instant,
+    recovered: Vec<RecoveredBuffer>, // Buffers from a crashed session awaiting restore
+    // Unsaved-changes confirmation
+    pending_close: Option<PendingClose>,
+    close_confirmed: bool, // Let the next window close request through
+    split: Option<SplitView>,
+    comparison: Option<Comparison>, // "Compare with saved" window
+    history: Option<HistoryBrowser>,
+    git_history: Option<GitHistory>,
+    show_git_commit: bool,
+    commit_message: String,
+    // Word completion
+    completion: Option<Completion>,
+    dictionary: Vec<String>,
+    typed: bool,                        // The focused editor changed this frame
+    completion_pos: Option<egui::Pos2>, // Below the cursor, where the list opens
+    emoji_picker: Option<EmojiPicker>,
+    show_symbols: bool,
+    show_attachments: bool,
+    symbol_dialog: Option<SymbolDialog>, // Kept once built, as indexing the names takes a moment
+    // WebDAV sync of the notes folder
+    sync_job: Option<Receiver<sync::Outcome>>, // Running sync or conflict resolution
+    sync_status: Option<String>,
+    sync_conflicts: Vec<sync::Conflict>,
+    // Wiki links between notes
+    pending_link: Option<String>, // Ctrl+clicked link, followed after drawing
+    context_link: Option<String>, // Web address right-clicked in the editor
+    backlinks: Option<(PathBuf, Vec<PathBuf>)>, // Notes linking to a note, by the note
+    ipc: Option<ipc::Listener>,   // Results sent from the calculator
+    // Expression being sent to the calculator, started if it is not running
+    calculate_job: Option<(String, Receiver<Result<(), ipc::SendError>>)>,
 }
 
 impl Default for NoteApp {
     fn default() -> Self {
         Self {
-            text_content: String::new(),
-            styled_ranges: Vec::new(),
-            file_path: None,
+            documents: vec![Document::new(0)],
+            active_tab: 0,
+            next_document_id: 1,
             error_message: None,
-            current_style: TextFormatting::Regular,
-            cursor_range: None,
-            font_size: 16.0,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            status_message: None,
+            current_size: None,
+            custom_size: 24.0,
+            font_size: DEFAULT_FONT_SIZE,
             find_text: String::new(),
+            replace_in_files: None,
             replace_text: String::new(),
             show_find_replace: false,
-            last_find_position: 0,
             show_line_numbers: true,
-            tab_width: 4,
+            focus_mode: false,
+            preview: false,
+            page_view: false,
+            focus_fullscreen: false,
+            reader: None,
             font_family: "Monospace".to_string(),
+            custom_fonts: CustomFonts::default(),
+            show_font_picker: false,
+            font_filter: String::new(),
+            system_fonts: None,
+            notebook: Notebook::default(),
             current_text_color: Color32::BLACK,
             current_bg_color: None,
-            pending_cursor_pos: None,
-            skip_cursor_capture: false,
+            show_go_to: false,
+            extract: None,
+            macros: Macros::default(),
+            show_macros: false,
+            focus_menu_bar: false,
+            show_log: false,
+            logged_message: None,
+            go_to_input: String::new(),
+            go_to_offset: false,
+            show_insert_table: false,
+            table_rows: 3,
+            table_columns: 3,
+            settings: NoteSettings::default(),
+            show_settings: false,
+            editing_style: None,
+            show_shortcuts: false,
+            show_word_count: false,
+            show_properties: false,
+            shortcut_capture: None,
+            last_autosave: Instant::now(),
+            recovery: RecoveryWriter::start(),
+            last_recovery: Instant::now(),
+            recovered: Vec::new(),
+            pending_close: None,
+            close_confirmed: false,
+            split: None,
+            comparison: None,
+            history: None,
+            git_history: None,
+            show_git_commit: false,
+            commit_message: String::new(),
+            completion: None,
+            dictionary: Vec::new(),
+            typed: false,
+            completion_pos: None,
+            emoji_picker: None,
+            show_symbols: false,
+            show_attachments: false,
+            symbol_dialog: None,
+            sync_job: None,
+            sync_status: None,
+            sync_conflicts: Vec::new(),
+            pending_link: None,
+            context_link: None,
+            backlinks: None,
+            ipc: None,
+            calculate_job: None,
+        }
+    }
+}
+
+impl NoteApp {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<NoteSettings>) -> Self {
+        let settings: NoteSettings = saved
+            .or_else(|| {
+                cc.storage
+                    .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            })
+            .unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let mut app = Self {
+            settings,
+            recovered: autosave::start_session(),
+            ..Default::default()
+        };
+        if let Some(path) = app.settings.font_file.clone() {
+            app.use_font_file(&cc.egui_ctx, &path);
+        }
+        app.notebook.set_root(app.settings.notes_dir.clone());
+        if let Some(path) = app.settings.dictionary_file.clone() {
+            app.load_dictionary(&path);
+        }
+        // The zoom shortcuts change the editor font size instead of scaling the UI
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        if app.settings.restore_session {
+            let session: Option<Session> = cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, SESSION_KEY));
+            if let Some(session) = session {
+                app.restore_session(session);
+            }
+        }
+        // Files named on the command line, as the file manager does
+        for path in std::env::args_os().skip(1) {
+            app.open_file(PathBuf::from(path));
+        }
+        app.ipc = ipc::listen(APP_ID, &cc.egui_ctx)
+            .inspect_err(|e| tracing::warn!(error = %e, "Not taking results from the calculator"))
+            .ok();
+        app
+    }
+
+    // Reopen the files of the previous session with their cursor and scroll
+    // positions; files that no longer exist are skipped
+    fn restore_session(&mut self, session: Session) {
+        if let Some(font_size) = session.font_size {
+            self.set_font_size(font_size);
+        }
+        let mut active_tab = None;
+        for (index, file) in session.files.iter().enumerate() {
+            if !file.path.exists() {
+                continue;
+            }
+            self.open_file(file.path.clone());
+            let doc = self.doc_mut();
+            let cursor = file.cursor.min(doc.char_count());
+            doc.cursor_range = Some(cursor..cursor);
+            doc.pending_cursor_pos = Some(cursor);
+            doc.scroll_offset = file.scroll_offset;
+            doc.restore_scroll = true;
+            doc.pinned = file.pinned;
+            if session.active_file == Some(index) {
+                active_tab = Some(self.active_tab);
+            }
+        }
+        if let Some(tab) = active_tab {
+            self.active_tab = tab;
+        }
+        // A file that failed to open is not worth an error at startup
+        self.error_message = None;
+    }
+
+    // Load a font file and make it the editor font
+    fn use_font_file(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        match self.custom_fonts.load(ctx, path) {
+            Ok(name) => {
+                self.font_family = name;
+                self.settings.font_file = Some(path.to_path_buf());
+            }
+            Err(e) => {
+                self.error_message = Some(e);
+                self.settings.font_file = None;
+            }
+        }
+    }
+
+    fn show_notebook_panel(&mut self, ctx: &egui::Context) {
+        let mut action = None;
+        egui::SidePanel::left("notebook")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let title = self
+                        .notebook
+                        .root()
+                        .and_then(|root| root.file_name())
+                        .map_or("Notes".into(), |name| name.to_string_lossy());
+                    ui.strong(format!("📚 {}", title));
+                    if ui
+                        .small_button("📂")
+                        .labeled("Choose notes folder")
+                        .clicked()
+                    {
+                        if let Some(dir) = self.settings.common.dialogs.folder() {
+                            self.notebook.set_root(Some(dir.clone()));
+                            self.settings.notes_dir = Some(dir);
+                        }
+                    }
+                    if self.notebook.repo().is_some()
+                        && ui
+                            .small_button("⎇")
+                            .labeled("Commit changes to git")
+                            .clicked()
+                    {
+                        self.notebook.refresh_status();
+                        self.show_git_commit = true;
+                    }
+                    if self.settings.sync_account.is_set() {
+                        let idle = self.sync_job.is_none() && self.notebook.root().is_some();
+                        if ui
+                            .add_enabled(idle, egui::Button::new("⇅").small())
+                            .labeled("Sync with the WebDAV server")
+                            .clicked()
+                        {
+                            self.start_sync(ui.ctx());
+                        }
+                    }
+                });
+                if self.sync_job.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Syncing...");
+                    });
+                } else if let Some(status) = &self.sync_status {
+                    ui.weak(status);
+                }
+                ui.separator();
+                let mut backlink = None;
+                if self.doc().file_path.is_some() {
+                    egui::TopBottomPanel::bottom("backlinks")
+                        .resizable(true)
+                        .show_inside(ui, |ui| backlink = self.show_backlinks(ui));
+                }
+                action = self
+                    .notebook
+                    .show(ui)
+                    .or(backlink.map(NotebookAction::Open));
+            });
+        if action.is_some() {
+            self.backlinks = None;
+        }
+
+        match action {
+            Some(NotebookAction::Open(path)) => self.open_file(path),
+            Some(NotebookAction::Renamed { from, to }) => {
+                // Keep open tabs pointing at the renamed file or folder
+                for doc in &mut self.documents {
+                    let moved = doc
+                        .file_path
+                        .as_ref()
+                        .and_then(|path| path.strip_prefix(&from).ok())
+                        .map(|rest| {
+                            // Joining an empty path would add a trailing separator
+                            if rest.as_os_str().is_empty() {
+                                to.clone()
+                            } else {
+                                to.join(rest)
+                            }
+                        });
+                    if let Some(path) = moved {
+                        doc.file_path = Some(path);
+                    }
+                }
+            }
+            Some(NotebookAction::Deleted(deleted)) => {
+                // Open copies of deleted notes become unsaved documents
+                for doc in &mut self.documents {
+                    if doc
+                        .file_path
+                        .as_ref()
+                        .is_some_and(|path| path.starts_with(&deleted))
+                    {
+                        doc.file_path = None;
+                        doc.dirty = true;
+                    }
+                }
+            }
+            Some(NotebookAction::GitHistory(path)) => {
+                self.open_file(path);
+                self.open_git_history(ctx);
+            }
+            None => {}
+        }
+    }
+
+    // Notes linking to the current note, read again when another note is
+    // shown or after saving; returns a clicked one
+    fn show_backlinks(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let root = self.notebook.root()?.to_path_buf();
+        let path = self
+            .doc()
+            .file_path
+            .clone()
+            .filter(|path| path.starts_with(&root))?;
+        if self
+            .backlinks
+            .as_ref()
+            .is_none_or(|(target, _)| *target != path)
+        {
+            let found = wikilink::backlinks(&root, &self.notebook.notes(), &path);
+            self.backlinks = Some((path, found));
+        }
+        let (_, found) = self.backlinks.as_ref()?;
+        let mut clicked = None;
+        egui::CollapsingHeader::new(format!("🔗 Backlinks ({})", found.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if found.is_empty() {
+                        ui.weak("No notes link here");
+                    }
+                    for source in found {
+                        let name = wikilink::note_name(&root, source).unwrap_or_default();
+                        if ui.selectable_label(false, format!("📄 {}", name)).clicked() {
+                            clicked = Some(source.clone());
+                        }
+                    }
+                });
+            });
+        clicked
+    }
+
+    // Names of the notes in the notes folder, as [[links]] use them
+    fn note_names(&self) -> Vec<String> {
+        let Some(root) = self.notebook.root() else {
+            return Vec::new();
+        };
+        self.notebook
+            .notes()
+            .iter()
+            .filter_map(|path| wikilink::note_name(root, path))
+            .collect()
+    }
+
+    // Open the note a [[link]] names, creating it in the notes folder if it
+    // does not exist yet
+    fn follow_wiki_link(&mut self, target: &str) {
+        let Some(root) = self.notebook.root().map(PathBuf::from) else {
+            self.error_message = Some("Choose a notes folder to follow links".to_string());
+            return;
+        };
+        let path = match wikilink::resolve(&root, &self.notebook.notes(), target) {
+            Some(path) => path,
+            None => match notebook::create_note(&root, target) {
+                Ok(path) => {
+                    self.notebook.refresh();
+                    self.backlinks = None;
+                    path
+                }
+                Err(e) => {
+                    self.error_message = Some(e);
+                    return;
+                }
+            },
+        };
+        self.open_file(path);
+    }
+
+    // The active document's named styles; changes show at once wherever a
+    // style is used
+    fn show_styles_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let doc = &mut self.documents[self.active_tab];
+        let mut selected = self.editing_style.unwrap_or(0);
+        egui::Window::new("Styles")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(120.0);
+                        for (i, style) in doc.named_styles.iter().enumerate() {
+                            ui.selectable_value(&mut selected, i, &style.name);
+                        }
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            if ui.button("+ New").clicked() {
+                                let name = stylesheet::unused_name(&doc.named_styles, "Style");
+                                doc.named_styles.push(NamedStyle::new(&name));
+                                selected = doc.named_styles.len() - 1;
+                                doc.dirty = true;
+                            }
+                            let delete = ui
+                                .add_enabled(
+                                    selected < doc.named_styles.len(),
+                                    egui::Button::new("Delete"),
+                                )
+                                .on_hover_text(
+                                    "Text using the style keeps only its own formatting",
+                                );
+                            if delete.clicked() {
+                                let style = doc.named_styles.remove(selected);
+                                doc.rename_named_style(&style.name, None);
+                                selected = selected.saturating_sub(1);
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    let Some(style) = doc.named_styles.get(selected) else {
+                        ui.label("No styles");
+                        return;
+                    };
+                    let mut edited = style.clone();
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Name:");
+                            ui.text_edit_singleline(&mut edited.name);
+                        });
+                        ui.checkbox(&mut edited.paragraph, "Paragraph style")
+                            .on_hover_text("Applies to whole lines rather than the selection");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut edited.bold, "Bold");
+                            ui.checkbox(&mut edited.italic, "Italic");
+                            ui.checkbox(&mut edited.underline, "Underline");
+                            ui.checkbox(&mut edited.monospace, "Monospace");
+                        });
+                        for (label, color, default) in [
+                            ("Text color:", &mut edited.text_color, Color32::BLACK),
+                            ("Highlight:", &mut edited.bg_color, Color32::YELLOW),
+                        ] {
+                            ui.horizontal(|ui| {
+                                let label = ui.label(label);
+                                let mut has_color = color.is_some();
+                                if ui
+                                    .checkbox(&mut has_color, "")
+                                    .labelled_by(label.id)
+                                    .changed()
+                                {
+                                    *color = has_color.then_some(default);
+                                }
+                                if let Some(color) = color {
+                                    ui.color_edit_button_srgba(color).labelled_by(label.id);
+                                }
+                            });
+                        }
+                        egui::ComboBox::from_label("Size")
+                            .selected_text(
+                                edited.size.map_or("Normal".to_string(), TextSize::label),
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut edited.size, None, "Normal");
+                                for level in 1..=3 {
+                                    let size = Some(TextSize::Heading(level));
+                                    ui.selectable_value(
+                                        &mut edited.size,
+                                        size,
+                                        format!("H{}", level),
+                                    );
+                                }
+                            });
+                    });
+
+                    // A new name must be free; the text using the style follows it
+                    let name_taken = edited.name.trim().is_empty()
+                        || doc
+                            .named_styles
+                            .iter()
+                            .any(|other| other.name == edited.name);
+                    if edited.name != style.name && name_taken {
+                        edited.name = style.name.clone();
+                    }
+                    if edited != *style {
+                        let old_name = style.name.clone();
+                        if edited.name != old_name {
+                            doc.rename_named_style(&old_name, Some(&edited.name));
+                        }
+                        doc.named_styles[selected] = edited;
+                        doc.dirty = true;
+                    }
+                });
+            });
+        self.editing_style = open.then_some(selected);
+    }
+
+    // Files attached to the current note, each with buttons to open or
+    // remove it, and a button to attach more
+    fn show_attachments_panel(&mut self, ctx: &egui::Context) {
+        let (mut attach, mut open, mut remove) = (false, None, None);
+        let doc = &self.documents[self.active_tab];
+        // Attachments are listed in the .rtxt metadata
+        let note = doc
+            .file_path
+            .clone()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rtxt"));
+        egui::SidePanel::right("attachments")
+            .resizable(true)
+            .default_width(200.0)
+            .show(ctx, |ui| {
+                ui.strong("📎 Attachments");
+                let Some(note) = &note else {
+                    ui.weak("Save the note as .rtxt to attach files to it");
+                    return;
+                };
+                attach = ui.button("➕ Attach files...").clicked();
+                ui.separator();
+                if doc.metadata.attachments.is_empty() {
+                    ui.weak("No attachments");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (index, name) in doc.metadata.attachments.iter().enumerate() {
+                        let file = attachment::path(note, name);
+                        let label = name.rsplit('/').next().unwrap_or(name);
+                        ui.horizontal(|ui| {
+                            if ui
+                                .small_button("🗑")
+                                .labeled("Remove the attachment and delete its copy")
+                                .clicked()
+                            {
+                                remove = Some(index);
+                            }
+                            match file.filter(|file| file.exists()) {
+                                None => {
+                                    ui.colored_label(palette::ERROR, label)
+                                        .on_hover_text("The attached file is missing");
+                                }
+                                Some(file) => {
+                                    if ui.link(label).on_hover_text("Open").clicked() {
+                                        open = Some(file);
+                                    }
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+        let Some(note) = note else {
+            return;
+        };
+
+        if let Some(file) = open {
+            if let Err(e) = launch::open_with_system(&file) {
+                self.error_message = Some(e);
+            }
+        }
+        if let Some(index) = remove {
+            let doc = self.doc_mut();
+            let name = doc.metadata.attachments.remove(index);
+            doc.dirty = true;
+            if let Err(e) = attachment::remove(&note, &name) {
+                self.error_message = Some(e);
+            }
+        }
+        if attach {
+            for source in self.settings.common.dialogs.open_many(&[FileType::Any]) {
+                match attachment::attach(&note, &source) {
+                    Ok(name) => {
+                        let doc = self.doc_mut();
+                        doc.metadata.attachments.push(name);
+                        doc.dirty = true;
+                    }
+                    Err(e) => self.error_message = Some(e),
+                }
+            }
+        }
+    }
+
+    fn open_git_history(&mut self, ctx: &egui::Context) {
+        let Some(repo) = self.notebook.repo() else {
+            return;
+        };
+        match GitHistory::open(ctx, repo, &self.documents[self.active_tab]) {
+            Ok(browser) => self.git_history = Some(browser),
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    fn show_git_history_window(&mut self, ctx: &egui::Context) {
+        let (Some(browser), Some(repo)) = (&mut self.git_history, self.notebook.repo()) else {
+            self.git_history = None;
+            return;
+        };
+        let Some(index) = self
+            .documents
+            .iter()
+            .position(|doc| doc.id == browser.doc_id)
+        else {
+            self.git_history = None;
+            return;
+        };
+        let mut open = true;
+        let restored = browser.show(ctx, repo, &self.documents[index], &mut open);
+        if !open {
+            self.git_history = None;
+        }
+        if let Some(revision) = restored {
+            self.restore_version(index, revision);
+        }
+    }
+
+    fn show_git_commit_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_git_commit;
+        let mut commit = false;
+        let changed = self.notebook.changed_notes();
+        egui::Window::new("Commit Notes")
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                if changed.is_empty() {
+                    ui.label("No changed notes");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        for (path, status) in &changed {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(status.color(), status.marker())
+                                    .on_hover_text(status.label());
+                                ui.label(path);
+                            });
+                        }
+                    });
+                ui.add(
+                    TextEdit::multiline(&mut self.commit_message)
+                        .hint_text("Commit message")
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.horizontal(|ui| {
+                    let ready = !changed.is_empty() && !self.commit_message.trim().is_empty();
+                    commit = ui
+                        .add_enabled(ready, egui::Button::new("✔ Commit"))
+                        .on_hover_text("Commit every change in the notes folder, saved notes only")
+                        .clicked();
+                });
+            });
+        if commit {
+            let (Some(repo), Some(root)) = (self.notebook.repo(), self.notebook.root()) else {
+                return;
+            };
+            match repo.commit_folder(root, self.commit_message.trim()) {
+                Ok(()) => {
+                    self.commit_message.clear();
+                    open = false;
+                }
+                Err(e) => self.error_message = Some(e),
+            }
+            self.notebook.refresh_status();
+        }
+        self.show_git_commit = open;
+    }
+
+    fn show_properties_window(&mut self, ctx: &egui::Context) {
+        let doc = self.doc();
+        let info = doc.file_path.as_deref().and_then(properties::file_info);
+        let now = history::now();
+        let time = |time: Option<u64>| {
+            time.map_or("—".to_string(), |time| {
+                format!(
+                    "{} ({})",
+                    properties::format_time(time),
+                    history::age(time, now)
+                )
+            })
+        };
+        // The file system may not record creation; .rtxt files keep their own
+        let created = info
+            .as_ref()
+            .and_then(|info| info.created)
+            .or(doc.metadata.created);
+        let encoding = match doc.file_path.as_ref().and_then(|path| path.extension()) {
+            Some(extension) if extension == "rtxt" => "UTF-8",
+            _ => doc.encoding.label(),
+        };
+        let stats = text::stats(&doc.text_content);
+        let rows = [
+            ("Name", doc.title()),
+            (
+                "Path",
+                doc.file_path
+                    .as_ref()
+                    .map_or("Not saved".to_string(), |path| path.display().to_string()),
+            ),
+            (
+                "Size on disk",
+                info.as_ref()
+                    .map_or("—".to_string(), |info| properties::format_size(info.size)),
+            ),
+            ("Created", time(created)),
+            (
+                "Modified",
+                time(info.as_ref().and_then(|info| info.modified)),
+            ),
+            ("Encoding", encoding.to_string()),
+            (
+                "Line endings",
+                properties::line_endings(&doc.text_content).to_string(),
+            ),
+            ("Characters", stats.chars.to_string()),
+            ("Words", stats.words.to_string()),
+            ("Lines", stats.lines.to_string()),
+            (
+                "Unsaved changes",
+                if doc.dirty { "Yes" } else { "No" }.to_string(),
+            ),
+        ];
+        let folder = doc
+            .file_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|folder| folder.to_path_buf());
+        let mut open = self.show_properties;
+        let mut reveal = false;
+        egui::Window::new("Properties")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("properties")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (label, value) in rows {
+                            ui.strong(label);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(4.0);
+                reveal = ui
+                    .add_enabled(folder.is_some(), egui::Button::new("📂 Show in Folder"))
+                    .on_hover_text("Open the note's folder in the file manager")
+                    .clicked();
+            });
+        if reveal {
+            if let Some(folder) = folder {
+                if let Err(e) = launch::open_with_system(&folder) {
+                    self.error_message = Some(e);
+                }
+            }
+        }
+        self.show_properties = open;
+    }
+
+    fn show_font_picker_window(&mut self, ctx: &egui::Context) {
+        let fonts = self.system_fonts.get_or_insert_with(fonts::system_fonts);
+        let filter = self.font_filter.to_lowercase();
+        let mut chosen = None;
+        let mut open = self.show_font_picker;
+        egui::Window::new("System Fonts")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut self.font_filter);
+                });
+                ui.separator();
+                if fonts.is_empty() {
+                    ui.label("No .ttf or .otf fonts found in the system font folders");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for path in fonts.iter() {
+                        let name = path
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy())
+                            .unwrap_or_default();
+                        if !name.to_lowercase().contains(&filter) {
+                            continue;
+                        }
+                        if ui
+                            .selectable_label(*name == self.font_family, name.as_ref())
+                            .on_hover_text(path.display().to_string())
+                            .clicked()
+                        {
+                            chosen = Some(path.clone());
+                        }
+                    }
+                });
+            });
+        self.show_font_picker = open;
+        if let Some(path) = chosen {
+            self.use_font_file(ctx, &path);
+        }
+    }
+
+    // Save modified documents in place (if enabled) and write the remaining
+    // unsaved buffers to the recovery folder
+    fn autosave(&mut self) {
+        self.last_autosave = Instant::now();
+        self.save_autosaved();
+        for index in 0..self.documents.len() {
+            self.take_snapshot(index);
+        }
+    }
+
+    // Whether a document is saved automatically: its own setting if it has
+    // one, otherwise the global one
+    fn autosaves(&self, doc: &Document) -> bool {
+        doc.file_path.as_ref().is_some_and(|path| {
+            scratch::is_scratch(path)
+                || self
+                    .settings
+                    .autosave_files
+                    .get(path)
+                    .copied()
+                    .unwrap_or(self.settings.autosave)
+        })
+    }
+
+    // Scratchpads save without asking, so closing them never prompts
+    fn save_scratchpads(&mut self) {
+        for doc in &mut self.documents {
+            let Some(path) = doc
+                .file_path
+                .clone()
+                .filter(|path| scratch::is_scratch(path))
+            else {
+                continue;
+            };
+            if doc.dirty {
+                match doc.save_with_formatting(&path) {
+                    Ok(_) => doc.dirty = false,
+                    Err(e) => self.error_message = Some(e),
+                }
+            }
+        }
+    }
+
+    // Save the modified documents that autosave
+    fn save_autosaved(&mut self) {
+        let indices: Vec<usize> = (0..self.documents.len())
+            .filter(|index| {
+                let doc = &self.documents[*index];
+                doc.dirty && self.autosaves(doc)
+            })
+            .collect();
+        let keep = self.backups_to_keep();
+        for &index in &indices {
+            let doc = &mut self.documents[index];
+            let Some(path) = doc.file_path.clone() else {
+                continue;
+            };
+            match backup::back_up(&path, keep).and_then(|()| doc.save_with_formatting(&path)) {
+                Ok(_) => doc.dirty = false,
+                Err(e) => self.error_message = Some(e),
+            }
+        }
+        if !indices.is_empty() {
+            self.notebook.refresh_status();
+        }
+    }
+
+    // Hand copies of the unsaved buffers to the recovery thread
+    fn write_recovery(&mut self) {
+        self.last_recovery = Instant::now();
+        // Keep the previous session's files until the user answers the restore prompt
+        if self.recovered.is_empty() {
+            if let Err(e) = self.recovery.write(&self.documents) {
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    fn restore_recovered(&mut self) {
+        let recovered = std::mem::take(&mut self.recovered);
+        for buffer in &recovered {
+            // Replace a restored session tab for the same file
+            let open_tab = buffer.original_path.as_ref().and_then(|original| {
+                self.documents
+                    .iter()
+                    .position(|doc| doc.file_path.as_ref() == Some(original))
+            });
+            if let Some(index) = open_tab {
+                self.active_tab = index;
+            } else if !self.doc().is_blank() {
+                self.new_tab();
+            }
+            let doc = self.doc_mut();
+            match doc.load_with_formatting(&buffer.recovery_file) {
+                Ok(_) => {
+                    doc.file_path = buffer.original_path.clone();
+                    doc.dirty = true;
+                }
+                Err(e) => self.error_message = Some(e),
+            }
+        }
+        autosave::discard(&recovered);
+    }
+
+    fn show_recovery_window(&mut self, ctx: &egui::Context) {
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Recover unsaved notes?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    "The app did not exit cleanly last time. These unsaved notes can be restored:",
+                );
+                for buffer in &self.recovered {
+                    let name = buffer
+                        .original_path
+                        .as_ref()
+                        .map_or_else(|| "Untitled".to_string(), |path| path.display().to_string());
+                    ui.label(format!("📄 {}", name));
+                }
+                ui.horizontal(|ui| {
+                    restore = ui.button("Restore").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        if restore {
+            self.restore_recovered();
+        } else if discard {
+            autosave::discard(&std::mem::take(&mut self.recovered));
+        }
+    }
+
+    fn go_to(&mut self) {
+        let doc = &mut self.documents[self.active_tab];
+        let target = match self.go_to_input.trim().parse::<usize>() {
+            Ok(line) if !self.go_to_offset && line >= 1 => doc.line_start_char(line - 1),
+            Ok(offset) if self.go_to_offset => doc.byte_to_char(offset),
+            _ => {
+                self.error_message = Some(format!(
+                    "Invalid {}: {}",
+                    if self.go_to_offset {
+                        "offset"
+                    } else {
+                        "line number"
+                    },
+                    self.go_to_input.trim()
+                ));
+                return;
+            }
+        };
+        doc.cursor_range = Some(target..target);
+        doc.pending_cursor_pos = Some(target);
+        doc.skip_cursor_capture = true;
+        self.error_message = None;
+        self.show_go_to = false;
+    }
+
+    fn show_go_to_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_go_to;
+        let line_count = self.doc().text_content.split('\n').count();
+        let byte_count = self.doc().text_content.len();
+        let mut go = false;
+        egui::Window::new("Go To")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.go_to_offset, false, "Line");
+                    ui.radio_value(&mut self.go_to_offset, true, "Byte offset");
+                });
+                ui.horizontal(|ui| {
+                    let input = ui.text_edit_singleline(&mut self.go_to_input);
+                    input.request_focus();
+                    go = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    go |= ui.button("Go").clicked();
+                });
+                ui.label(if self.go_to_offset {
+                    format!("0–{}", byte_count)
+                } else {
+                    format!("1–{}", line_count)
+                });
+            });
+        self.show_go_to = open;
+        if go {
+            self.go_to();
+        }
+    }
+
+    fn show_insert_table_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_insert_table;
+        let mut insert = false;
+        egui::Window::new("Insert Table")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.table_rows).range(1..=50));
+                    ui.label("rows ×");
+                    ui.add(egui::DragValue::new(&mut self.table_columns).range(1..=10));
+                    ui.label("columns");
+                });
+                insert = ui.button("Insert").clicked();
+            });
+        if insert {
+            let (rows, columns) = (self.table_rows, self.table_columns);
+            match self.doc_mut().insert_table(rows, columns) {
+                Ok(()) => open = false,
+                Err(e) => self.error_message = Some(e),
+            }
+        }
+        self.show_insert_table = open;
+    }
+
+    fn show_emoji_window(&mut self, ctx: &egui::Context) {
+        let Some(picker) = &mut self.emoji_picker else {
+            return;
+        };
+        let mut open = true;
+        if let Some(emoji) = picker.show(ctx, &mut open) {
+            self.doc_mut().insert_text(&emoji.to_string());
+        }
+        if !open {
+            self.emoji_picker = None;
+        }
+    }
+
+    fn show_symbol_window(&mut self, ctx: &egui::Context) {
+        let dialog = self.symbol_dialog.get_or_insert_with(SymbolDialog::new);
+        let mut open = self.show_symbols;
+        if let Some(symbol) = dialog.show(ctx, &mut open, &self.settings.recent_symbols) {
+            self.doc_mut().insert_text(&symbol.to_string());
+            let recent = &mut self.settings.recent_symbols;
+            recent.retain(|&c| c != symbol);
+            recent.insert(0, symbol);
+            recent.truncate(symbols::MAX_RECENT);
+        }
+        self.show_symbols = open;
+    }
+
+    fn start_sync(&mut self, ctx: &egui::Context) {
+        let Some(root) = self.notebook.root().map(|root| root.to_path_buf()) else {
+            return;
+        };
+        let account = self.settings.sync_account.clone();
+        self.sync_job = Some(sync::spawn(ctx, move || sync::run(&account, &root)));
+    }
+
+    fn resolve_conflict(
+        &mut self,
+        ctx: &egui::Context,
+        index: usize,
+        resolution: sync::Resolution,
+    ) {
+        let Some(root) = self.notebook.root().map(|root| root.to_path_buf()) else {
+            return;
+        };
+        // An open copy's unsaved edits are saved to be uploaded or merged, or
+        // given up for theirs (undo brings them back)
+        let file = self.sync_conflicts[index]
+            .path
+            .split('/')
+            .fold(root.clone(), |dir, part| dir.join(part));
+        if let Some(open) = self
+            .documents
+            .iter()
+            .position(|doc| doc.dirty && doc.file_path.as_ref() == Some(&file))
+        {
+            if resolution == sync::Resolution::KeepTheirs {
+                self.documents[open].dirty = false;
+            } else if !self.save_document(open) {
+                return;
+            }
+        }
+        let conflict = self.sync_conflicts.remove(index);
+        let account = self.settings.sync_account.clone();
+        self.sync_job = Some(sync::spawn(ctx, move || {
+            sync::resolve(&account, &root, conflict, resolution)
+        }));
+    }
+
+    // Take the result of a finished sync job, reloading open notes whose
+    // files it changed; a note with unsaved edits becomes a conflict instead
+    fn poll_sync(&mut self) {
+        let Some(job) = &self.sync_job else {
+            return;
+        };
+        let mut outcome = match job.try_recv() {
+            Ok(outcome) => outcome,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.sync_job = None;
+                self.sync_status = Some("Sync stopped unexpectedly".to_string());
+                return;
+            }
+        };
+        self.sync_job = None;
+        let root = self.notebook.root().map(|root| root.to_path_buf());
+        for path in &outcome.changed {
+            for doc in &mut self.documents {
+                if doc.file_path.as_ref() != Some(path) || !path.exists() {
+                    continue;
+                }
+                if doc.dirty {
+                    let account = &self.settings.sync_account;
+                    outcome.conflicts.extend(root.as_ref().and_then(|root| {
+                        sync::unsaved_conflict(account, root, path, doc.plain_text())
+                    }));
+                    continue;
+                }
+                doc.save_state_for_undo();
+                if let Err(e) = doc.load_with_formatting(path) {
+                    self.error_message = Some(e);
+                }
+                doc.cursor_range = Some(0..0);
+                doc.pending_cursor_pos = Some(0);
+                doc.skip_cursor_capture = true;
+            }
+        }
+        self.notebook.refresh();
+        self.sync_status = Some(outcome.summary());
+        if let Some(e) = outcome.errors.first() {
+            self.error_message = Some(e.clone());
+        }
+        // A newer conflict over the same note replaces the older
+        let conflicts = outcome.conflicts;
+        self.sync_conflicts
+            .retain(|old| conflicts.iter().all(|new| new.path != old.path));
+        self.sync_conflicts.extend(conflicts);
+    }
+
+    fn show_sync_conflicts_window(&mut self, ctx: &egui::Context) {
+        let mut chosen = None;
+        let idle = self.sync_job.is_none();
+        egui::Window::new("Sync Conflicts")
+            .collapsible(false)
+            .default_size([560.0, 360.0])
+            .show(ctx, |ui| {
+                ui.label("These notes changed both here and on the server since the last sync.");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (index, conflict) in self.sync_conflicts.iter().enumerate() {
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.strong(&conflict.path);
+                            ui.add_enabled_ui(idle, |ui| {
+                                if ui
+                                    .button("Keep mine")
+                                    .on_hover_text("Upload this computer's version")
+                                    .clicked()
+                                {
+                                    chosen = Some((index, sync::Resolution::KeepMine));
+                                }
+                                if ui
+                                    .button("Keep theirs")
+                                    .on_hover_text("Replace this computer's version")
+                                    .clicked()
+                                {
+                                    chosen = Some((index, sync::Resolution::KeepTheirs));
+                                }
+                                if ui
+                                    .button("Merge")
+                                    .on_hover_text(
+                                        "Keep the lines of both, marking differences with \
+                                         <<<<<<< and >>>>>>>; formatting is dropped",
+                                    )
+                                    .clicked()
+                                {
+                                    chosen = Some((index, sync::Resolution::Merge));
+                                }
+                            });
+                        });
+                        ui.columns(2, |columns| {
+                            for (ui, (title, text)) in columns
+                                .iter_mut()
+                                .zip([("Mine", &conflict.mine), ("Theirs", &conflict.theirs)])
+                            {
+                                ui.label(title);
+                                egui::ScrollArea::vertical()
+                                    .id_salt((index, title))
+                                    .max_height(120.0)
+                                    .show(ui, |ui| {
+                                        ui.monospace(text.as_str());
+                                    });
+                            }
+                        });
+                    }
+                });
+            });
+        if let Some((index, resolution)) = chosen {
+            self.resolve_conflict(ctx, index, resolution);
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        let mut choose_dictionary = false;
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            ui.checkbox(&mut self.settings.autosave, "Autosave documents")
+                .on_hover_text(
+                    "Periodically save modified documents that already have a file; \
+                         a document can override this from the status bar",
+                );
+            ui.horizontal(|ui| {
+                ui.label("Interval:");
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.autosave_interval_secs)
+                        .range(10..=3600)
+                        .suffix(" s"),
+                );
+            })
+            .response
+            .on_hover_text(format!(
+                "Version history snapshots are also taken at this interval. Unsaved \
+                     buffers are copied to the recovery folder every {} s regardless",
+                autosave::RECOVERY_INTERVAL.as_secs()
+            ));
+            ui.checkbox(
+                &mut self.settings.autosave_on_focus_loss,
+                "Also autosave when switching to another window",
+            );
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.settings.backup_on_save, "Back up files on save");
+                ui.add_enabled(
+                    self.settings.backup_on_save,
+                    egui::DragValue::new(&mut self.settings.backup_count)
+                        .range(1..=50)
+                        .suffix(" kept"),
+                );
+            })
+            .response
+            .on_hover_text(
+                "Copy the previous file beside it before overwriting it: one keeps \
+                     \"name.bak\", more keep timestamped \"name.<time>.bak\" copies",
+            );
+
+            ui.separator();
+            ui.checkbox(
+                &mut self.settings.restore_session,
+                "Reopen files from the last session",
+            )
+            .on_hover_text("Also restores cursor and scroll positions and the font size");
+            ui.separator();
+            ui.checkbox(&mut self.settings.keep_history, "Keep version history")
+                .on_hover_text("Snapshot documents on save and at the autosave interval");
+            ui.add_enabled_ui(self.settings.keep_history, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Keep up to");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.history_max_snapshots)
+                            .range(1..=1000),
+                    );
+                    ui.label("snapshots or");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.history_max_mb)
+                            .range(1..=1000)
+                            .suffix(" MB"),
+                    );
+                    ui.label("per document");
+                });
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Indent with");
+                ui.radio_value(&mut self.settings.indent_with_tabs, false, "spaces");
+                ui.radio_value(&mut self.settings.indent_with_tabs, true, "tabs");
+                ui.label("Tab width:");
+                ui.add(egui::DragValue::new(&mut self.settings.tab_width).range(1..=16));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Line spacing:");
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.line_spacing)
+                        .range(1.0..=3.0)
+                        .speed(0.05)
+                        .suffix("×"),
+                );
+                ui.label("Space after paragraphs:");
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.paragraph_spacing)
+                        .range(0.0..=48.0)
+                        .suffix(" pt"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Focus mode column width:");
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.focus_width)
+                        .range(300.0..=2000.0)
+                        .speed(10.0)
+                        .suffix(" px"),
+                );
+            });
+            ui.checkbox(&mut self.settings.auto_indent, "Auto-indent new lines");
+            ui.checkbox(
+                &mut self.settings.match_brackets,
+                "Highlight matching brackets",
+            );
+            ui.checkbox(
+                &mut self.settings.highlight_line,
+                "Highlight the current line",
+            );
+            ui.checkbox(
+                &mut self.settings.auto_close_brackets,
+                "Auto-close brackets and quotes",
+            );
+            ui.collapsing("Autocorrect", |ui| {
+                let autocorrect = &mut self.settings.autocorrect;
+                ui.checkbox(&mut autocorrect.smart_quotes, "Smart quotes “ ” and ‘ ’");
+                ui.checkbox(&mut autocorrect.dashes, "-- between words to em dash —");
+                ui.checkbox(&mut autocorrect.ellipsis, "... to ellipsis …");
+                ui.checkbox(
+                    &mut autocorrect.replace_words,
+                    "Replace words from the table",
+                )
+                .on_hover_text("When the word is finished with a space or punctuation");
+                ui.add_enabled_ui(autocorrect.replace_words, |ui| {
+                    let mut remove = None;
+                    egui::Grid::new("autocorrect_table").show(ui, |ui| {
+                        for (i, (typed, replacement)) in autocorrect.table.iter_mut().enumerate() {
+                            ui.add(egui::TextEdit::singleline(typed).desired_width(100.0));
+                            ui.label("→");
+                            ui.add(egui::TextEdit::singleline(replacement).desired_width(100.0));
+                            if ui.small_button("🗑").labeled("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(i) = remove {
+                        autocorrect.table.remove(i);
+                    }
+                    if ui.button("+ Add").clicked() {
+                        autocorrect.table.push(Default::default());
+                    }
+                });
+            });
+            ui.checkbox(
+                &mut self.settings.complete_as_you_type,
+                "Suggest completions while typing",
+            )
+            .on_hover_text(format!(
+                "After {} letters of a word; the completion shortcut works any time",
+                COMPLETE_AFTER
+            ));
+            ui.horizontal(|ui| {
+                ui.label("Dictionary:");
+                let name = self
+                    .settings
+                    .dictionary_file
+                    .as_ref()
+                    .and_then(|path| path.file_name())
+                    .map_or("None".to_string(), |name| {
+                        name.to_string_lossy().into_owned()
+                    });
+                ui.label(name)
+                    .on_hover_text("Words from this file are offered as completions");
+                if ui.button("Choose...").clicked() {
+                    choose_dictionary = true;
+                }
+                if self.settings.dictionary_file.is_some() && ui.button("Clear").clicked() {
+                    self.settings.dictionary_file = None;
+                    self.dictionary.clear();
+                }
+            });
+            ui.separator();
+            ui.label("WebDAV sync of the notes folder").on_hover_text(
+                "For Nextcloud, use the WebDAV address shown in its file settings, plus a folder",
+            );
+            egui::Grid::new("sync_account")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    let account = &mut self.settings.sync_account;
+                    ui.label("Folder URL:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut account.url)
+                            .hint_text("https://cloud.example.com/remote.php/dav/files/me/Notes"),
+                    );
+                    ui.end_row();
+                    ui.label("User:");
+                    ui.text_edit_singleline(&mut account.user);
+                    ui.end_row();
+                    ui.label("Password:");
+                    ui.add(egui::TextEdit::singleline(&mut account.password).password(true))
+                        .on_hover_text("Stored with the app settings; an app password is best");
+                    ui.end_row();
+                });
+            ui.separator();
+            if ui.button("⌨ Keyboard Shortcuts...").clicked() {
+                self.show_shortcuts = true;
+            }
+        });
+        self.settings.common = common;
+        self.show_settings = open;
+        if choose_dictionary {
+            let path = self.settings.common.dialogs.open(&[FileType::WordList]);
+            if let Some(path) = path {
+                self.load_dictionary(&path);
+                self.settings.dictionary_file = Some(path);
+            }
+        }
+    }
+
+    fn show_shortcuts_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_shortcuts;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.settings
+                    .keymap
+                    .show_editor(ui, &mut self.shortcut_capture);
+            });
+        if !open {
+            self.shortcut_capture = None;
+        }
+        self.show_shortcuts = open;
+    }
+
+    fn selection_stats(&self) -> Option<text::Stats> {
+        let doc = self.doc();
+        let range = doc.cursor_range.clone().filter(|range| !range.is_empty())?;
+        Some(text::stats(text::char_slice(&doc.text_content, range)))
+    }
+
+    fn show_word_count_window(&mut self, ctx: &egui::Context) {
+        // Each statistic as text, for the document and for the selection
+        let column = |text: &str| {
+            let stats = text::stats(text);
+            let mut values = vec![
+                stats.chars.to_string(),
+                stats.words.to_string(),
+                stats.lines.to_string(),
+            ];
+            match text::readability(text) {
+                Some(readability) => values.extend([
+                    readability.sentences.to_string(),
+                    format!("{:.1}", readability.reading_ease),
+                    format!("{:.1}", readability.grade),
+                    format!("{:.1}", readability.words_per_sentence),
+                    format!("{:.1}", readability.letters_per_word),
+                ]),
+                None => values.resize(8, "—".to_string()),
+            }
+            values
+        };
+        let doc = self.doc();
+        let document = column(&doc.text_content);
+        let selection = doc
+            .cursor_range
+            .clone()
+            .filter(|range| !range.is_empty())
+            .map(|range| column(text::char_slice(&doc.text_content, range)));
+        let labels = [
+            ("Characters", ""),
+            ("Words", ""),
+            ("Lines", ""),
+            ("Sentences", "Ended by . ! or ?"),
+            (
+                "Reading ease",
+                "Flesch reading ease: 0-100, higher is easier; plain English scores 60-70",
+            ),
+            (
+                "Grade level",
+                "Flesch–Kincaid grade: years of US schooling needed",
+            ),
+            ("Words per sentence", ""),
+            ("Letters per word", ""),
+        ];
+        let mut open = self.show_word_count;
+        egui::Window::new("Word Count")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("word_count")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.strong("Document");
+                        ui.strong("Selection");
+                        ui.end_row();
+                        for (index, (label, hint)) in labels.into_iter().enumerate() {
+                            let response = ui.label(label);
+                            if !hint.is_empty() {
+                                response.on_hover_text(hint);
+                            }
+                            ui.label(&document[index]);
+                            ui.label(
+                                selection
+                                    .as_ref()
+                                    .map_or("—", |selection| selection[index].as_str()),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                ui.weak("Readability scores are estimates for English text");
+            });
+        self.show_word_count = open;
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+            Command::Find => self.show_find_replace = !self.show_find_replace,
+            Command::GoTo => {
+                self.show_go_to = !self.show_go_to;
+                self.go_to_input.clear();
+            }
+            Command::Indent => {
+                let unit = self.indent_unit();
+                self.doc_mut().indent(&unit);
+            }
+            Command::Outdent => {
+                let tab_width = self.settings.tab_width;
+                self.doc_mut().outdent(tab_width);
+            }
+            Command::DuplicateLines => self.doc_mut().duplicate_lines(),
+            Command::DeleteLines => self.doc_mut().delete_lines(),
+            Command::MoveLinesUp => self.doc_mut().move_lines(true),
+            Command::MoveLinesDown => self.doc_mut().move_lines(false),
+            Command::NextTab => self.cycle_tab(true),
+            Command::PreviousTab => self.cycle_tab(false),
+            Command::ZoomIn => self.set_font_size(self.font_size + 2.0),
+            Command::ZoomOut => self.set_font_size(self.font_size - 2.0),
+            Command::ZoomReset => self.font_size = DEFAULT_FONT_SIZE,
+            Command::Complete => {
+                self.completion = Completion::link(self.doc(), &self.note_names())
+                    .or_else(|| Completion::new(self.doc(), &self.dictionary, 1));
+            }
+            Command::FocusMode => self.focus_mode = !self.focus_mode,
+            Command::Preview => self.preview = !self.preview,
+            Command::Footnote => self.insert_footnote(),
+            Command::ExtractNote => self.open_extract_note(),
+            Command::OpenLinks => self.open_selected_links(),
+            Command::Calculate => self.calculate_selection(),
+            Command::RecordMacro => self.toggle_macro_recording(),
+            Command::PlayMacro => {
+                let events = self.macros.last.clone();
+                self.play_macro(events);
+            }
+            Command::MenuBar => {
+                // The menu bar is hidden in focus mode
+                self.focus_mode = false;
+                self.focus_menu_bar = true;
+            }
+            Command::ShowLog => self.show_log = !self.show_log,
+            Command::ReadAloud => self.toggle_read_aloud(),
+            Command::AlignTable => {
+                self.doc_mut().edit_pipe_table(TableEdit::Align);
+            }
+        }
+    }
+
+    // Read the selection, or the whole document, or stop reading
+    fn toggle_read_aloud(&mut self) {
+        if self.reader.take().is_some() {
+            return;
+        }
+        let doc = self.doc();
+        let range = doc
+            .cursor_range
+            .clone()
+            .filter(|range| !range.is_empty())
+            .unwrap_or(0..doc.char_count());
+        self.reader = speech::Reader::new(doc.id, &doc.text_content, range);
+    }
+
+    // Start each sentence when the previous one is done, and show the
+    // reading controls
+    fn show_read_aloud_window(&mut self, ctx: &egui::Context) {
+        let Some(reader) = &mut self.reader else {
+            return;
+        };
+        let Some(doc) = self.documents.iter().find(|doc| doc.id == reader.doc_id) else {
+            self.reader = None;
+            return;
+        };
+        match reader.poll(&doc.text_content) {
+            Ok(true) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Ok(false) => {
+                self.reader = None;
+                return;
+            }
+            Err(e) => {
+                self.error_message = Some(e);
+                self.reader = None;
+                return;
+            }
+        }
+        let mut stop = false;
+        egui::Window::new("Read Aloud")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-16.0, -40.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if reader.paused {
+                        if ui.button("▶ Resume").clicked() {
+                            reader.resume();
+                        }
+                    } else if ui.button("⏸ Pause").clicked() {
+                        reader.pause();
+                    }
+                    stop = ui.button("⏹ Stop").clicked();
+                    let (current, total) = reader.progress();
+                    ui.label(format!("Sentence {} of {}", current, total));
+                });
+            });
+        if stop {
+            self.reader = None;
+        }
+    }
+
+    fn load_dictionary(&mut self, path: &std::path::Path) {
+        match complete::load_dictionary(path) {
+            Ok(words) => self.dictionary = words,
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    // Keys for the completion list, taken before the editor and the
+    // shortcuts see them
+    fn handle_completion_keys(&mut self, ctx: &egui::Context) {
+        let pane = self.focused_pane();
+        let doc_id = self.doc().id;
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+        if !ctx.memory(|m| m.has_focus(editor_id(doc_id, pane))) {
+            if !completion.hovered {
+                self.completion = None;
+            }
+            return;
+        }
+        match completion.handle_keys(ctx) {
+            Some(CompletionKey::Accept) => self.accept_completion(),
+            Some(CompletionKey::Cancel) => self.completion = None,
+            None => {}
+        }
+    }
+
+    // Follow the word being typed, and close the list when the cursor
+    // leaves it
+    fn update_completion(&mut self) {
+        let typed = std::mem::take(&mut self.typed);
+        let doc = &self.documents[self.active_tab];
+        if let Some(completion) = &self.completion {
+            let end = completion.start + text::char_count(&completion.prefix);
+            if completion.doc_id != doc.id || (!typed && doc.cursor_range != Some(end..end)) {
+                self.completion = None;
+            }
+        }
+        if !typed {
+            return;
+        }
+        // Note names inside [[, otherwise words; a list of note names closes
+        // rather than turning into one of words
+        let open = self
+            .completion
+            .as_ref()
+            .map(|completion| completion.suffix.is_empty());
+        self.completion = Completion::link(doc, &self.note_names());
+        if self.completion.is_none() {
+            self.completion = match open {
+                Some(true) => Completion::new(doc, &self.dictionary, 1),
+                None if self.settings.complete_as_you_type => {
+                    Completion::new(doc, &self.dictionary, COMPLETE_AFTER)
+                }
+                _ => None,
+            };
+        }
+    }
+
+    fn show_completion(&mut self, ctx: &egui::Context) {
+        let (Some(completion), Some(pos)) = (&mut self.completion, self.completion_pos) else {
+            return;
+        };
+        if let Some(index) = completion.show(ctx, pos) {
+            completion.selected = index;
+            self.accept_completion();
+        }
+    }
+
+    // Replace the start of the word at the cursor with the chosen completion
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let mut word = completion.items[completion.selected].clone();
+        let end = completion.start + text::char_count(&completion.prefix);
+        let doc = self.doc_mut();
+        let after = text::char_slice(&doc.text_content, end..doc.char_count());
+        if !after.starts_with(&completion.suffix) {
+            word.push_str(&completion.suffix);
+        }
+        doc.save_state_for_undo();
+        doc.replace_chars(completion.start..end, &word);
+        let cursor = completion.start + text::char_count(&word);
+        doc.cursor_range = Some(cursor..cursor);
+        doc.pending_cursor_pos = Some(cursor);
+        doc.skip_cursor_capture = true;
+        doc.restore_scroll = true;
+    }
+
+    // One level of indentation, as the settings ask for
+    fn indent_unit(&self) -> String {
+        if self.settings.indent_with_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.settings.tab_width)
+        }
+    }
+
+    fn set_font_size(&mut self, size: f32) {
+        self.font_size = size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+    }
+
+    // Ctrl+scroll (or pinch) scales the editor font; the keyboard zoom
+    // commands are in the keymap
+    fn handle_zoom(&mut self, ctx: &egui::Context) {
+        let zoom = ctx.input(|i| i.zoom_delta());
+        if zoom != 1.0 {
+            self.set_font_size(self.font_size * zoom);
+        }
+    }
+
+    // Ask for a name for a note made from the selection, suggesting its
+    // first line
+    fn open_extract_note(&mut self) {
+        let doc = self.doc();
+        let Some(range) = doc.cursor_range.clone().filter(|range| !range.is_empty()) else {
+            self.error_message = Some("Select the text to extract first".to_string());
+            return;
+        };
+        let first_line = text::char_slice(&doc.text_content, range)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default();
+        let name: String = first_line
+            .chars()
+            .filter(|c| c.is_alphanumeric() || " -_".contains(*c))
+            .take(40)
+            .collect();
+        self.extract = Some(ExtractNote {
+            name: name.trim().to_string(),
+            copy: false,
+            link: true,
+        });
+    }
+
+    fn show_extract_note_window(&mut self, ctx: &egui::Context) {
+        let Some(extract) = &mut self.extract else {
+            return;
+        };
+        let mut open = true;
+        let mut create = false;
+        egui::Window::new("Extract to Note")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    let input = ui.text_edit_singleline(&mut extract.name);
+                    create = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                });
+                ui.radio_value(&mut extract.copy, false, "Move the selection");
+                ui.radio_value(&mut extract.copy, true, "Copy the selection");
+                ui.add_enabled(
+                    !extract.copy,
+                    egui::Checkbox::new(&mut extract.link, "Leave a [[link]] to the new note"),
+                );
+                create |= ui.button("Create Note").clicked();
+            });
+        if create {
+            self.extract_note();
+        } else if !open {
+            self.extract = None;
+        }
+    }
+
+    // Write the selection to a new note in the notes folder, or beside the
+    // current note when there is none
+    fn extract_note(&mut self) {
+        let Some(extract) = self.extract.take() else {
+            return;
+        };
+        let root = self.notebook.root().map(PathBuf::from);
+        let doc = self.doc();
+        let Some(range) = doc.cursor_range.clone().filter(|range| !range.is_empty()) else {
+            return;
+        };
+        let Some(folder) = root.clone().or_else(|| {
+            let path = doc.file_path.as_ref()?;
+            path.parent().map(PathBuf::from)
+        }) else {
+            self.error_message = Some("Choose a notes folder or save this note first".to_string());
+            return;
+        };
+        let note = doc.extract(range.clone());
+        let path = notebook::create_note(&folder, &extract.name)
+            .and_then(|path| note.save_with_formatting(&path).map(|()| path));
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                self.error_message = Some(e);
+                // Keep the window open to choose another name
+                self.extract = Some(extract);
+                return;
+            }
+        };
+        if !extract.copy {
+            let replacement = if extract.link {
+                let name = root
+                    .as_deref()
+                    .and_then(|root| wikilink::note_name(root, &path))
+                    .or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                    .unwrap_or_default();
+                format!("[[{}]]", name)
+            } else {
+                String::new()
+            };
+            let doc = self.doc_mut();
+            doc.save_state_for_undo();
+            doc.replace_chars(range.clone(), &replacement);
+            let end = range.start + replacement.chars().count();
+            doc.cursor_range = Some(end..end);
+            doc.pending_cursor_pos = Some(end);
+            doc.skip_cursor_capture = true;
+        }
+        self.notebook.refresh();
+        self.backlinks = None;
+        self.error_message = Some(format!("Extracted to {}", path.display()));
+    }
+
+    fn show_macros_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_macros;
+        let action = self.macros.show(ctx, &mut self.settings.macros, &mut open);
+        self.show_macros = open;
+        match action {
+            Some(MacroAction::ToggleRecording) => self.toggle_macro_recording(),
+            Some(MacroAction::Play(events)) => self.play_macro(events),
+            None => {}
+        }
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        let stop_key = self
+            .settings
+            .keymap
+            .shortcut(Command::RecordMacro)
+            .map(|shortcut| shortcut.key);
+        self.macros.toggle_recording(stop_key);
+        if self.macros.recording.is_some() {
+            self.error_message = Some("Recording a macro…".to_string());
+        } else if self.error_message.as_deref() == Some("Recording a macro…") {
+            self.error_message = None;
+        }
+    }
+
+    // Keep the keys typed into the editor while recording
+    fn record_macro(&mut self, ctx: &egui::Context) {
+        if self.macros.recording.is_none() || self.macros.playback.is_some() {
+            return;
+        }
+        let editor = editor_id(self.doc().id, self.focused_pane());
+        if ctx.memory(|m| m.has_focus(editor)) {
+            let events = ctx.input(|i| i.events.clone());
+            self.macros.record(&events);
+        }
+    }
+
+    fn play_macro(&mut self, events: Vec<egui::Event>) {
+        let caret = self
+            .doc()
+            .cursor_range
+            .as_ref()
+            .map_or(0, |range| range.end);
+        self.macros.play(events, caret);
+    }
+
+    // Hand the editor the next keys of the macro being played; a run ends
+    // when its keys have been typed, and the next begins unless it was the
+    // last one, or, running to the end, the caret stopped moving forward
+    fn play_macro_step(&mut self, ctx: &egui::Context) {
+        let editor = editor_id(self.doc().id, self.focused_pane());
+        let caret = self
+            .doc()
+            .cursor_range
+            .as_ref()
+            .map_or(0, |range| range.end);
+        let at_end = caret >= self.doc().char_count();
+        // Typed text can go in one batch unless it is corrected as it goes
+        let batch_text =
+            !self.settings.autocorrect.any_enabled() && !self.settings.auto_close_brackets;
+        let Some(playback) = &mut self.macros.playback else {
+            return;
+        };
+        ctx.request_repaint();
+        if !ctx.memory(|m| m.has_focus(editor)) {
+            ctx.memory_mut(|m| m.request_focus(editor));
+            return;
+        }
+        if playback.position == playback.events.len() {
+            let finished = match &mut playback.runs_left {
+                Some(runs) => {
+                    *runs -= 1;
+                    *runs == 0
+                }
+                None => at_end || caret <= playback.run_start,
+            };
+            if finished {
+                self.macros.playback = None;
+                return;
+            }
+            playback.position = 0;
+            playback.run_start = caret;
+        }
+        let end = macros::next_batch(&playback.events, playback.position, batch_text);
+        let batch = playback.events[playback.position..end].to_vec();
+        playback.position = end;
+        ctx.input_mut(|i| i.events.extend(batch));
+    }
+
+    fn open_url(&mut self, url: &str) {
+        if let Err(e) = weblink::open(url) {
+            self.error_message = Some(e);
+        }
+    }
+
+    // Open every web address in the selection, or the one at the caret
+    fn open_selected_links(&mut self) {
+        let doc = self.doc();
+        let Some(range) = doc.cursor_range.clone() else {
+            return;
+        };
+        let urls = if range.is_empty() {
+            weblink::link_at(&doc.text_content, range.start)
+                .into_iter()
+                .collect()
+        } else {
+            weblink::links_in(&doc.text_content, range)
+        };
+        if urls.is_empty() {
+            self.error_message = Some("No web addresses in the selection".to_string());
+        }
+        for url in urls {
+            self.open_url(&url);
+        }
+    }
+
+    // Work out the selected expression in the calculator, starting it if
+    // need be; its result can be sent back with Insert into Note
+    fn calculate_selection(&mut self) {
+        let doc = self.doc();
+        let Some(range) = doc.cursor_range.clone().filter(|range| !range.is_empty()) else {
+            self.error_message = Some("Select an expression to calculate first".to_string());
+            return;
+        };
+        let expression = text::char_slice(&doc.text_content, range)
+            .trim()
+            .to_string();
+        let job = ipc::send_in_background(ipc::CALCULATOR, Message::Calculate(expression.clone()));
+        self.calculate_job = Some((expression, job));
+    }
+
+    fn poll_calculate(&mut self, ctx: &egui::Context) {
+        let Some((expression, job)) = &self.calculate_job else {
+            return;
+        };
+        let result = match job.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return;
+            }
+            Err(TryRecvError::Disconnected) => Err(ipc::SendError::Failed(
+                "Sending to the calculator stopped unexpectedly".to_string(),
+            )),
+        };
+        match result {
+            Ok(()) => {}
+            Err(ipc::SendError::NotRunning(_)) => {
+                if let Err(e) = launch::start_app("calc_app", ["--calculate", expression]) {
+                    self.error_message = Some(e);
+                }
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+        self.calculate_job = None;
+    }
+
+    // Text sent from other apps goes in at the caret, like a paste
+    fn receive_messages(&mut self) {
+        let messages = self
+            .ipc
+            .as_ref()
+            .map(ipc::Listener::messages)
+            .unwrap_or_default();
+        for message in messages {
+            match message {
+                Message::Insert(text) => {
+                    self.doc_mut().insert_text(&text);
+                    self.status_message = Some((
+                        format!("Inserted {} from the calculator", text),
+                        Instant::now(),
+                    ));
+                }
+                Message::Calculate(_) => {}
+            }
+        }
+    }
+
+    fn insert_footnote(&mut self) {
+        if let Err(e) = self.doc_mut().insert_footnote() {
+            self.error_message = Some(e);
+        }
+    }
+
+    // The active document as it reads, with the source left untouched
+    fn show_preview(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.page_view, false, "Continuous");
+            ui.selectable_value(&mut self.page_view, true, "📄 Pages")
+                .on_hover_text("How the document breaks across pages when printed");
+            if self.page_view {
+                ui.separator();
+                let page = &mut self.settings.page;
+                egui::ComboBox::from_id_salt("paper_size")
+                    .selected_text(page.paper.label())
+                    .show_ui(ui, |ui| {
+                        for paper in PaperSize::ALL {
+                            ui.selectable_value(&mut page.paper, paper, paper.label());
+                        }
+                    });
+                ui.checkbox(&mut page.landscape, "Landscape");
+                ui.label("Margins:");
+                ui.add(
+                    egui::DragValue::new(&mut page.margin_mm)
+                        .range(0.0..=60.0)
+                        .suffix(" mm"),
+                );
+            }
+        });
+        ui.separator();
+
+        let doc = self.doc();
+        let job = preview_job(doc, &self.font_family, self.font_size);
+        egui::ScrollArea::vertical()
+            .id_salt(("preview", doc.id))
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if self.page_view {
+                    show_pages(ui, job, &self.settings.page);
+                } else {
+                    ui.add(egui::Label::new(job).wrap());
+                }
+            });
+    }
+
+    fn doc(&self) -> &Document {
+        &self.documents[self.active_tab]
+    }
+
+    fn doc_mut(&mut self) -> &mut Document {
+        &mut self.documents[self.active_tab]
+    }
+
+    fn new_tab(&mut self) {
+        self.documents.push(Document::new(self.next_document_id));
+        self.next_document_id += 1;
+        self.active_tab = self.documents.len() - 1;
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        self.documents.remove(index);
+        if self.documents.is_empty() {
+            self.new_tab();
+        } else if self.active_tab > index || self.active_tab >= self.documents.len() {
+            self.active_tab = self.active_tab.saturating_sub(1);
+        }
+        // The other pane falls back to the active document
+        let active_id = self.doc().id;
+        if let Some(split) = &mut self.split {
+            if !self.documents.iter().any(|doc| doc.id == split.other) {
+                split.other = active_id;
+            }
+        }
+    }
+
+    fn focused_pane(&self) -> usize {
+        self.split
+            .as_ref()
+            .map_or(0, |split| usize::from(split.second_focused))
+    }
+
+    fn open_split(&mut self, vertical: bool) {
+        match &mut self.split {
+            Some(split) => split.vertical = vertical,
+            None => {
+                self.split = Some(SplitView {
+                    vertical,
+                    other: self.doc().id,
+                    second_focused: false,
+                })
+            }
+        }
+    }
+
+    fn close_split(&mut self) {
+        self.split = None;
+        // The single editor takes over the cursor of the pane that had focus
+        let doc = self.doc_mut();
+        if let Some(range) = doc.cursor_range.clone() {
+            doc.pending_cursor_pos = Some(range.end);
+            doc.skip_cursor_capture = true;
+        }
+    }
+
+    // Lay out both panes of the split view, moving the focus (and the active
+    // tab) to whichever pane the user clicked into
+    fn show_split_panes(&mut self, ui: &mut egui::Ui) {
+        let Some(split) = &self.split else {
+            return;
+        };
+        let other = self
+            .documents
+            .iter()
+            .position(|doc| doc.id == split.other)
+            .unwrap_or(self.active_tab);
+        let indices = if split.second_focused {
+            [other, self.active_tab]
+        } else {
+            [self.active_tab, other]
+        };
+        let rect = ui.available_rect_before_wrap();
+        let (first, second) = if split.vertical {
+            rect.split_left_right_at_fraction(0.5)
+        } else {
+            rect.split_top_bottom_at_fraction(0.5)
+        };
+        let focused_pane = self.focused_pane();
+
+        let mut clicked_pane = None;
+        for (pane, (index, rect)) in indices.into_iter().zip([first, second]).enumerate() {
+            let mut pane_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .id_salt(("pane", pane))
+                    .max_rect(rect.shrink(4.0)),
+            );
+            let focused = pane == focused_pane;
+            if self.show_editor_pane(&mut pane_ui, index, pane, focused) && !focused {
+                clicked_pane = Some(pane);
+            }
+        }
+        let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+        if self.split.as_ref().is_some_and(|split| split.vertical) {
+            ui.painter().vline(first.right(), rect.y_range(), stroke);
+        } else {
+            ui.painter().hline(rect.x_range(), first.bottom(), stroke);
+        }
+        ui.allocate_rect(rect, egui::Sense::hover());
+
+        if let (Some(pane), Some(split)) = (clicked_pane, &mut self.split) {
+            split.other = self.documents[self.active_tab].id;
+            split.second_focused = pane == 1;
+            self.active_tab = indices[pane];
+        }
+    }
+
+    // One editor with its line numbers in its own scroll area. Returns whether
+    // the editor has the keyboard focus
+    fn show_editor_pane(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        pane: usize,
+        focused: bool,
+    ) -> bool {
+        // One scroll area per document and pane, so each tab keeps its own
+        // scroll position
+        let doc = &self.documents[index];
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_salt(("editor_scroll", doc.id, pane))
+            .auto_shrink([false, false]);
+        if focused && doc.restore_scroll {
+            scroll_area = scroll_area.vertical_scroll_offset(doc.scroll_offset);
+        }
+        let mut has_focus = false;
+        // The minimap takes a strip on the right
+        let mut editor_rect = ui.available_rect_before_wrap();
+        let minimap_rect = (self.settings.show_minimap && !self.focus_mode).then(|| {
+            let strip = egui::Rect::from_x_y_ranges(
+                editor_rect.right() - minimap::WIDTH..=editor_rect.right(),
+                editor_rect.y_range(),
+            );
+            editor_rect.max.x = strip.left() - 4.0;
+            strip
+        });
+        let mut editor_ui = ui.new_child(egui::UiBuilder::new().max_rect(editor_rect));
+        let scroll_output = scroll_area.show(&mut editor_ui, |ui| {
+            ui.horizontal_top(|ui| {
+                // Room for the line numbers, drawn once the editor rows are laid out
+                let font_id = fonts::font_id(&self.font_family, self.font_size);
+                let digits = self.documents[index]
+                    .text_content
+                    .lines()
+                    .count()
+                    .max(1)
+                    .to_string()
+                    .len();
+                let digit_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+                let gutter_width = digits.max(3) as f32 * digit_width + 8.0;
+                let gutter_left = ui.cursor().min.x;
+                let show_gutter = self.show_line_numbers && !self.focus_mode;
+                if show_gutter {
+                    ui.add_space(gutter_width);
+                    ui.separator();
+                }
+                // Focus mode centers a column of the chosen width
+                let column_width = self.settings.focus_width;
+                if self.focus_mode {
+                    ui.add_space(((ui.available_width() - column_width) / 2.0).max(0.0));
+                }
+
+                // Text editor column
+                let output = ui
+                    .vertical(|ui| {
+                        if self.focus_mode {
+                            ui.set_max_width(column_width);
+                        }
+                        self.render_rich_text_editable(ui, index, pane, focused)
+                    })
+                    .inner;
+                has_focus = output.response.has_focus();
+
+                // Ctrl+click follows a [[link]] or jumps between a footnote
+                // reference and its note
+                let hover = output
+                    .response
+                    .hover_pos()
+                    .filter(|_| ui.input(|i| i.modifiers.command));
+                if let Some(pos) = hover {
+                    let cursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+                    let doc = &mut self.documents[index];
+                    if let Some(target) = wikilink::link_at(&doc.text_content, cursor.ccursor.index)
+                    {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                        if output.response.clicked() {
+                            self.pending_link = Some(target);
+                        }
+                    } else if let Some(target) = doc.footnote_target(cursor.ccursor.index) {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                        if output.response.clicked() {
+                            doc.cursor_range = Some(target..target);
+                            doc.pending_cursor_pos = Some(target);
+                            doc.skip_cursor_capture = true;
+                        }
+                    } else if let Some(url) =
+                        weblink::link_at(&doc.text_content, cursor.ccursor.index)
+                    {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                        if output.response.clicked() {
+                            self.open_url(&url);
+                        }
+                    }
+                }
+
+                // Right-click menu for web addresses
+                if output.response.secondary_clicked() {
+                    let doc = &self.documents[index];
+                    self.context_link = output.response.interact_pointer_pos().and_then(|pos| {
+                        let cursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+                        weblink::link_at(&doc.text_content, cursor.ccursor.index)
+                    });
+                }
+                output.response.context_menu(|ui| {
+                    let link = self.context_link.clone();
+                    if ui
+                        .add_enabled(link.is_some(), egui::Button::new("🔗 Open Link"))
+                        .on_disabled_hover_text("Right-click a web address")
+                        .clicked()
+                    {
+                        if let Some(url) = link {
+                            self.open_url(&url);
+                        }
+                        ui.close_menu();
+                    }
+                    let shortcut = self
+                        .settings
+                        .keymap
+                        .shortcut(Command::OpenLinks)
+                        .map_or(String::new(), |shortcut| shortcut.label());
+                    if ui
+                        .add(egui::Button::new("Open Links in Selection").shortcut_text(shortcut))
+                        .clicked()
+                    {
+                        self.open_selected_links();
+                        ui.close_menu();
+                    }
+                });
+
+                if show_gutter {
+                    let gutter = egui::Rect::from_x_y_ranges(
+                        gutter_left..=gutter_left + gutter_width,
+                        output.response.rect.y_range(),
+                    );
+                    has_focus |= self.show_gutter(ui, index, pane, gutter, &font_id, &output);
+                }
+            });
+        });
+        if let Some(rect) = minimap_rect {
+            let search = if self.show_find_replace {
+                self.find_text.as_str()
+            } else {
+                ""
+            };
+            let marks = minimap::line_marks(&self.documents[index], search);
+            let view = minimap::View {
+                offset: scroll_output.state.offset.y,
+                height: scroll_output.inner_rect.height(),
+                content_height: scroll_output.content_size.y,
+            };
+            if let Some(offset) = minimap::show(ui, rect, &marks, &view) {
+                let mut state = scroll_output.state;
+                state.offset.y = offset;
+                state.store(ui.ctx(), scroll_output.id);
+                ui.ctx().request_repaint();
+            }
+        }
+        ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
+
+        if focused {
+            let doc = &mut self.documents[index];
+            doc.scroll_offset = scroll_output.state.offset.y;
+            doc.restore_scroll = false;
+        }
+        has_focus
+    }
+
+    // Line numbers beside the editor rows, so a wrapped line keeps one number
+    // on its first row. Clicking a number selects the line and dragging
+    // selects a range of lines; returns whether the gutter was clicked
+    fn show_gutter(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        pane: usize,
+        gutter: egui::Rect,
+        font_id: &egui::FontId,
+        output: &egui::text_edit::TextEditOutput,
+    ) -> bool {
+        let focused = pane == self.focused_pane();
+        let doc = &mut self.documents[index];
+        // Zero-based line and screen y range of each row
+        let mut rows = Vec::new();
+        let mut line = 0;
+        for row in &output.galley.rows {
+            let top = output.galley_pos.y + row.rect.min.y;
+            rows.push((line, top..=top + row.rect.height()));
+            if row.ends_with_newline {
+                line += 1;
+            }
+        }
+        let current_line = TextEdit::load_state(ui.ctx(), editor_id(doc.id, pane))
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| doc.line_col(range.primary.index).0 - 1);
+
+        let painter = ui.painter();
+        let highlight = ui.visuals().faint_bg_color;
+        let mut previous = None;
+        for (line, y_range) in &rows {
+            let current = current_line == Some(*line);
+            if current {
+                let rect = egui::Rect::from_x_y_ranges(gutter.x_range(), y_range.clone());
+                painter.rect_filled(rect, 0.0, highlight);
+            }
+            if previous != Some(*line) {
+                let color = if current {
+                    ui.visuals().strong_text_color()
+                } else {
+                    Color32::DARK_GRAY
+                };
+                painter.text(
+                    egui::pos2(
+                        gutter.right() - 4.0,
+                        (y_range.start() + y_range.end()) / 2.0,
+                    ),
+                    egui::Align2::RIGHT_CENTER,
+                    (line + 1).to_string(),
+                    font_id.clone(),
+                    color,
+                );
+            }
+            previous = Some(*line);
+        }
+
+        let response = ui.interact(
+            gutter,
+            ui.id().with(("gutter", doc.id, pane)),
+            egui::Sense::click_and_drag(),
+        );
+        let pressed = response.hovered() && ui.input(|i| i.pointer.primary_pressed());
+        if !pressed && !response.dragged() {
+            return false;
+        }
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return false;
+        };
+        let Some(line) = rows
+            .iter()
+            .find(|(_, y_range)| pointer.y <= *y_range.end())
+            .or(rows.last())
+            .map(|(line, _)| *line)
+        else {
+            return false;
+        };
+        // The line where the press started anchors a drag selection
+        if pressed {
+            ui.data_mut(|d| d.insert_temp(response.id, line));
+        }
+        let anchor = ui.data(|d| d.get_temp(response.id)).unwrap_or(line);
+        let range = doc.lines_char_range(anchor.min(line), anchor.max(line));
+        doc.cursor_range = Some(range.clone());
+        doc.pending_cursor_pos = Some(range.end);
+        doc.skip_cursor_capture = true;
+        // Keep the view still instead of centering the selection; the other
+        // pane of a split view has no recorded scroll offset to keep
+        doc.restore_scroll = focused;
+        true
+    }
+
+    // Close a tab, asking first if it has unsaved changes
+    // Close the tabs at once, asking first about those with unsaved changes
+    fn request_close_tabs(&mut self, indices: &[usize]) {
+        self.save_scratchpads();
+        let (dirty, clean): (Vec<&Document>, Vec<&Document>) = indices
+            .iter()
+            .map(|index| &self.documents[*index])
+            .partition(|doc| doc.dirty);
+        let dirty: Vec<u64> = dirty.iter().map(|doc| doc.id).collect();
+        let clean: Vec<u64> = clean.iter().map(|doc| doc.id).collect();
+        self.close_tabs_by_id(&clean);
+        if !dirty.is_empty() {
+            self.pending_close = Some(PendingClose::Tabs(dirty));
+        }
+    }
+
+    fn close_tabs_by_id(&mut self, ids: &[u64]) {
+        for id in ids {
+            if let Some(index) = self.documents.iter().position(|doc| doc.id == *id) {
+                self.close_tab(index);
+            }
+        }
+    }
+
+    // Move a tab to another position, keeping the same document active
+    fn move_tab(&mut self, from: usize, to: usize) {
+        let active_id = self.doc().id;
+        let doc = self.documents.remove(from);
+        self.documents.insert(to.min(self.documents.len()), doc);
+        self.active_tab = self
+            .documents
+            .iter()
+            .position(|doc| doc.id == active_id)
+            .unwrap_or(0);
+    }
+
+    // Pinned tabs stay together at the left: pinning moves a tab to the end
+    // of that group and unpinning to just after it
+    fn set_pinned(&mut self, index: usize, pinned: bool) {
+        self.documents[index].pinned = pinned;
+        let pinned_count = self.documents.iter().filter(|doc| doc.pinned).count();
+        let to = if pinned {
+            pinned_count - 1
+        } else {
+            pinned_count
+        };
+        self.move_tab(index, to);
+    }
+
+    fn show_confirm_close_window(&mut self, ctx: &egui::Context, pending: PendingClose) {
+        // Documents whose changes would be lost
+        let indices: Vec<usize> = match pending {
+            PendingClose::Tabs(ref ids) => ids
+                .iter()
+                .filter_map(|id| self.documents.iter().position(|doc| doc.id == *id))
+                .collect(),
+            PendingClose::Window => (0..self.documents.len())
+                .filter(|index| self.documents[*index].dirty)
+                .collect(),
+        };
+        if indices.is_empty() {
+            self.pending_close = None;
+            return;
+        }
+
+        let (mut save, mut discard, mut cancel) = (false, false, false);
+        egui::Window::new("Save changes?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("These documents have unsaved changes:");
+                for index in &indices {
+                    ui.label(format!("📄 {}", self.documents[*index].title()));
+                }
+                ui.horizontal(|ui| {
+                    save = ui.button("💾 Save").clicked();
+                    discard = ui.button("Don't Save").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if save {
+            // Stop at the first document the user declines to save
+            let saved = indices.iter().all(|index| self.save_document(*index));
+            if !saved {
+                return;
+            }
+        }
+        if save || discard {
+            self.pending_close = None;
+            match pending {
+                PendingClose::Tabs(ids) => self.close_tabs_by_id(&ids),
+                PendingClose::Window => {
+                    self.close_confirmed = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        } else if cancel {
+            self.pending_close = None;
+        }
+    }
+
+    fn cycle_tab(&mut self, forward: bool) {
+        let count = self.documents.len();
+        self.active_tab = if forward {
+            (self.active_tab + 1) % count
+        } else {
+            (self.active_tab + count - 1) % count
+        };
+    }
+
+    // Open a file in a new tab, reusing a blank current tab and switching to
+    // the file's tab if it is already open
+    fn open_file(&mut self, path: PathBuf) {
+        if let Some(index) = self
+            .documents
+            .iter()
+            .position(|doc| doc.file_path.as_ref() == Some(&path))
+        {
+            self.active_tab = index;
+            return;
+        }
+        if !self.doc().is_blank() {
+            self.new_tab();
+        }
+        let doc = self.doc_mut();
+        match doc.load_with_formatting(&path) {
+            Ok(_) => {
+                tracing::info!(path = %path.display(), "Opened");
+                doc.file_path = Some(path);
+                doc.dirty = false;
+                self.error_message = None;
+                self.add_recent(self.active_tab);
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Could not open");
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    // Remember the document's file for the Recent menu; scratchpads have
+    // their own menu
+    fn add_recent(&mut self, index: usize) {
+        if let Some(path) = &self.documents[index].file_path {
+            if !scratch::is_scratch(path) {
+                self.settings.common.add_recent(path);
+            }
         }
     }
-}
 
-impl NoteApp {
-    fn save_with_formatting(&self, path: &PathBuf) -> Result<(), String> {
-        // Check file extension
-        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-
-        if extension == "rtxt" {
-            // Save with formatting metadata for .rtxt files
-            let mut content = String::new();
-            content.push_str("TEXT:\n");
-            content.push_str(&self.text_content);
-            content.push_str("\n---STYLES---\n");
-            for styled_range in &self.styled_ranges {
-                let style_name = match styled_range.style {
-                    TextFormatting::Regular => "Regular",
-                    TextFormatting::Bold => "Bold",
-                    TextFormatting::Italic => "Italic",
-                    TextFormatting::BoldItalic => "BoldItalic",
-                };
+    // Save a document to its file, asking for a path if it has none; returns
+    // false if the user cancelled or saving failed
+    fn save_document(&mut self, index: usize) -> bool {
+        let keep = self.backups_to_keep();
+        let doc = &mut self.documents[index];
+        let dialogs = &mut self.settings.common.dialogs;
+        let Some(path) = doc
+            .file_path
+            .clone()
+            .or_else(|| dialogs.save(&[FileType::RichText], "untitled.rtxt"))
+        else {
+            return false;
+        };
+        match backup::back_up(&path, keep).and_then(|()| doc.save_with_formatting(&path)) {
+            Ok(_) => {
+                tracing::info!(path = %path.display(), "Saved");
+                doc.file_path = Some(path);
+                doc.dirty = false;
+                self.error_message = None;
+                self.take_snapshot(index);
+                self.add_recent(index);
+                self.notebook.refresh_status();
+                self.backlinks = None;
+                true
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Could not save");
+                self.error_message = Some(e);
+                false
+            }
+        }
+    }
 
-                // Format: start..end:style:text_color:bg_color
-                let text_color_str = if let Some(color) = styled_range.text_color {
-                    format!("{}_{}_{}_{}", color.r(), color.g(), color.b(), color.a())
-                } else {
-                    "none".to_string()
-                };
+    // Save the text to a new file without making it the document's file; the
+    // attachments are copied along
+    fn save_copy(&mut self) {
+        let name = self
+            .doc()
+            .file_path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map_or("untitled".to_string(), |stem| {
+                stem.to_string_lossy().into_owned()
+            });
+        let Some(path) = self.settings.common.dialogs.save(
+            &[FileType::RichText, FileType::PlainText],
+            &format!("{} copy.rtxt", name),
+        ) else {
+            return;
+        };
+        let doc = self.doc();
+        let result = doc
+            .save_with_formatting(&path)
+            .and_then(|()| match &doc.file_path {
+                Some(note) => attachment::copy_all(note, &path, &doc.metadata.attachments),
+                None => Ok(()),
+            });
+        self.error_message = match result {
+            Ok(()) => Some(format!("Saved a copy as {}", path.display())),
+            Err(e) => Some(e),
+        };
+        self.notebook.refresh();
+    }
 
-                let bg_color_str = if let Some(color) = styled_range.bg_color {
-                    format!("{}_{}_{}_{}", color.r(), color.g(), color.b(), color.a())
-                } else {
-                    "none".to_string()
-                };
+    // An untitled tab with the same text and formatting; attachments stay
+    // with the original
+    fn duplicate_document(&mut self) {
+        let original = self.doc().content_copy();
+        self.new_tab();
+        let doc = self.doc_mut();
+        doc.text_content = original.text_content;
+        doc.styled_ranges = original.styled_ranges;
+        doc.tables = original.tables;
+        doc.sketches = original.sketches;
+        doc.named_styles = original.named_styles;
+        doc.encoding = original.encoding;
+        doc.dirty = true;
+    }
 
-                content.push_str(&format!(
-                    "{}..{}:{}:{}:{}\n",
-                    styled_range.range.start,
-                    styled_range.range.end,
-                    style_name,
-                    text_color_str,
-                    bg_color_str
-                ));
+    fn export_html(&mut self) {
+        let name = self.doc().title();
+        let stem = name
+            .rsplit_once('.')
+            .map_or(name.as_str(), |(stem, _)| stem);
+        let file_name = format!("{}.html", stem);
+        let Some(path) = self
+            .settings
+            .common
+            .dialogs
+            .save(&[FileType::Html], &file_name)
+        else {
+            return;
+        };
+        let html = export::to_html(
+            self.doc(),
+            &self.font_family,
+            self.font_size,
+            &self.settings.page,
+        );
+        match std::fs::write(&path, html) {
+            Ok(_) => self.error_message = None,
+            Err(e) => self.error_message = Some(format!("Error exporting HTML: {}", e)),
+        }
+    }
+
+    fn compare_with_saved(&mut self) {
+        match Comparison::open(self.doc()) {
+            Ok(comparison) => {
+                self.comparison = Some(comparison);
+                self.error_message = None;
             }
-            std::fs::write(path, content).map_err(|e| format!("Error saving file: {}", e))
-        } else {
-            // Save plain text exactly as-is for .txt and other files
-            std::fs::write(path, &self.text_content)
-                .map_err(|e| format!("Error saving file: {}", e))
-        }
-    }
-
-    fn load_with_formatting(&mut self, path: &PathBuf) -> Result<(), String> {
-        let content =
-            std::fs::read_to_string(path).map_err(|e| format!("Error reading file: {}", e))?;
-
-        // Check if it's the new format with TEXT: header
-        if content.starts_with("TEXT:\n") {
-            // New format - find the separator
-            if let Some(separator_pos) = content.find("\n---STYLES---\n") {
-                // Extract text content (skip "TEXT:\n")
-                self.text_content = content[6..separator_pos].to_string();
-
-                // Extract styles section
-                let styles_section = &content[separator_pos + 14..];
-                self.styled_ranges.clear();
-
-                for line in styles_section.lines() {
-                    let parts: Vec<&str> = line.split(':').collect();
-                    if parts.len() >= 2 {
-                        let range_part = parts[0];
-                        let style_part = parts[1];
-                        let text_color_part = parts.get(2).copied();
-                        let bg_color_part = parts.get(3).copied();
-
-                        if let Some((start_str, end_str)) = range_part.split_once("..") {
-                            if let (Ok(start), Ok(end)) =
-                                (start_str.parse::<usize>(), end_str.parse::<usize>())
-                            {
-                                let style = match style_part {
-                                    "Bold" => TextFormatting::Bold,
-                                    "Italic" => TextFormatting::Italic,
-                                    "BoldItalic" => TextFormatting::BoldItalic,
-                                    _ => TextFormatting::Regular,
-                                };
-
-                                // Parse text color
-                                let text_color = if let Some(color_str) = text_color_part {
-                                    if color_str != "none" {
-                                        let rgba: Vec<&str> = color_str.split('_').collect();
-                                        if rgba.len() == 4 {
-                                            if let (Ok(r), Ok(g), Ok(b), Ok(a)) = (
-                                                rgba[0].parse::<u8>(),
-                                                rgba[1].parse::<u8>(),
-                                                rgba[2].parse::<u8>(),
-                                                rgba[3].parse::<u8>(),
-                                            ) {
-                                                Some(Color32::from_rgba_unmultiplied(r, g, b, a))
-                                            } else {
-                                                None
-                                            }
-                                        } else {
-                                            None
-                                        }
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    None
-                                };
-
-                                // Parse background color
-                                let bg_color = if let Some(color_str) = bg_color_part {
-                                    if color_str != "none" {
-                                        let rgba: Vec<&str> = color_str.split('_').collect();
-                                        if rgba.len() == 4 {
-                                            if let (Ok(r), Ok(g), Ok(b), Ok(a)) = (
-                                                rgba[0].parse::<u8>(),
-                                                rgba[1].parse::<u8>(),
-                                                rgba[2].parse::<u8>(),
-                                                rgba[3].parse::<u8>(),
-                                            ) {
-                                                Some(Color32::from_rgba_unmultiplied(r, g, b, a))
-                                            } else {
-                                                None
-                                            }
-                                        } else {
-                                            None
-                                        }
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    None
-                                };
-
-                                self.styled_ranges.push(StyledRange {
-                                    range: start..end,
-                                    style,
-                                    text_color,
-                                    bg_color,
-                                });
-                            }
-                        }
-                    }
-                }
-            } else {
-                // No separator found, just use the text
-                self.text_content = content[6..].to_string();
-                self.styled_ranges.clear();
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    fn show_comparison_window(&mut self, ctx: &egui::Context) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+        let Some(index) = self
+            .documents
+            .iter()
+            .position(|doc| doc.id == comparison.doc_id)
+        else {
+            self.comparison = None;
+            return;
+        };
+        let mut open = true;
+        let reverted = comparison.show(ctx, &self.documents[index], &mut open);
+        if !open {
+            self.comparison = None;
+        }
+        if let Some(new_text) = reverted {
+            let doc = &mut self.documents[index];
+            let (start, old_end, new_end) = text::changed_range(&doc.text_content, &new_text);
+            doc.save_state_for_undo();
+            doc.replace_chars(start..old_end, text::char_slice(&new_text, start..new_end));
+        }
+    }
+
+    fn save_to(&mut self, path: PathBuf) {
+        let keep = self.backups_to_keep();
+        let doc = self.doc_mut();
+        match backup::back_up(&path, keep).and_then(|()| doc.save_with_formatting(&path)) {
+            Ok(_) => {
+                tracing::info!(path = %path.display(), "Saved");
+                doc.file_path = Some(path);
+                doc.dirty = false;
+                self.error_message = None;
+                self.take_snapshot(self.active_tab);
+                self.add_recent(self.active_tab);
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Could not save");
+                self.error_message = Some(e);
             }
-        } else {
-            // Old format - plain text file
-            self.text_content = content;
-            self.styled_ranges.clear();
         }
+    }
 
-        Ok(())
+    // Add the document to its version history if it changed since the last snapshot
+    fn take_snapshot(&mut self, index: usize) {
+        if !self.settings.keep_history {
+            return;
+        }
+        if let Err(e) = history::snapshot(&self.documents[index], self.history_limits()) {
+            self.error_message = Some(e);
+        }
     }
 
-    fn apply_style_to_selection(&mut self) {
-        if let Some(range) = self.cursor_range.clone() {
-            if range.start < range.end {
-                // Save state before modification
-                self.save_state_for_undo();
+    // Backups made of a file before a save overwrites it
+    fn backups_to_keep(&self) -> usize {
+        if self.settings.backup_on_save {
+            self.settings.backup_count
+        } else {
+            0
+        }
+    }
 
-                // Remove overlapping ranges
-                self.styled_ranges
-                    .retain(|r| r.range.end <= range.start || r.range.start >= range.end);
+    fn history_limits(&self) -> history::Limits {
+        history::Limits {
+            max_count: self.settings.history_max_snapshots,
+            max_bytes: self.settings.history_max_mb * 1024 * 1024,
+        }
+    }
 
-                // Add new styled range
-                self.styled_ranges.push(StyledRange {
-                    range: range.clone(),
-                    style: self.current_style,
-                    text_color: if self.current_text_color != Color32::BLACK {
-                        Some(self.current_text_color)
-                    } else {
-                        None
-                    },
-                    bg_color: self.current_bg_color,
-                });
+    // List the matches of the Find text in every note for review
+    fn scan_notes_for_replace(&mut self, find: &str, replace: &str) {
+        let Some(root) = self.notebook.root() else {
+            self.error_message = Some("Choose a notes folder to search it".to_string());
+            return;
+        };
+        let open_text = |path: &std::path::Path| {
+            self.documents
+                .iter()
+                .find(|doc| doc.file_path.as_deref() == Some(path))
+                .map(|doc| doc.text_content.clone())
+        };
+        let scan = ReplaceInFiles::scan(root, &self.notebook.notes(), find, replace, open_text);
+        let written = self
+            .replace_in_files
+            .take()
+            .map_or(Vec::new(), |previous| previous.written);
+        self.replace_in_files = Some(ReplaceInFiles { written, ..scan });
+    }
 
-                // Sort ranges by start position
-                self.styled_ranges.sort_by_key(|r| r.range.start);
-            }
+    fn show_replace_in_files(&mut self, ctx: &egui::Context) {
+        let Some(replace) = &mut self.replace_in_files else {
+            return;
+        };
+        let mut open = true;
+        let action = replace.show(ctx, &mut open);
+        if !open {
+            self.replace_in_files = None;
+            return;
+        }
+        let (find, replace_with) = (replace.find.clone(), replace.replace.clone());
+        match action {
+            Some(ReplaceAction::Apply) => self.replace_in_notes(),
+            Some(ReplaceAction::Undo) => self.undo_replace_in_notes(),
+            Some(ReplaceAction::Rescan) => self.scan_notes_for_replace(&find, &replace_with),
+            None => {}
         }
     }
 
-    // Undo/Redo functionality
-    fn save_state_for_undo(&mut self) {
-        let state = EditorState {
-            text_content: self.text_content.clone(),
-            styled_ranges: self.styled_ranges.clone(),
+    // Notes open in a tab change there, as one undo step each, and are left
+    // unsaved; the others are written, keeping what they were for undo
+    fn replace_in_notes(&mut self) {
+        let Some(mut replace) = self.replace_in_files.take() else {
+            return;
         };
-        self.undo_stack.push(state);
-        self.redo_stack.clear(); // Clear redo stack when new change is made
+        let (mut in_tabs, mut written, mut errors) = (0, Vec::new(), Vec::new());
+        for change in replace.changes.iter().filter(|change| change.include) {
+            if let Some(doc) = self
+                .documents
+                .iter_mut()
+                .find(|doc| doc.file_path.as_ref() == Some(&change.path))
+            {
+                filereplace::replace_in(doc, &replace.find, &replace.replace);
+                in_tabs += 1;
+                continue;
+            }
+            let mut doc = Document::new(0);
+            if let Err(e) = doc.load_with_formatting(&change.path) {
+                errors.push(e);
+                continue;
+            }
+            doc.file_path = Some(change.path.clone());
+            let original = doc.content_copy();
+            if self.settings.keep_history {
+                let _ = history::snapshot(&original, self.history_limits());
+            }
+            filereplace::replace_in(&mut doc, &replace.find, &replace.replace);
+            match doc.save_with_formatting(&change.path) {
+                Ok(()) => written.push(original),
+                Err(e) => errors.push(e),
+            }
+        }
+        replace.status = Some(if errors.is_empty() {
+            format!(
+                "Saved {} note(s); {} open note(s) changed in their tabs, not saved yet",
+                written.len(),
+                in_tabs
+            )
+        } else {
+            errors.join("; ")
+        });
+        replace.written = written;
+        replace.changes.clear();
+        self.replace_in_files = Some(replace);
+        self.notebook.refresh_status();
+        self.backlinks = None;
+    }
 
-        // Limit undo stack to 100 states
-        if self.undo_stack.len() > 100 {
-            self.undo_stack.remove(0);
+    fn undo_replace_in_notes(&mut self) {
+        let Some(replace) = &mut self.replace_in_files else {
+            return;
+        };
+        let mut errors = Vec::new();
+        for original in replace.written.drain(..) {
+            if let Some(path) = &original.file_path {
+                if let Err(e) = original.save_with_formatting(path) {
+                    errors.push(e);
+                }
+            }
         }
+        replace.status = Some(if errors.is_empty() {
+            "The saved notes are back as they were; undo open notes in their tabs".to_string()
+        } else {
+            errors.join("; ")
+        });
+        self.notebook.refresh_status();
+        self.backlinks = None;
     }
 
-    fn undo(&mut self) {
-        if let Some(state) = self.undo_stack.pop() {
-            // Save current state to redo stack
-            let current = EditorState {
-                text_content: self.text_content.clone(),
-                styled_ranges: self.styled_ranges.clone(),
-            };
-            self.redo_stack.push(current);
+    fn open_history(&mut self) {
+        match HistoryBrowser::open(self.doc()) {
+            Ok(browser) => {
+                self.history = Some(browser);
+                self.error_message = None;
+            }
+            Err(e) => self.error_message = Some(e),
+        }
+    }
 
-            // Restore previous state
-            self.text_content = state.text_content;
-            self.styled_ranges = state.styled_ranges;
+    fn show_history_window(&mut self, ctx: &egui::Context) {
+        let Some(browser) = &mut self.history else {
+            return;
+        };
+        let Some(index) = self
+            .documents
+            .iter()
+            .position(|doc| doc.id == browser.doc_id)
+        else {
+            self.history = None;
+            return;
+        };
+        let mut open = true;
+        let restored = browser.show(ctx, &self.documents[index], &mut open);
+        if !open {
+            self.history = None;
+        }
+        if let Some(snapshot) = restored {
+            self.restore_version(index, snapshot);
         }
     }
 
+    // Put an earlier version's text and formatting into a document, undoably
+    fn restore_version(&mut self, index: usize, version: Document) {
+        let doc = &mut self.documents[index];
+        doc.save_state_for_undo();
+        doc.text_content = version.text_content;
+        doc.styled_ranges = version.styled_ranges;
+        doc.tables = version.tables;
+        doc.sketches = version.sketches;
+        doc.named_styles = version.named_styles;
+        doc.cursor_range = Some(0..0);
+        doc.pending_cursor_pos = Some(0);
+        doc.skip_cursor_capture = true;
+    }
+
+    fn apply_style_to_selection(&mut self, change: StyleChange) {
+        self.doc_mut().apply_style_to_selection(change);
+    }
+
+    // Bold, italic and underline turn off when the whole selection has them
+    fn toggle_style(
+        &mut self,
+        attribute: fn(&StyledRange) -> bool,
+        change: fn(bool) -> StyleChange,
+    ) {
+        let on = !self.doc().selection_has(attribute);
+        self.apply_style_to_selection(change(on));
+    }
+
+    fn undo(&mut self) {
+        self.doc_mut().undo();
+    }
+
     fn redo(&mut self) {
-        if let Some(state) = self.redo_stack.pop() {
-            // Save current state to undo stack
-            let current = EditorState {
-                text_content: self.text_content.clone(),
-                styled_ranges: self.styled_ranges.clone(),
-            };
-            self.undo_stack.push(current);
+        self.doc_mut().redo();
+    }
 
-            // Restore redone state
-            self.text_content = state.text_content;
-            self.styled_ranges = state.styled_ranges;
-        }
+    // Find & Replace functionality; positions are character indices. The
+    // search covers the "In selection" range when there is one
+    fn find_scope(doc: &Document) -> std::ops::Range<usize> {
+        let len = doc.char_count();
+        doc.find_scope
+            .clone()
+            .map_or(0..len, |scope| scope.start.min(len)..scope.end.min(len))
     }
 
-    // Find & Replace functionality
     fn find_next(&mut self) {
+        let doc = &mut self.documents[self.active_tab];
         if self.find_text.is_empty() {
             return;
         }
 
-        if let Some(pos) = self.text_content[self.last_find_position..].find(&self.find_text) {
-            let actual_pos = self.last_find_position + pos;
-            self.cursor_range = Some(actual_pos..actual_pos + self.find_text.len());
-            self.last_find_position = actual_pos + 1;
+        let scope = Self::find_scope(doc);
+        let find_len = text::char_count(&self.find_text);
+        let in_scope = |pos: &usize| pos + find_len <= scope.end;
+        // Wrap around to the beginning
+        let from = doc.last_find_position.max(scope.start);
+        let found = text::find_from(&doc.text_content, &self.find_text, from)
+            .filter(in_scope)
+            .or_else(|| {
+                text::find_from(&doc.text_content, &self.find_text, scope.start).filter(in_scope)
+            });
+        if let Some(pos) = found {
+            let end = pos + find_len;
+            doc.cursor_range = Some(pos..end);
+            doc.last_find_position = pos + 1;
             // Set pending cursor to the end of found text for visual feedback
-            self.pending_cursor_pos = Some(actual_pos + self.find_text.len());
-            self.skip_cursor_capture = true;
-        } else {
-            // Wrap around to beginning
-            self.last_find_position = 0;
-            if let Some(pos) = self.text_content.find(&self.find_text) {
-                self.cursor_range = Some(pos..pos + self.find_text.len());
-                self.last_find_position = pos + 1;
-                self.pending_cursor_pos = Some(pos + self.find_text.len());
-                self.skip_cursor_capture = true;
-            }
+            doc.pending_cursor_pos = Some(end);
+            doc.skip_cursor_capture = true;
         }
     }
 
     fn find_previous(&mut self) {
+        let doc = &mut self.documents[self.active_tab];
         if self.find_text.is_empty() {
             return;
         }
 
-        let search_end = if self.last_find_position > 0 {
-            self.last_find_position - 1
-        } else {
-            self.text_content.len()
+        let scope = Self::find_scope(doc);
+        let find_len = text::char_count(&self.find_text);
+        let in_scope = |pos: &usize| *pos >= scope.start;
+        // Search before the last match, wrapping around to the end
+        let search_end = match doc.last_find_position {
+            0 => scope.end,
+            last => (last - 1).min(scope.end),
         };
-
-        if let Some(pos) = self.text_content[..search_end].rfind(&self.find_text) {
-            self.cursor_range = Some(pos..pos + self.find_text.len());
-            self.last_find_position = pos;
-            self.pending_cursor_pos = Some(pos + self.find_text.len());
-            self.skip_cursor_capture = true;
-        } else {
-            // Wrap around to end
-            if let Some(pos) = self.text_content.rfind(&self.find_text) {
-                self.cursor_range = Some(pos..pos + self.find_text.len());
-                self.last_find_position = pos;
-                self.pending_cursor_pos = Some(pos + self.find_text.len());
-                self.skip_cursor_capture = true;
-            }
+        let found = text::rfind_before(&doc.text_content, &self.find_text, search_end)
+            .filter(in_scope)
+            .or_else(|| {
+                text::rfind_before(&doc.text_content, &self.find_text, scope.end).filter(in_scope)
+            });
+        if let Some(pos) = found {
+            doc.cursor_range = Some(pos..pos + find_len);
+            doc.last_find_position = pos;
+            doc.pending_cursor_pos = Some(pos + find_len);
+            doc.skip_cursor_capture = true;
         }
     }
 
-    fn replace_current(&mut self) {
-        let range = self.cursor_range.clone();
-        if let Some(range) = range {
-            if range.start < range.end && range.end <= self.text_content.len() {
-                self.save_state_for_undo();
-
-                let selected_text = &self.text_content[range.clone()];
-                if selected_text == self.find_text {
-                    self.text_content
-                        .replace_range(range.clone(), &self.replace_text);
-
-                    // Adjust styled ranges
-                    let diff = self.replace_text.len() as i32 - self.find_text.len() as i32;
-                    for styled_range in &mut self.styled_ranges {
-                        if styled_range.range.start >= range.end {
-                            styled_range.range.start =
-                                (styled_range.range.start as i32 + diff).max(0) as usize;
-                            styled_range.range.end =
-                                (styled_range.range.end as i32 + diff).max(0) as usize;
+    // The matches of the Find text, updated as it is typed; clicking one
+    // selects it in the editor
+    fn show_find_results(&mut self, ui: &mut egui::Ui) {
+        let doc = self.doc();
+        let results = search::matches(&doc.text_content, &self.find_text, Self::find_scope(doc));
+        let selected = doc.cursor_range.clone();
+        let count = if results.len() == search::MAX_RESULTS {
+            format!("The first {} matches", results.len())
+        } else {
+            format!("{} match(es)", results.len())
+        };
+        ui.weak(count);
+        let font_id = egui::TextStyle::Body.resolve(ui.style());
+        let text_color = ui.visuals().text_color();
+        let weak_color = ui.visuals().weak_text_color();
+        let match_background = ui.visuals().selection.bg_fill.gamma_multiply(0.5);
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .max_height(160.0)
+            .auto_shrink([false, true])
+            .show_rows(
+                ui,
+                ui.spacing().interact_size.y,
+                results.len(),
+                |ui, rows| {
+                    for result in &results[rows] {
+                        let format = |color, background| egui::TextFormat {
+                            font_id: font_id.clone(),
+                            color,
+                            background,
+                            ..Default::default()
+                        };
+                        let mut job = egui::text::LayoutJob::default();
+                        let plain = format(text_color, Color32::TRANSPARENT);
+                        job.append(
+                            &format!("{:>5}  ", result.line),
+                            0.0,
+                            format(weak_color, Color32::TRANSPARENT),
+                        );
+                        job.append(&result.before, 0.0, plain.clone());
+                        job.append(&self.find_text, 0.0, format(text_color, match_background));
+                        job.append(&result.after, 0.0, plain);
+                        let is_selected = selected.as_ref() == Some(&result.range);
+                        if ui.selectable_label(is_selected, job).clicked() {
+                            clicked = Some(result.range.clone());
                         }
                     }
+                },
+            );
+        if let Some(range) = clicked {
+            let doc = self.doc_mut();
+            doc.last_find_position = range.start + 1;
+            doc.pending_cursor_pos = Some(range.end);
+            doc.cursor_range = Some(range);
+            doc.skip_cursor_capture = true;
+        }
+    }
 
-                    self.find_next();
+    // Limit Find & Replace to the selection, or search everything again
+    fn set_find_in_selection(&mut self, on: bool) {
+        let doc = &mut self.documents[self.active_tab];
+        doc.find_scope = None;
+        if on {
+            match doc.cursor_range.clone().filter(|range| !range.is_empty()) {
+                Some(range) => {
+                    doc.last_find_position = range.start;
+                    doc.find_scope = Some(range);
                 }
+                None => self.error_message = Some("Select the text to search in first".to_string()),
             }
         }
     }
 
+    // Keep the end of the search range on the same text after a replacement
+    // inside it
+    fn grow_find_scope(doc: &mut Document, replaced: usize, by: usize) {
+        if let Some(scope) = &mut doc.find_scope {
+            scope.end = (scope.end + by).saturating_sub(replaced).max(scope.start);
+        }
+    }
+
+    fn replace_current(&mut self) {
+        let doc = &mut self.documents[self.active_tab];
+        let Some(range) = doc.cursor_range.clone() else {
+            return;
+        };
+        if range.start < range.end
+            && text::char_slice(&doc.text_content, range.clone()) == self.find_text
+        {
+            doc.save_state_for_undo();
+            doc.replace_chars(range.clone(), &self.replace_text);
+            let replace_len = text::char_count(&self.replace_text);
+            Self::grow_find_scope(doc, range.len(), replace_len);
+            // Continue searching after the replacement
+            doc.last_find_position = range.start + replace_len;
+            self.find_next();
+        }
+    }
+
     fn replace_all(&mut self) {
+        let doc = &mut self.documents[self.active_tab];
         if self.find_text.is_empty() {
             return;
         }
 
-        self.save_state_for_undo();
+        let scope = Self::find_scope(doc);
+        let find_len = text::char_count(&self.find_text);
+        let matches: Vec<usize> = doc
+            .text_content
+            .match_indices(&self.find_text)
+            .map(|(pos, _)| doc.byte_to_char(pos))
+            .filter(|&pos| pos >= scope.start && pos + find_len <= scope.end)
+            .collect();
+        if matches.is_empty() {
+            return;
+        }
 
-        let mut count = 0;
-        while self.text_content.contains(&self.find_text) {
-            self.text_content = self
-                .text_content
-                .replacen(&self.find_text, &self.replace_text, 1);
-            count += 1;
+        doc.save_state_for_undo();
+        // Replace from the end so earlier positions stay valid
+        for pos in matches.iter().rev() {
+            doc.replace_chars(*pos..pos + find_len, &self.replace_text);
         }
+        let replace_len = text::char_count(&self.replace_text);
+        Self::grow_find_scope(doc, matches.len() * find_len, matches.len() * replace_len);
+        self.error_message = Some(format!("Replaced {} occurrence(s)", matches.len()));
+    }
 
-        if count > 0 {
-            // Clear styled ranges when replacing all (simpler than adjusting all)
-            self.styled_ranges.clear();
-            self.error_message = Some(format!("Replaced {} occurrence(s)", count));
+    // Auto-indent and auto-close take over Enter and bracket keys while the
+    // editor has focus
+    fn handle_editor_typing(&mut self, ctx: &egui::Context) {
+        let settings = self.settings.clone();
+        let pane = self.focused_pane();
+        let doc = &mut self.documents[self.active_tab];
+        if !ctx.memory(|m| m.has_focus(editor_id(doc.id, pane))) {
+            return;
+        }
+        if settings.auto_indent
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter))
+        {
+            doc.newline_with_indent();
+        }
+        // Autocorrect first, so smart quotes are not paired as plain ones
+        if settings.autocorrect.any_enabled() {
+            ctx.input_mut(|i| {
+                i.events.retain(|event| match event {
+                    egui::Event::Text(text) => {
+                        let mut chars = text.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => !doc.autocorrect(&settings.autocorrect, c),
+                            _ => true,
+                        }
+                    }
+                    _ => true,
+                })
+            });
+        }
+        if settings.auto_close_brackets {
+            ctx.input_mut(|i| {
+                i.events.retain(|event| match event {
+                    egui::Event::Text(text) => {
+                        let mut chars = text.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) if "()[]{}\"'".contains(c) => !doc.type_paired(c),
+                            _ => true,
+                        }
+                    }
+                    _ => true,
+                })
+            });
         }
     }
 
-    fn render_rich_text_editable(&mut self, ui: &mut egui::Ui) -> egui::Response {
-        let styled_ranges = self.styled_ranges.clone();
+    fn render_rich_text_editable(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        pane: usize,
+        focused: bool,
+    ) -> egui::text_edit::TextEditOutput {
         let font_size = self.font_size;
         let font_family = self.font_family.clone();
+        // [[Links]] resolve by full name or by the name without its folders
+        let link_targets: HashSet<String> = self
+            .note_names()
+            .iter()
+            .flat_map(|name| {
+                let name = name.to_lowercase();
+                let file_name = name.rsplit('/').next().unwrap_or_default().to_string();
+                [name, file_name]
+            })
+            .collect();
+        let (link_color, missing_link_color) =
+            (palette::ACCENT, Color32::from_rgb(0xb0, 0x40, 0x40));
+        let doc = &mut self.documents[index];
+        let styled_ranges = doc.styled_ranges.clone();
+        let named_styles = doc.named_styles.clone();
+        let bracket_pair = if self.settings.match_brackets {
+            doc.bracket_pair_at_cursor()
+        } else {
+            None
+        };
+        let bracket_highlight = ui.visuals().selection.bg_fill.gamma_multiply(0.5);
+        // The sentence being read aloud
+        let spoken = self
+            .reader
+            .as_ref()
+            .filter(|reader| reader.doc_id == doc.id)
+            .and_then(|reader| reader.current());
+        let spoken_highlight = Color32::from_rgb(0xff, 0xec, 0x99);
+        let (line_spacing, paragraph_spacing) =
+            (self.settings.line_spacing, self.settings.paragraph_spacing);
+        // Focus mode dims all but the lines with the cursor
+        let focus_lines = doc
+            .cursor_range
+            .clone()
+            .filter(|_| self.focus_mode && focused)
+            .map(|range| {
+                doc.lines_char_range(
+                    doc.line_col(range.start).0 - 1,
+                    doc.line_col(range.end).0 - 1,
+                )
+            });
+        // The caret's line; while an edit is laid out, before the caret is
+        // updated, the caret moves by the change in length
+        let caret = doc
+            .cursor_range
+            .clone()
+            .filter(|range| self.settings.highlight_line && focused && range.is_empty())
+            .map(|range| (range.end, doc.char_count()));
+        let line_highlight = ui.visuals().faint_bg_color;
+        let block_heights: Vec<(char, f32)> = doc
+            .tables
+            .iter()
+            .map(|table| (table.placeholder(), table.height()))
+            .chain(
+                doc.sketches
+                    .iter()
+                    .map(|sketch| (sketch.placeholder(), sketch.total_height())),
+            )
+            .collect();
 
         let mut layouter = move |ui: &egui::Ui, text: &str, wrap_width: f32| {
             let mut layout_job = egui::text::LayoutJob::default();
             layout_job.wrap.max_width = wrap_width;
 
+            // Styled ranges and brackets are character indices; the layout job
+            // needs byte offsets into this (possibly just edited) text
+            let boundaries = text::char_boundaries(text);
+            let to_byte = |index: usize| boundaries[index.min(boundaries.len() - 1)];
+            let styled_ranges: Vec<StyledRange> = styled_ranges
+                .iter()
+                .map(|styled| StyledRange {
+                    range: to_byte(styled.range.start)..to_byte(styled.range.end),
+                    ..styled.clone()
+                })
+                .collect();
+            let brackets =
+                bracket_pair.map(|(a, b)| [to_byte(a), to_byte(a + 1), to_byte(b), to_byte(b + 1)]);
+            let focus_lines = focus_lines
+                .clone()
+                .map(|lines| to_byte(lines.start)..to_byte(lines.end));
+            let spoken = spoken
+                .clone()
+                .map(|sentence| to_byte(sentence.start)..to_byte(sentence.end));
+            // Checklist markers are hidden under the checkboxes drawn over them
+            let checkboxes: Vec<usize> = document::checkbox_markers(text)
+                .into_iter()
+                .map(|(offset, _)| offset)
+                .collect();
+            // Each link with whether its note exists
+            let links: Vec<(std::ops::Range<usize>, bool)> = wikilink::links(text)
+                .into_iter()
+                .map(|link| {
+                    let exists = link_targets.contains(&link.target.to_lowercase());
+                    (link.range, exists)
+                })
+                .collect();
+
+            let footnotes: Vec<std::ops::Range<usize>> = footnote::references(text)
+                .into_iter()
+                .map(|reference| reference.range)
+                .collect();
+            let web_links = weblink::links(text);
+
             let mut current_pos = 0;
             while current_pos < text.len() {
                 // Find next style change
                 let mut next_change = text.len();
+                if let Some(brackets) = brackets {
+                    for pos in brackets {
+                        if pos > current_pos && pos < next_change {
+                            next_change = pos;
+                        }
+                    }
+                }
+                for &marker in &checkboxes {
+                    for pos in [marker, marker + 3] {
+                        if pos > current_pos && pos < next_change {
+                            next_change = pos;
+                        }
+                    }
+                }
+                let link_ranges = links.iter().map(|(range, _)| range);
+                for range in focus_lines
+                    .iter()
+                    .chain(&spoken)
+                    .chain(link_ranges)
+                    .chain(&footnotes)
+                    .chain(&web_links)
+                {
+                    for pos in [range.start, range.end] {
+                        if pos > current_pos && pos < next_change {
+                            next_change = pos;
+                        }
+                    }
+                }
                 for styled_range in &styled_ranges {
                     if styled_range.range.start > current_pos
                         && styled_range.range.start < next_change
@@ -457,82 +3796,186 @@ impl NoteApp {
                 let end = next_change.min(text.len());
                 let segment = &text[current_pos..end];
 
-                // Determine style for this position
-                let mut style = TextFormatting::Regular;
-                let mut text_color: Option<Color32> = None;
-                let mut bg_color: Option<Color32> = None;
-
-                for styled_range in &styled_ranges {
-                    if styled_range.range.contains(&current_pos) {
-                        style = styled_range.style;
-                        text_color = styled_range.text_color;
-                        bg_color = styled_range.bg_color;
-                        break;
-                    }
+                let styled = styled_ranges
+                    .iter()
+                    .find(|styled| styled.range.contains(&current_pos));
+                let mut format = text_format(styled, &named_styles, &font_family, font_size);
+                if brackets.is_some_and(|[a, _, b, _]| current_pos == a || current_pos == b) {
+                    format.background = bracket_highlight;
                 }
-
-                // Create text format based on style
-                let get_font_id = |size: f32| {
-                    if font_family == "Proportional" || font_family == "Emoji" {
-                        FontId::proportional(size)
+                if checkboxes.contains(&current_pos) {
+                    format.color = Color32::TRANSPARENT;
+                    format.background = Color32::TRANSPARENT;
+                }
+                if let Some((_, exists)) =
+                    links.iter().find(|(range, _)| range.contains(&current_pos))
+                {
+                    let color = if *exists {
+                        link_color
                     } else {
-                        FontId::monospace(size)
-                    }
-                };
-
-                let base_color = text_color.unwrap_or(Color32::BLACK);
-
-                let mut format = match style {
-                    TextFormatting::Regular => egui::TextFormat {
-                        font_id: get_font_id(font_size),
-                        color: base_color,
-                        ..Default::default()
-                    },
-                    TextFormatting::Bold => egui::TextFormat {
-                        font_id: get_font_id(font_size * 1.3),
-                        color: base_color,
-                        ..Default::default()
-                    },
-                    TextFormatting::Italic => egui::TextFormat {
-                        font_id: get_font_id(font_size),
-                        color: base_color,
-                        italics: true,
-                        ..Default::default()
-                    },
-                    TextFormatting::BoldItalic => egui::TextFormat {
-                        font_id: get_font_id(font_size * 1.3),
-                        color: base_color,
-                        italics: true,
-                        ..Default::default()
-                    },
-                };
-
-                // Apply background color if specified
-                if let Some(bg) = bg_color {
-                    format.background = bg;
+                        missing_link_color
+                    };
+                    format.color = color;
+                    format.underline = egui::Stroke::new(1.0, color);
+                }
+                if footnotes.iter().any(|range| range.contains(&current_pos)) {
+                    format.color = link_color;
+                }
+                if web_links.iter().any(|range| range.contains(&current_pos)) {
+                    format.color = link_color;
+                    format.underline = egui::Stroke::new(1.0, link_color);
+                }
+                if focus_lines
+                    .as_ref()
+                    .is_some_and(|lines| !lines.contains(&current_pos))
+                {
+                    format.color = format.color.gamma_multiply(0.3);
+                }
+                if spoken
+                    .as_ref()
+                    .is_some_and(|sentence| sentence.contains(&current_pos))
+                {
+                    format.background = spoken_highlight;
+                }
+                if line_spacing != 1.0 {
+                    let height = ui.fonts(|f| f.row_height(&format.font_id));
+                    format.line_height = Some(height * line_spacing);
                 }
 
-                layout_job.append(segment, 0.0, format);
+                // Table and sketch placeholders get an invisible glyph on a row
+                // as tall as the block drawn over it
+                let mut rest = segment;
+                while let Some((index, height)) = rest.char_indices().find_map(|(i, c)| {
+                    block_heights
+                        .iter()
+                        .find(|(placeholder, _)| *placeholder == c)
+                        .map(|(_, height)| (i, *height))
+                }) {
+                    let placeholder_len = rest[index..].chars().next().map_or(1, char::len_utf8);
+                    layout_job.append(&rest[..index], 0.0, format.clone());
+                    layout_job.append(
+                        &rest[index..index + placeholder_len],
+                        0.0,
+                        egui::TextFormat {
+                            font_id: format.font_id.clone(),
+                            line_height: Some(height),
+                            color: Color32::TRANSPARENT,
+                            ..Default::default()
+                        },
+                    );
+                    rest = &rest[index + placeholder_len..];
+                }
+                layout_job.append(rest, 0.0, format);
                 current_pos = end;
             }
 
-            ui.fonts(|f| f.layout_job(layout_job))
+            let mut galley = ui.fonts(|f| f.layout_job(layout_job));
+            if paragraph_spacing > 0.0 {
+                space_paragraphs(std::sync::Arc::make_mut(&mut galley), paragraph_spacing);
+            }
+            if let Some((caret, old_len)) = caret {
+                let caret = (caret + (boundaries.len() - 1)).saturating_sub(old_len);
+                let line = text[..to_byte(caret)].matches('\n').count();
+                highlight_line(std::sync::Arc::make_mut(&mut galley), line, line_highlight);
+            }
+            galley
         };
 
-        let response = ui.add(
-            TextEdit::multiline(&mut self.text_content)
-                .desired_width(f32::INFINITY)
-                .desired_rows(10)
-                .layouter(&mut layouter),
-        );
+        let old_text = doc.text_content.clone();
+        let output = TextEdit::multiline(&mut doc.text_content)
+            .id(editor_id(doc.id, pane))
+            .desired_width(f32::INFINITY)
+            .desired_rows(10)
+            // Tab indents instead of moving the focus
+            .lock_focus(true)
+            .layouter(&mut layouter)
+            .show(ui);
+        let response = output.response.clone();
+        if response.changed() {
+            doc.apply_editor_edit(&old_text);
+            self.typed |= focused;
+            // This frame was laid out with the old ranges
+            ui.ctx().request_repaint();
+        }
+
+        // Draw each table and sketch over the space its placeholder reserved
+        for table in &mut doc.tables {
+            let placeholder = table.placeholder();
+            let Some(char_index) = doc.text_content.chars().position(|c| c == placeholder) else {
+                continue;
+            };
+            let cursor_rect = output
+                .galley
+                .pos_from_ccursor(egui::text::CCursor::new(char_index));
+            let rect = egui::Rect::from_min_size(
+                output.galley_pos + cursor_rect.min.to_vec2() + egui::vec2(4.0, 0.0),
+                egui::vec2(table.width(), table.height()),
+            );
+            let mut table_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .id_salt(("table", doc.id, pane, table.id))
+                    .max_rect(rect),
+            );
+            if table.show(&mut table_ui) {
+                doc.dirty = true;
+            }
+        }
+        for sketch in &mut doc.sketches {
+            let placeholder = sketch.placeholder();
+            let Some(char_index) = doc.text_content.chars().position(|c| c == placeholder) else {
+                continue;
+            };
+            let cursor_rect = output
+                .galley
+                .pos_from_ccursor(egui::text::CCursor::new(char_index));
+            let rect = egui::Rect::from_min_size(
+                output.galley_pos + cursor_rect.min.to_vec2() + egui::vec2(4.0, 0.0),
+                egui::vec2(sketch.width, sketch.total_height()),
+            );
+            let mut sketch_ui = ui.new_child(
+                egui::UiBuilder::new()
+                    .id_salt(("sketch", doc.id, pane, sketch.id))
+                    .max_rect(rect),
+            );
+            if sketch.show(&mut sketch_ui) {
+                doc.dirty = true;
+            }
+        }
+
+        // Draw a checkbox over each checklist marker
+        let mut toggled = None;
+        for (marker, mut checked) in doc.checklist() {
+            let first = output
+                .galley
+                .pos_from_ccursor(egui::text::CCursor::new(marker));
+            let last = output
+                .galley
+                .pos_from_ccursor(egui::text::CCursor::new(marker + 3));
+            let rect = egui::Rect::from_min_max(first.min, last.max)
+                .translate(output.galley_pos.to_vec2());
+            if ui
+                .put(rect, egui::Checkbox::without_text(&mut checked))
+                .changed()
+            {
+                toggled = Some(marker);
+            }
+        }
+        if let Some(marker) = toggled {
+            doc.toggle_checkbox(marker);
+        }
+
+        // The other pane of a split view keeps its own cursor in the editor state
+        if !focused {
+            return output;
+        }
 
         // Capture cursor selection
         if let Some(mut state) = TextEdit::load_state(ui.ctx(), response.id) {
             // If we have a pending cursor position, set it now
-            if let Some(pending_pos) = self.pending_cursor_pos.take() {
+            if let Some(pending_pos) = doc.pending_cursor_pos.take() {
                 use egui::text::{CCursor, CCursorRange};
                 // Check if we have a selection range (from Find operation)
-                if let Some(range) = &self.cursor_range {
+                if let Some(range) = &doc.cursor_range {
                     if range.start < range.end {
                         // Set selection from start to end
                         let start_cursor = CCursor::new(range.start);
@@ -558,197 +4001,663 @@ impl NoteApp {
                 state.store(ui.ctx(), response.id);
                 // Request focus on the text editor to ensure selection is visible
                 response.request_focus();
-                return response;
+                // A restored session keeps its saved scroll position instead
+                if !doc.restore_scroll {
+                    let cursor_rect = output.galley.pos_from_ccursor(CCursor::new(pending_pos));
+                    ui.scroll_to_rect(
+                        cursor_rect.translate(output.galley_pos.to_vec2()),
+                        Some(egui::Align::Center),
+                    );
+                }
+                return output;
             }
 
             // Only capture cursor position if we're not programmatically setting it
-            if !self.skip_cursor_capture {
+            if !doc.skip_cursor_capture {
                 let cursor_range = state.cursor.char_range();
                 if let Some(range) = cursor_range {
                     let start = range.primary.index.min(range.secondary.index);
                     let end = range.primary.index.max(range.secondary.index);
-                    self.cursor_range = Some(start..end);
+                    // Redraw so the bracket highlight follows the cursor
+                    if doc.cursor_range != Some(start..end) {
+                        ui.ctx().request_repaint();
+                    }
+                    doc.cursor_range = Some(start..end);
                 }
             }
 
             // Reset flag after applying
-            self.skip_cursor_capture = false;
+            doc.skip_cursor_capture = false;
 
             state.store(ui.ctx(), response.id);
         }
 
-        response
+        // Where the completion list opens
+        if let Some(range) = &doc.cursor_range {
+            let cursor_rect = output
+                .galley
+                .pos_from_ccursor(egui::text::CCursor::new(range.end));
+            self.completion_pos = Some(output.galley_pos + cursor_rect.left_bottom().to_vec2());
+        }
+
+        output
     }
 }
 
 impl eframe::App for NoteApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+            self.error_message = Some(e);
+        }
+        let session = Session::capture(&self.documents, self.active_tab, self.font_size);
+        eframe::set_value(storage, SESSION_KEY, &session);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.recovery.finish();
+        autosave::end_session();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Handle keyboard shortcuts
-        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Z)) {
-            self.undo();
+        self.settings.common.remember_window(ctx);
+        // Errors and notices shown in the status bar go to the log as well
+        if self.error_message != self.logged_message {
+            if let Some(message) = &self.error_message {
+                tracing::info!(message = %message, "Status bar");
+            }
+            self.logged_message = self.error_message.clone();
+        }
+
+        // Periodic autosave, history snapshots and recovery files
+        let interval = Duration::from_secs(self.settings.autosave_interval_secs);
+        if self.last_autosave.elapsed() >= interval {
+            self.autosave();
+        }
+        if self.settings.autosave_on_focus_loss
+            && ctx.input(|i| {
+                i.events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::WindowFocused(false)))
+            })
+        {
+            self.save_autosaved();
+        }
+        if self.last_recovery.elapsed() >= autosave::RECOVERY_INTERVAL {
+            self.write_recovery();
+        }
+        if let Some(e) = self.recovery.take_error() {
+            self.error_message = Some(e);
+        }
+        self.poll_sync();
+        self.receive_messages();
+        self.poll_calculate(ctx);
+        if let Some((_, shown)) = &self.status_message {
+            match STATUS_DURATION.checked_sub(shown.elapsed()) {
+                Some(left) => ctx.request_repaint_after(left),
+                None => self.status_message = None,
+            }
+        }
+        ctx.request_repaint_after(interval.min(autosave::RECOVERY_INTERVAL));
+
+        // Hold the window open while any document has unsaved changes
+        let close_requested = ctx.input(|i| i.viewport().close_requested());
+        if close_requested {
+            self.save_scratchpads();
+        }
+        if close_requested && !self.close_confirmed && self.documents.iter().any(|doc| doc.dirty) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_close = Some(PendingClose::Window);
+        }
+        if let Some(pending) = self.pending_close.clone() {
+            self.show_confirm_close_window(ctx, pending);
         }
-        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Y)) {
-            self.redo();
+
+        if !self.recovered.is_empty() {
+            self.show_recovery_window(ctx);
+        }
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+        if self.editing_style.is_some() {
+            self.show_styles_window(ctx);
+        }
+        self.show_replace_in_files(ctx);
+        if self.show_shortcuts {
+            self.show_shortcuts_window(ctx);
+        }
+        if self.show_word_count {
+            self.show_word_count_window(ctx);
+        }
+        if self.show_properties {
+            self.show_properties_window(ctx);
+        }
+        if self.show_font_picker {
+            self.show_font_picker_window(ctx);
+        }
+        if self.show_insert_table {
+            self.show_insert_table_window(ctx);
+        }
+        if self.show_go_to {
+            self.show_go_to_window(ctx);
+        }
+        if self.extract.is_some() {
+            self.show_extract_note_window(ctx);
+        }
+        if self.show_macros {
+            self.show_macros_window(ctx);
+        }
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        self.show_comparison_window(ctx);
+        self.show_history_window(ctx);
+        self.show_git_history_window(ctx);
+        if self.show_git_commit {
+            self.show_git_commit_window(ctx);
+        }
+        self.show_emoji_window(ctx);
+        if self.show_symbols {
+            self.show_symbol_window(ctx);
+        }
+        if !self.sync_conflicts.is_empty() {
+            self.show_sync_conflicts_window(ctx);
+        }
+        self.show_read_aloud_window(ctx);
+
+        // A macro records the keys as they come and plays back by adding its
+        // own, before the shortcuts below take theirs
+        self.record_macro(ctx);
+        self.play_macro_step(ctx);
+
+        // Keyboard shortcuts, consumed before the editor sees the keys
+        self.handle_completion_keys(ctx);
+        self.handle_editor_typing(ctx);
+        self.handle_zoom(ctx);
+        if self.focus_mode && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.focus_mode = false;
+        }
+        if self.shortcut_capture.is_none() {
+            let pane = self.focused_pane();
+            let editor = editor_id(self.doc().id, pane);
+            let editor_focused = ctx.memory(|m| m.has_focus(editor));
+            let commands = self
+                .settings
+                .keymap
+                .pressed(ctx, |command| editor_focused || !command.needs_editor());
+            for command in commands {
+                self.run_command(command);
+            }
         }
-        if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::F)) {
-            self.show_find_replace = !self.show_find_replace;
+        if self.focus_mode != self.focus_fullscreen {
+            self.focus_fullscreen = self.focus_mode;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(self.focus_mode));
         }
 
-        // Top menu bar
-        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+        // Top menu bar, hidden in focus mode like the tab and status bars
+        egui::TopBottomPanel::top("menu_bar").show_animated(ctx, !self.focus_mode, |ui| {
             ui.horizontal_wrapped(|ui| {
                 // File operations
-                if ui.button("📂 Open").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("Rich Text", &["rtxt"])
-                        .add_filter("Plain Text", &["txt"])
-                        .pick_file()
+                let new = ui.button("📄 New");
+                if std::mem::take(&mut self.focus_menu_bar) {
+                    new.request_focus();
+                }
+                if new.clicked() {
+                    self.new_tab();
+                }
+
+                // Choosing a recent folder opens the dialog there
+                let dialogs = &mut self.settings.common.dialogs;
+                let open = ui.button("📂 Open").clicked();
+                if dialogs.recent_menu(ui, "📁 Folders") || open {
+                    let path = dialogs.open(&[FileType::RichText, FileType::PlainText]);
+                    if let Some(path) = path {
+                        self.open_file(path);
+                    }
+                }
+
+                ui.add_enabled_ui(!self.settings.common.recent_files.is_empty(), |ui| {
+                    ui.menu_button("🕘 Recent", |ui| {
+                        let recent: Vec<PathBuf> = self
+                            .settings
+                            .common
+                            .existing_recent_files()
+                            .cloned()
+                            .collect();
+                        for path in recent {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().into_owned())
+                                .unwrap_or_default();
+                            if ui
+                                .button(name)
+                                .on_hover_text(path.display().to_string())
+                                .clicked()
+                            {
+                                self.open_file(path);
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Clear Recent Files").clicked() {
+                            self.settings.common.recent_files.clear();
+                            ui.close_menu();
+                        }
+                    });
+                });
+
+                ui.menu_button("🗒 Scratch", |ui| {
+                    for path in scratch::list() {
+                        let name = path
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        if ui.button(name).clicked() {
+                            self.open_file(path);
+                            ui.close_menu();
+                        }
+                    }
+                    if ui
+                        .button("➕ New Scratchpad")
+                        .on_hover_text("A note that saves itself in the app data folder")
+                        .clicked()
                     {
-                        match self.load_with_formatting(&path) {
-                            Ok(_) => {
-                                self.file_path = Some(path);
-                                self.error_message = None;
-                            }
+                        match scratch::create() {
+                            Ok(path) => self.open_file(path),
                             Err(e) => self.error_message = Some(e),
                         }
+                        ui.close_menu();
                     }
-                }
+                })
+                .response
+                .on_hover_text("Quick notes that never ask for a file name");
 
                 if ui.button("💾 Save").clicked() {
-                    let path_option = if let Some(path) = &self.file_path {
-                        Some(path.clone())
-                    } else {
-                        rfd::FileDialog::new()
-                            .add_filter("Rich Text", &["rtxt"])
-                            .set_file_name("untitled.rtxt")
-                            .save_file()
-                    };
-
-                    if let Some(path) = path_option {
-                        match self.save_with_formatting(&path) {
-                            Ok(_) => {
-                                self.file_path = Some(path);
-                                self.error_message = None;
-                            }
-                            Err(e) => self.error_message = Some(e),
-                        }
-                    }
+                    self.save_document(self.active_tab);
                 }
 
                 if ui.button("💾 Save As...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("Rich Text", &["rtxt"])
-                        .set_file_name("untitled.rtxt")
-                        .save_file()
-                    {
-                        match self.save_with_formatting(&path) {
-                            Ok(_) => {
-                                self.file_path = Some(path);
-                                self.error_message = None;
-                            }
-                            Err(e) => self.error_message = Some(e),
-                        }
+                    let dialogs = &mut self.settings.common.dialogs;
+                    if let Some(path) = dialogs.save(&[FileType::RichText], "untitled.rtxt") {
+                        self.save_to(path);
                     }
                 }
 
+                if ui
+                    .button("💾 Save a Copy...")
+                    .on_hover_text("Write the text to another file and keep editing this one")
+                    .clicked()
+                {
+                    self.save_copy();
+                }
+
+                if ui
+                    .button("⧉ Duplicate")
+                    .on_hover_text("Open an untitled copy in a new tab")
+                    .clicked()
+                {
+                    self.duplicate_document();
+                }
+
+                if ui
+                    .button("🌐 Export HTML")
+                    .on_hover_text("Save a copy as HTML with inline formatting")
+                    .clicked()
+                {
+                    self.export_html();
+                }
+
+                if ui
+                    .button("⇄ Compare")
+                    .on_hover_text("Compare the text with the saved file")
+                    .clicked()
+                {
+                    self.compare_with_saved();
+                }
+
+                if ui
+                    .button("🕘 History")
+                    .on_hover_text("Browse and restore earlier versions")
+                    .clicked()
+                {
+                    self.open_history();
+                }
+
+                if ui
+                    .button("ℹ Properties")
+                    .on_hover_text("File, encoding and counts of this note")
+                    .clicked()
+                {
+                    self.show_properties = !self.show_properties;
+                }
+
                 ui.separator();
 
                 // Undo/Redo
-                if ui.button("↶ Undo").clicked()
-                    || ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Z))
-                {
+                if ui.button("↶ Undo").clicked() {
                     self.undo();
                 }
-                if ui.button("↷ Redo").clicked()
-                    || ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Y))
-                {
+                if ui.button("↷ Redo").clicked() {
                     self.redo();
                 }
 
+                ui.menu_button("✏ Edit", |ui| {
+                    let has_selection = self
+                        .doc()
+                        .cursor_range
+                        .as_ref()
+                        .is_some_and(|range| !range.is_empty());
+                    for case in text::Case::ALL {
+                        if ui
+                            .add_enabled(has_selection, egui::Button::new(case.label()))
+                            .on_disabled_hover_text("Select some text first")
+                            .clicked()
+                        {
+                            self.doc_mut().change_case(case);
+                            ui.close_menu();
+                        }
+                    }
+                    let shortcut = self
+                        .settings
+                        .keymap
+                        .shortcut(Command::ExtractNote)
+                        .map_or(String::new(), |shortcut| shortcut.label());
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Extract to New Note...").shortcut_text(shortcut),
+                        )
+                        .on_hover_text("Move or copy the selection into a note of its own")
+                        .on_disabled_hover_text("Select some text first")
+                        .clicked()
+                    {
+                        self.open_extract_note();
+                        ui.close_menu();
+                    }
+                    let shortcut = self
+                        .settings
+                        .keymap
+                        .shortcut(Command::Calculate)
+                        .map_or(String::new(), |shortcut| shortcut.label());
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Calculate Selection").shortcut_text(shortcut),
+                        )
+                        .on_hover_text(
+                            "Work out the selected expression in the calculator; \
+                             its Insert into Note puts the result here",
+                        )
+                        .on_disabled_hover_text("Select some text first")
+                        .clicked()
+                    {
+                        self.calculate_selection();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let in_table = self.doc().pipe_table().is_some();
+                    ui.add_enabled_ui(in_table, |ui| {
+                        ui.menu_button("▦ Markdown table", |ui| {
+                            for edit in TableEdit::ALL {
+                                if ui.button(edit.label()).clicked() {
+                                    self.doc_mut().edit_pipe_table(edit);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    })
+                    .response
+                    .on_disabled_hover_text("Put the caret in a table made of | lines");
+                });
+
                 ui.separator();
 
-                // Formatting
+                // Named styles from the document's stylesheet
+                let doc = self.doc();
+                let caret = doc.cursor_range.as_ref().map_or(0, |range| range.start);
+                let current = doc
+                    .styled_ranges
+                    .iter()
+                    .find(|styled| styled.range.contains(&caret))
+                    .and_then(|styled| styled.named.clone());
+                let mut chosen_style = None;
+                egui::ComboBox::from_id_salt("named_style")
+                    .selected_text(current.as_deref().unwrap_or("No style"))
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(current.is_none(), "No style").clicked() {
+                            chosen_style = Some(None);
+                        }
+                        for style in &self.doc().named_styles {
+                            let is_current = current.as_deref() == Some(style.name.as_str());
+                            if ui.selectable_label(is_current, &style.name).clicked() {
+                                chosen_style = Some(Some(style.name.clone()));
+                            }
+                        }
+                        ui.separator();
+                        if ui.selectable_label(false, "Edit styles...").clicked() {
+                            self.editing_style = Some(0);
+                        }
+                    })
+                    .response
+                    .on_hover_text("Named style of the selection, or of the line for paragraph styles");
+                if let Some(name) = chosen_style {
+                    self.doc_mut().apply_named_style(name.as_deref());
+                }
+
+                // Formatting; each attribute is set on its own and kept when
+                // the others change
                 if ui.button("Bold").clicked() {
-                    self.current_style = TextFormatting::Bold;
-                    self.apply_style_to_selection();
+                    self.toggle_style(|styled| styled.style.is_bold(), StyleChange::Bold);
                 }
                 if ui.button("Italic").clicked() {
-                    self.current_style = TextFormatting::Italic;
-                    self.apply_style_to_selection();
+                    self.toggle_style(|styled| styled.style.is_italic(), StyleChange::Italic);
                 }
-                if ui.button("Bold+Italic").clicked() {
-                    self.current_style = TextFormatting::BoldItalic;
-                    self.apply_style_to_selection();
+                if ui.button("Underline").clicked() {
+                    self.toggle_style(|styled| styled.underline, StyleChange::Underline);
                 }
-                if ui.button("Regular").clicked() {
-                    self.current_style = TextFormatting::Regular;
-                    self.apply_style_to_selection();
+                if ui
+                    .button("Regular")
+                    .on_hover_text("Remove all formatting from the selection")
+                    .clicked()
+                {
+                    self.apply_style_to_selection(StyleChange::Clear);
                 }
 
                 ui.separator();
 
                 // Font size
                 if ui.button("🔍+ Larger").clicked() {
-                    self.font_size = (self.font_size + 2.0).min(72.0);
+                    self.set_font_size(self.font_size + 2.0);
                 }
                 if ui.button("🔍− Smaller").clicked() {
-                    self.font_size = (self.font_size - 2.0).max(8.0);
+                    self.set_font_size(self.font_size - 2.0);
                 }
                 ui.label(format!("{:.0}px", self.font_size));
 
                 ui.separator();
 
+                // Heading level or explicit size for the selection
+                let size_label = self
+                    .current_size
+                    .map_or("Normal".to_string(), TextSize::label);
+                let mut chosen_size = None;
+                egui::ComboBox::from_label("Size")
+                    .selected_text(size_label)
+                    .show_ui(ui, |ui| {
+                        if ui
+                            .selectable_label(self.current_size.is_none(), "Normal")
+                            .clicked()
+                        {
+                            chosen_size = Some(None);
+                        }
+                        for level in 1..=3 {
+                            let size = TextSize::Heading(level);
+                            if ui
+                                .selectable_label(self.current_size == Some(size), size.label())
+                                .clicked()
+                            {
+                                chosen_size = Some(Some(size));
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.custom_size)
+                                    .range(6.0..=96.0)
+                                    .suffix("px"),
+                            );
+                            if ui.button("Apply").clicked() {
+                                chosen_size = Some(Some(TextSize::Pixels(self.custom_size)));
+                            }
+                        });
+                    });
+                if let Some(size) = chosen_size {
+                    self.current_size = size;
+                    self.apply_style_to_selection(StyleChange::Size(size));
+                }
+
+                ui.separator();
+
                 // Font family
+                let mut font_file = None;
                 egui::ComboBox::from_label("Font")
                     .selected_text(&self.font_family)
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(
-                            &mut self.font_family,
-                            "Monospace".to_string(),
-                            "Monospace",
-                        );
-                        ui.selectable_value(
-                            &mut self.font_family,
-                            "Proportional".to_string(),
-                            "Proportional",
-                        );
-                        ui.selectable_value(
-                            &mut self.font_family,
-                            "Emoji".to_string(),
-                            "Emoji (Proportional + Emoji)",
-                        );
+                        for name in fonts::BUILT_IN {
+                            let label = if name == "Emoji" {
+                                "Emoji (Proportional + Emoji)"
+                            } else {
+                                name
+                            };
+                            if ui
+                                .selectable_value(&mut self.font_family, name.to_string(), label)
+                                .clicked()
+                            {
+                                self.settings.font_file = None;
+                            }
+                        }
+                        if !self.custom_fonts.loaded.is_empty() {
+                            ui.separator();
+                        }
+                        for (name, path) in &self.custom_fonts.loaded {
+                            if ui
+                                .selectable_label(*name == self.font_family, name)
+                                .on_hover_text(path.display().to_string())
+                                .clicked()
+                            {
+                                font_file = Some(path.clone());
+                            }
+                        }
+                        ui.separator();
+                        if ui.selectable_label(false, "System font...").clicked() {
+                            self.show_font_picker = true;
+                        }
+                        if ui.selectable_label(false, "Load font file...").clicked() {
+                            font_file = self.settings.common.dialogs.open(&[FileType::Font]);
+                        }
                     });
+                if let Some(path) = font_file {
+                    self.use_font_file(ctx, &path);
+                }
 
                 ui.separator();
 
                 // Color options
                 ui.horizontal(|ui| {
-                    ui.label("Text Color:");
+                    let label = ui.label("Text Color:");
                     if ui
                         .color_edit_button_srgba(&mut self.current_text_color)
+                        .labelled_by(label.id)
                         .changed()
                     {
-                        self.apply_style_to_selection();
+                        // Black is the default text color
+                        let color = Some(self.current_text_color)
+                            .filter(|color| *color != Color32::BLACK);
+                        self.apply_style_to_selection(StyleChange::TextColor(color));
                     }
                 });
 
                 ui.horizontal(|ui| {
-                    ui.label("Highlight:");
+                    let label = ui.label("Highlight:");
                     let mut has_bg = self.current_bg_color.is_some();
                     let mut bg_color = self.current_bg_color.unwrap_or(Color32::YELLOW);
 
-                    if ui.checkbox(&mut has_bg, "").changed() {
+                    if ui.checkbox(&mut has_bg, "").labelled_by(label.id).changed() {
                         self.current_bg_color = if has_bg { Some(bg_color) } else { None };
+                        self.apply_style_to_selection(StyleChange::Highlight(
+                            self.current_bg_color,
+                        ));
                     }
 
-                    if has_bg {
-                        if ui.color_edit_button_srgba(&mut bg_color).changed() {
-                            self.current_bg_color = Some(bg_color);
-                            self.apply_style_to_selection();
-                        }
+                    if has_bg
+                        && ui
+                            .color_edit_button_srgba(&mut bg_color)
+                            .labelled_by(label.id)
+                            .changed()
+                    {
+                        self.current_bg_color = Some(bg_color);
+                        self.apply_style_to_selection(StyleChange::Highlight(Some(bg_color)));
+                    }
+                });
+
+                ui.separator();
+
+                if ui.button("▦ Table").clicked() {
+                    self.show_insert_table = !self.show_insert_table;
+                }
+
+                ui.menu_button("¹ Footnote", |ui| {
+                    let shortcut = self
+                        .settings
+                        .keymap
+                        .shortcut(Command::Footnote)
+                        .map_or(String::new(), |shortcut| shortcut.label());
+                    if ui
+                        .add(egui::Button::new("Insert footnote").shortcut_text(shortcut))
+                        .on_hover_text("Add a numbered reference here and its note at the end")
+                        .clicked()
+                    {
+                        self.insert_footnote();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Renumber footnotes")
+                        .on_hover_text("Number the references in order and gather the notes at the end")
+                        .clicked()
+                    {
+                        self.doc_mut().renumber_footnotes();
+                        ui.close_menu();
                     }
                 });
 
+                if ui
+                    .button("✍ Sketch")
+                    .on_hover_text("Insert a canvas to draw on with the mouse")
+                    .clicked()
+                {
+                    if let Err(e) = self.doc_mut().insert_sketch() {
+                        self.error_message = Some(e);
+                    }
+                }
+
+                if ui
+                    .button("😀 Emoji")
+                    .on_hover_text("Pick an emoji to insert at the cursor")
+                    .clicked()
+                {
+                    self.emoji_picker = match self.emoji_picker {
+                        Some(_) => None,
+                        None => Some(EmojiPicker::new(ctx)),
+                    };
+                }
+
+                if ui
+                    .button("Ω Symbol")
+                    .on_hover_text("Find a character by name or code point and insert it")
+                    .clicked()
+                {
+                    self.show_symbols = !self.show_symbols;
+                }
+
                 ui.separator();
 
                 // View options
@@ -763,12 +4672,227 @@ impl eframe::App for NoteApp {
                     self.show_line_numbers = !self.show_line_numbers;
                 }
 
+                if ui
+                    .selectable_label(self.settings.show_minimap, "🗺 Minimap")
+                    .on_hover_text("Show an overview of the document beside the editor")
+                    .clicked()
+                {
+                    self.settings.show_minimap = !self.settings.show_minimap;
+                }
+
+                let reading = self.reader.is_some();
+                if ui
+                    .selectable_label(reading, "🔊 Read aloud")
+                    .on_hover_text(
+                        "Speak the selection, or the whole document, highlighting each sentence",
+                    )
+                    .clicked()
+                {
+                    self.toggle_read_aloud();
+                }
+
+                if ui
+                    .button("🎯 Focus")
+                    .on_hover_text(format!(
+                        "Full screen with only the text, dimmed except around the cursor{}; Esc leaves",
+                        self.settings
+                            .keymap
+                            .shortcut(Command::FocusMode)
+                            .map_or(String::new(), |shortcut| format!(" ({})", shortcut.label()))
+                    ))
+                    .clicked()
+                {
+                    self.focus_mode = true;
+                }
+
+                if ui
+                    .selectable_label(self.preview, "👁 Preview")
+                    .on_hover_text(format!(
+                        "Read-only view with formatting and $math$ rendered{}",
+                        self.settings
+                            .keymap
+                            .shortcut(Command::Preview)
+                            .map_or(String::new(), |shortcut| format!(" ({})", shortcut.label()))
+                    ))
+                    .clicked()
+                {
+                    self.preview = !self.preview;
+                }
+
+                if ui
+                    .selectable_label(self.show_attachments, "📎 Attachments")
+                    .on_hover_text("Files attached to this note")
+                    .clicked()
+                {
+                    self.show_attachments = !self.show_attachments;
+                }
+
                 ui.separator();
 
                 // Find & Replace
                 if ui.button("🔍 Find").clicked() {
                     self.show_find_replace = !self.show_find_replace;
                 }
+
+                let macros_label = if self.macros.recording.is_some() {
+                    "⏺ Recording"
+                } else {
+                    "⏺ Macros"
+                };
+                if ui
+                    .selectable_label(self.show_macros, macros_label)
+                    .on_hover_text("Record keystrokes and play them back")
+                    .clicked()
+                {
+                    self.show_macros = !self.show_macros;
+                }
+
+                ui.separator();
+
+                ui.menu_button("◫ Split", |ui| {
+                    if ui.button("Side by side").clicked() {
+                        self.open_split(true);
+                        ui.close_menu();
+                    }
+                    if ui.button("Top and bottom").clicked() {
+                        self.open_split(false);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(self.split.is_some(), egui::Button::new("Close split"))
+                        .clicked()
+                    {
+                        self.close_split();
+                        ui.close_menu();
+                    }
+                });
+
+                if ui
+                    .selectable_label(self.settings.show_notebook, "📚 Notes")
+                    .on_hover_text("Show the notebook sidebar")
+                    .clicked()
+                {
+                    self.settings.show_notebook = !self.settings.show_notebook;
+                }
+
+                if ui.button("⚙ Settings").clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+            });
+        });
+
+        // Tab bar
+        egui::TopBottomPanel::top("tab_bar").show_animated(ctx, !self.focus_mode, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                let mut close = Vec::new();
+                let mut pin = None;
+                let mut moved = None;
+                let count = self.documents.len();
+                let pinned_count = self.documents.iter().filter(|doc| doc.pinned).count();
+                for (index, doc) in self.documents.iter().enumerate() {
+                    let mut title = if doc.dirty {
+                        format!("{} ●", doc.title())
+                    } else {
+                        doc.title()
+                    };
+                    if doc.file_path.as_deref().is_some_and(scratch::is_scratch) {
+                        title = format!("🗒 {}", title);
+                    }
+                    if doc.pinned {
+                        title = format!("📌 {}", title);
+                    }
+                    let response = ui
+                        .selectable_label(index == self.active_tab, title)
+                        .interact(egui::Sense::drag())
+                        .on_hover_text(doc.file_path.as_ref().map_or_else(
+                            || "Not saved yet".to_string(),
+                            |path| path.display().to_string(),
+                        ));
+                    if response.clicked() {
+                        self.active_tab = index;
+                    }
+                    if response.middle_clicked() && !doc.pinned {
+                        close = vec![index];
+                    }
+                    // Drag a tab onto another to move it there, within its group
+                    response.dnd_set_drag_payload(index);
+                    if let Some(from) = response.dnd_hover_payload::<usize>() {
+                        let rect = response.rect;
+                        let x = if *from < index {
+                            rect.right()
+                        } else {
+                            rect.left()
+                        };
+                        ui.painter()
+                            .vline(x, rect.y_range(), ui.visuals().selection.stroke);
+                    }
+                    if let Some(from) = response.dnd_release_payload::<usize>() {
+                        let to = if self.documents[*from].pinned {
+                            index.min(pinned_count - 1)
+                        } else {
+                            index.max(pinned_count)
+                        };
+                        moved = Some((*from, to));
+                    }
+                    response.context_menu(|ui| {
+                        let label = if doc.pinned { "Unpin" } else { "📌 Pin" };
+                        if ui.button(label).clicked() {
+                            pin = Some((index, !doc.pinned));
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button("Close").clicked() {
+                            close = vec![index];
+                            ui.close_menu();
+                        }
+                        // Pinned tabs are left open by these two
+                        let others: Vec<usize> = (0..count)
+                            .filter(|other| *other != index && *other >= pinned_count)
+                            .collect();
+                        if ui
+                            .add_enabled(!others.is_empty(), egui::Button::new("Close Others"))
+                            .clicked()
+                        {
+                            close = others;
+                            ui.close_menu();
+                        }
+                        let right: Vec<usize> = (pinned_count.max(index + 1)..count).collect();
+                        if ui
+                            .add_enabled(
+                                !right.is_empty(),
+                                egui::Button::new("Close Tabs to the Right"),
+                            )
+                            .clicked()
+                        {
+                            close = right;
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui
+                            .add_enabled(doc.file_path.is_some(), egui::Button::new("Copy Path"))
+                            .clicked()
+                        {
+                            if let Some(path) = &doc.file_path {
+                                ui.ctx().copy_text(path.display().to_string());
+                            }
+                            ui.close_menu();
+                        }
+                    });
+                    if !doc.pinned && ui.small_button("✖").labeled("Close tab").clicked() {
+                        close = vec![index];
+                    }
+                    ui.separator();
+                }
+                if ui.small_button("➕").labeled("New tab").clicked() {
+                    self.new_tab();
+                }
+                if let Some((index, pinned)) = pin {
+                    self.set_pinned(index, pinned);
+                } else if let Some((from, to)) = moved {
+                    self.move_tab(from, to);
+                } else if !close.is_empty() {
+                    self.request_close_tabs(&close);
+                }
             });
         });
 
@@ -785,6 +4909,14 @@ impl eframe::App for NoteApp {
                     if ui.button("⬆ Prev").clicked() {
                         self.find_previous();
                     }
+                    let mut in_selection = self.doc().find_scope.is_some();
+                    if ui
+                        .checkbox(&mut in_selection, "In selection")
+                        .on_hover_text("Search and replace only within the text selected now")
+                        .changed()
+                    {
+                        self.set_find_in_selection(in_selection);
+                    }
 
                     ui.separator();
 
@@ -797,99 +4929,160 @@ impl eframe::App for NoteApp {
                     if ui.button("Replace All").clicked() {
                         self.replace_all();
                     }
+                    if ui
+                        .add_enabled(
+                            self.notebook.root().is_some() && !self.find_text.is_empty(),
+                            egui::Button::new("In All Notes..."),
+                        )
+                        .on_hover_text("Review the replacements in every note of the notes folder")
+                        .clicked()
+                    {
+                        let (find, replace) = (self.find_text.clone(), self.replace_text.clone());
+                        self.scan_notes_for_replace(&find, &replace);
+                    }
+
+                    ui.toggle_value(&mut self.settings.find_results, "☰ Results")
+                        .on_hover_text("List every match with its line");
 
                     if ui.button("✖ Close").clicked() {
                         self.show_find_replace = false;
                     }
                 });
+                if self.settings.find_results && !self.find_text.is_empty() {
+                    self.show_find_results(ui);
+                }
             });
         }
 
         // Status bar at bottom
-        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        egui::TopBottomPanel::bottom("status_bar").show_animated(ctx, !self.focus_mode, |ui| {
             ui.horizontal(|ui| {
                 if let Some(err) = &self.error_message {
-                    ui.colored_label(Color32::RED, err);
-                } else if let Some(path) = &self.file_path {
-                    // Show just the filename, not the full path
-                    let filename = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("Unknown");
-                    ui.label(format!("📄 {}", filename));
+                    ui.colored_label(palette::ERROR, err);
+                } else if let Some((status, _)) = &self.status_message {
+                    ui.label(status);
                 } else {
-                    ui.label("📄 Untitled");
+                    // Show just the filename, not the full path
+                    ui.label(format!("📄 {}", self.doc().title()));
                 }
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.label(format!(
-                        "Lines: {} | Chars: {} | Tab: CTRL+[",
-                        self.text_content.lines().count(),
-                        self.text_content.len()
-                    ));
+                    let current = self.doc().encoding;
+                    ui.menu_button(current.label(), |ui| {
+                        for encoding in encoding::Encoding::ALL {
+                            if ui.radio(encoding == current, encoding.label()).clicked() {
+                                let doc = self.doc_mut();
+                                doc.encoding = encoding;
+                                doc.dirty = true;
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Encoding for saving as plain text; .rtxt files are always UTF-8",
+                    );
+                    // This document's autosave, which may differ from the settings
+                    let path = self.doc().file_path.clone();
+                    // Scratchpads always save themselves
+                    if let Some(path) = path.filter(|path| !scratch::is_scratch(path)) {
+                        let label = if self.autosaves(self.doc()) {
+                            "Autosave: On"
+                        } else {
+                            "Autosave: Off"
+                        };
+                        let global = if self.settings.autosave { "on" } else { "off" };
+                        let files = &mut self.settings.autosave_files;
+                        let current = files.get(&path).copied();
+                        ui.menu_button(label, |ui| {
+                            let choices = [
+                                (None, format!("As in settings ({})", global)),
+                                (Some(true), "Always for this file".to_string()),
+                                (Some(false), "Never for this file".to_string()),
+                            ];
+                            for (choice, text) in choices {
+                                if ui.radio(current == choice, text).clicked() {
+                                    match choice {
+                                        Some(on) => files.insert(path.clone(), on),
+                                        None => files.remove(&path),
+                                    };
+                                    ui.close_menu();
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(format!(
+                            "Every {} s{}",
+                            self.settings.autosave_interval_secs,
+                            if self.settings.autosave_on_focus_loss {
+                                " and when the window loses focus"
+                            } else {
+                                ""
+                            }
+                        ));
+                    }
+                    if self.settings.indent_with_tabs {
+                        ui.label("Indent: Tab");
+                    } else {
+                        ui.label(format!("Indent: {} spaces", self.settings.tab_width));
+                    }
+                    let stats = text::stats(&self.doc().text_content);
+                    if ui
+                        .add(
+                            egui::Label::new(format!(
+                                "Lines: {} | Chars: {} |",
+                                stats.lines, stats.chars
+                            ))
+                            .sense(egui::Sense::click()),
+                        )
+                        .on_hover_text("Word count")
+                        .clicked()
+                    {
+                        self.show_word_count = !self.show_word_count;
+                    }
+                    // Caret position, updated as the editor reports cursor moves
+                    if let Some(range) = self.doc().cursor_range.clone() {
+                        let (line, column) = self.doc().line_col(range.end);
+                        let selected = match self.selection_stats() {
+                            Some(stats) => format!(
+                                " ({} chars, {} words, {} lines selected)",
+                                stats.chars, stats.words, stats.lines
+                            ),
+                            None => String::new(),
+                        };
+                        ui.label(format!("Ln {}, Col {}{} |", line, column, selected));
+                    }
+                    let (done, total) = self.doc().checklist_progress();
+                    if total > 0 {
+                        ui.label(format!("☑ {}/{} done |", done, total));
+                    }
                 });
             });
         });
 
+        if self.settings.show_notebook && !self.focus_mode {
+            self.show_notebook_panel(ctx);
+        }
+        if self.show_attachments && !self.focus_mode {
+            self.show_attachments_panel(ctx);
+        }
+
         // Central text editor panel
         egui::CentralPanel::default().show(ctx, |ui| {
-            // Create a scroll area that fills the entire central panel
-            egui::ScrollArea::vertical()
-                .auto_shrink([false, false])
-                .show(ui, |ui| {
-                    ui.horizontal_top(|ui| {
-                        // Line numbers column
-                        if self.show_line_numbers {
-                            let line_count = self.text_content.lines().count().max(1);
-
-                            // Use the same font family as the text editor
-                            let font_id = if self.font_family == "Proportional"
-                                || self.font_family == "Emoji"
-                            {
-                                FontId::proportional(self.font_size)
-                            } else {
-                                FontId::monospace(self.font_size)
-                            };
-                            ui.vertical(|ui| {
-                                // Set spacing to match text editor line height exactly
-                                ui.spacing_mut().item_spacing.y = 0.0;
-
-                                for i in 1..=line_count {
-                                    ui.add(egui::Label::new(
-                                        egui::RichText::new(format!("{:4}", i))
-                                            .font(font_id.clone())
-                                            .color(Color32::DARK_GRAY),
-                                    ));
-                                }
-                            });
-                            ui.separator();
-                        }
-
-                        // Text editor column - use Ctrl+[ to insert 4 spaces
-                        ui.vertical(|ui| {
-                            // Check if Ctrl+[ was pressed for inserting spaces (indent)
-                            let indent_pressed = ui.input(|i| {
-                                i.key_pressed(egui::Key::OpenBracket) && i.modifiers.ctrl
-                            });
-
-                            // Handle Ctrl+[ to insert 4 spaces BEFORE rendering
-                            if indent_pressed {
-                                self.save_state_for_undo();
-                                if let Some(range) = &self.cursor_range {
-                                    let spaces = " ".repeat(self.tab_width);
-                                    let cursor_pos = range.start;
-                                    self.text_content.insert_str(cursor_pos, &spaces);
-                                    // Set pending cursor position for next frame
-                                    let new_cursor_pos = cursor_pos + spaces.len();
-                                    self.cursor_range = Some(new_cursor_pos..new_cursor_pos);
-                                    self.pending_cursor_pos = Some(new_cursor_pos);
-                                }
-                            }
-
-                            self.render_rich_text_editable(ui);
-                        });
-                    });
-                });
+            if self.preview {
+                self.show_preview(ui);
+            } else if self.focus_mode {
+                self.show_editor_pane(ui, self.active_tab, self.focused_pane(), true);
+            } else if self.split.is_some() {
+                self.show_split_panes(ui);
+            } else {
+                self.show_editor_pane(ui, self.active_tab, 0, true);
+            }
         });
+        self.update_completion();
+        self.show_completion(ctx);
+        if let Some(target) = self.pending_link.take() {
+            self.follow_wiki_link(&target);
+        }
     }
 }
@@ -0,0 +1,448 @@
+// Inline $math$: finding the regions in the text, and rendering a small LaTeX
+// subset for the preview with Unicode symbols and raised or lowered text
+use egui::text::{LayoutJob, TextFormat};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Script {
+    Normal,
+    Super,
+    Sub,
+}
+
+// A run of rendered text; letters are italic, as variables
+#[derive(Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub script: Script,
+    pub italic: bool,
+}
+
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("emptyset", "∅"),
+    ("neg", "¬"),
+    ("ldots", "…"),
+    ("cdots", "⋯"),
+    ("circ", "∘"),
+    ("degree", "°"),
+    ("prime", "′"),
+];
+
+// Relations and operators, which get space on both sides
+const OPERATORS: &[(&str, &str)] = &[
+    ("times", "×"),
+    ("cdot", "·"),
+    ("div", "÷"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("wedge", "∧"),
+    ("vee", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("leftrightarrow", "↔"),
+    ("Leftrightarrow", "⇔"),
+    ("mapsto", "↦"),
+];
+
+// Written upright, as names rather than products of variables
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "exp", "lim", "max", "min", "sup", "inf", "det", "gcd", "deg", "dim", "arg",
+    "mod",
+];
+
+fn double_struck(c: char) -> char {
+    match c {
+        'C' => 'ℂ',
+        'N' => 'ℕ',
+        'P' => 'ℙ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'Z' => 'ℤ',
+        other => other,
+    }
+}
+
+// Byte ranges of the $...$ regions, dollars included. As in Pandoc, the math
+// may not start or end with a space, so "$5 and $6" stays text; "\$" is a
+// literal dollar and "$$" is left alone
+pub fn regions(text: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '$' if chars.peek().is_some_and(|&(_, next)| next == '$') => {
+                chars.next();
+            }
+            '$' => {
+                let rest = &text[start + 1..];
+                let Some(len) = closing_dollar(rest) else {
+                    continue;
+                };
+                let inner = &rest[..len];
+                if inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace) {
+                    continue;
+                }
+                let end = start + 1 + len + 1;
+                regions.push(start..end);
+                while chars.peek().is_some_and(|&(i, _)| i < end) {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    regions
+}
+
+// Length of the math before the next unescaped "$" on the line
+fn closing_dollar(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return None,
+            '$' => return (i > 0).then_some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    spans: Vec<Span>,
+}
+
+impl Parser<'_> {
+    fn push(&mut self, text: &str, script: Script, italic: bool) {
+        match self.spans.last_mut() {
+            Some(last) if last.script == script && last.italic == italic => {
+                last.text.push_str(text);
+            }
+            _ => self.spans.push(Span {
+                text: text.to_string(),
+                script,
+                italic,
+            }),
+        }
+    }
+
+    // An operator gets space around it, except as a sign at the start
+    fn push_operator(&mut self, symbol: &str, script: Script) {
+        let sign = self
+            .spans
+            .last()
+            .and_then(|span| span.text.chars().last())
+            .is_none_or(|c| "(=[{ ".contains(c));
+        if sign || script != Script::Normal {
+            self.push(symbol, script, false);
+        } else {
+            self.push(&format!(" {} ", symbol), script, false);
+        }
+    }
+
+    fn command_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            name.push(c);
+            self.chars.next();
+        }
+        if name.is_empty() {
+            // A symbol escaped with a backslash, like \{ or \,
+            name.extend(self.chars.next());
+        }
+        name
+    }
+
+    // A command's argument, rendered on its own, and whether it was more
+    // than one character
+    fn argument(&mut self, script: Script) -> (Vec<Span>, bool) {
+        let outer = std::mem::take(&mut self.spans);
+        while self.chars.peek() == Some(&' ') {
+            self.chars.next();
+        }
+        if self.chars.peek() == Some(&'{') {
+            self.chars.next();
+            self.group(script, true);
+        } else {
+            self.token(script);
+        }
+        let argument = std::mem::replace(&mut self.spans, outer);
+        let long = argument
+            .iter()
+            .map(|span| span.text.chars().count())
+            .sum::<usize>()
+            > 1;
+        (argument, long)
+    }
+
+    // A command's argument as written, for text inside math
+    fn raw_argument(&mut self) -> String {
+        while self.chars.peek() == Some(&' ') {
+            self.chars.next();
+        }
+        if self.chars.peek() != Some(&'{') {
+            return self.chars.next().map(String::from).unwrap_or_default();
+        }
+        self.chars.next();
+        let mut text = String::new();
+        let mut depth = 0;
+        for c in self.chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    fn append(&mut self, spans: Vec<Span>) {
+        for span in spans {
+            self.push(&span.text, span.script, span.italic);
+        }
+    }
+
+    // Put an argument in, in parentheses when it is longer than a character
+    fn append_wrapped(&mut self, (spans, long): (Vec<Span>, bool), script: Script) {
+        if long {
+            self.push("(", script, false);
+        }
+        self.append(spans);
+        if long {
+            self.push(")", script, false);
+        }
+    }
+
+    fn command(&mut self, script: Script) {
+        let name = self.command_name();
+        if let Some((_, symbol)) = SYMBOLS.iter().find(|(command, _)| *command == name) {
+            self.push(symbol, script, false);
+        } else if let Some((_, symbol)) = OPERATORS.iter().find(|(command, _)| *command == name) {
+            self.push_operator(symbol, script);
+        } else if FUNCTIONS.contains(&name.as_str()) {
+            self.push(&name, script, false);
+            // Keep "sin θ" apart, but not "sin(x)"
+            if self
+                .chars
+                .peek()
+                .is_some_and(|&c| c.is_alphanumeric() || c == '\\' || c == ' ')
+            {
+                self.push(" ", script, false);
+            }
+        } else {
+            match name.as_str() {
+                "frac" => {
+                    let numerator = self.argument(script);
+                    let denominator = self.argument(script);
+                    self.append_wrapped(numerator, script);
+                    self.push("/", script, false);
+                    self.append_wrapped(denominator, script);
+                }
+                "sqrt" => {
+                    let radicand = self.argument(script);
+                    self.push("√", script, false);
+                    self.append_wrapped(radicand, script);
+                }
+                "text" | "mathrm" | "operatorname" => {
+                    let text = self.raw_argument();
+                    self.push(&text, script, false);
+                }
+                "mathbb" => {
+                    let text: String = self.raw_argument().chars().map(double_struck).collect();
+                    self.push(&text, script, false);
+                }
+                // Sized delimiters are drawn at the normal size
+                "left" | "right" => {}
+                "," | ";" | ":" | " " => self.push(" ", script, false),
+                "quad" => self.push("  ", script, false),
+                symbol if symbol.chars().count() == 1 => self.push(symbol, script, false),
+                unknown => self.push(&format!("\\{}", unknown), script, false),
+            }
+        }
+    }
+
+    // One character, command or group
+    fn token(&mut self, script: Script) {
+        let Some(c) = self.chars.next() else {
+            return;
+        };
+        match c {
+            '\\' => self.command(script),
+            '{' => self.group(script, true),
+            '^' | '_' => {
+                let inner = if c == '^' { Script::Super } else { Script::Sub };
+                let (spans, _) = self.argument(inner);
+                for span in spans {
+                    self.push(&span.text, inner, span.italic);
+                }
+            }
+            '-' => self.push_operator("−", script),
+            '+' | '=' | '<' | '>' => self.push_operator(&c.to_string(), script),
+            '*' => self.push_operator("∗", script),
+            '\'' => self.push("′", script, false),
+            c if c.is_whitespace() => {}
+            c => self.push(&c.to_string(), script, c.is_alphabetic()),
+        }
+    }
+
+    fn group(&mut self, script: Script, in_braces: bool) {
+        while let Some(&c) = self.chars.peek() {
+            if in_braces && c == '}' {
+                self.chars.next();
+                return;
+            }
+            self.token(script);
+        }
+    }
+}
+
+// Render the source of a region, without its dollars
+pub fn render(source: &str) -> Vec<Span> {
+    let mut parser = Parser {
+        chars: source.chars().peekable(),
+        spans: Vec::new(),
+    };
+    parser.group(Script::Normal, false);
+    parser.spans
+}
+
+// Add rendered math to a layout job in the surrounding text's format
+pub fn append(job: &mut LayoutJob, source: &str, format: &TextFormat) {
+    for span in render(source) {
+        let mut format = format.clone();
+        format.italics = span.italic;
+        match span.script {
+            Script::Normal => {}
+            Script::Super | Script::Sub => {
+                format.font_id.size *= 0.7;
+                format.valign = if span.script == Script::Super {
+                    egui::Align::TOP
+                } else {
+                    egui::Align::BOTTOM
+                };
+            }
+        }
+        job.append(&span.text, 0.0, format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendered(source: &str) -> Vec<(String, Script, bool)> {
+        render(source)
+            .into_iter()
+            .map(|span| (span.text, span.script, span.italic))
+            .collect()
+    }
+
+    #[test]
+    fn math_regions_are_found() {
+        let text = "Area $\\pi r^2$, cost $5 and $6, not $$x$$ or \\$y$ or $a\nb$. $é$";
+        let found: Vec<&str> = regions(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(found, vec!["$\\pi r^2$", "$é$"]);
+        assert_eq!(regions("$ x$ $x $"), Vec::<Range<usize>>::new());
+    }
+
+    #[test]
+    fn latex_subset_renders_to_spans() {
+        use Script::*;
+        assert_eq!(
+            rendered("x^2 + y_{i,j}"),
+            vec![
+                ("x".to_string(), Normal, true),
+                ("2".to_string(), Super, false),
+                (" + ".to_string(), Normal, false),
+                ("y".to_string(), Normal, true),
+                ("i".to_string(), Sub, true),
+                (",".to_string(), Sub, false),
+                ("j".to_string(), Sub, true),
+            ]
+        );
+        let text = |source| -> String { render(source).into_iter().map(|s| s.text).collect() };
+        assert_eq!(text("\\frac{a+b}{2} \\leq \\sqrt x"), "(a + b)/2 ≤ √x");
+        assert_eq!(text("-\\alpha \\in \\mathbb{R}"), "−α ∈ ℝ");
+        assert_eq!(
+            text("\\sin\\theta = \\text{half of } 1"),
+            "sin θ = half of 1"
+        );
+        assert_eq!(text("\\sin(x)"), "sin(x)");
+        assert_eq!(text("\\unknown{x}"), "\\unknownx");
+    }
+}
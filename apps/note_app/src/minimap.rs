@@ -0,0 +1,135 @@
+// Zoomed-out overview of the document beside the editor
+use crate::document::{Document, TextSize};
+use egui::{Color32, Rect, Sense};
+
+pub const WIDTH: f32 = 80.0;
+// Height of one line when the whole document fits in the strip
+const LINE_HEIGHT: f32 = 3.0;
+// Characters that fill the full width of the strip
+const FULL_LINE: usize = 100;
+
+// What the minimap draws for one line of text
+#[derive(Debug, PartialEq)]
+pub struct LineMark {
+    pub indent: usize, // Leading whitespace, in characters
+    pub len: usize,    // Characters after the indentation
+    pub heading: bool,
+    pub search_hit: bool,
+}
+
+pub fn line_marks(doc: &Document, search: &str) -> Vec<LineMark> {
+    let mut start = 0;
+    doc.text_content
+        .split('\n')
+        .map(|line| {
+            let chars = line.chars().count();
+            let range = start..start + chars;
+            start += chars + 1;
+            let trimmed = line.trim_start();
+            let indent = chars - trimmed.chars().count();
+            LineMark {
+                indent,
+                len: trimmed.trim_end().chars().count(),
+                heading: doc.styled_ranges.iter().any(|styled| {
+                    matches!(styled.size, Some(TextSize::Heading(_)))
+                        && styled.range.start < range.end
+                        && styled.range.end > range.start
+                }),
+                search_hit: !search.is_empty() && line.contains(search),
+            }
+        })
+        .collect()
+}
+
+// Where the editor's scroll area is: its offset, visible height and the
+// height of everything it scrolls
+pub struct View {
+    pub offset: f32,
+    pub height: f32,
+    pub content_height: f32,
+}
+
+// Draw the minimap in `rect`; returns the scroll offset to move to when it is
+// clicked or dragged
+pub fn show(ui: &mut egui::Ui, rect: Rect, marks: &[LineMark], view: &View) -> Option<f32> {
+    let response = ui.interact(rect, ui.id().with("minimap"), Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+
+    // The whole document is squeezed in when it is taller than the strip
+    let line_height = LINE_HEIGHT.min(rect.height() / marks.len().max(1) as f32);
+    let map_height = line_height * marks.len() as f32;
+    let char_width = (rect.width() - 4.0) / FULL_LINE as f32;
+    for (index, mark) in marks.iter().enumerate() {
+        let top = rect.top() + index as f32 * line_height;
+        if mark.search_hit {
+            let hit = Rect::from_min_max(
+                egui::pos2(rect.left(), top),
+                egui::pos2(rect.right(), top + line_height.max(2.0)),
+            );
+            painter.rect_filled(hit, 0.0, Color32::from_rgb(255, 170, 0));
+        }
+        if mark.len == 0 {
+            continue;
+        }
+        let left = rect.left() + 2.0 + mark.indent.min(FULL_LINE) as f32 * char_width;
+        let right = (left + mark.len as f32 * char_width).min(rect.right() - 2.0);
+        let color = if mark.heading {
+            visuals.strong_text_color()
+        } else {
+            visuals.weak_text_color()
+        };
+        let bar = Rect::from_min_max(
+            egui::pos2(left, top),
+            egui::pos2(right, top + (line_height * 0.7).max(1.0)),
+        );
+        painter.rect_filled(bar, 0.0, color);
+    }
+
+    // The part of the document the editor shows
+    let content_height = view.content_height.max(view.height).max(1.0);
+    let visible = Rect::from_x_y_ranges(
+        rect.x_range(),
+        rect.top() + view.offset / content_height * map_height
+            ..=rect.top() + (view.offset + view.height) / content_height * map_height,
+    );
+    painter.rect_filled(visible, 0.0, visuals.selection.bg_fill.gamma_multiply(0.3));
+
+    // Center the view on the clicked point
+    if !response.is_pointer_button_down_on() && !response.clicked() {
+        return None;
+    }
+    let pointer = response.interact_pointer_pos()?;
+    let fraction = ((pointer.y - rect.top()) / map_height.max(1.0)).clamp(0.0, 1.0);
+    let offset = fraction * content_height - view.height / 2.0;
+    Some(offset.clamp(0.0, (content_height - view.height).max(0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{StyledRange, TextFormatting};
+
+    #[test]
+    fn marks_describe_each_line() {
+        let mut doc = Document::new(0);
+        doc.text_content = "Title\n  indented text  \n\nfind me".to_string();
+        doc.styled_ranges.push(StyledRange {
+            range: 0..5,
+            style: TextFormatting::Regular,
+            text_color: None,
+            bg_color: None,
+            size: Some(TextSize::Heading(1)),
+            underline: false,
+            named: None,
+        });
+        let marks = line_marks(&doc, "find");
+        assert_eq!(marks.len(), 4);
+        assert!(marks[0].heading);
+        assert!(!marks[1].heading);
+        assert_eq!((marks[1].indent, marks[1].len), (2, 13));
+        assert_eq!(marks[2].len, 0);
+        assert!(marks[3].search_hit && !marks[0].search_hit);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::attachment;
+use crate::git::{NoteStatus, NotesRepo};
+use gui_common::Labeled;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Files shown in the tree; other files in the notes folder are hidden
+const NOTE_EXTENSIONS: [&str; 2] = ["rtxt", "txt"];
+
+// A file or folder in the notes directory
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String,
+    pub children: Option<Vec<Entry>>, // Some for folders
+}
+
+// Requests from the sidebar that the app carries out
+pub enum NotebookAction {
+    Open(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+    Deleted(PathBuf),
+    GitHistory(PathBuf),
+}
+
+// An inline name prompt: for a new note or folder inside `parent`, or for
+// renaming `path`
+enum NamePrompt {
+    NewNote { parent: PathBuf, name: String },
+    NewFolder { parent: PathBuf, name: String },
+    Rename { path: PathBuf, name: String },
+}
+
+#[derive(Default)]
+pub struct Notebook {
+    root: Option<PathBuf>,
+    tree: Vec<Entry>,
+    prompt: Option<NamePrompt>,
+    confirm_delete: Option<PathBuf>,
+    pub error: Option<String>,
+    repo: Option<NotesRepo>, // When the folder is in a git repository
+    status: HashMap<PathBuf, NoteStatus>,
+}
+
+impl Notebook {
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    pub fn set_root(&mut self, root: Option<PathBuf>) {
+        self.repo = root.as_deref().and_then(NotesRepo::discover);
+        self.root = root;
+        self.refresh();
+    }
+
+    pub fn refresh(&mut self) {
+        self.tree = self.root.as_deref().map(read_tree).unwrap_or_default();
+        self.refresh_status();
+    }
+
+    // Git status only; call after notes are saved
+    pub fn refresh_status(&mut self) {
+        self.status = self
+            .repo
+            .as_ref()
+            .map(NotesRepo::status)
+            .unwrap_or_default();
+    }
+
+    // Every note in the tree
+    pub fn notes(&self) -> Vec<PathBuf> {
+        fn collect(entries: &[Entry], notes: &mut Vec<PathBuf>) {
+            for entry in entries {
+                match &entry.children {
+                    Some(children) => collect(children, notes),
+                    None => notes.push(entry.path.clone()),
+                }
+            }
+        }
+        let mut notes = Vec::new();
+        collect(&self.tree, &mut notes);
+        notes
+    }
+
+    pub fn repo(&self) -> Option<&NotesRepo> {
+        self.repo.as_ref()
+    }
+
+    // Changed notes below the folder with their status, by relative path
+    pub fn changed_notes(&self) -> Vec<(String, NoteStatus)> {
+        let Some(root) = &self.root else {
+            return Vec::new();
+        };
+        let mut changed: Vec<(String, NoteStatus)> = self
+            .status
+            .iter()
+            .filter_map(|(path, &status)| {
+                let relative = path.strip_prefix(root).ok()?;
+                Some((relative.to_string_lossy().into_owned(), status))
+            })
+            .collect();
+        changed.sort_by(|a, b| a.0.cmp(&b.0));
+        changed
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<NotebookAction> {
+        let Some(root) = self.root.clone() else {
+            ui.label("No notes folder chosen");
+            return None;
+        };
+
+        ui.horizontal(|ui| {
+            if ui.small_button("➕ Note").clicked() {
+                self.prompt = Some(NamePrompt::NewNote {
+                    parent: root.clone(),
+                    name: String::new(),
+                });
+            }
+            if ui.small_button("➕ Folder").clicked() {
+                self.prompt = Some(NamePrompt::NewFolder {
+                    parent: root.clone(),
+                    name: String::new(),
+                });
+            }
+            if ui.small_button("⟳").labeled("Refresh").clicked() {
+                self.refresh();
+            }
+        });
+        let mut action = self.show_prompt(ui);
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.separator();
+
+        let mut request = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let git = self.repo.is_some().then_some(&self.status);
+            for entry in &self.tree {
+                show_entry(ui, entry, git, &mut request);
+            }
+            if self.tree.is_empty() {
+                ui.weak("No notes yet");
+            }
+        });
+        match request {
+            Some(EntryRequest::Open(path)) => action = Some(NotebookAction::Open(path)),
+            Some(EntryRequest::NewNote(parent)) => {
+                self.prompt = Some(NamePrompt::NewNote {
+                    parent,
+                    name: String::new(),
+                })
+            }
+            Some(EntryRequest::NewFolder(parent)) => {
+                self.prompt = Some(NamePrompt::NewFolder {
+                    parent,
+                    name: String::new(),
+                })
+            }
+            Some(EntryRequest::Rename(path)) => {
+                let name = file_name(&path);
+                self.prompt = Some(NamePrompt::Rename { path, name });
+            }
+            Some(EntryRequest::Delete(path)) => self.confirm_delete = Some(path),
+            Some(EntryRequest::GitHistory(path)) => action = Some(NotebookAction::GitHistory(path)),
+            None => {}
+        }
+
+        action.or(self.show_delete_confirmation(ui.ctx()))
+    }
+
+    fn show_prompt(&mut self, ui: &mut egui::Ui) -> Option<NotebookAction> {
+        let (label, name) = match &mut self.prompt {
+            Some(NamePrompt::NewNote { name, .. }) => ("New note:", name),
+            Some(NamePrompt::NewFolder { name, .. }) => ("New folder:", name),
+            Some(NamePrompt::Rename { name, .. }) => ("Rename to:", name),
+            None => return None,
+        };
+        let (mut confirm, mut cancel) = (false, false);
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let response = ui.text_edit_singleline(name);
+            if ui.memory(|m| m.focused().is_none()) {
+                response.request_focus();
+            }
+            confirm = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            confirm |= ui.small_button("✔").labeled("OK").clicked();
+            cancel = ui.small_button("✖").labeled("Cancel").clicked()
+                || ui.input(|i| i.key_pressed(egui::Key::Escape));
+        });
+        if cancel {
+            self.prompt = None;
+            return None;
+        }
+        if !confirm {
+            return None;
+        }
+
+        let prompt = self.prompt.take()?;
+        let result = match prompt {
+            NamePrompt::NewNote { parent, name } => {
+                create_note(&parent, &name).map(|path| Some(NotebookAction::Open(path)))
+            }
+            NamePrompt::NewFolder { parent, name } => create_folder(&parent, &name).map(|_| None),
+            NamePrompt::Rename { path, name } => {
+                rename(&path, &name).map(|to| Some(NotebookAction::Renamed { from: path, to }))
+            }
+        };
+        self.refresh();
+        match result {
+            Ok(action) => {
+                self.error = None;
+                action
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    fn show_delete_confirmation(&mut self, ctx: &egui::Context) -> Option<NotebookAction> {
+        let path = self.confirm_delete.clone()?;
+        let (mut delete, mut cancel) = (false, false);
+        egui::Window::new("Delete?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                if path.is_dir() {
+                    ui.label(format!(
+                        "Delete the folder \"{}\" and everything in it?",
+                        file_name(&path)
+                    ));
+                } else {
+                    ui.label(format!("Delete \"{}\"?", file_name(&path)));
+                }
+                ui.horizontal(|ui| {
+                    delete = ui.button("🗑 Delete").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if cancel {
+            self.confirm_delete = None;
+        }
+        if !delete {
+            return None;
+        }
+        self.confirm_delete = None;
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        self.refresh();
+        match result {
+            Ok(_) => Some(NotebookAction::Deleted(path)),
+            Err(e) => {
+                self.error = Some(format!("Error deleting: {}", e));
+                None
+            }
+        }
+    }
+}
+
+enum EntryRequest {
+    Open(PathBuf),
+    NewNote(PathBuf),
+    NewFolder(PathBuf),
+    Rename(PathBuf),
+    Delete(PathBuf),
+    GitHistory(PathBuf),
+}
+
+// `git` holds file statuses when the folder is in a repository
+fn show_entry(
+    ui: &mut egui::Ui,
+    entry: &Entry,
+    git: Option<&HashMap<PathBuf, NoteStatus>>,
+    request: &mut Option<EntryRequest>,
+) {
+    let response = match &entry.children {
+        Some(children) => {
+            let header = egui::CollapsingHeader::new(format!("📁 {}", entry.name))
+                .id_salt(&entry.path)
+                .show(ui, |ui| {
+                    for child in children {
+                        show_entry(ui, child, git, request);
+                    }
+                });
+            header.header_response
+        }
+        None => {
+            let status = git.and_then(|statuses| statuses.get(&entry.path));
+            let response = ui
+                .horizontal(|ui| {
+                    let response = ui.selectable_label(false, format!("📄 {}", entry.name));
+                    if let Some(status) = status {
+                        ui.colored_label(status.color(), status.marker())
+                            .on_hover_text(status.label());
+                    }
+                    response
+                })
+                .inner;
+            if response.clicked() {
+                *request = Some(EntryRequest::Open(entry.path.clone()));
+            }
+            response
+        }
+    };
+    response.context_menu(|ui| {
+        if entry.children.is_some() {
+            if ui.button("➕ New note here").clicked() {
+                *request = Some(EntryRequest::NewNote(entry.path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("➕ New folder here").clicked() {
+                *request = Some(EntryRequest::NewFolder(entry.path.clone()));
+                ui.close_menu();
+            }
+        }
+        if ui.button("✏ Rename").clicked() {
+            *request = Some(EntryRequest::Rename(entry.path.clone()));
+            ui.close_menu();
+        }
+        if ui.button("🗑 Delete").clicked() {
+            *request = Some(EntryRequest::Delete(entry.path.clone()));
+            ui.close_menu();
+        }
+        if git.is_some() && entry.children.is_none() && ui.button("🕘 Git history").clicked() {
+            *request = Some(EntryRequest::GitHistory(entry.path.clone()));
+            ui.close_menu();
+        }
+    });
+}
+
+// Folders first, then notes, each sorted by name
+fn read_tree(dir: &Path) -> Vec<Entry> {
+    let Ok(read) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<Entry> = read
+        .flatten()
+        .map(|item| item.path())
+        .filter(|path| !file_name(path).starts_with('.'))
+        .filter(|path| !(path.is_dir() && file_name(path) == attachment::FOLDER))
+        .filter_map(|path| {
+            if path.is_dir() {
+                Some(Entry {
+                    name: file_name(&path),
+                    children: Some(read_tree(&path)),
+                    path,
+                })
+            } else {
+                is_note(&path).then(|| Entry {
+                    name: file_name(&path),
+                    children: None,
+                    path,
+                })
+            }
+        })
+        .collect();
+    entries.sort_by_key(|entry| (entry.children.is_none(), entry.name.to_lowercase()));
+    entries
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| NOTE_EXTENSIONS.contains(&ext))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Reject names that would escape the folder or are not valid file names
+fn check_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(format!("\"{}\" is not a valid name", name));
+    }
+    Ok(name)
+}
+
+// Create an empty note, adding .rtxt unless a note extension was given
+pub fn create_note(parent: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = check_name(name)?;
+    let mut path = parent.join(name);
+    if !is_note(&path) {
+        path = parent.join(format!("{}.rtxt", name));
+    }
+    if path.exists() {
+        return Err(format!("{} already exists", file_name(&path)));
+    }
+    fs::write(&path, "").map_err(|e| format!("Error creating note: {}", e))?;
+    Ok(path)
+}
+
+fn create_folder(parent: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = parent.join(check_name(name)?);
+    if path.exists() {
+        return Err(format!("{} already exists", file_name(&path)));
+    }
+    fs::create_dir(&path).map_err(|e| format!("Error creating folder: {}", e))?;
+    Ok(path)
+}
+
+fn rename(path: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = check_name(name)?;
+    let to = path.with_file_name(name);
+    if to == path {
+        return Ok(to);
+    }
+    if to.exists() {
+        return Err(format!("{} already exists", name));
+    }
+    fs::rename(path, &to).map_err(|e| format!("Error renaming: {}", e))?;
+    Ok(to)
+}
@@ -0,0 +1,142 @@
+// Page size and margins, shared by the paginated preview and the print
+// styles of the HTML export
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+// CSS pixels, which are also egui points at a zoom of 1
+const PX_PER_MM: f32 = 96.0 / 25.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PaperSize {
+    A4,
+    A5,
+    Letter,
+    Legal,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 4] = [
+        PaperSize::A4,
+        PaperSize::A5,
+        PaperSize::Letter,
+        PaperSize::Legal,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PaperSize::A4 => "A4",
+            PaperSize::A5 => "A5",
+            PaperSize::Letter => "Letter",
+            PaperSize::Legal => "Legal",
+        }
+    }
+
+    // Portrait width and height in millimetres
+    fn size_mm(self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::Letter => (215.9, 279.4),
+            PaperSize::Legal => (215.9, 355.6),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageSetup {
+    pub paper: PaperSize,
+    pub landscape: bool,
+    pub margin_mm: f32, // The same on all four sides
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            landscape: false,
+            margin_mm: 20.0,
+        }
+    }
+}
+
+impl PageSetup {
+    pub fn size(&self) -> egui::Vec2 {
+        let (width, height) = self.paper.size_mm();
+        let (width, height) = if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        egui::vec2(width, height) * PX_PER_MM
+    }
+
+    pub fn margin(&self) -> f32 {
+        self.margin_mm * PX_PER_MM
+    }
+
+    // The area inside the margins that text is laid out in
+    pub fn content_size(&self) -> egui::Vec2 {
+        (self.size() - egui::Vec2::splat(2.0 * self.margin())).max(egui::Vec2::splat(1.0))
+    }
+
+    // Print rule for the HTML export, so a browser breaks pages the same way
+    pub fn css(&self) -> String {
+        let orientation = if self.landscape {
+            "landscape"
+        } else {
+            "portrait"
+        };
+        format!(
+            "@page {{ size: {} {}; margin: {}mm; }}",
+            self.paper.label(),
+            orientation,
+            self.margin_mm
+        )
+    }
+}
+
+// Index of the first row on each page, given each row's top and bottom; a page
+// takes rows until the next would run past its bottom, and a row taller than
+// a page gets one to itself
+pub fn page_breaks(rows: &[Range<f32>], page_height: f32) -> Vec<usize> {
+    let mut breaks = vec![0];
+    let mut page_top = rows.first().map_or(0.0, |row| row.start);
+    for (i, row) in rows.iter().enumerate().skip(1) {
+        if row.end - page_top > page_height {
+            breaks.push(i);
+            page_top = row.start;
+        }
+    }
+    breaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_fill_pages() {
+        let rows: Vec<Range<f32>> = (0..10)
+            .map(|i| i as f32 * 20.0..(i + 1) as f32 * 20.0)
+            .collect();
+        assert_eq!(page_breaks(&rows, 70.0), vec![0, 3, 6, 9]);
+        assert_eq!(
+            page_breaks(&[0.0..10.0, 10.0..500.0, 500.0..510.0], 100.0),
+            vec![0, 1, 2]
+        );
+        assert_eq!(page_breaks(&[], 100.0), vec![0]);
+
+        let letter = PageSetup {
+            paper: PaperSize::Letter,
+            landscape: true,
+            margin_mm: 25.4,
+        };
+        assert_eq!(letter.size().round(), egui::vec2(1056.0, 816.0));
+        assert_eq!(letter.content_size().round(), egui::vec2(864.0, 624.0));
+        assert_eq!(
+            letter.css(),
+            "@page { size: Letter landscape; margin: 25.4mm; }"
+        );
+    }
+}
@@ -0,0 +1,332 @@
+// Markdown pipe tables typed as text: finding the table around the caret,
+// adding and removing rows and columns, and lining the pipes up
+use crate::text;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableEdit {
+    Align,
+    AddRow,    // Below the caret's row
+    RemoveRow, // The caret's row
+    AddColumn, // Right of the caret's column
+    RemoveColumn,
+}
+
+impl TableEdit {
+    pub const ALL: [TableEdit; 5] = [
+        TableEdit::Align,
+        TableEdit::AddRow,
+        TableEdit::RemoveRow,
+        TableEdit::AddColumn,
+        TableEdit::RemoveColumn,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TableEdit::Align => "Align columns",
+            TableEdit::AddRow => "Add row below",
+            TableEdit::RemoveRow => "Remove row",
+            TableEdit::AddColumn => "Add column right",
+            TableEdit::RemoveColumn => "Remove column",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Alignment {
+    Default,
+    Left,
+    Center,
+    Right,
+}
+
+// A table found in the text: its character range, from the start of its first
+// line to the end of its last, and its cells
+#[derive(Debug, PartialEq)]
+pub struct PipeTable {
+    pub range: Range<usize>,
+    rows: Vec<Vec<String>>,             // Without the separator row
+    alignments: Option<Vec<Alignment>>, // From the separator row under the header
+    row: usize,                         // Caret position, counting rows without the separator
+    column: usize,
+}
+
+fn is_table_line(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+// Cells between the pipes, trimmed; "\|" does not split
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+    let mut cells = vec![String::new()];
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !escaped {
+            cells.push(String::new());
+        } else if let Some(cell) = cells.last_mut() {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+// "---", ":--", "--:" or ":-:" in every cell
+fn alignments(cells: &[String]) -> Option<Vec<Alignment>> {
+    cells
+        .iter()
+        .map(|cell| {
+            let inner = cell.trim_start_matches(':').trim_end_matches(':');
+            if inner.is_empty() || !inner.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::Default,
+            })
+        })
+        .collect()
+}
+
+impl PipeTable {
+    // The table whose lines include the caret
+    pub fn at(text: &str, caret: usize) -> Option<Self> {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let mut starts = Vec::with_capacity(lines.len());
+        let mut pos = 0;
+        for line in &lines {
+            starts.push(pos);
+            pos += text::char_count(line) + 1;
+        }
+        let caret_line = starts.iter().rposition(|&start| start <= caret)?;
+        if !is_table_line(lines[caret_line]) {
+            return None;
+        }
+        let first = (0..=caret_line)
+            .rev()
+            .take_while(|&line| is_table_line(lines[line]))
+            .last()?;
+        let last = (caret_line..lines.len())
+            .take_while(|&line| is_table_line(lines[line]))
+            .last()?;
+
+        let mut rows: Vec<Vec<String>> =
+            lines[first..=last].iter().map(|line| cells(line)).collect();
+        let alignments = rows.get(1).and_then(|row| alignments(row));
+        if alignments.is_some() {
+            rows.remove(1);
+        }
+        // The caret's row and column, by the pipes before it on its line
+        let line_index = caret_line - first;
+        let row = match (alignments.is_some(), line_index) {
+            (true, 0) => 0,
+            (true, index) => index - 1,
+            (false, index) => index,
+        };
+        let before: String = lines[caret_line]
+            .chars()
+            .take(caret - starts[caret_line])
+            .collect();
+        let pipes = before.matches('|').count() - before.matches("\\|").count();
+        Some(Self {
+            range: starts[first]..starts[last] + text::char_count(lines[last]),
+            rows,
+            alignments,
+            row,
+            column: pipes.saturating_sub(1),
+        })
+    }
+
+    fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0).max(1)
+    }
+
+    pub fn apply(&mut self, edit: TableEdit) {
+        let columns = self.columns();
+        self.column = self.column.min(columns - 1);
+        match edit {
+            TableEdit::Align => {}
+            TableEdit::AddRow => {
+                self.row = (self.row + 1).min(self.rows.len());
+                self.rows.insert(self.row, vec![String::new(); columns]);
+            }
+            TableEdit::RemoveRow => {
+                if self.rows.len() > 1 {
+                    self.rows.remove(self.row.min(self.rows.len() - 1));
+                    self.row = self.row.min(self.rows.len() - 1);
+                }
+            }
+            TableEdit::AddColumn => {
+                self.column += 1;
+                for row in &mut self.rows {
+                    row.resize(columns, String::new());
+                    row.insert(self.column, String::new());
+                }
+                if let Some(alignments) = &mut self.alignments {
+                    alignments.resize(columns, Alignment::Default);
+                    alignments.insert(self.column, Alignment::Default);
+                }
+            }
+            TableEdit::RemoveColumn => {
+                if columns > 1 {
+                    for row in &mut self.rows {
+                        row.resize(columns, String::new());
+                        row.remove(self.column);
+                    }
+                    if let Some(alignments) = &mut self.alignments {
+                        alignments.resize(columns, Alignment::Default);
+                        alignments.remove(self.column);
+                    }
+                    self.column = self.column.min(columns - 2);
+                }
+            }
+        }
+    }
+
+    // The table with every column padded to its widest cell, and where the
+    // caret goes: the start of its cell
+    pub fn format(&self) -> (String, usize) {
+        let columns = self.columns();
+        let mut widths = vec![3; columns];
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(text::char_count(cell));
+            }
+        }
+        let alignment = |column: usize| {
+            self.alignments
+                .as_ref()
+                .and_then(|alignments| alignments.get(column).copied())
+                .unwrap_or(Alignment::Default)
+        };
+        let format_row = |row: &[String]| {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map_or("", String::as_str);
+                    let padding = widths[column] - text::char_count(cell);
+                    let (left, right) = match alignment(column) {
+                        Alignment::Right => (padding, 0),
+                        Alignment::Center => (padding / 2, padding - padding / 2),
+                        Alignment::Default | Alignment::Left => (0, padding),
+                    };
+                    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let separator = || {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let width = widths[column];
+                    match alignment(column) {
+                        Alignment::Default => "-".repeat(width),
+                        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                    }
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+
+        let mut lines: Vec<String> = self.rows.iter().map(|row| format_row(row)).collect();
+        if self.alignments.is_some() {
+            lines.insert(1.min(lines.len()), separator());
+        }
+        let caret_line = if self.alignments.is_some() && self.row > 0 {
+            self.row + 1
+        } else {
+            self.row
+        };
+        let line_start: usize = lines[..caret_line]
+            .iter()
+            .map(|line| text::char_count(line) + 1)
+            .sum();
+        // "| " plus each earlier cell with its " | "
+        let cell_start = 2 + widths[..self.column]
+            .iter()
+            .map(|width| width + 3)
+            .sum::<usize>();
+        let cell = self.rows[self.row]
+            .get(self.column)
+            .map_or("", String::as_str);
+        let padding = widths[self.column] - text::char_count(cell);
+        let indent = match alignment(self.column) {
+            Alignment::Right => padding,
+            Alignment::Center => padding / 2,
+            Alignment::Default | Alignment::Left => 0,
+        };
+        (lines.join("\n"), line_start + cell_start + indent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "Intro\n| Name | Qty |\n|---|--:|\n| apple | 3 |\n|pear|12|\nOutro";
+
+    #[test]
+    fn table_around_the_caret_is_found() {
+        assert!(PipeTable::at(TABLE, 2).is_none());
+        // Caret in "pear"
+        let caret = TABLE.find("pear").unwrap() + 1;
+        let table = PipeTable::at(TABLE, caret).unwrap();
+        assert_eq!(
+            text::char_slice(TABLE, table.range.clone()),
+            &TABLE[6..TABLE.len() - 6]
+        );
+        assert_eq!((table.row, table.column), (2, 0));
+        assert_eq!(table.rows[0], vec!["Name", "Qty"]);
+        assert_eq!(cells(r"| a \| b | c |"), vec![r"a \| b", "c"]);
+    }
+
+    #[test]
+    fn align_pads_columns_and_keeps_alignment() {
+        let caret = TABLE.find("12").unwrap();
+        let table = PipeTable::at(TABLE, caret).unwrap();
+        let (text, caret) = table.format();
+        assert_eq!(
+            text,
+            "| Name  | Qty |\n| ----- | --: |\n| apple |   3 |\n| pear  |  12 |"
+        );
+        assert_eq!(&text[caret..caret + 2], "12");
+    }
+
+    #[test]
+    fn rows_and_columns_are_added_and_removed() {
+        // Caret in the "Qty" column
+        let caret = TABLE.find('3').unwrap();
+        let mut table = PipeTable::at(TABLE, caret).unwrap();
+        table.apply(TableEdit::AddRow);
+        table.apply(TableEdit::AddColumn);
+        let (text, caret) = table.format();
+        assert_eq!(
+            text,
+            "| Name  | Qty |     |\n| ----- | --: | --- |\n| apple |   3 |     |\n\
+             |       |     |     |\n| pear  |  12 |     |"
+        );
+        // The new row's new cell
+        assert_eq!(caret, text.find("|     |     |\n| pear").unwrap() + 8);
+
+        table.apply(TableEdit::RemoveColumn);
+        table.apply(TableEdit::RemoveRow);
+        assert_eq!(
+            table.format().0,
+            "| Name  | Qty |\n| ----- | --: |\n| apple |   3 |\n| pear  |  12 |"
+        );
+
+        // A header row alone keeps at least one row and column
+        let mut single = PipeTable::at("|a|", 1).unwrap();
+        single.apply(TableEdit::RemoveRow);
+        single.apply(TableEdit::RemoveColumn);
+        assert_eq!(single.format().0, "| a   |");
+    }
+}
@@ -0,0 +1,100 @@
+// Facts about a document and its file for the properties window
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// Line break style of the text
+pub fn line_endings(text: &str) -> &'static str {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let cr = text.matches('\r').count() - crlf;
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => "None",
+        (true, false, false) => "LF (Unix)",
+        (false, true, false) => "CRLF (Windows)",
+        (false, false, true) => "CR (classic Mac)",
+        _ => "Mixed",
+    }
+}
+
+// "512 bytes", "1.5 KB", "2.0 MB"
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size < KB {
+        format!("{} bytes", bytes)
+    } else if size < KB * KB {
+        format!("{:.1} KB", size / KB)
+    } else {
+        format!("{:.1} MB", size / (KB * KB))
+    }
+}
+
+// "2024-03-09 14:05 UTC" for seconds since the Unix epoch
+pub fn format_time(time: u64) -> String {
+    let days = (time / 86400) as i64;
+    let seconds = time % 86400;
+    // Days to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60
+    )
+}
+
+// Size, creation and modification times of a file; the times are None where
+// the file system does not keep them
+pub struct FileInfo {
+    pub size: u64,
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+}
+
+pub fn file_info(path: &Path) -> Option<FileInfo> {
+    let metadata = fs::metadata(path).ok()?;
+    let seconds = |time: std::io::Result<std::time::SystemTime>| {
+        time.ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs())
+    };
+    Some(FileInfo {
+        size: metadata.len(),
+        created: seconds(metadata.created()),
+        modified: seconds(metadata.modified()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_sizes_and_times() {
+        assert_eq!(line_endings("one\ntwo\n"), "LF (Unix)");
+        assert_eq!(line_endings("one\r\ntwo"), "CRLF (Windows)");
+        assert_eq!(line_endings("one\r\ntwo\n"), "Mixed");
+        assert_eq!(line_endings("one"), "None");
+
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+
+        assert_eq!(format_time(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_time(951_782_400 + 3661), "2000-02-29 01:01 UTC");
+        assert_eq!(format_time(1_709_993_100), "2024-03-09 14:05 UTC");
+    }
+}
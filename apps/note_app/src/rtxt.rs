@@ -0,0 +1,309 @@
+use crate::attachment;
+use crate::document::{Document, StyledRange, TextFormatting, TextSize};
+use crate::sketch::Sketch;
+use crate::stylesheet::{self, StyleDef};
+use crate::table::Table;
+use crate::text;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Identifies a JSON .rtxt file and the newest layout this build understands
+const FORMAT_NAME: &str = "note_app.rtxt";
+pub const FORMAT_VERSION: u32 = 1;
+
+// Version 1 layout; span offsets are character indices, as in the editor
+#[derive(Serialize, Deserialize)]
+struct RtxtFile {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    metadata: Metadata,
+    text: String,
+    #[serde(default)]
+    styles: Vec<StyleSpan>,
+    #[serde(default)]
+    tables: Vec<Table>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sketches: Vec<Sketch>,
+    // Named styles; omitted while they are the defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stylesheet: Option<Vec<StyleDef>>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub created: Option<u64>,  // Seconds since the Unix epoch
+    pub modified: Option<u64>, // Updated on every save
+    pub generator: String,     // App and version that wrote the file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>, // Relative to the note's folder, with "/" separators
+}
+
+#[derive(Serialize, Deserialize)]
+struct StyleSpan {
+    start: usize,
+    end: usize,
+    style: TextFormatting,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_color: Option<[u8; 4]>, // Unmultiplied RGBA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bg_color: Option<[u8; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<TextSize>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    underline: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    named: Option<String>,
+}
+
+pub fn write(doc: &Document) -> Result<String, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .ok();
+    let file = RtxtFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        metadata: Metadata {
+            created: doc.metadata.created.or(now),
+            modified: now,
+            generator: format!("note_app {}", env!("CARGO_PKG_VERSION")),
+            attachments: doc.metadata.attachments.clone(),
+        },
+        text: doc.text_content.clone(),
+        styles: doc
+            .styled_ranges
+            .iter()
+            .map(|styled| StyleSpan {
+                start: styled.range.start,
+                end: styled.range.end,
+                style: styled.style,
+                text_color: styled.text_color.map(|c| c.to_srgba_unmultiplied()),
+                bg_color: styled.bg_color.map(|c| c.to_srgba_unmultiplied()),
+                size: styled.size,
+                underline: styled.underline,
+                named: styled.named.clone(),
+            })
+            .collect(),
+        // Tables whose placeholder was deleted are dropped
+        tables: doc
+            .tables
+            .iter()
+            .filter(|table| doc.text_content.contains(table.placeholder()))
+            .cloned()
+            .collect(),
+        sketches: doc
+            .sketches
+            .iter()
+            .filter(|sketch| doc.text_content.contains(sketch.placeholder()))
+            .cloned()
+            .collect(),
+        stylesheet: (doc.named_styles != stylesheet::defaults())
+            .then(|| doc.named_styles.iter().map(StyleDef::from).collect()),
+    };
+    serde_json::to_string_pretty(&file).map_err(|e| format!("Error encoding file: {}", e))
+}
+
+// Load a JSON file, a legacy "TEXT:/---STYLES---" file or plain text
+pub fn read(doc: &mut Document, content: &str) -> Result<(), String> {
+    doc.tables.clear();
+    doc.sketches.clear();
+    doc.named_styles = stylesheet::defaults();
+    doc.styled_ranges.clear();
+    doc.metadata = Metadata::default();
+
+    if content.trim_start().starts_with('{') {
+        // Plain text that merely looks like JSON is loaded as text
+        if let Ok(file) = serde_json::from_str::<RtxtFile>(content) {
+            if file.format == FORMAT_NAME {
+                return read_json(doc, file);
+            }
+        }
+    }
+    if let Some(body) = content.strip_prefix("TEXT:\n") {
+        read_legacy(doc, body);
+    } else {
+        doc.text_content = content.to_string();
+    }
+    Ok(())
+}
+
+fn read_json(doc: &mut Document, file: RtxtFile) -> Result<(), String> {
+    if file.version > FORMAT_VERSION {
+        return Err(format!(
+            "This file was saved by a newer version of the app (format version {})",
+            file.version
+        ));
+    }
+    let char_count = text::char_count(&file.text);
+    doc.text_content = file.text;
+    doc.styled_ranges = file
+        .styles
+        .into_iter()
+        .filter(|span| span.start < span.end.min(char_count))
+        .map(|span| StyledRange {
+            range: span.start..span.end.min(char_count),
+            style: span.style,
+            text_color: span.text_color.map(color_from_rgba),
+            bg_color: span.bg_color.map(color_from_rgba),
+            size: span.size,
+            underline: span.underline,
+            named: span.named,
+        })
+        .collect();
+    doc.tables = file.tables;
+    doc.sketches = file.sketches;
+    if let Some(sheet) = file.stylesheet {
+        doc.named_styles = sheet.into_iter().map(Into::into).collect();
+    }
+    doc.metadata = file.metadata;
+    // Dropped rather than trusted, as removing or opening one acts on its file
+    doc.metadata
+        .attachments
+        .retain(|name| attachment::is_valid(name));
+    Ok(())
+}
+
+// Pre-JSON format: the text, a "---STYLES---" line, then one
+// start..end:style:text_color:bg_color:size line per range and TABLE lines.
+// Ranges were taken from the editor selection, so they are character indices
+fn read_legacy(doc: &mut Document, body: &str) {
+    // The styles section never contains the separator, so the last one is
+    // the real one even if the text itself contains that line
+    let Some(separator_pos) = body.rfind("\n---STYLES---\n") else {
+        doc.text_content = body.to_string();
+        return;
+    };
+    doc.text_content = body[..separator_pos].to_string();
+    let char_count = text::char_count(&doc.text_content);
+
+    for line in body[separator_pos + 14..].lines() {
+        if line.starts_with("TABLE:") {
+            doc.tables.extend(Table::from_line(line));
+            continue;
+        }
+        let parts: Vec<&str> = line.split(':').collect();
+        let Some((start, end)) = parts[0].split_once("..") else {
+            continue;
+        };
+        let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) else {
+            continue;
+        };
+        let end = end.min(char_count);
+        if start >= end {
+            continue;
+        }
+        let Some(style) = parts.get(1) else {
+            continue;
+        };
+        let style = match *style {
+            "Bold" => TextFormatting::Bold,
+            "Italic" => TextFormatting::Italic,
+            "BoldItalic" => TextFormatting::BoldItalic,
+            _ => TextFormatting::Regular,
+        };
+        doc.styled_ranges.push(StyledRange {
+            range: start..end,
+            style,
+            text_color: parts.get(2).and_then(|field| parse_legacy_color(field)),
+            bg_color: parts.get(3).and_then(|field| parse_legacy_color(field)),
+            // Files written before sizes existed have no fifth field
+            size: parts.get(4).and_then(|field| TextSize::parse_field(field)),
+            underline: false,
+            named: None,
+        });
+    }
+}
+
+// R_G_B_A, or "none"
+fn parse_legacy_color(field: &str) -> Option<Color32> {
+    let rgba: Vec<u8> = field
+        .split('_')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let rgba: [u8; 4] = rgba.try_into().ok()?;
+    Some(color_from_rgba(rgba))
+}
+
+fn color_from_rgba([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip_keeps_char_ranges() {
+        let mut doc = Document::new(0);
+        doc.text_content = "Grüße 😀 aus Köln".to_string();
+        doc.styled_ranges.push(StyledRange {
+            range: 6..7,
+            style: TextFormatting::Bold,
+            text_color: Some(Color32::RED),
+            bg_color: None,
+            size: Some(TextSize::Heading(2)),
+            underline: true,
+            named: Some("Note".to_string()),
+        });
+        doc.named_styles
+            .push(crate::stylesheet::NamedStyle::new("Note"));
+        doc.metadata.attachments = vec![
+            "attachments/plan (2).pdf".to_string(),
+            "../../.bashrc".to_string(), // Dropped when read
+        ];
+        let mut sketch = Sketch::new(0);
+        sketch.strokes.push(crate::sketch::PenStroke {
+            points: vec![[1.0, 2.0], [30.5, 40.0]],
+            color: [255, 0, 0, 255],
+            width: 3.0,
+        });
+        doc.text_content.push(sketch.placeholder());
+        doc.sketches.push(sketch);
+
+        let content = write(&doc).unwrap();
+        let mut loaded = Document::new(1);
+        read(&mut loaded, &content).unwrap();
+        assert_eq!(loaded.text_content, doc.text_content);
+        assert_eq!(loaded.styled_ranges.len(), 1);
+        let styled = &loaded.styled_ranges[0];
+        assert_eq!(styled.range, 6..7);
+        assert_eq!(styled.text_color, Some(Color32::RED));
+        assert_eq!(styled.size, Some(TextSize::Heading(2)));
+        assert!(styled.underline);
+        assert_eq!(styled.named.as_deref(), Some("Note"));
+        assert_eq!(loaded.named_styles, doc.named_styles);
+        assert!(loaded.metadata.created.is_some());
+        assert_eq!(loaded.metadata.attachments, ["attachments/plan (2).pdf"]);
+        assert_eq!(loaded.sketches[0].strokes[0].points[1], [30.5, 40.0]);
+    }
+
+    #[test]
+    fn legacy_format_with_separator_in_text() {
+        let content =
+            "TEXT:\nnaïve\n---STYLES---\nmore\n---STYLES---\n2..5:Italic:none:255_255_0_200:h1\n";
+        let mut doc = Document::new(0);
+        read(&mut doc, content).unwrap();
+        assert_eq!(doc.text_content, "naïve\n---STYLES---\nmore");
+        let styled = &doc.styled_ranges[0];
+        assert_eq!(styled.range, 2..5);
+        assert_eq!(styled.style, TextFormatting::Italic);
+        assert_eq!(
+            styled.bg_color,
+            Some(Color32::from_rgba_unmultiplied(255, 255, 0, 200))
+        );
+        assert_eq!(styled.size, Some(TextSize::Heading(1)));
+    }
+
+    #[test]
+    fn newer_versions_and_plain_text() {
+        let mut doc = Document::new(0);
+        let newer = r#"{"format": "note_app.rtxt", "version": 99, "text": "x"}"#;
+        assert!(read(&mut doc, newer).is_err());
+
+        read(&mut doc, "{ not json").unwrap();
+        assert_eq!(doc.text_content, "{ not json");
+    }
+}
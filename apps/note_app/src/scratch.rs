@@ -0,0 +1,67 @@
+// Scratchpads: notes kept in the app data folder that save themselves, for
+// jotting things down without choosing a file name
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn dir() -> Option<PathBuf> {
+    eframe::storage_dir(crate::APP_ID).map(|dir| dir.join("scratch"))
+}
+
+pub fn is_scratch(path: &Path) -> bool {
+    dir().is_some_and(|dir| path.parent() == Some(dir.as_path()))
+}
+
+// The scratchpads, in the order they were made
+pub fn list() -> Vec<PathBuf> {
+    let Some(entries) = dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut pads: Vec<(usize, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            Some((number(name.strip_suffix(".rtxt")?)?, entry.path()))
+        })
+        .collect();
+    pads.sort();
+    pads.into_iter().map(|(_, path)| path).collect()
+}
+
+// The number in a "Scratch 3" name
+fn number(name: &str) -> Option<usize> {
+    name.strip_prefix("Scratch ")?.parse().ok()
+}
+
+// "Scratch 1", or the first number not taken
+fn next_name(taken: &[&str]) -> String {
+    (1..)
+        .map(|n| format!("Scratch {}", n))
+        .find(|name| !taken.contains(&name.as_str()))
+        .unwrap_or_default()
+}
+
+pub fn create() -> Result<PathBuf, String> {
+    let dir = dir().ok_or("No data directory for scratchpads")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating scratchpad folder: {}", e))?;
+    let pads = list();
+    let taken: Vec<&str> = pads
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str())
+        .collect();
+    let path = dir.join(format!("{}.rtxt", next_name(&taken)));
+    fs::write(&path, "").map_err(|e| format!("Error creating scratchpad: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratchpads_are_numbered() {
+        assert_eq!(next_name(&[]), "Scratch 1");
+        assert_eq!(next_name(&["Scratch 1", "Scratch 3"]), "Scratch 2");
+        assert_eq!(number("Scratch 12"), Some(12));
+        assert_eq!(number("Notes"), None);
+    }
+}
@@ -0,0 +1,96 @@
+// Every match of the Find text with the line around it, for the results list
+// under the find bar
+use crate::text;
+use std::ops::Range;
+
+// Results listed at most, so a one-letter search stays quick
+pub const MAX_RESULTS: usize = 1000;
+// Characters of context kept on each side of a match
+const CONTEXT: usize = 40;
+
+#[derive(Debug, PartialEq)]
+pub struct SearchMatch {
+    pub range: Range<usize>, // Characters of the document
+    pub line: usize,         // 1-based
+    pub before: String,      // The line before the match, shortened at its start
+    pub after: String,       // And after it, shortened at its end
+}
+
+// Matches within the character range `scope`, in order
+pub fn matches(text: &str, find: &str, scope: Range<usize>) -> Vec<SearchMatch> {
+    if find.is_empty() {
+        return Vec::new();
+    }
+    let find_len = text::char_count(find);
+    let bytes = text::char_range_to_bytes(text, scope);
+    let mut results = Vec::new();
+    // Characters and lines up to the previous match, counted as we go
+    let (mut last_byte, mut last_char, mut line) = (0, 0, 1);
+    for (offset, _) in text[bytes.clone()].match_indices(find) {
+        if results.len() == MAX_RESULTS {
+            break;
+        }
+        let start = bytes.start + offset;
+        let skipped = &text[last_byte..start];
+        last_char += text::char_count(skipped);
+        line += skipped.matches('\n').count();
+        last_byte = start;
+
+        let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let end = start + find.len();
+        let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+        let before = &text[line_start..start];
+        let before_len = text::char_count(before);
+        let before = if before_len > CONTEXT {
+            format!(
+                "…{}",
+                text::char_slice(before, before_len - CONTEXT..before_len)
+            )
+        } else {
+            before.to_string()
+        };
+        let after = &text[end..line_end];
+        let after = if text::char_count(after) > CONTEXT {
+            format!("{}…", text::char_slice(after, 0..CONTEXT))
+        } else {
+            after.to_string()
+        };
+        results.push(SearchMatch {
+            range: last_char..last_char + find_len,
+            line,
+            before: before.trim_start().to_string(),
+            after: after.trim_end().to_string(),
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_with_lines_and_context() {
+        let text = "  café cat\nno match\ncat and cat";
+        let found = matches(text, "cat", 0..text::char_count(text));
+        assert_eq!(found.len(), 3);
+        assert_eq!(
+            found[0],
+            SearchMatch {
+                range: 7..10,
+                line: 1,
+                before: "café ".to_string(),
+                after: String::new(),
+            }
+        );
+        assert_eq!((found[1].line, found[1].after.as_str()), (3, " and cat"));
+        assert_eq!((found[2].range.clone(), found[2].line), (28..31, 3));
+
+        // Only inside the scope, and long lines are shortened
+        assert_eq!(matches(text, "cat", 11..31).len(), 2);
+        let long = format!("{}x{}", "a".repeat(50), "b".repeat(50));
+        let found = matches(&long, "x", 0..101);
+        assert_eq!(found[0].before, format!("…{}", "a".repeat(40)));
+        assert_eq!(found[0].after, format!("{}…", "b".repeat(40)));
+    }
+}
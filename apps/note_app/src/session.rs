@@ -0,0 +1,48 @@
+use crate::document::Document;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const SESSION_KEY: &str = "note_session";
+
+// Open files and view state, saved on exit and restored at the next launch.
+// Untitled buffers are left to crash recovery
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub files: Vec<SessionFile>,
+    pub active_file: Option<usize>, // Index into files
+    pub font_size: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    pub cursor: usize, // Character index
+    pub scroll_offset: f32,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Session {
+    pub fn capture(documents: &[Document], active_tab: usize, font_size: f32) -> Self {
+        let mut session = Session {
+            font_size: Some(font_size),
+            ..Default::default()
+        };
+        for (index, doc) in documents.iter().enumerate() {
+            let Some(path) = &doc.file_path else {
+                continue;
+            };
+            if index == active_tab {
+                session.active_file = Some(session.files.len());
+            }
+            session.files.push(SessionFile {
+                path: path.clone(),
+                cursor: doc.cursor_range.as_ref().map_or(0, |range| range.end),
+                scroll_offset: doc.scroll_offset,
+                pinned: doc.pinned,
+            });
+        }
+        session
+    }
+}
@@ -0,0 +1,177 @@
+use egui::{Color32, Pos2, Sense, Shape, Stroke};
+use serde::{Deserialize, Serialize};
+
+// Sketches are anchored in the text like tables, by private-use characters
+// from the supplementary area, as the tables use up the basic one
+const PLACEHOLDER_BASE: u32 = 0xF0000;
+const MAX_SKETCHES: u32 = 0x1000;
+
+const DEFAULT_WIDTH: f32 = 400.0;
+const DEFAULT_HEIGHT: f32 = 240.0;
+const CONTROLS_HEIGHT: f32 = 28.0;
+const DEFAULT_PEN: (Color32, f32) = (Color32::BLACK, 2.0);
+
+// A line drawn in one drag; points are relative to the canvas corner
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PenStroke {
+    pub points: Vec<[f32; 2]>,
+    pub color: [u8; 4], // Unmultiplied RGBA
+    pub width: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sketch {
+    pub id: u32,
+    pub width: f32,
+    pub height: f32,
+    pub strokes: Vec<PenStroke>,
+    #[serde(skip)]
+    pen: Option<(Color32, f32)>, // Color and width for the next stroke
+}
+
+impl Sketch {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            strokes: Vec::new(),
+            pen: None,
+        }
+    }
+
+    pub fn next_id(sketches: &[Sketch]) -> Option<u32> {
+        let id = sketches.iter().map(|s| s.id + 1).max().unwrap_or(0);
+        (id < MAX_SKETCHES).then_some(id)
+    }
+
+    pub fn placeholder(&self) -> char {
+        char::from_u32(PLACEHOLDER_BASE + self.id).unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+
+    pub fn is_placeholder(c: char) -> bool {
+        (PLACEHOLDER_BASE..PLACEHOLDER_BASE + MAX_SKETCHES).contains(&(c as u32))
+    }
+
+    // Space reserved in the text layout for the canvas and its buttons
+    pub fn total_height(&self) -> f32 {
+        self.height + CONTROLS_HEIGHT
+    }
+
+    // Inline SVG for the HTML export
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             style=\"border: 1px solid #999; background: #fff;\">",
+            self.width, self.height
+        );
+        for stroke in &self.strokes {
+            let points: Vec<String> = stroke
+                .points
+                .iter()
+                .map(|[x, y]| format!("{:.1},{:.1}", x, y))
+                .collect();
+            let [r, g, b, a] = stroke.color;
+            svg.push_str(&format!(
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"rgba({}, {}, {}, {:.2})\" \
+                 stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                points.join(" "),
+                r,
+                g,
+                b,
+                f32::from(a) / 255.0,
+                stroke.width
+            ));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    // Draw the canvas, adding to the sketch while the mouse is dragged, and
+    // the pen controls under it; returns true if anything changed
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let (mut color, mut width) = self.pen.unwrap_or(DEFAULT_PEN);
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(self.width, self.height), Sense::drag());
+        let rect = response.rect;
+        painter.rect(rect, 0.0, Color32::WHITE, Stroke::new(1.0, Color32::GRAY));
+
+        let pointer = response
+            .interact_pointer_pos()
+            .map(|pos| rect.clamp(pos) - rect.min);
+        if let Some(pos) = pointer {
+            let point = [pos.x, pos.y];
+            if response.drag_started() {
+                self.strokes.push(PenStroke {
+                    points: vec![point],
+                    color: color.to_srgba_unmultiplied(),
+                    width,
+                });
+                changed = true;
+            } else if response.dragged() {
+                // Skip points too close to the last to matter
+                if let Some(stroke) = self.strokes.last_mut() {
+                    let far_enough = stroke
+                        .points
+                        .last()
+                        .is_none_or(|[x, y]| (pos.x - x).hypot(pos.y - y) >= 1.0);
+                    if far_enough {
+                        stroke.points.push(point);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let painter = painter.with_clip_rect(rect);
+        for stroke in &self.strokes {
+            let [r, g, b, a] = stroke.color;
+            let pen = Stroke::new(stroke.width, Color32::from_rgba_unmultiplied(r, g, b, a));
+            let points: Vec<Pos2> = stroke
+                .points
+                .iter()
+                .map(|[x, y]| rect.min + egui::vec2(*x, *y))
+                .collect();
+            match points.as_slice() {
+                [dot] => {
+                    painter.circle_filled(*dot, stroke.width / 2.0, pen.color);
+                }
+                _ => {
+                    painter.add(Shape::line(points, pen));
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgba(&mut color)
+                .on_hover_text("Pen color");
+            ui.add(
+                egui::DragValue::new(&mut width)
+                    .range(1.0..=20.0)
+                    .suffix(" px"),
+            )
+            .on_hover_text("Pen width");
+            if ui
+                .add_enabled(
+                    !self.strokes.is_empty(),
+                    egui::Button::new("↶ Stroke").small(),
+                )
+                .on_hover_text("Remove the last stroke")
+                .clicked()
+            {
+                self.strokes.pop();
+                changed = true;
+            }
+            if ui
+                .add_enabled(!self.strokes.is_empty(), egui::Button::new("Clear").small())
+                .clicked()
+            {
+                self.strokes.clear();
+                changed = true;
+            }
+        });
+        self.pen = Some((color, width));
+        changed
+    }
+}
@@ -0,0 +1,286 @@
+// Read aloud: speaks text a sentence at a time through the platform's speech
+// service (the tts crate), so the editor can highlight the sentence being
+// spoken. Builds without the "tts" feature, or systems where the service is
+// not available, hand each sentence to a speech program instead
+use std::io::Write;
+use std::ops::Range;
+use std::process::{Child, Command, Stdio};
+#[cfg(feature = "tts")]
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+// Character ranges of the sentences in `range` of the text. A sentence ends
+// after ., ! or ? followed by whitespace, or at a line break; blank stretches
+// are skipped
+pub fn sentences(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let chars: Vec<char> = text
+        .chars()
+        .skip(range.start)
+        .take(range.end - range.start)
+        .collect();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (index, &c) in chars.iter().enumerate() {
+        let next = chars.get(index + 1);
+        let end = c == '\n' || (".!?".contains(c) && next.is_none_or(|next| next.is_whitespace()));
+        if end || next.is_none() {
+            let sentence = &chars[start..=index];
+            // Trim surrounding whitespace off the range
+            if let Some(first) = sentence.iter().position(|c| !c.is_whitespace()) {
+                let last = sentence
+                    .iter()
+                    .rposition(|c| !c.is_whitespace())
+                    .unwrap_or(first);
+                sentences.push(range.start + start + first..range.start + start + last + 1);
+            }
+            start = index + 1;
+        }
+    }
+    sentences
+}
+
+// The platform's speech service, speaking one sentence at a time
+#[cfg(feature = "tts")]
+struct Voice {
+    tts: tts::Tts,
+    ended: Option<Receiver<()>>, // None when the service has no callbacks
+}
+
+#[cfg(feature = "tts")]
+impl Voice {
+    fn new() -> Option<Self> {
+        let tts = match tts::Tts::default() {
+            Ok(tts) => tts,
+            Err(e) => {
+                tracing::warn!(error = %e, "No speech service, using a speech program");
+                return None;
+            }
+        };
+        let (sender, ended) = mpsc::channel();
+        let ended = tts
+            .on_utterance_end(Some(Box::new(move |_| {
+                let _ = sender.send(());
+            })))
+            .ok()
+            .map(|()| ended);
+        Some(Self { tts, ended })
+    }
+
+    fn speak(&mut self, text: &str) -> Result<(), String> {
+        // Ends of sentences stopped by a pause
+        if let Some(ended) = &self.ended {
+            while ended.try_recv().is_ok() {}
+        }
+        self.tts
+            .speak(text, true)
+            .map(|_| ())
+            .map_err(|e| format!("Could not speak: {}", e))
+    }
+
+    fn finished(&self) -> bool {
+        match &self.ended {
+            Some(ended) => !matches!(ended.try_recv(), Err(TryRecvError::Empty)),
+            None => !self.tts.is_speaking().unwrap_or(false),
+        }
+    }
+
+    fn stop(&mut self) {
+        let _ = self.tts.stop();
+    }
+}
+
+// A sentence being spoken
+enum Utterance {
+    Program(Child),
+    #[cfg(feature = "tts")]
+    Voice,
+}
+
+// Start speaking on a separate process, trying each known speech program
+fn speak_with_program(text: &str) -> Result<Child, String> {
+    #[cfg(target_os = "macos")]
+    let programs: &[(&str, &[&str])] = &[("say", &["-f", "-"])];
+    #[cfg(target_os = "windows")]
+    let programs: &[(&str, &[&str])] = &[(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())",
+        ],
+    )];
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let programs: &[(&str, &[&str])] = &[
+        ("espeak-ng", &["--stdin"]),
+        ("espeak", &["--stdin"]),
+        ("spd-say", &["--wait", "--pipe-mode"]),
+    ];
+
+    for (program, args) in programs {
+        let mut command = Command::new(program);
+        command
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        #[cfg(target_os = "windows")]
+        {
+            // CREATE_NO_WINDOW, so no console flashes up for each sentence
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x0800_0000);
+        }
+        let spawned = command.spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Could not start {}: {}", program, e)),
+        };
+        // Closing stdin afterwards tells the program the text is complete
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        return Ok(child);
+    }
+    let names: Vec<&str> = programs.iter().map(|(program, _)| *program).collect();
+    Err(format!(
+        "No speech program found; install one of: {}",
+        names.join(", ")
+    ))
+}
+
+pub struct Reader {
+    pub doc_id: u64,
+    sentences: Vec<Range<usize>>,
+    current: usize,
+    speaking: Option<Utterance>,
+    #[cfg(feature = "tts")]
+    voice: Option<Voice>, // None to use a speech program
+    pub paused: bool,
+}
+
+impl Reader {
+    // Read the sentences of `range` in the document text
+    pub fn new(doc_id: u64, text: &str, range: Range<usize>) -> Option<Self> {
+        let sentences = sentences(text, range);
+        (!sentences.is_empty()).then_some(Self {
+            doc_id,
+            sentences,
+            current: 0,
+            speaking: None,
+            #[cfg(feature = "tts")]
+            voice: Voice::new(),
+            paused: false,
+        })
+    }
+
+    // The sentence being read, to highlight
+    pub fn current(&self) -> Option<Range<usize>> {
+        self.sentences.get(self.current).cloned()
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.current + 1, self.sentences.len())
+    }
+
+    // Move on when a sentence has been spoken and start the next one; returns
+    // false once everything was read
+    pub fn poll(&mut self, text: &str) -> Result<bool, String> {
+        if let Some(utterance) = &mut self.speaking {
+            let finished = match utterance {
+                Utterance::Program(child) => !matches!(child.try_wait(), Ok(None)),
+                #[cfg(feature = "tts")]
+                Utterance::Voice => self.voice.as_ref().is_none_or(Voice::finished),
+            };
+            if !finished {
+                return Ok(true);
+            }
+            self.speaking = None;
+            self.current += 1;
+        }
+        if self.paused {
+            return Ok(true);
+        }
+        let Some(sentence) = self.current() else {
+            return Ok(false);
+        };
+        // The text may have been edited while reading
+        let spoken: String = text
+            .chars()
+            .skip(sentence.start)
+            .take(sentence.end - sentence.start)
+            .collect();
+        if spoken.trim().is_empty() {
+            self.current += 1;
+            return Ok(true);
+        }
+        self.speaking = Some(self.speak(&spoken)?);
+        Ok(true)
+    }
+
+    fn speak(&mut self, text: &str) -> Result<Utterance, String> {
+        #[cfg(feature = "tts")]
+        if let Some(voice) = &mut self.voice {
+            voice.speak(text)?;
+            return Ok(Utterance::Voice);
+        }
+        speak_with_program(text).map(Utterance::Program)
+    }
+
+    // Stop mid-sentence; resuming repeats that sentence
+    pub fn pause(&mut self) {
+        self.paused = true;
+        match self.speaking.take() {
+            Some(Utterance::Program(mut child)) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            #[cfg(feature = "tts")]
+            Some(Utterance::Voice) => {
+                if let Some(voice) = &mut self.voice {
+                    voice.stop();
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.pause();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<&str> {
+        sentences(text, 0..text.chars().count())
+            .into_iter()
+            .map(|range| crate::text::char_slice(text, range))
+            .collect()
+    }
+
+    #[test]
+    fn text_is_read_by_sentence() {
+        assert_eq!(
+            split("Hello there. How are you?  Fine!\n\nA list\n- item 3.5 kg"),
+            vec![
+                "Hello there.",
+                "How are you?",
+                "Fine!",
+                "A list",
+                "- item 3.5 kg"
+            ]
+        );
+        assert!(split(" \n\t").is_empty());
+        // Ranges are in the whole text when reading a selection
+        let text = "Skip. Überall gut. Rest";
+        assert_eq!(sentences(text, 6..18), vec![6..18]);
+    }
+}
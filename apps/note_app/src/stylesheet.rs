@@ -0,0 +1,166 @@
+// Named styles: formatting kept once per document and referenced by name from
+// styled ranges, so changing a style changes all text that uses it
+use crate::document::{StyledRange, TextSize};
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedStyle {
+    pub name: String,
+    pub paragraph: bool, // Applied to whole lines rather than the selection
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub monospace: bool,
+    pub text_color: Option<Color32>,
+    pub bg_color: Option<Color32>,
+    pub size: Option<TextSize>,
+}
+
+impl NamedStyle {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            paragraph: false,
+            bold: false,
+            italic: false,
+            underline: false,
+            monospace: false,
+            text_color: None,
+            bg_color: None,
+            size: None,
+        }
+    }
+}
+
+// The styles a new document starts with
+pub fn defaults() -> Vec<NamedStyle> {
+    vec![
+        NamedStyle {
+            paragraph: true,
+            bold: true,
+            text_color: Some(Color32::from_rgb(0x1f, 0x3a, 0x68)),
+            size: Some(TextSize::Heading(2)),
+            ..NamedStyle::new("Heading")
+        },
+        NamedStyle {
+            monospace: true,
+            text_color: Some(Color32::from_rgb(0x8b, 0x1a, 0x4a)),
+            bg_color: Some(Color32::from_rgb(0xee, 0xee, 0xee)),
+            ..NamedStyle::new("Code")
+        },
+        NamedStyle {
+            paragraph: true,
+            italic: true,
+            text_color: Some(Color32::from_rgb(0x5a, 0x5a, 0x5a)),
+            ..NamedStyle::new("Quote")
+        },
+    ]
+}
+
+// A name not used by another style: "Style", "Style 2" and so on
+pub fn unused_name(sheet: &[NamedStyle], base: &str) -> String {
+    let taken = |name: &str| sheet.iter().any(|style| style.name == name);
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} {}", base, n))
+        .find(|name| !taken(name))
+        .unwrap_or_default()
+}
+
+// How a range looks: its named style with the range's own attributes on top
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Effective {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub monospace: bool,
+    pub text_color: Option<Color32>,
+    pub bg_color: Option<Color32>,
+    pub size: Option<TextSize>,
+}
+
+pub fn effective(styled: Option<&StyledRange>, sheet: &[NamedStyle]) -> Effective {
+    let Some(styled) = styled else {
+        return Effective::default();
+    };
+    let named = styled
+        .named
+        .as_ref()
+        .and_then(|name| sheet.iter().find(|style| &style.name == name));
+    let base = named.map_or(Effective::default(), |style| Effective {
+        bold: style.bold,
+        italic: style.italic,
+        underline: style.underline,
+        monospace: style.monospace,
+        text_color: style.text_color,
+        bg_color: style.bg_color,
+        size: style.size,
+    });
+    Effective {
+        bold: base.bold || styled.style.is_bold(),
+        italic: base.italic || styled.style.is_italic(),
+        underline: base.underline || styled.underline,
+        monospace: base.monospace,
+        text_color: styled.text_color.or(base.text_color),
+        bg_color: styled.bg_color.or(base.bg_color),
+        size: styled.size.or(base.size),
+    }
+}
+
+// Stored form in .rtxt files, with colors as unmultiplied RGBA
+#[derive(Serialize, Deserialize)]
+pub struct StyleDef {
+    name: String,
+    #[serde(default)]
+    paragraph: bool,
+    #[serde(default)]
+    bold: bool,
+    #[serde(default)]
+    italic: bool,
+    #[serde(default)]
+    underline: bool,
+    #[serde(default)]
+    monospace: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_color: Option<[u8; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bg_color: Option<[u8; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<TextSize>,
+}
+
+impl From<&NamedStyle> for StyleDef {
+    fn from(style: &NamedStyle) -> Self {
+        Self {
+            name: style.name.clone(),
+            paragraph: style.paragraph,
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+            monospace: style.monospace,
+            text_color: style.text_color.map(|c| c.to_srgba_unmultiplied()),
+            bg_color: style.bg_color.map(|c| c.to_srgba_unmultiplied()),
+            size: style.size,
+        }
+    }
+}
+
+impl From<StyleDef> for NamedStyle {
+    fn from(def: StyleDef) -> Self {
+        let color = |[r, g, b, a]: [u8; 4]| Color32::from_rgba_unmultiplied(r, g, b, a);
+        Self {
+            name: def.name,
+            paragraph: def.paragraph,
+            bold: def.bold,
+            italic: def.italic,
+            underline: def.underline,
+            monospace: def.monospace,
+            text_color: def.text_color.map(color),
+            bg_color: def.bg_color.map(color),
+            size: def.size,
+        }
+    }
+}
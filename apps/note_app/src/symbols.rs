@@ -0,0 +1,159 @@
+// "Insert symbol" dialog: find any character by name or code point
+use crate::emoji::name_matches;
+use gui_common::Labeled;
+
+// Results listed at once; a longer query narrows them down
+const MAX_RESULTS: usize = 400;
+// Symbols kept in the recently used row
+pub const MAX_RECENT: usize = 16;
+
+// "U+2713", "0x2713" or "2713"
+pub fn parse_code_point(query: &str) -> Option<char> {
+    let query = query.trim();
+    let hex = ["U+", "u+", "0x", "0X"]
+        .into_iter()
+        .find_map(|prefix| query.strip_prefix(prefix))
+        .unwrap_or(query);
+    if hex.is_empty() || hex.len() > 6 {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+pub fn code_point(c: char) -> String {
+    format!("U+{:04X}", c as u32)
+}
+
+// Named characters, leaving out ideographs and syllables whose names are
+// only their code point (they can still be found by code point)
+fn named_characters() -> Vec<(char, String)> {
+    (0..=0x3FFFF)
+        .filter_map(char::from_u32)
+        .filter(|c| !c.is_control())
+        .filter_map(|c| {
+            let name = unicode_names2::name(c)?.to_string();
+            let numbered = name.ends_with(&format!("-{:04X}", c as u32));
+            (!numbered).then(|| (c, name.to_lowercase()))
+        })
+        .collect()
+}
+
+// The character at a typed code point first, then those whose names match
+fn search(names: &[(char, String)], query: &str) -> Vec<char> {
+    let exact = parse_code_point(query).filter(|c| !c.is_control());
+    let mut results: Vec<char> = exact.into_iter().collect();
+    if query.trim().is_empty() {
+        return results;
+    }
+    results.extend(
+        names
+            .iter()
+            .filter(|(c, name)| Some(*c) != exact && name_matches(name, query))
+            .map(|(c, _)| *c)
+            .take(MAX_RESULTS),
+    );
+    results
+}
+
+fn symbol_button(ui: &mut egui::Ui, c: char) -> bool {
+    let name = unicode_names2::name(c).map_or(String::new(), |name| name.to_string());
+    ui.add(egui::Button::new(egui::RichText::new(c.to_string()).size(20.0)).frame(false))
+        .labeled(&format!("{} {}", code_point(c), name))
+        .clicked()
+}
+
+pub struct SymbolDialog {
+    query: String,
+    names: Vec<(char, String)>,
+    results: Vec<char>,
+    searched: String, // Query the results are for
+}
+
+impl SymbolDialog {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            names: named_characters(),
+            results: Vec::new(),
+            searched: String::new(),
+        }
+    }
+
+    // Show the dialog; returns the symbol that was clicked
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, recent: &[char]) -> Option<char> {
+        let mut chosen = None;
+        egui::Window::new("Insert Symbol")
+            .open(open)
+            .default_size([420.0, 360.0])
+            .show(ctx, |ui| {
+                if !recent.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Recent:");
+                        for &c in recent {
+                            if symbol_button(ui, c) {
+                                chosen = Some(c);
+                            }
+                        }
+                    });
+                    ui.separator();
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Name or code point, e.g. check mark or U+2713")
+                        .desired_width(f32::INFINITY),
+                );
+                if self.query != self.searched {
+                    self.results = search(&self.names, &self.query);
+                    self.searched = self.query.clone();
+                }
+                if self.results.is_empty() && !self.query.trim().is_empty() {
+                    ui.label("No matching characters");
+                }
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for &c in &self.results {
+                                if symbol_button(ui, c) {
+                                    chosen = Some(c);
+                                }
+                            }
+                        });
+                    });
+            });
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_points() {
+        assert_eq!(parse_code_point("U+2713"), Some('✓'));
+        assert_eq!(parse_code_point(" 0x1F600 "), Some('😀'));
+        assert_eq!(parse_code_point("e9"), Some('é'));
+        assert_eq!(parse_code_point("D800"), None); // A surrogate
+        assert_eq!(parse_code_point("check"), None);
+        assert_eq!(parse_code_point("U+"), None);
+        assert_eq!(code_point('✓'), "U+2713");
+        assert_eq!(code_point('a'), "U+0061");
+    }
+
+    #[test]
+    fn search_by_name_or_code_point() {
+        let names = vec![
+            ('✓', "check mark".to_string()),
+            ('✔', "heavy check mark".to_string()),
+            ('a', "latin small letter a".to_string()),
+        ];
+        assert_eq!(search(&names, "CHECK"), vec!['✓', '✔']);
+        assert_eq!(search(&names, "heavy check"), vec!['✔']);
+        // "41" is a code point; "a" would be too, but U+000A is a control
+        assert_eq!(search(&names, "41"), vec!['A']);
+        assert_eq!(search(&names, "a").len(), 3);
+        assert_eq!(search(&names, "U+2713"), vec!['✓']);
+        assert!(search(&names, " ").is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+// Two-way sync of the notes folder with a WebDAV folder. Each file's state at
+// the last sync is remembered, so a change on one side can be told apart from
+// changes on both, which are conflicts for the user to resolve
+use crate::document::Document;
+use crate::webdav::Account;
+use crate::{diff, encoding, history, notebook, rtxt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+// A file as it was when both sides last agreed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Synced {
+    hash: u64,    // Of the local file's bytes
+    etag: String, // Of the remote file
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SyncState {
+    files: BTreeMap<String, Synced>, // By path relative to the notes folder
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Upload,
+    Download,
+    DeleteLocal,
+    DeleteRemote,
+    Conflict, // Changed on both sides, or new on both
+    Forget,   // Gone from both sides
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resolution {
+    KeepMine,
+    KeepTheirs,
+    Merge, // Both versions' lines, with differing runs between conflict markers
+}
+
+// A note changed both here and on the server since the last sync
+pub struct Conflict {
+    pub path: String,
+    pub mine: String, // Text of each version, for previews
+    pub theirs: String,
+    theirs_bytes: Vec<u8>,
+    etag: String,
+}
+
+#[derive(Default)]
+pub struct Outcome {
+    pub transferred: usize,
+    pub conflicts: Vec<Conflict>,
+    pub errors: Vec<String>,
+    pub changed: Vec<PathBuf>, // Local files written or deleted
+}
+
+impl Outcome {
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Synced: {} file(s) transferred", self.transferred);
+        if !self.conflicts.is_empty() {
+            summary.push_str(&format!(", {} conflict(s)", self.conflicts.len()));
+        }
+        if !self.errors.is_empty() {
+            summary.push_str(&format!(", {} error(s)", self.errors.len()));
+        }
+        summary
+    }
+}
+
+// Run a sync job on a background thread; the app polls the receiver
+pub fn spawn(
+    ctx: &egui::Context,
+    job: impl FnOnce() -> Outcome + Send + 'static,
+) -> Receiver<Outcome> {
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    std::thread::spawn(move || {
+        let _ = sender.send(job());
+        ctx.request_repaint();
+    });
+    receiver
+}
+
+// One state file per server folder and notes folder pair
+fn state_path(account: &Account, root: &Path) -> Option<PathBuf> {
+    let key = format!("{}\n{}", account.url.trim(), root.to_string_lossy());
+    let dir = eframe::storage_dir(crate::APP_ID)?.join("sync");
+    Some(dir.join(format!("{:016x}.json", history::fnv1a(key.as_bytes()))))
+}
+
+impl SyncState {
+    fn load(account: &Account, root: &Path) -> Self {
+        state_path(account, root)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, account: &Account, root: &Path) -> Result<(), String> {
+        let path = state_path(account, root).ok_or("No folder for sync state")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Error saving sync state: {}", e))?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Error saving sync state: {}", e))
+    }
+}
+
+// Note files below the folder by relative path, with hashes of their bytes
+fn local_files(root: &Path) -> BTreeMap<String, u64> {
+    fn walk(dir: &Path, prefix: &str, files: &mut BTreeMap<String, u64>) {
+        let Ok(read) = fs::read_dir(dir) else {
+            return;
+        };
+        for item in read.flatten() {
+            let name = item.file_name().to_string_lossy().into_owned();
+            let path = item.path();
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, &format!("{}{}/", prefix, name), files);
+            } else if notebook::is_note(&path) {
+                if let Ok(bytes) = fs::read(&path) {
+                    files.insert(format!("{}{}", prefix, name), history::fnv1a(&bytes));
+                }
+            }
+        }
+    }
+    let mut files = BTreeMap::new();
+    walk(root, "", &mut files);
+    files
+}
+
+fn is_remote_note(path: &str) -> bool {
+    !path.split('/').any(|part| part.starts_with('.')) && notebook::is_note(Path::new(path))
+}
+
+// Compare both sides with the last sync: a side changed if its hash or tag
+// differs, and a deletion only spreads if the other side is unchanged
+fn plan(
+    state: &BTreeMap<String, Synced>,
+    local: &BTreeMap<String, u64>,
+    remote: &BTreeMap<String, String>,
+) -> Vec<(String, Action)> {
+    let paths: BTreeSet<&String> = state
+        .keys()
+        .chain(local.keys())
+        .chain(remote.keys())
+        .collect();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let base = state.get(path);
+            let action = match (local.get(path), remote.get(path), base) {
+                (Some(hash), Some(etag), Some(base)) => {
+                    match (*hash != base.hash, *etag != base.etag) {
+                        (false, false) => return None,
+                        (true, false) => Action::Upload,
+                        (false, true) => Action::Download,
+                        (true, true) => Action::Conflict,
+                    }
+                }
+                (Some(_), Some(_), None) => Action::Conflict,
+                (Some(hash), None, Some(base)) if *hash == base.hash => Action::DeleteLocal,
+                (Some(_), None, _) => Action::Upload,
+                (None, Some(etag), Some(base)) if *etag == base.etag => Action::DeleteRemote,
+                (None, Some(_), _) => Action::Download,
+                (None, None, _) => Action::Forget,
+            };
+            Some((path.clone(), action))
+        })
+        .collect()
+}
+
+fn local_path(root: &Path, path: &str) -> PathBuf {
+    path.split('/')
+        .fold(root.to_path_buf(), |dir, part| dir.join(part))
+}
+
+// The server path of a file in the notes folder
+fn relative_path(root: &Path, file: &Path) -> Option<String> {
+    let parts: Vec<String> = file
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+fn write_local(file: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating folder: {}", e))?;
+    }
+    fs::write(file, bytes).map_err(|e| format!("Error saving {}: {}", file.display(), e))
+}
+
+// A note's text, without formatting, for previews and merging
+fn note_document(bytes: &[u8]) -> Document {
+    let mut doc = Document::new(0);
+    let (content, encoding) = encoding::decode(bytes);
+    doc.encoding = encoding;
+    if rtxt::read(&mut doc, &content).is_err() {
+        doc.text_content = content;
+    }
+    doc
+}
+
+pub fn run(account: &Account, root: &Path) -> Outcome {
+    let mut outcome = Outcome::default();
+    let remote = match account.list() {
+        Ok(files) => files
+            .into_iter()
+            .filter(|file| is_remote_note(&file.path))
+            .map(|file| (file.path, file.etag))
+            .collect(),
+        Err(e) => {
+            outcome.errors.push(e);
+            return outcome;
+        }
+    };
+    let local = local_files(root);
+    let mut state = SyncState::load(account, root);
+
+    for (path, action) in plan(&state.files, &local, &remote) {
+        let file = local_path(root, &path);
+        let result = match action {
+            Action::Upload => account.put(&path, &file).map(|etag| {
+                let hash = local[&path];
+                state.files.insert(path.clone(), Synced { hash, etag });
+            }),
+            Action::Download => account.get(&path).and_then(|bytes| {
+                write_local(&file, &bytes)?;
+                let (hash, etag) = (history::fnv1a(&bytes), remote[&path].clone());
+                state.files.insert(path.clone(), Synced { hash, etag });
+                outcome.changed.push(file.clone());
+                Ok(())
+            }),
+            Action::DeleteLocal => fs::remove_file(&file)
+                .map_err(|e| format!("Error deleting {}: {}", path, e))
+                .map(|()| {
+                    state.files.remove(&path);
+                    outcome.changed.push(file.clone());
+                }),
+            Action::DeleteRemote => account.delete(&path).map(|()| {
+                state.files.remove(&path);
+            }),
+            Action::Forget => {
+                state.files.remove(&path);
+                continue;
+            }
+            Action::Conflict => {
+                let result = account.get(&path).and_then(|theirs_bytes| {
+                    let mine_bytes = fs::read(&file).map_err(|e| e.to_string())?;
+                    let (hash, etag) = (history::fnv1a(&mine_bytes), remote[&path].clone());
+                    if history::fnv1a(&theirs_bytes) == hash {
+                        // Both sides made the same change
+                        state.files.insert(path.clone(), Synced { hash, etag });
+                    } else {
+                        outcome.conflicts.push(Conflict {
+                            path: path.clone(),
+                            mine: note_document(&mine_bytes).plain_text(),
+                            theirs: note_document(&theirs_bytes).plain_text(),
+                            theirs_bytes,
+                            etag,
+                        });
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    outcome.errors.push(format!("{}: {}", path, e));
+                }
+                continue;
+            }
+        };
+        match result {
+            Ok(()) => outcome.transferred += 1,
+            Err(e) => outcome.errors.push(e),
+        }
+    }
+    if let Err(e) = state.save(account, root) {
+        outcome.errors.push(e);
+    }
+    outcome
+}
+
+// A note a sync downloaded while it was open with unsaved edits: those edits
+// are `mine`, and the file now holds theirs. Keeping mine or merging needs the
+// edits saved first
+pub fn unsaved_conflict(
+    account: &Account,
+    root: &Path,
+    file: &Path,
+    mine: String,
+) -> Option<Conflict> {
+    let path = relative_path(root, file)?;
+    let etag = SyncState::load(account, root)
+        .files
+        .get(&path)?
+        .etag
+        .clone();
+    let theirs_bytes = fs::read(file).ok()?;
+    Some(Conflict {
+        path,
+        mine,
+        theirs: note_document(&theirs_bytes).plain_text(),
+        theirs_bytes,
+        etag,
+    })
+}
+
+// Settle a conflict, bringing both sides to the chosen version
+pub fn resolve(
+    account: &Account,
+    root: &Path,
+    conflict: Conflict,
+    resolution: Resolution,
+) -> Outcome {
+    let mut outcome = Outcome::default();
+    let file = local_path(root, &conflict.path);
+    let result = (|| {
+        let synced = match resolution {
+            Resolution::KeepMine => {
+                let bytes = fs::read(&file).map_err(|e| e.to_string())?;
+                let etag = account.put(&conflict.path, &file)?;
+                Synced {
+                    hash: history::fnv1a(&bytes),
+                    etag,
+                }
+            }
+            Resolution::KeepTheirs => {
+                write_local(&file, &conflict.theirs_bytes)?;
+                outcome.changed.push(file.clone());
+                Synced {
+                    hash: history::fnv1a(&conflict.theirs_bytes),
+                    etag: conflict.etag.clone(),
+                }
+            }
+            Resolution::Merge => {
+                let bytes = fs::read(&file).map_err(|e| e.to_string())?;
+                let mut merged = note_document(&bytes);
+                // The local file may have changed since the sync
+                merged.text_content = merge_text(&merged.plain_text(), &conflict.theirs);
+                merged.styled_ranges.clear();
+                merged.tables.clear();
+                merged.sketches.clear();
+                merged.save_with_formatting(&file)?;
+                outcome.changed.push(file.clone());
+                let etag = account.put(&conflict.path, &file)?;
+                let bytes = fs::read(&file).map_err(|e| e.to_string())?;
+                Synced {
+                    hash: history::fnv1a(&bytes),
+                    etag,
+                }
+            }
+        };
+        let mut state = SyncState::load(account, root);
+        state.files.insert(conflict.path.clone(), synced);
+        state.save(account, root)
+    })();
+    match result {
+        Ok(()) => outcome.transferred += 1,
+        Err(e) => outcome.errors.push(format!("{}: {}", conflict.path, e)),
+    }
+    outcome
+}
+
+// Lines both versions share are kept once; every differing run shows mine
+// then theirs between conflict markers, to be edited by hand
+pub fn merge_text(mine: &str, theirs: &str) -> String {
+    let (mine, theirs) = (diff::lines(mine), diff::lines(theirs));
+    let mut merged = Vec::new();
+    let mut next = 0;
+    for hunk in diff::diff(&mine, &theirs) {
+        merged.extend_from_slice(&mine[next..hunk.old.start]);
+        merged.push("<<<<<<< mine");
+        merged.extend_from_slice(&mine[hunk.old.clone()]);
+        merged.push("=======");
+        merged.extend_from_slice(&theirs[hunk.new.clone()]);
+        merged.push(">>>>>>> theirs");
+        next = hunk.old.end;
+    }
+    merged.extend_from_slice(&mine[next..]);
+    merged.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced(hash: u64, etag: &str) -> Synced {
+        Synced {
+            hash,
+            etag: etag.to_string(),
+        }
+    }
+
+    #[test]
+    fn plan_compares_both_sides_with_last_sync() {
+        let state = BTreeMap::from([
+            ("same.txt".to_string(), synced(1, "a")),
+            ("mine.txt".to_string(), synced(1, "a")),
+            ("theirs.txt".to_string(), synced(1, "a")),
+            ("both.txt".to_string(), synced(1, "a")),
+            ("deleted here.txt".to_string(), synced(1, "a")),
+            ("deleted there.txt".to_string(), synced(1, "a")),
+            ("edited, deleted there.txt".to_string(), synced(1, "a")),
+            ("gone.txt".to_string(), synced(1, "a")),
+        ]);
+        let local = BTreeMap::from([
+            ("same.txt".to_string(), 1),
+            ("mine.txt".to_string(), 2),
+            ("theirs.txt".to_string(), 1),
+            ("both.txt".to_string(), 2),
+            ("deleted there.txt".to_string(), 1),
+            ("edited, deleted there.txt".to_string(), 2),
+            ("new here.txt".to_string(), 5),
+            ("new on both.txt".to_string(), 5),
+        ]);
+        let remote = BTreeMap::from([
+            ("same.txt".to_string(), "a".to_string()),
+            ("mine.txt".to_string(), "a".to_string()),
+            ("theirs.txt".to_string(), "b".to_string()),
+            ("both.txt".to_string(), "b".to_string()),
+            ("deleted here.txt".to_string(), "a".to_string()),
+            ("new there.txt".to_string(), "c".to_string()),
+            ("new on both.txt".to_string(), "c".to_string()),
+        ]);
+        let actions: BTreeMap<String, Action> = plan(&state, &local, &remote).into_iter().collect();
+        let expected = BTreeMap::from([
+            ("mine.txt".to_string(), Action::Upload),
+            ("theirs.txt".to_string(), Action::Download),
+            ("both.txt".to_string(), Action::Conflict),
+            ("deleted here.txt".to_string(), Action::DeleteRemote),
+            ("deleted there.txt".to_string(), Action::DeleteLocal),
+            ("edited, deleted there.txt".to_string(), Action::Upload),
+            ("gone.txt".to_string(), Action::Forget),
+            ("new here.txt".to_string(), Action::Upload),
+            ("new there.txt".to_string(), Action::Download),
+            ("new on both.txt".to_string(), Action::Conflict),
+        ]);
+        assert_eq!(actions, expected);
+    }
+
+    #[test]
+    fn server_paths_of_local_files() {
+        let root = Path::new("notes");
+        let file = local_path(root, "work/plan.rtxt");
+        assert_eq!(
+            relative_path(root, &file).as_deref(),
+            Some("work/plan.rtxt")
+        );
+        assert_eq!(relative_path(root, Path::new("elsewhere/plan.rtxt")), None);
+        assert_eq!(relative_path(root, root), None);
+    }
+
+    #[test]
+    fn merge_marks_differing_lines() {
+        assert_eq!(merge_text("a\nb\nc", "a\nb\nc"), "a\nb\nc");
+        assert_eq!(
+            merge_text("a\nmine\nc", "a\ntheirs\nc\nd"),
+            "a\n<<<<<<< mine\nmine\n=======\ntheirs\n>>>>>>> theirs\nc\n\
+             <<<<<<< mine\n=======\nd\n>>>>>>> theirs"
+        );
+    }
+
+    #[test]
+    fn only_visible_notes_are_synced() {
+        assert!(is_remote_note("Work/plan.rtxt"));
+        assert!(is_remote_note("todo.txt"));
+        assert!(!is_remote_note("photo.jpg"));
+        assert!(!is_remote_note(".hidden/plan.txt"));
+        assert_eq!(
+            local_path(Path::new("notes"), "a/b.txt"),
+            Path::new("notes").join("a").join("b.txt")
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use egui::{Color32, TextEdit};
+use serde::{Deserialize, Serialize};
+
+// Tables are anchored in the text by a private-use character, one per table,
+// whose line is laid out tall enough for the grid drawn over it
+const PLACEHOLDER_BASE: u32 = 0xE000;
+const MAX_TABLES: u32 = 0x1900; // Size of the Private Use Area block
+
+const ROW_HEIGHT: f32 = 24.0;
+const COLUMN_WIDTH: f32 = 120.0;
+const CONTROLS_HEIGHT: f32 = 28.0;
+const MIN_WIDTH: f32 = 240.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Table {
+    pub id: u32,
+    pub cells: Vec<Vec<String>>, // Rows of cells, all rows the same length
+}
+
+impl Table {
+    pub fn new(id: u32, rows: usize, columns: usize) -> Self {
+        Self {
+            id,
+            cells: vec![vec![String::new(); columns.max(1)]; rows.max(1)],
+        }
+    }
+
+    pub fn next_id(tables: &[Table]) -> Option<u32> {
+        let id = tables.iter().map(|t| t.id + 1).max().unwrap_or(0);
+        (id < MAX_TABLES).then_some(id)
+    }
+
+    pub fn placeholder(&self) -> char {
+        char::from_u32(PLACEHOLDER_BASE + self.id).unwrap_or(char::REPLACEMENT_CHARACTER)
+    }
+
+    pub fn is_placeholder(c: char) -> bool {
+        (PLACEHOLDER_BASE..PLACEHOLDER_BASE + MAX_TABLES).contains(&(c as u32))
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    // Space reserved in the text layout for the grid and its buttons
+    pub fn height(&self) -> f32 {
+        self.rows() as f32 * ROW_HEIGHT + CONTROLS_HEIGHT
+    }
+
+    pub fn width(&self) -> f32 {
+        // Wide enough for the row and column buttons
+        (self.columns() as f32 * COLUMN_WIDTH).max(MIN_WIDTH)
+    }
+
+    // Pipe table used when saving as plain text
+    pub fn to_markdown(&self) -> String {
+        let mut lines = Vec::new();
+        for (index, row) in self.cells.iter().enumerate() {
+            lines.push(format!("| {} |", row.join(" | ")));
+            if index == 0 {
+                lines.push(format!("|{}", " --- |".repeat(self.columns())));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Legacy .rtxt line: TABLE:id:rows:columns:cell<TAB>cell...
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.strip_prefix("TABLE:")?.splitn(4, ':');
+        let id: u32 = parts.next()?.parse().ok().filter(|id| *id < MAX_TABLES)?;
+        let rows: usize = parts.next()?.parse().ok()?;
+        let columns: usize = parts.next()?.parse().ok()?;
+        let mut cells = parts.next()?.split('\t').map(unescape);
+
+        let mut table = Table::new(id, rows, columns);
+        for cell in table.cells.iter_mut().flatten() {
+            *cell = cells.next().unwrap_or_default();
+        }
+        Some(table)
+    }
+
+    // Draw the editable grid; returns true if anything changed
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        egui::Frame::none()
+            .fill(ui.visuals().extreme_bg_color)
+            .stroke(egui::Stroke::new(1.0, Color32::GRAY))
+            .show(ui, |ui| {
+                egui::Grid::new(("table_grid", self.id))
+                    .spacing([2.0, 2.0])
+                    .show(ui, |ui| {
+                        for row in &mut self.cells {
+                            for cell in row {
+                                changed |= ui
+                                    .add(
+                                        TextEdit::singleline(cell)
+                                            .desired_width(COLUMN_WIDTH - 6.0),
+                                    )
+                                    .changed();
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.small_button("+ Row").clicked() {
+                        self.cells.push(vec![String::new(); self.columns()]);
+                        changed = true;
+                    }
+                    if ui.small_button("+ Col").clicked() {
+                        self.cells
+                            .iter_mut()
+                            .for_each(|row| row.push(String::new()));
+                        changed = true;
+                    }
+                    if ui
+                        .add_enabled(self.rows() > 1, egui::Button::new("− Row").small())
+                        .clicked()
+                    {
+                        self.cells.pop();
+                        changed = true;
+                    }
+                    if ui
+                        .add_enabled(self.columns() > 1, egui::Button::new("− Col").small())
+                        .clicked()
+                    {
+                        self.cells.iter_mut().for_each(|row| {
+                            row.pop();
+                        });
+                        changed = true;
+                    }
+                });
+            });
+        changed
+    }
+}
+
+fn unescape(cell: &str) -> String {
+    let mut result = String::new();
+    let mut chars = cell.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
@@ -0,0 +1,309 @@
+// Conversions between character indices, used by the editor cursor and styled
+// ranges, and the byte offsets needed to slice a `str`
+use std::ops::Range;
+
+pub fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+// Byte offset of the character index, clamped to the end of the text
+pub fn char_to_byte(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+// Character index at the byte offset, rounded up to a character boundary
+pub fn byte_to_char(text: &str, offset: usize) -> usize {
+    text.char_indices()
+        .take_while(|(index, _)| *index < offset)
+        .count()
+}
+
+pub fn char_range_to_bytes(text: &str, range: Range<usize>) -> Range<usize> {
+    let start = char_to_byte(text, range.start);
+    let end = char_to_byte(text, range.end.max(range.start));
+    start..end
+}
+
+pub fn char_slice(text: &str, range: Range<usize>) -> &str {
+    &text[char_range_to_bytes(text, range)]
+}
+
+// Byte offset of every character boundary, including the end of the text, for
+// converting many character indices at once
+pub fn char_boundaries(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(index, _)| index)
+        .chain([text.len()])
+        .collect()
+}
+
+// Character index of the first match at or after the character index `from`
+pub fn find_from(text: &str, pattern: &str, from: usize) -> Option<usize> {
+    let start = char_to_byte(text, from);
+    text[start..]
+        .find(pattern)
+        .map(|pos| from + char_count(&text[start..start + pos]))
+}
+
+// Character index of the last match that ends at or before the character index `to`
+pub fn rfind_before(text: &str, pattern: &str, to: usize) -> Option<usize> {
+    let end = char_to_byte(text, to);
+    text[..end]
+        .rfind(pattern)
+        .map(|pos| byte_to_char(text, pos))
+}
+
+// The part of `old` that differs from `new`, as character indices: the change
+// starts at `start` and ends at `old_end` in the old text and `new_end` in the
+// new one
+pub fn changed_range(old: &str, new: &str) -> (usize, usize, usize) {
+    let prefix = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_len, new_len) = (char_count(old), char_count(new));
+    // The common suffix may not overlap the common prefix
+    let suffix = old
+        .chars()
+        .rev()
+        .zip(new.chars().rev())
+        .take(old_len.min(new_len) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix, old_len - suffix, new_len - suffix)
+}
+
+// Counts shown in the status bar and the word count window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub chars: usize,
+    pub words: usize,
+    pub lines: usize,
+}
+
+pub fn stats(text: &str) -> Stats {
+    Stats {
+        chars: char_count(text),
+        words: text.split_whitespace().count(),
+        lines: text.lines().count(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Case {
+    Upper,
+    Lower,
+    Title,    // First letter of each word upper case
+    Sentence, // First letter of each sentence upper case
+}
+
+impl Case {
+    pub const ALL: [Case; 4] = [Case::Upper, Case::Lower, Case::Title, Case::Sentence];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Case::Upper => "UPPERCASE",
+            Case::Lower => "lowercase",
+            Case::Title => "Title Case",
+            Case::Sentence => "Sentence case",
+        }
+    }
+}
+
+// The text in another case, as one piece per character of the input so
+// that styles can follow characters that grow, like "ß" into "SS"
+pub fn change_case(text: &str, case: Case) -> Vec<String> {
+    let mut capitalize = true; // The next letter starts a word or sentence
+    let mut previous = ' ';
+    text.chars()
+        .map(|c| {
+            let upper = match case {
+                Case::Upper => true,
+                Case::Lower => false,
+                Case::Title => !(previous.is_alphanumeric() || previous == '\''),
+                Case::Sentence => {
+                    if ".!?".contains(previous) && c.is_whitespace() {
+                        capitalize = true;
+                    }
+                    capitalize && c.is_alphanumeric()
+                }
+            };
+            if c.is_alphanumeric() {
+                capitalize = false;
+            }
+            previous = c;
+            if upper {
+                c.to_uppercase().collect()
+            } else {
+                c.to_lowercase().collect()
+            }
+        })
+        .collect()
+}
+
+// Flesch scores and averages for English prose
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Readability {
+    pub sentences: usize,
+    pub reading_ease: f32, // 0-100, higher is easier; plain English is 60-70
+    pub grade: f32,        // Flesch–Kincaid US school grade
+    pub words_per_sentence: f32,
+    pub letters_per_word: f32,
+}
+
+// None when the text has no words
+pub fn readability(text: &str) -> Option<Readability> {
+    let mut words = 0;
+    let mut letters = 0;
+    let mut syllables = 0;
+    let mut sentences = 0;
+    let mut open_sentence = false;
+    for token in text.split_whitespace() {
+        let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+        if !word.is_empty() {
+            words += 1;
+            letters += word.chars().filter(|c| c.is_alphanumeric()).count();
+            syllables += syllable_count(word);
+            open_sentence = true;
+        }
+        // A sentence ends at a word ending in . ! or ?, before any quotes
+        // or brackets that close it
+        let end = token.trim_end_matches(['"', '\'', ')', ']', '”', '’']);
+        if open_sentence && end.ends_with(['.', '!', '?']) {
+            sentences += 1;
+            open_sentence = false;
+        }
+    }
+    if words == 0 {
+        return None;
+    }
+    // Trailing words without a full stop are a sentence too
+    sentences += usize::from(open_sentence);
+    let words_per_sentence = words as f32 / sentences as f32;
+    let syllables_per_word = syllables as f32 / words as f32;
+    Some(Readability {
+        sentences,
+        reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+        words_per_sentence,
+        letters_per_word: letters as f32 / words as f32,
+    })
+}
+
+// Estimate from groups of vowels, not counting a silent final "e"
+fn syllable_count(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let is_vowel = |c: char| "aeiouyàáâäèéêëìíîïòóôöùúûü".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if count > 1 && word.ends_with('e') && !word.ends_with("le") && !word.ends_with("ee") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_with_multibyte_text() {
+        let text = "aé😀b";
+        assert_eq!(char_count(text), 4);
+        assert_eq!(char_to_byte(text, 0), 0);
+        assert_eq!(char_to_byte(text, 2), 3);
+        assert_eq!(char_to_byte(text, 3), 7);
+        assert_eq!(char_to_byte(text, 10), text.len());
+        assert_eq!(byte_to_char(text, 3), 2);
+        assert_eq!(byte_to_char(text, 4), 3); // Inside the emoji, rounded up
+        assert_eq!(byte_to_char(text, text.len()), 4);
+        assert_eq!(char_slice(text, 1..3), "é😀");
+        assert_eq!(char_boundaries(text), vec![0, 1, 3, 7, 8]);
+    }
+
+    #[test]
+    fn changed_range_of_edits() {
+        assert_eq!(changed_range("héllo", "héllo"), (5, 5, 5));
+        assert_eq!(changed_range("héllo", "hé😀llo"), (2, 2, 3));
+        assert_eq!(changed_range("hé😀llo", "héllo"), (2, 3, 2));
+        assert_eq!(changed_range("aaa", "aaaa"), (3, 3, 4));
+        assert_eq!(changed_range("ab😀cd", "abXYcd"), (2, 3, 4));
+        assert_eq!(changed_range("", "ü"), (0, 0, 1));
+    }
+
+    #[test]
+    fn stats_count_characters_words_and_lines() {
+        let stats = stats("Grüße aus\n  Köln 😀\n");
+        assert_eq!(stats.chars, 19);
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.lines, 2);
+        assert_eq!(super::stats("").lines, 0);
+    }
+
+    #[test]
+    fn case_changes() {
+        let change = |text, case| change_case(text, case).concat();
+        let text = "the quick brown fox. it's GREAT!  right? yes";
+        assert_eq!(
+            change(text, Case::Upper),
+            "THE QUICK BROWN FOX. IT'S GREAT!  RIGHT? YES"
+        );
+        assert_eq!(
+            change(text, Case::Title),
+            "The Quick Brown Fox. It's Great!  Right? Yes"
+        );
+        assert_eq!(
+            change(text, Case::Sentence),
+            "The quick brown fox. It's great!  Right? Yes"
+        );
+        assert_eq!(change("ÉTÉ", Case::Lower), "été");
+        // One piece per character, even when a character grows
+        let pieces = change_case("straße", Case::Upper);
+        assert_eq!(pieces.len(), 6);
+        assert_eq!(pieces[4], "SS");
+    }
+
+    #[test]
+    fn readability_of_simple_and_harder_text() {
+        assert_eq!(syllable_count("table"), 2);
+        assert_eq!(syllable_count("Make"), 1);
+        assert_eq!(syllable_count("beautiful"), 3);
+        assert_eq!(syllable_count("rhythm"), 1);
+        assert_eq!(syllable_count("the"), 1);
+
+        let simple = readability("The cat sat on the mat. It was fun!").unwrap();
+        assert_eq!(simple.sentences, 2);
+        assert_eq!(simple.words_per_sentence, 4.5);
+        assert!(simple.reading_ease > 100.0 && simple.grade < 1.0);
+
+        let harder = readability(
+            "Institutional considerations necessitate comprehensive organizational \
+             restructuring (\"immediately\")",
+        )
+        .unwrap();
+        assert_eq!(harder.sentences, 1);
+        assert!(harder.reading_ease < 0.0 && harder.grade > 20.0);
+        assert_eq!(readability(" -- \n"), None);
+    }
+
+    #[test]
+    fn searching_returns_char_indices() {
+        let text = "😀 café, 😀 café";
+        assert_eq!(find_from(text, "café", 0), Some(2));
+        assert_eq!(find_from(text, "café", 3), Some(10));
+        assert_eq!(find_from(text, "café", 11), None);
+        assert_eq!(rfind_before(text, "café", char_count(text)), Some(10));
+        assert_eq!(rfind_before(text, "café", 13), Some(2));
+        assert_eq!(rfind_before(text, "😀", 1), Some(0));
+    }
+}
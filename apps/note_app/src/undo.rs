@@ -0,0 +1,221 @@
+// Undo history that keeps each change as the text it replaced and the text it
+// inserted, rather than a copy of the whole document per step
+use crate::document::StyledRange;
+use crate::text;
+
+// Text and styles kept for undo; the oldest steps are dropped beyond this
+const BUDGET_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+struct Step {
+    start: usize,     // Character index where the change starts
+    removed: String,  // Text the change replaced
+    inserted: String, // Text it put in its place
+    styles_before: Vec<StyledRange>,
+    styles_after: Vec<StyledRange>,
+    typing: bool, // Typed in the editor, so the next keystroke may join it
+}
+
+impl Step {
+    fn size(&self) -> usize {
+        let styles = self.styles_before.len() + self.styles_after.len();
+        self.removed.len() + self.inserted.len() + styles * std::mem::size_of::<StyledRange>()
+    }
+
+    // Fold the next keystroke into this step when it continues the same word
+    // or the same run of deletions
+    fn merge(&mut self, next: &Step) -> bool {
+        if !self.typing || !next.typing {
+            return false;
+        }
+        let inserting = self.removed.is_empty() && next.removed.is_empty();
+        let deleting = self.inserted.is_empty() && next.inserted.is_empty();
+        if inserting && next.start == self.start + text::char_count(&self.inserted) {
+            // A word and the spaces after it form one step
+            let ends_word = self.inserted.ends_with(char::is_whitespace)
+                && !next.inserted.starts_with(char::is_whitespace);
+            if ends_word {
+                return false;
+            }
+            self.inserted.push_str(&next.inserted);
+        } else if deleting && next.start + text::char_count(&next.removed) == self.start {
+            // Backspace
+            self.removed.insert_str(0, &next.removed);
+            self.start = next.start;
+        } else if deleting && next.start == self.start {
+            // Delete
+            self.removed.push_str(&next.removed);
+        } else {
+            return false;
+        }
+        self.styles_after = next.styles_after.clone();
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct UndoHistory {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    // State before a programmatic change that is still being made; recorded
+    // as a step at the next history call
+    checkpoint: Option<(String, Vec<StyledRange>)>,
+    size: usize,
+}
+
+impl UndoHistory {
+    // Remember the state before a change made by the app
+    pub fn checkpoint(&mut self, text: &str, styles: &[StyledRange]) {
+        self.flush(text, styles);
+        self.checkpoint = Some((text.to_string(), styles.to_vec()));
+    }
+
+    // Record a change typed in the editor
+    pub fn record_typing(
+        &mut self,
+        old_text: &str,
+        old_styles: &[StyledRange],
+        text: &str,
+        styles: &[StyledRange],
+    ) {
+        self.flush(old_text, old_styles);
+        self.record(old_text, old_styles, text, styles, true);
+    }
+
+    // Revert the last step, returning where to put the cursor
+    pub fn undo(&mut self, text: &mut String, styles: &mut Vec<StyledRange>) -> Option<usize> {
+        self.flush(text, styles);
+        let step = self.undo.pop()?;
+        self.size -= step.size();
+        let inserted = step.start..step.start + text::char_count(&step.inserted);
+        text.replace_range(text::char_range_to_bytes(text, inserted), &step.removed);
+        *styles = step.styles_before.clone();
+        let cursor = step.start + text::char_count(&step.removed);
+        self.redo.push(step);
+        Some(cursor)
+    }
+
+    pub fn redo(&mut self, text: &mut String, styles: &mut Vec<StyledRange>) -> Option<usize> {
+        self.flush(text, styles);
+        let mut step = self.redo.pop()?;
+        let removed = step.start..step.start + text::char_count(&step.removed);
+        text.replace_range(text::char_range_to_bytes(text, removed), &step.inserted);
+        *styles = step.styles_after.clone();
+        let cursor = step.start + text::char_count(&step.inserted);
+        // A redone step starts a new typing group
+        step.typing = false;
+        self.push(step);
+        Some(cursor)
+    }
+
+    // Turn a pending checkpoint into a step, given the state it led to
+    fn flush(&mut self, text: &str, styles: &[StyledRange]) {
+        if let Some((old_text, old_styles)) = self.checkpoint.take() {
+            self.record(&old_text, &old_styles, text, styles, false);
+        }
+    }
+
+    fn record(
+        &mut self,
+        old_text: &str,
+        old_styles: &[StyledRange],
+        text: &str,
+        styles: &[StyledRange],
+        typing: bool,
+    ) {
+        if old_text == text && old_styles == styles {
+            return;
+        }
+        let (start, old_end, new_end) = text::changed_range(old_text, text);
+        let step = Step {
+            start,
+            removed: text::char_slice(old_text, start..old_end).to_string(),
+            inserted: text::char_slice(text, start..new_end).to_string(),
+            styles_before: old_styles.to_vec(),
+            styles_after: styles.to_vec(),
+            typing,
+        };
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut() {
+            let last_size = last.size();
+            if last.merge(&step) {
+                self.size = self.size - last_size + last.size();
+                return;
+            }
+        }
+        self.push(step);
+    }
+
+    fn push(&mut self, step: Step) {
+        self.size += step.size();
+        self.undo.push(step);
+        // Drop the oldest steps, but always keep the newest one
+        let mut dropped = 0;
+        while self.size > BUDGET_BYTES && dropped + 1 < self.undo.len() {
+            self.size -= self.undo[dropped].size();
+            dropped += 1;
+        }
+        self.undo.drain(..dropped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Type `typed` one character at a time at the end of `text`
+    fn type_text(history: &mut UndoHistory, text: &mut String, typed: &str) {
+        for c in typed.chars() {
+            let old = text.clone();
+            text.push(c);
+            history.record_typing(&old, &[], text, &[]);
+        }
+    }
+
+    #[test]
+    fn typing_is_grouped_by_word() {
+        let mut history = UndoHistory::default();
+        let mut text = String::new();
+        let mut styles = Vec::new();
+        type_text(&mut history, &mut text, "héllo wörld  again");
+        assert_eq!(history.undo.len(), 3);
+
+        assert_eq!(history.undo(&mut text, &mut styles), Some(13));
+        assert_eq!(text, "héllo wörld  ");
+        history.undo(&mut text, &mut styles);
+        assert_eq!(text, "héllo ");
+        assert_eq!(history.redo(&mut text, &mut styles), Some(13));
+        assert_eq!(text, "héllo wörld  ");
+
+        // Backspacing is one step too
+        for _ in 0..3 {
+            let old = text.clone();
+            text.pop();
+            history.record_typing(&old, &[], &text, &[]);
+        }
+        assert_eq!(text, "héllo wörl");
+        history.undo(&mut text, &mut styles);
+        assert_eq!(text, "héllo wörld  ");
+    }
+
+    #[test]
+    fn checkpoints_record_changes_made_by_the_app() {
+        let mut history = UndoHistory::default();
+        let mut text = "one two".to_string();
+        let mut styles = Vec::new();
+
+        history.checkpoint(&text, &styles);
+        text = "one 2 two".to_string();
+        // A checkpoint without a change records nothing
+        history.checkpoint(&text, &styles);
+        history.checkpoint(&text, &styles);
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.undo[0].inserted, "2 ");
+
+        assert_eq!(history.undo(&mut text, &mut styles), Some(4));
+        assert_eq!(text, "one two");
+        assert!(history.undo(&mut text, &mut styles).is_none());
+        history.redo(&mut text, &mut styles);
+        assert_eq!(text, "one 2 two");
+    }
+}
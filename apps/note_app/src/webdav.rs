@@ -0,0 +1,364 @@
+// Minimal WebDAV client for syncing the notes folder. Requests are made by
+// running curl, which takes care of HTTPS and authentication
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:"><d:prop><d:getetag/><d:resourcetype/></d:prop></d:propfind>"#;
+
+// A folder on a WebDAV server, such as a Nextcloud folder
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Account {
+    pub url: String,
+    pub user: String,
+    pub password: String,
+}
+
+// A file below the account's folder: its path relative to the folder, with
+// "/" separators, and its entity tag, which changes with its content
+#[derive(Debug, PartialEq)]
+pub struct RemoteFile {
+    pub path: String,
+    pub etag: String,
+}
+
+struct Response {
+    status: u16,
+    headers: String,
+    body: Vec<u8>,
+}
+
+impl Account {
+    pub fn is_set(&self) -> bool {
+        !self.url.trim().is_empty()
+    }
+
+    fn folder_url(&self) -> String {
+        format!("{}/", self.url.trim().trim_end_matches('/'))
+    }
+
+    fn file_url(&self, path: &str) -> String {
+        format!("{}{}", self.folder_url(), percent_encode(path))
+    }
+
+    // Run curl with options given as a config on its standard input, so the
+    // password does not show in the process list
+    fn request(&self, options: &[(&str, &str)]) -> Result<Response, String> {
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--include", "--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not run curl: {}", e))?;
+        let mut config = String::new();
+        if !self.user.is_empty() {
+            let credentials = format!("{}:{}", self.user, self.password);
+            config.push_str(&format!("user = \"{}\"\n", config_escape(&credentials)));
+        }
+        for (option, value) in options {
+            config.push_str(&format!("{} = \"{}\"\n", option, config_escape(value)));
+        }
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(config.as_bytes())
+                .map_err(|e| format!("Could not run curl: {}", e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Could not run curl: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        parse_response(&output.stdout).ok_or_else(|| "Unreadable reply from the server".to_string())
+    }
+
+    fn check(response: Response, what: &str) -> Result<Response, String> {
+        match response.status {
+            200..=299 => Ok(response),
+            401 | 403 => Err("The server refused the user name or password".to_string()),
+            status => Err(format!("{} failed with HTTP status {}", what, status)),
+        }
+    }
+
+    // Every file below the folder, searching subfolders one level at a time
+    pub fn list(&self) -> Result<Vec<RemoteFile>, String> {
+        let base_path = percent_decode(url_path(&self.folder_url()));
+        let mut files = Vec::new();
+        let mut folders = vec![String::new()];
+        while let Some(folder) = folders.pop() {
+            let url = self.file_url(&folder);
+            let response = self.request(&[
+                ("url", &url),
+                ("request", "PROPFIND"),
+                ("header", "Depth: 1"),
+                ("header", "Content-Type: application/xml"),
+                ("data-binary", PROPFIND_BODY),
+            ])?;
+            let response = Self::check(response, "Listing the folder")?;
+            let xml = String::from_utf8_lossy(&response.body);
+            for entry in elements(&xml, "response") {
+                let Some(href) = elements(entry, "href")
+                    .first()
+                    .map(|href| decode_entities(href))
+                else {
+                    continue;
+                };
+                let href = percent_decode(url_path(&href));
+                let Some(path) = href.strip_prefix(&base_path) else {
+                    continue;
+                };
+                let is_folder = elements(entry, "resourcetype")
+                    .first()
+                    .is_some_and(|kind| kind.contains("collection"));
+                let path = path.trim_end_matches('/').to_string();
+                if path == folder.trim_end_matches('/') {
+                    continue; // The folder itself
+                }
+                if is_folder {
+                    folders.push(format!("{}/", path));
+                } else {
+                    let etag = elements(entry, "getetag")
+                        .first()
+                        .map_or(String::new(), |etag| normalize_etag(&decode_entities(etag)));
+                    files.push(RemoteFile { path, etag });
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    pub fn get(&self, path: &str) -> Result<Vec<u8>, String> {
+        let response = self.request(&[("url", &self.file_url(path))])?;
+        Ok(Self::check(response, &format!("Downloading {}", path))?.body)
+    }
+
+    // Upload a local file, creating the folders above it; returns the new
+    // entity tag
+    pub fn put(&self, path: &str, file: &Path) -> Result<String, String> {
+        let mut folder = String::new();
+        for part in path
+            .split('/')
+            .rev()
+            .skip(1)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+        {
+            folder = format!("{}{}/", folder, part);
+            // Fails harmlessly when the folder exists
+            self.request(&[("url", &self.file_url(&folder)), ("request", "MKCOL")])?;
+        }
+        let file = file.to_string_lossy();
+        let response = self.request(&[("url", &self.file_url(path)), ("upload-file", &file)])?;
+        let response = Self::check(response, &format!("Uploading {}", path))?;
+        match header(&response.headers, "etag") {
+            Some(etag) => Ok(normalize_etag(etag)),
+            // Not every server sends it back, so look it up
+            None => self
+                .list()?
+                .into_iter()
+                .find(|remote| remote.path == path)
+                .map(|remote| remote.etag)
+                .ok_or_else(|| format!("{} is missing after uploading it", path)),
+        }
+    }
+
+    pub fn delete(&self, path: &str) -> Result<(), String> {
+        let response = self.request(&[("url", &self.file_url(path)), ("request", "DELETE")])?;
+        Self::check(response, &format!("Deleting {}", path)).map(|_| ())
+    }
+}
+
+// A value for a quoted string in curl's config. Line breaks are escaped as
+// well, as a raw one would end the value and start another option; names from
+// the server can hold them once percent-decoded
+fn config_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x0b' => escaped.push_str("\\v"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Status, headers and body of the final reply, skipping "100 Continue"
+fn parse_response(output: &[u8]) -> Option<Response> {
+    let mut rest = output;
+    loop {
+        let end = rest.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 4..];
+        let status: u16 = headers.split_whitespace().nth(1)?.parse().ok()?;
+        if !(100..200).contains(&status) {
+            return Some(Response {
+                status,
+                headers,
+                body: rest.to_vec(),
+            });
+        }
+    }
+}
+
+fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+// Weak and strong tags for the same content compare equal
+fn normalize_etag(etag: &str) -> String {
+    etag.trim()
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .to_string()
+}
+
+// The path part of a URL, or the whole text if it is already a path
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => url,
+    }
+}
+
+// Contents of the XML elements with the given name, whatever their namespace
+// prefix; empty for self-closing elements
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..close];
+        let tag_name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        let local_name = tag_name.rsplit(':').next().unwrap_or_default();
+        rest = &rest[close + 1..];
+        if local_name != name || tag.starts_with('/') {
+            continue;
+        }
+        if tag.ends_with('/') {
+            found.push("");
+            continue;
+        }
+        let end_tag = format!("</{}>", tag_name);
+        if let Some(end) = rest.find(&end_tag) {
+            found.push(&rest[..end]);
+            rest = &rest[end + end_tag.len()..];
+        }
+    }
+    found
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// Escape everything but unreserved characters and "/" in a path
+fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multistatus_replies_are_read() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+ <d:response><d:href>/dav/Notes/</d:href><d:propstat><d:prop>
+  <d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat></d:response>
+ <d:response><d:href>/dav/Notes/Caf%C3%A9%20list.txt</d:href><d:propstat><d:prop>
+  <d:getetag>&quot;5f2a&quot;</d:getetag><d:resourcetype/></d:prop></d:propstat></d:response>
+</d:multistatus>"#;
+        let responses = elements(xml, "response");
+        assert_eq!(responses.len(), 2);
+        assert_eq!(elements(responses[0], "href"), vec!["/dav/Notes/"]);
+        assert!(elements(responses[0], "resourcetype")[0].contains("collection"));
+        assert_eq!(elements(responses[1], "resourcetype"), vec![""]);
+        let etag = decode_entities(elements(responses[1], "getetag")[0]);
+        assert_eq!(normalize_etag(&etag), "5f2a");
+        assert_eq!(
+            percent_decode(elements(responses[1], "href")[0]),
+            "/dav/Notes/Café list.txt"
+        );
+    }
+
+    #[test]
+    fn paths_and_replies() {
+        assert_eq!(percent_encode("a b/Café.txt"), "a%20b/Caf%C3%A9.txt");
+        assert_eq!(percent_decode("a%20b/Caf%C3%A9.txt"), "a b/Café.txt");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(url_path("https://cloud.example/dav/Notes/"), "/dav/Notes/");
+        assert_eq!(url_path("/dav/x"), "/dav/x");
+        assert_eq!(normalize_etag("W/\"abc\""), "abc");
+
+        let reply =
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 201 Created\r\nETag: \"e1\"\r\n\r\nbody";
+        let response = parse_response(reply).unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(header(&response.headers, "etag"), Some("\"e1\""));
+        assert_eq!(response.body, b"body");
+        assert!(parse_response(b"garbage").is_none());
+    }
+
+    #[test]
+    fn config_values_stay_on_one_line() {
+        assert_eq!(config_escape(r#"pa"ss\word"#), r#"pa\"ss\\word"#);
+        let name = percent_decode("/dav/a%0Aupload-file%20=%20%22/etc/passwd%22");
+        let escaped = config_escape(&name);
+        assert!(!escaped.contains(['\n', '\r']));
+        assert_eq!(escaped, r#"/dav/a\nupload-file = \"/etc/passwd\""#);
+        assert_eq!(config_escape("a\r\tb"), r"a\r\tb");
+    }
+}
@@ -0,0 +1,144 @@
+// Web addresses typed in the text, opened in the system browser
+use crate::text;
+use gui_common::launch;
+use std::ops::Range;
+use std::path::Path;
+
+const SCHEMES: [&str; 4] = ["https://", "http://", "mailto:", "www."];
+
+// Byte ranges of the addresses in the text. An address runs to the next space
+// or quote, without trailing punctuation or a closing bracket it did not open,
+// so "(see https://example.com/a_(b))." keeps only the inner brackets
+pub fn links(text: &str) -> Vec<Range<usize>> {
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some((start, scheme)) = SCHEMES
+        .iter()
+        .filter_map(|scheme| text[from..].find(scheme).map(|i| (from + i, scheme)))
+        .min_by_key(|(start, _)| *start)
+    {
+        // Not the middle of a word, like "xhttp://"
+        let in_word = text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '/' || c == '.');
+        let rest = &text[start..];
+        let mut end = start
+            + rest
+                .find(|c: char| c.is_whitespace() || "<>\"`".contains(c))
+                .unwrap_or(rest.len());
+        loop {
+            let address = &text[start..end];
+            let Some(last) = address.chars().next_back() else {
+                break;
+            };
+            let unopened =
+                |open: char| address.matches(open).count() < address.matches(last).count();
+            let trim = ".,;:!?'*".contains(last)
+                || (last == ')' && unopened('('))
+                || (last == ']' && unopened('['));
+            if !trim {
+                break;
+            }
+            end -= last.len_utf8();
+        }
+        if !in_word && end > start + scheme.len() {
+            links.push(start..end);
+            from = end;
+        } else {
+            from = start + scheme.len();
+        }
+    }
+    links
+}
+
+// The address as the browser needs it
+fn url(address: &str) -> String {
+    if address.starts_with("www.") {
+        format!("https://{}", address)
+    } else {
+        address.to_string()
+    }
+}
+
+// The address at a character index
+pub fn link_at(text: &str, char_index: usize) -> Option<String> {
+    let byte = text::char_range_to_bytes(text, char_index..char_index).start;
+    links(text)
+        .into_iter()
+        .find(|range| range.contains(&byte))
+        .map(|range| url(&text[range]))
+}
+
+// Every address touching a character range, in order and without repeats
+pub fn links_in(text: &str, chars: Range<usize>) -> Vec<String> {
+    let bytes = text::char_range_to_bytes(text, chars);
+    let mut urls: Vec<String> = Vec::new();
+    for range in links(text) {
+        if range.start < bytes.end && bytes.start < range.end {
+            let url = url(&text[range]);
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+// Only web and mail addresses are handed to the system, which would run
+// anything else it has a handler for
+fn openable(url: &str) -> bool {
+    ["https://", "http://", "mailto:"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+    })
+}
+
+pub fn open(url: &str) -> Result<(), String> {
+    if !openable(url) {
+        return Err(format!("Not a web address: {}", url));
+    }
+    launch::open_with_system(Path::new(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<&str> {
+        links(text).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn addresses_end_before_punctuation() {
+        assert_eq!(
+            found("See https://example.com/a_(b)). Or www.rust-lang.org, now."),
+            ["https://example.com/a_(b)", "www.rust-lang.org"]
+        );
+        assert_eq!(
+            found("[docs](https://docs.rs/egui) and <mailto:me@example.com>"),
+            ["https://docs.rs/egui", "mailto:me@example.com"]
+        );
+        assert!(found("xhttp://no https:// nor www.").is_empty());
+    }
+
+    #[test]
+    fn links_at_the_caret_and_in_a_selection() {
+        let text = "é www.a.org and http://b.org/x?y=1 and http://b.org/x?y=1";
+        assert_eq!(link_at(text, 4).as_deref(), Some("https://www.a.org"));
+        assert_eq!(link_at(text, 1), None);
+        assert_eq!(
+            links_in(text, 5..text::char_count(text)),
+            ["https://www.a.org", "http://b.org/x?y=1"]
+        );
+    }
+
+    #[test]
+    fn only_web_and_mail_addresses_are_opened() {
+        assert!(openable("HTTPS://example.com/?a=1&b=2"));
+        assert!(openable("mailto:me@example.com"));
+        assert!(!openable("file:///etc/passwd"));
+        assert!(!openable("javascript:alert(1)"));
+        assert!(!openable("C:\\Windows\\System32\\calc.exe"));
+    }
+}
@@ -0,0 +1,140 @@
+// Wiki-style [[Note Name]] links between the notes of the notes folder
+use crate::document::Document;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// A link in the text: byte range of the whole "[[...]]" and the note name
+#[derive(Debug, PartialEq)]
+pub struct WikiLink {
+    pub range: Range<usize>,
+    pub target: String,
+}
+
+// Every link in the text; a link stays on one line and the name has no brackets
+pub fn links(text: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(open) = text[from..].find("[[").map(|i| from + i) {
+        let inner_start = open + 2;
+        let close = text[inner_start..]
+            .find(['[', ']', '\n'])
+            .map(|i| inner_start + i)
+            .filter(|&close| text[close..].starts_with("]]"));
+        match close {
+            Some(close) if !text[inner_start..close].trim().is_empty() => {
+                links.push(WikiLink {
+                    range: open..close + 2,
+                    target: text[inner_start..close].trim().to_string(),
+                });
+                from = close + 2;
+            }
+            _ => from = open + 1,
+        }
+    }
+    links
+}
+
+// The link at a character index, for following it on a click
+pub fn link_at(text: &str, char_index: usize) -> Option<String> {
+    let byte = crate::text::char_range_to_bytes(text, char_index..char_index).start;
+    links(text)
+        .into_iter()
+        .find(|link| link.range.contains(&byte))
+        .map(|link| link.target)
+}
+
+// When the cursor is in an unclosed "[[" on its line: the character index
+// just after the brackets and the name typed so far
+pub fn name_before(text: &str, cursor: usize) -> Option<(usize, String)> {
+    let before = crate::text::char_slice(text, 0..cursor);
+    let line = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+    let open = line.rfind("[[")?;
+    let typed = &line[open + 2..];
+    if typed.contains([']', '[']) {
+        return None;
+    }
+    let start = cursor.min(crate::text::char_count(text)) - crate::text::char_count(typed);
+    Some((start, typed.to_string()))
+}
+
+// How a note is named in links: its path below the folder, without the
+// extension, with "/" between folders
+pub fn note_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.with_extension("");
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+// The note a link points to: the full name first, then any note with that
+// file name in a subfolder, ignoring case
+pub fn resolve(root: &Path, notes: &[PathBuf], target: &str) -> Option<PathBuf> {
+    let target = target.trim().to_lowercase();
+    let named = |path: &&PathBuf| note_name(root, path).map(|name| name.to_lowercase());
+    notes
+        .iter()
+        .find(|path| named(path).as_deref() == Some(target.as_str()))
+        .or_else(|| {
+            notes.iter().find(|path| {
+                named(path).is_some_and(|name| name.rsplit('/').next() == Some(target.as_str()))
+            })
+        })
+        .cloned()
+}
+
+// Notes with a link to `target`, read from disk
+pub fn backlinks(root: &Path, notes: &[PathBuf], target: &Path) -> Vec<PathBuf> {
+    notes
+        .iter()
+        .filter(|path| path.as_path() != target)
+        .filter(|path| {
+            let mut doc = Document::new(0);
+            doc.load_with_formatting(path).is_ok()
+                && links(&doc.text_content)
+                    .iter()
+                    .any(|link| resolve(root, notes, &link.target).as_deref() == Some(target))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_found() {
+        let text = "See [[Plan]] and [[ Work/Ideas ]], not [[]] or [[a\nb]] or [x]]";
+        let found = links(text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(&text[found[0].range.clone()], "[[Plan]]");
+        assert_eq!(found[1].target, "Work/Ideas");
+        assert_eq!(link_at("é [[Café]]", 4), Some("Café".to_string()));
+        assert_eq!(link_at("é [[Café]]", 1), None);
+    }
+
+    #[test]
+    fn name_being_typed() {
+        assert_eq!(name_before("x [[Caf", 7), Some((4, "Caf".to_string())));
+        assert_eq!(name_before("ü [[My n", 8), Some((4, "My n".to_string())));
+        assert_eq!(name_before("[[done]] more", 13), None);
+        assert_eq!(name_before("[[a\nb", 5), None);
+    }
+
+    #[test]
+    fn links_resolve_to_notes() {
+        let root = Path::new("notes");
+        let notes = vec![
+            root.join("Plan.rtxt"),
+            root.join("Work").join("Ideas.txt"),
+            root.join("Work").join("Plan.rtxt"),
+        ];
+        assert_eq!(note_name(root, &notes[1]).unwrap(), "Work/Ideas");
+        assert_eq!(resolve(root, &notes, "plan"), Some(notes[0].clone()));
+        assert_eq!(resolve(root, &notes, "Work/Plan"), Some(notes[2].clone()));
+        assert_eq!(resolve(root, &notes, "ideas"), Some(notes[1].clone()));
+        assert_eq!(resolve(root, &notes, "Missing"), None);
+    }
+}
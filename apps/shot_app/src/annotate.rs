@@ -0,0 +1,298 @@
+// Arrows, boxes and text drawn over a screenshot. They are kept in image
+// pixels, painted with egui while editing and drawn into the pixels
+// themselves when the picture is saved or copied
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use egui::{Color32, ColorImage, Pos2, Rect, Stroke, Vec2};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    Arrow,
+    Box,
+    Text,
+    Crop,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 4] = [Tool::Arrow, Tool::Box, Tool::Text, Tool::Crop];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Tool::Arrow => "↗ Arrow",
+            Tool::Box => "⬜ Box",
+            Tool::Text => "🗛 Text",
+            Tool::Crop => "✂ Crop",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Mark {
+    Arrow { from: Pos2, to: Pos2 },
+    Box(Rect),
+    Text { at: Pos2, text: String }, // `at` is the top left corner
+}
+
+#[derive(Clone)]
+pub struct Annotation {
+    pub mark: Mark,
+    pub color: Color32,
+    pub size: f32, // Line width, or the height of text
+}
+
+impl Annotation {
+    pub fn translate(&mut self, offset: Vec2) {
+        match &mut self.mark {
+            Mark::Arrow { from, to } => {
+                *from += offset;
+                *to += offset;
+            }
+            Mark::Box(rect) => *rect = rect.translate(offset),
+            Mark::Text { at, .. } => *at += offset,
+        }
+    }
+
+    // The straight lines making up an arrow or a box
+    fn segments(&self) -> Vec<[Pos2; 2]> {
+        match &self.mark {
+            Mark::Arrow { from, to } => {
+                let mut segments = vec![[*from, *to]];
+                segments.extend(arrow_head(*from, *to, self.size).map(|end| [*to, end]));
+                segments
+            }
+            Mark::Box(rect) => {
+                let corners = [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                ];
+                (0..4).map(|i| [corners[i], corners[(i + 1) % 4]]).collect()
+            }
+            Mark::Text { .. } => Vec::new(),
+        }
+    }
+
+    // Painted over the image, shown at `scale` screen points per image pixel
+    pub fn paint(&self, painter: &egui::Painter, origin: Pos2, scale: f32) {
+        let to_screen = |pos: Pos2| origin + pos.to_vec2() * scale;
+        match &self.mark {
+            Mark::Text { at, text } => {
+                painter.text(
+                    to_screen(*at),
+                    egui::Align2::LEFT_TOP,
+                    text,
+                    egui::FontId::proportional(self.size * scale),
+                    self.color,
+                );
+            }
+            _ => {
+                let stroke = Stroke::new(self.size * scale, self.color);
+                for [a, b] in self.segments() {
+                    painter.line_segment([to_screen(a), to_screen(b)], stroke);
+                    // Round ends, as they are drawn into the image
+                    painter.circle_filled(to_screen(b), stroke.width / 2.0, self.color);
+                }
+            }
+        }
+    }
+}
+
+// The two ends of an arrow's head, sized to its line
+fn arrow_head(from: Pos2, to: Pos2, width: f32) -> [Pos2; 2] {
+    let back = (from - to).normalized();
+    let length = (width * 4.0).max(12.0).min((from - to).length());
+    let turn = |angle: f32| to + egui::emath::Rot2::from_angle(angle) * back * length;
+    [turn(0.5), turn(-0.5)]
+}
+
+// `annotations` drawn into a copy of `image`; text only when there is a font
+pub fn render(
+    image: &ColorImage,
+    annotations: &[Annotation],
+    font: Option<&FontArc>,
+) -> ColorImage {
+    let mut image = image.clone();
+    for annotation in annotations {
+        match &annotation.mark {
+            Mark::Text { at, text } => {
+                if let Some(font) = font {
+                    draw_text(&mut image, *at, text, annotation, font);
+                }
+            }
+            _ => {
+                for segment in annotation.segments() {
+                    draw_segment(&mut image, segment, annotation.size, annotation.color);
+                }
+            }
+        }
+    }
+    image
+}
+
+// Mix `color` into one pixel, `coverage` being how much of it is covered
+fn blend(image: &mut ColorImage, x: i64, y: i64, color: Color32, coverage: f32) {
+    let [width, height] = image.size;
+    if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 || coverage <= 0.0 {
+        return;
+    }
+    let alpha = coverage.min(1.0) * color.a() as f32 / 255.0;
+    let pixel = &mut image.pixels[y as usize * width + x as usize];
+    let mix = |under: u8, over: u8| (under as f32 * (1.0 - alpha) + over as f32 * alpha).round();
+    *pixel = Color32::from_rgb(
+        mix(pixel.r(), color.r()) as u8,
+        mix(pixel.g(), color.g()) as u8,
+        mix(pixel.b(), color.b()) as u8,
+    );
+}
+
+// A line `width` wide with round ends, its edges smoothed
+fn draw_segment(image: &mut ColorImage, [a, b]: [Pos2; 2], width: f32, color: Color32) {
+    let radius = width / 2.0;
+    let bounds = Rect::from_two_pos(a, b).expand(radius + 1.0);
+    let along = b - a;
+    let length_sq = along.length_sq();
+    for y in bounds.min.y.floor() as i64..=bounds.max.y.ceil() as i64 {
+        for x in bounds.min.x.floor() as i64..=bounds.max.x.ceil() as i64 {
+            // Distance from the pixel's center to the nearest point of the line
+            let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let t = if length_sq > 0.0 {
+                ((center - a).dot(along) / length_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance = (center - (a + along * t)).length();
+            blend(image, x, y, color, radius - distance + 0.5);
+        }
+    }
+}
+
+fn draw_text(
+    image: &mut ColorImage,
+    at: Pos2,
+    text: &str,
+    annotation: &Annotation,
+    font: &FontArc,
+) {
+    let font = font.as_scaled(PxScale::from(annotation.size));
+    let mut baseline = at.y + font.ascent();
+    for line in text.lines() {
+        let mut x = at.x;
+        let mut previous = None;
+        for c in line.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                x += font.kern(previous, id);
+            }
+            previous = Some(id);
+            let glyph = id.with_scale_and_position(font.scale, ab_glyph::point(x, baseline));
+            x += font.h_advance(id);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue; // Spaces
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                blend(image, px, py, annotation.color, coverage);
+            });
+        }
+        baseline += font.height() + font.line_gap();
+    }
+}
+
+// The part of `image` inside `rect`, which is clipped to the image
+pub fn crop(image: &ColorImage, rect: Rect) -> Option<ColorImage> {
+    let [width, height] = image.size;
+    let left = rect.min.x.round().clamp(0.0, width as f32) as usize;
+    let top = rect.min.y.round().clamp(0.0, height as f32) as usize;
+    let right = rect.max.x.round().clamp(0.0, width as f32) as usize;
+    let bottom = rect.max.y.round().clamp(0.0, height as f32) as usize;
+    if right <= left || bottom <= top {
+        return None;
+    }
+    let pixels = (top..bottom)
+        .flat_map(|y| &image.pixels[y * width + left..y * width + right])
+        .copied()
+        .collect();
+    Some(ColorImage {
+        size: [right - left, bottom - top],
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank(width: usize, height: usize) -> ColorImage {
+        ColorImage::new([width, height], Color32::WHITE)
+    }
+
+    fn pixel(image: &ColorImage, x: usize, y: usize) -> Color32 {
+        image.pixels[y * image.size[0] + x]
+    }
+
+    #[test]
+    fn lines_and_boxes_are_drawn_into_the_image() {
+        let font = FontArc::try_from_vec(
+            egui::FontDefinitions::default().font_data["Ubuntu-Light"]
+                .font
+                .to_vec(),
+        )
+        .unwrap();
+        let image = blank(40, 40);
+        let line = Annotation {
+            mark: Mark::Arrow {
+                from: Pos2::new(2.0, 20.0),
+                to: Pos2::new(38.0, 20.0),
+            },
+            color: Color32::RED,
+            size: 4.0,
+        };
+        let outline = Annotation {
+            mark: Mark::Box(Rect::from_min_max(
+                Pos2::new(5.0, 5.0),
+                Pos2::new(15.0, 15.0),
+            )),
+            color: Color32::BLUE,
+            size: 2.0,
+        };
+        let drawn = render(&image, &[line, outline], None);
+        assert_eq!(pixel(&drawn, 20, 20), Color32::RED);
+        assert_eq!(pixel(&drawn, 20, 30), Color32::WHITE);
+        assert_eq!(pixel(&drawn, 5, 10), Color32::BLUE);
+        // Inside the box is left alone
+        assert_eq!(pixel(&drawn, 10, 10), Color32::WHITE);
+        // The original is untouched
+        assert_eq!(pixel(&image, 20, 20), Color32::WHITE);
+
+        let text = Annotation {
+            mark: Mark::Text {
+                at: Pos2::new(2.0, 2.0),
+                text: "Hi".to_string(),
+            },
+            color: Color32::BLACK,
+            size: 20.0,
+        };
+        let drawn = render(&image, &[text], Some(&font));
+        assert!(drawn.pixels.iter().any(|&p| p != Color32::WHITE));
+    }
+
+    #[test]
+    fn crop_is_clipped_to_the_image() {
+        let mut image = blank(10, 10);
+        image.pixels[3 * 10 + 4] = Color32::RED;
+        let cropped = crop(
+            &image,
+            Rect::from_min_max(Pos2::new(4.0, 3.0), Pos2::new(20.0, 5.0)),
+        )
+        .unwrap();
+        assert_eq!(cropped.size, [6, 2]);
+        assert_eq!(pixel(&cropped, 0, 0), Color32::RED);
+        assert!(crop(
+            &image,
+            Rect::from_min_max(Pos2::new(12.0, 0.0), Pos2::new(20.0, 5.0))
+        )
+        .is_none());
+    }
+}
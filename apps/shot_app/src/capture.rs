@@ -0,0 +1,203 @@
+// Taking a picture of the whole screen. This runs on its own thread after a
+// delay, so the app's window can get out of the way first. Reading the screen
+// is up to each platform: X11 on Linux and the BSDs, and GDI on Windows
+use egui::ColorImage;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+// The screen as it will be once `delay` has passed
+pub fn start(ctx: &egui::Context, delay: Duration) -> Receiver<Result<ColorImage, String>> {
+    let (sender, result) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        thread::sleep(delay);
+        let _ = sender.send(platform::screen());
+        ctx.request_repaint();
+    });
+    result
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use egui::ColorImage;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder, Screen, Setup};
+
+    fn failed(e: impl std::fmt::Display) -> String {
+        format!("Screen not readable: {}", e)
+    }
+
+    // How the root window's pixels are laid out
+    struct PixelFormat {
+        bytes: usize,
+        scanline_pad: usize, // Rows are padded to a multiple of this many bits
+        msb_first: bool,
+        masks: [u32; 3],
+    }
+
+    impl PixelFormat {
+        fn of(setup: &Setup, screen: &Screen) -> Option<Self> {
+            let visual = screen
+                .allowed_depths
+                .iter()
+                .flat_map(|depth| &depth.visuals)
+                .find(|visual| visual.visual_id == screen.root_visual)?;
+            let format = setup
+                .pixmap_formats
+                .iter()
+                .find(|format| format.depth == screen.root_depth)?;
+            let bits = format.bits_per_pixel;
+            // Palette-based screens of 8 bits or fewer are long gone
+            (bits == 16 || bits == 24 || bits == 32).then_some(Self {
+                bytes: bits as usize / 8,
+                scanline_pad: format.scanline_pad.max(8) as usize,
+                msb_first: setup.image_byte_order == ImageOrder::MSB_FIRST,
+                masks: [visual.red_mask, visual.green_mask, visual.blue_mask],
+            })
+        }
+
+        fn stride(&self, width: usize) -> usize {
+            (width * self.bytes * 8).div_ceil(self.scanline_pad) * self.scanline_pad / 8
+        }
+
+        fn rgb(&self, bytes: &[u8]) -> [u8; 3] {
+            let fold = |pixel: u32, byte: &u8| pixel << 8 | *byte as u32;
+            let pixel = if self.msb_first {
+                bytes.iter().fold(0, fold)
+            } else {
+                bytes.iter().rev().fold(0, fold)
+            };
+            self.masks
+                .map(|mask| {
+                    let max = mask >> mask.trailing_zeros();
+                    ((pixel & mask) >> mask.trailing_zeros()) * 255 / max.max(1)
+                })
+                .map(|channel| channel as u8)
+        }
+    }
+
+    pub fn screen() -> Result<ColorImage, String> {
+        let (conn, screen_number) = x11rb::connect(None).map_err(failed)?;
+        let screen = &conn.setup().roots[screen_number];
+        let format = PixelFormat::of(conn.setup(), screen)
+            .ok_or("Screen not readable: unsupported pixel format")?;
+        let (width, height) = (screen.width_in_pixels, screen.height_in_pixels);
+        let image = conn
+            .get_image(ImageFormat::Z_PIXMAP, screen.root, 0, 0, width, height, !0)
+            .map_err(failed)?
+            .reply()
+            .map_err(failed)?;
+        let (width, height) = (width as usize, height as usize);
+        let stride = format.stride(width);
+        if image.data.len() < stride * height {
+            return Err(failed("incomplete image"));
+        }
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for row in image.data.chunks(stride).take(height) {
+            for pixel in row[..width * format.bytes].chunks(format.bytes) {
+                rgba.extend(format.rgb(pixel));
+                rgba.push(255);
+            }
+        }
+        Ok(ColorImage::from_rgba_unmultiplied([width, height], &rgba))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use egui::ColorImage;
+    use std::mem;
+    use windows_sys::Win32::Graphics::Gdi::{
+        BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC,
+        GetDIBits, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT,
+        DIB_RGB_COLORS, SRCCOPY,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetSystemMetrics, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
+        SM_YVIRTUALSCREEN,
+    };
+
+    pub fn screen() -> Result<ColorImage, String> {
+        // Every monitor, which may start left of or above the main one
+        let (x, y, width, height) = unsafe {
+            (
+                GetSystemMetrics(SM_XVIRTUALSCREEN),
+                GetSystemMetrics(SM_YVIRTUALSCREEN),
+                GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                GetSystemMetrics(SM_CYVIRTUALSCREEN),
+            )
+        };
+        if width <= 0 || height <= 0 {
+            return Err("Screen size not available".to_string());
+        }
+        let mut bgra = vec![0u8; width as usize * height as usize * 4];
+        // SAFETY: every object made here is released before returning, and
+        // `bgra` holds exactly the rows asked for
+        let rows = unsafe {
+            let screen = GetDC(std::ptr::null_mut());
+            let memory = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, width, height);
+            let previous = SelectObject(memory, bitmap);
+            let copied = BitBlt(
+                memory,
+                0,
+                0,
+                width,
+                height,
+                screen,
+                x,
+                y,
+                SRCCOPY | CAPTUREBLT,
+            );
+            let mut info: BITMAPINFO = mem::zeroed();
+            info.bmiHeader = BITMAPINFOHEADER {
+                biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height, // Top row first
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB,
+                ..mem::zeroed()
+            };
+            let rows = if copied != 0 {
+                GetDIBits(
+                    memory,
+                    bitmap,
+                    0,
+                    height as u32,
+                    bgra.as_mut_ptr().cast(),
+                    &mut info,
+                    DIB_RGB_COLORS,
+                )
+            } else {
+                0
+            };
+            SelectObject(memory, previous);
+            DeleteObject(bitmap);
+            DeleteDC(memory);
+            ReleaseDC(std::ptr::null_mut(), screen);
+            rows
+        };
+        if rows != height {
+            return Err("Screen not readable".to_string());
+        }
+        for pixel in bgra.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 255;
+        }
+        Ok(ColorImage::from_rgba_unmultiplied(
+            [width as usize, height as usize],
+            &bgra,
+        ))
+    }
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+mod platform {
+    use egui::ColorImage;
+
+    pub fn screen() -> Result<ColorImage, String> {
+        Err("Capturing the screen is not supported on this system".to_string())
+    }
+}
@@ -0,0 +1,678 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod annotate;
+mod capture;
+
+use ab_glyph::FontArc;
+use annotate::{Annotation, Mark, Tool};
+use eframe::egui;
+use egui::{Color32, ColorImage, Pos2, Rect, Sense, Vec2};
+use gui_common::dialog::FileType;
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{palette, Labeled};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+
+const APP_ID: &str = "Screenshot";
+// Choices for the wait before the screen is read, in seconds
+const DELAYS: [u32; 4] = [0, 3, 5, 10];
+// Time for the window to get out of the way before the screen is read
+const HIDE_TIME: Duration = Duration::from_millis(500);
+// Steps kept for undo; each crop keeps a whole picture
+const MAX_UNDO: usize = 30;
+const SWATCHES: [Color32; 6] = [
+    Color32::from_rgb(230, 40, 40),
+    Color32::from_rgb(250, 200, 30),
+    Color32::from_rgb(40, 170, 70),
+    Color32::from_rgb(30, 111, 217),
+    Color32::BLACK,
+    Color32::WHITE,
+];
+
+fn main() -> eframe::Result<()> {
+    // Without a log the app still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<ShotSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
+        "Screenshot",
+        [1000.0, 720.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
+    eframe::run_native(
+        APP_ID,
+        options,
+        Box::new(|cc| Ok(Box::new(ShotApp::new(cc, saved)))),
+    )
+}
+
+// User preferences persisted between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ShotSettings {
+    delay: u32,             // Seconds to wait before capturing
+    hide_window: bool,      // Minimized while the screen is read
+    color: [u8; 3],         // For new annotations
+    width: f32,             // Of arrows and boxes
+    text_size: f32,         // Height of text annotations
+    common: CommonSettings, // Theme, window and save folder, as in the other apps
+}
+
+impl Default for ShotSettings {
+    fn default() -> Self {
+        let [r, g, b, _] = SWATCHES[0].to_array();
+        Self {
+            delay: 0,
+            hide_window: true,
+            color: [r, g, b],
+            width: 4.0,
+            text_size: 24.0,
+            common: CommonSettings::default(),
+        }
+    }
+}
+
+// A picture and what has been drawn on it
+#[derive(Clone)]
+struct Shot {
+    image: Arc<ColorImage>,
+    annotations: Vec<Annotation>,
+}
+
+struct ShotApp {
+    settings: ShotSettings,
+    shot: Option<Shot>,
+    undo: Vec<Shot>,
+    texture: Option<egui::TextureHandle>, // Of `shot`'s image; dropped when it changes
+    tool: Tool,
+    drag_start: Option<Pos2>,       // In image pixels
+    typing: Option<(Pos2, String)>, // Text being placed, before it is added
+    capturing: Option<Receiver<Result<ColorImage, String>>>,
+    crop_after_capture: bool, // Capturing a region: the whole screen, then cropped
+    font: Option<FontArc>,    // Egui's own, for drawing text into saved pictures
+    // On Linux a copied picture lasts only as long as the clipboard is open
+    clipboard: Option<arboard::Clipboard>,
+    status: String,
+    error_message: Option<String>,
+    show_settings: bool,
+    show_log: bool, // Recent log lines, for problem reports
+}
+
+impl ShotApp {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<ShotSettings>) -> Self {
+        let settings = saved.unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let font = egui::FontDefinitions::default()
+            .font_data
+            .get("Ubuntu-Light")
+            .and_then(|data| FontArc::try_from_vec(data.font.to_vec()).ok());
+        if font.is_none() {
+            tracing::warn!("No font for text annotations");
+        }
+        Self {
+            settings,
+            shot: None,
+            undo: Vec::new(),
+            texture: None,
+            tool: Tool::Arrow,
+            drag_start: None,
+            typing: None,
+            capturing: None,
+            crop_after_capture: false,
+            font,
+            clipboard: None,
+            status: "Ctrl+N captures the screen, Ctrl+R a region of it".to_string(),
+            error_message: None,
+            show_settings: false,
+            show_log: false,
+        }
+    }
+
+    fn capture(&mut self, ctx: &egui::Context, region: bool) {
+        if self.capturing.is_some() {
+            return;
+        }
+        self.commit_text();
+        let mut delay = Duration::from_secs(self.settings.delay as u64);
+        if self.settings.hide_window {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            delay += HIDE_TIME;
+        }
+        self.capturing = Some(capture::start(ctx, delay));
+        self.crop_after_capture = region;
+        self.status = "Capturing...".to_string();
+    }
+
+    fn handle_capture(&mut self, ctx: &egui::Context) {
+        let Some(capturing) = &self.capturing else {
+            return;
+        };
+        let result = match capturing.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("Capture stopped".to_string()),
+        };
+        self.capturing = None;
+        if self.settings.hide_window {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+        match result {
+            Ok(image) => {
+                tracing::info!(size = ?image.size, "Captured the screen");
+                self.change(Shot {
+                    image: Arc::new(image),
+                    annotations: Vec::new(),
+                });
+                self.error_message = None;
+                if self.crop_after_capture {
+                    self.tool = Tool::Crop;
+                    self.status = "Drag over the part to keep".to_string();
+                } else {
+                    self.status = "Captured".to_string();
+                }
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Capture failed");
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    // Replace the picture, keeping the old one for undo
+    fn change(&mut self, shot: Shot) {
+        self.drop_texture_unless_same(&shot);
+        if let Some(old) = self.shot.replace(shot) {
+            self.undo.push(old);
+            if self.undo.len() > MAX_UNDO {
+                self.undo.remove(0);
+            }
+        }
+    }
+
+    fn add(&mut self, annotation: Annotation) {
+        if let Some(shot) = &self.shot {
+            let mut shot = shot.clone();
+            shot.annotations.push(annotation);
+            self.change(shot);
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(shot) = self.undo.pop() {
+            self.drop_texture_unless_same(&shot);
+            self.shot = Some(shot);
+        }
+    }
+
+    // Annotations alone leave the image, and so its texture, as it was
+    fn drop_texture_unless_same(&mut self, shot: &Shot) {
+        let same = self
+            .shot
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(&current.image, &shot.image));
+        if !same {
+            self.texture = None;
+        }
+    }
+
+    fn commit_text(&mut self) {
+        if let Some((at, text)) = self.typing.take() {
+            if !text.trim().is_empty() {
+                self.add(Annotation {
+                    mark: Mark::Text { at, text },
+                    color: self.color(),
+                    size: self.settings.text_size,
+                });
+            }
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        let [r, g, b] = self.settings.color;
+        Color32::from_rgb(r, g, b)
+    }
+
+    // The shape being dragged out from `start` to `end`
+    fn dragged(&self, start: Pos2, end: Pos2) -> Option<Annotation> {
+        let mark = match self.tool {
+            Tool::Arrow => Mark::Arrow {
+                from: start,
+                to: end,
+            },
+            Tool::Box => Mark::Box(Rect::from_two_pos(start, end)),
+            Tool::Text | Tool::Crop => return None,
+        };
+        Some(Annotation {
+            mark,
+            color: self.color(),
+            size: self.settings.width,
+        })
+    }
+
+    fn crop(&mut self, rect: Rect) {
+        let Some(shot) = &self.shot else {
+            return;
+        };
+        let rect = Rect::from_min_max(rect.min.round(), rect.max.round());
+        let Some(image) = annotate::crop(&shot.image, rect) else {
+            return;
+        };
+        let mut annotations = shot.annotations.clone();
+        for annotation in &mut annotations {
+            annotation.translate(-rect.min.to_vec2());
+        }
+        self.change(Shot {
+            image: Arc::new(image),
+            annotations,
+        });
+        self.tool = Tool::Arrow;
+        self.status = "Cropped".to_string();
+    }
+
+    // The picture with its annotations drawn in
+    fn rendered(&mut self) -> Option<ColorImage> {
+        self.commit_text();
+        let shot = self.shot.as_ref()?;
+        Some(annotate::render(
+            &shot.image,
+            &shot.annotations,
+            self.font.as_ref(),
+        ))
+    }
+
+    fn save_as(&mut self) {
+        let Some(image) = self.rendered() else {
+            return;
+        };
+        let name = chrono::Local::now()
+            .format("Screenshot %Y-%m-%d %H%M%S.png")
+            .to_string();
+        let Some(path) = self.settings.common.dialogs.save(&[FileType::Png], &name) else {
+            return;
+        };
+        match write_png(&path, &image) {
+            Ok(()) => {
+                tracing::info!(path = %path.display(), "Saved");
+                self.status = format!("Saved {}", path.display());
+                self.error_message = None;
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Save failed");
+                self.error_message = Some(format!("Save failed: {}", e));
+            }
+        }
+    }
+
+    fn copy(&mut self) {
+        let Some(image) = self.rendered() else {
+            return;
+        };
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    tracing::error!(error = %e, "Clipboard not available");
+                    self.error_message = Some(format!("Clipboard not available: {}", e));
+                    return;
+                }
+            }
+        }
+        let Some(clipboard) = &mut self.clipboard else {
+            return;
+        };
+        let [width, height] = image.size;
+        let data = arboard::ImageData {
+            width,
+            height,
+            bytes: Cow::Borrowed(image.as_raw()),
+        };
+        match clipboard.set_image(data) {
+            Ok(()) => {
+                self.status = format!("Copied {} × {}", width, height);
+                self.error_message = None;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Copy failed");
+                self.error_message = Some(format!("Copy failed: {}", e));
+            }
+        }
+    }
+
+    fn show_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let idle = self.capturing.is_none();
+            if ui
+                .add_enabled(idle, egui::Button::new("📷 Screen"))
+                .labeled("Capture the Screen (Ctrl+N)")
+                .clicked()
+            {
+                self.capture(ui.ctx(), false);
+            }
+            if ui
+                .add_enabled(idle, egui::Button::new("⬚ Region"))
+                .labeled("Capture a Region (Ctrl+R)")
+                .clicked()
+            {
+                self.capture(ui.ctx(), true);
+            }
+            egui::ComboBox::from_id_salt("delay")
+                .selected_text(format!("Delay {} s", self.settings.delay))
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    for delay in DELAYS {
+                        ui.selectable_value(
+                            &mut self.settings.delay,
+                            delay,
+                            format!("{} s", delay),
+                        );
+                    }
+                });
+            ui.separator();
+
+            for tool in Tool::ALL {
+                if ui
+                    .selectable_label(self.tool == tool, tool.label())
+                    .clicked()
+                {
+                    self.commit_text();
+                    self.tool = tool;
+                }
+            }
+            ui.separator();
+
+            for swatch in SWATCHES {
+                let [r, g, b, _] = swatch.to_array();
+                let selected = self.settings.color == [r, g, b];
+                let (rect, response) = ui.allocate_exact_size(Vec2::splat(18.0), Sense::click());
+                ui.painter().rect_filled(rect, 3.0, swatch);
+                let stroke = if selected {
+                    ui.visuals().selection.stroke
+                } else {
+                    ui.visuals().widgets.noninteractive.bg_stroke
+                };
+                ui.painter().rect_stroke(rect, 3.0, stroke);
+                if response.labeled("Color").clicked() {
+                    self.settings.color = [r, g, b];
+                }
+            }
+            ui.color_edit_button_srgb(&mut self.settings.color)
+                .labeled("Other Color");
+            let size = if self.tool == Tool::Text {
+                &mut self.settings.text_size
+            } else {
+                &mut self.settings.width
+            };
+            let range = if self.tool == Tool::Text {
+                8.0..=96.0
+            } else {
+                1.0..=20.0
+            };
+            ui.add(
+                egui::DragValue::new(size)
+                    .range(range)
+                    .max_decimals(0)
+                    .suffix(" px"),
+            )
+            .labeled(if self.tool == Tool::Text {
+                "Text Size"
+            } else {
+                "Line Width"
+            });
+            ui.separator();
+
+            let has_shot = self.shot.is_some();
+            if ui
+                .add_enabled(!self.undo.is_empty(), egui::Button::new("↶"))
+                .labeled("Undo (Ctrl+Z)")
+                .clicked()
+            {
+                self.undo();
+            }
+            if ui
+                .add_enabled(has_shot, egui::Button::new("💾 Save..."))
+                .on_hover_text("Ctrl+S")
+                .clicked()
+            {
+                self.save_as();
+            }
+            if ui
+                .add_enabled(has_shot, egui::Button::new("📋 Copy"))
+                .on_hover_text("Ctrl+C")
+                .clicked()
+            {
+                self.copy();
+            }
+        });
+    }
+
+    fn show_shot(&mut self, ui: &mut egui::Ui) {
+        let Some(shot) = &self.shot else {
+            ui.centered_and_justified(|ui| {
+                ui.weak(if self.capturing.is_some() {
+                    "Capturing..."
+                } else {
+                    "Capture the screen or a region of it to start"
+                });
+            });
+            return;
+        };
+        let texture = self.texture.get_or_insert_with(|| {
+            ui.ctx()
+                .load_texture("shot", (*shot.image).clone(), egui::TextureOptions::LINEAR)
+        });
+        let [width, height] = shot.image.size;
+        let size = Vec2::new(width as f32, height as f32);
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        // Shrunk to fit, but never enlarged
+        let scale = (response.rect.size() / size).min_elem().min(1.0);
+        let rect = Rect::from_center_size(response.rect.center(), size * scale);
+        painter.image(
+            texture.id(),
+            rect,
+            Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        for annotation in &shot.annotations {
+            annotation.paint(&painter, rect.min, scale);
+        }
+        let to_image = |pos: Pos2| {
+            let pos = Pos2::ZERO + (pos - rect.min) / scale;
+            pos.clamp(Pos2::ZERO, size.to_pos2())
+        };
+        let response = response.on_hover_cursor(match self.tool {
+            Tool::Text => egui::CursorIcon::Text,
+            _ => egui::CursorIcon::Crosshair,
+        });
+        let pointer = response.interact_pointer_pos().map(to_image);
+
+        if self.tool == Tool::Text {
+            if response.clicked() {
+                self.commit_text();
+                if let Some(at) = pointer {
+                    self.typing = Some((at, String::new()));
+                }
+            }
+        } else if response.drag_started() {
+            self.commit_text();
+            self.drag_start = pointer;
+        }
+        if let (Some(start), Some(end)) = (self.drag_start, pointer) {
+            if self.tool == Tool::Crop {
+                // Everything but the part kept is dimmed
+                let keep = Rect::from_two_pos(start, end);
+                let keep = Rect::from_min_max(
+                    rect.min + keep.min.to_vec2() * scale,
+                    rect.min + keep.max.to_vec2() * scale,
+                );
+                let dim = Color32::from_black_alpha(140);
+                for part in [
+                    Rect::from_min_max(rect.min, Pos2::new(rect.max.x, keep.min.y)),
+                    Rect::from_min_max(Pos2::new(rect.min.x, keep.max.y), rect.max),
+                    Rect::from_min_max(Pos2::new(rect.min.x, keep.min.y), keep.left_bottom()),
+                    Rect::from_min_max(keep.right_top(), Pos2::new(rect.max.x, keep.max.y)),
+                ] {
+                    painter.rect_filled(part, 0.0, dim);
+                }
+                painter.rect_stroke(keep, 0.0, ui.visuals().selection.stroke);
+            } else if let Some(shape) = self.dragged(start, end) {
+                shape.paint(&painter, rect.min, scale);
+            }
+            if response.drag_stopped() {
+                self.drag_start = None;
+                // A click rather than a drag adds nothing
+                if (end - start).length() >= 3.0 {
+                    if self.tool == Tool::Crop {
+                        self.crop(Rect::from_two_pos(start, end));
+                    } else if let Some(shape) = self.dragged(start, end) {
+                        self.add(shape);
+                    }
+                }
+            }
+        }
+
+        let scale_text = self.settings.text_size * scale;
+        let color = self.color();
+        let mut finished = false;
+        let mut cancelled = false;
+        if let Some((at, text)) = &mut self.typing {
+            egui::Area::new(egui::Id::new("typing"))
+                .fixed_pos(rect.min + at.to_vec2() * scale)
+                .show(ui.ctx(), |ui| {
+                    let edit = ui.add(
+                        egui::TextEdit::singleline(text)
+                            .font(egui::FontId::proportional(scale_text))
+                            .text_color(color)
+                            .hint_text("Text")
+                            .frame(false)
+                            .desired_width((rect.max.x - ui.min_rect().min.x).max(80.0)),
+                    );
+                    if !edit.has_focus() && !edit.lost_focus() {
+                        edit.request_focus();
+                    }
+                    if edit.lost_focus() {
+                        cancelled = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                        finished = true;
+                    }
+                });
+        }
+        if cancelled {
+            self.typing = None;
+        } else if finished {
+            self.commit_text();
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        let folder = common
+            .dialogs
+            .last_dir(FileType::Png)
+            .map(Path::to_path_buf);
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            ui.checkbox(
+                &mut self.settings.hide_window,
+                "Hide this window while capturing",
+            );
+            if let Some(folder) = &folder {
+                ui.label(format!("Pictures are saved to {}", folder.display()));
+            }
+        });
+        self.settings.common = common;
+        self.show_settings = open;
+    }
+}
+
+fn write_png(path: &Path, image: &ColorImage) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let [width, height] = image.size;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
+
+impl eframe::App for ShotApp {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        // Saved again on the next save or at exit
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.settings.common.ui_scale = ctx.zoom_factor();
+        self.settings.common.remember_window(ctx);
+        self.handle_capture(ctx);
+        let typing = self.typing.is_some();
+        let (log, screen, region, undo, save, copy, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::L),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::N),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::R),
+                !typing && i.consume_key(egui::Modifiers::CTRL, egui::Key::Z),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::S),
+                // Ctrl+C arrives as a copy event rather than a key
+                !typing && i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                !typing && i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if log {
+            self.show_log = !self.show_log;
+        }
+        if screen || region {
+            self.capture(ctx, region);
+        }
+        if undo {
+            self.undo();
+        }
+        if save && self.shot.is_some() {
+            self.save_as();
+        }
+        if copy && self.shot.is_some() {
+            self.copy();
+        }
+        if escape {
+            self.drag_start = None;
+        }
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| self.show_toolbar(ui));
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(error) = &self.error_message {
+                    ui.colored_label(palette::ERROR, error);
+                } else {
+                    ui.label(&self.status);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("⚙").labeled("Settings").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                    if let Some(shot) = &self.shot {
+                        let [width, height] = shot.image.size;
+                        ui.label(format!("{} × {}", width, height));
+                    }
+                });
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| self.show_shot(ui));
+    }
+}
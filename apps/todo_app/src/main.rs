@@ -0,0 +1,495 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod task;
+
+use chrono::{Local, NaiveDate};
+use eframe::egui;
+use egui::RichText;
+use gui_common::settings::{self, CommonSettings};
+use gui_common::{palette, Labeled};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use task::{parse_date, parse_tags, Filter, Priority, Show, Sort, TaskList};
+
+const APP_ID: &str = "Todo App";
+// Edits are written to the tasks file once they have settled this long
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+fn main() -> eframe::Result<()> {
+    // Without a log the app still runs
+    let _ = gui_common::logging::init(APP_ID);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting");
+    // Loaded before the window opens, so it opens where it was left
+    let saved: Option<TodoSettings> = settings::load(APP_ID);
+    let common = saved.as_ref().map(|saved| &saved.common);
+    let options = gui_common::native_options(
+        "Todo App",
+        [900.0, 700.0],
+        common.unwrap_or(&CommonSettings::default()),
+    );
+    eframe::run_native(
+        APP_ID,
+        options,
+        Box::new(|cc| Ok(Box::new(TodoApp::new(cc, saved)))),
+    )
+}
+
+// User preferences persisted between sessions
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TodoSettings {
+    show: Show,             // All, active or done tasks
+    sort: Sort,             // Order of the list
+    common: CommonSettings, // Theme and window, as in the other apps
+}
+
+struct TodoApp {
+    tasks: TaskList,
+    settings: TodoSettings,
+    filter: Filter,
+    new_task: String, // The quick-entry field
+    selected: Option<u64>,
+    // The selected task's due date and tags as typed in the details panel,
+    // applied once they parse
+    due_text: String,
+    tags_text: String,
+    editing: Option<u64>,        // Task the fields above were filled from
+    changed_at: Option<Instant>, // First edit not yet written to the tasks file
+    error_message: Option<String>,
+    show_settings: bool,
+    show_log: bool, // Recent log lines, for problem reports
+}
+
+impl TodoApp {
+    fn new(cc: &eframe::CreationContext<'_>, saved: Option<TodoSettings>) -> Self {
+        let settings = saved.unwrap_or_default();
+        gui_common::setup(&cc.egui_ctx);
+        settings.common.apply(&cc.egui_ctx);
+        let (tasks, error_message) = match TaskList::load() {
+            Ok(tasks) => (tasks, None),
+            Err(e) => {
+                tracing::error!(error = %e, "Tasks not loaded");
+                (TaskList::default(), Some(e))
+            }
+        };
+        Self {
+            tasks,
+            filter: Filter {
+                show: settings.show,
+                ..Filter::default()
+            },
+            settings,
+            new_task: String::new(),
+            selected: None,
+            due_text: String::new(),
+            tags_text: String::new(),
+            editing: None,
+            changed_at: None,
+            error_message,
+            show_settings: false,
+            show_log: false,
+        }
+    }
+
+    fn mark_changed(&mut self) {
+        self.changed_at.get_or_insert_with(Instant::now);
+    }
+
+    fn save_tasks(&mut self) {
+        self.changed_at = None;
+        match self.tasks.save() {
+            Ok(()) => self.error_message = None,
+            Err(e) => {
+                tracing::error!(error = %e, "Tasks not saved");
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    fn add_task(&mut self, today: NaiveDate) {
+        if let Some(id) = self.tasks.add(&self.new_task, today) {
+            self.new_task.clear();
+            self.selected = Some(id);
+            self.mark_changed();
+        }
+    }
+
+    fn delete_task(&mut self, id: u64) {
+        self.tasks.remove(id);
+        if self.selected == Some(id) {
+            self.selected = None;
+        }
+        self.mark_changed();
+    }
+
+    fn show_toolbar(&mut self, ui: &mut egui::Ui, today: NaiveDate) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.new_task)
+                    .id(egui::Id::new("new_task"))
+                    .hint_text("New task, e.g. Call Sam #work !high due:friday")
+                    .desired_width(ui.available_width() - 80.0),
+            );
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("➕ Add").clicked() || entered {
+                self.add_task(today);
+                response.request_focus();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.text)
+                    .id(egui::Id::new("search"))
+                    .hint_text("🔍 Search")
+                    .desired_width(160.0),
+            );
+            ui.separator();
+            for (show, label) in [
+                (Show::All, "All"),
+                (Show::Active, "Active"),
+                (Show::Done, "Done"),
+            ] {
+                ui.selectable_value(&mut self.filter.show, show, label);
+            }
+            ui.separator();
+            let tags = self.tasks.tags();
+            // A tag no task has any more would hide every task
+            if self
+                .filter
+                .tag
+                .as_ref()
+                .is_some_and(|tag| !tags.contains(tag))
+            {
+                self.filter.tag = None;
+            }
+            egui::ComboBox::from_id_salt("tag_filter")
+                .selected_text(
+                    self.filter
+                        .tag
+                        .as_ref()
+                        .map_or("All tags".to_string(), |tag| format!("#{}", tag)),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter.tag, None, "All tags");
+                    for tag in tags {
+                        let label = format!("#{}", tag);
+                        ui.selectable_value(&mut self.filter.tag, Some(tag), label);
+                    }
+                });
+            egui::ComboBox::from_id_salt("sort")
+                .selected_text(format!("Sort: {}", self.settings.sort.label()))
+                .show_ui(ui, |ui| {
+                    for sort in Sort::ALL {
+                        ui.selectable_value(&mut self.settings.sort, sort, sort.label());
+                    }
+                });
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("⚙").labeled("Settings").clicked() {
+                    self.show_settings = !self.show_settings;
+                }
+            });
+        });
+    }
+
+    fn show_task_list(&mut self, ui: &mut egui::Ui, today: NaiveDate) {
+        let view = task::view(&self.tasks.tasks, &self.filter, self.settings.sort);
+        if view.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.weak(if self.tasks.tasks.is_empty() {
+                    "No tasks yet. Type one above and press Enter"
+                } else {
+                    "No tasks match"
+                });
+            });
+            return;
+        }
+        // Dragging only makes sense in the order it changes
+        let manual = self.settings.sort == Sort::Manual;
+        let mut moved = None;
+        let mut delete = None;
+        let mut changed = false;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for index in view {
+                let task = &mut self.tasks.tasks[index];
+                let row = ui.horizontal(|ui| {
+                    if manual {
+                        let handle = ui
+                            .add(egui::Label::new("☰").sense(egui::Sense::drag()))
+                            .on_hover_cursor(egui::CursorIcon::Grab)
+                            .labeled("Drag to reorder");
+                        handle.dnd_set_drag_payload(index);
+                    }
+                    changed |= ui.checkbox(&mut task.done, "").labeled("Done").changed();
+                    match task.priority {
+                        Priority::High => ui.colored_label(palette::ERROR, "❗"),
+                        Priority::Normal => ui.label("  "),
+                        Priority::Low => ui.weak("⬇"),
+                    }
+                    .on_hover_text(format!("{} priority", task.priority.label()));
+                    let mut title = RichText::new(&task.title);
+                    if task.done {
+                        title = title.strikethrough().weak();
+                    }
+                    if ui
+                        .selectable_label(self.selected == Some(task.id), title)
+                        .clicked()
+                    {
+                        self.selected = Some(task.id);
+                    }
+                    if let Some(due) = task.due {
+                        let text = due_label(due, today);
+                        if task.is_overdue(today) {
+                            ui.colored_label(palette::ERROR, text)
+                                .on_hover_text("Overdue");
+                        } else if due == today && !task.done {
+                            ui.colored_label(palette::ACCENT, text);
+                        } else {
+                            ui.weak(text);
+                        }
+                    }
+                    for tag in &task.tags {
+                        if ui
+                            .small_button(format!("#{}", tag))
+                            .on_hover_text("Show only this tag")
+                            .clicked()
+                        {
+                            self.filter.tag = Some(tag.clone());
+                        }
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("🗑").labeled("Delete").clicked() {
+                            delete = Some(task.id);
+                        }
+                    });
+                });
+                // Drop a task on a row to move it there
+                if manual {
+                    let response = row.response;
+                    if let Some(from) = response.dnd_hover_payload::<usize>() {
+                        let rect = response.rect;
+                        let y = if *from < index {
+                            rect.bottom()
+                        } else {
+                            rect.top()
+                        };
+                        ui.painter()
+                            .hline(rect.x_range(), y, ui.visuals().selection.stroke);
+                    }
+                    if let Some(from) = response.dnd_release_payload::<usize>() {
+                        moved = Some((*from, index));
+                    }
+                }
+            }
+        });
+        if let Some((from, to)) = moved.filter(|(from, to)| from != to) {
+            self.tasks.move_task(from, to);
+            changed = true;
+        }
+        if let Some(id) = delete {
+            self.delete_task(id);
+        }
+        if changed {
+            self.mark_changed();
+        }
+    }
+
+    // The selected task's fields, in the panel on the right
+    fn show_details(&mut self, ui: &mut egui::Ui, today: NaiveDate) {
+        let Some(id) = self.selected else {
+            return;
+        };
+        let Some(task) = self.tasks.get_mut(id) else {
+            self.selected = None;
+            return;
+        };
+        if self.editing != Some(id) {
+            self.editing = Some(id);
+            self.due_text = task.due.map(|due| due.to_string()).unwrap_or_default();
+            self.tags_text = task.tags.join(", ");
+        }
+        let mut changed = false;
+        let mut close = false;
+        let mut delete = false;
+        ui.heading("Task");
+        ui.add_space(4.0);
+        changed |= ui
+            .add(egui::TextEdit::singleline(&mut task.title).desired_width(f32::INFINITY))
+            .labeled("Title")
+            .changed();
+        changed |= ui.checkbox(&mut task.done, "Done").changed();
+        ui.horizontal(|ui| {
+            ui.label("Priority:");
+            for priority in Priority::ALL {
+                changed |= ui
+                    .radio_value(&mut task.priority, priority, priority.label())
+                    .changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Due:");
+            let response = ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.due_text)
+                        .hint_text("YYYY-MM-DD")
+                        .desired_width(100.0),
+                )
+                .on_hover_text("A date, today, tomorrow or a weekday; empty for none");
+            let text = self.due_text.trim();
+            let due = parse_date(text, today);
+            if response.changed() && (text.is_empty() || due.is_some()) {
+                task.due = due;
+                changed = true;
+            }
+            if !text.is_empty() && due.is_none() {
+                ui.colored_label(palette::ERROR, "Not a date");
+            } else if let Some(due) = task.due {
+                ui.weak(due.format("%A").to_string());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Tags:");
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.tags_text)
+                        .hint_text("work, home")
+                        .desired_width(f32::INFINITY),
+                )
+                .changed()
+            {
+                task.tags = parse_tags(&self.tags_text);
+                changed = true;
+            }
+        });
+        ui.label("Notes:");
+        changed |= ui
+            .add(
+                egui::TextEdit::multiline(&mut task.notes)
+                    .desired_rows(8)
+                    .desired_width(f32::INFINITY),
+            )
+            .labeled("Notes")
+            .changed();
+        ui.horizontal(|ui| {
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+            if ui.button("🗑 Delete").clicked() {
+                delete = true;
+            }
+        });
+        if changed {
+            self.mark_changed();
+        }
+        if delete {
+            self.delete_task(id);
+        } else if close {
+            self.selected = None;
+        }
+    }
+
+    fn show_status_bar(&self, ui: &mut egui::Ui, today: NaiveDate) {
+        ui.horizontal(|ui| {
+            let tasks = &self.tasks.tasks;
+            let done = tasks.iter().filter(|task| task.done).count();
+            let overdue = tasks.iter().filter(|task| task.is_overdue(today)).count();
+            ui.label(format!("{} active, {} done", tasks.len() - done, done));
+            if overdue > 0 {
+                ui.colored_label(palette::ERROR, format!("{} overdue", overdue));
+            }
+            if let Some(error) = &self.error_message {
+                ui.separator();
+                ui.colored_label(palette::ERROR, error);
+            }
+        });
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut common = std::mem::take(&mut self.settings.common);
+        settings::window(ctx, "Settings", &mut open, &mut common, |ui| {
+            if let Some(path) = TaskList::path() {
+                ui.label(format!("Tasks are kept in {}", path.display()));
+            }
+            ui.label("Ctrl+N: new task   Ctrl+F: search   Ctrl+Shift+L: log");
+        });
+        self.settings.common = common;
+        self.show_settings = open;
+    }
+}
+
+// "Today", "Tomorrow", or the date with its weekday
+fn due_label(due: NaiveDate, today: NaiveDate) -> String {
+    match (due - today).num_days() {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        -1 => "Yesterday".to_string(),
+        _ => due.format("%a %Y-%m-%d").to_string(),
+    }
+}
+
+impl eframe::App for TodoApp {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+        self.settings.show = self.filter.show;
+        // Saved again on the next save or at exit
+        if let Err(e) = settings::save(APP_ID, &self.settings) {
+            tracing::error!(error = %e, "Settings not saved");
+        }
+        if self.changed_at.is_some() {
+            self.save_tasks();
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.settings.common.ui_scale = ctx.zoom_factor();
+        self.settings.common.remember_window(ctx);
+        let today = Local::now().date_naive();
+        let (log, new, search) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::L),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::N),
+                i.consume_key(egui::Modifiers::CTRL, egui::Key::F),
+            )
+        });
+        if log {
+            self.show_log = !self.show_log;
+        }
+        if new {
+            ctx.memory_mut(|memory| memory.request_focus(egui::Id::new("new_task")));
+        }
+        if search {
+            ctx.memory_mut(|memory| memory.request_focus(egui::Id::new("search")));
+        }
+        if self.show_log {
+            gui_common::logging::window(ctx, &mut self.show_log);
+        }
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.add_space(4.0);
+            self.show_toolbar(ui, today);
+            ui.add_space(2.0);
+        });
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            self.show_status_bar(ui, today);
+        });
+        if self.selected.is_some() {
+            egui::SidePanel::right("details")
+                .default_width(300.0)
+                .show(ctx, |ui| self.show_details(ui, today));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.show_task_list(ui, today);
+        });
+
+        if let Some(changed_at) = self.changed_at {
+            let waited = changed_at.elapsed();
+            if waited >= SAVE_DELAY {
+                self.save_tasks();
+            } else {
+                ctx.request_repaint_after(SAVE_DELAY - waited);
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+// Tasks, the quick-entry syntax for adding them, and filtering and sorting
+// for the list
+use chrono::{Days, NaiveDate};
+use gui_common::settings;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = "tasks.json";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: u64,
+    pub title: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub done: bool,
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>, // Without the '#'
+}
+
+impl Task {
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.done && self.due.is_some_and(|due| due < today)
+    }
+}
+
+// The tasks in their manual order, as saved in the tasks file
+#[derive(Default, Serialize, Deserialize)]
+pub struct TaskList {
+    pub tasks: Vec<Task>,
+    next_id: u64,
+}
+
+impl TaskList {
+    // Add a task typed in the quick-entry syntax at the end of the list,
+    // returning its id; None when there is no title
+    pub fn add(&mut self, text: &str, today: NaiveDate) -> Option<u64> {
+        let mut task = parse_quick(text, today)?;
+        self.next_id = self
+            .next_id
+            .max(self.tasks.iter().map(|t| t.id + 1).max().unwrap_or(0));
+        task.id = self.next_id;
+        self.next_id += 1;
+        self.tasks.push(task);
+        Some(self.next_id - 1)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.id == id)
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.tasks.retain(|task| task.id != id);
+    }
+
+    // Move the task at `from` to `to`, shifting the ones between
+    pub fn move_task(&mut self, from: usize, to: usize) {
+        if from < self.tasks.len() && to < self.tasks.len() {
+            let task = self.tasks.remove(from);
+            self.tasks.insert(to, task);
+        }
+    }
+
+    // tasks.json in the app's data folder, beside the settings
+    pub fn path() -> Option<PathBuf> {
+        settings::dir(crate::APP_ID).map(|dir| dir.join(FILE_NAME))
+    }
+
+    // The saved tasks, or none before the first save. A file that cannot be
+    // read as tasks is kept beside as "tasks.json.bad", so the next save does
+    // not lose them
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path().ok_or("No data folder for tasks")?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Error reading tasks: {}", e)),
+        };
+        serde_json::from_str(&text).map_err(|e| {
+            let _ = fs::copy(&path, path.with_extension("json.bad"));
+            format!("Tasks unreadable, kept as tasks.json.bad: {}", e)
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No data folder for tasks")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Error creating data folder: {}", e))?;
+        }
+        settings::write_json(&path, self).map_err(|e| format!("Error saving tasks: {}", e))
+    }
+
+    // Every tag in use, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.tasks.iter().flat_map(|t| t.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+}
+
+// "Call Sam #work !high due:friday" is a High priority task "Call Sam" tagged
+// work. Due dates are YYYY-MM-DD, today, tomorrow or a weekday (the next one
+// after today); the priorities are !high and !low
+pub fn parse_quick(text: &str, today: NaiveDate) -> Option<Task> {
+    let mut task = Task {
+        id: 0,
+        title: String::new(),
+        notes: String::new(),
+        done: false,
+        due: None,
+        priority: Priority::Normal,
+        tags: Vec::new(),
+    };
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            if !task.tags.iter().any(|t| t == tag) {
+                task.tags.push(tag.to_string());
+            }
+        } else if word.eq_ignore_ascii_case("!high") {
+            task.priority = Priority::High;
+        } else if word.eq_ignore_ascii_case("!low") {
+            task.priority = Priority::Low;
+        } else if let Some(due) = word
+            .strip_prefix("due:")
+            .and_then(|date| parse_date(date, today))
+        {
+            task.due = Some(due);
+        } else {
+            words.push(word);
+        }
+    }
+    task.title = words.join(" ");
+    (!task.title.is_empty()).then_some(task)
+}
+
+// A date as typed: YYYY-MM-DD, today, tomorrow, or a weekday name or its
+// first three letters for the next such day
+pub fn parse_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let text = text.trim().to_lowercase();
+    match text.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return today.checked_add_days(Days::new(1)),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let weekday: chrono::Weekday = text.parse().ok()?;
+    (1..=7)
+        .filter_map(|n| today.checked_add_days(Days::new(n)))
+        .find(|date| chrono::Datelike::weekday(date) == weekday)
+}
+
+// "work, #home" as tags without the '#', in order and without repeats
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split([',', ' ']) {
+        let tag = tag.trim().trim_start_matches('#');
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Show {
+    #[default]
+    All,
+    Active,
+    Done,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    pub show: Show,
+    pub tag: Option<String>,
+    pub text: String, // Matched in the title, notes and tags, ignoring case
+}
+
+impl Filter {
+    pub fn matches(&self, task: &Task) -> bool {
+        let shown = match self.show {
+            Show::All => true,
+            Show::Active => !task.done,
+            Show::Done => task.done,
+        };
+        let text = self.text.trim().to_lowercase();
+        shown
+            && self.tag.as_ref().is_none_or(|tag| task.tags.contains(tag))
+            && (text.is_empty()
+                || task.title.to_lowercase().contains(&text)
+                || task.notes.to_lowercase().contains(&text)
+                || task
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(&text)))
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Sort {
+    #[default]
+    Manual, // The order tasks were added or dragged into
+    Due,      // Soonest first, tasks without a date last
+    Priority, // Highest first, then by due date
+}
+
+impl Sort {
+    pub const ALL: [Sort; 3] = [Sort::Manual, Sort::Due, Sort::Priority];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Sort::Manual => "Manual",
+            Sort::Due => "Due date",
+            Sort::Priority => "Priority",
+        }
+    }
+}
+
+// Indices of the tasks to list, in order
+pub fn view(tasks: &[Task], filter: &Filter, sort: Sort) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..tasks.len())
+        .filter(|&i| filter.matches(&tasks[i]))
+        .collect();
+    // Stable sorts, so ties keep the manual order
+    let by_due = |i: &usize| (tasks[*i].due.is_none(), tasks[*i].due);
+    match sort {
+        Sort::Manual => {}
+        Sort::Due => indices.sort_by_key(by_due),
+        Sort::Priority => indices.sort_by_key(|i| (Reverse(tasks[*i].priority), by_due(i))),
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn quick_entry() {
+        // A Friday
+        let today = date("2026-10-16");
+        let task = parse_quick("Call Sam #work !high due:tomorrow #work", today).unwrap();
+        assert_eq!(task.title, "Call Sam");
+        assert_eq!(task.tags, ["work"]);
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due, Some(date("2026-10-17")));
+
+        assert_eq!(parse_date("fri", today), Some(date("2026-10-23")));
+        assert_eq!(parse_date("Monday", today), Some(date("2026-10-19")));
+        assert_eq!(parse_date("2026-12-01", today), Some(date("2026-12-01")));
+        assert_eq!(parse_date("soon", today), None);
+        // Words that are not dates stay in the title
+        assert_eq!(parse_quick("due:soon", today).unwrap().title, "due:soon");
+        assert!(parse_quick(" #only !low ", today).is_none());
+        assert_eq!(
+            parse_tags("work, #home work  errands"),
+            ["work", "home", "errands"]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    fn titles(tasks: &[Task], indices: Vec<usize>) -> Vec<&str> {
+        indices.iter().map(|&i| tasks[i].title.as_str()).collect()
+    }
+
+    #[test]
+    fn filtered_and_sorted() {
+        let today = date("2026-10-16");
+        let mut list = TaskList::default();
+        for text in [
+            "a #home",
+            "b !high due:2026-10-20",
+            "c due:2026-10-18",
+            "d !high",
+        ] {
+            list.add(text, today);
+        }
+        list.tasks[2].done = true;
+        let all = Filter::default();
+        assert_eq!(
+            titles(&list.tasks, view(&list.tasks, &all, Sort::Due)),
+            ["c", "b", "a", "d"]
+        );
+        assert_eq!(
+            titles(&list.tasks, view(&list.tasks, &all, Sort::Priority)),
+            ["b", "d", "c", "a"]
+        );
+        let active = Filter {
+            show: Show::Active,
+            ..Filter::default()
+        };
+        assert_eq!(
+            titles(&list.tasks, view(&list.tasks, &active, Sort::Manual)),
+            ["a", "b", "d"]
+        );
+        let home = Filter {
+            tag: Some("home".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(
+            titles(&list.tasks, view(&list.tasks, &home, Sort::Manual)),
+            ["a"]
+        );
+
+        list.move_task(3, 0);
+        assert_eq!(
+            titles(&list.tasks, view(&list.tasks, &all, Sort::Manual)),
+            ["d", "a", "b", "c"]
+        );
+        assert_eq!(list.tasks.iter().map(|t| t.id).max(), Some(3));
+        assert!(!list.tasks[3].is_overdue(date("2026-10-19")));
+        assert!(list.tasks[2].is_overdue(date("2026-10-21")));
+    }
+}
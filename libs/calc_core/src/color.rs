@@ -0,0 +1,136 @@
+//! Color conversions between hex, RGB, HSL and HSV.
+
+// Parse "#RRGGBB", "RRGGBB" or the short "#RGB" form
+pub fn parse_hex_color(input: &str) -> Option<[u8; 3]> {
+    let hex = input.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        6 => Some([
+            u8::from_str_radix(&hex[0..2], 16).ok()?,
+            u8::from_str_radix(&hex[2..4], 16).ok()?,
+            u8::from_str_radix(&hex[4..6], 16).ok()?,
+        ]),
+        3 => {
+            let mut rgb = [0u8; 3];
+            for (channel, c) in rgb.iter_mut().zip(hex.chars()) {
+                let v = c.to_digit(16)? as u8;
+                *channel = v * 16 + v;
+            }
+            Some(rgb)
+        }
+        _ => None,
+    }
+}
+
+pub fn rgb_to_hsl(rgb: [u8; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return [0.0, 0.0, lightness * 100.0];
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    [hue, saturation * 100.0, lightness * 100.0]
+}
+
+pub fn hsl_to_rgb(hsl: [f64; 3]) -> [u8; 3] {
+    let hue = hsl[0].rem_euclid(360.0);
+    let saturation = hsl[1] / 100.0;
+    let lightness = hsl[2] / 100.0;
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = lightness - chroma / 2.0;
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r, g, b].map(|c| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|c| c as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    if delta == 0.0 {
+        return [0.0, 0.0, max * 100.0];
+    }
+    // Same hue as HSL
+    let hue = rgb_to_hsl(rgb)[0];
+    [hue, delta / max * 100.0, max * 100.0]
+}
+
+pub fn hsv_to_rgb(hsv: [f64; 3]) -> [u8; 3] {
+    let saturation = hsv[1] / 100.0;
+    let value = hsv[2] / 100.0;
+    let lightness = value * (1.0 - saturation / 2.0);
+    let hsl_saturation = if lightness == 0.0 || lightness == 1.0 {
+        0.0
+    } else {
+        (value - lightness) / lightness.min(1.0 - lightness)
+    };
+    hsl_to_rgb([hsv[0], hsl_saturation * 100.0, lightness * 100.0])
+}
+
+pub fn format_hex_color(rgb: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_parsing() {
+        assert_eq!(parse_hex_color("#FF8000"), Some([255, 128, 0]));
+        assert_eq!(parse_hex_color("f80"), Some([255, 136, 0]));
+        assert_eq!(parse_hex_color("#GG0000"), None);
+        assert_eq!(format_hex_color([18, 171, 52]), "#12AB34");
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        assert_eq!(rgb_to_hsl([255, 0, 0]), [0.0, 100.0, 50.0]);
+        for rgb in [
+            [255, 128, 0],
+            [10, 200, 30],
+            [0, 0, 0],
+            [255, 255, 255],
+            [12, 34, 250],
+        ] {
+            assert_eq!(hsl_to_rgb(rgb_to_hsl(rgb)), rgb);
+        }
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        assert_eq!(rgb_to_hsv([255, 0, 0]), [0.0, 100.0, 100.0]);
+        assert_eq!(rgb_to_hsv([128, 128, 128])[1], 0.0);
+        assert_eq!(hsv_to_rgb([120.0, 100.0, 50.0]), [0, 128, 0]);
+        for rgb in [
+            [255, 128, 0],
+            [10, 200, 30],
+            [0, 0, 0],
+            [255, 255, 255],
+            [12, 34, 250],
+        ] {
+            assert_eq!(hsv_to_rgb(rgb_to_hsv(rgb)), rgb);
+        }
+    }
+}
@@ -0,0 +1,675 @@
+//! Expression evaluator with operator precedence, functions and constants.
+
+use crate::math::{combinations, factorial, permutations, round_to, ModuloMode, RoundingMode};
+use std::f64::consts::{E, PI};
+
+/// User-defined functions callable from expressions, e.g. from a script
+pub trait CustomFunctions {
+    /// Whether `name` is defined with the given number of parameters
+    fn has_function(&self, name: &str, arity: usize) -> bool;
+    fn call(&self, name: &str, args: &[f64]) -> Result<f64, String>;
+}
+
+pub struct Evaluator<'a> {
+    pub degree_mode: bool, // true = degrees, false = radians
+    pub modulo_mode: ModuloMode,
+    pub rounding_mode: RoundingMode, // Used by round()
+    pub functions: Option<&'a dyn CustomFunctions>,
+}
+
+impl Default for Evaluator<'_> {
+    fn default() -> Self {
+        Self {
+            degree_mode: true,
+            modulo_mode: ModuloMode::Truncated,
+            rounding_mode: RoundingMode::HalfUp,
+            functions: None,
+        }
+    }
+}
+
+impl Evaluator<'_> {
+    /// Evaluate an expression such as `2 * (3 + sqrt(16)) / pi`
+    pub fn evaluate(&self, expr: &str) -> Result<f64, String> {
+        // Remove spaces
+        let mut expr = expr.replace(" ", "");
+
+        // Handle implicit multiplication: )( -> )*(
+        expr = expr.replace(")(", ")*(");
+        // Handle implicit multiplication: number( -> number*(
+        expr = self.add_implicit_multiplication(&expr);
+
+        // Try to evaluate as a simple arithmetic expression
+        self.evaluate_with_precedence(&expr)
+    }
+
+    fn add_implicit_multiplication(&self, expr: &str) -> String {
+        let mut result = String::new();
+        let chars: Vec<char> = expr.chars().collect();
+
+        for i in 0..chars.len() {
+            result.push(chars[i]);
+
+            if i + 1 < chars.len() {
+                let current = chars[i];
+                let next = chars[i + 1];
+
+                // Digits that end an identifier (e.g. a script function "f2") are not numbers
+                let in_identifier = chars[..=i]
+                    .iter()
+                    .rev()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .last()
+                    .is_some_and(|c| c.is_alphabetic() || *c == '_');
+
+                // Add * between: digit and (, ) and digit, ) and (
+                if (current.is_numeric() && next == '(' && !in_identifier)
+                    || (current == ')' && next.is_numeric())
+                    || (current == ')' && next == '(')
+                {
+                    result.push('*');
+                }
+            }
+        }
+        result
+    }
+
+    fn evaluate_with_precedence(&self, expr: &str) -> Result<f64, String> {
+        // Handle parentheses first
+        if let Some(result) = self.handle_parentheses(expr)? {
+            return Ok(result);
+        }
+
+        // Check for addition/subtraction (lowest precedence)
+        // Need to skip operators inside parentheses
+        if let Some(pos) = self.find_operator_outside_parens(expr, '+') {
+            let left = self.evaluate_with_precedence(&expr[..pos])?;
+            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+            return Ok(left + right);
+        }
+
+        if let Some(pos) = self.find_operator_outside_parens(expr, '-') {
+            if pos > 0 {
+                // Check if it's a negative sign or subtraction
+                let prev_char = expr.chars().nth(pos - 1);
+                if let Some(ch) = prev_char {
+                    if ch != '('
+                        && ch != '*'
+                        && ch != '/'
+                        && ch != '^'
+                        && ch != '+'
+                        && ch != '-'
+                        && ch != '%'
+                    {
+                        let left = self.evaluate_with_precedence(&expr[..pos])?;
+                        let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+                        return Ok(left - right);
+                    }
+                }
+            }
+        }
+
+        // Check for multiplication/division
+        if let Some(pos) = self.find_operator_outside_parens(expr, '*') {
+            let left = self.evaluate_with_precedence(&expr[..pos])?;
+            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+            return Ok(left * right);
+        }
+
+        if let Some(pos) = self.find_operator_outside_parens(expr, '/') {
+            let left = self.evaluate_with_precedence(&expr[..pos])?;
+            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+            if right == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            return Ok(left / right);
+        }
+
+        // Check for modulo
+        if let Some(pos) = self.find_operator_outside_parens(expr, '%') {
+            let left = self.evaluate_with_precedence(&expr[..pos])?;
+            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+            return Ok(self.modulo_mode.apply(left, right));
+        }
+
+        // Check for power
+        if let Some(pos) = self.find_operator_outside_parens(expr, '^') {
+            let left = self.evaluate_with_precedence(&expr[..pos])?;
+            let right = self.evaluate_with_precedence(&expr[pos + 1..])?;
+            return Ok(left.powf(right));
+        }
+
+        // Handle functions
+        if expr.starts_with("sqrt(") && expr.ends_with(")") {
+            let inner = &expr[5..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            return Ok(val.sqrt());
+        }
+
+        if expr.starts_with("sin(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            let angle = if self.degree_mode {
+                val * PI / 180.0
+            } else {
+                val
+            };
+            return Ok(angle.sin());
+        }
+
+        if expr.starts_with("cos(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            let angle = if self.degree_mode {
+                val * PI / 180.0
+            } else {
+                val
+            };
+            return Ok(angle.cos());
+        }
+
+        if expr.starts_with("tan(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            let angle = if self.degree_mode {
+                val * PI / 180.0
+            } else {
+                val
+            };
+            return Ok(angle.tan());
+        }
+
+        if expr.starts_with("log(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            return Ok(val.log10());
+        }
+
+        if expr.starts_with("ln(") && expr.ends_with(")") {
+            let inner = &expr[3..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            return Ok(val.ln());
+        }
+
+        // Angle conversions from the current angle unit: deg(pi) = 180 in RAD mode
+        if expr.starts_with("deg(") && expr.ends_with(")") {
+            let val = self.evaluate_with_precedence(&expr[4..expr.len() - 1])?;
+            return Ok(self.angle_to_radians(val).to_degrees());
+        }
+        if expr.starts_with("rad(") && expr.ends_with(")") {
+            let val = self.evaluate_with_precedence(&expr[4..expr.len() - 1])?;
+            return Ok(self.angle_to_radians(val));
+        }
+        if expr.starts_with("grad(") && expr.ends_with(")") {
+            let val = self.evaluate_with_precedence(&expr[5..expr.len() - 1])?;
+            return Ok(self.angle_to_radians(val) * 200.0 / PI);
+        }
+
+        // Series: sum(i, 1, 100, i^2) and prod(i, 1, 10, i)
+        if expr.starts_with("sum(") && expr.ends_with(")") {
+            return self.evaluate_series(&expr[4..expr.len() - 1], "sum");
+        }
+        if expr.starts_with("prod(") && expr.ends_with(")") {
+            return self.evaluate_series(&expr[5..expr.len() - 1], "prod");
+        }
+
+        // Root finding: solve(x^2 - 2, 1)
+        if expr.starts_with("solve(") && expr.ends_with(")") {
+            return self.solve(&expr[6..expr.len() - 1]);
+        }
+
+        // round(x) or round(x, decimals)
+        if expr.starts_with("round(") && expr.ends_with(")") {
+            let args = split_arguments(&expr[6..expr.len() - 1]);
+            let value = self.evaluate_with_precedence(args[0])?;
+            let decimals = match args.get(1) {
+                Some(arg) => self.evaluate_with_precedence(arg)?,
+                None => 0.0,
+            };
+            if args.len() > 2 || decimals.fract() != 0.0 || decimals.abs() > 15.0 {
+                return Err("round requires round(x) or round(x, decimals)".to_string());
+            }
+            return Ok(round_to(value, decimals as i32, self.rounding_mode));
+        }
+
+        // Handle factorial function
+        if expr.starts_with("factorial(") && expr.ends_with(")") {
+            let inner = &expr[10..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            return Ok(factorial(val));
+        }
+
+        if expr.starts_with("fact(") && expr.ends_with(")") {
+            let inner = &expr[5..expr.len() - 1];
+            let val = self.evaluate_with_precedence(inner)?;
+            return Ok(factorial(val));
+        }
+
+        // Handle nPr and nCr functions
+        if expr.starts_with("nPr(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            if let Some(comma_pos) = inner.find(',') {
+                let n = self.evaluate_with_precedence(&inner[..comma_pos])?;
+                let r = self.evaluate_with_precedence(&inner[comma_pos + 1..])?;
+                return permutations(n, r);
+            }
+            return Err("nPr requires two arguments: nPr(n,r)".to_string());
+        }
+
+        if expr.starts_with("nCr(") && expr.ends_with(")") {
+            let inner = &expr[4..expr.len() - 1];
+            if let Some(comma_pos) = inner.find(',') {
+                let n = self.evaluate_with_precedence(&inner[..comma_pos])?;
+                let r = self.evaluate_with_precedence(&inner[comma_pos + 1..])?;
+                return combinations(n, r);
+            }
+            return Err("nCr requires two arguments: nCr(n,r)".to_string());
+        }
+
+        // Handle parentheses
+        if expr.starts_with("(") && expr.ends_with(")") {
+            return self.evaluate_with_precedence(&expr[1..expr.len() - 1]);
+        }
+
+        // Handle constants
+        if expr == "pi" {
+            return Ok(PI);
+        }
+        if expr == "e" {
+            return Ok(E);
+        }
+
+        // Handle user-defined (script) functions
+        if let Some(result) = self.call_custom_function(expr)? {
+            return Ok(result);
+        }
+
+        // Angle unit suffixes convert to the current unit, so sin(30deg) works in
+        // RAD mode and sin(1rad) in DEG mode. "grad" is checked before "rad".
+        for (suffix, radians_per_unit) in [("grad", PI / 200.0), ("deg", PI / 180.0), ("rad", 1.0)]
+        {
+            if let Some(value) = expr.strip_suffix(suffix).filter(|value| !value.is_empty()) {
+                let val = self.evaluate_with_precedence(value)?;
+                return Ok(self.angle_from_radians(val * radians_per_unit));
+            }
+        }
+
+        // Try to parse as a number
+        expr.parse::<f64>()
+            .map_err(|_| format!("Invalid expression: {}", expr))
+    }
+
+    // Convert an angle in the current unit (DEG or RAD mode) to radians and back
+    fn angle_to_radians(&self, angle: f64) -> f64 {
+        if self.degree_mode {
+            angle.to_radians()
+        } else {
+            angle
+        }
+    }
+
+    fn angle_from_radians(&self, radians: f64) -> f64 {
+        if self.degree_mode {
+            radians.to_degrees()
+        } else {
+            radians
+        }
+    }
+
+    // Sum or multiply `body` for each integer value of the loop variable from
+    // `from` to `to` inclusive; an empty range gives 0 for sum and 1 for prod
+    fn evaluate_series(&self, inner: &str, name: &str) -> Result<f64, String> {
+        let usage = || format!("{} requires {}(var, from, to, expression)", name, name);
+        let [variable, from, to, body] = split_arguments(inner)[..] else {
+            return Err(usage());
+        };
+        if !is_identifier(variable) {
+            return Err(usage());
+        }
+        let from = self.evaluate_with_precedence(from)?;
+        let to = self.evaluate_with_precedence(to)?;
+        if from.fract() != 0.0 || to.fract() != 0.0 {
+            return Err(format!("{} bounds must be integers", name));
+        }
+        // Past 2^53 adding 1 no longer changes a float, so the loop variable
+        // could not count to the end
+        if from.abs() > MAX_SERIES_BOUND || to.abs() > MAX_SERIES_BOUND {
+            return Err(format!("{} bounds too large", name));
+        }
+        if to - from >= MAX_SERIES_TERMS as f64 {
+            return Err(format!(
+                "{} range too large (max {} terms)",
+                name, MAX_SERIES_TERMS
+            ));
+        }
+
+        let mut result = if name == "sum" { 0.0 } else { 1.0 };
+        let terms = if to < from { 0 } else { (to - from) as u64 + 1 };
+        for i in 0..terms {
+            let value = from + i as f64;
+            let term = self.evaluate(&substitute_variable(body, variable, value))?;
+            if name == "sum" {
+                result += term;
+            } else {
+                result *= term;
+            }
+        }
+        Ok(result)
+    }
+
+    // Find a root of `body` in x near the guess with Newton's method, using a
+    // central difference for the derivative
+    fn solve(&self, inner: &str) -> Result<f64, String> {
+        let [body, guess] = split_arguments(inner)[..] else {
+            return Err("solve requires solve(expression in x, guess)".to_string());
+        };
+        let f = |x: f64| self.evaluate(&substitute_variable(body, "x", x));
+
+        let mut x = self.evaluate_with_precedence(guess)?;
+        for _ in 0..SOLVE_MAX_ITERATIONS {
+            let fx = f(x)?;
+            if fx == 0.0 {
+                return Ok(x);
+            }
+            let h = 1e-6 * x.abs().max(1.0);
+            let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+            if slope == 0.0 || !slope.is_finite() {
+                return Err(format!("solve failed: flat slope at x = {}", x));
+            }
+
+            let step = fx / slope;
+            x -= step;
+            if !x.is_finite() {
+                return Err("solve failed: iteration diverged".to_string());
+            }
+            if step.abs() <= 1e-12 * x.abs().max(1.0) {
+                return if f(x)?.abs() < 1e-9 {
+                    Ok(x)
+                } else {
+                    Err(format!("solve failed: stalled at x = {} without a root", x))
+                };
+            }
+        }
+        Err(format!(
+            "solve did not converge after {} iterations",
+            SOLVE_MAX_ITERATIONS
+        ))
+    }
+
+    // Call a custom function if expr is exactly `name(args...)` and the name is defined
+    fn call_custom_function(&self, expr: &str) -> Result<Option<f64>, String> {
+        let Some(functions) = self.functions else {
+            return Ok(None);
+        };
+        let Some(open) = expr.find('(') else {
+            return Ok(None);
+        };
+        if !expr.ends_with(')') {
+            return Ok(None);
+        }
+        let name = &expr[..open];
+        let inner = &expr[open + 1..expr.len() - 1];
+        let arg_exprs = if inner.is_empty() {
+            Vec::new()
+        } else {
+            split_arguments(inner)
+        };
+        if !functions.has_function(name, arg_exprs.len()) {
+            return Ok(None);
+        }
+
+        let mut args = Vec::new();
+        for arg in &arg_exprs {
+            args.push(self.evaluate_with_precedence(arg)?);
+        }
+        functions.call(name, &args).map(Some)
+    }
+
+    // Find the rightmost occurrence of an operator outside of parentheses
+    fn find_operator_outside_parens(&self, expr: &str, op: char) -> Option<usize> {
+        let mut depth = 0;
+        let mut last_pos = None;
+
+        for (i, c) in expr.chars().enumerate() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {
+                    if c == op && depth == 0 {
+                        last_pos = Some(i);
+                    }
+                }
+            }
+        }
+
+        last_pos
+    }
+
+    // Handle parentheses - check if entire expression is wrapped
+    fn handle_parentheses(&self, expr: &str) -> Result<Option<f64>, String> {
+        if expr.starts_with("(") && expr.ends_with(")") {
+            // Verify matching parentheses
+            let mut depth = 0;
+            for (i, c) in expr.chars().enumerate() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                // If depth reaches 0 before the end, outer parens don't wrap everything
+                if depth == 0 && i < expr.len() - 1 {
+                    return Ok(None);
+                }
+            }
+            // The entire expression is wrapped in parentheses
+            return Ok(Some(
+                self.evaluate_with_precedence(&expr[1..expr.len() - 1])?,
+            ));
+        }
+        Ok(None)
+    }
+}
+
+// Upper bound on sum()/prod() terms so a typo cannot freeze the UI
+const MAX_SERIES_TERMS: u64 = 1_000_000;
+const MAX_SERIES_BOUND: f64 = 9_007_199_254_740_992.0; // 2^53
+
+const SOLVE_MAX_ITERATIONS: usize = 100;
+
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// Replace whole-identifier occurrences of `name` with the parenthesized value,
+// leaving longer identifiers such as "sin" untouched when name is "i"
+fn substitute_variable(expr: &str, name: &str, value: f64) -> String {
+    let replacement = format!("({})", value);
+    let mut result = String::new();
+    let mut identifier = String::new();
+    for c in expr.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            identifier.push(c);
+            continue;
+        }
+        if identifier == name {
+            result.push_str(&replacement);
+        } else {
+            result.push_str(&identifier);
+        }
+        identifier.clear();
+        result.push(c);
+    }
+    result.pop(); // The sentinel space
+    result
+}
+
+// Split function arguments on commas that are not nested inside parentheses
+pub fn split_arguments(inner: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&inner[start..]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str) -> Result<f64, String> {
+        Evaluator::default().evaluate(expr)
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(eval("2 + 3 * 4"), Ok(14.0));
+        assert_eq!(eval("(2 + 3) * 4"), Ok(20.0));
+        assert_eq!(eval("2(3)(4)"), Ok(24.0));
+        assert_eq!(eval("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(eval("2 ^ 10"), Ok(1024.0));
+        assert_eq!(eval("15 % 7"), Ok(1.0));
+        assert_eq!(eval("-7 % 3"), Ok(-1.0));
+
+        let floored = Evaluator {
+            modulo_mode: ModuloMode::Floored,
+            ..Default::default()
+        };
+        assert_eq!(floored.evaluate("-7 % 3"), Ok(2.0));
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_eq!(eval("sqrt(16)"), Ok(4.0));
+        assert_eq!(eval("fact(5)"), Ok(120.0));
+        assert_eq!(eval("nCr(49,6)").map(f64::round), Ok(13983816.0));
+        assert_eq!(eval("nPr(5,2)"), Ok(20.0));
+        assert!((eval("sin(30)").unwrap_or(0.0) - 0.5).abs() < 1e-12);
+        assert_eq!(eval("pi"), Ok(PI));
+
+        let radians = Evaluator {
+            degree_mode: false,
+            ..Default::default()
+        };
+        assert_eq!(radians.evaluate("cos(0)"), Ok(1.0));
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(eval("round(2.5)"), Ok(3.0));
+        assert_eq!(eval("round(2.675, 2)"), Ok(2.68));
+        let half_even = Evaluator {
+            rounding_mode: RoundingMode::HalfEven,
+            ..Default::default()
+        };
+        assert_eq!(half_even.evaluate("round(2.5) + round(0.125, 2)"), Ok(2.12));
+        assert!(eval("round(1, 0.5)").is_err());
+    }
+
+    #[test]
+    fn angle_units() {
+        let close = |a: Result<f64, String>, b: f64| (a.unwrap_or(f64::NAN) - b).abs() < 1e-12;
+        let radians = Evaluator {
+            degree_mode: false,
+            ..Default::default()
+        };
+        assert!(close(eval("sin(30deg)"), 0.5));
+        assert!(close(radians.evaluate("sin(30deg)"), 0.5));
+        assert!(close(eval("cos(pirad)"), -1.0));
+        assert!(close(eval("sin(100grad)"), 1.0));
+        assert!(close(eval("(pi/2)rad + 90deg"), 180.0));
+        assert!(close(radians.evaluate("180deg"), PI));
+        assert!(close(radians.evaluate("deg(pi)"), 180.0));
+        assert!(close(eval("rad(180)"), PI));
+        assert!(close(eval("grad(90)"), 100.0));
+        assert!(close(eval("deg(45)"), 45.0));
+        assert!(eval("deg").is_err());
+    }
+
+    #[test]
+    fn series() {
+        assert_eq!(eval("sum(i, 1, 100, i^2)"), Ok(338350.0));
+        assert_eq!(eval("prod(i, 1, 10, i)"), Ok(3628800.0));
+        assert_eq!(eval("sum(k, 0, 3, 2 * k + 1)"), Ok(16.0));
+        assert_eq!(eval("sum(i, -2, 2, i)"), Ok(0.0));
+        assert_eq!(eval("sum(i, 1, 3, sum(j, 1, i, j))"), Ok(10.0));
+        assert_eq!(eval("1 + prod(i, 5, 1, i)"), Ok(2.0));
+        assert!((eval("sum(n, 0, 20, 1/fact(n))").unwrap_or(0.0) - E).abs() < 1e-12);
+        assert!(eval("sum(i, 1, 10)").is_err());
+        assert!(eval("sum(i, 0.5, 10, i)").is_err());
+        assert!(eval("sum(i, 1, 1e9, i)").is_err());
+        // Bounds where counting up by 1 would stall
+        assert!(eval("sum(x, 1e16, 1e16+5, x)").is_err());
+    }
+
+    #[test]
+    fn solve() {
+        let evaluator = Evaluator {
+            degree_mode: false,
+            ..Default::default()
+        };
+        let root = |expr: &str| evaluator.evaluate(expr).unwrap_or(f64::NAN);
+        assert!((root("solve(x^2 - 2, 1)") - 2f64.sqrt()).abs() < 1e-12);
+        assert!((root("solve(x^2 - 2, -1)") + 2f64.sqrt()).abs() < 1e-12);
+        assert!((root("solve(cos(x) - x, 0)") - 0.739_085_133_215_160_7).abs() < 1e-12);
+        assert!((root("solve(sin(x), 3)") - PI).abs() < 1e-12);
+        assert_eq!(root("solve(x - 5, 5)"), 5.0);
+        assert!(evaluator.evaluate("solve(x^2 + 1, 1)").is_err());
+        assert!(evaluator.evaluate("solve(x^2 + 1, 0)").is_err());
+        assert!(evaluator.evaluate("solve(x - 1)").is_err());
+    }
+
+    #[test]
+    fn variable_substitution() {
+        assert_eq!(
+            substitute_variable("sin(i)*i2+i", "i", 3.0),
+            "sin((3))*i2+(3)"
+        );
+        assert_eq!(substitute_variable("x^2-x", "x", -0.5), "(-0.5)^2-(-0.5)");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("1/0").is_err());
+        assert!(eval("foo").is_err());
+        assert!(eval("nPr(3)").is_err());
+    }
+
+    struct Doubler;
+
+    impl CustomFunctions for Doubler {
+        fn has_function(&self, name: &str, arity: usize) -> bool {
+            name == "double2" && arity == 1
+        }
+
+        fn call(&self, _name: &str, args: &[f64]) -> Result<f64, String> {
+            Ok(args[0] * 2.0)
+        }
+    }
+
+    #[test]
+    fn custom_functions() {
+        let evaluator = Evaluator {
+            functions: Some(&Doubler),
+            ..Default::default()
+        };
+        assert_eq!(evaluator.evaluate("double2(3 + 1) + 1"), Ok(9.0));
+        assert!(evaluator.evaluate("double2(1, 2)").is_err());
+    }
+
+    #[test]
+    fn argument_splitting() {
+        assert_eq!(split_arguments("1,(2,3),4"), vec!["1", "(2,3)", "4"]);
+    }
+}
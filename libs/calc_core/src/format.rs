@@ -0,0 +1,609 @@
+//! Number formatting and parsing: display formats, number bases,
+//! hex-float notation and English number words.
+
+use crate::math::{round_to, RoundingMode};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayFormat {
+    Regular,     // Standard format
+    Fixed,       // Fixed decimal places
+    Scientific,  // Scientific notation
+    Engineering, // Engineering notation (exponent is multiple of 3)
+    Triads,      // Thousands separators (commas)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberBase {
+    Dec,
+    Bin,
+    Oct,
+    Hex,
+    HexFloat, // C99 hex-float notation, keeps the fraction
+}
+
+impl NumberBase {
+    pub fn label(self) -> &'static str {
+        match self {
+            NumberBase::Dec => "DEC",
+            NumberBase::Bin => "BIN",
+            NumberBase::Oct => "OCT",
+            NumberBase::Hex => "HEX",
+            NumberBase::HexFloat => "HEXF",
+        }
+    }
+
+    fn radix(self) -> u32 {
+        match self {
+            NumberBase::Bin => 2,
+            NumberBase::Oct => 8,
+            NumberBase::Dec => 10,
+            NumberBase::Hex | NumberBase::HexFloat => 16,
+        }
+    }
+
+    /// Parse display text written in this base. BIN/OCT/HEX accept a
+    /// fractional part, e.g. "0.1" in BIN is 0.5.
+    pub fn parse(self, text: &str) -> Option<f64> {
+        match self {
+            NumberBase::HexFloat => parse_hex_float(text),
+            NumberBase::Dec => text.replace(',', "").parse().ok(),
+            _ => {
+                let radix = self.radix();
+                let Some((int_part, frac_part)) = text.split_once('.') else {
+                    // Patterns with the top bit set are read as 64-bit two's complement
+                    return i64::from_str_radix(text, radix)
+                        .ok()
+                        .or_else(|| u64::from_str_radix(text, radix).ok().map(|v| v as i64))
+                        .map(|v| v as f64);
+                };
+                let (negative, int_part) = match int_part.strip_prefix('-') {
+                    Some(rest) => (true, rest),
+                    None => (false, int_part),
+                };
+                let mut value = if int_part.is_empty() {
+                    0.0
+                } else {
+                    u64::from_str_radix(int_part, radix).ok()? as f64
+                };
+                let mut scale = 1.0 / radix as f64;
+                for c in frac_part.chars() {
+                    value += c.to_digit(radix)? as f64 * scale;
+                    scale /= radix as f64;
+                }
+                Some(if negative { -value } else { value })
+            }
+        }
+    }
+
+    /// Format a value in this base with at most `fraction_digits` digits after
+    /// the point (BIN/OCT/HEX truncate, DEC rounds; trailing zeros are dropped).
+    /// Integers, and every value when `fraction_digits` is 0, are shown as i64.
+    pub fn format(self, value: f64, fraction_digits: usize) -> String {
+        if self == NumberBase::HexFloat {
+            return format_hex_float(value);
+        }
+        let int_value = value as i64;
+        let fraction = value.fract().abs();
+        if fraction == 0.0 || fraction_digits == 0 || !value.is_finite() {
+            return self.format_integer(int_value);
+        }
+        if self == NumberBase::Dec {
+            let text = format!("{:.*}", fraction_digits, value);
+            return text.trim_end_matches('0').trim_end_matches('.').to_string();
+        }
+
+        // Sign and magnitude, since a two's complement pattern has no fraction
+        let radix = self.radix();
+        let mut text = String::new();
+        if value < 0.0 {
+            text.push('-');
+        }
+        text.push_str(&self.format_integer(int_value.abs()));
+
+        // Multiplying by a power of two is exact, so every digit is exact too
+        let mut digits = String::new();
+        let mut rest = fraction;
+        for _ in 0..fraction_digits {
+            if rest == 0.0 {
+                break;
+            }
+            rest *= radix as f64;
+            let digit = rest.trunc() as u32;
+            rest -= digit as f64;
+            digits.extend(char::from_digit(digit, radix).map(|c| c.to_ascii_uppercase()));
+        }
+        let digits = digits.trim_end_matches('0');
+        if !digits.is_empty() {
+            text.push('.');
+            text.push_str(digits);
+        }
+        text
+    }
+
+    /// Format an unsigned bit pattern, e.g. the result of NOT at a word size
+    pub fn format_bits(self, value: u64) -> String {
+        match self {
+            NumberBase::Bin => format!("{:b}", value),
+            NumberBase::Oct => format!("{:o}", value),
+            NumberBase::Hex => format!("{:X}", value),
+            NumberBase::HexFloat => format_hex_float(value as f64),
+            NumberBase::Dec => value.to_string(),
+        }
+    }
+
+    fn format_integer(self, value: i64) -> String {
+        match self {
+            NumberBase::Bin => format!("{:b}", value),
+            NumberBase::Oct => format!("{:o}", value),
+            NumberBase::Hex | NumberBase::HexFloat => format!("{:X}", value),
+            NumberBase::Dec => value.to_string(),
+        }
+    }
+}
+
+pub fn format_number(num: f64) -> String {
+    if num.is_infinite() {
+        return "Error: Overflow".to_string();
+    }
+    if num.is_nan() {
+        return "Error: Invalid".to_string();
+    }
+
+    // Format with up to 18 significant digits
+    let formatted = format!("{:.18}", num);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    // Handle very large or very small numbers with scientific notation
+    if num.abs() >= 1e15 || (num.abs() < 1e-15 && num != 0.0) {
+        format!("{:.12e}", num)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Format for display; `rounding` applies to the Fixed format
+pub fn format_with_style(
+    num: f64,
+    display_format: DisplayFormat,
+    rounding: RoundingMode,
+) -> String {
+    if num.is_infinite() {
+        return "Error: Overflow".to_string();
+    }
+    if num.is_nan() {
+        return "Error: Invalid".to_string();
+    }
+
+    match display_format {
+        DisplayFormat::Regular => format_number(num),
+        DisplayFormat::Fixed => {
+            // Fixed 6 decimal places
+            format!("{:.6}", round_to(num, 6, rounding))
+        }
+        DisplayFormat::Scientific => {
+            // Always scientific notation
+            format!("{:.12e}", num)
+        }
+        DisplayFormat::Engineering => {
+            // Engineering notation (exponent is multiple of 3)
+            if num == 0.0 {
+                return "0.000000000000e0".to_string();
+            }
+
+            let abs_num = num.abs();
+            let sign = if num < 0.0 { "-" } else { "" };
+
+            // Calculate the base-10 exponent
+            let exponent = abs_num.log10().floor() as i32;
+
+            // Round down to nearest multiple of 3
+            let eng_exponent = (exponent / 3) * 3;
+
+            // Calculate mantissa (should be between 1 and 999.999...)
+            let mantissa = abs_num / 10_f64.powi(eng_exponent);
+
+            format!(
+                "{}{}e{}",
+                sign,
+                format!("{:.9}", mantissa)
+                    .trim_end_matches('0')
+                    .trim_end_matches('.'),
+                eng_exponent
+            )
+        }
+        DisplayFormat::Triads => {
+            // Format with thousands separators
+            let formatted = format!("{:.18}", num);
+            let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+            if let Some(dot_pos) = trimmed.find('.') {
+                let integer_part = &trimmed[..dot_pos];
+                let decimal_part = &trimmed[dot_pos..];
+                format!("{}{}", add_thousands_separators(integer_part), decimal_part)
+            } else {
+                add_thousands_separators(trimmed)
+            }
+        }
+    }
+}
+
+/// Format an exact non-negative integer given as decimal digits. Scientific and
+/// engineering formats round to the same precision as floats; every other
+/// format shows all digits with thousands separators.
+pub fn format_big_integer(digits: &str, display_format: DisplayFormat) -> String {
+    let digits = digits.trim_start_matches('0');
+    let digits = if digits.is_empty() { "0" } else { digits };
+
+    match display_format {
+        DisplayFormat::Scientific => {
+            let (rounded, exponent) = round_digits(digits, 13);
+            format!("{}.{}e{}", &rounded[..1], &rounded[1..], exponent)
+        }
+        DisplayFormat::Engineering => {
+            let (rounded, exponent) = round_digits(digits, 10);
+            let eng_exponent = exponent / 3 * 3;
+            let (int_part, frac_part) = rounded.split_at(exponent - eng_exponent + 1);
+            let frac_part = frac_part.trim_end_matches('0');
+            if frac_part.is_empty() {
+                format!("{}e{}", int_part, eng_exponent)
+            } else {
+                format!("{}.{}e{}", int_part, frac_part, eng_exponent)
+            }
+        }
+        _ => add_thousands_separators(digits),
+    }
+}
+
+// Round decimal digits (no leading zeros) to `significant` digits, padding with
+// zeros. Returns the digits and the base-10 exponent of the first one.
+fn round_digits(digits: &str, significant: usize) -> (String, usize) {
+    let mut exponent = digits.len() - 1;
+    let mut kept: Vec<u8> = digits.bytes().take(significant).collect();
+    kept.resize(significant, b'0');
+
+    if digits
+        .as_bytes()
+        .get(significant)
+        .is_some_and(|&d| d >= b'5')
+    {
+        // Propagate the carry; 999.. rolls over to 100.. with a larger exponent
+        let mut i = significant;
+        loop {
+            if i == 0 {
+                kept.insert(0, b'1');
+                kept.pop();
+                exponent += 1;
+                break;
+            }
+            i -= 1;
+            if kept[i] == b'9' {
+                kept[i] = b'0';
+            } else {
+                kept[i] += 1;
+                break;
+            }
+        }
+    }
+    (kept.iter().map(|&d| d as char).collect(), exponent)
+}
+
+/// Insert commas between groups of three digits in an integer string
+pub fn add_thousands_separators(num_str: &str) -> String {
+    let is_negative = num_str.starts_with('-');
+    let num_str = num_str.strip_prefix('-').unwrap_or(num_str);
+
+    let len = num_str.len();
+    let mut formatted = String::new();
+    for (i, ch) in num_str.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(ch);
+    }
+
+    if is_negative {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+// Format a float in C99 hex-float notation, e.g. 12.0 -> "0x1.8p3"
+pub fn format_hex_float(num: f64) -> String {
+    if num.is_nan() {
+        return "nan".to_string();
+    }
+    let sign = if num.is_sign_negative() { "-" } else { "" };
+    if num.is_infinite() {
+        return format!("{}inf", sign);
+    }
+    if num == 0.0 {
+        return format!("{}0x0p0", sign);
+    }
+
+    let bits = num.to_bits();
+    let biased_exp = ((bits >> 52) & 0x7FF) as i32;
+    let mantissa = bits & 0x000F_FFFF_FFFF_FFFF;
+
+    // Subnormals have no implicit leading 1 and a fixed exponent
+    let (lead, exponent) = if biased_exp == 0 {
+        (0, -1022)
+    } else {
+        (1, biased_exp - 1023)
+    };
+
+    let fraction = format!("{:013x}", mantissa);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}0x{}p{}", sign, lead, exponent)
+    } else {
+        format!("{}0x{}.{}p{}", sign, lead, fraction, exponent)
+    }
+}
+
+// Parse hex-float notation such as "0x1.8p3", "-0x.4p-1" or "1F.8" (prefix and exponent optional)
+pub fn parse_hex_float(input: &str) -> Option<f64> {
+    let input = input.trim();
+    let (negative, rest) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, input.strip_prefix('+').unwrap_or(input)),
+    };
+    let rest = rest
+        .strip_prefix("0x")
+        .or_else(|| rest.strip_prefix("0X"))
+        .unwrap_or(rest);
+
+    let (digits, exponent) = match rest.find(['p', 'P']) {
+        Some(pos) => (&rest[..pos], rest[pos + 1..].parse::<i32>().ok()?),
+        None => (rest, 0),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+
+    let mut value = 0.0_f64;
+    for c in int_part.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in frac_part.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+
+    let value = value * 2_f64.powi(exponent);
+    Some(if negative { -value } else { value })
+}
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+// Short-scale names for each group of three digits
+const SCALES: [&str; 7] = [
+    "",
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+];
+
+// Spell out 0..=999
+fn hundreds_to_words(n: u64) -> String {
+    let mut parts = Vec::new();
+    if n >= 100 {
+        parts.push(format!("{} hundred", ONES[(n / 100) as usize]));
+    }
+    let rest = n % 100;
+    if rest >= 20 {
+        if rest.is_multiple_of(10) {
+            parts.push(TENS[(rest / 10) as usize].to_string());
+        } else {
+            parts.push(format!(
+                "{}-{}",
+                TENS[(rest / 10) as usize],
+                ONES[(rest % 10) as usize]
+            ));
+        }
+    } else if rest > 0 || parts.is_empty() {
+        parts.push(ONES[rest as usize].to_string());
+    }
+    parts.join(" ")
+}
+
+// Spell out a number in English, e.g. 1234.5 -> "one thousand two hundred thirty-four point five"
+pub fn number_to_words(num: f64) -> String {
+    if !num.is_finite() {
+        return "not a number".to_string();
+    }
+    if num.abs() >= 1e21 {
+        return "too large to spell out".to_string();
+    }
+
+    // Shortest round-trip representation, so float noise does not show up as extra decimals
+    let text = num.abs().to_string();
+    let (int_text, frac_text) = text.split_once('.').unwrap_or((&text, ""));
+    let mut integer: u128 = int_text.parse().unwrap_or(0);
+
+    let mut groups = Vec::new();
+    let mut scale = 0;
+    while integer > 0 {
+        let group = (integer % 1000) as u64;
+        if group > 0 {
+            let words = hundreds_to_words(group);
+            groups.push(if SCALES[scale].is_empty() {
+                words
+            } else {
+                format!("{} {}", words, SCALES[scale])
+            });
+        }
+        integer /= 1000;
+        scale += 1;
+    }
+    groups.reverse();
+
+    let mut words = if groups.is_empty() {
+        "zero".to_string()
+    } else {
+        groups.join(" ")
+    };
+    if !frac_text.is_empty() {
+        let digits: Vec<&str> = frac_text
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .map(|d| ONES[d as usize])
+            .collect();
+        words = format!("{} point {}", words, digits.join(" "));
+    }
+    if num < 0.0 {
+        words = format!("minus {}", words);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_format_trims_and_switches_to_scientific() {
+        assert_eq!(format_number(12.5), "12.5");
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(1e16), "1.000000000000e16");
+        assert_eq!(format_number(f64::INFINITY), "Error: Overflow");
+    }
+
+    #[test]
+    fn styled_formats() {
+        let half_up = RoundingMode::HalfUp;
+        assert_eq!(
+            format_with_style(1.5, DisplayFormat::Fixed, half_up),
+            "1.500000"
+        );
+        assert_eq!(
+            format_with_style(0.0000125, DisplayFormat::Fixed, half_up),
+            "0.000013"
+        );
+        assert_eq!(
+            format_with_style(0.0000125, DisplayFormat::Fixed, RoundingMode::HalfEven),
+            "0.000012"
+        );
+        assert_eq!(
+            format_with_style(123456.0, DisplayFormat::Engineering, half_up),
+            "123.456e3"
+        );
+        assert_eq!(
+            format_with_style(-1234567.25, DisplayFormat::Triads, half_up),
+            "-1,234,567.25"
+        );
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(add_thousands_separators("999"), "999");
+        assert_eq!(add_thousands_separators("1000"), "1,000");
+        assert_eq!(add_thousands_separators("-123456789"), "-123,456,789");
+    }
+
+    #[test]
+    fn big_integers() {
+        let digits = "15511210043330985984000000"; // 25!
+        assert_eq!(
+            format_big_integer(digits, DisplayFormat::Regular),
+            "15,511,210,043,330,985,984,000,000"
+        );
+        assert_eq!(
+            format_big_integer(digits, DisplayFormat::Scientific),
+            "1.551121004333e25"
+        );
+        assert_eq!(
+            format_big_integer(digits, DisplayFormat::Engineering),
+            "15.51121004e24"
+        );
+        assert_eq!(
+            format_big_integer("99999999999999", DisplayFormat::Scientific),
+            "1.000000000000e14"
+        );
+        assert_eq!(
+            format_big_integer("0", DisplayFormat::Scientific),
+            "0.000000000000e0"
+        );
+    }
+
+    #[test]
+    fn hex_float_round_trip() {
+        assert_eq!(format_hex_float(12.0), "0x1.8p3");
+        assert_eq!(format_hex_float(-0.5), "-0x1p-1");
+        for value in [0.1, 1e300, -7.25, f64::MIN_POSITIVE / 4.0] {
+            assert_eq!(parse_hex_float(&format_hex_float(value)), Some(value));
+        }
+        assert_eq!(parse_hex_float("1F.8"), Some(31.5));
+        assert_eq!(parse_hex_float("0xzz"), None);
+    }
+
+    #[test]
+    fn bases_parse_and_format() {
+        assert_eq!(NumberBase::Hex.parse("FF"), Some(255.0));
+        assert_eq!(NumberBase::Bin.format(5.0, 8), "101");
+        assert_eq!(NumberBase::Dec.parse("1,234.5"), Some(1234.5));
+        assert_eq!(NumberBase::HexFloat.format(12.0, 8), "0x1.8p3");
+    }
+
+    #[test]
+    fn fractional_bases() {
+        assert_eq!(NumberBase::Bin.format(0.5, 8), "0.1");
+        assert_eq!(NumberBase::Bin.format(5.75, 8), "101.11");
+        assert_eq!(NumberBase::Hex.format(-255.5, 8), "-FF.8");
+        assert_eq!(NumberBase::Oct.format(0.1, 4), "0.0631");
+        assert_eq!(NumberBase::Bin.format(5.75, 0), "101");
+        assert_eq!(NumberBase::Dec.format(1.23456, 3), "1.235");
+        assert_eq!(NumberBase::Bin.parse("101.11"), Some(5.75));
+        assert_eq!(NumberBase::Hex.parse("-FF.8"), Some(-255.5));
+        assert_eq!(NumberBase::Oct.parse("0.9"), None);
+        assert_eq!(
+            NumberBase::Hex.parse(&NumberBase::Hex.format(-5.0, 8)),
+            Some(-5.0)
+        );
+        assert_eq!(NumberBase::Hex.format_bits(u64::MAX), "FFFFFFFFFFFFFFFF");
+        for value in [0.5, 0.1, -0.375, 1234.0625] {
+            let text = NumberBase::Hex.format(value, 16);
+            assert_eq!(NumberBase::Hex.parse(&text), Some(value));
+        }
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(number_to_words(0.0), "zero");
+        assert_eq!(number_to_words(101.0), "one hundred one");
+        assert_eq!(
+            number_to_words(1234567.0),
+            "one million two hundred thirty-four thousand five hundred sixty-seven"
+        );
+        assert_eq!(number_to_words(-12.3), "minus twelve point three");
+    }
+}
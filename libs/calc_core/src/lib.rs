@@ -0,0 +1,19 @@
+//! Calculation engine for the scientific programmer calculator.
+//!
+//! Everything here is independent of the GUI: expression evaluation, number
+//! formatting, statistics, probability, business math and programmer tools.
+//! `calc_app` is a thin egui layer on top of this crate.
+
+pub mod color;
+pub mod eval;
+pub mod format;
+pub mod math;
+pub mod programmer;
+pub mod script;
+pub mod stats;
+pub mod subnet;
+pub mod uncertain;
+
+pub use eval::{CustomFunctions, Evaluator};
+pub use format::{format_number, DisplayFormat, NumberBase};
+pub use math::{ModuloMode, Operation, RoundingMode};
@@ -0,0 +1,558 @@
+//! Binary operations, factorials, probability and business math.
+
+pub use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+// Longest factorial, nPr or nCr result worked out digit for digit; a hundred
+// thousand digits take seconds
+const MAX_EXACT_DIGITS: f64 = 5_000.0;
+
+// Largest n whose factorial is shown in scientific notation; past it f64
+// cannot hold log10(n!) closely enough for the leading digits
+const MAX_SCIENTIFIC_FACTORIAL: f64 = 1e8;
+
+// Digits of f64::MAX, past which a result is infinite as a float
+const MAX_F64_DIGITS: f64 = 309.0;
+
+// Integers up to 2^53 convert to f64 without losing precision
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Root,
+    Modulo(ModuloMode),
+    Permutation,    // nPr
+    Combination,    // nCr
+    SellFromMarkup, // cost MU% -> sell price
+    SellFromMargin, // cost MG% -> sell price
+    CostFromMarkup, // sell MU% -> cost
+    CostFromMargin, // sell MG% -> cost
+}
+
+impl Operation {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Subtract => "−",
+            Operation::Multiply => "×",
+            Operation::Divide => "÷",
+            Operation::Power => "^",
+            Operation::Root => "y-Root",
+            Operation::Modulo(_) => "mod",
+            Operation::Permutation => "nPr",
+            Operation::Combination => "nCr",
+            Operation::SellFromMarkup => "MU→Sell",
+            Operation::SellFromMargin => "MG→Sell",
+            Operation::CostFromMarkup => "MU→Cost",
+            Operation::CostFromMargin => "MG→Cost",
+        }
+    }
+
+    /// The expression operator for this operation, if it has an infix form
+    pub fn infix(&self) -> Option<char> {
+        match self {
+            Operation::Add => Some('+'),
+            Operation::Subtract => Some('-'),
+            Operation::Multiply => Some('*'),
+            Operation::Divide => Some('/'),
+            Operation::Power => Some('^'),
+            Operation::Modulo(_) => Some('%'),
+            _ => None,
+        }
+    }
+
+    /// Exact integer result for nPr and nCr; `None` for every other operation
+    pub fn apply_exact(&self, first: f64, second: f64) -> Option<Result<BigUint, String>> {
+        match self {
+            Operation::Permutation => Some(exact_permutations(first, second)),
+            Operation::Combination => Some(exact_combinations(first, second)),
+            _ => None,
+        }
+    }
+
+    /// Apply the operation to `first op second`
+    pub fn apply(&self, first: f64, second: f64) -> Result<f64, String> {
+        match self {
+            Operation::Add => Ok(first + second),
+            Operation::Subtract => Ok(first - second),
+            Operation::Multiply => Ok(first * second),
+            Operation::Divide => {
+                if second != 0.0 {
+                    Ok(first / second)
+                } else {
+                    Err("Div by 0".to_string())
+                }
+            }
+            Operation::Power => Ok(first.powf(second)),
+            Operation::Root => {
+                if second != 0.0 {
+                    Ok(first.powf(1.0 / second))
+                } else {
+                    Err("Root 0".to_string())
+                }
+            }
+            Operation::Modulo(mode) => Ok(mode.apply(first, second)),
+            Operation::Permutation => permutations(first, second),
+            Operation::Combination => combinations(first, second),
+            Operation::SellFromMarkup => Ok(first * (1.0 + second / 100.0)),
+            Operation::SellFromMargin => {
+                if second < 100.0 {
+                    Ok(first / (1.0 - second / 100.0))
+                } else {
+                    Err("Margin >= 100%".to_string())
+                }
+            }
+            Operation::CostFromMarkup => {
+                if second > -100.0 {
+                    Ok(first / (1.0 + second / 100.0))
+                } else {
+                    Err("Markup <= -100%".to_string())
+                }
+            }
+            Operation::CostFromMargin => Ok(first * (1.0 - second / 100.0)),
+        }
+    }
+}
+
+/// How `mod` treats negative operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ModuloMode {
+    #[default]
+    Truncated, // Sign follows the dividend, like Rust's `%`: -7 mod 3 = -1
+    Floored, // Sign follows the divisor (mathematical modulo): -7 mod 3 = 2
+}
+
+impl ModuloMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            ModuloMode::Truncated => "Truncated",
+            ModuloMode::Floored => "Floored",
+        }
+    }
+
+    pub fn apply(self, dividend: f64, divisor: f64) -> f64 {
+        let remainder = dividend % divisor;
+        match self {
+            ModuloMode::Truncated => remainder,
+            ModuloMode::Floored => {
+                if remainder != 0.0 && (remainder < 0.0) != (divisor < 0.0) {
+                    remainder + divisor
+                } else {
+                    remainder
+                }
+            }
+        }
+    }
+}
+
+/// How values are rounded to a number of decimal places
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RoundingMode {
+    #[default]
+    HalfUp, // Ties away from zero: 2.5 -> 3, -2.5 -> -3
+    HalfEven,   // Ties to the even digit (banker's rounding): 2.5 -> 2, 3.5 -> 4
+    TowardZero, // Truncate: 2.9 -> 2, -2.9 -> -2
+}
+
+impl RoundingMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "Half up",
+            RoundingMode::HalfEven => "Half even",
+            RoundingMode::TowardZero => "Toward zero",
+        }
+    }
+}
+
+/// Round to `decimals` places. Works on the shortest decimal representation of
+/// the value, so 2.675 is treated as the tie it is written as even though the
+/// nearest f64 is slightly below it.
+pub fn round_to(num: f64, decimals: i32, mode: RoundingMode) -> f64 {
+    if !num.is_finite() || num == 0.0 {
+        return num;
+    }
+
+    // "2.675e0" -> digits "2675" with the first digit at 10^0
+    let text = format!("{:e}", num.abs());
+    let Some((mantissa, exponent)) = text.split_once('e') else {
+        return num;
+    };
+    let Ok(exponent) = exponent.parse::<i32>() else {
+        return num;
+    };
+    let digits: Vec<u32> = mantissa.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    // Number of leading digits that survive rounding
+    let keep = exponent + 1 + decimals;
+    if keep >= digits.len() as i32 {
+        return num;
+    }
+    if keep < 0 {
+        // Less than half a unit in the last place
+        return 0.0_f64.copysign(num);
+    }
+
+    let keep = keep as usize;
+    let kept = digits[..keep]
+        .iter()
+        .fold(0.0_f64, |acc, &d| acc * 10.0 + d as f64);
+    let first_dropped = digits[keep];
+    let rest_nonzero = digits[keep + 1..].iter().any(|&d| d != 0);
+    let round_up = match mode {
+        RoundingMode::TowardZero => false,
+        RoundingMode::HalfUp => first_dropped >= 5,
+        RoundingMode::HalfEven => {
+            first_dropped > 5 || (first_dropped == 5 && (rest_nonzero || kept % 2.0 == 1.0))
+        }
+    };
+    let kept = if round_up { kept + 1.0 } else { kept };
+
+    let value = if decimals >= 0 {
+        kept / 10_f64.powi(decimals)
+    } else {
+        kept * 10_f64.powi(-decimals)
+    };
+    value.copysign(num)
+}
+
+/// The simplest fraction within `tolerance` of `value`, as (numerator,
+/// denominator), found by walking the continued-fraction convergents. Returns
+/// None for non-finite values or when no convergent with terms up to 2^53 is
+/// close enough.
+pub fn to_fraction(value: f64, tolerance: f64) -> Option<(i64, i64)> {
+    if !value.is_finite() {
+        return None;
+    }
+    let target = value.abs();
+    let (mut h, mut h_prev) = (1.0_f64, 0.0_f64);
+    let (mut k, mut k_prev) = (0.0_f64, 1.0_f64);
+    let mut x = target;
+    loop {
+        let a = x.floor();
+        (h, h_prev) = (a * h + h_prev, h);
+        (k, k_prev) = (a * k + k_prev, k);
+        if h > MAX_SAFE_INTEGER as f64 || k > MAX_SAFE_INTEGER as f64 {
+            return None;
+        }
+        let fraction = x - a;
+        if (target - h / k).abs() <= tolerance || fraction == 0.0 {
+            let numerator = if value < 0.0 { -h } else { h };
+            return Some((numerator as i64, k as i64));
+        }
+        x = 1.0 / fraction;
+    }
+}
+
+// Calculate factorial using f64 to handle large values (up to ~170)
+pub fn factorial(n: f64) -> f64 {
+    if n < 0.0 || n.fract() != 0.0 {
+        return f64::NAN; // Factorial only defined for non-negative integers
+    }
+    if n > 170.0 {
+        return f64::INFINITY; // Overflow protection
+    }
+    let mut result = 1.0;
+    for i in 2..=(n as i64) {
+        result *= i as f64;
+    }
+    result
+}
+
+// Calculate exact factorials using BigUint (beyond the f64 limit of 170)
+pub fn big_factorial(n: f64) -> Result<BigUint, String> {
+    if n < 0.0 || n.fract() != 0.0 {
+        return Err("Invalid (not a non-negative integer)".to_string());
+    }
+    // n! is the falling product nPn
+    if permutation_digits(n as u64, n as u64, MAX_EXACT_DIGITS) > MAX_EXACT_DIGITS {
+        return Err(too_large());
+    }
+
+    let mut result: BigUint = One::one();
+    for i in 2..=(n as u64) {
+        result *= i;
+    }
+    Ok(result)
+}
+
+// n! in scientific notation to six significant digits, for factorials too
+// long for `big_factorial`. The digits come from log10(n!) by Stirling's
+// series, which is that close from n = 10 on
+pub fn factorial_scientific(n: f64) -> Result<String, String> {
+    if n < 0.0 || n.fract() != 0.0 {
+        return Err("Invalid (not a non-negative integer)".to_string());
+    }
+    if n > MAX_SCIENTIFIC_FACTORIAL {
+        return Err(format!("Too large (max {})", MAX_SCIENTIFIC_FACTORIAL));
+    }
+    let log10 = if n < 10.0 {
+        factorial(n).log10()
+    } else {
+        let ln = n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n)
+            - 1.0 / (360.0 * n.powi(3));
+        ln / std::f64::consts::LN_10
+    };
+    let mut exponent = log10.floor();
+    let mut mantissa = 10f64.powf(log10 - exponent);
+    // 9.999996 rounds up to the next power of ten
+    if mantissa >= 9.999_995 {
+        mantissa = 1.0;
+        exponent += 1.0;
+    }
+    Ok(format!("{:.5}e{}", mantissa, exponent))
+}
+
+/// Convert an exact integer to f64; huge values become infinity
+pub fn big_to_f64(value: &BigUint) -> f64 {
+    value.to_f64().unwrap_or(f64::INFINITY)
+}
+
+/// The value as f64 if the conversion is exact (at most 2^53)
+pub fn exact_f64(value: &BigUint) -> Option<f64> {
+    value
+        .to_u64()
+        .filter(|&v| v <= MAX_SAFE_INTEGER)
+        .map(|v| v as f64)
+}
+
+fn probability_args(n: f64, r: f64, name: &str) -> Result<(u64, u64), String> {
+    if n < 0.0 || r < 0.0 || r > n || n.fract() != 0.0 || r.fract() != 0.0 {
+        return Err(format!("Invalid {} arguments", name));
+    }
+    if n > MAX_SAFE_INTEGER as f64 {
+        return Err("n too large".to_string());
+    }
+    Ok((n as u64, r as u64))
+}
+
+// Roughly how many digits nPr has, from the falling product
+// n * (n-1) * ... * (n-r+1). Every factor is at least 1, so the count only
+// grows and stops once past `limit`
+fn permutation_digits(n: u64, r: u64, limit: f64) -> f64 {
+    let mut digits = 0.0;
+    for i in 0..r {
+        digits += ((n - i) as f64).log10();
+        if digits > limit {
+            break;
+        }
+    }
+    digits
+}
+
+// The same for nCr, from the factors (n-i)/(i+1), which are at least 1 while
+// i < r <= n/2
+fn combination_digits(n: u64, r: u64, limit: f64) -> f64 {
+    let mut digits = 0.0;
+    for i in 0..r.min(n - r) {
+        digits += ((n - i) as f64 / (i + 1) as f64).log10();
+        if digits > limit {
+            break;
+        }
+    }
+    digits
+}
+
+fn too_large() -> String {
+    format!("Too large (over {} digits)", MAX_EXACT_DIGITS)
+}
+
+// nPr = n! / (n-r)!, computed as the falling product n * (n-1) * ... * (n-r+1)
+pub fn exact_permutations(n: f64, r: f64) -> Result<BigUint, String> {
+    let (n, r) = probability_args(n, r, "nPr")?;
+    if permutation_digits(n, r, MAX_EXACT_DIGITS) > MAX_EXACT_DIGITS {
+        return Err(too_large());
+    }
+    let mut result: BigUint = One::one();
+    for i in 0..r {
+        result *= n - i;
+    }
+    Ok(result)
+}
+
+// nCr = (n * (n-1) * ... * (n-r+1)) / (r * (r-1) * ... * 1), dividing at each
+// step so intermediate values stay small; every partial result is itself a
+// binomial coefficient, so the division is exact
+pub fn exact_combinations(n: f64, r: f64) -> Result<BigUint, String> {
+    let (n, r) = probability_args(n, r, "nCr")?;
+    if combination_digits(n, r, MAX_EXACT_DIGITS) > MAX_EXACT_DIGITS {
+        return Err(too_large());
+    }
+    let r = r.min(n - r); // Use smaller of r and n-r
+    let mut result: BigUint = One::one();
+    for i in 0..r {
+        result = result * (n - i) / (i + 1);
+    }
+    Ok(result)
+}
+
+// As floats: infinity once past f64's range, without multiplying it all out
+pub fn permutations(n: f64, r: f64) -> Result<f64, String> {
+    let (whole_n, whole_r) = probability_args(n, r, "nPr")?;
+    if permutation_digits(whole_n, whole_r, MAX_F64_DIGITS) > MAX_F64_DIGITS {
+        return Ok(f64::INFINITY);
+    }
+    exact_permutations(n, r).map(|value| big_to_f64(&value))
+}
+
+pub fn combinations(n: f64, r: f64) -> Result<f64, String> {
+    let (whole_n, whole_r) = probability_args(n, r, "nCr")?;
+    if combination_digits(whole_n, whole_r, MAX_F64_DIGITS) > MAX_F64_DIGITS {
+        return Ok(f64::INFINITY);
+    }
+    exact_combinations(n, r).map(|value| big_to_f64(&value))
+}
+
+// Business Functions
+pub fn add_tax(net: f64, rate: f64) -> f64 {
+    net * (1.0 + rate / 100.0)
+}
+
+pub fn remove_tax(gross: f64, rate: f64) -> f64 {
+    gross / (1.0 + rate / 100.0)
+}
+
+// Convert a markup percentage (on cost) to the equivalent margin percentage (on sell price)
+pub fn markup_to_margin(markup: f64) -> f64 {
+    markup / (100.0 + markup) * 100.0
+}
+
+// Convert a margin percentage (on sell price) to the equivalent markup percentage (on cost)
+pub fn margin_to_markup(margin: f64) -> f64 {
+    if margin < 100.0 {
+        margin / (100.0 - margin) * 100.0
+    } else {
+        f64::NAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn basic_operations() {
+        assert_eq!(Operation::Add.apply(2.0, 3.0), Ok(5.0));
+        assert_eq!(Operation::Root.apply(27.0, 3.0), Ok(3.0));
+        assert_eq!(
+            Operation::Modulo(ModuloMode::Truncated).apply(-7.0, 3.0),
+            Ok(-1.0)
+        );
+        assert!(Operation::Divide.apply(1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn floored_modulo() {
+        let floored = ModuloMode::Floored;
+        assert_eq!(floored.apply(-7.0, 3.0), 2.0);
+        assert_eq!(floored.apply(7.0, -3.0), -2.0);
+        assert_eq!(floored.apply(-7.0, -3.0), -1.0);
+        assert_eq!(floored.apply(-6.0, 3.0), 0.0);
+        assert_eq!(floored.apply(-5.5, 2.0), 0.5);
+    }
+
+    #[test]
+    fn rounding_modes() {
+        use RoundingMode::*;
+        assert_eq!(round_to(2.675, 2, HalfUp), 2.68);
+        assert_eq!(round_to(2.5, 0, HalfUp), 3.0);
+        assert_eq!(round_to(-2.5, 0, HalfUp), -3.0);
+        assert_eq!(round_to(2.5, 0, HalfEven), 2.0);
+        assert_eq!(round_to(3.5, 0, HalfEven), 4.0);
+        assert_eq!(round_to(2.5000001, 0, HalfEven), 3.0);
+        assert_eq!(round_to(0.125, 2, HalfEven), 0.12);
+        assert_eq!(round_to(-2.9, 0, TowardZero), -2.0);
+        assert_eq!(round_to(9.995, 2, HalfUp), 10.0);
+        assert_eq!(round_to(1234.0, -2, HalfUp), 1200.0);
+        assert_eq!(round_to(0.0004, 3, HalfUp), 0.0);
+        assert_eq!(round_to(0.0005, 3, HalfUp), 0.001);
+        assert_eq!(round_to(1.5, 3, HalfEven), 1.5);
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(to_fraction(0.333333, 1e-6), Some((1, 3)));
+        assert_eq!(to_fraction(0.75, 1e-9), Some((3, 4)));
+        assert_eq!(to_fraction(-2.5, 1e-9), Some((-5, 2)));
+        assert_eq!(to_fraction(5.0, 1e-9), Some((5, 1)));
+        assert_eq!(to_fraction(0.0, 1e-9), Some((0, 1)));
+        assert_eq!(to_fraction(PI, 2e-3), Some((22, 7)));
+        assert_eq!(to_fraction(PI, 1e-6), Some((355, 113)));
+        assert_eq!(to_fraction(f64::NAN, 1e-6), None);
+        assert_eq!(to_fraction(1e300, 1e-6), None);
+    }
+
+    #[test]
+    fn factorials() {
+        assert_eq!(factorial(5.0), 120.0);
+        assert!(factorial(-1.0).is_nan());
+        assert!(factorial(171.0).is_infinite());
+        assert_eq!(big_factorial(5.0).as_ref().map(exact_f64), Ok(Some(120.0)));
+        assert_eq!(big_factorial(25.0).as_ref().map(exact_f64), Ok(None));
+        assert_eq!(
+            big_factorial(25.0).map(|n| n.to_string()).as_deref(),
+            Ok("15511210043330985984000000")
+        );
+        // Past 5000 digits only the leading ones are worked out
+        assert!(big_factorial(1000.0).is_ok());
+        assert_eq!(big_factorial(100_000.0), Err(too_large()));
+        assert_eq!(factorial_scientific(1000.0).as_deref(), Ok("4.02387e2567"));
+        assert_eq!(
+            factorial_scientific(100_000.0).as_deref(),
+            Ok("2.82423e456573")
+        );
+        assert_eq!(factorial_scientific(5.0).as_deref(), Ok("1.20000e2"));
+        assert!(factorial_scientific(1e9).is_err());
+        assert!(factorial_scientific(-1.0).is_err());
+    }
+
+    #[test]
+    fn probability() {
+        assert_eq!(permutations(5.0, 2.0), Ok(20.0));
+        assert_eq!(combinations(49.0, 6.0), Ok(13983816.0));
+        assert!(combinations(3.0, 4.0).is_err());
+        assert_eq!(permutations(171.0, 1.0), Ok(171.0));
+        assert_eq!(permutations(1000.0, 500.0), Ok(f64::INFINITY));
+        // Overflowing results are not multiplied out
+        assert_eq!(combinations(100_000.0, 50_000.0), Ok(f64::INFINITY));
+        assert_eq!(permutations(1e15, 1e15), Ok(f64::INFINITY));
+        assert_eq!(combinations(1e15, 1.0), Ok(1e15));
+    }
+
+    #[test]
+    fn exact_probability() {
+        assert_eq!(
+            exact_combinations(200.0, 100.0)
+                .map(|n| n.to_string())
+                .as_deref(),
+            Ok("90548514656103281165404177077484163874504589675413336841320")
+        );
+        assert_eq!(exact_permutations(30.0, 30.0), big_factorial(30.0));
+        assert_eq!(
+            exact_combinations(1e15, 1e15 - 2.0)
+                .map(|n| n.to_string())
+                .as_deref(),
+            Ok("499999999999999500000000000000")
+        );
+        assert!(exact_permutations(1e16, 1.0).is_err());
+        assert_eq!(exact_combinations(100_000.0, 50_000.0), Err(too_large()));
+        assert!(exact_permutations(100_000.0, 100_000.0).is_err());
+    }
+
+    #[test]
+    fn business() {
+        assert!((Operation::SellFromMarkup.apply(80.0, 25.0).unwrap_or(0.0) - 100.0).abs() < 1e-9);
+        assert!((Operation::SellFromMargin.apply(80.0, 20.0).unwrap_or(0.0) - 100.0).abs() < 1e-9);
+        assert!((Operation::CostFromMarkup.apply(100.0, 25.0).unwrap_or(0.0) - 80.0).abs() < 1e-9);
+        assert!((remove_tax(add_tax(50.0, 20.0), 20.0) - 50.0).abs() < 1e-9);
+        assert!((markup_to_margin(25.0) - 20.0).abs() < 1e-9);
+        assert!((margin_to_markup(20.0) - 25.0).abs() < 1e-9);
+        assert!(Operation::SellFromMargin.apply(10.0, 100.0).is_err());
+    }
+}
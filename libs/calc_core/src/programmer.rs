@@ -0,0 +1,268 @@
+//! Programmer tools: bit manipulation, checksums and character lookup.
+
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Register width used to show NOT and two's complement as bit patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WordSize {
+    Byte,
+    Word,
+    DWord,
+    #[default]
+    QWord,
+}
+
+impl WordSize {
+    pub const ALL: [WordSize; 4] = [
+        WordSize::Byte,
+        WordSize::Word,
+        WordSize::DWord,
+        WordSize::QWord,
+    ];
+
+    pub fn bits(self) -> u32 {
+        match self {
+            WordSize::Byte => 8,
+            WordSize::Word => 16,
+            WordSize::DWord => 32,
+            WordSize::QWord => 64,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WordSize::Byte => "8-bit",
+            WordSize::Word => "16-bit",
+            WordSize::DWord => "32-bit",
+            WordSize::QWord => "64-bit",
+        }
+    }
+
+    pub fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits())
+    }
+}
+
+/// Bitwise complement as an unsigned pattern of the word size: NOT 0 at 8-bit = 0xFF
+pub fn bitwise_not(value: i64, word: WordSize) -> u64 {
+    !(value as u64) & word.mask()
+}
+
+/// Two's complement (negation) as an unsigned pattern: 1 at 8-bit = 0xFF
+pub fn twos_complement(value: i64, word: WordSize) -> u64 {
+    (value as u64).wrapping_neg() & word.mask()
+}
+
+// Bit manipulation on the integer part of a value
+pub fn shift_left(value: i64) -> i64 {
+    value << 1
+}
+
+pub fn shift_right(value: i64) -> i64 {
+    value >> 1
+}
+
+pub fn rotate_left(value: u32) -> u32 {
+    value.rotate_left(1)
+}
+
+pub fn rotate_right(value: u32) -> u32 {
+    value.rotate_right(1)
+}
+
+pub fn count_bits(value: u64) -> u32 {
+    value.count_ones()
+}
+
+// Byte representation used for hashing the current value: integers as
+// big-endian i64 (two's complement), everything else as big-endian IEEE 754 f64
+pub fn value_bytes(value: f64) -> (Vec<u8>, &'static str) {
+    if value.fract() == 0.0 && value >= i64::MIN as f64 && value <= i64::MAX as f64 {
+        ((value as i64).to_be_bytes().to_vec(), "i64 big-endian")
+    } else {
+        (value.to_be_bytes().to_vec(), "f64 big-endian")
+    }
+}
+
+// Parse a pasted hex string such as "DE AD be ef" or "0xCAFE" into bytes
+pub fn parse_hex_bytes(input: &str) -> Option<Vec<u8>> {
+    let digits: String = input
+        .trim()
+        .trim_start_matches("0x")
+        .trim_start_matches("0X")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+        .collect();
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn checksums(bytes: &[u8]) -> [(&'static str, String); 3] {
+    [
+        ("CRC32", format!("{:08x}", crc32fast::hash(bytes))),
+        ("MD5", to_hex(&Md5::digest(bytes))),
+        ("SHA-256", to_hex(&Sha256::digest(bytes))),
+    ]
+}
+
+// Names of the C0 control characters and DEL, which have no Unicode character name
+const CONTROL_NAMES: [&str; 33] = [
+    "NULL",
+    "START OF HEADING",
+    "START OF TEXT",
+    "END OF TEXT",
+    "END OF TRANSMISSION",
+    "ENQUIRY",
+    "ACKNOWLEDGE",
+    "BELL",
+    "BACKSPACE",
+    "CHARACTER TABULATION",
+    "LINE FEED",
+    "LINE TABULATION",
+    "FORM FEED",
+    "CARRIAGE RETURN",
+    "SHIFT OUT",
+    "SHIFT IN",
+    "DATA LINK ESCAPE",
+    "DEVICE CONTROL ONE",
+    "DEVICE CONTROL TWO",
+    "DEVICE CONTROL THREE",
+    "DEVICE CONTROL FOUR",
+    "NEGATIVE ACKNOWLEDGE",
+    "SYNCHRONOUS IDLE",
+    "END OF TRANSMISSION BLOCK",
+    "CANCEL",
+    "END OF MEDIUM",
+    "SUBSTITUTE",
+    "ESCAPE",
+    "FILE SEPARATOR",
+    "GROUP SEPARATOR",
+    "RECORD SEPARATOR",
+    "UNIT SEPARATOR",
+    "DELETE",
+];
+
+pub fn char_name(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0x1F => CONTROL_NAMES[code as usize].to_string(),
+        0x7F => CONTROL_NAMES[32].to_string(),
+        _ => unicode_names2::name(ch)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| "(unnamed)".to_string()),
+    }
+}
+
+// Control characters are shown as their Unicode "control picture" symbols
+pub fn printable_char(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0x1F => char::from_u32(0x2400 + code).unwrap_or(ch).to_string(),
+        0x7F => "\u{2421}".to_string(),
+        0x80..=0x9F => "·".to_string(),
+        _ => ch.to_string(),
+    }
+}
+
+// Resolve a lookup query: a single literal character, U+XXXX, 0x/0o/0b
+// prefixed or decimal code points, or an exact Unicode character name. A lone
+// digit is the character itself, so "7" is '7' rather than U+0007
+pub fn lookup_char(query: &str) -> Option<char> {
+    let mut chars = query.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => return None,
+        (Some(ch), None) => return Some(ch),
+        _ => {}
+    }
+    let lower = query.to_lowercase();
+    let code = if let Some(hex) = lower.strip_prefix("u+") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(oct) = lower.strip_prefix("0o") {
+        u32::from_str_radix(oct, 8).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else if query.chars().all(|c| c.is_ascii_digit()) {
+        query.parse::<u32>().ok()
+    } else {
+        None
+    };
+    if let Some(code) = code {
+        return char::from_u32(code);
+    }
+    unicode_names2::character(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_operations() {
+        assert_eq!(shift_left(5), 10);
+        assert_eq!(shift_right(-4), -2);
+        assert_eq!(rotate_left(0x8000_0000), 1);
+        assert_eq!(rotate_right(1), 0x8000_0000);
+        assert_eq!(count_bits(0xFF), 8);
+    }
+
+    #[test]
+    fn word_size_patterns() {
+        assert_eq!(bitwise_not(0, WordSize::Byte), 0xFF);
+        assert_eq!(bitwise_not(0x0F, WordSize::Word), 0xFFF0);
+        assert_eq!(bitwise_not(0, WordSize::QWord), u64::MAX);
+        assert_eq!(twos_complement(1, WordSize::Byte), 0xFF);
+        assert_eq!(twos_complement(-1, WordSize::DWord), 1);
+        assert_eq!(twos_complement(0x80, WordSize::Byte), 0x80);
+        assert_eq!(twos_complement(0, WordSize::QWord), 0);
+    }
+
+    #[test]
+    fn checksums_of_known_input() {
+        let bytes = b"abc";
+        let [crc, md5, sha] = checksums(bytes);
+        assert_eq!(crc.1, "352441c2");
+        assert_eq!(md5.1, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            sha.1,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn value_and_hex_bytes() {
+        assert_eq!(value_bytes(1.0).0, vec![0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(value_bytes(0.5).1, "f64 big-endian");
+        assert_eq!(
+            parse_hex_bytes("DE AD be:ef"),
+            Some(vec![0xDE, 0xAD, 0xBE, 0xEF])
+        );
+        assert_eq!(parse_hex_bytes("ABC"), None);
+        assert_eq!(parse_hex_bytes("é1"), None);
+    }
+
+    #[test]
+    fn character_lookup() {
+        assert_eq!(lookup_char("U+263A"), Some('☺'));
+        assert_eq!(lookup_char("0x41"), Some('A'));
+        assert_eq!(lookup_char("0b1100001"), Some('a'));
+        assert_eq!(lookup_char("65"), Some('A'));
+        assert_eq!(lookup_char("7"), Some('7'));
+        assert_eq!(lookup_char("U+7"), Some('\u{7}'));
+        assert_eq!(lookup_char("é"), Some('é'));
+        assert_eq!(lookup_char("SNOWMAN"), Some('☃'));
+        assert_eq!(char_name('\n'), "LINE FEED");
+        assert_eq!(char_name('A'), "LATIN CAPITAL LETTER A");
+        assert_eq!(printable_char('\0'), "\u{2400}");
+    }
+}
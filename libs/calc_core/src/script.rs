@@ -0,0 +1,147 @@
+//! Rhai scripting for user-defined functions.
+
+use crate::eval::CustomFunctions;
+use rhai::{Dynamic, Engine, Scope, AST};
+
+pub const DEFAULT_SCRIPT: &str =
+    "// Functions defined here can be called from the expression field,
+// e.g. hyp(3, 4) or fib(20).
+
+fn hyp(a, b) {
+    sqrt(a * a + b * b)
+}
+
+fn fib(n) {
+    let a = 0.0;
+    let b = 1.0;
+    for i in 0..n.to_int() {
+        let t = a + b;
+        a = b;
+        b = t;
+    }
+    a
+}
+";
+
+// Upper bound on script operations so a runaway loop cannot freeze the UI
+const SCRIPT_MAX_OPERATIONS: u64 = 10_000_000;
+
+/// A compiled user script whose functions can be called from expressions
+pub struct Script {
+    engine: Engine,
+    ast: Option<AST>,
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+        Self { engine, ast: None }
+    }
+}
+
+impl Script {
+    /// Compile `source`, returning the signatures of the functions it defines.
+    /// On error the previously compiled functions are dropped.
+    pub fn compile(&mut self, source: &str) -> Result<Vec<String>, String> {
+        match self.engine.compile(source) {
+            Ok(ast) => {
+                let mut names: Vec<String> = ast
+                    .iter_functions()
+                    .map(|f| format!("{}({})", f.name, f.params.join(", ")))
+                    .collect();
+                names.sort();
+                self.ast = Some(ast);
+                Ok(names)
+            }
+            Err(e) => {
+                self.ast = None;
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Run the script's top-level statements and return the final value
+    pub fn run(&self) -> Result<f64, String> {
+        let Some(ast) = &self.ast else {
+            return Err("script not compiled".to_string());
+        };
+        self.engine
+            .eval_ast::<Dynamic>(ast)
+            .map_err(|e| e.to_string())
+            .and_then(dynamic_to_f64)
+    }
+}
+
+impl CustomFunctions for Script {
+    fn has_function(&self, name: &str, arity: usize) -> bool {
+        self.ast.as_ref().is_some_and(|ast| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == arity)
+        })
+    }
+
+    fn call(&self, name: &str, args: &[f64]) -> Result<f64, String> {
+        let Some(ast) = &self.ast else {
+            return Err(format!("{}: script not compiled", name));
+        };
+        let args: Vec<Dynamic> = args.iter().map(|&arg| Dynamic::from_float(arg)).collect();
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, name, args)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        dynamic_to_f64(result)
+    }
+}
+
+fn dynamic_to_f64(value: Dynamic) -> Result<f64, String> {
+    if let Ok(float) = value.as_float() {
+        Ok(float)
+    } else if let Ok(int) = value.as_int() {
+        Ok(int as f64)
+    } else if let Ok(flag) = value.as_bool() {
+        Ok(if flag { 1.0 } else { 0.0 })
+    } else {
+        Err(format!(
+            "script returned {} instead of a number",
+            value.type_name()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Evaluator;
+
+    #[test]
+    fn default_script_functions() {
+        let mut script = Script::default();
+        let names = script.compile(DEFAULT_SCRIPT).unwrap();
+        assert_eq!(names, vec!["fib(n)", "hyp(a, b)"]);
+        let evaluator = Evaluator {
+            functions: Some(&script),
+            ..Default::default()
+        };
+        assert_eq!(evaluator.evaluate("hyp(3, 4)"), Ok(5.0));
+        assert_eq!(evaluator.evaluate("fib(20) + 1"), Ok(6766.0));
+    }
+
+    #[test]
+    fn run_and_errors() {
+        let mut script = Script::default();
+        script.compile("let x = 6; x * 7").unwrap();
+        assert_eq!(script.run(), Ok(42.0));
+        script.compile(r#""text""#).unwrap();
+        assert!(script.run().is_err());
+        assert!(script.compile("fn broken( {").is_err());
+        assert!(!script.has_function("hyp", 2));
+    }
+
+    #[test]
+    fn runaway_loop_is_stopped() {
+        let mut script = Script::default();
+        script.compile("loop {}").unwrap();
+        assert!(script.run().is_err());
+    }
+}
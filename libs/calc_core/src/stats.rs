@@ -0,0 +1,48 @@
+//! Statistics over a dataset. Variance and standard deviation are population
+//! statistics (divide by n).
+
+pub fn sum(data: &[f64]) -> Option<f64> {
+    if data.is_empty() {
+        None
+    } else {
+        Some(data.iter().sum())
+    }
+}
+
+pub fn mean(data: &[f64]) -> Option<f64> {
+    sum(data).map(|sum| sum / data.len() as f64)
+}
+
+/// Requires at least two values
+pub fn variance(data: &[f64]) -> Option<f64> {
+    if data.len() < 2 {
+        return None;
+    }
+    let mean = mean(data)?;
+    Some(data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / data.len() as f64)
+}
+
+/// Requires at least two values
+pub fn std_dev(data: &[f64]) -> Option<f64> {
+    variance(data).map(f64::sqrt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics() {
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(sum(&data), Some(40.0));
+        assert_eq!(mean(&data), Some(5.0));
+        assert_eq!(variance(&data), Some(4.0));
+        assert_eq!(std_dev(&data), Some(2.0));
+    }
+
+    #[test]
+    fn too_little_data() {
+        assert_eq!(mean(&[]), None);
+        assert_eq!(variance(&[1.0]), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! IPv4 subnet calculations.
+
+use std::net::Ipv4Addr;
+
+pub struct SubnetInfo {
+    pub network: Ipv4Addr,
+    pub broadcast: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub wildcard: Ipv4Addr,
+    pub first_host: Ipv4Addr,
+    pub last_host: Ipv4Addr,
+    pub host_count: u64,
+}
+
+impl SubnetInfo {
+    pub fn new(address: Ipv4Addr, prefix: u32) -> Self {
+        let mask = if prefix == 0 {
+            0
+        } else {
+            u32::MAX << (32 - prefix)
+        };
+        let network = u32::from(address) & mask;
+        let broadcast = network | !mask;
+
+        // /31 point-to-point links (RFC 3021) and /32 host routes have no
+        // separate network and broadcast addresses
+        let (first_host, last_host, host_count) = match prefix {
+            32 => (network, network, 1),
+            31 => (network, broadcast, 2),
+            _ => (network + 1, broadcast - 1, (1u64 << (32 - prefix)) - 2),
+        };
+
+        Self {
+            network: network.into(),
+            broadcast: broadcast.into(),
+            netmask: mask.into(),
+            wildcard: (!mask).into(),
+            first_host: first_host.into(),
+            last_host: last_host.into(),
+            host_count,
+        }
+    }
+}
+
+// Accept a prefix length ("24", "/24") or a contiguous dotted netmask ("255.255.255.0")
+pub fn parse_prefix(input: &str) -> Option<u32> {
+    let input = input.trim().trim_start_matches('/');
+    if let Ok(prefix) = input.parse::<u32>() {
+        return (prefix <= 32).then_some(prefix);
+    }
+    let mask = u32::from(input.parse::<Ipv4Addr>().ok()?);
+    // Valid netmasks are a run of ones followed by a run of zeros
+    (mask.leading_ones() + mask.trailing_zeros() == 32).then_some(mask.leading_ones())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_c_network() {
+        let info = SubnetInfo::new(Ipv4Addr::new(192, 168, 1, 10), 24);
+        assert_eq!(info.network, Ipv4Addr::new(192, 168, 1, 0));
+        assert_eq!(info.broadcast, Ipv4Addr::new(192, 168, 1, 255));
+        assert_eq!(info.wildcard, Ipv4Addr::new(0, 0, 0, 255));
+        assert_eq!(info.first_host, Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(info.last_host, Ipv4Addr::new(192, 168, 1, 254));
+        assert_eq!(info.host_count, 254);
+    }
+
+    #[test]
+    fn point_to_point_and_host_routes() {
+        assert_eq!(
+            SubnetInfo::new(Ipv4Addr::new(10, 0, 0, 1), 31).host_count,
+            2
+        );
+        assert_eq!(
+            SubnetInfo::new(Ipv4Addr::new(10, 0, 0, 1), 32).host_count,
+            1
+        );
+        assert_eq!(
+            SubnetInfo::new(Ipv4Addr::new(10, 0, 0, 1), 0).host_count,
+            (1u64 << 32) - 2
+        );
+    }
+
+    #[test]
+    fn prefix_parsing() {
+        assert_eq!(parse_prefix("/24"), Some(24));
+        assert_eq!(parse_prefix("255.255.240.0"), Some(20));
+        assert_eq!(parse_prefix("255.0.255.0"), None);
+        assert_eq!(parse_prefix("33"), None);
+    }
+}
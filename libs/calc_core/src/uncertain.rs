@@ -0,0 +1,160 @@
+//! Values with an uncertainty (`12.3 ± 0.2`) and first-order error
+//! propagation. Errors of the two operands are assumed independent and are
+//! added in quadrature.
+
+use crate::format::format_number;
+use crate::math::Operation;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uncertain {
+    pub value: f64,
+    pub sigma: f64, // Standard uncertainty, never negative
+}
+
+impl Uncertain {
+    pub fn new(value: f64, sigma: f64) -> Self {
+        Self {
+            value,
+            sigma: sigma.abs(),
+        }
+    }
+
+    pub fn exact(value: f64) -> Self {
+        Self::new(value, 0.0)
+    }
+
+    /// Raise to a power: σ² = (b·a^(b-1)·σa)² + (a^b·ln a·σb)²
+    pub fn powf(self, exponent: Uncertain) -> Uncertain {
+        let value = self.value.powf(exponent.value);
+        let from_base = exponent.value * self.value.powf(exponent.value - 1.0) * self.sigma;
+        // An exact exponent contributes nothing, which also keeps negative bases usable
+        let from_exponent = if exponent.sigma == 0.0 {
+            0.0
+        } else {
+            value * self.value.ln() * exponent.sigma
+        };
+        Uncertain::new(value, from_base.hypot(from_exponent))
+    }
+
+    /// Apply a binary operation, or `None` if it does not propagate uncertainty
+    pub fn apply(self, op: Operation, other: Uncertain) -> Option<Result<Uncertain, String>> {
+        let result = match op {
+            Operation::Add => self + other,
+            Operation::Subtract => self - other,
+            Operation::Multiply => self * other,
+            Operation::Divide => {
+                if other.value == 0.0 {
+                    return Some(Err("Div by 0".to_string()));
+                }
+                self / other
+            }
+            Operation::Power => self.powf(other),
+            _ => return None,
+        };
+        Some(Ok(result))
+    }
+}
+
+impl Add for Uncertain {
+    type Output = Uncertain;
+
+    fn add(self, other: Uncertain) -> Uncertain {
+        Uncertain::new(self.value + other.value, self.sigma.hypot(other.sigma))
+    }
+}
+
+impl Sub for Uncertain {
+    type Output = Uncertain;
+
+    fn sub(self, other: Uncertain) -> Uncertain {
+        Uncertain::new(self.value - other.value, self.sigma.hypot(other.sigma))
+    }
+}
+
+impl Mul for Uncertain {
+    type Output = Uncertain;
+
+    // σ² = (b·σa)² + (a·σb)²
+    fn mul(self, other: Uncertain) -> Uncertain {
+        let sigma = (other.value * self.sigma).hypot(self.value * other.sigma);
+        Uncertain::new(self.value * other.value, sigma)
+    }
+}
+
+impl Div for Uncertain {
+    type Output = Uncertain;
+
+    // σ² = (σa / b)² + (a·σb / b²)²
+    fn div(self, other: Uncertain) -> Uncertain {
+        let sigma = (self.sigma / other.value)
+            .hypot(self.value * other.sigma / (other.value * other.value));
+        Uncertain::new(self.value / other.value, sigma)
+    }
+}
+
+/// Format as "value ± sigma", with the value shown to the precision of the
+/// uncertainty's second significant digit. Exact values use `format_number`.
+pub fn format_uncertain(u: Uncertain) -> String {
+    if u.sigma == 0.0 || !u.value.is_finite() || !u.sigma.is_finite() {
+        return format_number(u.value);
+    }
+    let decimals = (1 - u.sigma.log10().floor() as i32).clamp(0, 15) as usize;
+    format!("{:.*} ± {:.*}", decimals, u.value, decimals, u.sigma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn sums_add_in_quadrature() {
+        let sum = Uncertain::new(10.0, 0.3) + Uncertain::new(5.0, 0.4);
+        assert_eq!(sum, Uncertain::new(15.0, 0.5));
+        let difference = Uncertain::new(10.0, 0.3) - Uncertain::new(5.0, 0.4);
+        assert_eq!(difference, Uncertain::new(5.0, 0.5));
+    }
+
+    #[test]
+    fn products_and_quotients() {
+        // Relative errors of 3% and 4% combine to 5%
+        let product = Uncertain::new(100.0, 3.0) * Uncertain::new(2.0, 0.08);
+        assert!(close(product.value, 200.0) && close(product.sigma, 10.0));
+        let quotient = Uncertain::new(100.0, 3.0) / Uncertain::new(2.0, 0.08);
+        assert!(close(quotient.value, 50.0) && close(quotient.sigma, 2.5));
+        assert_eq!(
+            Uncertain::exact(1.0).apply(Operation::Divide, Uncertain::exact(0.0)),
+            Some(Err("Div by 0".to_string()))
+        );
+    }
+
+    #[test]
+    fn powers() {
+        let square = Uncertain::new(3.0, 0.1).powf(Uncertain::exact(2.0));
+        assert!(close(square.value, 9.0) && close(square.sigma, 0.6));
+        let cube = Uncertain::new(-2.0, 0.1).powf(Uncertain::exact(3.0));
+        assert!(close(cube.value, -8.0) && close(cube.sigma, 1.2));
+        let exp = Uncertain::exact(std::f64::consts::E).powf(Uncertain::new(1.0, 0.5));
+        assert!(close(exp.sigma, std::f64::consts::E * 0.5));
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_uncertain(Uncertain::new(12.3, 0.2)), "12.30 ± 0.20");
+        assert_eq!(format_uncertain(Uncertain::new(1234.5, 25.0)), "1234 ± 25");
+        assert_eq!(
+            format_uncertain(Uncertain::new(0.012345, 0.00012)),
+            "0.01235 ± 0.00012"
+        );
+        assert_eq!(format_uncertain(Uncertain::exact(2.5)), "2.5");
+        assert_eq!(
+            Uncertain::exact(1.0)
+                .apply(Operation::Modulo(Default::default()), Uncertain::exact(1.0)),
+            None
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! Names read out by screen readers (through AccessKit) for widgets whose own
+//! text does not say what they do, like "⚙" or "÷".
+
+use egui::{Response, WidgetInfo, WidgetType};
+
+pub trait Labeled {
+    // Name a button for screen readers, and show the name on hover
+    fn labeled(self, name: &str) -> Self;
+    // The same for another kind of widget
+    fn labeled_as(self, widget_type: WidgetType, name: &str) -> Self;
+}
+
+impl Labeled for Response {
+    fn labeled(self, name: &str) -> Self {
+        self.labeled_as(WidgetType::Button, name)
+    }
+
+    fn labeled_as(self, widget_type: WidgetType, name: &str) -> Self {
+        let enabled = self.enabled();
+        self.widget_info(|| WidgetInfo::labeled(widget_type, enabled, name));
+        self.on_hover_text(name)
+    }
+}
@@ -0,0 +1,210 @@
+//! Open, save and folder dialogs that start where the user last was.
+//!
+//! Each kind of file has its filter in [`FileType`], and [`FileDialogs`]
+//! remembers the folder last used for each kind, so saving a PNG opens
+//! where the last PNG went even after exporting a CSV elsewhere. It also
+//! keeps the app's recent folders, offered by [`FileDialogs::recent_menu`].
+//! It lives in [`CommonSettings`](crate::settings::CommonSettings), so every
+//! app saves it with its settings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Recent folders offered
+pub const MAX_RECENT_DIRS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum FileType {
+    RichText,
+    PlainText,
+    Html,
+    Csv,
+    Json,
+    Png,
+    GimpPalette,
+    Font,
+    WordList,
+    Any,    // Attachments and the like, with no filter
+    Folder, // For the folder dialog
+}
+
+impl FileType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileType::RichText => "Rich Text",
+            FileType::PlainText => "Plain Text",
+            FileType::Html => "HTML",
+            FileType::Csv => "CSV",
+            FileType::Json => "JSON",
+            FileType::Png => "PNG image",
+            FileType::GimpPalette => "GIMP palette",
+            FileType::Font => "Font Files",
+            FileType::WordList => "Word list",
+            FileType::Any => "All files",
+            FileType::Folder => "Folder",
+        }
+    }
+
+    // The first is added to saved files typed without one
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            FileType::RichText => &["rtxt"],
+            FileType::PlainText => &["txt"],
+            FileType::Html => &["html", "htm"],
+            FileType::Csv => &["csv"],
+            FileType::Json => &["json"],
+            FileType::Png => &["png"],
+            FileType::GimpPalette => &["gpl"],
+            FileType::Font => &["ttf", "otf"],
+            FileType::WordList => &["txt", "dic"],
+            FileType::Any | FileType::Folder => &[],
+        }
+    }
+}
+
+// Where the dialogs start, kept with the app's settings
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileDialogs {
+    last_dirs: BTreeMap<FileType, PathBuf>,
+    recent_dirs: Vec<PathBuf>, // Most recent first
+    #[serde(skip)]
+    start_in: Option<PathBuf>, // Chosen from the recent folders for the next dialog
+}
+
+impl FileDialogs {
+    // Pick a file of one of `types`; the first decides the starting folder
+    pub fn open(&mut self, types: &[FileType]) -> Option<PathBuf> {
+        let path = self.dialog(types).pick_file()?;
+        self.remember(types, &path);
+        Some(path)
+    }
+
+    pub fn open_many(&mut self, types: &[FileType]) -> Vec<PathBuf> {
+        let paths = self.dialog(types).pick_files().unwrap_or_default();
+        if let Some(path) = paths.first() {
+            self.remember(types, path);
+        }
+        paths
+    }
+
+    // A file to save as, suggesting `file_name`. A name typed without an
+    // extension gets the first type's
+    pub fn save(&mut self, types: &[FileType], file_name: &str) -> Option<PathBuf> {
+        let mut path = self.dialog(types).set_file_name(file_name).save_file()?;
+        let extension = types.first().and_then(|kind| kind.extensions().first());
+        if let (None, Some(extension)) = (path.extension(), extension) {
+            path.set_extension(extension);
+        }
+        self.remember(types, &path);
+        Some(path)
+    }
+
+    pub fn folder(&mut self) -> Option<PathBuf> {
+        let dir = self.dialog(&[FileType::Folder]).pick_folder()?;
+        self.remember_dir(FileType::Folder, &dir);
+        Some(dir)
+    }
+
+    // Where the last file of `file_type` was opened or saved
+    pub fn last_dir(&self, file_type: FileType) -> Option<&Path> {
+        self.last_dirs.get(&file_type).map(PathBuf::as_path)
+    }
+
+    // The recent folders that still exist
+    pub fn recent_dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        self.recent_dirs.iter().filter(|dir| dir.is_dir())
+    }
+
+    // A menu of the recent folders; true when one was chosen, and the next
+    // dialog starts there
+    pub fn recent_menu(&mut self, ui: &mut egui::Ui, title: &str) -> bool {
+        let recent: Vec<PathBuf> = self.recent_dirs().cloned().collect();
+        let mut chosen = false;
+        ui.add_enabled_ui(!recent.is_empty(), |ui| {
+            ui.menu_button(title, |ui| {
+                for dir in recent {
+                    let name = dir.file_name().map_or_else(
+                        || dir.display().to_string(),
+                        |name| name.to_string_lossy().into_owned(),
+                    );
+                    if ui
+                        .button(format!("📁 {}", name))
+                        .on_hover_text(dir.display().to_string())
+                        .clicked()
+                    {
+                        self.start_in = Some(dir);
+                        chosen = true;
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+        chosen
+    }
+
+    fn dialog(&mut self, types: &[FileType]) -> rfd::FileDialog {
+        let mut dialog = rfd::FileDialog::new();
+        for kind in types.iter().filter(|kind| !kind.extensions().is_empty()) {
+            dialog = dialog.add_filter(kind.name(), kind.extensions());
+        }
+        if let Some(dir) = self.start_dir(types) {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog
+    }
+
+    fn start_dir(&mut self, types: &[FileType]) -> Option<PathBuf> {
+        self.start_in
+            .take()
+            .or_else(|| {
+                let kind = types.first()?;
+                self.last_dirs.get(kind).cloned()
+            })
+            .or_else(|| self.recent_dirs.first().cloned())
+            .filter(|dir| dir.is_dir())
+    }
+
+    fn remember(&mut self, types: &[FileType], path: &Path) {
+        if let (Some(&kind), Some(dir)) = (types.first(), path.parent()) {
+            self.remember_dir(kind, dir);
+        }
+    }
+
+    fn remember_dir(&mut self, file_type: FileType, dir: &Path) {
+        self.last_dirs.insert(file_type, dir.to_path_buf());
+        self.recent_dirs.retain(|recent| recent != dir);
+        self.recent_dirs.insert(0, dir.to_path_buf());
+        self.recent_dirs.truncate(MAX_RECENT_DIRS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_are_remembered_per_type() {
+        let mut dialogs = FileDialogs::default();
+        dialogs.remember(&[FileType::Png], Path::new("/pictures/shot.png"));
+        dialogs.remember(&[FileType::Csv, FileType::Json], Path::new("/data/a.csv"));
+        dialogs.remember(&[FileType::Png], Path::new("/pictures/other.png"));
+        assert_eq!(
+            dialogs.last_dir(FileType::Png),
+            Some(Path::new("/pictures"))
+        );
+        assert_eq!(dialogs.last_dir(FileType::Csv), Some(Path::new("/data")));
+        assert_eq!(dialogs.last_dir(FileType::Json), None);
+        assert_eq!(
+            dialogs.recent_dirs,
+            [PathBuf::from("/pictures"), PathBuf::from("/data")]
+        );
+
+        for i in 0..MAX_RECENT_DIRS + 2 {
+            dialogs.remember_dir(FileType::Folder, Path::new(&format!("/notes/{}", i)));
+        }
+        assert_eq!(dialogs.recent_dirs.len(), MAX_RECENT_DIRS);
+        assert_eq!(dialogs.recent_dirs[0], PathBuf::from("/notes/9"));
+    }
+}
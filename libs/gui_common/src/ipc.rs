@@ -0,0 +1,252 @@
+//! Messages between the workspace's apps on one computer, such as a
+//! calculator result sent to the caret of the open note.
+//!
+//! An app that takes messages calls [`listen`], which opens a port on the
+//! loopback address and writes it with a random secret to `ipc.json` in the
+//! app's data folder. [`send`] reads that file to reach the app, so only
+//! programs that can read the user's data folder can send.
+//! [`send_in_background`] does the same off the UI thread, and tells apart
+//! an app that is not running, which the sender may start, from one that
+//! could not take the message.
+
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// The app ids the note app and the calculator listen under
+pub const NOTE_APP: &str = "Note App";
+pub const CALCULATOR: &str = "Scientific Calculator";
+
+const FILE_NAME: &str = "ipc.json";
+// Longest a message may be, and how long a sender may take to write it
+const MAX_MESSAGE: u64 = 1 << 20;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    Insert(String),    // Text for the note, at the caret
+    Calculate(String), // An expression for the calculator to work out
+}
+
+// Why a message was not delivered
+#[derive(Clone, Debug, PartialEq)]
+pub enum SendError {
+    NotRunning(String), // No ipc.json, or nothing listening on its port
+    Failed(String),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendError::NotRunning(app_id) => write!(f, "{} is not running", app_id),
+            SendError::Failed(e) => f.write_str(e),
+        }
+    }
+}
+
+// Where a listening app is, as kept in its ipc.json
+#[derive(Serialize, Deserialize)]
+struct Address {
+    port: u16,
+    secret: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    secret: u64,
+    message: Message,
+}
+
+// Messages arriving for this app; dropping it stops others finding the app
+pub struct Listener {
+    messages: Receiver<Message>,
+    path: PathBuf,
+    secret: u64,
+}
+
+impl Listener {
+    // Messages since the last call
+    pub fn messages(&self) -> Vec<Message> {
+        self.messages.try_iter().collect()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // Unless another copy of the app has taken over the file since
+        let ours = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Address>(&text).ok())
+            .is_some_and(|address| address.secret == self.secret);
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Take messages sent to `app_id`, repainting `ctx` when one arrives. The
+// last copy of an app started is the one that receives them
+pub fn listen(app_id: &str, ctx: &egui::Context) -> io::Result<Listener> {
+    let dir = settings::dir(app_id)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No folder for app data"))?;
+    fs::create_dir_all(&dir)?;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let secret = new_secret();
+    let path = dir.join(FILE_NAME);
+    let port = listener.local_addr()?.port();
+    settings::write_json(&path, &Address { port, secret })?;
+
+    let (sender, messages) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            match receive(&mut stream, secret) {
+                Ok(message) => {
+                    let _ = stream.write_all(b"ok\n");
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Message refused");
+                    let _ = writeln!(stream, "{}", e);
+                }
+            }
+        }
+    });
+    Ok(Listener {
+        messages,
+        path,
+        secret,
+    })
+}
+
+// Send `message` to `app_id`, which must be running and listening
+pub fn send(app_id: &str, message: Message) -> Result<(), SendError> {
+    let failed = |e: String| SendError::Failed(format!("Could not reach {}: {}", app_id, e));
+    let path = settings::dir(app_id)
+        .ok_or_else(|| failed("no folder for app data".to_string()))?
+        .join(FILE_NAME);
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(SendError::NotRunning(app_id.to_string()))
+        }
+        Err(e) => return Err(failed(e.to_string())),
+    };
+    let address: Address =
+        serde_json::from_str(&text).map_err(|e| failed(format!("bad {}: {}", FILE_NAME, e)))?;
+    let socket = SocketAddr::from((Ipv4Addr::LOCALHOST, address.port));
+    // The file outlives an app that crashed, leaving nothing on the port
+    let mut stream = match TcpStream::connect_timeout(&socket, TIMEOUT) {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            return Err(SendError::NotRunning(app_id.to_string()))
+        }
+        Err(e) => return Err(failed(e.to_string())),
+    };
+    let envelope = Envelope {
+        secret: address.secret,
+        message,
+    };
+    let io_failed = |e: io::Error| failed(e.to_string());
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(io_failed)?;
+    serde_json::to_writer(&mut stream, &envelope).map_err(|e| io_failed(e.into()))?;
+    stream.shutdown(Shutdown::Write).map_err(io_failed)?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(io_failed)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        "" => Err(failed("no reply".to_string())),
+        refused => Err(SendError::Failed(format!(
+            "{} refused the message: {}",
+            app_id, refused
+        ))),
+    }
+}
+
+// `send` on its own thread, as reaching the app may take up to the timeout;
+// the result arrives on the receiver
+pub fn send_in_background(app_id: &str, message: Message) -> Receiver<Result<(), SendError>> {
+    let (sender, result) = mpsc::channel();
+    let app_id = app_id.to_string();
+    thread::spawn(move || {
+        let _ = sender.send(send(&app_id, message));
+    });
+    result
+}
+
+fn receive(stream: &mut TcpStream, secret: u64) -> Result<Message, String> {
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    read_message(stream, secret)
+}
+
+// A message written by `send`, if it carries the listener's secret
+fn read_message(reader: impl Read, secret: u64) -> Result<Message, String> {
+    let mut text = String::new();
+    reader
+        .take(MAX_MESSAGE)
+        .read_to_string(&mut text)
+        .map_err(|e| e.to_string())?;
+    let envelope: Envelope = serde_json::from_str(&text).map_err(|_| "Not a message")?;
+    if envelope.secret != secret {
+        return Err("Wrong secret".to_string());
+    }
+    Ok(envelope.message)
+}
+
+fn new_secret() -> u64 {
+    // RandomState is seeded from the system's random source
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(since) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(since.as_nanos());
+    }
+    hasher.write_u32(std::process::id());
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_need_the_secret() {
+        let envelope = Envelope {
+            secret: 42,
+            message: Message::Calculate("2 + 3".to_string()),
+        };
+        let bytes = serde_json::to_vec(&envelope).unwrap();
+        assert_eq!(
+            read_message(bytes.as_slice(), 42),
+            Ok(Message::Calculate("2 + 3".to_string()))
+        );
+        assert!(read_message(bytes.as_slice(), 7).is_err());
+        assert!(read_message(&b"hello"[..], 42).is_err());
+    }
+
+    #[test]
+    fn apps_without_an_address_are_not_running() {
+        let app_id = "IPC test app that never listens";
+        assert_eq!(
+            send(app_id, Message::Insert("1".to_string())),
+            Err(SendError::NotRunning(app_id.to_string()))
+        );
+    }
+}
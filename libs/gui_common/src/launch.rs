@@ -0,0 +1,76 @@
+//! Starting other programs: the system's default app for a file or web
+//! address, and the workspace's own apps.
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Open a file with the program the system uses for it. Windows is asked
+// directly: through `cmd /C start` a name with `&` or `^` in it would be cut
+// short and the rest run as a command
+#[cfg(target_os = "windows")]
+pub fn open_with_system(path: &Path) -> Result<(), String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let wide = |text: &OsStr| text.encode_wide().chain([0]).collect::<Vec<u16>>();
+    let verb = wide(OsStr::new("open"));
+    let file = wide(path.as_os_str());
+    // SAFETY: both strings end in a null and outlive the call
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            verb.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // Anything above 32 is success
+    match result as isize {
+        code if code > 32 => Ok(()),
+        code => Err(format!("Could not open {}: error {}", path.display(), code)),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn open_with_system(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(target_os = "macos"))]
+    let mut command = Command::new("xdg-open");
+    command
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Could not open {}: {}", path.display(), e))
+}
+
+// Where another workspace app is: beside this app's executable when it was
+// built there too, so a debug build starts the other debug build; otherwise
+// the name alone, found on the PATH
+pub fn app_path(name: &str) -> PathBuf {
+    let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(&file_name))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(file_name))
+}
+
+// Start the workspace app `name` (its package name, such as "note_app")
+pub fn start_app<I, S>(name: &str, args: I) -> Result<(), String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let path = app_path(name);
+    Command::new(&path)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Could not start {}: {}", name, e))
+}
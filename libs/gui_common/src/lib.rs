@@ -0,0 +1,53 @@
+//! Look and setup shared by the workspace's egui apps.
+//!
+//! Each app calls [`setup`] once at startup so text sizes, spacing and
+//! rounding match, and takes its colors from [`palette`] and colored buttons
+//! from [`style`]. Preferences are saved with [`settings`], which also has the
+//! settings window. Icon buttons get names for screen readers from
+//! [`accessibility`], and [`logging`] keeps a log file and a window showing
+//! it. [`launch`] opens files in other programs, including the other apps,
+//! and [`ipc`] passes messages to apps already running. File dialogs come
+//! from [`dialog`], which remembers where each kind of file was last kept. A
+//! new app can start from [`native_options`].
+
+pub mod accessibility;
+pub mod dialog;
+pub mod ipc;
+pub mod launch;
+pub mod logging;
+pub mod palette;
+pub mod settings;
+pub mod style;
+
+pub use accessibility::Labeled;
+pub use style::filled_button;
+
+// Window options for an app with the given title, opening where the window
+// was last left, or at `default_size` the first time
+pub fn native_options(
+    title: &str,
+    default_size: [f32; 2],
+    saved: &settings::CommonSettings,
+) -> eframe::NativeOptions {
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(default_size)
+        .with_title(title);
+    if let Some(window) = &saved.window {
+        viewport = viewport
+            .with_inner_size(window.size.map(|side| side.max(settings::MIN_WINDOW_SIDE)))
+            .with_maximized(window.maximized);
+        if let Some(position) = window.position {
+            viewport = viewport.with_position(position);
+        }
+    }
+    eframe::NativeOptions {
+        viewport,
+        persist_window: false, // The settings file has the geometry instead
+        ..Default::default()
+    }
+}
+
+// Apply the shared fonts and spacing; call once from the app's constructor
+pub fn setup(ctx: &egui::Context) {
+    ctx.all_styles_mut(style::apply);
+}
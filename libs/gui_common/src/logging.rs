@@ -0,0 +1,251 @@
+//! Logging for the apps: `tracing` events, and the `log` records egui and
+//! eframe write, go to `<data folder>/logs/app.log` and to a list of recent
+//! lines shown by [`window`]. The file is rotated when it grows past
+//! [`MAX_FILE_BYTES`], keeping [`KEEP_FILES`] old ones as `app.log.1`, `.2`...
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{span, Event, Level, Metadata, Subscriber};
+
+pub const MAX_FILE_BYTES: u64 = 1024 * 1024;
+pub const KEEP_FILES: usize = 3;
+// Lines kept for the log window
+pub const MAX_RECENT: usize = 1000;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+struct Logger {
+    path: PathBuf,
+    max_level: Level,
+    file: Mutex<Option<File>>,
+    recent: Mutex<VecDeque<String>>,
+    next_span: AtomicU64,
+}
+
+impl Logger {
+    fn write(&self, level: Level, target: &str, message: &str) {
+        let line = format!(
+            "{} {:>5} {}: {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            level,
+            target,
+            message
+        );
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == MAX_RECENT {
+                recent.pop_front();
+            }
+            recent.push_back(line.clone());
+        }
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        if file
+            .as_ref()
+            .and_then(|file| file.metadata().ok())
+            .is_some_and(|metadata| metadata.len() > MAX_FILE_BYTES)
+        {
+            *file = None;
+            rotate(&self.path);
+        }
+        if file.is_none() {
+            *file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .ok();
+        }
+        if let Some(file) = file.as_mut() {
+            // A log that cannot be written has nowhere to report it
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+// app.log.2 becomes app.log.3, app.log.1 becomes app.log.2, and so on, the
+// oldest dropping off the end
+fn rotate(path: &Path) {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    let _ = fs::remove_file(numbered(KEEP_FILES));
+    for n in (1..KEEP_FILES).rev() {
+        let _ = fs::rename(numbered(n), numbered(n + 1));
+    }
+    let _ = fs::rename(path, numbered(1));
+}
+
+// The event's message followed by its other fields as key=value
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+// The subscriber handed to tracing; spans are numbered but not recorded
+struct TracingLogger(&'static Logger);
+
+impl Subscriber for TracingLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.0.max_level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.0.max_level))
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(self.0.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        self.0.write(
+            *metadata.level(),
+            metadata.target(),
+            &format!("{}{}", fields.message, fields.rest),
+        );
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+// Warnings and errors from egui, eframe and the graphics backends
+struct LogBridge(&'static Logger);
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let level = match record.level() {
+            log::Level::Error => Level::ERROR,
+            _ => Level::WARN,
+        };
+        self.0
+            .write(level, record.target(), &record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+// Start logging for the app; call first thing in main. Debug builds also log
+// debug events
+pub fn init(app_id: &str) -> Result<(), String> {
+    let dir = crate::settings::dir(app_id)
+        .ok_or("No folder for logs")?
+        .join("logs");
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating log folder: {}", e))?;
+    let max_level = if cfg!(debug_assertions) {
+        Level::DEBUG
+    } else {
+        Level::INFO
+    };
+    let logger = LOGGER.get_or_init(|| Logger {
+        path: dir.join("app.log"),
+        max_level,
+        file: Mutex::new(None),
+        recent: Mutex::new(VecDeque::new()),
+        next_span: AtomicU64::new(1),
+    });
+    tracing::subscriber::set_global_default(TracingLogger(logger))
+        .map_err(|e| format!("Error starting the log: {}", e))?;
+    log::set_boxed_logger(Box::new(LogBridge(logger)))
+        .map_err(|e| format!("Error starting the log: {}", e))?;
+    log::set_max_level(log::LevelFilter::Warn);
+    Ok(())
+}
+
+// The log file, once logging has started
+pub fn path() -> Option<&'static Path> {
+    LOGGER.get().map(|logger| logger.path.as_path())
+}
+
+pub fn recent_lines() -> Vec<String> {
+    LOGGER
+        .get()
+        .and_then(|logger| {
+            logger
+                .recent
+                .lock()
+                .ok()
+                .map(|recent| recent.iter().cloned().collect())
+        })
+        .unwrap_or_default()
+}
+
+// The log window, for copying recent lines into a problem report. Sets
+// `open` to false when it is closed
+pub fn window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new("Log")
+        .open(open)
+        .default_size([720.0, 360.0])
+        .show(ctx, |ui| {
+            let lines = recent_lines();
+            ui.horizontal(|ui| {
+                match path() {
+                    Some(path) => ui.label(format!("Written to {}", path.display())),
+                    None => ui.label("Logging did not start"),
+                };
+                if ui.button("📋 Copy All").clicked() {
+                    ctx.copy_text(lines.join("\n"));
+                }
+            });
+            ui.separator();
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                    for line in &lines[rows] {
+                        let color = if line.contains(" ERROR ") {
+                            crate::palette::ERROR
+                        } else if line.contains("  WARN ") {
+                            ui.visuals().warn_fg_color
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        ui.label(egui::RichText::new(line).monospace().color(color));
+                    }
+                });
+        });
+}
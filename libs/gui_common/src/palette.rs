@@ -0,0 +1,16 @@
+//! Colors with a meaning, so the same kind of action or message looks the
+//! same in every app.
+
+use egui::Color32;
+
+// Links and marked values
+pub const ACCENT: Color32 = Color32::from_rgb(0x1e, 0x6f, 0xd9);
+// Error messages
+pub const ERROR: Color32 = Color32::RED;
+
+// Button fills, by what the button does
+pub const CONFIRM: Color32 = Color32::from_rgb(0, 200, 0); // Carry out: "=", OK
+pub const CLEAR: Color32 = Color32::from_rgb(255, 165, 0); // Start over
+pub const DESTRUCTIVE: Color32 = Color32::from_rgb(255, 0, 0); // Throws input away
+pub const EDIT: Color32 = Color32::from_rgb(173, 216, 230); // Small corrections
+pub const SPECIAL: Color32 = Color32::from_rgb(255, 215, 0); // Stands out from its row
@@ -0,0 +1,279 @@
+//! Preferences saved as JSON in the app's folder under the platform's data
+//! directory, and the settings window that edits them.
+//!
+//! Each app keeps its own settings struct with a [`CommonSettings`] field for
+//! what every app has: the theme and the recently used files.
+
+use crate::dialog::FileDialogs;
+use crate::style;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "settings.json";
+// Recently used files remembered
+pub const MAX_RECENT: usize = 10;
+// Range of the interface scale, 1.0 being the system's own size
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+// Smallest width or height a window is restored at
+pub const MIN_WINDOW_SIDE: f32 = 200.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System, // Follow the operating system's light or dark mode
+    Light,
+    Dark,
+    HighContrast, // White on black with strong outlines and focus marks
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [
+        Theme::System,
+        Theme::Light,
+        Theme::Dark,
+        Theme::HighContrast,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn apply(self, ctx: &egui::Context) {
+        ctx.set_theme(match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark | Theme::HighContrast => egui::ThemePreference::Dark,
+        });
+        // High contrast replaces the dark colors, which the others put back
+        ctx.style_mut_of(egui::Theme::Dark, |style| {
+            style.visuals = if self == Theme::HighContrast {
+                style::high_contrast_visuals()
+            } else {
+                egui::Visuals::dark()
+            };
+            style::round_corners(&mut style.visuals);
+        });
+    }
+}
+
+// Where the window was and how big, restored at the next launch
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub size: [f32; 2],             // Inside the frame, in points at 100% scale
+    pub position: Option<[f32; 2]>, // Top-left corner of the frame
+    pub maximized: bool,            // Size and position are from before maximizing
+}
+
+// The preferences every app has
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommonSettings {
+    pub theme: Theme,
+    pub ui_scale: f32,              // Zoom of the whole interface
+    pub recent_files: Vec<PathBuf>, // Most recent first
+    pub window: Option<WindowGeometry>,
+    pub dialogs: FileDialogs, // Where the file dialogs start
+}
+
+impl Default for CommonSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            ui_scale: 1.0,
+            recent_files: Vec::new(),
+            window: None,
+            dialogs: FileDialogs::default(),
+        }
+    }
+}
+
+impl CommonSettings {
+    // Apply the theme and scale; call at startup once the settings are loaded
+    pub fn apply(&self, ctx: &egui::Context) {
+        self.theme.apply(ctx);
+        ctx.set_zoom_factor(self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE));
+    }
+
+    // Keep the window's geometry for the next launch; call every frame. A
+    // maximized window keeps the size it had before, and a minimized or
+    // full screen one changes nothing
+    pub fn remember_window(&mut self, ctx: &egui::Context) {
+        // The viewport is measured in points, which the interface scale enlarges
+        let zoom = ctx.zoom_factor();
+        let (inner, outer, maximized) = ctx.input(|i| {
+            let viewport = i.viewport();
+            let skip = viewport.minimized == Some(true) || viewport.fullscreen == Some(true);
+            (
+                viewport.inner_rect.filter(|_| !skip),
+                viewport.outer_rect,
+                viewport.maximized == Some(true),
+            )
+        });
+        let Some(inner) = inner else {
+            return;
+        };
+        let geometry = WindowGeometry {
+            size: (inner.size() * zoom).into(),
+            position: outer.map(|outer| (outer.min.to_vec2() * zoom).into()),
+            maximized,
+        };
+        match &mut self.window {
+            Some(window) if maximized => window.maximized = true,
+            window => *window = Some(geometry),
+        }
+    }
+
+    pub fn add_recent(&mut self, path: &Path) {
+        self.recent_files.retain(|recent| recent != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(MAX_RECENT);
+    }
+
+    // The recent files that still exist
+    pub fn existing_recent_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.recent_files.iter().filter(|path| path.exists())
+    }
+}
+
+// The folder holding the app's settings; eframe keeps its own state there too
+pub fn dir(app_id: &str) -> Option<PathBuf> {
+    eframe::storage_dir(app_id)
+}
+
+// The saved settings, or None when there are none yet. A file that cannot be
+// read as settings is kept beside as "settings.json.bad" rather than being
+// overwritten by the defaults at the next save
+pub fn load<T: DeserializeOwned>(app_id: &str) -> Option<T> {
+    let path = dir(app_id)?.join(FILE_NAME);
+    let text = fs::read_to_string(&path).ok()?;
+    let settings = serde_json::from_str(&text).ok();
+    if settings.is_none() {
+        tracing::warn!(path = %path.display(), "Settings unreadable, kept as settings.json.bad");
+        let _ = fs::copy(&path, path.with_extension("json.bad"));
+    }
+    settings
+}
+
+pub fn save<T: Serialize>(app_id: &str, settings: &T) -> Result<(), String> {
+    let dir = dir(app_id).ok_or("No folder for settings")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creating settings folder: {}", e))?;
+    write_json(&dir.join(FILE_NAME), settings).map_err(|e| format!("Error saving settings: {}", e))
+}
+
+// Write `value` as JSON through a temporary file beside `path`, so a crash
+// mid-write keeps the old file; for app data kept next to the settings. On
+// Unix only the user can read it, as settings can hold passwords
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp)?;
+    #[cfg(unix)]
+    {
+        // A temporary file left by a crash keeps its old mode when reopened
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(json.as_bytes())?;
+    drop(file);
+    fs::rename(&temp, path)
+}
+
+// The settings window: the app's own settings drawn by `add_contents`, then
+// the ones every app shares. Sets `open` to false when it is closed
+pub fn window(
+    ctx: &egui::Context,
+    title: &str,
+    open: &mut bool,
+    common: &mut CommonSettings,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) {
+    egui::Window::new(title)
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            add_contents(ui);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                for theme in Theme::ALL {
+                    if ui
+                        .radio_value(&mut common.theme, theme, theme.label())
+                        .changed()
+                    {
+                        theme.apply(ctx);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Interface scale:");
+                let response = ui.add(
+                    egui::Slider::new(&mut common.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE)
+                        .step_by(0.05)
+                        .custom_formatter(|scale, _| format!("{:.0}%", scale * 100.0))
+                        .custom_parser(|text| {
+                            let percent: f64 = text.trim().trim_end_matches('%').parse().ok()?;
+                            Some(percent / 100.0)
+                        }),
+                );
+                // Scaling while dragging would move the slider under the pointer
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    ctx.set_zoom_factor(common.ui_scale);
+                }
+                if ui.button("Reset").clicked() {
+                    common.ui_scale = 1.0;
+                    ctx.set_zoom_factor(1.0);
+                }
+            })
+            .response
+            .on_hover_text(
+                "Enlarges or shrinks everything, on top of the system's display scaling",
+            );
+            if !common.recent_files.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} recent file(s)", common.recent_files.len()));
+                    if ui.button("Clear").clicked() {
+                        common.recent_files.clear();
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_files_move_to_the_front() {
+        let mut common = CommonSettings::default();
+        for name in ["a", "b", "a"] {
+            common.add_recent(Path::new(name));
+        }
+        assert_eq!(
+            common.recent_files,
+            [PathBuf::from("a"), PathBuf::from("b")]
+        );
+        for n in 0..20 {
+            common.add_recent(Path::new(&n.to_string()));
+        }
+        assert_eq!(common.recent_files.len(), MAX_RECENT);
+        assert_eq!(common.recent_files[0], PathBuf::from("19"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Text sizes, spacing and button styles.
+
+use egui::{Color32, FontFamily, FontId, RichText, Stroke, TextStyle};
+
+// Sizes of egui's text styles, a little larger than its defaults
+const TEXT_SIZES: [(TextStyle, f32, FontFamily); 5] = [
+    (TextStyle::Small, 10.0, FontFamily::Proportional),
+    (TextStyle::Body, 13.5, FontFamily::Proportional),
+    (TextStyle::Button, 13.5, FontFamily::Proportional),
+    (TextStyle::Heading, 20.0, FontFamily::Proportional),
+    (TextStyle::Monospace, 13.5, FontFamily::Monospace),
+];
+
+pub fn apply(style: &mut egui::Style) {
+    for (text_style, size, family) in TEXT_SIZES {
+        style
+            .text_styles
+            .insert(text_style, FontId::new(size, family));
+    }
+    style.spacing.item_spacing = egui::vec2(8.0, 5.0);
+    style.spacing.button_padding = egui::vec2(6.0, 2.0);
+    round_corners(&mut style.visuals);
+}
+
+pub fn round_corners(visuals: &mut egui::Visuals) {
+    visuals.window_rounding = egui::Rounding::same(8.0);
+    visuals.menu_rounding = egui::Rounding::same(6.0);
+    for widgets in [
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widgets.rounding = egui::Rounding::same(4.0);
+    }
+}
+
+// White text on black, every widget outlined, and yellow for the hovered or
+// focused one so keyboard focus is easy to follow
+pub fn high_contrast_visuals() -> egui::Visuals {
+    let mut visuals = egui::Visuals::dark();
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(40);
+    visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+    visuals.hyperlink_color = Color32::from_rgb(0x80, 0xd0, 0xff);
+    visuals.selection.bg_fill = Color32::from_rgb(0x00, 0x50, 0xc0);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+    let widgets = &mut visuals.widgets;
+    widgets.noninteractive.fg_stroke = Stroke::new(1.0, Color32::WHITE);
+    widgets.noninteractive.bg_stroke = Stroke::new(1.0, Color32::from_gray(180));
+    for (state, fill) in [
+        (&mut widgets.inactive, Color32::BLACK),
+        (&mut widgets.hovered, Color32::from_gray(30)),
+        (&mut widgets.active, Color32::from_gray(50)),
+        (&mut widgets.open, Color32::from_gray(30)),
+    ] {
+        state.bg_fill = fill;
+        state.weak_bg_fill = fill;
+        state.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+        state.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+    widgets.hovered.bg_stroke = Stroke::new(2.0, Color32::YELLOW);
+    widgets.active.bg_stroke = Stroke::new(3.0, Color32::YELLOW);
+    widgets.active.fg_stroke = Stroke::new(2.0, Color32::YELLOW);
+    visuals
+}
+
+// Black or white, whichever reads better on the fill
+pub fn text_on(fill: Color32) -> Color32 {
+    let [r, g, b, _] = fill.to_array();
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 140.0 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    }
+}
+
+// A button filled with a palette color, its text readable in light and dark
+// themes alike
+pub fn filled_button(text: &str, fill: Color32) -> egui::Button<'static> {
+    egui::Button::new(RichText::new(text).color(text_on(fill))).fill(fill)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette;
+
+    #[test]
+    fn text_contrasts_with_the_fill() {
+        assert_eq!(text_on(palette::SPECIAL), Color32::BLACK);
+        assert_eq!(text_on(palette::EDIT), Color32::BLACK);
+        assert_eq!(text_on(palette::ACCENT), Color32::WHITE);
+        assert_eq!(text_on(Color32::from_gray(30)), Color32::WHITE);
+    }
+}
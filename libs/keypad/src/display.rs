@@ -0,0 +1,97 @@
+//! The calculator's display: a framed, right-aligned value with a pending
+//! line above it and an optional note below.
+
+use egui::{Color32, RichText};
+
+// Gray of the pending line and the note
+const DIM: Color32 = Color32::from_gray(120);
+
+pub struct Display<'a> {
+    value: &'a str,
+    pending: &'a str,
+    note: Option<&'a str>,
+    width: f32,
+    min_height: f32,
+    value_size: f32,
+}
+
+impl<'a> Display<'a> {
+    pub fn new(value: &'a str) -> Self {
+        Self {
+            value,
+            pending: "",
+            note: None,
+            width: 900.0,
+            min_height: 150.0,
+            value_size: 18.0,
+        }
+    }
+
+    // The operation waiting for its second number, such as "12 +"
+    pub fn pending(mut self, pending: &'a str) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    // Small text under the value, such as the value before an error
+    pub fn note(mut self, note: &'a str) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn min_height(mut self, min_height: f32) -> Self {
+        self.min_height = min_height;
+        self
+    }
+
+    pub fn value_size(mut self, size: f32) -> Self {
+        self.value_size = size;
+        self
+    }
+
+    // The response is the value's, which senses clicks so the app can add a
+    // context menu to it. Long values wrap and scroll
+    pub fn show(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::Frame::none()
+            .fill(Color32::from_gray(240))
+            .stroke(egui::Stroke::new(2.0, Color32::from_gray(100)))
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.set_min_width(self.width);
+                ui.set_max_width(self.width);
+                ui.set_min_height(self.min_height);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                    ui.label(
+                        RichText::new(self.pending)
+                            .size(16.0)
+                            .monospace()
+                            .color(DIM),
+                    );
+                });
+                let value = RichText::new(self.value).size(self.value_size).monospace();
+                let value_height = (self.min_height - 20.0).max(self.value_size * 2.0);
+                let response = egui::ScrollArea::vertical()
+                    .max_height(value_height)
+                    .show(ui, |ui| {
+                        ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                            ui.set_max_width(self.width - 20.0);
+                            ui.add(egui::Label::new(value).wrap().sense(egui::Sense::click()))
+                        })
+                        .inner
+                    })
+                    .inner;
+                if let Some(note) = self.note {
+                    ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                        ui.label(RichText::new(note).size(16.0).monospace().color(DIM));
+                    });
+                }
+                response
+            })
+            .inner
+    }
+}
@@ -0,0 +1,14 @@
+//! The calculator's number pad and display, for any app that takes numbers.
+//!
+//! [`Keypad`] draws the clear keys, the digits and optionally the four
+//! operators and returns the [`PadKey`] clicked; what a key does is up to the
+//! app. An app that only needs a number typed in can hand the key to
+//! [`enter`], which edits the text of a number field. [`Display`] shows a
+//! value right-aligned in the calculator's framed display, with a small line
+//! above for what is pending and one below for a note.
+
+mod display;
+mod pad;
+
+pub use display::Display;
+pub use pad::{enter, Keypad, Operator, PadKey};
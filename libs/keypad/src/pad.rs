@@ -0,0 +1,205 @@
+//! The keys: C, CE, DEL and ± above the digits, and the operators beside them.
+
+use egui::Vec2;
+use gui_common::{filled_button, palette, Labeled};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadKey {
+    Digit(u8),
+    Point,
+    Operator(Operator),
+    Equals,
+    Clear,      // C: everything
+    ClearEntry, // CE: the number being typed
+    Delete,     // DEL: its last digit
+    Negate,     // ±
+}
+
+// The pad, drawn with `show`; by default with the operators and = as in the
+// calculator
+pub struct Keypad {
+    button_size: Vec2,
+    operators: bool,
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self {
+            button_size: Vec2::new(80.0, 40.0),
+            operators: true,
+        }
+    }
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn button_size(mut self, size: Vec2) -> Self {
+        self.button_size = size;
+        self
+    }
+
+    // Without them the pad is only for typing a number
+    pub fn operators(mut self, operators: bool) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    // The key clicked this frame, if any
+    pub fn show(self, ui: &mut egui::Ui) -> Option<PadKey> {
+        let size = self.button_size;
+        let mut pressed = None;
+        let mut key = |ui: &mut egui::Ui, button: egui::Button, name: &str, key: PadKey| {
+            let response = ui.add_sized(size, button);
+            // Digits name themselves
+            let response = if name.is_empty() {
+                response
+            } else {
+                response.labeled(name)
+            };
+            if response.clicked() {
+                pressed = Some(key);
+            }
+        };
+        let digit = |d: u8| (egui::Button::new(d.to_string()), "", PadKey::Digit(d));
+
+        ui.horizontal(|ui| {
+            key(
+                ui,
+                filled_button("C", palette::CLEAR),
+                "Clear",
+                PadKey::Clear,
+            );
+            key(
+                ui,
+                filled_button("CE", palette::DESTRUCTIVE),
+                "Clear entry",
+                PadKey::ClearEntry,
+            );
+            key(
+                ui,
+                filled_button("DEL", palette::EDIT),
+                "Delete last digit",
+                PadKey::Delete,
+            );
+            key(ui, egui::Button::new("±"), "Change sign", PadKey::Negate);
+        });
+        let operators = [
+            ("÷", "Divide", Operator::Divide),
+            ("×", "Multiply", Operator::Multiply),
+            ("−", "Subtract", Operator::Subtract),
+        ];
+        for (row, operator) in [[7, 8, 9], [4, 5, 6], [1, 2, 3]].into_iter().zip(operators) {
+            ui.horizontal(|ui| {
+                for d in row {
+                    let (button, name, pressed) = digit(d);
+                    key(ui, button, name, pressed);
+                }
+                if self.operators {
+                    let (label, name, operator) = operator;
+                    key(
+                        ui,
+                        egui::Button::new(label),
+                        name,
+                        PadKey::Operator(operator),
+                    );
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            let (button, name, pressed) = digit(0);
+            key(ui, button, name, pressed);
+            key(ui, egui::Button::new("."), "Decimal point", PadKey::Point);
+            if self.operators {
+                key(
+                    ui,
+                    filled_button("=", palette::CONFIRM),
+                    "Equals",
+                    PadKey::Equals,
+                );
+                key(
+                    ui,
+                    egui::Button::new("+"),
+                    "Add",
+                    PadKey::Operator(Operator::Add),
+                );
+            }
+        });
+        pressed
+    }
+}
+
+// Apply a key to the text of a number field: digits and the point are added,
+// DEL removes the last character, C and CE empty it and ± flips the sign.
+// Returns false for the operators and =, which are left to the caller
+pub fn enter(text: &mut String, key: PadKey) -> bool {
+    match key {
+        PadKey::Digit(d) => {
+            if text == "0" || text == "-0" {
+                text.pop();
+            }
+            text.push(char::from(b'0' + d.min(9)));
+        }
+        PadKey::Point => {
+            if !text.contains('.') {
+                if text.is_empty() || text == "-" {
+                    text.push('0');
+                }
+                text.push('.');
+            }
+        }
+        PadKey::Delete => {
+            text.pop();
+            if text == "-" {
+                text.clear();
+            }
+        }
+        PadKey::Clear | PadKey::ClearEntry => text.clear(),
+        PadKey::Negate => match text.strip_prefix('-') {
+            Some(positive) => *text = positive.to_string(),
+            None if !text.is_empty() => text.insert(0, '-'),
+            None => {}
+        },
+        PadKey::Operator(_) | PadKey::Equals => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &[PadKey]) -> String {
+        let mut text = String::new();
+        for &key in keys {
+            enter(&mut text, key);
+        }
+        text
+    }
+
+    #[test]
+    fn numbers_are_typed_on_the_pad() {
+        use PadKey::*;
+        assert_eq!(typed(&[Digit(0), Digit(4), Point, Digit(5)]), "4.5");
+        assert_eq!(typed(&[Point, Digit(5), Point, Digit(1)]), "0.51");
+        assert_eq!(typed(&[Digit(1), Digit(2), Negate, Delete]), "-1");
+        assert_eq!(typed(&[Digit(3), Negate, Delete]), "");
+        // ± waits for a number
+        assert_eq!(typed(&[Negate, Digit(7), Negate]), "-7");
+        assert_eq!(typed(&[Digit(9), ClearEntry, Digit(2)]), "2");
+
+        let mut text = "12".to_string();
+        assert!(!enter(&mut text, Operator(super::Operator::Add)));
+        assert_eq!(text, "12");
+    }
+}